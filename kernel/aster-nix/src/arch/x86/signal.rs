@@ -10,4 +10,12 @@ impl SignalContext for UserContext {
         self.set_rsi(siginfo_addr);
         self.set_rdx(ucontext_addr);
     }
+
+    fn restart_syscall(&mut self, syscall_num: usize) {
+        // The length of the `syscall` instruction is 2 bytes.
+        const SYSCALL_INSN_LEN: usize = 2;
+
+        self.set_rax(syscall_num);
+        self.set_rip(self.rip() - SYSCALL_INSN_LEN);
+    }
 }
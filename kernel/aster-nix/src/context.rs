@@ -57,6 +57,10 @@ impl CurrentUserSpace {
     ///
     /// Returns `Err` if the `vaddr` and `len` do not represent a user space memory range.
     pub fn reader(&self, vaddr: Vaddr, len: usize) -> Result<VmReader<'_, UserSpace>> {
+        if len > 0 {
+            check_vaddr(vaddr)?;
+        }
+
         Ok(self.0.reader(vaddr, len)?)
     }
 
@@ -64,6 +68,10 @@ impl CurrentUserSpace {
     ///
     /// Returns `Err` if the `vaddr` and `len` do not represent a user space memory range.
     pub fn writer(&self, vaddr: Vaddr, len: usize) -> Result<VmWriter<'_, UserSpace>> {
+        if len > 0 {
+            check_vaddr(vaddr)?;
+        }
+
         Ok(self.0.writer(vaddr, len)?)
     }
 
@@ -78,11 +86,6 @@ impl CurrentUserSpace {
     /// it returns `Ok`.
     pub fn read_bytes(&self, src: Vaddr, dest: &mut VmWriter<'_>) -> Result<()> {
         let copy_len = dest.avail();
-
-        if copy_len > 0 {
-            check_vaddr(src)?;
-        }
-
         let mut user_reader = self.reader(src, copy_len)?;
         user_reader.read_fallible(dest).map_err(|err| err.0)?;
         Ok(())
@@ -90,10 +93,6 @@ impl CurrentUserSpace {
 
     /// Reads a value typed `Pod` from the user space of the current process.
     pub fn read_val<T: Pod>(&self, src: Vaddr) -> Result<T> {
-        if core::mem::size_of::<T>() > 0 {
-            check_vaddr(src)?;
-        }
-
         let mut user_reader = self.reader(src, core::mem::size_of::<T>())?;
         Ok(user_reader.read_val()?)
     }
@@ -109,11 +108,6 @@ impl CurrentUserSpace {
     /// `Ok`.
     pub fn write_bytes(&self, dest: Vaddr, src: &mut VmReader<'_>) -> Result<()> {
         let copy_len = src.remain();
-
-        if copy_len > 0 {
-            check_vaddr(dest)?;
-        }
-
         let mut user_writer = self.writer(dest, copy_len)?;
         user_writer.write_fallible(src).map_err(|err| err.0)?;
         Ok(())
@@ -121,10 +115,6 @@ impl CurrentUserSpace {
 
     /// Writes `val` to the user space of the current process.
     pub fn write_val<T: Pod>(&self, dest: Vaddr, val: &T) -> Result<()> {
-        if core::mem::size_of::<T>() > 0 {
-            check_vaddr(dest)?;
-        }
-
         let mut user_writer = self.writer(dest, core::mem::size_of::<T>())?;
         Ok(user_writer.write_val(val)?)
     }
@@ -133,10 +123,6 @@ impl CurrentUserSpace {
     /// The length of the string should not exceed `max_len`,
     /// including the final `\0` byte.
     pub fn read_cstring(&self, vaddr: Vaddr, max_len: usize) -> Result<CString> {
-        if max_len > 0 {
-            check_vaddr(vaddr)?;
-        }
-
         let mut user_reader = self.reader(vaddr, max_len)?;
         user_reader.read_cstring()
     }
@@ -226,6 +212,9 @@ const fn has_zero(value: usize) -> bool {
 /// deny the access in the page fault handler either. It may save a page fault
 /// in some occasions. More importantly, double page faults may not be handled
 /// quite well on some platforms.
+///
+/// Ranges that reach into the kernel space are rejected by [`VmSpace::reader`]
+/// and [`VmSpace::writer`].
 fn check_vaddr(va: Vaddr) -> Result<()> {
     if va < crate::vm::vmar::ROOT_VMAR_LOWEST_ADDR {
         Err(Error::with_message(
@@ -190,7 +190,7 @@ impl FileIo for PtyMaster {
                 let fd = {
                     let mut file_table = current.file_table().lock();
                     // TODO: deal with the O_CLOEXEC flag
-                    file_table.insert(slave, FdFlags::empty())
+                    file_table.insert(slave, FdFlags::empty())?
                 };
                 Ok(fd)
             }
@@ -72,16 +72,17 @@ impl<E: Events, F: EventsFilter<E>> Subject<E, F> {
         // Slow path: broadcast the new events to all observers.
         let mut observers = self.observers.lock();
         observers.retain(|observer, filter| {
-            if let Some(observer) = observer.upgrade() {
-                if !filter.filter(events) {
-                    return true;
-                }
-                observer.on_events(events);
-                true
-            } else {
+            let is_alive = observer
+                .with_upgraded(|observer| {
+                    if filter.filter(events) {
+                        observer.on_events(events);
+                    }
+                })
+                .is_some();
+            if !is_alive {
                 self.num_observers.fetch_sub(1, Ordering::Relaxed);
-                false
             }
+            is_alive
         });
     }
 }
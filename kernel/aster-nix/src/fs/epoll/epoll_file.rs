@@ -242,16 +242,16 @@ impl EpollFile {
             for entry in ready_entries {
                 let (ep_event, ep_flags) = entry.event_and_flags();
                 // If this entry's file is ready, save it in the output array.
-                // EPOLLHUP and EPOLLERR should always be reported.
-                let ready_events = entry.poll() & (ep_event.events | IoEvents::HUP | IoEvents::ERR);
+                // EPOLLHUP and EPOLLERR should always be reported, unless the
+                // entry is not interested in any events (e.g., it is disarmed).
+                let ready_events = if ep_event.events.is_empty() {
+                    IoEvents::empty()
+                } else {
+                    entry.poll() & (ep_event.events | IoEvents::HUP | IoEvents::ERR)
+                };
                 // If there are no events, the entry should be removed from the ready list.
                 if ready_events.is_empty() {
                     entry.reset_ready();
-                    // For EPOLLONESHOT flag, this entry should also be removed from the interest list
-                    if ep_flags.intersects(EpollFlags::ONE_SHOT) {
-                        self.del_interest(entry.fd())
-                            .expect("this entry should be in the interest list");
-                    }
                     continue;
                 }
 
@@ -268,10 +268,10 @@ impl EpollFile {
                 // its ready flag.
                 else {
                     entry.reset_ready();
-                    // For EPOLLONESHOT flag, this entry should also be removed from the interest list
+                    // For EPOLLONESHOT flag, this entry should also be disarmed until it is
+                    // re-armed by `EPOLL_CTL_MOD`. It is kept in the interest list.
                     if ep_flags.intersects(EpollFlags::ONE_SHOT) {
-                        self.del_interest(entry.fd())
-                            .expect("this entry should be in the interest list");
+                        entry.disarm();
                     }
                 }
             }
@@ -456,6 +456,14 @@ impl EpollEntry {
         *inner = Inner { event, flags }
     }
 
+    /// Disarms the epoll entry, so that it reports no events until it is updated.
+    ///
+    /// This happens to an `EPOLLONESHOT` entry after its events are reported.
+    pub fn disarm(&self) {
+        let mut inner = self.inner.lock();
+        inner.event.events = IoEvents::empty();
+    }
+
     /// Returns whether the epoll entry is in the ready list.
     pub fn is_ready(&self) -> bool {
         self.is_ready.load(Ordering::Relaxed)
@@ -488,12 +496,20 @@ impl EpollEntry {
 }
 
 impl Observer<IoEvents> for EpollEntry {
-    fn on_events(&self, _events: &IoEvents) {
+    fn on_events(&self, events: &IoEvents) {
         // Fast path
         if self.is_deleted() {
             return;
         }
 
+        // Only the events that the entry is interested in make it ready. This
+        // keeps an edge-triggered entry from being reported again because of
+        // unrelated events, and keeps a disarmed one-shot entry silent.
+        let mask = self.event().events;
+        if mask.is_empty() || !events.intersects(mask | IoEvents::HUP | IoEvents::ERR) {
+            return;
+        }
+
         if let Some(epoll_file) = self.epoll_file() {
             epoll_file.push_ready(self.self_arc());
         }
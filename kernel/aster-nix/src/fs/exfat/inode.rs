@@ -1103,6 +1103,10 @@ impl Inode for ExfatInode {
         }
 
         let file_size = inner.size;
+        if new_size == file_size {
+            return Ok(());
+        }
+
         let fs = inner.fs();
         let fs_guard = fs.lock();
 
@@ -1251,18 +1255,21 @@ impl Inode for ExfatInode {
             return_errno_with_message!(Errno::EINVAL, "not block-aligned");
         }
 
-        let sector_size = inner.fs().sector_size();
-
         let (read_off, read_len) = {
             let file_size = inner.size;
-            let start = file_size.min(offset).align_down(sector_size);
-            let end = file_size.min(offset + buf.len()).align_down(sector_size);
+            let start = file_size.min(offset);
+            let end = file_size.min(offset + buf.len());
             (start, end - start)
         };
+        if read_len == 0 {
+            return Ok(0);
+        }
 
+        // The data written to the page cache must reach the device before it is read
+        // bypassing the page cache.
         inner
             .page_cache
-            .discard_range(read_off..read_off + read_len);
+            .evict_range(read_off..read_off + read_len)?;
 
         let mut buf_offset = 0;
         let frame = FrameAllocOptions::new(1)
@@ -1274,13 +1281,16 @@ impl Inode for ExfatInode {
         let cluster_size = inner.fs().cluster_size();
         let mut cur_cluster = start_pos.0.clone();
         let mut cur_offset = start_pos.1;
-        for _ in Bid::from_offset(read_off)..Bid::from_offset(read_off + read_len) {
+        // The last block can be partially beyond the end of the file.
+        let end_off = (read_off + read_len).align_up(BLOCK_SIZE);
+        for _ in Bid::from_offset(read_off)..Bid::from_offset(end_off) {
             let physical_bid =
                 Bid::from_offset(cur_cluster.cluster_id() as usize * cluster_size + cur_offset);
             inner.fs().block_device().read_block(physical_bid, &frame)?;
 
-            frame.read_bytes(0, &mut buf[buf_offset..buf_offset + BLOCK_SIZE])?;
-            buf_offset += BLOCK_SIZE;
+            let copy_len = BLOCK_SIZE.min(read_len - buf_offset);
+            frame.read_bytes(0, &mut buf[buf_offset..buf_offset + copy_len])?;
+            buf_offset += copy_len;
 
             cur_offset += BLOCK_SIZE;
             if cur_offset >= cluster_size {
@@ -1352,9 +1362,11 @@ impl Inode for ExfatInode {
         let file_allocated_size = inner.size_allocated;
         let end_offset = offset + buf.len();
 
+        // The dirty pages in the range are written back first, so that they cannot
+        // overwrite the new data on the device later.
         let start = offset.min(file_size);
         let end = end_offset.min(file_size);
-        inner.page_cache.discard_range(start..end);
+        inner.page_cache.evict_range(start..end)?;
 
         let new_size = {
             let mut inner = inner.upgrade();
@@ -1397,6 +1409,8 @@ impl Inode for ExfatInode {
                 cur_offset %= BLOCK_SIZE;
             }
         }
+        // The cached pages in the range are stale now.
+        inner.page_cache.discard_range(start..end);
 
         {
             let mut inner = inner.upgrade();
@@ -648,6 +648,52 @@ mod test {
         assert!(buf.eq(&read), "File mismatch. Data read result:{:?}", read);
     }
 
+    #[ktest]
+    fn misaligned_direct_io() {
+        let fs = load_exfat();
+        let root = fs.root_inode() as Arc<dyn Inode>;
+        let file = create_file(root.clone(), "test");
+
+        let mut buf = vec![0u8; PAGE_SIZE * 2];
+        let errno_of = |result: Result<usize>| result.unwrap_err().error();
+        assert_eq!(errno_of(file.write_direct_at(1, &buf)), Errno::EINVAL);
+        assert_eq!(errno_of(file.write_direct_at(0, &buf[1..])), Errno::EINVAL);
+        assert_eq!(errno_of(file.read_direct_at(1, &mut buf)), Errno::EINVAL);
+        assert_eq!(errno_of(file.read_direct_at(0, &mut buf[1..])), Errno::EINVAL);
+    }
+
+    #[ktest]
+    fn mix_buffered_and_direct_io() {
+        let fs = load_exfat();
+        let root = fs.root_inode() as Arc<dyn Inode>;
+        let file = create_file(root.clone(), "test");
+
+        const BUF_SIZE: usize = PAGE_SIZE * 4;
+
+        // The direct read sees the data written to the page cache.
+        let buf = vec![1u8; BUF_SIZE];
+        file.write_at(0, &buf).unwrap();
+        let mut read = vec![0u8; BUF_SIZE];
+        assert_eq!(file.read_direct_at(0, &mut read).unwrap(), BUF_SIZE);
+        assert!(buf.eq(&read));
+
+        // The buffered read sees the data written bypassing the page cache.
+        let buf = vec![2u8; PAGE_SIZE];
+        file.write_direct_at(PAGE_SIZE, &buf).unwrap();
+        let mut read = vec![0u8; BUF_SIZE];
+        file.read_at(0, &mut read).unwrap();
+        assert!(read[..PAGE_SIZE].iter().all(|byte| *byte == 1));
+        assert!(read[PAGE_SIZE..PAGE_SIZE * 2].iter().all(|byte| *byte == 2));
+        assert!(read[PAGE_SIZE * 2..].iter().all(|byte| *byte == 1));
+
+        // The direct read stops at the end of the file, which need not be aligned.
+        file.write_at(BUF_SIZE, &[3u8; 100]).unwrap();
+        let mut read = vec![0u8; BUF_SIZE];
+        assert_eq!(file.read_direct_at(BUF_SIZE, &mut read).unwrap(), 100);
+        assert!(read[..100].iter().all(|byte| *byte == 3));
+        assert_eq!(file.read_direct_at(BUF_SIZE * 2, &mut read).unwrap(), 0);
+    }
+
     #[ktest]
     fn write_and_read_file() {
         let fs = load_exfat();
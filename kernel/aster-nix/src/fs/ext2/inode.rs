@@ -931,22 +931,29 @@ impl Inner {
     }
 
     pub fn read_direct_at(&self, offset: usize, buf: &mut [u8]) -> Result<usize> {
-        let (offset, read_len) = {
+        let read_len = {
             let file_size = self.inode_impl.file_size();
-            let start = file_size.min(offset).align_down(BLOCK_SIZE);
-            let end = file_size.min(offset + buf.len()).align_down(BLOCK_SIZE);
-            (start, end - start)
+            let start = file_size.min(offset);
+            let end = file_size.min(offset + buf.len());
+            end - start
         };
-        self.page_cache.discard_range(offset..offset + read_len);
+        if read_len == 0 {
+            return Ok(0);
+        }
+
+        // The data written to the page cache must reach the device before it is read
+        // bypassing the page cache.
+        self.page_cache.evict_range(offset..offset + read_len)?;
 
         let start_bid = Bid::from_offset(offset).to_raw() as Ext2Bid;
-        let buf_nblocks = buf.len() / BLOCK_SIZE;
-        let segment = FrameAllocOptions::new(buf_nblocks)
+        // The last block can be partially beyond the end of the file.
+        let nblocks = read_len.div_ceil(BLOCK_SIZE);
+        let segment = FrameAllocOptions::new(nblocks)
             .uninit(true)
             .alloc_contiguous()?;
 
         self.inode_impl.read_blocks(start_bid, &segment)?;
-        segment.read_bytes(0, buf)?;
+        segment.read_bytes(0, &mut buf[..read_len])?;
         Ok(read_len)
     }
 
@@ -968,11 +975,14 @@ impl Inner {
         let write_len = buf.len();
         let end_offset = offset + write_len;
 
+        // The dirty pages in the range are written back first, so that they cannot
+        // overwrite the new data on the device later.
         let start = offset.min(file_size);
         let end = end_offset.min(file_size);
-        self.page_cache.discard_range(start..end);
+        self.page_cache.evict_range(start..end)?;
 
         if end_offset > file_size {
+            self.page_cache.resize(end_offset)?;
             self.inode_impl.resize(end_offset)?;
         }
 
@@ -987,6 +997,8 @@ impl Inner {
         };
 
         self.inode_impl.write_blocks(start_bid, &segment)?;
+        // The cached pages in the range are stale now.
+        self.page_cache.discard_range(start..end);
         Ok(write_len)
     }
 
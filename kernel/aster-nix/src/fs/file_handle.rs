@@ -4,6 +4,8 @@
 
 //! Opend File Handle
 
+use ostd::mm::UserSpace;
+
 use crate::{
     events::{IoEvents, Observer},
     fs::{
@@ -13,6 +15,7 @@ use crate::{
     net::socket::Socket,
     prelude::*,
     process::{signal::Pollable, Gid, Uid},
+    util::read_all_from_user,
 };
 
 /// The basic operations defined on a file
@@ -46,6 +49,18 @@ pub trait FileLike: Pollable + Send + Sync + Any {
         return_errno_with_message!(Errno::ESPIPE, "write_at is not supported");
     }
 
+    /// Write the data from a user space reader at the given file offset.
+    ///
+    /// This is the same as [`write_at`], except that a file may consume the user
+    /// buffer directly without copying it to a kernel buffer first. If a page fault
+    /// occurs on the user buffer, the number of bytes written so far is returned.
+    ///
+    /// [`write_at`]: FileLike::write_at
+    fn write_user_at(&self, offset: usize, reader: &mut VmReader<'_, UserSpace>) -> Result<usize> {
+        let buf = read_all_from_user(reader)?;
+        self.write_at(offset, &buf)
+    }
+
     fn ioctl(&self, cmd: IoctlCmd, arg: usize) -> Result<i32> {
         return_errno_with_message!(Errno::EINVAL, "ioctl is not supported");
     }
@@ -5,6 +5,7 @@
 use core::sync::atomic::{AtomicU8, Ordering};
 
 use aster_util::slot_vec::SlotVec;
+use id_alloc::IdAlloc;
 
 use super::{
     file_handle::FileLike,
@@ -18,28 +19,37 @@ use crate::{
     prelude::*,
     process::{
         process_table,
-        signal::{constants::SIGIO, signals::kernel::KernelSignal},
+        signal::{
+            constants::{SIGIO, SIGURG},
+            signals::kernel::KernelSignal,
+        },
         Pid, Process,
     },
 };
 
 pub type FileDesc = i32;
 
+/// The maximum number of fds that [`FileTable::insert`] allocates, which is `NR_OPEN` in Linux.
+const MAX_NR_FDS: usize = 1024 * 1024;
+
 pub struct FileTable {
     table: SlotVec<FileTableEntry>,
+    /// The allocated fds, which are exactly the occupied slots of `table`.
+    fd_alloc: IdAlloc,
     subject: Subject<FdEvents>,
 }
 
 impl FileTable {
-    pub const fn new() -> Self {
+    pub fn new() -> Self {
         Self {
             table: SlotVec::new(),
+            fd_alloc: IdAlloc::with_capacity(0),
             subject: Subject::new(),
         }
     }
 
     pub fn new_with_stdio() -> Self {
-        let mut table = SlotVec::new();
+        let mut table = Self::new();
         let fs_resolver = FsResolver::new();
         let tty_path = FsPath::new(AT_FDCWD, "/dev/console").expect("cannot find tty");
         let stdin = {
@@ -57,13 +67,10 @@ impl FileTable {
             let mode = InodeMode::S_IWUSR;
             fs_resolver.open(&tty_path, flags, mode.bits()).unwrap()
         };
-        table.put(FileTableEntry::new(Arc::new(stdin), FdFlags::empty()));
-        table.put(FileTableEntry::new(Arc::new(stdout), FdFlags::empty()));
-        table.put(FileTableEntry::new(Arc::new(stderr), FdFlags::empty()));
-        Self {
-            table,
-            subject: Subject::new(),
-        }
+        table.insert(Arc::new(stdin), FdFlags::empty()).unwrap();
+        table.insert(Arc::new(stdout), FdFlags::empty()).unwrap();
+        table.insert(Arc::new(stderr), FdFlags::empty()).unwrap();
+        table
     }
 
     pub fn dup(&mut self, fd: FileDesc, new_fd: FileDesc, flags: FdFlags) -> Result<FileDesc> {
@@ -71,34 +78,35 @@ impl FileTable {
             .table
             .get(fd as usize)
             .map(|entry| entry.file.clone())
-            .ok_or(Error::with_message(Errno::ENOENT, "No such file"))?;
+            .ok_or(Error::with_message(Errno::EBADF, "fd not exits"))?;
 
         // Get the lowest-numbered available fd equal to or greater than `new_fd`.
-        let get_min_free_fd = || -> usize {
-            let new_fd = new_fd as usize;
-            if self.table.get(new_fd).is_none() {
-                return new_fd;
-            }
+        let new_fd = new_fd as usize;
+        let capacity = self.fd_alloc.capacity();
+        let min_free_fd = (new_fd..capacity)
+            .find(|fd| !self.fd_alloc.is_allocated(*fd))
+            .unwrap_or(new_fd.max(capacity));
 
-            for idx in new_fd + 1..self.table.slots_len() {
-                if self.table.get(idx).is_none() {
-                    return idx;
-                }
-            }
-            self.table.slots_len()
-        };
-
-        let min_free_fd = get_min_free_fd();
         let entry = FileTableEntry::new(file, flags);
-        self.table.put_at(min_free_fd, entry);
+        self.put_entry_at(min_free_fd, entry);
         Ok(min_free_fd as FileDesc)
     }
 
-    pub fn insert(&mut self, item: Arc<dyn FileLike>, flags: FdFlags) -> FileDesc {
+    /// Inserts the file at the lowest-numbered available fd.
+    pub fn insert(&mut self, item: Arc<dyn FileLike>, flags: FdFlags) -> Result<FileDesc> {
+        let fd = self
+            .fd_alloc
+            .alloc_or_grow(MAX_NR_FDS)
+            .ok_or_else(|| Error::with_message(Errno::EMFILE, "too many open files"))?;
         let entry = FileTableEntry::new(item, flags);
-        self.table.put(entry) as FileDesc
+        self.table.put_at(fd, entry);
+        Ok(fd as FileDesc)
     }
 
+    /// Inserts the file at `fd`, and returns the file previously at `fd`, if any.
+    ///
+    /// The returned file should be dropped after the file table is unlocked. See
+    /// [`Self::close_file`] for the reason.
     pub fn insert_at(
         &mut self,
         fd: FileDesc,
@@ -106,7 +114,7 @@ impl FileTable {
         flags: FdFlags,
     ) -> Option<Arc<dyn FileLike>> {
         let entry = FileTableEntry::new(item, flags);
-        let entry = self.table.put_at(fd as usize, entry);
+        let entry = self.put_entry_at(fd as usize, entry);
         if entry.is_some() {
             let events = FdEvents::Close(fd);
             self.notify_fd_events(&events);
@@ -115,8 +123,12 @@ impl FileTable {
         entry.map(|e| e.file)
     }
 
+    /// Closes the file at `fd`, and returns the file, if any.
+    ///
+    /// The returned file should be dropped after the file table is unlocked, since dropping
+    /// the last reference to a file may sleep, e.g., to linger on a UNIX stream socket.
     pub fn close_file(&mut self, fd: FileDesc) -> Option<Arc<dyn FileLike>> {
-        let entry = self.table.remove(fd as usize);
+        let entry = self.remove_entry(fd as usize);
         if entry.is_some() {
             let events = FdEvents::Close(fd);
             self.notify_fd_events(&events);
@@ -133,7 +145,7 @@ impl FileTable {
             .map(|(idx, _)| idx as FileDesc)
             .collect();
         for fd in closed_fds {
-            let entry = self.table.remove(fd as usize).unwrap();
+            let entry = self.remove_entry(fd as usize).unwrap();
             let events = FdEvents::Close(fd);
             self.notify_fd_events(&events);
             entry.notify_fd_events(&events);
@@ -156,7 +168,7 @@ impl FileTable {
             })
             .collect();
         for fd in closed_fds {
-            let entry = self.table.remove(fd as usize).unwrap();
+            let entry = self.remove_entry(fd as usize).unwrap();
             let events = FdEvents::Close(fd);
             self.notify_fd_events(&events);
             entry.notify_fd_events(&events);
@@ -202,6 +214,21 @@ impl FileTable {
     fn notify_fd_events(&self, events: &FdEvents) {
         self.subject.notify_observers(events);
     }
+
+    /// Puts the entry at `fd`, and returns the entry that is replaced.
+    fn put_entry_at(&mut self, fd: usize, entry: FileTableEntry) -> Option<FileTableEntry> {
+        if fd >= self.fd_alloc.capacity() {
+            self.fd_alloc.grow(fd + 1);
+        }
+        self.fd_alloc.alloc_specific(fd);
+        self.table.put_at(fd, entry)
+    }
+
+    fn remove_entry(&mut self, fd: usize) -> Option<FileTableEntry> {
+        let entry = self.table.remove(fd)?;
+        self.fd_alloc.free(fd);
+        Some(entry)
+    }
 }
 
 impl Default for FileTable {
@@ -214,6 +241,7 @@ impl Clone for FileTable {
     fn clone(&self) -> Self {
         Self {
             table: self.table.clone(),
+            fd_alloc: self.fd_alloc.clone(),
             subject: Subject::new(),
         }
     }
@@ -289,8 +317,9 @@ impl FileTableEntry {
         *self_owner = match new_owner {
             None => None,
             Some((pid, observer)) => {
+                // The urgent data is notified with `IoEvents::PRI`.
                 self.file
-                    .register_observer(observer.weak_self(), IoEvents::empty())?;
+                    .register_observer(observer.weak_self(), IoEvents::PRI)?;
                 Some((pid, observer))
             }
         };
@@ -360,9 +389,16 @@ impl OwnerObserver {
 
 impl Observer<IoEvents> for OwnerObserver {
     fn on_events(&self, events: &IoEvents) {
-        if self.file.status_flags().contains(StatusFlags::O_ASYNC)
-            && let Some(process) = self.owner.upgrade()
-        {
+        let Some(process) = self.owner.upgrade() else {
+            return;
+        };
+
+        // Like Linux, `SIGURG` is sent for the urgent data regardless of `O_ASYNC`.
+        if events.contains(IoEvents::PRI) {
+            process.enqueue_signal(KernelSignal::new(SIGURG));
+        }
+
+        if self.file.status_flags().contains(StatusFlags::O_ASYNC) {
             process.enqueue_signal(KernelSignal::new(SIGIO));
         }
     }
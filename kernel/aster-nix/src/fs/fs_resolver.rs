@@ -136,16 +136,19 @@ impl FsResolver {
 
     fn lookup_inner(&self, path: &FsPath, follow_tail_link: bool) -> Result<Arc<Dentry>> {
         let dentry = match path.inner {
-            FsPathInner::Absolute(path) => {
-                self.lookup_from_parent(&self.root, path.trim_start_matches('/'), follow_tail_link)?
-            }
+            FsPathInner::Absolute(path) => self.lookup_from_parent(
+                &self.root,
+                path.trim_start_matches('/'),
+                follow_tail_link,
+                &mut 0,
+            )?,
             FsPathInner::CwdRelative(path) => {
-                self.lookup_from_parent(&self.cwd, path, follow_tail_link)?
+                self.lookup_from_parent(&self.cwd, path, follow_tail_link, &mut 0)?
             }
             FsPathInner::Cwd => self.cwd.clone(),
             FsPathInner::FdRelative(fd, path) => {
                 let parent = self.lookup_from_fd(fd)?;
-                self.lookup_from_parent(&parent, path, follow_tail_link)?
+                self.lookup_from_parent(&parent, path, follow_tail_link, &mut 0)?
             }
             FsPathInner::Fd(fd) => self.lookup_from_fd(fd)?,
         };
@@ -159,7 +162,10 @@ impl FsResolver {
     /// If `path` ends with `/`, then the returned inode must be a directory inode.
     ///
     /// While looking up the dentry, symbolic links will be followed for
-    /// at most `SYMLINKS_MAX` times.
+    /// at most `SYMLINKS_MAX` times in total, including the `follows` times
+    /// that have been followed before this lookup. Otherwise, `ELOOP` is
+    /// returned, which is the case for symlink cycles. The relative target of
+    /// a symlink is resolved against the directory containing the symlink.
     ///
     /// If `follow_tail_link` is true and the trailing component is a symlink,
     /// it will be followed.
@@ -169,6 +175,7 @@ impl FsResolver {
         parent: &Arc<Dentry>,
         relative_path: &str,
         follow_tail_link: bool,
+        follows: &mut usize,
     ) -> Result<Arc<Dentry>> {
         debug_assert!(!relative_path.starts_with('/'));
 
@@ -178,7 +185,6 @@ impl FsResolver {
 
         // To handle symlinks
         let mut link_path = String::new();
-        let mut follows = 0;
 
         // Initialize the first dentry and the relative path
         let (mut dentry, mut relative_path) = (parent.clone(), relative_path);
@@ -199,7 +205,7 @@ impl FsResolver {
 
             // If next inode is a symlink, follow symlinks at most `SYMLINKS_MAX` times.
             if next_type == InodeType::SymLink && (follow_tail_link || !next_is_tail) {
-                if follows >= SYMLINKS_MAX {
+                if *follows >= SYMLINKS_MAX {
                     return_errno_with_message!(Errno::ELOOP, "too many symlinks");
                 }
                 let link_path_remain = {
@@ -223,7 +229,7 @@ impl FsResolver {
                 link_path.clear();
                 link_path.push_str(link_path_remain.trim_start_matches('/'));
                 relative_path = &link_path;
-                follows += 1;
+                *follows += 1;
             } else {
                 // If path ends with `/`, the inode must be a directory
                 if must_be_dir && next_type != InodeType::Dir {
@@ -260,18 +266,26 @@ impl FsResolver {
         path: &FsPath,
         follow_tail_link: bool,
     ) -> Result<(Arc<Dentry>, String)> {
+        // The symlinks followed in the directory and the tail are counted together.
+        let mut follows = 0;
+
         let (mut dir_dentry, mut base_name) = match path.inner {
             FsPathInner::Absolute(path) => {
                 let (dir, file_name) = split_path(path);
                 (
-                    self.lookup_from_parent(&self.root, dir.trim_start_matches('/'), true)?,
+                    self.lookup_from_parent(
+                        &self.root,
+                        dir.trim_start_matches('/'),
+                        true,
+                        &mut follows,
+                    )?,
                     String::from(file_name),
                 )
             }
             FsPathInner::CwdRelative(path) => {
                 let (dir, file_name) = split_path(path);
                 (
-                    self.lookup_from_parent(&self.cwd, dir, true)?,
+                    self.lookup_from_parent(&self.cwd, dir, true, &mut follows)?,
                     String::from(file_name),
                 )
             }
@@ -279,7 +293,7 @@ impl FsResolver {
                 let (dir, file_name) = split_path(path);
                 let parent = self.lookup_from_fd(fd)?;
                 (
-                    self.lookup_from_parent(&parent, dir, true)?,
+                    self.lookup_from_parent(&parent, dir, true, &mut follows)?,
                     String::from(file_name),
                 )
             }
@@ -293,6 +307,11 @@ impl FsResolver {
         loop {
             match dir_dentry.lookup(base_name.trim_end_matches('/')) {
                 Ok(dentry) if dentry.type_() == InodeType::SymLink => {
+                    if follows >= SYMLINKS_MAX {
+                        return_errno_with_message!(Errno::ELOOP, "too many symlinks");
+                    }
+                    follows += 1;
+
                     let link = {
                         let mut link = dentry.inode().read_link()?;
                         if link.is_empty() {
@@ -305,11 +324,16 @@ impl FsResolver {
                     };
                     let (dir, file_name) = split_path(&link);
                     if dir.starts_with('/') {
-                        dir_dentry =
-                            self.lookup_from_parent(&self.root, dir.trim_start_matches('/'), true)?;
+                        dir_dentry = self.lookup_from_parent(
+                            &self.root,
+                            dir.trim_start_matches('/'),
+                            true,
+                            &mut follows,
+                        )?;
                         base_name = String::from(file_name);
                     } else {
-                        dir_dentry = self.lookup_from_parent(&dir_dentry, dir, true)?;
+                        dir_dentry =
+                            self.lookup_from_parent(&dir_dentry, dir, true, &mut follows)?;
                         base_name = String::from(file_name);
                     }
                 }
@@ -437,3 +461,81 @@ pub fn split_path(path: &str) -> (&str, &str) {
 
     (dir_path, file_name)
 }
+
+#[cfg(ktest)]
+mod test {
+    use alloc::format;
+
+    use ostd::prelude::*;
+
+    use super::*;
+    use crate::fs::{path::MountNode, ramfs::RamFS, rootfs::init_root_mount};
+
+    fn new_resolver() -> FsResolver {
+        init_root_mount();
+        let root = Dentry::new_fs_root(MountNode::new_root(RamFS::new()));
+        let mut fs = FsResolver::new();
+        fs.set_root(root.clone());
+        fs.set_cwd(root);
+        fs
+    }
+
+    fn symlink(dir: &Arc<Dentry>, name: &str, target: &str) {
+        let link = dir
+            .new_fs_child(
+                name,
+                InodeType::SymLink,
+                InodeMode::from_bits_truncate(0o777),
+            )
+            .unwrap();
+        link.inode().write_link(target).unwrap();
+    }
+
+    fn lookup(fs: &FsResolver, path: &str) -> Result<Arc<Dentry>> {
+        fs.lookup(&FsPath::try_from(path).unwrap())
+    }
+
+    #[ktest]
+    fn symlink_cycle() {
+        let fs = new_resolver();
+        symlink(fs.root(), "self", "self");
+        symlink(fs.root(), "ping", "pong");
+        symlink(fs.root(), "pong", "/ping");
+
+        for path in ["self", "ping", "/pong", "self/file"] {
+            let err = lookup(&fs, path).unwrap_err();
+            assert_eq!(err.error(), Errno::ELOOP);
+        }
+
+        // The symlinks themselves can still be looked up.
+        let path = FsPath::try_from("self").unwrap();
+        assert_eq!(
+            fs.lookup_no_follow(&path).unwrap().type_(),
+            InodeType::SymLink
+        );
+    }
+
+    #[ktest]
+    fn symlink_chain() {
+        let fs = new_resolver();
+        let mode = InodeMode::from_bits_truncate(0o755);
+        let dir = fs.root().new_fs_child("dir", InodeType::Dir, mode).unwrap();
+        let file = dir.new_fs_child("file", InodeType::File, mode).unwrap();
+
+        // Relative targets are resolved against the directory of the symlink.
+        symlink(&dir, "link0", "file");
+        for i in 1..SYMLINKS_MAX {
+            symlink(&dir, &format!("link{}", i), &format!("link{}", i - 1));
+        }
+        let last = format!("dir/link{}", SYMLINKS_MAX - 1);
+        symlink(fs.root(), "entry", &last);
+
+        // The chain is long but not cyclic, so it is resolved within the limit.
+        let dentry = lookup(&fs, &last).unwrap();
+        assert_eq!(dentry.metadata().ino, file.metadata().ino);
+
+        // One more hop exceeds the limit.
+        let err = lookup(&fs, "entry").unwrap_err();
+        assert_eq!(err.error(), Errno::ELOOP);
+    }
+}
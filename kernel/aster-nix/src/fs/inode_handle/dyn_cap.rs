@@ -126,6 +126,13 @@ impl FileLike for InodeHandle<Rights> {
         self.0.write_at(offset, buf)
     }
 
+    fn write_user_at(&self, offset: usize, reader: &mut VmReader<'_, UserSpace>) -> Result<usize> {
+        if !self.1.contains(Rights::WRITE) {
+            return_errno_with_message!(Errno::EBADF, "file is not writable");
+        }
+        self.0.write_user_at(offset, reader)
+    }
+
     fn resize(&self, new_size: usize) -> Result<()> {
         if !self.1.contains(Rights::WRITE) {
             return_errno_with_message!(Errno::EINVAL, "file is not writable");
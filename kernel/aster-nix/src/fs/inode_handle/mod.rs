@@ -11,6 +11,7 @@ use core::sync::atomic::{AtomicU32, Ordering};
 
 use aster_rights::Rights;
 use inherit_methods_macro::inherit_methods;
+use ostd::mm::UserSpace;
 
 use crate::{
     events::IoEvents,
@@ -26,6 +27,7 @@ use crate::{
     },
     prelude::*,
     process::{signal::Poller, Gid, Uid},
+    util::read_all_from_user,
 };
 
 #[derive(Debug)]
@@ -102,6 +104,24 @@ impl InodeHandle_ {
         }
     }
 
+    pub fn write_user_at(
+        &self,
+        mut offset: usize,
+        reader: &mut VmReader<'_, UserSpace>,
+    ) -> Result<usize> {
+        if self.file_io.is_some() || self.status_flags().contains(StatusFlags::O_DIRECT) {
+            let buf = read_all_from_user(reader)?;
+            return self.write_at(offset, &buf);
+        }
+
+        if self.status_flags().contains(StatusFlags::O_APPEND) {
+            // If the file has the O_APPEND flag, the offset is ignored
+            offset = self.dentry.size();
+        }
+
+        self.dentry.inode().write_user_at(offset, reader)
+    }
+
     pub fn read_to_end(&self, buf: &mut Vec<u8>) -> Result<usize> {
         if self.file_io.is_some() {
             return_errno_with_message!(Errno::EINVAL, "file io does not support read to end");
@@ -12,12 +12,16 @@ use crate::{
     events::{IoEvents, Observer},
     prelude::*,
     process::{
-        signal::{Pollable, Poller},
+        posix_thread::PosixThreadExt,
+        signal::{constants::SIGPIPE, signals::kernel::KernelSignal, Pollable, Poller},
         Gid, Uid,
     },
     time::clocks::RealTimeCoarseClock,
 };
 
+/// The maximum number of bytes that are guaranteed to be written to a pipe atomically.
+pub const PIPE_BUF: usize = 4096;
+
 pub struct PipeReader {
     consumer: Consumer<u8>,
     status_flags: AtomicU32,
@@ -109,11 +113,28 @@ impl PipeWriter {
     pub fn new(producer: Producer<u8>, status_flags: StatusFlags) -> Result<Arc<Self>> {
         check_status_flags(status_flags)?;
 
+        // A write of at most `PIPE_BUF` bytes waits until it can be done atomically. So the pipe
+        // is writable only if there is room for `PIPE_BUF` bytes, like Linux. Otherwise, a
+        // blocking writer would be woken up again and again without making progress.
+        producer.set_low_watermark(PIPE_BUF);
+
         Ok(Arc::new(Self {
             producer,
             status_flags: AtomicU32::new(status_flags.bits()),
         }))
     }
+
+    fn try_write(&self, buf: &[u8]) -> Result<usize> {
+        // "POSIX.1 says that write(2)s of less than PIPE_BUF bytes must be atomic: the output
+        // data is written to the pipe as a contiguous sequence."
+        //
+        // See <https://man7.org/linux/man-pages/man7/pipe.7.html>.
+        if buf.len() <= PIPE_BUF {
+            self.producer.try_write_atomic(buf)
+        } else {
+            self.producer.try_write(buf)
+        }
+    }
 }
 
 impl Pollable for PipeWriter {
@@ -124,11 +145,19 @@ impl Pollable for PipeWriter {
 
 impl FileLike for PipeWriter {
     fn write(&self, buf: &[u8]) -> Result<usize> {
-        if self.status_flags().contains(StatusFlags::O_NONBLOCK) {
-            self.producer.try_write(buf)
+        let res = if self.status_flags().contains(StatusFlags::O_NONBLOCK) {
+            self.try_write(buf)
         } else {
-            self.wait_events(IoEvents::OUT, || self.producer.try_write(buf))
+            self.wait_events(IoEvents::OUT, || self.try_write(buf))
+        };
+
+        if res.as_ref().is_err_and(|err| err.error() == Errno::EPIPE) {
+            if let Some(posix_thread) = current_thread!().as_posix_thread() {
+                posix_thread.enqueue_signal(Box::new(KernelSignal::new(SIGPIPE)));
+            }
         }
+
+        res
     }
 
     fn status_flags(&self) -> StatusFlags {
@@ -304,6 +333,57 @@ mod test {
         );
     }
 
+    #[ktest]
+    fn test_write_atomic() {
+        let (producer, consumer) = Channel::new(PIPE_BUF * 2).split();
+        let writer = PipeWriter::new(producer, StatusFlags::O_NONBLOCK).unwrap();
+        let reader = PipeReader::new(consumer, StatusFlags::O_NONBLOCK).unwrap();
+
+        assert_eq!(writer.write(&[1; PIPE_BUF + 1]).unwrap(), PIPE_BUF + 1);
+
+        // A small write either succeeds as a whole or fails.
+        assert_eq!(
+            writer.write(&[2; PIPE_BUF]).unwrap_err().error(),
+            Errno::EAGAIN
+        );
+        assert_eq!(writer.write(&[2; PIPE_BUF - 1]).unwrap(), PIPE_BUF - 1);
+
+        let mut buf = [0; PIPE_BUF];
+        assert_eq!(reader.read(&mut buf).unwrap(), PIPE_BUF);
+
+        // A large write can be partial.
+        assert_eq!(writer.write(&[3; PIPE_BUF * 2]).unwrap(), PIPE_BUF);
+        assert_eq!(
+            writer.write(&[3; PIPE_BUF * 2]).unwrap_err().error(),
+            Errno::EAGAIN
+        );
+    }
+
+    #[ktest]
+    fn test_write_atomic_blocking() {
+        let (producer, consumer) = Channel::new(PIPE_BUF * 2).split();
+        let writer = PipeWriter::new(producer, StatusFlags::empty()).unwrap();
+        let reader = PipeReader::new(consumer, StatusFlags::empty()).unwrap();
+
+        assert_eq!(writer.write(&[1; PIPE_BUF + 1]).unwrap(), PIPE_BUF + 1);
+
+        // There is some room, but not enough for an atomic write. So the pipe is not writable,
+        // and a blocking atomic write sleeps until the room is made.
+        assert!(!writer.poll(IoEvents::OUT, None).contains(IoEvents::OUT));
+
+        let writer_thread = Thread::spawn_kernel_thread(ThreadOptions::new(move || {
+            assert_eq!(writer.write(&[2; PIPE_BUF]).unwrap(), PIPE_BUF);
+        }));
+
+        let mut buf = [0; PIPE_BUF + 1];
+        assert_eq!(reader.read(&mut buf[..1]).unwrap(), 1);
+        writer_thread.join();
+
+        assert_eq!(reader.read(&mut buf).unwrap(), PIPE_BUF + 1);
+        assert!(buf[..PIPE_BUF].iter().all(|byte| *byte == 1));
+        assert_eq!(buf[PIPE_BUF], 2);
+    }
+
     #[ktest]
     fn test_read_closed() {
         test_blocking(
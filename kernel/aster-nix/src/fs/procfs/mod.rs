@@ -6,7 +6,9 @@ use sys::SysDirOps;
 
 use self::{
     meminfo::MemInfoFileOps,
+    net::NetDirOps,
     pid::PidDirOps,
+    sched_debug::SchedDebugFileOps,
     self_::SelfSymOps,
     template::{DirOps, ProcDir, ProcDirBuilder, ProcSymBuilder, SymOps},
 };
@@ -22,7 +24,9 @@ use crate::{
 
 mod filesystems;
 mod meminfo;
+mod net;
 mod pid;
+mod sched_debug;
 mod self_;
 mod sys;
 mod template;
@@ -106,6 +110,10 @@ impl DirOps for RootDirOps {
             FileSystemsFileOps::new_inode(this_ptr.clone())
         } else if name == "meminfo" {
             MemInfoFileOps::new_inode(this_ptr.clone())
+        } else if name == "net" {
+            NetDirOps::new_inode(this_ptr.clone())
+        } else if name == "sched_debug" {
+            SchedDebugFileOps::new_inode(this_ptr.clone())
         } else if let Ok(pid) = name.parse::<Pid>() {
             let process_ref =
                 process_table::get_process(pid).ok_or_else(|| Error::new(Errno::ENOENT))?;
@@ -129,6 +137,10 @@ impl DirOps for RootDirOps {
         });
         cached_children
             .put_entry_if_not_found("meminfo", || MemInfoFileOps::new_inode(this_ptr.clone()));
+        cached_children.put_entry_if_not_found("net", || NetDirOps::new_inode(this_ptr.clone()));
+        cached_children.put_entry_if_not_found("sched_debug", || {
+            SchedDebugFileOps::new_inode(this_ptr.clone())
+        });
 
         for process in process_table::process_table().iter() {
             let pid = process.pid().to_string();
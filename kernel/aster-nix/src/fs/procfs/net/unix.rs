@@ -0,0 +1,68 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! This module offers `/proc/net/unix` file support, which lists the UNIX
+//! domain sockets for debugging.
+//!
+//! Only the listening and connected stream sockets are listed. The columns
+//! follow the format of Linux, but the `Num`, `RefCount` and `Inode` columns
+//! are not meaningful and are always zero.
+//!
+//! Reference: <https://man7.org/linux/man-pages/man5/proc.5.html>
+
+use alloc::format;
+use core::fmt::Write;
+
+use crate::{
+    fs::{
+        procfs::template::{FileOps, ProcFileBuilder},
+        utils::Inode,
+    },
+    net::socket::unix::{stream_sockets, UnixSocketAddr, UnixStreamState},
+    prelude::*,
+};
+
+/// The `__SO_ACCEPTCON` flag of a listening socket.
+const ACCEPTCON: u32 = 1 << 16;
+/// The `SOCK_STREAM` type.
+const SOCK_STREAM: u16 = 1;
+/// The `SS_UNCONNECTED` state.
+const SS_UNCONNECTED: u8 = 1;
+/// The `SS_CONNECTED` state.
+const SS_CONNECTED: u8 = 3;
+
+/// Represents the inode at `/proc/net/unix`.
+pub struct UnixFileOps;
+
+impl UnixFileOps {
+    pub fn new_inode(parent: Weak<dyn Inode>) -> Arc<dyn Inode> {
+        ProcFileBuilder::new(Self).parent(parent).build().unwrap()
+    }
+}
+
+impl FileOps for UnixFileOps {
+    fn data(&self) -> Result<Vec<u8>> {
+        let mut output = String::from("Num       RefCount Protocol Flags    Type St Inode Path\n");
+
+        for info in stream_sockets() {
+            let (flags, state) = match info.state {
+                UnixStreamState::Listening => (ACCEPTCON, SS_UNCONNECTED),
+                UnixStreamState::Connected => (0, SS_CONNECTED),
+            };
+            let path = match &info.addr {
+                UnixSocketAddr::Unnamed => String::new(),
+                UnixSocketAddr::Path(path) => format!(" {}", path),
+                UnixSocketAddr::Abstract(name) => {
+                    format!(" @{}", String::from_utf8_lossy(name))
+                }
+            };
+            writeln!(
+                output,
+                "{:016x}: {:08X} {:08X} {:08X} {:04X} {:02X} {:5}{}",
+                0, 0, 0, flags, SOCK_STREAM, state, 0, path
+            )
+            .unwrap();
+        }
+
+        Ok(output.into_bytes())
+    }
+}
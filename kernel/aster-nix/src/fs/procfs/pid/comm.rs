@@ -23,7 +23,11 @@ impl CommFileOps {
 
 impl FileOps for CommFileOps {
     fn data(&self) -> Result<Vec<u8>> {
-        let mut comm_output = {
+        let mut comm_output = if let Some(main_thread) = self.0.main_thread() {
+            // The task name is kept in sync with the thread name, which is truncated to fit in
+            // `TASK_COMM_LEN` bytes.
+            main_thread.task_name().as_str().as_bytes().to_vec()
+        } else {
             let exe_path = self.0.executable_path();
             let last_component = exe_path.rsplit('/').next().unwrap_or(&exe_path);
             let mut comm = last_component.as_bytes().to_vec();
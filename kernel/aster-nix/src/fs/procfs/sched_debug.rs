@@ -0,0 +1,31 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! This module offers `/proc/sched_debug` file support, which shows the
+//! current task, the queued tasks and the remaining time slices of the
+//! runqueue of every CPU. The format is similar to that of Linux's but
+//! only a few fields are provided.
+
+use crate::{
+    fs::{
+        procfs::template::{FileOps, ProcFileBuilder},
+        utils::Inode,
+    },
+    prelude::*,
+    sched::sched_debug,
+};
+
+/// Represents the inode at `/proc/sched_debug`.
+pub struct SchedDebugFileOps;
+
+impl SchedDebugFileOps {
+    pub fn new_inode(parent: Weak<dyn Inode>) -> Arc<dyn Inode> {
+        ProcFileBuilder::new(Self).parent(parent).build().unwrap()
+    }
+}
+
+impl FileOps for SchedDebugFileOps {
+    fn data(&self) -> Result<Vec<u8>> {
+        let output = sched_debug().unwrap_or_default();
+        Ok(output.into_bytes())
+    }
+}
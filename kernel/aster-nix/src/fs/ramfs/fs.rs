@@ -9,7 +9,7 @@ use aster_block::bio::BioWaiter;
 use aster_rights::Full;
 use aster_util::slot_vec::SlotVec;
 use ostd::{
-    mm::{Frame, VmIo},
+    mm::{Frame, UserSpace, VmIo},
     sync::RwMutexWriteGuard,
 };
 
@@ -20,12 +20,14 @@ use crate::{
         device::Device,
         utils::{
             CStr256, DirentVisitor, Extension, FallocMode, FileSystem, FsFlags, Inode, InodeMode,
-            InodeType, IoctlCmd, Metadata, PageCache, PageCacheBackend, SuperBlock,
+            InodeType, IoctlCmd, Metadata, PageCache, PageCacheBackend, SuperBlock, XattrName,
+            XattrNamespace, XattrSetFlags, Xattrs,
         },
     },
     prelude::*,
     process::{signal::Poller, Gid, Uid},
     time::clocks::RealTimeCoarseClock,
+    util::read_all_from_user,
     vm::vmo::Vmo,
 };
 
@@ -56,6 +58,7 @@ impl RamFS {
                 this: weak_root.clone(),
                 fs: weak_fs.clone(),
                 extension: Extension::new(),
+                xattrs: Xattrs::new(),
             }),
             inode_allocator: AtomicU64::new(ROOT_INO + 1),
         })
@@ -102,6 +105,8 @@ struct RamInode {
     fs: Weak<RamFS>,
     /// Extensions
     extension: Extension,
+    /// Extended attributes
+    xattrs: Xattrs,
 }
 
 struct Node {
@@ -419,6 +424,7 @@ impl RamInode {
             this: weak_self.clone(),
             fs: Arc::downgrade(fs),
             extension: Extension::new(),
+            xattrs: Xattrs::new(),
         })
     }
 
@@ -430,6 +436,7 @@ impl RamInode {
             this: weak_self.clone(),
             fs: Arc::downgrade(fs),
             extension: Extension::new(),
+            xattrs: Xattrs::new(),
         })
     }
 
@@ -441,6 +448,7 @@ impl RamInode {
             this: weak_self.clone(),
             fs: Arc::downgrade(fs),
             extension: Extension::new(),
+            xattrs: Xattrs::new(),
         })
     }
 
@@ -452,6 +460,7 @@ impl RamInode {
             this: weak_self.clone(),
             fs: Arc::downgrade(fs),
             extension: Extension::new(),
+            xattrs: Xattrs::new(),
         })
     }
 
@@ -469,6 +478,7 @@ impl RamInode {
             this: weak_self.clone(),
             fs: Arc::downgrade(fs),
             extension: Extension::new(),
+            xattrs: Xattrs::new(),
         })
     }
 
@@ -564,7 +574,9 @@ impl Inode for RamInode {
         let file_size = self_inode.metadata.size;
         let new_size = offset + buf.len();
         let should_expand_size = new_size > file_size;
-        if should_expand_size {
+        // The page cache may be larger than the file if a write from the user space is in
+        // progress (see `write_user_at`), so only expand the page cache here.
+        if new_size > page_cache.pages().size() {
             page_cache.resize(new_size)?;
         }
         page_cache.pages().write_bytes(offset, buf)?;
@@ -584,6 +596,43 @@ impl Inode for RamInode {
         self.write_at(offset, buf)
     }
 
+    fn write_user_at(&self, offset: usize, reader: &mut VmReader<'_, UserSpace>) -> Result<usize> {
+        let self_inode = self.node.upread();
+
+        let Some(page_cache) = self_inode.inner.as_file() else {
+            drop(self_inode);
+            let buf = read_all_from_user(reader)?;
+            return self.write_at(offset, &buf);
+        };
+        let new_size = offset + reader.remain();
+        if new_size > page_cache.pages().size() {
+            page_cache.resize(new_size)?;
+        }
+        let pages = page_cache.pages().dup();
+
+        // The lock must not be held while accessing the user buffer, which may fault and
+        // take arbitrarily long. If a page fault occurs, the file is only extended to the
+        // end of the written part, while the page cache is left as is.
+        drop(self_inode);
+        let written_len = pages.write_from_user(offset, reader)?;
+
+        let mut self_inode = self.node.write();
+        let now = now();
+        self_inode.set_mtime(now);
+        self_inode.set_ctime(now);
+        let end = offset + written_len;
+        if end > self_inode.metadata.size {
+            // The page cache may be truncated concurrently while copying.
+            let page_cache = self_inode.inner.as_file().unwrap();
+            if end > page_cache.pages().size() {
+                page_cache.resize(end)?;
+            }
+            self_inode.resize(end);
+        }
+
+        Ok(written_len)
+    }
+
     fn size(&self) -> usize {
         self.node.read().metadata.size
     }
@@ -1163,6 +1212,38 @@ impl Inode for RamInode {
         return_errno_with_message!(Errno::EINVAL, "ioctl is not supported");
     }
 
+    fn set_xattr(&self, name: XattrName, value: &[u8], flags: XattrSetFlags) -> Result<()> {
+        // The user attributes are only allowed on regular files and directories, since the
+        // permission bits of other types of files (e.g., sockets) have different meanings.
+        if name.namespace() == XattrNamespace::User
+            && self.typ != InodeType::File
+            && self.typ != InodeType::Dir
+        {
+            return_errno_with_message!(
+                Errno::EPERM,
+                "user xattr is only allowed on regular files and directories"
+            );
+        }
+
+        self.xattrs.set(name, value, flags)?;
+        self.set_ctime(now());
+        Ok(())
+    }
+
+    fn get_xattr(&self, name: XattrName) -> Result<Vec<u8>> {
+        self.xattrs.get(name)
+    }
+
+    fn list_xattr(&self) -> Result<Vec<String>> {
+        Ok(self.xattrs.list())
+    }
+
+    fn remove_xattr(&self, name: XattrName) -> Result<()> {
+        self.xattrs.remove(name)?;
+        self.set_ctime(now());
+        Ok(())
+    }
+
     fn extension(&self) -> Option<&Extension> {
         Some(&self.extension)
     }
@@ -1186,3 +1267,55 @@ fn write_lock_two_inodes<'a>(
 fn now() -> Duration {
     RealTimeCoarseClock::get().read_time()
 }
+
+#[cfg(ktest)]
+mod test {
+    use ostd::prelude::*;
+
+    use super::*;
+    use crate::fs::utils::XATTR_VALUE_MAX_LEN;
+
+    #[ktest]
+    fn xattr() {
+        let fs = RamFS::new();
+        let file = fs
+            .root_inode()
+            .create(
+                "file",
+                InodeType::File,
+                InodeMode::from_bits_truncate(0o644),
+            )
+            .unwrap();
+        let name = XattrName::try_from_full_name("user.foo").unwrap();
+
+        file.set_xattr(name, b"bar", XattrSetFlags::empty())
+            .unwrap();
+        assert_eq!(
+            file.set_xattr(name, b"baz", XattrSetFlags::CREATE_ONLY)
+                .unwrap_err()
+                .error(),
+            Errno::EEXIST
+        );
+        assert_eq!(file.list_xattr().unwrap(), vec![String::from("user.foo")]);
+        assert_eq!(file.get_xattr(name).unwrap(), b"bar");
+
+        let too_long = vec![0u8; XATTR_VALUE_MAX_LEN + 1];
+        assert_eq!(
+            file.set_xattr(name, &too_long, XattrSetFlags::empty())
+                .unwrap_err()
+                .error(),
+            Errno::E2BIG
+        );
+
+        file.remove_xattr(name).unwrap();
+        assert!(file.list_xattr().unwrap().is_empty());
+        assert_eq!(file.get_xattr(name).unwrap_err().error(), Errno::ENODATA);
+        assert_eq!(file.remove_xattr(name).unwrap_err().error(), Errno::ENODATA);
+        assert_eq!(
+            file.set_xattr(name, b"bar", XattrSetFlags::REPLACE_ONLY)
+                .unwrap_err()
+                .error(),
+            Errno::ENODATA
+        );
+    }
+}
@@ -1,5 +1,6 @@
 // SPDX-License-Identifier: MPL-2.0
 
+use core2::io::Read;
 use cpio_decoder::{CpioDecoder, FileType};
 use lending_iterator::LendingIterator;
 use libflate::gzip::Decoder as GZipDecoder;
@@ -7,23 +8,61 @@ use spin::Once;
 
 use super::{
     fs_resolver::{FsPath, FsResolver},
-    path::MountNode,
+    path::{Dentry, MountNode},
     procfs::ProcFS,
     ramfs::RamFS,
     utils::{FileSystem, InodeMode, InodeType},
 };
 use crate::prelude::*;
 
+/// The magic number at the beginning of a gzip buffer.
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
 /// Unpack and prepare the rootfs from the initramfs CPIO buffer.
-pub fn init(initramfs_buf: &[u8]) -> Result<()> {
+///
+/// The buffer can be either a plain or a gzip-compressed CPIO archive. If there is no
+/// initramfs, the rootfs only contains the mount points of the pseudo file systems.
+pub fn init(initramfs_buf: Option<&[u8]>) -> Result<()> {
     init_root_mount();
 
-    println!("[kernel] unpacking the initramfs.cpio.gz to rootfs ...");
     let fs = FsResolver::new();
-    let mut decoder = CpioDecoder::new(
-        GZipDecoder::new(initramfs_buf)
-            .map_err(|_| Error::with_message(Errno::EINVAL, "invalid gzip buffer"))?,
-    );
+    if let Some(initramfs_buf) = initramfs_buf {
+        if initramfs_buf.starts_with(&GZIP_MAGIC) {
+            println!("[kernel] unpacking the gzip-compressed CPIO initramfs to rootfs ...");
+            let gzip_decoder = GZipDecoder::new(initramfs_buf)
+                .map_err(|_| Error::with_message(Errno::EINVAL, "invalid gzip buffer"))?;
+            unpack_cpio(&fs, CpioDecoder::new(gzip_decoder))?;
+        } else {
+            println!("[kernel] unpacking the plain CPIO initramfs to rootfs ...");
+            unpack_cpio(&fs, CpioDecoder::new(initramfs_buf))?;
+        }
+    } else {
+        println!("[kernel] no initramfs found, the rootfs is empty");
+        let mode = InodeMode::from_bits_truncate(0o755);
+        for name in ["proc", "dev"] {
+            let _ = fs.root().new_fs_child(name, InodeType::Dir, mode)?;
+        }
+    }
+
+    // Mount ProcFS
+    let proc_dentry = fs.lookup(&FsPath::try_from("/proc")?)?;
+    proc_dentry.mount(ProcFS::new())?;
+    // Mount DevFS
+    let dev_dentry = fs.lookup(&FsPath::try_from("/dev")?)?;
+    dev_dentry.mount(RamFS::new())?;
+
+    println!("[kernel] rootfs is ready");
+
+    Ok(())
+}
+
+/// Unpacks a CPIO archive in the "newc" format to the root directory of `fs`.
+///
+/// The regular files that share the same inode number are hard links to each other. Only
+/// one of these entries carries the data, which is usually the last one.
+fn unpack_cpio<R: Read>(fs: &FsResolver, mut decoder: CpioDecoder<R>) -> Result<()> {
+    // The files that have multiple hard links, indexed by the device and inode numbers.
+    let mut hard_links: BTreeMap<(u32, u32, u32), Arc<Dentry>> = BTreeMap::new();
 
     loop {
         let Some(entry_result) = decoder.next() else {
@@ -51,9 +90,21 @@ pub fn init(initramfs_buf: &[u8]) -> Result<()> {
             (fs.root().clone(), entry_name)
         };
 
-        let metadata = entry.metadata();
+        let metadata = entry.metadata().clone();
         let mode = InodeMode::from_bits_truncate(metadata.permission_mode());
         match metadata.file_type() {
+            FileType::File if metadata.nlink() > 1 => {
+                let key = (metadata.dev_maj(), metadata.dev_min(), metadata.ino());
+                let dentry = if let Some(dentry) = hard_links.get(&key) {
+                    parent.link(dentry, name)?;
+                    dentry.clone()
+                } else {
+                    let dentry = parent.new_fs_child(name, InodeType::File, mode)?;
+                    hard_links.insert(key, dentry.clone());
+                    dentry
+                };
+                entry.read_all(dentry.inode().writer(0))?;
+            }
             FileType::File => {
                 let dentry = parent.new_fs_child(name, InodeType::File, mode)?;
                 entry.read_all(dentry.inode().writer(0))?;
@@ -75,15 +126,6 @@ pub fn init(initramfs_buf: &[u8]) -> Result<()> {
             }
         }
     }
-    // Mount ProcFS
-    let proc_dentry = fs.lookup(&FsPath::try_from("/proc")?)?;
-    proc_dentry.mount(ProcFS::new())?;
-    // Mount DevFS
-    let dev_dentry = fs.lookup(&FsPath::try_from("/dev")?)?;
-    dev_dentry.mount(RamFS::new())?;
-
-    println!("[kernel] rootfs is ready");
-
     Ok(())
 }
 
@@ -105,3 +147,108 @@ pub fn init_root_mount() {
 pub fn root_mount() -> &'static Arc<MountNode> {
     ROOT_MOUNT.get().unwrap()
 }
+
+#[cfg(ktest)]
+mod test {
+    use alloc::format;
+
+    use ostd::prelude::*;
+
+    use super::*;
+
+    /// Appends an entry in the "newc" format to the CPIO archive.
+    fn push_entry(archive: &mut Vec<u8>, name: &str, ino: u32, mode: u32, nlink: u32, data: &[u8]) {
+        let header = format!(
+            "070701{:08x}{:08x}{:08x}{:08x}{:08x}{:08x}{:08x}{:08x}{:08x}{:08x}{:08x}{:08x}{:08x}",
+            ino,
+            mode,
+            0, // uid
+            0, // gid
+            nlink,
+            0, // mtime
+            data.len(),
+            0, // dev_maj
+            0, // dev_min
+            0, // rdev_maj
+            0, // rdev_min
+            name.len() + 1,
+            0, // check
+        );
+        archive.extend_from_slice(header.as_bytes());
+        archive.extend_from_slice(name.as_bytes());
+        archive.push(0);
+        archive.resize(archive.len().next_multiple_of(4), 0);
+        archive.extend_from_slice(data);
+        archive.resize(archive.len().next_multiple_of(4), 0);
+    }
+
+    fn new_resolver() -> FsResolver {
+        init_root_mount();
+        let root = Dentry::new_fs_root(MountNode::new_root(RamFS::new()));
+        let mut fs = FsResolver::new();
+        fs.set_root(root.clone());
+        fs.set_cwd(root);
+        fs
+    }
+
+    fn read_file(fs: &FsResolver, path: &str) -> Vec<u8> {
+        let dentry = fs.lookup(&FsPath::try_from(path).unwrap()).unwrap();
+        let mut buf = vec![0u8; dentry.size()];
+        let len = dentry.inode().read_at(0, &mut buf).unwrap();
+        buf.truncate(len);
+        buf
+    }
+
+    fn mode_of(fs: &FsResolver, path: &str) -> u16 {
+        let dentry = fs.lookup(&FsPath::try_from(path).unwrap()).unwrap();
+        dentry.mode().unwrap().bits()
+    }
+
+    #[ktest]
+    fn unpack_entries() {
+        let mut archive = Vec::new();
+        push_entry(&mut archive, ".", 1, 0o040755, 3, &[]);
+        push_entry(&mut archive, "bin", 2, 0o040700, 2, &[]);
+        push_entry(&mut archive, "bin/hello", 3, 0o100755, 1, b"Hello, cpio!");
+        push_entry(&mut archive, "hello", 4, 0o120777, 1, b"bin/hello");
+        // Only the last hard link carries the data.
+        push_entry(&mut archive, "bin/first", 5, 0o100640, 2, &[]);
+        push_entry(&mut archive, "second", 5, 0o100640, 2, b"shared");
+        push_entry(&mut archive, "TRAILER!!!", 0, 0, 1, &[]);
+        // Anything after the trailer is ignored.
+        archive.extend_from_slice(&[0u8; 512]);
+
+        let fs = new_resolver();
+        unpack_cpio(&fs, CpioDecoder::new(archive.as_slice())).unwrap();
+
+        let bin = fs.lookup(&FsPath::try_from("bin").unwrap()).unwrap();
+        assert_eq!(bin.type_(), InodeType::Dir);
+        assert_eq!(mode_of(&fs, "bin"), 0o700);
+
+        assert_eq!(read_file(&fs, "bin/hello"), b"Hello, cpio!");
+        assert_eq!(mode_of(&fs, "bin/hello"), 0o755);
+
+        let link = fs
+            .lookup_no_follow(&FsPath::try_from("hello").unwrap())
+            .unwrap();
+        assert_eq!(link.type_(), InodeType::SymLink);
+        assert_eq!(link.inode().read_link().unwrap(), "bin/hello");
+        assert_eq!(read_file(&fs, "hello"), b"Hello, cpio!");
+
+        let first = fs.lookup(&FsPath::try_from("bin/first").unwrap()).unwrap();
+        let second = fs.lookup(&FsPath::try_from("second").unwrap()).unwrap();
+        assert_eq!(first.metadata().ino, second.metadata().ino);
+        assert_eq!(first.metadata().nlinks, 2);
+        assert_eq!(read_file(&fs, "bin/first"), b"shared");
+        assert_eq!(mode_of(&fs, "second"), 0o640);
+    }
+
+    #[ktest]
+    fn missing_trailer() {
+        let mut archive = Vec::new();
+        push_entry(&mut archive, "file", 2, 0o100644, 1, b"data");
+
+        let fs = new_resolver();
+        assert!(unpack_cpio(&fs, CpioDecoder::new(archive.as_slice())).is_err());
+    }
+}
@@ -1,6 +1,9 @@
 // SPDX-License-Identifier: MPL-2.0
 
-use core::sync::atomic::{AtomicBool, Ordering};
+use core::{
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+    time::Duration,
+};
 
 use aster_rights::{Read, ReadOp, TRights, Write, WriteOp};
 use aster_rights_proc::require;
@@ -10,6 +13,8 @@ use crate::{
     events::{IoEvents, Observer},
     prelude::*,
     process::signal::{Pollee, Poller},
+    thread::work_queue::{submit_work_item, work_item::WorkItem, WorkPriority},
+    time::{clocks::JIFFIES_TIMER_MANAGER, timer::Timeout, Timer},
 };
 
 /// A unidirectional communication channel, intended to implement IPC, e.g., pipe,
@@ -62,6 +67,16 @@ macro_rules! impl_common_methods_for_channel {
             self.this_end().shutdown()
         }
 
+        /// Shuts down the channel and notifies both ends.
+        ///
+        /// Unlike [`Self::shutdown`], this method adds `IoEvents::IN` to the consumer end, since
+        /// the end of the data can be read, and adds `IoEvents::OUT` to the producer end, since
+        /// a write fails immediately. So the waiters on either end are woken up.
+        pub fn shutdown_and_notify(&self) {
+            self.shutdown();
+            self.0.common.notify_shutdown();
+        }
+
         pub fn is_shutdown(&self) -> bool {
             self.this_end().is_shutdown()
         }
@@ -101,7 +116,7 @@ impl<T> Producer<T> {
         &self.0.common.consumer
     }
 
-    fn update_pollee(&self) {
+    fn update_pollee(&self, written_len: usize) {
         // In theory, `rb.is_full()`/`rb.is_empty()`, where the `rb` is taken from either
         // `this_end` or `peer_end`, should reflect the same state. However, we need to take the
         // correct lock when updating the events to avoid races between the state check and the
@@ -111,19 +126,81 @@ impl<T> Producer<T> {
         let rb = this_end.rb();
         if self.is_shutdown() || self.is_peer_shutdown() {
             // The POLLOUT event is always set in this case. Don't try to remove it.
-        } else if rb.is_full() {
+        } else if rb.free_len() < self.low_watermark() {
             this_end.pollee.del_events(IoEvents::OUT);
         }
         drop(rb);
 
+        // The data is readable at once. Only the wakeups of the batched writes are deferred.
         let peer_end = self.peer_end();
         let rb = peer_end.rb();
-        if !rb.is_empty() {
+        if rb.is_empty() {
+            // Nothing to notify.
+        } else if self.0.common.batch.defer_notify(written_len, rb.len()) {
+            peer_end.pollee.add_events_quietly(IoEvents::IN);
+        } else {
             peer_end.pollee.add_events(IoEvents::IN);
         }
         drop(rb);
     }
 
+    /// Returns whether small writes are batched.
+    pub fn is_batching(&self) -> bool {
+        self.0.common.batch.is_enabled()
+    }
+
+    /// Returns the low watermark of the free space.
+    ///
+    /// The channel is reported to be writable (`IoEvents::OUT`) only if the free space is no
+    /// less than the low watermark.
+    pub fn low_watermark(&self) -> usize {
+        self.0.common.low_watermark.load(Ordering::Relaxed)
+    }
+
+    /// Sets the low watermark of the free space.
+    ///
+    /// The low watermark is clamped to `1..=capacity`, where `1` means that the channel is
+    /// writable whenever it is not full.
+    pub fn set_low_watermark(&self, low_watermark: usize) {
+        let this_end = self.this_end();
+        let rb = this_end.rb();
+        let low_watermark = low_watermark.clamp(1, rb.capacity());
+        self.0
+            .common
+            .low_watermark
+            .store(low_watermark, Ordering::Relaxed);
+
+        if self.is_shutdown() || self.is_peer_shutdown() {
+            // The POLLOUT event is always set in this case. Don't try to remove it.
+        } else if rb.free_len() < low_watermark {
+            this_end.pollee.del_events(IoEvents::OUT);
+        } else if !this_end
+            .pollee
+            .poll(IoEvents::OUT, None)
+            .contains(IoEvents::OUT)
+        {
+            this_end.pollee.add_events(IoEvents::OUT);
+        }
+    }
+
+    /// Returns whether all the data written to the channel has been consumed.
+    pub fn is_drained(&self) -> bool {
+        self.peer_end().rb().is_empty()
+    }
+
+    /// Discards the data written to the channel that has not been consumed.
+    pub fn discard(&self) {
+        let peer_end = self.peer_end();
+        let mut rb = peer_end.rb();
+        rb.clear();
+        peer_end.pollee.del_events(IoEvents::IN);
+        drop(rb);
+
+        let this_end = self.this_end();
+        let _rb = this_end.rb();
+        this_end.pollee.add_events(IoEvents::OUT);
+    }
+
     impl_common_methods_for_channel!();
 }
 
@@ -134,6 +211,20 @@ impl<T: Copy> Producer<T> {
     /// - Returns `Err(EPIPE)` if the channel is shut down.
     /// - Returns `Err(EAGAIN)` if the channel is full.
     pub fn try_write(&self, buf: &[T]) -> Result<usize> {
+        self.try_write_inner(buf, false)
+    }
+
+    /// Tries to write `buf` to the channel as a whole.
+    ///
+    /// This method behaves like [`Self::try_write`], except that it writes nothing and returns
+    /// `Err(EAGAIN)` if the free space is not enough to hold the whole `buf`. A `buf` that is
+    /// larger than the capacity of the channel can never be written as a whole, so it will be
+    /// written partially, just like [`Self::try_write`] does.
+    pub fn try_write_atomic(&self, buf: &[T]) -> Result<usize> {
+        self.try_write_inner(buf, true)
+    }
+
+    fn try_write_inner(&self, buf: &[T], is_atomic: bool) -> Result<usize> {
         if buf.is_empty() {
             // Even after shutdown, writing an empty buffer is still fine.
             return Ok(0);
@@ -143,8 +234,12 @@ impl<T: Copy> Producer<T> {
             return_errno_with_message!(Errno::EPIPE, "the channel is shut down");
         }
 
-        let written_len = self.0.write(buf);
-        self.update_pollee();
+        let written_len = if is_atomic {
+            self.0.write_atomic(buf)
+        } else {
+            self.0.write(buf)
+        };
+        self.update_pollee(written_len);
 
         if written_len > 0 {
             Ok(written_len)
@@ -170,16 +265,49 @@ impl<T> Producer<T> {
             let err = Error::with_message(Errno::EAGAIN, "the channel is full");
             (err, item)
         })?;
-        self.update_pollee();
+        self.update_pollee(1);
 
         Ok(())
     }
 }
 
+impl<T: Send + 'static> Producer<T> {
+    /// Enables or disables the batching of small writes.
+    ///
+    /// If the batching is enabled, the observers and pollers of the consumer end are not woken up
+    /// by a write smaller than [`BATCH_THRESHOLD`] immediately. The wakeup is deferred until the
+    /// unconsumed data reaches the threshold, a larger write comes, or [`BATCH_DELAY`] has
+    /// elapsed, so that a sequence of small writes wakes up the reader only once. Note that the
+    /// data is readable, and is reported as readable by polls, right after the write. Only the
+    /// wakeup is deferred.
+    ///
+    /// Disabling the batching wakes up the consumer end for the deferred data immediately.
+    pub fn set_batching(&self, is_batching: bool) {
+        let common = &self.0.common;
+
+        if is_batching {
+            let weak_common = Arc::downgrade(common);
+            common.batch.enable(move || {
+                if let Some(common) = weak_common.upgrade() {
+                    common.batch.disarm();
+                    common.flush();
+                }
+            });
+        } else if common.batch.disable() {
+            common.flush();
+        }
+    }
+}
+
 impl<T> Drop for Producer<T> {
     fn drop(&mut self) {
         self.shutdown();
 
+        // Do not leave the data written in batches unnotified.
+        if self.0.common.batch.disable() {
+            self.0.common.flush();
+        }
+
         // The POLLHUP event indicates that the write end is shut down.
         //
         // No need to take a lock. There is no race because no one is modifying this particular event.
@@ -209,14 +337,29 @@ impl<T> Consumer<T> {
         }
         drop(rb);
 
+        // Only notify the writer if the free space crosses the low watermark, so that a reader
+        // consuming little by little does not wake up the writer again and again. Notify the
+        // writer as well if all the data is consumed, which is what draining writers wait for.
         let peer_end = self.peer_end();
         let rb = peer_end.rb();
-        if !rb.is_full() {
+        let low_watermark = self.0.common.low_watermark.load(Ordering::Relaxed);
+        if rb.free_len() >= low_watermark
+            && (rb.is_empty()
+                || !peer_end
+                    .pollee
+                    .poll(IoEvents::OUT, None)
+                    .contains(IoEvents::OUT))
+        {
             peer_end.pollee.add_events(IoEvents::OUT);
         }
         drop(rb);
     }
 
+    /// Returns whether there is no data to consume.
+    pub fn is_empty(&self) -> bool {
+        self.this_end().rb().is_empty()
+    }
+
     impl_common_methods_for_channel!();
 }
 
@@ -311,6 +454,18 @@ impl<T: Copy, R: TRights> Fifo<T, R> {
         let mut rb = self.common.producer.rb();
         rb.push_slice(buf)
     }
+
+    /// Writes `buf` as a whole, or writes nothing if the free space is not enough.
+    ///
+    /// If `buf` is larger than the capacity, this method writes as much as possible.
+    #[require(R > Write)]
+    pub fn write_atomic(&self, buf: &[T]) -> usize {
+        let mut rb = self.common.producer.rb();
+        if rb.free_len() < buf.len() && buf.len() <= rb.capacity() {
+            return 0;
+        }
+        rb.push_slice(buf)
+    }
 }
 
 impl<T, R: TRights> Fifo<T, R> {
@@ -334,6 +489,9 @@ impl<T, R: TRights> Fifo<T, R> {
 struct Common<T> {
     producer: FifoInner<HeapRbProducer<T>>,
     consumer: FifoInner<HeapRbConsumer<T>>,
+    /// The low watermark of the free space, below which the producer is not writable.
+    low_watermark: AtomicUsize,
+    batch: Batch,
 }
 
 impl<T> Common<T> {
@@ -344,12 +502,134 @@ impl<T> Common<T> {
         let producer = FifoInner::new(rb_producer, IoEvents::OUT);
         let consumer = FifoInner::new(rb_consumer, IoEvents::empty());
 
-        Self { producer, consumer }
+        Self {
+            producer,
+            consumer,
+            low_watermark: AtomicUsize::new(1),
+            batch: Batch::new(),
+        }
     }
 
     pub fn capacity(&self) -> usize {
         self.producer.rb().capacity()
     }
+
+    /// Notifies the consumer end of the unconsumed data, if any.
+    fn flush(&self) {
+        let rb = self.consumer.rb();
+        if !rb.is_empty() {
+            self.consumer.pollee.add_events(IoEvents::IN);
+        }
+        drop(rb);
+    }
+
+    fn notify_shutdown(&self) {
+        // The locks are taken to avoid races with the event updates triggered by reads and writes.
+        let rb = self.consumer.rb();
+        self.consumer.pollee.add_events(IoEvents::IN);
+        drop(rb);
+
+        let rb = self.producer.rb();
+        self.producer.pollee.add_events(IoEvents::OUT);
+        drop(rb);
+    }
+}
+
+/// The size of a write below which the write is batched, if the batching is enabled.
+pub const BATCH_THRESHOLD: usize = 4096;
+
+/// The maximum time that the notification of a batched write is deferred.
+///
+/// This is the same as the time limit of `TCP_CORK` in Linux.
+pub const BATCH_DELAY: Duration = Duration::from_millis(200);
+
+/// The state of the batching of small writes.
+struct Batch {
+    is_enabled: AtomicBool,
+    flush_timer: Mutex<FlushTimer>,
+}
+
+/// The timer to notify the deferred writes.
+struct FlushTimer {
+    /// The timer, which is absent if the batching is disabled or if the timers are not available
+    /// yet. In the latter case, the notification is deferred until the threshold is reached.
+    timer: Option<Arc<Timer>>,
+    is_armed: bool,
+}
+
+impl Batch {
+    fn new() -> Self {
+        Self {
+            is_enabled: AtomicBool::new(false),
+            flush_timer: Mutex::new(FlushTimer {
+                timer: None,
+                is_armed: false,
+            }),
+        }
+    }
+
+    fn is_enabled(&self) -> bool {
+        self.is_enabled.load(Ordering::Relaxed)
+    }
+
+    fn enable<F>(&self, flush: F)
+    where
+        F: Fn() + Send + Sync + 'static,
+    {
+        let mut flush_timer = self.flush_timer.lock();
+        if self.is_enabled() {
+            return;
+        }
+
+        // The timer callback runs in the interrupt context, where the locks of the channel cannot
+        // be taken. So the flush is deferred to a work item.
+        let work_item = Arc::new(WorkItem::new(Box::new(flush)));
+        flush_timer.timer = JIFFIES_TIMER_MANAGER.get().map(|manager| {
+            manager.create_timer(move || {
+                submit_work_item(work_item.clone(), WorkPriority::High);
+            })
+        });
+        flush_timer.is_armed = false;
+        self.is_enabled.store(true, Ordering::Relaxed);
+    }
+
+    /// Disables the batching and returns whether it was enabled.
+    fn disable(&self) -> bool {
+        let mut flush_timer = self.flush_timer.lock();
+        if let Some(timer) = flush_timer.timer.take() {
+            timer.cancel();
+        }
+        flush_timer.is_armed = false;
+        self.is_enabled.swap(false, Ordering::Relaxed)
+    }
+
+    /// Marks the timer as expired, so that it will be armed again for the next deferred write.
+    fn disarm(&self) {
+        self.flush_timer.lock().is_armed = false;
+    }
+
+    /// Decides whether to defer the notification of a write of `written_len` bytes, given that
+    /// there are `pending_len` bytes unconsumed.
+    fn defer_notify(&self, written_len: usize, pending_len: usize) -> bool {
+        if !self.is_enabled() {
+            return false;
+        }
+
+        let mut flush_timer = self.flush_timer.lock();
+        let is_small = written_len < BATCH_THRESHOLD && pending_len < BATCH_THRESHOLD;
+        let FlushTimer { timer, is_armed } = &mut *flush_timer;
+        if let Some(timer) = timer {
+            if !is_small && *is_armed {
+                timer.cancel();
+                *is_armed = false;
+            } else if is_small && !*is_armed {
+                timer.set_timeout(Timeout::After(BATCH_DELAY));
+                *is_armed = true;
+            }
+        }
+
+        is_small
+    }
 }
 
 struct FifoInner<T> {
@@ -406,4 +686,131 @@ mod test {
             assert_eq!(data, expected_data);
         }
     }
+
+    #[ktest]
+    fn low_watermark() {
+        use core::sync::atomic::AtomicUsize;
+
+        struct Counter(AtomicUsize);
+
+        impl Observer<IoEvents> for Counter {
+            fn on_events(&self, events: &IoEvents) {
+                if events.contains(IoEvents::OUT) {
+                    self.0.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+        }
+
+        let (producer, consumer) = Channel::new(16).split();
+        producer.set_low_watermark(8);
+        assert_eq!(producer.low_watermark(), 8);
+
+        let counter = Arc::new(Counter(AtomicUsize::new(0)));
+        let observer = Arc::downgrade(&counter) as Weak<dyn Observer<IoEvents>>;
+        producer.register_observer(observer, IoEvents::OUT).unwrap();
+        let is_writable = || producer.poll(IoEvents::OUT, None).contains(IoEvents::OUT);
+        let notifications = || counter.0.load(Ordering::Relaxed);
+
+        // Fill the channel.
+        assert_eq!(producer.try_write(&[0u8; 16]).unwrap(), 16);
+        assert!(!is_writable());
+
+        // The free space is still below the low watermark.
+        let mut buf = [0u8; 4];
+        consumer.try_read(&mut buf).unwrap();
+        assert!(!is_writable());
+        assert_eq!(notifications(), 0);
+
+        // The free space reaches the low watermark.
+        consumer.try_read(&mut buf).unwrap();
+        assert!(is_writable());
+        assert_eq!(notifications(), 1);
+
+        // Reading more does not notify the writer again.
+        consumer.try_read(&mut buf[..1]).unwrap();
+        assert!(is_writable());
+        assert_eq!(notifications(), 1);
+
+        // Draining the channel notifies the writer.
+        consumer.try_read(&mut [0u8; 16]).unwrap();
+        assert!(producer.is_drained());
+        assert_eq!(notifications(), 2);
+
+        producer.try_write(&[0u8; 12]).unwrap();
+        assert!(!is_writable());
+
+        // Lowering the low watermark below the free space sets `IoEvents::OUT`.
+        producer.set_low_watermark(0);
+        assert_eq!(producer.low_watermark(), 1);
+        assert!(is_writable());
+    }
+
+    #[ktest]
+    fn batching() {
+        use core::sync::atomic::AtomicUsize;
+
+        struct Counter(AtomicUsize);
+
+        impl Observer<IoEvents> for Counter {
+            fn on_events(&self, events: &IoEvents) {
+                if events.contains(IoEvents::IN) {
+                    self.0.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+        }
+
+        const NR_WRITES: usize = 64;
+
+        let count_wakeups = |is_batching: bool| {
+            let (producer, consumer) = Channel::new(BATCH_THRESHOLD * 2).split();
+            producer.set_batching(is_batching);
+            assert_eq!(producer.is_batching(), is_batching);
+
+            let counter = Arc::new(Counter(AtomicUsize::new(0)));
+            let observer = Arc::downgrade(&counter) as Weak<dyn Observer<IoEvents>>;
+            consumer.register_observer(observer, IoEvents::IN).unwrap();
+
+            for _ in 0..NR_WRITES {
+                producer.try_write(&[0u8; 4]).unwrap();
+            }
+            let wakeups = counter.0.load(Ordering::Relaxed);
+
+            // The data is readable even if the notification is deferred.
+            let mut buf = [0u8; NR_WRITES * 4];
+            assert_eq!(consumer.try_read(&mut buf).unwrap(), buf.len());
+
+            wakeups
+        };
+
+        assert_eq!(count_wakeups(false), NR_WRITES);
+        assert_eq!(count_wakeups(true), 0);
+
+        let (producer, consumer) = Channel::new(BATCH_THRESHOLD * 2).split();
+        producer.set_batching(true);
+
+        let counter = Arc::new(Counter(AtomicUsize::new(0)));
+        let observer = Arc::downgrade(&counter) as Weak<dyn Observer<IoEvents>>;
+        consumer.register_observer(observer, IoEvents::IN).unwrap();
+        let is_readable = || consumer.poll(IoEvents::IN, None).contains(IoEvents::IN);
+        let wakeups = || counter.0.load(Ordering::Relaxed);
+
+        // A small write is reported as readable at once, but its wakeup is deferred.
+        producer.try_write(&[0u8; 4]).unwrap();
+        assert!(is_readable());
+        assert_eq!(wakeups(), 0);
+
+        // A large write flushes the deferred wakeup.
+        producer.try_write(&[0u8; BATCH_THRESHOLD]).unwrap();
+        assert!(is_readable());
+        assert_eq!(wakeups(), 1);
+        consumer.try_read(&mut [0u8; BATCH_THRESHOLD * 2]).unwrap();
+        assert!(!is_readable());
+
+        // Disabling the batching flushes the deferred wakeup.
+        producer.try_write(&[0u8; 4]).unwrap();
+        assert!(is_readable());
+        assert_eq!(wakeups(), 1);
+        producer.set_batching(false);
+        assert_eq!(wakeups(), 2);
+    }
 }
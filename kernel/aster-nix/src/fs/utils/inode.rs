@@ -6,14 +6,16 @@ use core::{any::TypeId, time::Duration};
 
 use aster_rights::Full;
 use core2::io::{Error as IoError, ErrorKind as IoErrorKind, Result as IoResult, Write};
+use ostd::mm::UserSpace;
 
-use super::{DirentVisitor, FallocMode, FileSystem, IoctlCmd};
+use super::{DirentVisitor, FallocMode, FileSystem, IoctlCmd, XattrName, XattrSetFlags};
 use crate::{
     events::IoEvents,
     fs::device::{Device, DeviceType},
     prelude::*,
     process::{signal::Poller, Gid, Uid},
     time::clocks::RealTimeCoarseClock,
+    util::read_all_from_user,
     vm::vmo::Vmo,
 };
 
@@ -296,6 +298,19 @@ pub trait Inode: Any + Sync + Send {
         Err(Error::new(Errno::EISDIR))
     }
 
+    /// Writes the data from a user space reader at the given offset.
+    ///
+    /// The default implementation copies the data to a kernel buffer and then calls
+    /// [`Inode::write_at`]. Inodes that can consume the reader directly should override
+    /// this method to avoid the intermediate buffer.
+    ///
+    /// If a page fault occurs on the user buffer, the number of bytes written so far is
+    /// returned.
+    fn write_user_at(&self, offset: usize, reader: &mut VmReader<'_, UserSpace>) -> Result<usize> {
+        let buf = read_all_from_user(reader)?;
+        self.write_at(offset, &buf)
+    }
+
     fn create(&self, name: &str, type_: InodeType, mode: InodeMode) -> Result<Arc<dyn Inode>> {
         Err(Error::new(Errno::ENOTDIR))
     }
@@ -358,6 +373,26 @@ pub trait Inode: Any + Sync + Send {
         return_errno!(Errno::EOPNOTSUPP);
     }
 
+    /// Sets the value of an extended attribute.
+    fn set_xattr(&self, name: XattrName, value: &[u8], flags: XattrSetFlags) -> Result<()> {
+        return_errno_with_message!(Errno::EOPNOTSUPP, "xattr is not supported");
+    }
+
+    /// Returns the value of an extended attribute.
+    fn get_xattr(&self, name: XattrName) -> Result<Vec<u8>> {
+        return_errno_with_message!(Errno::EOPNOTSUPP, "xattr is not supported");
+    }
+
+    /// Returns the full names of all the extended attributes.
+    fn list_xattr(&self) -> Result<Vec<String>> {
+        return_errno_with_message!(Errno::EOPNOTSUPP, "xattr is not supported");
+    }
+
+    /// Removes an extended attribute.
+    fn remove_xattr(&self, name: XattrName) -> Result<()> {
+        return_errno_with_message!(Errno::EOPNOTSUPP, "xattr is not supported");
+    }
+
     fn poll(&self, mask: IoEvents, _poller: Option<&mut Poller>) -> IoEvents {
         let events = IoEvents::IN | IoEvents::OUT;
         events & mask
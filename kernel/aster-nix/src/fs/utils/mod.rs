@@ -13,12 +13,16 @@ pub use flock::{FlockItem, FlockList, FlockType};
 pub use fs::{FileSystem, FsFlags, SuperBlock};
 pub use inode::{Extension, Inode, InodeMode, InodeType, Metadata};
 pub use ioctl::IoctlCmd;
-pub use page_cache::{PageCache, PageCacheBackend};
+pub use page_cache::{spawn_writeback_thread, PageCache, PageCacheBackend};
 pub use random_test::{generate_random_operation, new_fs_in_memory};
 pub use range_lock::{
     FileRange, RangeLockItem, RangeLockItemBuilder, RangeLockList, RangeLockType, OFFSET_MAX,
 };
 pub use status_flags::StatusFlags;
+pub use xattr::{
+    XattrName, XattrNamespace, XattrSetFlags, Xattrs, XATTR_LIST_MAX_LEN, XATTR_NAME_MAX_LEN,
+    XATTR_VALUE_MAX_LEN,
+};
 
 mod access_mode;
 mod channel;
@@ -35,6 +39,7 @@ mod page_cache;
 mod random_test;
 mod range_lock;
 mod status_flags;
+mod xattr;
 
 use crate::prelude::*;
 
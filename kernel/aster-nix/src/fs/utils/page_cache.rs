@@ -2,16 +2,23 @@
 
 #![allow(dead_code)]
 
-use core::{iter, ops::Range};
+use core::{iter, ops::Range, time::Duration};
 
 use align_ext::AlignExt;
 use aster_block::bio::{BioStatus, BioWaiter};
 use aster_rights::Full;
-use lru::LruCache;
-use ostd::mm::{Frame, FrameAllocOptions, VmIo};
+use ostd::{
+    collections::radix_tree::{RadixTree, RadixTreeTag},
+    mm::{Frame, FrameAllocOptions, VmIo},
+    sync::WaitQueue,
+};
 
 use crate::{
     prelude::*,
+    thread::{
+        kernel_thread::{KernelThreadExt, ThreadOptions},
+        Thread,
+    },
     vm::vmo::{get_page_idx_range, Pager, Vmo, VmoFlags, VmoOptions},
 };
 
@@ -23,7 +30,7 @@ pub struct PageCache {
 impl PageCache {
     /// Creates an empty size page cache associated with a new backend.
     pub fn new(backend: Weak<dyn PageCacheBackend>) -> Result<Self> {
-        let manager = Arc::new(PageCacheManager::new(backend));
+        let manager = PageCacheManager::new(backend);
         let pages = VmoOptions::<Full>::new(0)
             .flags(VmoFlags::RESIZABLE)
             .pager(manager.clone())
@@ -36,7 +43,7 @@ impl PageCache {
     /// The `capacity` is the initial cache size required by the backend.
     /// This size usually corresponds to the size of the backend.
     pub fn with_capacity(capacity: usize, backend: Weak<dyn PageCacheBackend>) -> Result<Self> {
-        let manager = Arc::new(PageCacheManager::new(backend));
+        let manager = PageCacheManager::new(backend);
         let pages = VmoOptions::<Full>::new(capacity)
             .flags(VmoFlags::RESIZABLE)
             .pager(manager.clone())
@@ -80,6 +87,13 @@ impl PageCache {
                 self.fill_zeros(new_size..new_size + gap_size)?;
             }
         }
+        // The pages beyond the new size are truncated, so they should be
+        // discarded rather than written back.
+        // TODO: Unmap the truncated pages from the shared mappings, so that
+        // the later accesses to them get `SIGBUS` like those beyond the EOF.
+        if old_size > new_size {
+            self.discard_range(new_size.align_up(PAGE_SIZE)..old_size);
+        }
         self.pages.resize(new_size)
     }
 
@@ -242,14 +256,14 @@ impl ReadaheadState {
     /// Waits for the previous readahead.
     pub fn wait_for_prev_readahead(
         &mut self,
-        pages: &mut MutexGuard<LruCache<usize, Page>>,
+        pages: &mut MutexGuard<RadixTree<Page>>,
     ) -> Result<()> {
         if matches!(self.waiter.wait(), Some(BioStatus::Complete)) {
             let Some(window) = &self.ra_window else {
                 return_errno!(Errno::EINVAL)
             };
             for idx in window.readahead_range() {
-                if let Some(page) = pages.get_mut(&idx) {
+                if let Some(page) = pages.get_mut(idx as u64) {
                     page.set_state(PageState::UpToDate);
                 }
             }
@@ -297,7 +311,7 @@ impl ReadaheadState {
     /// Sends the relevant read request and sets the relevant page in the page cache to `Uninit`.
     pub fn conduct_readahead(
         &mut self,
-        pages: &mut MutexGuard<LruCache<usize, Page>>,
+        pages: &mut MutexGuard<RadixTree<Page>>,
         backend: Arc<dyn PageCacheBackend>,
     ) -> Result<()> {
         let Some(window) = &self.ra_window else {
@@ -308,7 +322,7 @@ impl ReadaheadState {
             let pg_waiter = backend.read_page_async(async_idx, async_page.frame())?;
             self.waiter.concat(pg_waiter);
             async_page.set_state(PageState::Uninit);
-            pages.put(async_idx, async_page);
+            insert_page(pages, async_idx, async_page);
         }
         Ok(())
     }
@@ -319,19 +333,59 @@ impl ReadaheadState {
     }
 }
 
+/// The interval of the periodic writeback of the dirty pages.
+const WRITEBACK_INTERVAL: Duration = Duration::from_secs(5);
+
+/// All the page cache managers, whose dirty pages are written back periodically.
+static PAGE_CACHE_MANAGERS: Mutex<Vec<Weak<PageCacheManager>>> = Mutex::new(Vec::new());
+
+/// Spawns the kernel thread that periodically writes back the dirty pages
+/// of all the page caches.
+pub fn spawn_writeback_thread() {
+    Thread::spawn_kernel_thread(ThreadOptions::new(|| {
+        let sleep_queue = WaitQueue::new();
+        loop {
+            sleep_queue.wait_until_or_timeout(|| -> Option<()> { None }, &WRITEBACK_INTERVAL);
+            writeback_all();
+        }
+    }));
+}
+
+/// Writes back the dirty pages of all the page caches.
+fn writeback_all() {
+    let managers: Vec<_> = {
+        let mut managers = PAGE_CACHE_MANAGERS.lock();
+        managers.retain(|manager| manager.strong_count() > 0);
+        managers.iter().filter_map(Weak::upgrade).collect()
+    };
+    for manager in managers {
+        // The backend may be destroyed while its page cache is still alive.
+        let Some(backend) = manager.backend.upgrade() else {
+            continue;
+        };
+        let size = backend.npages() * PAGE_SIZE;
+        if let Err(err) = manager.evict_range(0..size) {
+            warn!("failed to write back the page cache: {:?}", err);
+        }
+    }
+}
+
 struct PageCacheManager {
-    pages: Mutex<LruCache<usize, Page>>,
+    /// The cached pages, where the dirty pages are tagged with [`RadixTreeTag::Dirty`].
+    pages: Mutex<RadixTree<Page>>,
     backend: Weak<dyn PageCacheBackend>,
     ra_state: Mutex<ReadaheadState>,
 }
 
 impl PageCacheManager {
-    pub fn new(backend: Weak<dyn PageCacheBackend>) -> Self {
-        Self {
-            pages: Mutex::new(LruCache::unbounded()),
+    pub fn new(backend: Weak<dyn PageCacheBackend>) -> Arc<Self> {
+        let manager = Arc::new(Self {
+            pages: Mutex::new(RadixTree::new()),
             backend,
             ra_state: Mutex::new(ReadaheadState::new()),
-        }
+        });
+        PAGE_CACHE_MANAGERS.lock().push(Arc::downgrade(&manager));
+        manager
     }
 
     pub fn backend(&self) -> Arc<dyn PageCacheBackend> {
@@ -342,39 +396,53 @@ impl PageCacheManager {
     pub fn discard_range(&self, range: Range<usize>) {
         let page_idx_range = get_page_idx_range(&range);
         let mut pages = self.pages.lock();
-        for idx in page_idx_range {
-            pages.pop(&idx);
+        let idxs: Vec<_> = pages
+            .range(page_idx_range.start as u64..page_idx_range.end as u64)
+            .map(|(idx, _)| idx)
+            .collect();
+        for idx in idxs {
+            pages.remove(idx);
         }
     }
 
+    /// Writes the dirty pages within the range back to the backend.
+    ///
+    /// The pages are marked clean and tagged with [`RadixTreeTag::Writeback`]
+    /// before the I/O, and the lock of the pages is released during the I/O,
+    /// so the pages written again meanwhile will be marked dirty again.
     pub fn evict_range(&self, range: Range<usize>) -> Result<()> {
         let page_idx_range = get_page_idx_range(&range);
-
-        let mut bio_waiter = BioWaiter::new();
-        let mut pages = self.pages.lock();
         let backend = self.backend();
         let backend_npages = backend.npages();
-        for idx in page_idx_range.start..page_idx_range.end {
-            if let Some(page) = pages.peek(&idx) {
-                if *page.state() == PageState::Dirty && idx < backend_npages {
-                    let waiter = backend.write_page_async(idx, page.frame())?;
-                    bio_waiter.concat(waiter);
-                }
+
+        let mut writeback_pages = Vec::new();
+        {
+            let mut pages = self.pages.lock();
+            let dirty_idxs: Vec<_> = pages
+                .range_tagged(
+                    page_idx_range.start as u64..page_idx_range.end as u64,
+                    RadixTreeTag::Dirty,
+                )
+                .map(|(idx, _)| idx as usize)
+                .filter(|idx| *idx < backend_npages)
+                .collect();
+            for idx in dirty_idxs {
+                set_page_state(&mut pages, idx, PageState::UpToDate);
+                pages.set_tag(idx as u64, RadixTreeTag::Writeback);
+                writeback_pages.push((idx, pages.get(idx as u64).unwrap().frame().clone()));
             }
         }
 
-        if !matches!(bio_waiter.wait(), Some(BioStatus::Complete)) {
-            // Do not allow partial failure
-            return_errno!(Errno::EIO);
-        }
+        let res = write_pages(backend.as_ref(), &writeback_pages);
 
-        for (_, page) in pages
-            .iter_mut()
-            .filter(|(idx, _)| page_idx_range.contains(idx))
-        {
-            page.set_state(PageState::UpToDate);
+        let mut pages = self.pages.lock();
+        for (idx, _) in writeback_pages {
+            pages.clear_tag(idx as u64, RadixTreeTag::Writeback);
+            if res.is_err() {
+                set_page_state(&mut pages, idx, PageState::Dirty);
+            }
         }
-        Ok(())
+        res
     }
 
     fn ondemand_readahead(&self, idx: usize) -> Result<Frame> {
@@ -389,7 +457,7 @@ impl PageCacheManager {
         // 1. The requested page is ready for read in page cache.
         // 2. The requested page is in previous readahead range, not ready for now.
         // 3. The requested page is on disk, need a sync read operation here.
-        let frame = if let Some(page) = pages.get(&idx) {
+        let frame = if let Some(page) = pages.get(idx as u64) {
             // Cond 1 & 2.
             if let PageState::Uninit = page.state() {
                 // Cond 2: We should wait for the previous readahead.
@@ -398,7 +466,7 @@ impl PageCacheManager {
                     return_errno!(Errno::EINVAL)
                 }
                 ra_state.wait_for_prev_readahead(&mut pages)?;
-                pages.get(&idx).unwrap().frame().clone()
+                pages.get(idx as u64).unwrap().frame().clone()
             } else {
                 // Cond 1.
                 page.frame().clone()
@@ -415,7 +483,7 @@ impl PageCacheManager {
                 Page::alloc_zero()?
             };
             let frame = page.frame().clone();
-            pages.put(idx, page);
+            insert_page(&mut pages, idx, page);
             frame
         };
         if ra_state.should_readahead(idx, backend.npages()) {
@@ -430,7 +498,7 @@ impl PageCacheManager {
 impl Debug for PageCacheManager {
     fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
         f.debug_struct("PageCacheManager")
-            .field("pages", &self.pages.lock())
+            .field("npages", &self.pages.lock().len())
             .finish()
     }
 }
@@ -442,8 +510,8 @@ impl Pager for PageCacheManager {
 
     fn update_page(&self, idx: usize) -> Result<()> {
         let mut pages = self.pages.lock();
-        if let Some(page) = pages.get_mut(&idx) {
-            page.set_state(PageState::Dirty);
+        if pages.get(idx as u64).is_some() {
+            set_page_state(&mut pages, idx, PageState::Dirty);
         } else {
             warn!("The page {} is not in page cache", idx);
         }
@@ -452,7 +520,7 @@ impl Pager for PageCacheManager {
     }
 
     fn decommit_page(&self, idx: usize) -> Result<()> {
-        let page_result = self.pages.lock().pop(&idx);
+        let page_result = self.pages.lock().remove(idx as u64);
         if let Some(page) = page_result {
             if let PageState::Dirty = page.state() {
                 let Some(backend) = self.backend.upgrade() else {
@@ -468,12 +536,57 @@ impl Pager for PageCacheManager {
     }
 
     fn commit_overwrite(&self, idx: usize) -> Result<Frame> {
-        if let Some(page) = self.pages.lock().get(&idx) {
+        let mut pages = self.pages.lock();
+        if let Some(page) = pages.get(idx as u64) {
             return Ok(page.frame.clone());
         }
 
         let page = Page::alloc_zero()?;
-        Ok(self.pages.lock().get_or_insert(idx, || page).frame.clone())
+        let frame = page.frame.clone();
+        insert_page(&mut pages, idx, page);
+        Ok(frame)
+    }
+}
+
+/// Writes the pages to the backend and waits for the completion.
+fn write_pages(backend: &dyn PageCacheBackend, pages: &[(usize, Frame)]) -> Result<()> {
+    let mut bio_waiter = BioWaiter::new();
+    let mut res = Ok(());
+    for (idx, frame) in pages {
+        match backend.write_page_async(*idx, frame) {
+            Ok(waiter) => bio_waiter.concat(waiter),
+            Err(err) => {
+                res = Err(err);
+                break;
+            }
+        }
+    }
+
+    // Wait for the submitted requests even if an error occurs.
+    if !matches!(bio_waiter.wait(), Some(BioStatus::Complete)) {
+        // Do not allow partial failure
+        return_errno!(Errno::EIO);
+    }
+    res
+}
+
+/// Inserts a page into the page cache, tagging it according to its state.
+fn insert_page(pages: &mut RadixTree<Page>, idx: usize, page: Page) {
+    let state = page.state;
+    pages.insert(idx as u64, page);
+    set_page_state(pages, idx, state);
+}
+
+/// Sets the state of a cached page, keeping its dirty tag consistent with the state.
+fn set_page_state(pages: &mut RadixTree<Page>, idx: usize, state: PageState) {
+    let Some(page) = pages.get_mut(idx as u64) else {
+        return;
+    };
+    page.set_state(state);
+    if state == PageState::Dirty {
+        pages.set_tag(idx as u64, RadixTreeTag::Dirty);
+    } else {
+        pages.clear_tag(idx as u64, RadixTreeTag::Dirty);
     }
 }
 
@@ -554,3 +667,130 @@ impl dyn PageCacheBackend {
         }
     }
 }
+
+#[cfg(ktest)]
+mod test {
+    use core::sync::atomic::{AtomicUsize, Ordering};
+
+    use ostd::prelude::*;
+
+    use super::*;
+    use crate::vm::{
+        page_fault_handler::PageFaultHandler, perms::VmPerms, vmar::Vmar, vmo::VmoRightsOp,
+    };
+
+    /// A backend that only counts the I/O requests.
+    struct MockBackend {
+        npages: usize,
+        nr_reads: AtomicUsize,
+        nr_writes: AtomicUsize,
+    }
+
+    impl PageCacheBackend for MockBackend {
+        fn read_page_async(&self, _idx: usize, _frame: &Frame) -> Result<BioWaiter> {
+            self.nr_reads.fetch_add(1, Ordering::Relaxed);
+            Ok(BioWaiter::new())
+        }
+
+        fn write_page_async(&self, _idx: usize, _frame: &Frame) -> Result<BioWaiter> {
+            self.nr_writes.fetch_add(1, Ordering::Relaxed);
+            Ok(BioWaiter::new())
+        }
+
+        fn npages(&self) -> usize {
+            self.npages
+        }
+    }
+
+    fn new_page_cache(npages: usize) -> (Arc<MockBackend>, PageCache) {
+        let backend = Arc::new(MockBackend {
+            npages,
+            nr_reads: AtomicUsize::new(0),
+            nr_writes: AtomicUsize::new(0),
+        });
+        let dyn_backend: Arc<dyn PageCacheBackend> = backend.clone();
+        let page_cache =
+            PageCache::with_capacity(npages * PAGE_SIZE, Arc::downgrade(&dyn_backend)).unwrap();
+        (backend, page_cache)
+    }
+
+    #[ktest]
+    fn second_read_hits_cache() {
+        let (backend, page_cache) = new_page_cache(1);
+        let mut buf = [0u8; 16];
+        page_cache.pages().read_bytes(0, &mut buf).unwrap();
+        page_cache.pages().read_bytes(0, &mut buf).unwrap();
+        assert_eq!(backend.nr_reads.load(Ordering::Relaxed), 1);
+    }
+
+    #[ktest]
+    fn writeback_dirty_pages() {
+        let (backend, page_cache) = new_page_cache(2);
+        page_cache.pages().write_bytes(0, &[1u8; 16]).unwrap();
+        assert!(page_cache
+            .manager
+            .pages
+            .lock()
+            .is_tagged(0, RadixTreeTag::Dirty));
+
+        page_cache.evict_range(0..2 * PAGE_SIZE).unwrap();
+        assert_eq!(backend.nr_writes.load(Ordering::Relaxed), 1);
+        // The page is clean after being written back.
+        page_cache.evict_range(0..2 * PAGE_SIZE).unwrap();
+        assert_eq!(backend.nr_writes.load(Ordering::Relaxed), 1);
+    }
+
+    #[ktest]
+    fn truncate_discards_pages() {
+        let (backend, page_cache) = new_page_cache(2);
+        page_cache
+            .pages()
+            .write_bytes(PAGE_SIZE, &[1u8; 16])
+            .unwrap();
+
+        page_cache.resize(PAGE_SIZE).unwrap();
+        assert!(page_cache.manager.pages.lock().get(1).is_none());
+        assert_eq!(backend.nr_writes.load(Ordering::Relaxed), 0);
+    }
+
+    #[ktest]
+    fn write_through_shared_mapping() {
+        const MAP_ADDR: usize = 0x1000_0000;
+        let (backend, page_cache) = new_page_cache(1);
+        page_cache.pages().write_bytes(0, &[1u8; 16]).unwrap();
+        page_cache.evict_range(0..PAGE_SIZE).unwrap();
+        assert_eq!(backend.nr_writes.load(Ordering::Relaxed), 1);
+
+        let vmar = Vmar::<Full>::new_root();
+        vmar.new_map(2 * PAGE_SIZE, VmPerms::READ | VmPerms::WRITE)
+            .unwrap()
+            .vmo(page_cache.pages().dup().to_dyn())
+            .offset(MAP_ADDR)
+            .is_shared(true)
+            .build()
+            .unwrap();
+
+        // A read fault maps the cached page without dirtying it.
+        vmar.handle_page_fault(MAP_ADDR, true, false).unwrap();
+        let is_dirty = || {
+            page_cache
+                .manager
+                .pages
+                .lock()
+                .is_tagged(0, RadixTreeTag::Dirty)
+        };
+        assert!(!is_dirty());
+
+        // The first write dirties the page, which is then written back.
+        vmar.handle_page_fault(MAP_ADDR, false, true).unwrap();
+        assert!(is_dirty());
+        page_cache.evict_range(0..PAGE_SIZE).unwrap();
+        assert_eq!(backend.nr_writes.load(Ordering::Relaxed), 2);
+
+        // The page beyond the end of the file cannot be accessed.
+        let err = vmar
+            .handle_page_fault(MAP_ADDR + PAGE_SIZE, true, false)
+            .unwrap_err();
+        assert_eq!(err.error(), Errno::EIO);
+    }
+}
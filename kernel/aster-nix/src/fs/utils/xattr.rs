@@ -0,0 +1,183 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Extended attributes of inodes.
+//!
+//! An extended attribute is a name-value pair associated with an inode. The name
+//! is prefixed with a namespace (e.g., `user.foo`), which decides who can access
+//! the attribute.
+
+use crate::prelude::*;
+
+/// The maximum length of an attribute name, including the namespace prefix.
+pub const XATTR_NAME_MAX_LEN: usize = 255;
+
+/// The maximum length of an attribute value.
+pub const XATTR_VALUE_MAX_LEN: usize = 65536;
+
+/// The maximum length of the list of attribute names.
+pub const XATTR_LIST_MAX_LEN: usize = 65536;
+
+/// The namespace of an extended attribute.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum XattrNamespace {
+    /// The namespace for any user who can access the file.
+    User,
+    /// The namespace for the security modules.
+    Security,
+    /// The namespace that can only be accessed with `CAP_SYS_ADMIN`.
+    Trusted,
+}
+
+impl XattrNamespace {
+    const ALL: [Self; 3] = [Self::User, Self::Security, Self::Trusted];
+
+    fn prefix(&self) -> &'static str {
+        match self {
+            Self::User => "user.",
+            Self::Security => "security.",
+            Self::Trusted => "trusted.",
+        }
+    }
+}
+
+/// A validated name of an extended attribute.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct XattrName<'a> {
+    namespace: XattrNamespace,
+    full_name: &'a str,
+}
+
+impl<'a> XattrName<'a> {
+    /// Parses the full name of an attribute, which includes the namespace prefix.
+    ///
+    /// An empty or too long name is rejected with `ERANGE`, and a name outside the
+    /// supported namespaces is rejected with `EOPNOTSUPP`, in the same way as Linux.
+    pub fn try_from_full_name(full_name: &'a str) -> Result<Self> {
+        if full_name.is_empty() || full_name.len() > XATTR_NAME_MAX_LEN {
+            return_errno_with_message!(Errno::ERANGE, "the xattr name length is invalid");
+        }
+
+        let namespace = XattrNamespace::ALL
+            .into_iter()
+            .find(|namespace| full_name.starts_with(namespace.prefix()))
+            .ok_or_else(|| {
+                Error::with_message(Errno::EOPNOTSUPP, "the xattr namespace is not supported")
+            })?;
+        if full_name.len() == namespace.prefix().len() {
+            return_errno_with_message!(Errno::EINVAL, "the xattr name has no suffix");
+        }
+
+        Ok(Self {
+            namespace,
+            full_name,
+        })
+    }
+
+    pub fn namespace(&self) -> XattrNamespace {
+        self.namespace
+    }
+
+    pub fn full_name(&self) -> &'a str {
+        self.full_name
+    }
+}
+
+bitflags! {
+    /// The flags of setting an extended attribute.
+    pub struct XattrSetFlags: u32 {
+        /// Fails if the attribute already exists.
+        const CREATE_ONLY = 1;
+        /// Fails if the attribute does not exist.
+        const REPLACE_ONLY = 2;
+    }
+}
+
+/// An in-memory set of extended attributes.
+///
+/// It can be used by the file systems that keep their inodes in memory.
+pub struct Xattrs {
+    attrs: RwMutex<BTreeMap<String, Vec<u8>>>,
+}
+
+impl Xattrs {
+    pub fn new() -> Self {
+        Self {
+            attrs: RwMutex::new(BTreeMap::new()),
+        }
+    }
+
+    /// Sets the value of an attribute.
+    pub fn set(&self, name: XattrName, value: &[u8], flags: XattrSetFlags) -> Result<()> {
+        if value.len() > XATTR_VALUE_MAX_LEN {
+            return_errno_with_message!(Errno::E2BIG, "the xattr value is too long");
+        }
+
+        let mut attrs = self.attrs.write();
+        let is_present = attrs.contains_key(name.full_name());
+        if flags.contains(XattrSetFlags::CREATE_ONLY) && is_present {
+            return_errno_with_message!(Errno::EEXIST, "the xattr already exists");
+        }
+        if flags.contains(XattrSetFlags::REPLACE_ONLY) && !is_present {
+            return_errno_with_message!(Errno::ENODATA, "the xattr does not exist");
+        }
+
+        attrs.insert(name.full_name().to_string(), value.to_vec());
+        Ok(())
+    }
+
+    /// Returns the value of an attribute.
+    pub fn get(&self, name: XattrName) -> Result<Vec<u8>> {
+        self.attrs
+            .read()
+            .get(name.full_name())
+            .cloned()
+            .ok_or_else(|| Error::with_message(Errno::ENODATA, "the xattr does not exist"))
+    }
+
+    /// Returns the full names of all the attributes.
+    pub fn list(&self) -> Vec<String> {
+        self.attrs.read().keys().cloned().collect()
+    }
+
+    /// Removes an attribute.
+    pub fn remove(&self, name: XattrName) -> Result<()> {
+        self.attrs
+            .write()
+            .remove(name.full_name())
+            .map(|_| ())
+            .ok_or_else(|| Error::with_message(Errno::ENODATA, "the xattr does not exist"))
+    }
+}
+
+impl Default for Xattrs {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(ktest)]
+mod test {
+    use ostd::prelude::*;
+
+    use super::*;
+
+    #[ktest]
+    fn parse_name() {
+        let name = XattrName::try_from_full_name("user.foo").unwrap();
+        assert_eq!(name.namespace(), XattrNamespace::User);
+        assert_eq!(name.full_name(), "user.foo");
+        assert_eq!(
+            XattrName::try_from_full_name("security.selinux")
+                .unwrap()
+                .namespace(),
+            XattrNamespace::Security
+        );
+
+        let errno_of = |name: &str| XattrName::try_from_full_name(name).unwrap_err().error();
+        assert_eq!(errno_of(""), Errno::ERANGE);
+        assert_eq!(errno_of(&"a".repeat(XATTR_NAME_MAX_LEN + 1)), Errno::ERANGE);
+        assert_eq!(errno_of("system.posix_acl_access"), Errno::EOPNOTSUPP);
+        assert_eq!(errno_of("foo"), Errno::EOPNOTSUPP);
+        assert_eq!(errno_of("user."), Errno::EINVAL);
+    }
+}
@@ -301,7 +301,7 @@ impl Socket for DatagramSocket {
 
         let MessageHeader {
             addr,
-            control_message,
+            control_messages,
         } = message_header;
 
         let remote_endpoint = match addr {
@@ -318,7 +318,7 @@ impl Socket for DatagramSocket {
             })?,
         };
 
-        if control_message.is_some() {
+        if !control_messages.is_empty() {
             // TODO: Support sending control message
             warn!("sending control message is not supported");
         }
@@ -344,7 +344,7 @@ impl Socket for DatagramSocket {
 
         // TODO: Receive control message
 
-        let message_header = MessageHeader::new(Some(peer_addr), None);
+        let message_header = MessageHeader::new(Some(peer_addr), Vec::new());
 
         Ok((copied_bytes, message_header))
     }
@@ -1,11 +1,22 @@
 // SPDX-License-Identifier: MPL-2.0
 
 use super::CongestionControl;
-use crate::impl_socket_options;
+use crate::{impl_socket_options, net::socket::options::SocketOption};
 
 impl_socket_options!(
     pub struct NoDelay(bool);
+    pub struct Cork(bool);
     pub struct Congestion(CongestionControl);
     pub struct MaxSegment(u32);
     pub struct WindowClamp(u32);
 );
+
+/// Returns whether `option` is an option at the TCP level.
+pub fn is_tcp_option(option: &dyn SocketOption) -> bool {
+    let option = option.as_any();
+    option.is::<NoDelay>()
+        || option.is::<Cork>()
+        || option.is::<Congestion>()
+        || option.is::<MaxSegment>()
+        || option.is::<WindowClamp>()
+}
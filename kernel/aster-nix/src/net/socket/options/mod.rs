@@ -17,7 +17,11 @@ impl_socket_options!(
     pub struct ReusePort(bool);
     pub struct SendBuf(u32);
     pub struct RecvBuf(u32);
+    pub struct SendLowat(u32);
     pub struct Error(Option<crate::error::Error>);
     pub struct Linger(LingerOption);
     pub struct KeepAlive(bool);
+    pub struct SendTimeout(core::time::Duration);
+    pub struct RecvTimeout(core::time::Duration);
+    pub struct PassCred(bool);
 );
@@ -1,6 +1,17 @@
 // SPDX-License-Identifier: MPL-2.0
 
-use crate::{fs::path::Dentry, net::socket::util::socket_addr::SocketAddr, prelude::*};
+use keyable_arc::KeyableWeak;
+
+use crate::{
+    fs::{
+        fs_resolver::{split_path, FsPath},
+        path::Dentry,
+        utils::{Inode, InodeMode, InodeType},
+    },
+    net::socket::util::socket_addr::SocketAddr,
+    prelude::*,
+    util::collections::ShardedMap,
+};
 
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub enum UnixSocketAddr {
@@ -9,6 +20,22 @@ pub enum UnixSocketAddr {
     Abstract(Arc<[u8]>),
 }
 
+impl UnixSocketAddr {
+    /// Resolves the address of a remote socket, to which the socket connects or sends.
+    pub(super) fn resolve_remote(self) -> Result<UnixSocketAddrBound> {
+        match self {
+            Self::Unnamed => {
+                return_errno_with_message!(Errno::EINVAL, "the remote address is unnamed")
+            }
+            Self::Abstract(abstract_name) => Ok(UnixSocketAddrBound::Abstract(abstract_name)),
+            Self::Path(path) => {
+                let dentry = lookup_socket_file(&path)?;
+                Ok(UnixSocketAddrBound::Path(path, dentry))
+            }
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub(super) enum UnixSocketAddrBound {
     Path(Arc<str>, Arc<Dentry>),
@@ -49,3 +76,96 @@ impl<T: Into<UnixSocketAddr>> From<T> for SocketAddr {
         SocketAddr::Unix(value.into())
     }
 }
+
+pub(super) fn create_socket_file(path: &str) -> Result<Arc<Dentry>> {
+    let (parent_pathname, file_name) = split_path(path);
+    let parent = {
+        let current = current!();
+        let fs = current.fs().read();
+        let parent_path = FsPath::try_from(parent_pathname)?;
+        fs.lookup(&parent_path)?
+    };
+    let dentry = parent
+        .new_fs_child(
+            file_name,
+            InodeType::Socket,
+            InodeMode::S_IRUSR | InodeMode::S_IWUSR,
+        )
+        .map_err(|err| match err.error() {
+            // Like Linux, report `EADDRINUSE` if the socket file already exists.
+            Errno::EEXIST => Error::with_message(Errno::EADDRINUSE, "the addr is already used"),
+            _ => err,
+        })?;
+    Ok(dentry)
+}
+
+/// Removes the socket file created by [`create_socket_file`], e.g., when the
+/// socket fails to be bound to it.
+pub(super) fn remove_socket_file(path: &str) -> Result<()> {
+    let (parent_pathname, file_name) = split_path(path);
+    let parent = {
+        let current = current!();
+        let fs = current.fs().read();
+        let parent_path = FsPath::try_from(parent_pathname)?;
+        fs.lookup(&parent_path)?
+    };
+    parent.unlink(file_name)
+}
+
+pub(super) fn lookup_socket_file(path: &str) -> Result<Arc<Dentry>> {
+    let dentry = {
+        let current = current!();
+        let fs = current.fs().read();
+        let fs_path = FsPath::try_from(path)?;
+        fs.lookup(&fs_path)?
+    };
+
+    if dentry.type_() != InodeType::Socket {
+        return_errno_with_message!(Errno::ENOTSOCK, "not a socket file")
+    }
+
+    if !dentry.mode()?.is_readable() || !dentry.mode()?.is_writable() {
+        return_errno_with_message!(Errno::EACCES, "the socket cannot be read or written")
+    }
+    Ok(dentry)
+}
+
+/// A hold on a socket file by the live socket that is bound to it.
+///
+/// At most one live socket can hold a socket file. Binding another socket to it fails
+/// with `EADDRINUSE` even if `SO_REUSEADDR` is set, until the holder is dropped along
+/// with the socket.
+pub(super) struct SocketFileHolder {
+    inode: KeyableWeak<dyn Inode>,
+}
+
+impl SocketFileHolder {
+    pub(super) fn new(dentry: &Arc<Dentry>) -> Result<Arc<Self>> {
+        let inode = KeyableWeak::from(Arc::downgrade(dentry.inode()));
+        let entry = HELD_SOCKET_FILES.entry(inode.clone());
+        if entry.get().is_some_and(|holder| holder.strong_count() > 0) {
+            return_errno_with_message!(Errno::EADDRINUSE, "the addr is used by a live socket");
+        }
+
+        let holder = Arc::new(Self { inode });
+        entry.insert(Arc::downgrade(&holder));
+        Ok(holder)
+    }
+}
+
+impl Drop for SocketFileHolder {
+    fn drop(&mut self) {
+        let entry = HELD_SOCKET_FILES.entry(self.inode.clone());
+        // The socket file may have been held by another socket after this holder became
+        // unreachable. In this case, the entry belongs to the new holder.
+        if entry
+            .get()
+            .is_some_and(|holder| core::ptr::eq(holder.as_ptr(), self))
+        {
+            entry.remove();
+        }
+    }
+}
+
+static HELD_SOCKET_FILES: ShardedMap<KeyableWeak<dyn Inode>, Weak<SocketFileHolder>> =
+    ShardedMap::new();
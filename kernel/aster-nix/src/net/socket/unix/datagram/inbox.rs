@@ -0,0 +1,245 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use keyable_arc::KeyableWeak;
+
+use crate::{
+    events::{IoEvents, Observer},
+    fs::{path::Dentry, utils::Inode},
+    net::socket::{unix::addr::UnixSocketAddrBound, UnixRights},
+    prelude::*,
+    process::signal::{Pollable, Pollee, Poller},
+};
+
+/// The maximum number of datagrams that can be queued in an inbox.
+const MAX_NR_DATAGRAMS: usize = 512;
+
+/// The default size of the send and receive buffers, which is the default of Linux.
+pub(super) const DEFAULT_BUF_SIZE: usize = 212992;
+/// The maximum size of the send and receive buffers that can be set.
+pub(super) const MAX_BUF_SIZE: usize = DEFAULT_BUF_SIZE * 2;
+
+/// A datagram sent to a UNIX datagram socket.
+pub(super) struct Datagram {
+    pub(super) src: Option<UnixSocketAddrBound>,
+    pub(super) payload: Vec<u8>,
+    /// The files sent along with the datagram.
+    pub(super) rights: Option<UnixRights>,
+}
+
+/// The datagrams that are sent to a UNIX datagram socket and are not yet received.
+///
+/// The pollee reports `IoEvents::IN` if there are datagrams to receive,
+/// and `IoEvents::OUT` if there is room for more datagrams.
+///
+/// There is room as long as the queued datagrams are fewer than [`MAX_NR_DATAGRAMS`] and their
+/// payloads are smaller than the receive buffer in total. So the last datagram may exceed the
+/// receive buffer, whose size is limited by the send buffer of the sending socket.
+pub(super) struct Inbox {
+    state: Mutex<InboxState>,
+    /// The `SO_RCVBUF` option of the receiving socket.
+    recv_buf: AtomicUsize,
+    pollee: Pollee,
+}
+
+struct InboxState {
+    datagrams: VecDeque<Datagram>,
+    /// The total length of the payloads of `datagrams`.
+    queued_bytes: usize,
+    is_closed: bool,
+}
+
+impl InboxState {
+    fn has_room(&self, recv_buf: usize) -> bool {
+        self.datagrams.len() < MAX_NR_DATAGRAMS && self.queued_bytes < recv_buf
+    }
+}
+
+impl Inbox {
+    pub(super) fn new() -> Self {
+        Self {
+            state: Mutex::new(InboxState {
+                datagrams: VecDeque::new(),
+                queued_bytes: 0,
+                is_closed: false,
+            }),
+            recv_buf: AtomicUsize::new(DEFAULT_BUF_SIZE),
+            pollee: Pollee::new(IoEvents::OUT),
+        }
+    }
+
+    pub(super) fn recv_buf(&self) -> usize {
+        self.recv_buf.load(Ordering::Relaxed)
+    }
+
+    pub(super) fn set_recv_buf(&self, recv_buf: usize) {
+        let state = self.state.lock();
+        self.recv_buf.store(recv_buf, Ordering::Relaxed);
+        if state.is_closed || state.has_room(recv_buf) {
+            self.pollee.add_events(IoEvents::OUT);
+        } else {
+            self.pollee.del_events(IoEvents::OUT);
+        }
+    }
+
+    pub(super) fn push(
+        &self,
+        src: Option<UnixSocketAddrBound>,
+        buf: &[u8],
+        rights: Option<&UnixRights>,
+    ) -> Result<usize> {
+        let mut state = self.state.lock();
+        if state.is_closed {
+            return_errno_with_message!(Errno::ECONNREFUSED, "the receiving socket is closed");
+        }
+        let recv_buf = self.recv_buf();
+        if !state.has_room(recv_buf) {
+            return_errno_with_message!(Errno::EAGAIN, "the inbox is full");
+        }
+
+        state.datagrams.push_back(Datagram {
+            src,
+            payload: buf.to_vec(),
+            rights: rights.cloned(),
+        });
+        state.queued_bytes += buf.len();
+        self.pollee.add_events(IoEvents::IN);
+        if !state.has_room(recv_buf) {
+            self.pollee.del_events(IoEvents::OUT);
+        }
+        Ok(buf.len())
+    }
+
+    /// Receives the first datagram with `f`.
+    ///
+    /// The datagram is removed from the inbox unless `is_peek` is true.
+    pub(super) fn recv<R>(&self, is_peek: bool, f: impl FnOnce(&Datagram) -> R) -> Option<R> {
+        let mut state = self.state.lock();
+        let res = f(state.datagrams.front()?);
+        if is_peek {
+            return Some(res);
+        }
+
+        let datagram = state.datagrams.pop_front().unwrap();
+        state.queued_bytes -= datagram.payload.len();
+        if state.datagrams.is_empty() {
+            self.pollee.del_events(IoEvents::IN);
+        }
+        if state.has_room(self.recv_buf()) {
+            self.pollee.add_events(IoEvents::OUT);
+        }
+        Some(res)
+    }
+
+    /// Rejects further datagrams and wakes up the sockets waiting for room in the inbox,
+    /// since the receiving socket is closed.
+    ///
+    /// The queued datagrams are dropped, which closes the files sent with them.
+    fn close(&self) {
+        let mut state = self.state.lock();
+        state.is_closed = true;
+        state.datagrams.clear();
+        state.queued_bytes = 0;
+        self.pollee.add_events(IoEvents::OUT);
+    }
+
+    pub(super) fn register_observer(
+        &self,
+        observer: Weak<dyn Observer<IoEvents>>,
+        mask: IoEvents,
+    ) -> Result<()> {
+        self.pollee.register_observer(observer, mask);
+        Ok(())
+    }
+
+    pub(super) fn unregister_observer(
+        &self,
+        observer: &Weak<dyn Observer<IoEvents>>,
+    ) -> Option<Weak<dyn Observer<IoEvents>>> {
+        self.pollee.unregister_observer(observer)
+    }
+}
+
+impl Pollable for Inbox {
+    fn poll(&self, mask: IoEvents, poller: Option<&mut Poller>) -> IoEvents {
+        // Lock to avoid any events may change pollee state when we poll
+        let _lock = self.state.lock();
+        self.pollee.poll(mask, poller)
+    }
+}
+
+static INBOX_TABLE: InboxTable = InboxTable::new();
+
+/// The inboxes of the bound UNIX datagram sockets.
+struct InboxTable {
+    inboxes: RwLock<BTreeMap<KeyableWeak<dyn Inode>, Arc<Inbox>>>,
+}
+
+impl InboxTable {
+    const fn new() -> Self {
+        Self {
+            inboxes: RwLock::new(BTreeMap::new()),
+        }
+    }
+
+    fn add_inbox(&self, addr: &UnixSocketAddrBound, inbox: Arc<Inbox>) -> Result<()> {
+        let UnixSocketAddrBound::Path(_, dentry) = addr else {
+            return_errno_with_message!(Errno::EOPNOTSUPP, "abstract addresses are not supported");
+        };
+        let inode = create_keyable_inode(dentry);
+
+        let mut inboxes = self.inboxes.write();
+        if inboxes.contains_key(&inode) {
+            return_errno_with_message!(Errno::EADDRINUSE, "the addr is already used");
+        }
+        inboxes.insert(inode, inbox);
+        Ok(())
+    }
+
+    fn get_inbox(&self, addr: &UnixSocketAddrBound) -> Result<Arc<Inbox>> {
+        let inbox = match addr {
+            UnixSocketAddrBound::Path(_, dentry) => {
+                let inode = create_keyable_inode(dentry);
+                self.inboxes.read().get(&inode).cloned()
+            }
+            // No datagram socket can be bound to an abstract address.
+            UnixSocketAddrBound::Abstract(_) => None,
+        };
+
+        inbox.ok_or_else(|| {
+            Error::with_message(
+                Errno::ECONNREFUSED,
+                "no datagram socket is bound to the remote address",
+            )
+        })
+    }
+
+    fn remove_inbox(&self, addr: &UnixSocketAddrBound) {
+        let UnixSocketAddrBound::Path(_, dentry) = addr else {
+            return;
+        };
+
+        let inode = create_keyable_inode(dentry);
+        if let Some(inbox) = self.inboxes.write().remove(&inode) {
+            inbox.close();
+        }
+    }
+}
+
+fn create_keyable_inode(dentry: &Arc<Dentry>) -> KeyableWeak<dyn Inode> {
+    let weak_inode = Arc::downgrade(dentry.inode());
+    KeyableWeak::from(weak_inode)
+}
+
+pub(super) fn register_inbox(addr: &UnixSocketAddrBound, inbox: Arc<Inbox>) -> Result<()> {
+    INBOX_TABLE.add_inbox(addr, inbox)
+}
+
+pub(super) fn unregister_inbox(addr: &UnixSocketAddrBound) {
+    INBOX_TABLE.remove_inbox(addr);
+}
+
+pub(super) fn lookup_inbox(addr: &UnixSocketAddrBound) -> Result<Arc<Inbox>> {
+    INBOX_TABLE.get_inbox(addr)
+}
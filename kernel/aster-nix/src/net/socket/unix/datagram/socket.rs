@@ -0,0 +1,388 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use core::{
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+    time::Duration,
+};
+
+use spin::Once;
+
+use super::inbox::{
+    lookup_inbox, register_inbox, unregister_inbox, Inbox, DEFAULT_BUF_SIZE, MAX_BUF_SIZE,
+};
+use crate::{
+    events::{IoEvents, Observer},
+    fs::{file_handle::FileLike, utils::StatusFlags},
+    match_sock_option_mut, match_sock_option_ref,
+    net::socket::{
+        options::{RecvBuf, RecvTimeout, SendBuf, SendTimeout, SocketOption},
+        unix::{
+            addr::{
+                create_socket_file, remove_socket_file, SocketFileHolder, UnixSocketAddrBound,
+            },
+            check_rights, timeout_of, UnixSocketAddr,
+        },
+        util::{
+            copy_message_from_user, copy_message_to_user, create_message_buffer,
+            options::{MIN_RECVBUF, MIN_SENDBUF},
+            send_recv_flags::SendRecvFlags,
+            socket_addr::SocketAddr,
+            ControlMessage,
+            MessageHeader,
+            UnixRights,
+        },
+        Socket,
+    },
+    prelude::*,
+    process::signal::{Pollable, Poller},
+    util::IoVec,
+};
+
+/// A UNIX socket of the `SOCK_DGRAM` type.
+///
+/// Connecting the socket does not establish a connection. Instead, it sets the default
+/// destination of the datagrams that are sent without an explicit address. Whether a
+/// socket is bound to the destination is only checked when a datagram is sent, so
+/// sending to an address that no socket is bound to fails with `ECONNREFUSED`.
+pub struct UnixDatagramSocket {
+    addr: RwLock<Option<UnixSocketAddrBound>>,
+    /// The hold on the socket file once the socket is bound.
+    file_holder: Once<Arc<SocketFileHolder>>,
+    peer_addr: RwLock<Option<UnixSocketAddrBound>>,
+    inbox: Arc<Inbox>,
+    is_nonblocking: AtomicBool,
+    /// The `SO_SNDBUF` option, which limits the size of the datagrams to send.
+    send_buf: AtomicUsize,
+    /// The `SO_SNDTIMEO` option. Zero means no timeout.
+    send_timeout: Mutex<Duration>,
+    /// The `SO_RCVTIMEO` option. Zero means no timeout.
+    recv_timeout: Mutex<Duration>,
+}
+
+impl UnixDatagramSocket {
+    pub fn new(is_nonblocking: bool) -> Arc<Self> {
+        Arc::new(Self {
+            addr: RwLock::new(None),
+            file_holder: Once::new(),
+            peer_addr: RwLock::new(None),
+            inbox: Arc::new(Inbox::new()),
+            is_nonblocking: AtomicBool::new(is_nonblocking),
+            send_buf: AtomicUsize::new(DEFAULT_BUF_SIZE),
+            send_timeout: Mutex::new(Duration::ZERO),
+            recv_timeout: Mutex::new(Duration::ZERO),
+        })
+    }
+
+    fn send(
+        &self,
+        buf: &[u8],
+        remote_addr: Option<UnixSocketAddrBound>,
+        rights: Option<&UnixRights>,
+    ) -> Result<usize> {
+        if buf.len() > self.send_buf.load(Ordering::Relaxed) {
+            return_errno_with_message!(
+                Errno::EMSGSIZE,
+                "the datagram is larger than the send buffer"
+            );
+        }
+
+        let remote_addr = match remote_addr {
+            Some(remote_addr) => remote_addr,
+            None => self.peer_addr.read().clone().ok_or_else(|| {
+                Error::with_message(Errno::ENOTCONN, "the destination address is not specified")
+            })?,
+        };
+        let inbox = lookup_inbox(&remote_addr)?;
+        let src = self.addr.read().clone();
+
+        if self.is_nonblocking() {
+            inbox.push(src, buf, rights)
+        } else {
+            // If the inbox of the remote socket is full, wait until the remote socket
+            // receives some datagrams, or until the timeout specified by `SO_SNDTIMEO` expires.
+            let timeout = timeout_of(*self.send_timeout.lock());
+            inbox.wait_events_with_timeout(IoEvents::OUT, timeout.as_ref(), || {
+                inbox.push(src.clone(), buf, rights)
+            })
+        }
+    }
+
+    /// Receives a datagram into `buf`.
+    ///
+    /// On success, returns the number of the copied bytes, the length of the whole datagram,
+    /// the source address, and the files sent with the datagram.
+    fn recv(
+        &self,
+        buf: &mut [u8],
+        flags: SendRecvFlags,
+    ) -> Result<(usize, usize, SocketAddr, Option<UnixRights>)> {
+        if self.is_nonblocking() {
+            self.try_recv(buf, flags)
+        } else {
+            let timeout = timeout_of(*self.recv_timeout.lock());
+            self.wait_events_with_timeout(IoEvents::IN, timeout.as_ref(), || {
+                self.try_recv(buf, flags)
+            })
+        }
+    }
+
+    fn try_recv(
+        &self,
+        buf: &mut [u8],
+        flags: SendRecvFlags,
+    ) -> Result<(usize, usize, SocketAddr, Option<UnixRights>)> {
+        let is_peek = flags.contains(SendRecvFlags::MSG_PEEK);
+        let received = self.inbox.recv(is_peek, |datagram| {
+            // Like Linux, the part of the datagram that does not fit in the buffer is discarded.
+            let len = buf.len().min(datagram.payload.len());
+            buf[..len].copy_from_slice(&datagram.payload[..len]);
+            // With `MSG_PEEK`, the files are received again by the next receive, just like
+            // `SCM_RIGHTS` messages that are peeked in Linux.
+            (
+                len,
+                datagram.payload.len(),
+                SocketAddr::from(datagram.src.clone()),
+                datagram.rights.clone(),
+            )
+        });
+
+        received.ok_or_else(|| Error::with_message(Errno::EAGAIN, "no datagram is available"))
+    }
+
+    fn is_nonblocking(&self) -> bool {
+        self.is_nonblocking.load(Ordering::Relaxed)
+    }
+
+    fn set_nonblocking(&self, nonblocking: bool) {
+        self.is_nonblocking.store(nonblocking, Ordering::Relaxed);
+    }
+}
+
+impl Pollable for UnixDatagramSocket {
+    fn poll(&self, mask: IoEvents, poller: Option<&mut Poller>) -> IoEvents {
+        // `IoEvents::OUT` of the inbox is for the sending sockets. Sending is always
+        // possible from the perspective of this socket.
+        let events = self.inbox.poll(mask, poller) - IoEvents::OUT;
+        (events | IoEvents::OUT) & mask
+    }
+}
+
+impl FileLike for UnixDatagramSocket {
+    fn as_socket(self: Arc<Self>) -> Option<Arc<dyn Socket>> {
+        Some(self)
+    }
+
+    fn read(&self, buf: &mut [u8]) -> Result<usize> {
+        self.recv(buf, SendRecvFlags::empty())
+            .map(|(copied_len, _, _, _)| copied_len)
+    }
+
+    fn write(&self, buf: &[u8]) -> Result<usize> {
+        self.send(buf, None, None)
+    }
+
+    fn status_flags(&self) -> StatusFlags {
+        if self.is_nonblocking() {
+            StatusFlags::O_NONBLOCK
+        } else {
+            StatusFlags::empty()
+        }
+    }
+
+    fn set_status_flags(&self, new_flags: StatusFlags) -> Result<()> {
+        self.set_nonblocking(new_flags.contains(StatusFlags::O_NONBLOCK));
+        Ok(())
+    }
+
+    fn register_observer(
+        &self,
+        observer: Weak<dyn Observer<IoEvents>>,
+        mask: IoEvents,
+    ) -> Result<()> {
+        self.inbox.register_observer(observer, mask)
+    }
+
+    fn unregister_observer(
+        &self,
+        observer: &Weak<dyn Observer<IoEvents>>,
+    ) -> Option<Weak<dyn Observer<IoEvents>>> {
+        self.inbox.unregister_observer(observer)
+    }
+}
+
+impl Socket for UnixDatagramSocket {
+    fn bind(&self, socket_addr: SocketAddr) -> Result<()> {
+        let path = match UnixSocketAddr::try_from(socket_addr)? {
+            UnixSocketAddr::Path(path) => path,
+            UnixSocketAddr::Unnamed => {
+                return_errno_with_message!(Errno::EOPNOTSUPP, "autobind is not supported")
+            }
+            UnixSocketAddr::Abstract(_) => {
+                return_errno_with_message!(
+                    Errno::EOPNOTSUPP,
+                    "abstract addresses are not supported"
+                )
+            }
+        };
+
+        let mut addr = self.addr.write();
+        if addr.is_some() {
+            return_errno_with_message!(Errno::EINVAL, "the socket is already bound");
+        }
+
+        let dentry = create_socket_file(&path)?;
+        // The socket file is just created, so no other socket can hold it.
+        let file_holder = SocketFileHolder::new(&dentry)?;
+        let bound_addr = UnixSocketAddrBound::Path(path.clone(), dentry);
+        if let Err(err) = register_inbox(&bound_addr, self.inbox.clone()) {
+            // Do not leave the socket file behind if the socket is not bound to it.
+            let _ = remove_socket_file(&path);
+            return Err(err);
+        }
+        self.file_holder.call_once(|| file_holder);
+        *addr = Some(bound_addr);
+
+        Ok(())
+    }
+
+    fn connect(&self, socket_addr: SocketAddr) -> Result<()> {
+        // Connecting again replaces the default destination.
+        let remote_addr = UnixSocketAddr::try_from(socket_addr)?.resolve_remote()?;
+        *self.peer_addr.write() = Some(remote_addr);
+        Ok(())
+    }
+
+    fn addr(&self) -> Result<SocketAddr> {
+        Ok(self.addr.read().clone().into())
+    }
+
+    fn peer_addr(&self) -> Result<SocketAddr> {
+        let peer_addr =
+            self.peer_addr.read().clone().ok_or_else(|| {
+                Error::with_message(Errno::ENOTCONN, "the socket is not connected")
+            })?;
+        Ok(peer_addr.into())
+    }
+
+    fn get_option(&self, option: &mut dyn SocketOption) -> Result<()> {
+        match_sock_option_mut!(option, {
+            socket_send_buf: SendBuf => {
+                let send_buf = self.send_buf.load(Ordering::Relaxed);
+                socket_send_buf.set(send_buf as u32);
+            },
+            socket_recv_buf: RecvBuf => {
+                let recv_buf = self.inbox.recv_buf();
+                socket_recv_buf.set(recv_buf as u32);
+            },
+            socket_send_timeout: SendTimeout => {
+                let send_timeout = *self.send_timeout.lock();
+                socket_send_timeout.set(send_timeout);
+            },
+            socket_recv_timeout: RecvTimeout => {
+                let recv_timeout = *self.recv_timeout.lock();
+                socket_recv_timeout.set(recv_timeout);
+            },
+            _ => return_errno_with_message!(Errno::ENOPROTOOPT, "the socket option to get is unknown")
+        });
+
+        Ok(())
+    }
+
+    fn set_option(&self, option: &dyn SocketOption) -> Result<()> {
+        match_sock_option_ref!(option, {
+            socket_send_buf: SendBuf => {
+                let send_buf = *socket_send_buf.get().unwrap() as usize;
+                let send_buf = send_buf.clamp(MIN_SENDBUF as usize, MAX_BUF_SIZE);
+                self.send_buf.store(send_buf, Ordering::Relaxed);
+            },
+            socket_recv_buf: RecvBuf => {
+                let recv_buf = *socket_recv_buf.get().unwrap() as usize;
+                let recv_buf = recv_buf.clamp(MIN_RECVBUF as usize, MAX_BUF_SIZE);
+                self.inbox.set_recv_buf(recv_buf);
+            },
+            socket_send_timeout: SendTimeout => {
+                let send_timeout = socket_send_timeout.get().unwrap();
+                *self.send_timeout.lock() = *send_timeout;
+            },
+            socket_recv_timeout: RecvTimeout => {
+                let recv_timeout = socket_recv_timeout.get().unwrap();
+                *self.recv_timeout.lock() = *recv_timeout;
+            },
+            _ => return_errno_with_message!(Errno::ENOPROTOOPT, "the socket option to be set is unknown")
+        });
+
+        Ok(())
+    }
+
+    fn sendmsg(
+        &self,
+        io_vecs: &[IoVec],
+        message_header: MessageHeader,
+        flags: SendRecvFlags,
+    ) -> Result<usize> {
+        // TODO: Deal with flags
+        debug_assert!(flags.is_all_supported());
+
+        let MessageHeader {
+            addr,
+            control_messages,
+        } = message_header;
+
+        let remote_addr = match addr {
+            Some(addr) => Some(UnixSocketAddr::try_from(addr)?.resolve_remote()?),
+            None => None,
+        };
+
+        let mut rights = None;
+        for control_message in control_messages {
+            match control_message {
+                // Like Linux, only the files in the last `SCM_RIGHTS` message are sent.
+                ControlMessage::Rights(new_rights) => {
+                    check_rights(&new_rights)?;
+                    rights = Some(new_rights);
+                }
+                // TODO: Support sending credentials
+                ControlMessage::Credentials(_) => warn!("sending credentials is not supported"),
+            }
+        }
+
+        let buf = copy_message_from_user(io_vecs);
+
+        self.send(&buf, remote_addr, rights.as_ref())
+    }
+
+    fn recvmsg(&self, io_vecs: &[IoVec], flags: SendRecvFlags) -> Result<(usize, MessageHeader)> {
+        // TODO: Deal with other flags
+        debug_assert!(
+            (flags - SendRecvFlags::MSG_PEEK - SendRecvFlags::MSG_TRUNC).is_all_supported()
+        );
+
+        let mut buf = create_message_buffer(io_vecs);
+
+        let (received_bytes, datagram_len, peer_addr, rights) = self.recv(&mut buf, flags)?;
+
+        let copied_bytes = {
+            let message = &buf[..received_bytes];
+            copy_message_to_user(io_vecs, message)
+        };
+
+        let control_messages = rights.map(ControlMessage::Rights).into_iter().collect();
+        let message_header = MessageHeader::new(Some(peer_addr), control_messages);
+
+        // With `MSG_TRUNC`, the real length of the datagram is returned even if it is
+        // longer than the buffer.
+        if flags.contains(SendRecvFlags::MSG_TRUNC) {
+            Ok((datagram_len, message_header))
+        } else {
+            Ok((copied_bytes, message_header))
+        }
+    }
+}
+
+impl Drop for UnixDatagramSocket {
+    fn drop(&mut self) {
+        if let Some(addr) = self.addr.read().as_ref() {
+            unregister_inbox(addr);
+        }
+    }
+}
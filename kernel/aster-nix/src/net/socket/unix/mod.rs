@@ -1,7 +1,38 @@
 // SPDX-License-Identifier: MPL-2.0
 
 mod addr;
+mod datagram;
 mod stream;
 
 pub use addr::UnixSocketAddr;
-pub use stream::UnixStreamSocket;
+pub use datagram::UnixDatagramSocket;
+pub use stream::{stream_sockets, UnixStreamInfo, UnixStreamSocket, UnixStreamState};
+
+use core::time::Duration;
+
+use super::UnixRights;
+use crate::{fs::file_handle::FileLike, prelude::*};
+
+/// Checks whether the files can be sent with `SCM_RIGHTS`.
+///
+/// The files of UNIX sockets cannot be sent. Otherwise, a socket may be kept alive only by
+/// the files that are queued in itself, which forms a reference cycle that is never freed.
+//
+// FIXME: Linux allows sending UNIX sockets and frees such cycles with a garbage collector.
+fn check_rights(rights: &UnixRights) -> Result<()> {
+    let is_unix_socket = |file: &Arc<dyn FileLike>| {
+        file.downcast_ref::<UnixStreamSocket>().is_some()
+            || file.downcast_ref::<UnixDatagramSocket>().is_some()
+    };
+    if rights.files().iter().any(is_unix_socket) {
+        return_errno_with_message!(Errno::EOPNOTSUPP, "UNIX sockets cannot be sent");
+    }
+    Ok(())
+}
+
+/// Converts the value of `SO_SNDTIMEO` or `SO_RCVTIMEO` to a timeout.
+///
+/// Like Linux, a zero value means that there is no timeout.
+fn timeout_of(duration: Duration) -> Option<Duration> {
+    (!duration.is_zero()).then_some(duration)
+}
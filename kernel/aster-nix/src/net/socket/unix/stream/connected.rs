@@ -1,20 +1,40 @@
 // SPDX-License-Identifier: MPL-2.0
 
+use core::{
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+
 use super::endpoint::Endpoint;
 use crate::{
     events::{IoEvents, Observer},
-    net::socket::{unix::addr::UnixSocketAddrBound, SockShutdownCmd},
+    net::socket::{
+        unix::addr::{SocketFileHolder, UnixSocketAddrBound},
+        LingerOption, SockShutdownCmd, UnixCredentials, UnixRights,
+    },
     prelude::*,
     process::signal::Poller,
 };
 
 pub(super) struct Connected {
     local_endpoint: Endpoint,
+    /// The hold on the socket file if the socket is bound before it is connected.
+    _file_holder: Option<Arc<SocketFileHolder>>,
+    /// The ID in the `CONNECTED_TABLE`.
+    id: u64,
 }
 
 impl Connected {
-    pub(super) fn new(local_endpoint: Endpoint) -> Self {
-        Connected { local_endpoint }
+    pub(super) fn new(
+        local_endpoint: Endpoint,
+        file_holder: Option<Arc<SocketFileHolder>>,
+    ) -> Self {
+        let id = CONNECTED_TABLE.add(local_endpoint.addr().cloned());
+        Connected {
+            local_endpoint,
+            _file_holder: file_holder,
+            id,
+        }
     }
 
     pub(super) fn addr(&self) -> Option<&UnixSocketAddrBound> {
@@ -25,18 +45,80 @@ impl Connected {
         self.local_endpoint.peer_addr()
     }
 
-    pub(super) fn try_write(&self, buf: &[u8]) -> Result<usize> {
-        self.local_endpoint.try_write(buf)
+    pub(super) fn try_write(
+        &self,
+        buf: &[u8],
+        credentials: Option<&UnixCredentials>,
+        rights: Option<&UnixRights>,
+    ) -> Result<usize> {
+        self.local_endpoint.try_write(buf, credentials, rights)
     }
 
-    pub(super) fn try_read(&self, buf: &mut [u8]) -> Result<usize> {
+    pub(super) fn try_read(&self, buf: &mut [u8]) -> Result<(usize, Option<UnixRights>)> {
         self.local_endpoint.try_read(buf)
     }
 
+    pub(super) fn try_write_oob(
+        &self,
+        buf: &[u8],
+        credentials: Option<&UnixCredentials>,
+        rights: Option<&UnixRights>,
+    ) -> Result<usize> {
+        self.local_endpoint.try_write_oob(buf, credentials, rights)
+    }
+
+    pub(super) fn try_read_oob(&self, buf: &mut [u8]) -> Result<usize> {
+        self.local_endpoint.try_read_oob(buf)
+    }
+
+    pub(super) fn send_low_watermark(&self) -> usize {
+        self.local_endpoint.send_low_watermark()
+    }
+
+    pub(super) fn set_send_low_watermark(&self, low_watermark: usize) {
+        self.local_endpoint.set_send_low_watermark(low_watermark);
+    }
+
+    pub(super) fn take_credentials(&self) -> Option<UnixCredentials> {
+        self.local_endpoint.take_credentials()
+    }
+
     pub(super) fn shutdown(&self, cmd: SockShutdownCmd) -> Result<()> {
         self.local_endpoint.shutdown(cmd)
     }
 
+    pub(super) fn take_error(&self) -> Option<Error> {
+        self.local_endpoint.take_error()
+    }
+
+    /// Prepares the connection to be closed along with the socket.
+    ///
+    /// The close is abortive if the `SO_LINGER` option is on with a zero timeout, in which case
+    /// the data that has not been read by the peer is discarded, or if there is data that has
+    /// not been read by this socket. The peer sees `ECONNRESET` after an abortive close, and
+    /// sees the EOF after a graceful one.
+    ///
+    /// With a nonzero timeout of the `SO_LINGER` option, this method waits until the peer reads
+    /// all the data or the timeout expires.
+    pub(super) fn close(&self, linger: &LingerOption) {
+        if linger.is_on() && linger.timeout() == Duration::ZERO {
+            self.local_endpoint.discard_unread();
+            self.local_endpoint.reset();
+            return;
+        }
+
+        if self.local_endpoint.has_unread_data() {
+            self.local_endpoint.reset();
+        }
+
+        if !linger.is_on() {
+            return;
+        }
+
+        // The socket is being closed, so there is no way to report a timeout or an interruption.
+        let _ = self.local_endpoint.wait_for_drain(&linger.timeout());
+    }
+
     pub(super) fn poll(&self, mask: IoEvents, poller: Option<&mut Poller>) -> IoEvents {
         self.local_endpoint.poll(mask, poller)
     }
@@ -56,3 +138,48 @@ impl Connected {
         self.local_endpoint.unregister_observer(observer)
     }
 }
+
+impl Drop for Connected {
+    fn drop(&mut self) {
+        CONNECTED_TABLE.remove(self.id);
+    }
+}
+
+static CONNECTED_TABLE: ConnectedTable = ConnectedTable::new();
+
+/// The registry of the connected sockets, which is used to enumerate them.
+struct ConnectedTable {
+    next_id: AtomicU64,
+    connected_sockets: RwLock<BTreeMap<u64, Option<UnixSocketAddrBound>>>,
+}
+
+impl ConnectedTable {
+    const fn new() -> Self {
+        Self {
+            next_id: AtomicU64::new(0),
+            connected_sockets: RwLock::new(BTreeMap::new()),
+        }
+    }
+
+    fn add(&self, addr: Option<UnixSocketAddrBound>) -> u64 {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        self.connected_sockets.write().insert(id, addr);
+        id
+    }
+
+    fn remove(&self, id: u64) {
+        self.connected_sockets.write().remove(&id);
+    }
+
+    /// Returns the local addresses of all the connected sockets.
+    ///
+    /// The addresses are collected with the table locked, so a socket that is being closed
+    /// either appears with its address or does not appear at all.
+    fn connected_addrs(&self) -> Vec<Option<UnixSocketAddrBound>> {
+        self.connected_sockets.read().values().cloned().collect()
+    }
+}
+
+pub(super) fn connected_addrs() -> Vec<Option<UnixSocketAddrBound>> {
+    CONNECTED_TABLE.connected_addrs()
+}
@@ -0,0 +1,74 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use super::{connected::Connected, endpoint::Endpoint, init::Init, listener::PendingConnection};
+use crate::{
+    events::{IoEvents, Observer},
+    net::socket::unix::addr::UnixSocketAddrBound,
+    prelude::*,
+    process::signal::Poller,
+};
+
+/// A non-blocking socket whose connection waits for room in the backlog of the listening
+/// socket.
+pub(super) struct Connecting {
+    init: Init,
+    local_endpoint: Endpoint,
+    connection: Arc<PendingConnection>,
+}
+
+impl Connecting {
+    pub(super) fn new(
+        init: Init,
+        local_endpoint: Endpoint,
+        connection: Arc<PendingConnection>,
+    ) -> Self {
+        Self {
+            init,
+            local_endpoint,
+            connection,
+        }
+    }
+
+    pub(super) fn addr(&self) -> Option<&UnixSocketAddrBound> {
+        self.init.addr()
+    }
+
+    /// Returns the result of the connection, or `None` if it is still pending.
+    pub(super) fn result(&self) -> Option<Result<()>> {
+        self.connection.result()
+    }
+
+    /// Turns the socket into the connected state after the connection succeeds.
+    pub(super) fn into_connected(self) -> Connected {
+        Connected::new(self.local_endpoint, self.init.file_holder().cloned())
+    }
+
+    /// Turns the socket back into the initial state after the connection fails.
+    pub(super) fn into_init(self) -> Init {
+        self.init
+    }
+
+    /// Cancels the connection, since the socket is closed.
+    pub(super) fn cancel(&self) {
+        self.connection.cancel();
+    }
+
+    pub(super) fn poll(&self, mask: IoEvents, poller: Option<&mut Poller>) -> IoEvents {
+        self.connection.poll(mask, poller)
+    }
+
+    pub(super) fn register_observer(
+        &self,
+        observer: Weak<dyn Observer<IoEvents>>,
+        mask: IoEvents,
+    ) -> Result<()> {
+        self.connection.register_observer(observer, mask)
+    }
+
+    pub(super) fn unregister_observer(
+        &self,
+        observer: &Weak<dyn Observer<IoEvents>>,
+    ) -> Option<Weak<dyn Observer<IoEvents>>> {
+        self.connection.unregister_observer(observer)
+    }
+}
@@ -1,11 +1,17 @@
 // SPDX-License-Identifier: MPL-2.0
 
+use core::{
+    ops::Range,
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
+};
+
 use crate::{
     events::{IoEvents, Observer},
     fs::utils::{Channel, Consumer, Producer},
-    net::socket::{unix::addr::UnixSocketAddrBound, SockShutdownCmd},
+    net::socket::{unix::addr::UnixSocketAddrBound, SockShutdownCmd, UnixCredentials, UnixRights},
     prelude::*,
-    process::signal::Poller,
+    process::signal::{Pauser, Pollee, Poller},
 };
 
 pub(super) struct Endpoint {
@@ -13,6 +19,25 @@ pub(super) struct Endpoint {
     peer_addr: Option<UnixSocketAddrBound>,
     reader: Consumer<u8>,
     writer: Producer<u8>,
+    /// The credentials sent by the peer along with the data in `reader`.
+    //
+    // FIXME: Linux does not merge data sent with different credentials. Here only the latest
+    // credentials are kept, which is enough for the common one-message handshakes.
+    read_credentials: Arc<Mutex<Option<UnixCredentials>>>,
+    /// The credentials sent by this endpoint along with the data in `writer`.
+    write_credentials: Arc<Mutex<Option<UnixCredentials>>>,
+    /// The files sent by the peer along with the data in `reader`.
+    read_rights: Arc<Mutex<RightsQueue>>,
+    /// The files sent by this endpoint along with the data in `writer`.
+    write_rights: Arc<Mutex<RightsQueue>>,
+    /// The out-of-band byte sent by the peer.
+    read_oob: Arc<OobByte>,
+    /// The out-of-band byte sent by this endpoint.
+    write_oob: Arc<OobByte>,
+    /// Whether the connection is reset by the peer and the error has not been reported.
+    is_reset: Arc<AtomicBool>,
+    /// Whether the connection is reset by this endpoint, i.e., the `is_reset` of the peer.
+    is_peer_reset: Arc<AtomicBool>,
 }
 
 impl Endpoint {
@@ -22,18 +47,42 @@ impl Endpoint {
     ) -> (Endpoint, Endpoint) {
         let (writer_this, reader_peer) = Channel::new(DAFAULT_BUF_SIZE).split();
         let (writer_peer, reader_this) = Channel::new(DAFAULT_BUF_SIZE).split();
+        let credentials_this = Arc::new(Mutex::new(None));
+        let credentials_peer = Arc::new(Mutex::new(None));
+        let rights_this = Arc::new(Mutex::new(RightsQueue::new()));
+        let rights_peer = Arc::new(Mutex::new(RightsQueue::new()));
+        let oob_this = Arc::new(OobByte::new());
+        let oob_peer = Arc::new(OobByte::new());
+        let reset_this = Arc::new(AtomicBool::new(false));
+        let reset_peer = Arc::new(AtomicBool::new(false));
 
         let this = Endpoint {
             addr: addr.clone(),
             peer_addr: peer_addr.clone(),
             reader: reader_this,
             writer: writer_this,
+            read_credentials: credentials_this.clone(),
+            write_credentials: credentials_peer.clone(),
+            read_rights: rights_this.clone(),
+            write_rights: rights_peer.clone(),
+            read_oob: oob_this.clone(),
+            write_oob: oob_peer.clone(),
+            is_reset: reset_this.clone(),
+            is_peer_reset: reset_peer.clone(),
         };
         let peer = Endpoint {
             addr: peer_addr,
             peer_addr: addr,
             reader: reader_peer,
             writer: writer_peer,
+            read_credentials: credentials_peer,
+            write_credentials: credentials_this,
+            read_rights: rights_peer,
+            write_rights: rights_this,
+            read_oob: oob_peer,
+            write_oob: oob_this,
+            is_reset: reset_peer,
+            is_peer_reset: reset_this,
         };
 
         (this, peer)
@@ -47,46 +96,214 @@ impl Endpoint {
         self.peer_addr.as_ref()
     }
 
-    pub(super) fn try_read(&self, buf: &mut [u8]) -> Result<usize> {
-        self.reader.try_read(buf)
+    /// Reads the data sent by the peer into `buf`, along with the files sent with the data.
+    ///
+    /// Like Linux, a read stops at the end of the data that is sent with files, so the files
+    /// are received with the first read that reaches the data, and never with other data.
+    pub(super) fn try_read(&self, buf: &mut [u8]) -> Result<(usize, Option<UnixRights>)> {
+        let mut read_rights = self.read_rights.lock();
+        let read_limit = read_rights.read_limit(buf.len());
+        let read_len = self.reader.try_read(&mut buf[..read_limit])?;
+        let rights = read_rights.consume(read_len);
+        drop(read_rights);
+
+        // Like Linux, the data that the peer sent before the reset can still be read, and the
+        // reset is reported in place of the EOF.
+        if read_len == 0 && !buf.is_empty() {
+            if let Some(err) = self.take_error() {
+                return Err(err);
+            }
+        }
+
+        Ok((read_len, rights))
+    }
+
+    pub(super) fn try_write(
+        &self,
+        buf: &[u8],
+        credentials: Option<&UnixCredentials>,
+        rights: Option<&UnixRights>,
+    ) -> Result<usize> {
+        if !buf.is_empty() {
+            if let Some(err) = self.take_error() {
+                return Err(err);
+            }
+        }
+
+        // Hold the lock so that the peer cannot consume the data before seeing the control
+        // messages. The lock is held even if there are no control messages, since all the
+        // data is counted to know where the files are sent.
+        let mut write_rights = self.write_rights.lock();
+        let written_bytes = self.writer.try_write(buf)?;
+        if let Some(credentials) = credentials {
+            *self.write_credentials.lock() = Some(*credentials);
+        }
+        write_rights.produce(written_bytes, rights);
+        Ok(written_bytes)
+    }
+
+    /// Writes `buf` with its last byte sent as the out-of-band byte, as `MSG_OOB` does.
+    ///
+    /// The out-of-band byte is sent only after all the other bytes are written. Otherwise,
+    /// the number of the bytes that are written as normal data is returned.
+    pub(super) fn try_write_oob(
+        &self,
+        buf: &[u8],
+        credentials: Option<&UnixCredentials>,
+        rights: Option<&UnixRights>,
+    ) -> Result<usize> {
+        let Some((&oob_byte, normal_bytes)) = buf.split_last() else {
+            return_errno_with_message!(Errno::EOPNOTSUPP, "no out-of-band byte to send");
+        };
+
+        if let Some(err) = self.take_error() {
+            return Err(err);
+        }
+        if self.writer.is_shutdown() || self.writer.is_peer_shutdown() {
+            return_errno_with_message!(Errno::EPIPE, "the channel is shut down");
+        }
+
+        if !normal_bytes.is_empty() {
+            let written_bytes = self.try_write(normal_bytes, credentials, rights)?;
+            if written_bytes < normal_bytes.len() {
+                return Ok(written_bytes);
+            }
+        }
+
+        self.write_oob.set(oob_byte);
+        Ok(buf.len())
+    }
+
+    /// Reads the out-of-band byte sent by the peer.
+    ///
+    /// Like Linux, this method never blocks and fails with `EINVAL` if there is no
+    /// out-of-band byte.
+    pub(super) fn try_read_oob(&self, buf: &mut [u8]) -> Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        let Some(oob_byte) = self.read_oob.take() else {
+            return_errno_with_message!(Errno::EINVAL, "there is no out-of-band byte");
+        };
+        buf[0] = oob_byte;
+        Ok(1)
+    }
+
+    /// Returns the low watermark of the free space in the send buffer.
+    pub(super) fn send_low_watermark(&self) -> usize {
+        self.writer.low_watermark()
+    }
+
+    /// Sets the low watermark of the free space in the send buffer, below which the endpoint
+    /// is not reported to be writable.
+    pub(super) fn set_send_low_watermark(&self, low_watermark: usize) {
+        self.writer.set_low_watermark(low_watermark);
     }
 
-    pub(super) fn try_write(&self, buf: &[u8]) -> Result<usize> {
-        self.writer.try_write(buf)
+    pub(super) fn take_credentials(&self) -> Option<UnixCredentials> {
+        self.read_credentials.lock().take()
     }
 
     pub(super) fn shutdown(&self, cmd: SockShutdownCmd) -> Result<()> {
         // FIXME: If the socket has already been shut down, should we return an error code?
 
         if cmd.shut_read() {
-            self.reader.shutdown();
+            self.reader.shutdown_and_notify();
         }
 
         if cmd.shut_write() {
-            self.writer.shutdown();
+            self.writer.shutdown_and_notify();
         }
 
         Ok(())
     }
 
-    pub(super) fn poll(&self, mask: IoEvents, mut poller: Option<&mut Poller>) -> IoEvents {
-        let mut events = IoEvents::empty();
+    /// Waits until the peer has read all the data written by this endpoint, or until the peer
+    /// stops reading.
+    ///
+    /// This method fails with `ETIME` if `timeout` expires first, or with `EINTR` if the current
+    /// thread is interrupted by a signal.
+    pub(super) fn wait_for_drain(&self, timeout: &Duration) -> Result<()> {
+        // Every read from the peer and the close of the peer add `IoEvents::OUT` to the writer.
+        let observer = Arc::new(DrainObserver(Pauser::new()));
+        let weak_observer = Arc::downgrade(&observer) as Weak<dyn Observer<IoEvents>>;
+        self.writer
+            .register_observer(weak_observer.clone(), IoEvents::OUT)?;
+
+        let cond = || {
+            if self.writer.is_drained() || self.writer.is_peer_shutdown() {
+                Some(())
+            } else {
+                None
+            }
+        };
+        let res = observer.0.pause_until_or_timeout(cond, timeout);
+
+        self.writer.unregister_observer(&weak_observer);
+        res
+    }
+
+    /// Discards the data written by this endpoint that has not been read by the peer,
+    /// along with the files sent with the data.
+    pub(super) fn discard_unread(&self) {
+        let mut write_rights = self.write_rights.lock();
+        self.writer.discard();
+        write_rights.discard();
+    }
+
+    /// Returns whether there is data sent by the peer that has not been read.
+    pub(super) fn has_unread_data(&self) -> bool {
+        !self.reader.is_empty()
+    }
+
+    /// Resets the connection, so that the peer sees `ECONNRESET` rather than the EOF.
+    ///
+    /// The channels are shut down when the endpoint is dropped, which wakes up the waiters of
+    /// the peer. So this method should be called right before the endpoint is dropped.
+    pub(super) fn reset(&self) {
+        self.is_peer_reset.store(true, Ordering::Release);
+    }
+
+    /// Takes the error caused by the reset of the connection, if any.
+    ///
+    /// Like Linux, the error is reported only once.
+    pub(super) fn take_error(&self) -> Option<Error> {
+        self.is_reset.swap(false, Ordering::AcqRel).then(|| {
+            Error::with_message(Errno::ECONNRESET, "the connection is reset by the peer")
+        })
+    }
 
-        // FIXME: should reader and writer use the same mask?
+    pub(super) fn poll(&self, mask: IoEvents, mut poller: Option<&mut Poller>) -> IoEvents {
         let reader_events = self.reader.poll(mask, poller.as_deref_mut());
+        let oob_events = self.read_oob.pollee.poll(mask, poller.as_deref_mut());
         let writer_events = self.writer.poll(mask, poller);
+        let mut events = (reader_events & IoEvents::IN)
+            | (writer_events & IoEvents::OUT)
+            | (oob_events & IoEvents::PRI);
 
-        // FIXME: Check this logic later.
-        if reader_events.contains(IoEvents::HUP) || self.reader.is_shutdown() {
-            events |= IoEvents::RDHUP | IoEvents::IN;
-            if writer_events.contains(IoEvents::ERR) || self.writer.is_shutdown() {
-                events |= IoEvents::HUP | IoEvents::OUT;
-            }
-        }
+        // A channel is shut down if either end of it is shut down or closed, i.e., if this
+        // endpoint or its peer has shut down the corresponding direction.
+        let is_read_shutdown = self.reader.is_shutdown() || self.reader.is_peer_shutdown();
+        let is_write_shutdown = self.writer.is_shutdown() || self.writer.is_peer_shutdown();
 
-        events |= (reader_events & IoEvents::IN) | (writer_events & IoEvents::OUT);
+        // Like Linux, the endpoint is readable after the read side is shut down, so that the
+        // EOF can be read, and it is writable after the write side is shut down, so that the
+        // `EPIPE` error can be seen. `HUP` is reported only if both sides are shut down.
+        if is_read_shutdown {
+            events |= IoEvents::IN | IoEvents::RDHUP;
+        }
+        if is_write_shutdown {
+            events |= IoEvents::OUT;
+        }
+        if is_read_shutdown && is_write_shutdown {
+            events |= IoEvents::HUP;
+        }
+        if self.is_reset.load(Ordering::Acquire) {
+            events |= IoEvents::ERR;
+        }
 
-        events
+        events & (mask | IoEvents::ALWAYS_POLL)
     }
 
     pub(super) fn register_observer(
@@ -94,8 +311,14 @@ impl Endpoint {
         observer: Weak<dyn Observer<IoEvents>>,
         mask: IoEvents,
     ) -> Result<()> {
-        if mask.contains(IoEvents::IN) {
-            self.reader.register_observer(observer.clone(), mask)?
+        // The shutdown of the read side is notified with `IoEvents::IN` on the reader.
+        if mask.intersects(IoEvents::IN | IoEvents::RDHUP) {
+            self.reader
+                .register_observer(observer.clone(), mask | IoEvents::IN)?
+        }
+
+        if mask.contains(IoEvents::PRI) {
+            self.read_oob.pollee.register_observer(observer.clone(), mask);
         }
 
         if mask.contains(IoEvents::OUT) {
@@ -111,14 +334,122 @@ impl Endpoint {
     ) -> Option<Weak<dyn Observer<IoEvents>>> {
         let observer0 = self.reader.unregister_observer(observer);
         let observer1 = self.writer.unregister_observer(observer);
+        let observer2 = self.read_oob.pollee.unregister_observer(observer);
+
+        observer0.or(observer1).or(observer2)
+    }
+}
+
+impl Drop for Endpoint {
+    fn drop(&mut self) {
+        // The files sent by the peer can no longer be received. They are closed now rather
+        // than when the peer is closed.
+        self.read_rights.lock().discard();
+    }
+}
+
+/// The files sent along with the data in a channel.
+///
+/// The data in the channel is numbered by its offset from the first byte ever written. Each
+/// `SCM_RIGHTS` message is attached to the range of the data that is sent with it.
+struct RightsQueue {
+    /// The number of bytes that have been written to the channel.
+    nr_written: usize,
+    /// The number of bytes that have been read from the channel.
+    nr_read: usize,
+    /// The files and the data ranges that they are attached to, in the order they are sent.
+    rights: VecDeque<(Range<usize>, UnixRights)>,
+}
+
+impl RightsQueue {
+    fn new() -> Self {
+        Self {
+            nr_written: 0,
+            nr_read: 0,
+            rights: VecDeque::new(),
+        }
+    }
+
+    /// Records that `len` bytes are written to the channel, along with `rights` if any.
+    fn produce(&mut self, len: usize, rights: Option<&UnixRights>) {
+        let range = self.nr_written..self.nr_written + len;
+        self.nr_written = range.end;
+
+        // The files sent with no data are dropped, since they can never be received.
+        if range.is_empty() {
+            return;
+        }
+        if let Some(rights) = rights {
+            self.rights.push_back((range, rights.clone()));
+        }
+    }
+
+    /// Returns the number of bytes that a read of `buf_len` bytes can read, which ends
+    /// at the end of the data that the first files are attached to.
+    fn read_limit(&self, buf_len: usize) -> usize {
+        match self.rights.front() {
+            Some((range, _)) => buf_len.min(range.end - self.nr_read),
+            None => buf_len,
+        }
+    }
 
-        if observer0.is_some() {
-            observer0
-        } else if observer1.is_some() {
-            observer1
-        } else {
-            None
+    /// Records that `len` bytes are read from the channel, and returns the files that are
+    /// attached to the read data.
+    fn consume(&mut self, len: usize) -> Option<UnixRights> {
+        self.nr_read += len;
+
+        let (range, _) = self.rights.front()?;
+        if range.start >= self.nr_read {
+            return None;
         }
+        self.rights.pop_front().map(|(_, rights)| rights)
+    }
+
+    /// Drops the files and records that the data in the channel is discarded.
+    fn discard(&mut self) {
+        self.nr_read = self.nr_written;
+        self.rights.clear();
+    }
+}
+
+/// The out-of-band byte of a UNIX stream socket.
+///
+/// At most one out-of-band byte is kept. Like Linux, a new out-of-band byte replaces the one
+/// that has not been read.
+//
+// FIXME: Linux keeps the replaced byte in the normal data and stops a read at the position of
+// the out-of-band byte (the "urgent mark"). Here the replaced byte is discarded and the mark
+// is not tracked.
+struct OobByte {
+    byte: Mutex<Option<u8>>,
+    pollee: Pollee,
+}
+
+impl OobByte {
+    fn new() -> Self {
+        Self {
+            byte: Mutex::new(None),
+            pollee: Pollee::new(IoEvents::empty()),
+        }
+    }
+
+    fn set(&self, byte: u8) {
+        *self.byte.lock() = Some(byte);
+        self.pollee.add_events(IoEvents::PRI);
+    }
+
+    fn take(&self) -> Option<u8> {
+        let mut byte = self.byte.lock();
+        self.pollee.del_events(IoEvents::PRI);
+        byte.take()
+    }
+}
+
+struct DrainObserver(Arc<Pauser>);
+
+impl Observer<IoEvents> for DrainObserver {
+    fn on_events(&self, _events: &IoEvents) {
+        self.0.resume_all();
     }
 }
 
@@ -1,20 +1,23 @@
 // SPDX-License-Identifier: MPL-2.0
 
-use super::{connected::Connected, endpoint::Endpoint, listener::push_incoming};
+use super::{
+    connected::Connected,
+    endpoint::Endpoint,
+    listener::{push_incoming, push_incoming_or_pending, PendingConnection},
+};
 use crate::{
     events::{IoEvents, Observer},
-    fs::{
-        fs_resolver::{split_path, FsPath},
-        path::Dentry,
-        utils::{InodeMode, InodeType},
+    net::socket::unix::addr::{
+        create_socket_file, lookup_socket_file, SocketFileHolder, UnixSocketAddr,
+        UnixSocketAddrBound,
     },
-    net::socket::unix::addr::{UnixSocketAddr, UnixSocketAddrBound},
     prelude::*,
     process::signal::{Pollee, Poller},
 };
 
 pub(super) struct Init {
     addr: Option<UnixSocketAddrBound>,
+    file_holder: Option<Arc<SocketFileHolder>>,
     pollee: Pollee,
 }
 
@@ -22,24 +25,44 @@ impl Init {
     pub(super) fn new() -> Self {
         Self {
             addr: None,
+            file_holder: None,
             pollee: Pollee::new(IoEvents::empty()),
         }
     }
 
-    pub(super) fn bind(&mut self, addr_to_bind: UnixSocketAddr) -> Result<()> {
+    /// Binds the socket to `addr_to_bind`.
+    ///
+    /// If `reuse_addr` is true, an existing socket file can be reused as long as no
+    /// live socket is bound to it, e.g., when the socket file is left behind by a
+    /// process that has exited abnormally.
+    pub(super) fn bind(&mut self, addr_to_bind: UnixSocketAddr, reuse_addr: bool) -> Result<()> {
         if self.addr.is_some() {
             return_errno_with_message!(Errno::EINVAL, "the socket is already bound");
         }
 
-        let bound_addr = match addr_to_bind {
-            UnixSocketAddr::Unnamed => todo!(),
-            UnixSocketAddr::Abstract(_) => todo!(),
+        let (bound_addr, file_holder) = match addr_to_bind {
+            UnixSocketAddr::Unnamed => {
+                return_errno_with_message!(Errno::EOPNOTSUPP, "autobind is not supported")
+            }
+            UnixSocketAddr::Abstract(_) => {
+                return_errno_with_message!(
+                    Errno::EOPNOTSUPP,
+                    "abstract addresses are not supported"
+                )
+            }
             UnixSocketAddr::Path(path) => {
-                let dentry = create_socket_file(&path)?;
-                UnixSocketAddrBound::Path(path, dentry)
+                let dentry = match create_socket_file(&path) {
+                    Err(err) if err.error() == Errno::EADDRINUSE && reuse_addr => {
+                        lookup_socket_file(&path)?
+                    }
+                    result => result?,
+                };
+                let file_holder = SocketFileHolder::new(&dentry)?;
+                (UnixSocketAddrBound::Path(path, dentry), file_holder)
             }
         };
         self.addr = Some(bound_addr);
+        self.file_holder = Some(file_holder);
 
         Ok(())
     }
@@ -50,13 +73,36 @@ impl Init {
 
         push_incoming(remote_addr, remote_end)?;
 
-        Ok(Connected::new(this_end))
+        Ok(Connected::new(this_end, self.file_holder.clone()))
+    }
+
+    /// Connects to `remote_addr` without waiting for room in the backlog of the listening
+    /// socket.
+    ///
+    /// If the backlog is full, the connection is queued, and is completed later when the
+    /// listening socket accepts some connections.
+    pub(super) fn connect_nonblocking(
+        &self,
+        remote_addr: &UnixSocketAddrBound,
+    ) -> Result<NonBlockingConnect> {
+        let (this_end, remote_end) =
+            Endpoint::new_pair(self.addr.clone(), Some(remote_addr.clone()));
+
+        let Some(connection) = push_incoming_or_pending(remote_addr, remote_end)? else {
+            let connected = Connected::new(this_end, self.file_holder.clone());
+            return Ok(NonBlockingConnect::Connected(connected));
+        };
+        Ok(NonBlockingConnect::Pending(this_end, connection))
     }
 
     pub(super) fn addr(&self) -> Option<&UnixSocketAddrBound> {
         self.addr.as_ref()
     }
 
+    pub(super) fn file_holder(&self) -> Option<&Arc<SocketFileHolder>> {
+        self.file_holder.as_ref()
+    }
+
     pub(super) fn poll(&self, mask: IoEvents, poller: Option<&mut Poller>) -> IoEvents {
         self.pollee.poll(mask, poller)
     }
@@ -78,18 +124,10 @@ impl Init {
     }
 }
 
-fn create_socket_file(path: &str) -> Result<Arc<Dentry>> {
-    let (parent_pathname, file_name) = split_path(path);
-    let parent = {
-        let current = current!();
-        let fs = current.fs().read();
-        let parent_path = FsPath::try_from(parent_pathname)?;
-        fs.lookup(&parent_path)?
-    };
-    let dentry = parent.new_fs_child(
-        file_name,
-        InodeType::Socket,
-        InodeMode::S_IRUSR | InodeMode::S_IWUSR,
-    )?;
-    Ok(dentry)
+/// The outcome of [`Init::connect_nonblocking`].
+pub(super) enum NonBlockingConnect {
+    /// The connection is in the backlog of the listening socket.
+    Connected(Connected),
+    /// The connection waits for room in the backlog of the listening socket.
+    Pending(Endpoint, Arc<PendingConnection>),
 }
@@ -1,24 +1,46 @@
 // SPDX-License-Identifier: MPL-2.0
 
+use core::{
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
+};
+
 use keyable_arc::KeyableWeak;
+use ostd::arch::timer::Jiffies;
 
 use super::{connected::Connected, endpoint::Endpoint, UnixStreamSocket};
 use crate::{
     events::{IoEvents, Observer},
     fs::{file_handle::FileLike, path::Dentry, utils::Inode},
-    net::socket::{unix::addr::UnixSocketAddrBound, SocketAddr},
+    net::socket::{
+        unix::addr::{SocketFileHolder, UnixSocketAddrBound},
+        SocketAddr,
+    },
     prelude::*,
-    process::signal::{Pollee, Poller},
+    process::signal::{Pauser, Pollee, Poller},
+    util::collections::ShardedMap,
 };
 
 pub(super) struct Listener {
     addr: UnixSocketAddrBound,
+    backlog: Arc<Backlog>,
+    /// The hold on the socket file, which is released after the backlog is removed.
+    _file_holder: Arc<SocketFileHolder>,
 }
 
 impl Listener {
-    pub(super) fn new(addr: UnixSocketAddrBound, backlog: usize) -> Result<Self> {
-        BACKLOG_TABLE.add_backlog(&addr, backlog)?;
-        Ok(Self { addr })
+    pub(super) fn new(
+        addr: UnixSocketAddrBound,
+        file_holder: Arc<SocketFileHolder>,
+        backlog: usize,
+        reuse_addr: bool,
+    ) -> Result<Self> {
+        let backlog = BACKLOG_TABLE.add_backlog(&addr, backlog, reuse_addr)?;
+        Ok(Self {
+            addr,
+            backlog,
+            _file_holder: file_holder,
+        })
     }
 
     pub(super) fn addr(&self) -> &UnixSocketAddrBound {
@@ -26,11 +48,12 @@ impl Listener {
     }
 
     pub(super) fn try_accept(&self) -> Result<(Arc<dyn FileLike>, SocketAddr)> {
-        let addr = self.addr().clone();
-
         let connected = {
-            let local_endpoint = BACKLOG_TABLE.pop_incoming(&addr)?;
-            Connected::new(local_endpoint)
+            let Some(local_endpoint) = self.backlog.pop_incoming() else {
+                return_errno_with_message!(Errno::EAGAIN, "no pending connection is available")
+            };
+            // The accepted socket is not bound, so it does not hold the socket file.
+            Connected::new(local_endpoint, None)
         };
 
         let peer_addr = connected.peer_addr().cloned().into();
@@ -41,9 +64,7 @@ impl Listener {
     }
 
     pub(super) fn poll(&self, mask: IoEvents, poller: Option<&mut Poller>) -> IoEvents {
-        let addr = self.addr();
-        let backlog = BACKLOG_TABLE.get_backlog(addr).unwrap();
-        backlog.poll(mask, poller)
+        self.backlog.poll(mask, poller)
     }
 
     pub(super) fn register_observer(
@@ -51,36 +72,50 @@ impl Listener {
         observer: Weak<dyn Observer<IoEvents>>,
         mask: IoEvents,
     ) -> Result<()> {
-        let addr = self.addr();
-        let backlog = BACKLOG_TABLE.get_backlog(addr)?;
-        backlog.register_observer(observer, mask)
+        self.backlog.register_observer(observer, mask)
     }
 
     pub(super) fn unregister_observer(
         &self,
         observer: &Weak<dyn Observer<IoEvents>>,
     ) -> Option<Weak<dyn Observer<IoEvents>>> {
-        let addr = self.addr();
-        let backlog = BACKLOG_TABLE.get_backlog(addr).ok()?;
-        backlog.unregister_observer(observer)
+        self.backlog.unregister_observer(observer)
+    }
+}
+
+impl Drop for Listener {
+    fn drop(&mut self) {
+        // The backlog is removed whenever the listener goes away, no matter whether the
+        // socket is closed explicitly or the owning process exits abnormally.
+        BACKLOG_TABLE.remove_backlog(&self.addr, &self.backlog);
     }
 }
 
 static BACKLOG_TABLE: BacklogTable = BacklogTable::new();
 
 struct BacklogTable {
-    backlog_sockets: RwLock<BTreeMap<KeyableWeak<dyn Inode>, Arc<Backlog>>>,
+    backlog_sockets: ShardedMap<KeyableWeak<dyn Inode>, Arc<Backlog>>,
     // TODO: For linux, there is also abstract socket domain that a socket addr is not bound to an inode.
 }
 
 impl BacklogTable {
     const fn new() -> Self {
         Self {
-            backlog_sockets: RwLock::new(BTreeMap::new()),
+            backlog_sockets: ShardedMap::new(),
         }
     }
 
-    fn add_backlog(&self, addr: &UnixSocketAddrBound, backlog: usize) -> Result<()> {
+    /// Adds a backlog for the listening socket at `addr`.
+    ///
+    /// If a stale backlog whose listening socket has gone is still registered at `addr`,
+    /// it will be taken over if `reuse_addr` is true. Otherwise, or if the registered
+    /// backlog is still alive, this method fails with `EADDRINUSE`.
+    fn add_backlog(
+        &self,
+        addr: &UnixSocketAddrBound,
+        backlog: usize,
+        reuse_addr: bool,
+    ) -> Result<Arc<Backlog>> {
         let inode = {
             let UnixSocketAddrBound::Path(_, dentry) = addr else {
                 todo!()
@@ -88,13 +123,25 @@ impl BacklogTable {
             create_keyable_inode(dentry)
         };
 
-        let mut backlog_sockets = self.backlog_sockets.write();
-        if backlog_sockets.contains_key(&inode) {
-            return_errno_with_message!(Errno::EADDRINUSE, "the addr is already used");
+        let entry = self.backlog_sockets.entry(inode);
+        if let Some((old_inode, old_backlog)) = entry.get_key_value() {
+            // The old inode may have been dropped while a new inode is allocated at the
+            // same address, in which case the old backlog is stale as well.
+            let is_stale = old_inode.upgrade().is_none() || old_backlog.is_shutdown();
+            if !is_stale {
+                return_errno_with_message!(Errno::EADDRINUSE, "the addr is already used");
+            }
+            if !reuse_addr {
+                return_errno_with_message!(
+                    Errno::EADDRINUSE,
+                    "the addr is used by a stale socket and SO_REUSEADDR is not set"
+                );
+            }
         }
-        let new_backlog = Arc::new(Backlog::new(backlog));
-        backlog_sockets.insert(inode, new_backlog);
-        Ok(())
+        // The new key replaces the old one, which may refer to a dropped inode.
+        let new_backlog = Arc::new(Backlog::new(addr.clone(), backlog));
+        entry.insert(new_backlog.clone());
+        Ok(new_backlog)
     }
 
     fn get_backlog(&self, addr: &UnixSocketAddrBound) -> Result<Arc<Backlog>> {
@@ -105,78 +152,185 @@ impl BacklogTable {
             create_keyable_inode(dentry)
         };
 
-        let backlog_sockets = self.backlog_sockets.read();
-        backlog_sockets
+        self.backlog_sockets
             .get(&inode)
-            .map(Arc::clone)
+            .filter(|backlog| !backlog.is_shutdown())
             .ok_or_else(|| Error::with_message(Errno::EINVAL, "the socket is not listened"))
     }
 
-    fn pop_incoming(&self, addr: &UnixSocketAddrBound) -> Result<Endpoint> {
-        let backlog = self.get_backlog(addr)?;
+    fn push_incoming(&self, addr: &UnixSocketAddrBound, endpoint: Endpoint) -> Result<()> {
+        let backlog = self.get_remote_backlog(addr)?;
+        backlog.push_incoming(endpoint)
+    }
 
-        if let Some(endpoint) = backlog.pop_incoming() {
-            Ok(endpoint)
-        } else {
-            return_errno_with_message!(Errno::EAGAIN, "no pending connection is available")
-        }
+    fn push_incoming_or_pending(
+        &self,
+        addr: &UnixSocketAddrBound,
+        endpoint: Endpoint,
+    ) -> Result<Option<Arc<PendingConnection>>> {
+        let backlog = self.get_remote_backlog(addr)?;
+        Ok(backlog.push_incoming_or_pending(endpoint))
     }
 
-    fn push_incoming(&self, addr: &UnixSocketAddrBound, endpoint: Endpoint) -> Result<()> {
-        let backlog = self.get_backlog(addr).map_err(|_| {
+    fn get_remote_backlog(&self, addr: &UnixSocketAddrBound) -> Result<Arc<Backlog>> {
+        self.get_backlog(addr).map_err(|_| {
             Error::with_message(
                 Errno::ECONNREFUSED,
                 "no socket is listened at the remote address",
             )
-        })?;
+        })
+    }
 
-        backlog.push_incoming(endpoint)
+    /// Returns the addresses of all the listening sockets.
+    ///
+    /// Each address is collected with its entry locked, so a socket that is being closed
+    /// either appears with its address or does not appear at all.
+    fn listening_addrs(&self) -> Vec<UnixSocketAddrBound> {
+        let mut addrs = Vec::new();
+        self.backlog_sockets.for_each(|_, backlog| {
+            if !backlog.is_shutdown() {
+                addrs.push(backlog.addr.clone());
+            }
+        });
+        addrs
     }
 
-    fn remove_backlog(&self, addr: &UnixSocketAddrBound) {
+    fn remove_backlog(&self, addr: &UnixSocketAddrBound, backlog: &Arc<Backlog>) {
         let UnixSocketAddrBound::Path(_, dentry) = addr else {
             todo!()
         };
 
         let inode = create_keyable_inode(dentry);
-        self.backlog_sockets.write().remove(&inode);
+        let entry = self.backlog_sockets.entry(inode);
+        // The address may have been taken over by another socket. In this case, the entry
+        // belongs to the new socket and must be left untouched.
+        if entry
+            .get()
+            .is_some_and(|registered| Arc::ptr_eq(registered, backlog))
+        {
+            entry.remove();
+        } else {
+            drop(entry);
+        }
+        backlog.shutdown();
     }
 }
 
+/// The pending connections of a listening socket.
+///
+/// The pollee reports `IoEvents::IN` if there are pending connections to accept.
 struct Backlog {
+    addr: UnixSocketAddrBound,
     pollee: Pollee,
+    /// The pauser of the connecting sockets that wait for room in the backlog.
+    pauser: Arc<Pauser>,
     backlog: usize,
     incoming_endpoints: Mutex<VecDeque<Endpoint>>,
+    /// The connections of the non-blocking sockets that wait for room in the backlog.
+    ///
+    /// This is locked after `incoming_endpoints`.
+    pending_connections: Mutex<VecDeque<Arc<PendingConnection>>>,
+    is_shutdown: AtomicBool,
 }
 
 impl Backlog {
-    fn new(backlog: usize) -> Self {
+    fn new(addr: UnixSocketAddrBound, backlog: usize) -> Self {
         Self {
+            addr,
             pollee: Pollee::new(IoEvents::empty()),
+            pauser: Pauser::new(),
             backlog,
-            incoming_endpoints: Mutex::new(VecDeque::with_capacity(backlog)),
+            incoming_endpoints: Mutex::new(VecDeque::with_capacity(backlog + 1)),
+            pending_connections: Mutex::new(VecDeque::new()),
+            is_shutdown: AtomicBool::new(false),
         }
     }
 
     fn push_incoming(&self, endpoint: Endpoint) -> Result<()> {
         let mut endpoints = self.incoming_endpoints.lock();
-        if endpoints.len() >= self.backlog {
-            return_errno_with_message!(Errno::ECONNREFUSED, "incoming_endpoints is full");
+        // Like Linux, at most `backlog + 1` connections can be pending.
+        if endpoints.len() > self.backlog {
+            return_errno_with_message!(Errno::EAGAIN, "incoming_endpoints is full");
         }
         endpoints.push_back(endpoint);
         self.pollee.add_events(IoEvents::IN);
         Ok(())
     }
 
+    /// Pushes `endpoint` as an incoming connection, or queues it as a pending connection
+    /// if the backlog is full.
+    ///
+    /// The pending connection is returned if the endpoint is queued.
+    fn push_incoming_or_pending(&self, endpoint: Endpoint) -> Option<Arc<PendingConnection>> {
+        let mut endpoints = self.incoming_endpoints.lock();
+        if endpoints.len() <= self.backlog {
+            endpoints.push_back(endpoint);
+            self.pollee.add_events(IoEvents::IN);
+            return None;
+        }
+
+        let connection = Arc::new(PendingConnection::new(endpoint));
+        self.pending_connections
+            .lock()
+            .push_back(connection.clone());
+        Some(connection)
+    }
+
     fn pop_incoming(&self) -> Option<Endpoint> {
         let mut incoming_endpoints = self.incoming_endpoints.lock();
         let endpoint = incoming_endpoints.pop_front();
+        if endpoint.is_some() {
+            self.fill_from_pending(&mut incoming_endpoints);
+        }
         if incoming_endpoints.is_empty() {
             self.pollee.del_events(IoEvents::IN);
         }
+        drop(incoming_endpoints);
+
+        if endpoint.is_some() {
+            self.pauser.resume_all();
+        }
         endpoint
     }
 
+    /// Moves the pending connections to the room in the backlog, which completes them.
+    fn fill_from_pending(&self, incoming_endpoints: &mut VecDeque<Endpoint>) {
+        let mut pending_connections = self.pending_connections.lock();
+        while incoming_endpoints.len() <= self.backlog {
+            let Some(connection) = pending_connections.pop_front() else {
+                break;
+            };
+            // The connecting socket may have been closed.
+            let Some(endpoint) = connection.take_endpoint() else {
+                continue;
+            };
+            incoming_endpoints.push_back(endpoint);
+            self.pollee.add_events(IoEvents::IN);
+            connection.complete(Ok(()));
+        }
+    }
+
+    /// Wakes up the sockets waiting for room in the backlog,
+    /// since the listening socket is closed.
+    fn shutdown(&self) {
+        self.is_shutdown.store(true, Ordering::Relaxed);
+        self.pauser.resume_all();
+
+        let pending_connections = core::mem::take(&mut *self.pending_connections.lock());
+        for connection in pending_connections {
+            if connection.take_endpoint().is_some() {
+                connection.complete(Err(Error::with_message(
+                    Errno::ECONNREFUSED,
+                    "the listening socket is closed",
+                )));
+            }
+        }
+    }
+
+    fn is_shutdown(&self) -> bool {
+        self.is_shutdown.load(Ordering::Relaxed)
+    }
+
     fn poll(&self, mask: IoEvents, poller: Option<&mut Poller>) -> IoEvents {
         // Lock to avoid any events may change pollee state when we poll
         let _lock = self.incoming_endpoints.lock();
@@ -200,15 +354,142 @@ impl Backlog {
     }
 }
 
+/// A connection of a non-blocking socket that waits for room in the backlog of a listening
+/// socket.
+///
+/// The pollee reports `IoEvents::OUT` once the connection is moved to the backlog, i.e., once
+/// the listening socket accepts some connections so that there is room. It reports
+/// `IoEvents::OUT | IoEvents::ERR` once the connection fails because the listening socket is
+/// closed.
+pub(super) struct PendingConnection {
+    /// The endpoint to be accepted by the listening socket.
+    ///
+    /// It is taken once the connection is moved to the backlog or canceled.
+    endpoint: Mutex<Option<Endpoint>>,
+    result: Mutex<Option<Result<()>>>,
+    pollee: Pollee,
+}
+
+impl PendingConnection {
+    fn new(endpoint: Endpoint) -> Self {
+        Self {
+            endpoint: Mutex::new(Some(endpoint)),
+            result: Mutex::new(None),
+            pollee: Pollee::new(IoEvents::empty()),
+        }
+    }
+
+    fn take_endpoint(&self) -> Option<Endpoint> {
+        self.endpoint.lock().take()
+    }
+
+    fn complete(&self, result: Result<()>) {
+        let events = if result.is_ok() {
+            IoEvents::OUT
+        } else {
+            IoEvents::OUT | IoEvents::ERR
+        };
+        *self.result.lock() = Some(result);
+        self.pollee.add_events(events);
+    }
+
+    /// Returns the result of the connection, or `None` if it is still pending.
+    pub(super) fn result(&self) -> Option<Result<()>> {
+        *self.result.lock()
+    }
+
+    /// Cancels the connection, since the connecting socket is closed.
+    ///
+    /// The endpoint is dropped, so the listening socket will never accept it.
+    pub(super) fn cancel(&self) {
+        drop(self.take_endpoint());
+    }
+
+    pub(super) fn poll(&self, mask: IoEvents, poller: Option<&mut Poller>) -> IoEvents {
+        self.pollee.poll(mask, poller)
+    }
+
+    pub(super) fn register_observer(
+        &self,
+        observer: Weak<dyn Observer<IoEvents>>,
+        mask: IoEvents,
+    ) -> Result<()> {
+        self.pollee.register_observer(observer, mask);
+        Ok(())
+    }
+
+    pub(super) fn unregister_observer(
+        &self,
+        observer: &Weak<dyn Observer<IoEvents>>,
+    ) -> Option<Weak<dyn Observer<IoEvents>>> {
+        self.pollee.unregister_observer(observer)
+    }
+}
+
 fn create_keyable_inode(dentry: &Arc<Dentry>) -> KeyableWeak<dyn Inode> {
     let weak_inode = Arc::downgrade(dentry.inode());
     KeyableWeak::from(weak_inode)
 }
 
-pub(super) fn unregister_backlog(addr: &UnixSocketAddrBound) {
-    BACKLOG_TABLE.remove_backlog(addr);
+pub(super) fn listening_addrs() -> Vec<UnixSocketAddrBound> {
+    BACKLOG_TABLE.listening_addrs()
 }
 
 pub(super) fn push_incoming(remote_addr: &UnixSocketAddrBound, remote_end: Endpoint) -> Result<()> {
     BACKLOG_TABLE.push_incoming(remote_addr, remote_end)
 }
+
+pub(super) fn push_incoming_or_pending(
+    remote_addr: &UnixSocketAddrBound,
+    remote_end: Endpoint,
+) -> Result<Option<Arc<PendingConnection>>> {
+    BACKLOG_TABLE.push_incoming_or_pending(remote_addr, remote_end)
+}
+
+/// Calls `cond` until it does not fail with `EAGAIN`, waiting for the backlog
+/// at `remote_addr` to have room between the calls.
+///
+/// If `timeout` is not `None` and `cond` still fails with `EAGAIN` after it,
+/// this function fails with `ETIMEDOUT`.
+pub(super) fn wait_for_backlog<F, R>(
+    remote_addr: &UnixSocketAddrBound,
+    timeout: Option<&Duration>,
+    mut cond: F,
+) -> Result<R>
+where
+    F: FnMut() -> Result<R>,
+{
+    let deadline = timeout.map(|timeout| Jiffies::elapsed().as_duration() + *timeout);
+
+    loop {
+        // The listening socket may be closed while waiting. In this case, `cond` is called
+        // again to report the error.
+        let Ok(backlog) = BACKLOG_TABLE.get_remote_backlog(remote_addr) else {
+            return cond();
+        };
+
+        // If the listening socket is closed, stop waiting for its backlog. Another socket
+        // may be listening at the address now, whose backlog is waited for in the next loop.
+        let wait_cond = || match cond() {
+            Err(err) if err.error() == Errno::EAGAIN && !backlog.is_shutdown() => None,
+            res => Some(res),
+        };
+        let res = match deadline {
+            None => backlog.pauser.pause_until(wait_cond)?,
+            Some(deadline) => {
+                let remaining = deadline.saturating_sub(Jiffies::elapsed().as_duration());
+                match backlog.pauser.pause_until_or_timeout(wait_cond, &remaining) {
+                    Err(err) if err.error() == Errno::ETIME => {
+                        return_errno_with_message!(Errno::ETIMEDOUT, "the timeout expires")
+                    }
+                    res => res?,
+                }
+            }
+        };
+
+        match res {
+            Err(err) if err.error() == Errno::EAGAIN => continue,
+            res => return res,
+        }
+    }
+}
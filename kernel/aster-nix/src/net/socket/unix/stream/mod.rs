@@ -1,9 +1,50 @@
 // SPDX-License-Identifier: MPL-2.0
 
 mod connected;
+mod connecting;
 mod endpoint;
 mod init;
 mod listener;
 mod socket;
 
 pub use socket::UnixStreamSocket;
+
+use super::UnixSocketAddr;
+use crate::prelude::*;
+
+/// The information of a UNIX stream socket, which is used for debugging.
+#[derive(Debug, Clone)]
+pub struct UnixStreamInfo {
+    /// The local address of the socket.
+    pub addr: UnixSocketAddr,
+    /// The state of the socket.
+    pub state: UnixStreamState,
+}
+
+/// The state of a UNIX stream socket reported by [`stream_sockets`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnixStreamState {
+    Listening,
+    Connected,
+}
+
+/// Returns the information of all the listening and connected UNIX stream sockets.
+///
+/// The result is a snapshot. Sockets that are created or closed concurrently may or may
+/// not be included.
+pub fn stream_sockets() -> Vec<UnixStreamInfo> {
+    let listening = listener::listening_addrs()
+        .into_iter()
+        .map(|addr| UnixStreamInfo {
+            addr: addr.into(),
+            state: UnixStreamState::Listening,
+        });
+    let connected = connected::connected_addrs()
+        .into_iter()
+        .map(|addr| UnixStreamInfo {
+            addr: addr.into(),
+            state: UnixStreamState::Connected,
+        });
+
+    listening.chain(connected).collect()
+}
@@ -1,30 +1,37 @@
 // SPDX-License-Identifier: MPL-2.0
 
-use core::sync::atomic::AtomicBool;
+use core::{
+    sync::atomic::{AtomicBool, AtomicUsize},
+    time::Duration,
+};
 
 use atomic::Ordering;
+use ostd::sync::RwLockReadGuard;
 
 use super::{
     connected::Connected,
+    connecting::Connecting,
     endpoint::Endpoint,
-    init::Init,
-    listener::{unregister_backlog, Listener},
+    init::{Init, NonBlockingConnect},
+    listener::{wait_for_backlog, Listener},
 };
 use crate::{
     events::{IoEvents, Observer},
-    fs::{
-        file_handle::FileLike,
-        fs_resolver::FsPath,
-        path::Dentry,
-        utils::{InodeType, StatusFlags},
-    },
+    fs::{file_handle::FileLike, utils::StatusFlags},
+    match_sock_option_mut, match_sock_option_ref,
     net::socket::{
-        unix::{addr::UnixSocketAddrBound, UnixSocketAddr},
+        ip::stream::options::is_tcp_option,
+        options::{
+            Error as SocketError, Linger, PassCred, RecvTimeout, ReuseAddr, SendLowat,
+            SendTimeout, SocketOption,
+        },
+        unix::{addr::UnixSocketAddrBound, check_rights, timeout_of, UnixSocketAddr},
         util::{
             copy_message_from_user, copy_message_to_user, create_message_buffer,
-            send_recv_flags::SendRecvFlags, socket_addr::SocketAddr, MessageHeader,
+            send_recv_flags::SendRecvFlags, socket_addr::SocketAddr, ControlMessage, MessageHeader,
+            UnixCredentials, UnixRights,
         },
-        SockShutdownCmd, Socket,
+        LingerOption, SockShutdownCmd, Socket,
     },
     prelude::*,
     process::signal::{Pollable, Poller},
@@ -34,6 +41,18 @@ use crate::{
 pub struct UnixStreamSocket {
     state: RwLock<State>,
     is_nonblocking: AtomicBool,
+    linger: Mutex<LingerOption>,
+    reuse_addr: AtomicBool,
+    /// The `SO_SNDLOWAT` option, which is applied to the send buffer once connected.
+    send_lowat: AtomicUsize,
+    /// The `SO_SNDTIMEO` option. Zero means no timeout.
+    send_timeout: Mutex<Duration>,
+    /// The `SO_RCVTIMEO` option. Zero means no timeout.
+    recv_timeout: Mutex<Duration>,
+    /// The error of the failed non-blocking connection, which is reported by `SO_ERROR`.
+    connect_error: Mutex<Option<Error>>,
+    /// The `SO_PASSCRED` option. The credentials of the peer are received only if it is set.
+    pass_cred: AtomicBool,
 }
 
 impl UnixStreamSocket {
@@ -41,6 +60,13 @@ impl UnixStreamSocket {
         Arc::new(Self {
             state: RwLock::new(State::Init(init)),
             is_nonblocking: AtomicBool::new(is_nonblocking),
+            linger: Mutex::new(LingerOption::default()),
+            reuse_addr: AtomicBool::new(false),
+            send_lowat: AtomicUsize::new(1),
+            send_timeout: Mutex::new(Duration::ZERO),
+            recv_timeout: Mutex::new(Duration::ZERO),
+            connect_error: Mutex::new(None),
+            pass_cred: AtomicBool::new(false),
         })
     }
 
@@ -48,6 +74,13 @@ impl UnixStreamSocket {
         Arc::new(Self {
             state: RwLock::new(State::Connected(connected)),
             is_nonblocking: AtomicBool::new(is_nonblocking),
+            linger: Mutex::new(LingerOption::default()),
+            reuse_addr: AtomicBool::new(false),
+            send_lowat: AtomicUsize::new(1),
+            send_timeout: Mutex::new(Duration::ZERO),
+            recv_timeout: Mutex::new(Duration::ZERO),
+            connect_error: Mutex::new(None),
+            pass_cred: AtomicBool::new(false),
         })
     }
 }
@@ -55,6 +88,7 @@ impl UnixStreamSocket {
 enum State {
     Init(Init),
     Listen(Listener),
+    Connecting(Connecting),
     Connected(Connected),
 }
 
@@ -66,50 +100,179 @@ impl UnixStreamSocket {
     pub fn new_pair(is_nonblocking: bool) -> (Arc<Self>, Arc<Self>) {
         let (end_a, end_b) = Endpoint::new_pair(None, None);
         (
-            Self::new_connected(Connected::new(end_a), is_nonblocking),
-            Self::new_connected(Connected::new(end_b), is_nonblocking),
+            Self::new_connected(Connected::new(end_a, None), is_nonblocking),
+            Self::new_connected(Connected::new(end_b, None), is_nonblocking),
         )
     }
 
-    fn bound_addr(&self) -> Option<UnixSocketAddrBound> {
-        let state = self.state.read();
-        match &*state {
-            State::Init(init) => init.addr().cloned(),
-            State::Listen(listen) => Some(listen.addr().clone()),
-            State::Connected(connected) => connected.addr().cloned(),
-        }
-    }
+    fn send(
+        &self,
+        buf: &[u8],
+        credentials: Option<&UnixCredentials>,
+        rights: Option<&UnixRights>,
+        flags: SendRecvFlags,
+    ) -> Result<usize> {
+        // Like Linux, the credentials of the sender are sent along with the data if it does
+        // not specify any, so that a receiver with `SO_PASSCRED` always gets some.
+        let credentials = credentials
+            .copied()
+            .unwrap_or_else(UnixCredentials::current);
+        let credentials = Some(&credentials);
 
-    fn send(&self, buf: &[u8], flags: SendRecvFlags) -> Result<usize> {
         if self.is_nonblocking() {
-            self.try_send(buf, flags)
+            self.try_send(buf, credentials, rights, flags)
         } else {
-            self.wait_events(IoEvents::OUT, || self.try_send(buf, flags))
+            let timeout = timeout_of(*self.send_timeout.lock());
+            self.wait_events_with_timeout(IoEvents::OUT, timeout.as_ref(), || {
+                self.try_send(buf, credentials, rights, flags)
+            })
         }
     }
 
-    fn try_send(&self, buf: &[u8], _flags: SendRecvFlags) -> Result<usize> {
-        match &*self.state.read() {
-            State::Connected(connected) => connected.try_write(buf),
+    fn try_send(
+        &self,
+        buf: &[u8],
+        credentials: Option<&UnixCredentials>,
+        rights: Option<&UnixRights>,
+        flags: SendRecvFlags,
+    ) -> Result<usize> {
+        match &*self.read_state() {
+            State::Connected(connected) if flags.contains(SendRecvFlags::MSG_OOB) => {
+                connected.try_write_oob(buf, credentials, rights)
+            }
+            State::Connected(connected) => connected.try_write(buf, credentials, rights),
             _ => return_errno_with_message!(Errno::ENOTCONN, "the socket is not connected"),
         }
     }
 
-    fn recv(&self, buf: &mut [u8], flags: SendRecvFlags) -> Result<usize> {
-        if self.is_nonblocking() {
+    /// Receives the data into `buf`, along with the files sent with the data.
+    fn recv(&self, buf: &mut [u8], flags: SendRecvFlags) -> Result<(usize, Option<UnixRights>)> {
+        // Like Linux, receiving the out-of-band byte never blocks.
+        if self.is_nonblocking() || flags.contains(SendRecvFlags::MSG_OOB) {
             self.try_recv(buf, flags)
         } else {
-            self.wait_events(IoEvents::IN, || self.try_recv(buf, flags))
+            let timeout = timeout_of(*self.recv_timeout.lock());
+            self.wait_events_with_timeout(IoEvents::IN, timeout.as_ref(), || {
+                self.try_recv(buf, flags)
+            })
         }
     }
 
-    fn try_recv(&self, buf: &mut [u8], _flags: SendRecvFlags) -> Result<usize> {
-        match &*self.state.read() {
+    fn try_recv(
+        &self,
+        buf: &mut [u8],
+        flags: SendRecvFlags,
+    ) -> Result<(usize, Option<UnixRights>)> {
+        match &*self.read_state() {
+            State::Connected(connected) if flags.contains(SendRecvFlags::MSG_OOB) => {
+                connected.try_read_oob(buf).map(|len| (len, None))
+            }
             State::Connected(connected) => connected.try_read(buf),
             _ => return_errno_with_message!(Errno::ENOTCONN, "the socket is not connected"),
         }
     }
 
+    fn take_credentials(&self) -> Option<UnixCredentials> {
+        match &*self.read_state() {
+            State::Connected(connected) => connected.take_credentials(),
+            _ => None,
+        }
+    }
+
+    fn try_connect(&self, remote_addr: &UnixSocketAddrBound) -> Result<()> {
+        let connected = match &*self.read_state() {
+            State::Init(init) => init.connect(remote_addr)?,
+            State::Listen(_) => {
+                return_errno_with_message!(Errno::EINVAL, "the socket is listening")
+            }
+            State::Connecting(_) => {
+                return_errno_with_message!(Errno::EALREADY, "the socket is connecting")
+            }
+            State::Connected(_) => {
+                return_errno_with_message!(Errno::EISCONN, "the socket is connected")
+            }
+        };
+
+        connected.set_send_low_watermark(self.send_lowat.load(Ordering::Relaxed));
+        *self.state.write() = State::Connected(connected);
+        Ok(())
+    }
+
+    /// Connects to `remote_addr` without waiting for room in the backlog.
+    ///
+    /// If the backlog is full, the socket turns to connecting and this method fails with
+    /// `EINPROGRESS`. The socket becomes writable once the connection is completed.
+    fn try_connect_nonblocking(&self, remote_addr: &UnixSocketAddrBound) -> Result<()> {
+        let mut state = self.state.write();
+
+        let res = match &*state {
+            State::Init(init) => init.connect_nonblocking(remote_addr)?,
+            State::Listen(_) => {
+                return_errno_with_message!(Errno::EINVAL, "the socket is listening")
+            }
+            State::Connecting(_) => {
+                return_errno_with_message!(Errno::EALREADY, "the socket is connecting")
+            }
+            State::Connected(_) => {
+                return_errno_with_message!(Errno::EISCONN, "the socket is connected")
+            }
+        };
+
+        let (local_endpoint, connection) = match res {
+            NonBlockingConnect::Connected(connected) => {
+                connected.set_send_low_watermark(self.send_lowat.load(Ordering::Relaxed));
+                *state = State::Connected(connected);
+                return Ok(());
+            }
+            NonBlockingConnect::Pending(local_endpoint, connection) => (local_endpoint, connection),
+        };
+
+        let State::Init(init) = core::mem::replace(&mut *state, State::Init(Init::new())) else {
+            unreachable!("the socket is not in the initial state");
+        };
+        *state = State::Connecting(Connecting::new(init, local_endpoint, connection));
+        return_errno_with_message!(Errno::EINPROGRESS, "the socket is connecting")
+    }
+
+    /// Locks the state for reading, completing the pending connection first if it is done.
+    fn read_state(&self) -> RwLockReadGuard<State> {
+        let state = self.state.read();
+        if !matches!(&*state, State::Connecting(connecting) if connecting.result().is_some()) {
+            return state;
+        }
+        drop(state);
+
+        self.finish_connect();
+        self.state.read()
+    }
+
+    fn finish_connect(&self) {
+        let mut state = self.state.write();
+        let State::Connecting(connecting) = &*state else {
+            return;
+        };
+        let Some(result) = connecting.result() else {
+            return;
+        };
+
+        let State::Connecting(connecting) =
+            core::mem::replace(&mut *state, State::Init(Init::new()))
+        else {
+            unreachable!("the socket is not connecting");
+        };
+        *state = match result {
+            Ok(()) => {
+                let connected = connecting.into_connected();
+                connected.set_send_low_watermark(self.send_lowat.load(Ordering::Relaxed));
+                State::Connected(connected)
+            }
+            Err(err) => {
+                *self.connect_error.lock() = Some(err);
+                State::Init(connecting.into_init())
+            }
+        };
+    }
+
     fn try_accept(&self) -> Result<(Arc<dyn FileLike>, SocketAddr)> {
         match &*self.state.read() {
             State::Listen(listen) => listen.try_accept() as _,
@@ -132,6 +295,7 @@ impl Pollable for UnixStreamSocket {
         match &*inner {
             State::Init(init) => init.poll(mask, poller),
             State::Listen(listen) => listen.poll(mask, poller),
+            State::Connecting(connecting) => connecting.poll(mask, poller),
             State::Connected(connected) => connected.poll(mask, poller),
         }
     }
@@ -145,13 +309,14 @@ impl FileLike for UnixStreamSocket {
     fn read(&self, buf: &mut [u8]) -> Result<usize> {
         // TODO: Set correct flags
         let flags = SendRecvFlags::empty();
-        self.recv(buf, flags)
+        // Like Linux, the files sent with the data are closed if they are not received.
+        self.recv(buf, flags).map(|(len, _)| len)
     }
 
     fn write(&self, buf: &[u8]) -> Result<usize> {
         // TODO: Set correct flags
         let flags = SendRecvFlags::empty();
-        self.send(buf, flags)
+        self.send(buf, None, None, flags)
     }
 
     fn status_flags(&self) -> StatusFlags {
@@ -176,6 +341,7 @@ impl FileLike for UnixStreamSocket {
         match &*inner {
             State::Init(init) => init.register_observer(observer, mask),
             State::Listen(listen) => listen.register_observer(observer, mask),
+            State::Connecting(connecting) => connecting.register_observer(observer, mask),
             State::Connected(connected) => connected.register_observer(observer, mask),
         }
     }
@@ -188,6 +354,7 @@ impl FileLike for UnixStreamSocket {
         match &*inner {
             State::Init(init) => init.unregister_observer(observer),
             State::Listen(listen) => listen.unregister_observer(observer),
+            State::Connecting(connecting) => connecting.unregister_observer(observer),
             State::Connected(connected) => connected.unregister_observer(observer),
         }
     }
@@ -198,7 +365,7 @@ impl Socket for UnixStreamSocket {
         let addr = UnixSocketAddr::try_from(socket_addr)?;
 
         match &mut *self.state.write() {
-            State::Init(init) => init.bind(addr),
+            State::Init(init) => init.bind(addr, self.reuse_addr.load(Ordering::Relaxed)),
             _ => return_errno_with_message!(
                 Errno::EINVAL,
                 "cannot bind a listening or connected socket"
@@ -208,19 +375,7 @@ impl Socket for UnixStreamSocket {
     }
 
     fn connect(&self, socket_addr: SocketAddr) -> Result<()> {
-        let remote_addr = {
-            let unix_socket_addr = UnixSocketAddr::try_from(socket_addr)?;
-            match unix_socket_addr {
-                UnixSocketAddr::Unnamed => todo!(),
-                UnixSocketAddr::Abstract(abstract_name) => {
-                    UnixSocketAddrBound::Abstract(abstract_name)
-                }
-                UnixSocketAddr::Path(path) => {
-                    let dentry = lookup_socket_file(&path)?;
-                    UnixSocketAddrBound::Path(path, dentry)
-                }
-            }
-        };
+        let remote_addr = UnixSocketAddr::try_from(socket_addr)?.resolve_remote()?;
 
         // Note that the Linux kernel implementation locks the remote socket and checks to see if
         // it is listening first. This is different from our implementation, which locks the local
@@ -231,38 +386,45 @@ impl Socket for UnixStreamSocket {
         //
         // See also <https://elixir.bootlin.com/linux/v6.10.4/source/net/unix/af_unix.c#L1527>.
 
-        let connected = match &*self.state.read() {
-            State::Init(init) => init.connect(&remote_addr)?,
-            State::Listen(_) => {
-                return_errno_with_message!(Errno::EINVAL, "the socket is listening")
-            }
-            State::Connected(_) => {
-                return_errno_with_message!(Errno::EISCONN, "the socket is connected")
-            }
-        };
-
-        *self.state.write() = State::Connected(connected);
-        Ok(())
+        // If the backlog of the remote socket is full, a non-blocking socket fails with
+        // `EINPROGRESS` and completes the connection when the remote socket accepts some
+        // pending connections, while a blocking socket waits until then, or fails with
+        // `ETIMEDOUT` after the timeout specified by `SO_SNDTIMEO` expires.
+        if self.is_nonblocking() {
+            self.try_connect_nonblocking(&remote_addr)
+        } else {
+            let timeout = timeout_of(*self.send_timeout.lock());
+            wait_for_backlog(&remote_addr, timeout.as_ref(), || {
+                self.try_connect(&remote_addr)
+            })
+        }
     }
 
     fn listen(&self, backlog: usize) -> Result<()> {
-        let addr = match &*self.state.read() {
-            State::Init(init) => init
-                .addr()
-                .ok_or(Error::with_message(
-                    Errno::EINVAL,
-                    "the socket is not bound",
-                ))?
-                .clone(),
+        let (addr, file_holder) = match &*self.read_state() {
+            State::Init(init) => {
+                let (Some(addr), Some(file_holder)) = (init.addr(), init.file_holder()) else {
+                    return_errno_with_message!(Errno::EINVAL, "the socket is not bound");
+                };
+                (addr.clone(), file_holder.clone())
+            }
             State::Listen(_) => {
                 return_errno_with_message!(Errno::EINVAL, "the socket is already listening")
             }
+            State::Connecting(_) => {
+                return_errno_with_message!(Errno::EINVAL, "the socket is connecting")
+            }
             State::Connected(_) => {
                 return_errno_with_message!(Errno::EISCONN, "the socket is already connected")
             }
         };
 
-        let listener = Listener::new(addr, backlog)?;
+        let listener = Listener::new(
+            addr,
+            file_holder,
+            backlog,
+            self.reuse_addr.load(Ordering::Relaxed),
+        )?;
         *self.state.write() = State::Listen(listener);
         Ok(())
     }
@@ -271,21 +433,22 @@ impl Socket for UnixStreamSocket {
         if self.is_nonblocking() {
             self.try_accept()
         } else {
-            self.wait_events(IoEvents::IN, || self.try_accept())
+            self.wait_events_exclusive(IoEvents::IN, || self.try_accept())
         }
     }
 
     fn shutdown(&self, cmd: SockShutdownCmd) -> Result<()> {
-        match &*self.state.read() {
+        match &*self.read_state() {
             State::Connected(connected) => connected.shutdown(cmd),
             _ => return_errno_with_message!(Errno::ENOTCONN, "the socked is not connected"),
         }
     }
 
     fn addr(&self) -> Result<SocketAddr> {
-        let addr = match &*self.state.read() {
+        let addr = match &*self.read_state() {
             State::Init(init) => init.addr().cloned(),
             State::Listen(listen) => Some(listen.addr().clone()),
+            State::Connecting(connecting) => connecting.addr().cloned(),
             State::Connected(connected) => connected.addr().cloned(),
         };
 
@@ -293,7 +456,7 @@ impl Socket for UnixStreamSocket {
     }
 
     fn peer_addr(&self) -> Result<SocketAddr> {
-        let peer_addr = match &*self.state.read() {
+        let peer_addr = match &*self.read_state() {
             State::Connected(connected) => connected.peer_addr().cloned(),
             _ => return_errno_with_message!(Errno::ENOTCONN, "the socket is not connected"),
         };
@@ -301,44 +464,154 @@ impl Socket for UnixStreamSocket {
         Ok(peer_addr.into())
     }
 
+    fn get_option(&self, option: &mut dyn SocketOption) -> Result<()> {
+        // Like Linux, the options at the TCP level are not supported.
+        if is_tcp_option(&*option) {
+            return_errno_with_message!(Errno::EOPNOTSUPP, "the TCP options are not supported");
+        }
+
+        match_sock_option_mut!(option, {
+            socket_errors: SocketError => {
+                // The error is cleared after it is read.
+                let sock_errors = match &*self.read_state() {
+                    State::Connected(connected) => connected.take_error(),
+                    _ => self.connect_error.lock().take(),
+                };
+                socket_errors.set(sock_errors);
+            },
+            socket_linger: Linger => {
+                let linger = *self.linger.lock();
+                socket_linger.set(linger);
+            },
+            socket_reuse_addr: ReuseAddr => {
+                let reuse_addr = self.reuse_addr.load(Ordering::Relaxed);
+                socket_reuse_addr.set(reuse_addr);
+            },
+            socket_send_lowat: SendLowat => {
+                let send_lowat = match &*self.read_state() {
+                    State::Connected(connected) => connected.send_low_watermark(),
+                    _ => self.send_lowat.load(Ordering::Relaxed),
+                };
+                socket_send_lowat.set(send_lowat as u32);
+            },
+            socket_send_timeout: SendTimeout => {
+                let send_timeout = *self.send_timeout.lock();
+                socket_send_timeout.set(send_timeout);
+            },
+            socket_recv_timeout: RecvTimeout => {
+                let recv_timeout = *self.recv_timeout.lock();
+                socket_recv_timeout.set(recv_timeout);
+            },
+            socket_pass_cred: PassCred => {
+                let pass_cred = self.pass_cred.load(Ordering::Relaxed);
+                socket_pass_cred.set(pass_cred);
+            },
+            _ => return_errno_with_message!(Errno::ENOPROTOOPT, "the socket option to get is unknown")
+        });
+
+        Ok(())
+    }
+
+    fn set_option(&self, option: &dyn SocketOption) -> Result<()> {
+        // Like Linux, the options at the TCP level are not supported.
+        if is_tcp_option(option) {
+            return_errno_with_message!(Errno::EOPNOTSUPP, "the TCP options are not supported");
+        }
+
+        match_sock_option_ref!(option, {
+            socket_linger: Linger => {
+                let linger = socket_linger.get().unwrap();
+                *self.linger.lock() = *linger;
+            },
+            socket_reuse_addr: ReuseAddr => {
+                let reuse_addr = socket_reuse_addr.get().unwrap();
+                self.reuse_addr.store(*reuse_addr, Ordering::Relaxed);
+            },
+            socket_send_lowat: SendLowat => {
+                // Like Linux, a zero low watermark is treated as one.
+                let send_lowat = (*socket_send_lowat.get().unwrap() as usize).max(1);
+                self.send_lowat.store(send_lowat, Ordering::Relaxed);
+                if let State::Connected(connected) = &*self.read_state() {
+                    connected.set_send_low_watermark(send_lowat);
+                }
+            },
+            socket_send_timeout: SendTimeout => {
+                let send_timeout = socket_send_timeout.get().unwrap();
+                *self.send_timeout.lock() = *send_timeout;
+            },
+            socket_recv_timeout: RecvTimeout => {
+                let recv_timeout = socket_recv_timeout.get().unwrap();
+                *self.recv_timeout.lock() = *recv_timeout;
+            },
+            socket_pass_cred: PassCred => {
+                let pass_cred = socket_pass_cred.get().unwrap();
+                self.pass_cred.store(*pass_cred, Ordering::Relaxed);
+            },
+            _ => return_errno_with_message!(Errno::ENOPROTOOPT, "the socket option to be set is unknown")
+        });
+
+        Ok(())
+    }
+
     fn sendmsg(
         &self,
         io_vecs: &[IoVec],
         message_header: MessageHeader,
         flags: SendRecvFlags,
     ) -> Result<usize> {
-        // TODO: Deal with flags
-        debug_assert!(flags.is_all_supported());
+        // TODO: Deal with other flags
+        debug_assert!((flags - SendRecvFlags::MSG_OOB).is_all_supported());
 
         let MessageHeader {
-            control_message, ..
+            control_messages, ..
         } = message_header;
 
-        if control_message.is_some() {
-            // TODO: Support sending control message
-            warn!("sending control message is not supported");
+        let mut credentials = None;
+        let mut rights = None;
+        for control_message in control_messages {
+            match control_message {
+                ControlMessage::Credentials(new_credentials) => {
+                    new_credentials.check_current()?;
+                    credentials = Some(new_credentials);
+                }
+                // Like Linux, only the files in the last `SCM_RIGHTS` message are sent.
+                ControlMessage::Rights(new_rights) => {
+                    check_rights(&new_rights)?;
+                    rights = Some(new_rights);
+                }
+            }
         }
 
         let buf = copy_message_from_user(io_vecs);
 
-        self.send(&buf, flags)
+        self.send(&buf, credentials.as_ref(), rights.as_ref(), flags)
     }
 
     fn recvmsg(&self, io_vecs: &[IoVec], flags: SendRecvFlags) -> Result<(usize, MessageHeader)> {
-        // TODO: Deal with flags
-        debug_assert!(flags.is_all_supported());
+        // TODO: Deal with other flags
+        debug_assert!((flags - SendRecvFlags::MSG_OOB).is_all_supported());
 
         let mut buf = create_message_buffer(io_vecs);
-        let received_bytes = self.recv(&mut buf, flags)?;
+        let (received_bytes, rights) = self.recv(&mut buf, flags)?;
 
         let copied_bytes = {
             let message = &buf[..received_bytes];
             copy_message_to_user(io_vecs, message)
         };
 
-        // TODO: Receive control message
+        let control_messages = if received_bytes > 0 && !flags.contains(SendRecvFlags::MSG_OOB) {
+            // The credentials are consumed even if they are not received.
+            let credentials = self
+                .take_credentials()
+                .filter(|_| self.pass_cred.load(Ordering::Relaxed))
+                .map(ControlMessage::Credentials);
+            let rights = rights.map(ControlMessage::Rights);
+            credentials.into_iter().chain(rights).collect()
+        } else {
+            Vec::new()
+        };
 
-        let message_header = MessageHeader::new(None, None);
+        let message_header = MessageHeader::new(None, control_messages);
 
         Ok((copied_bytes, message_header))
     }
@@ -346,30 +619,15 @@ impl Socket for UnixStreamSocket {
 
 impl Drop for UnixStreamSocket {
     fn drop(&mut self) {
-        let Some(bound_addr) = self.bound_addr() else {
-            return;
-        };
-
-        if let State::Listen(_) = &*self.state.read() {
-            unregister_backlog(&bound_addr);
+        // Lingering may sleep, so it cannot be done with the state locked. Nor is the socket
+        // dropped with the file table locked (see `FileTable::close_file`).
+        let state = core::mem::replace(&mut *self.state.write(), State::Init(Init::new()));
+
+        match &state {
+            State::Connected(connected) => connected.close(&self.linger.lock()),
+            State::Connecting(connecting) => connecting.cancel(),
+            // The backlog of the listener is unregistered when the listener is dropped.
+            State::Listen(_) | State::Init(_) => (),
         }
     }
 }
-
-fn lookup_socket_file(path: &str) -> Result<Arc<Dentry>> {
-    let dentry = {
-        let current = current!();
-        let fs = current.fs().read();
-        let fs_path = FsPath::try_from(path)?;
-        fs.lookup(&fs_path)?
-    };
-
-    if dentry.type_() != InodeType::Socket {
-        return_errno_with_message!(Errno::ENOTSOCK, "not a socket file")
-    }
-
-    if !dentry.mode()?.is_readable() || !dentry.mode()?.is_writable() {
-        return_errno_with_message!(Errno::EACCES, "the socket cannot be read or written")
-    }
-    Ok(dentry)
-}
@@ -1,21 +1,26 @@
 // SPDX-License-Identifier: MPL-2.0
 
 use super::socket_addr::SocketAddr;
-use crate::{prelude::*, util::IoVec};
+use crate::{
+    fs::file_handle::FileLike,
+    prelude::*,
+    process::{credentials::capabilities::CapSet, posix_thread::PosixThreadExt, Gid, Pid, Uid},
+    util::IoVec,
+};
 
 /// Message header used for sendmsg/recvmsg.
 #[derive(Debug)]
 pub struct MessageHeader {
     pub(in crate::net) addr: Option<SocketAddr>,
-    pub(in crate::net) control_message: Option<ControlMessage>,
+    pub(in crate::net) control_messages: Vec<ControlMessage>,
 }
 
 impl MessageHeader {
     /// Creates a new `MessageHeader`.
-    pub const fn new(addr: Option<SocketAddr>, control_message: Option<ControlMessage>) -> Self {
+    pub const fn new(addr: Option<SocketAddr>, control_messages: Vec<ControlMessage>) -> Self {
         Self {
             addr,
-            control_message,
+            control_messages,
         }
     }
 
@@ -23,13 +28,119 @@ impl MessageHeader {
     pub fn addr(&self) -> Option<&SocketAddr> {
         self.addr.as_ref()
     }
+
+    /// Returns the control messages.
+    pub fn control_messages(&self) -> &[ControlMessage] {
+        &self.control_messages
+    }
 }
 
 /// Control message carried by MessageHeader.
+#[derive(Debug, Clone)]
+pub enum ControlMessage {
+    /// The credentials of the sending process (`SCM_CREDENTIALS`).
+    Credentials(UnixCredentials),
+    /// The files sent by the sending process (`SCM_RIGHTS`).
+    Rights(UnixRights),
+}
+
+/// The files carried by an `SCM_RIGHTS` control message.
 ///
-/// TODO: Implement the struct. The struct is empty now.
-#[derive(Debug)]
-pub struct ControlMessage;
+/// The files are kept open while the message is in flight. The files that are not
+/// installed in the file table of the receiving process are closed when the message
+/// is dropped.
+#[derive(Clone)]
+pub struct UnixRights {
+    files: Vec<Arc<dyn FileLike>>,
+}
+
+impl UnixRights {
+    /// The maximum number of files in a message (`SCM_MAX_FD` in Linux).
+    pub const MAX_FILES: usize = 253;
+
+    /// Creates a message with the files.
+    pub fn new(files: Vec<Arc<dyn FileLike>>) -> Self {
+        debug_assert!(!files.is_empty() && files.len() <= Self::MAX_FILES);
+        Self { files }
+    }
+
+    /// Returns the files.
+    pub fn files(&self) -> &[Arc<dyn FileLike>] {
+        &self.files
+    }
+}
+
+impl Debug for UnixRights {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("UnixRights")
+            .field("nr_files", &self.files.len())
+            .finish()
+    }
+}
+
+/// The credentials carried by an `SCM_CREDENTIALS` control message.
+///
+/// The layout is the same as `struct ucred` in Linux.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+pub struct UnixCredentials {
+    pub pid: Pid,
+    pub uid: Uid,
+    pub gid: Gid,
+}
+
+impl UnixCredentials {
+    /// Returns the credentials of the current process.
+    ///
+    /// Like Linux, they consist of the PID, the real user ID and the real group ID.
+    pub fn current() -> Self {
+        let current = current_thread!();
+        let posix_thread = current.as_posix_thread().unwrap();
+        let credentials = posix_thread.credentials();
+
+        Self {
+            pid: posix_thread.process().pid(),
+            uid: credentials.ruid(),
+            gid: credentials.rgid(),
+        }
+    }
+
+    /// Checks whether the current process is allowed to send the credentials.
+    ///
+    /// A process can only claim its own PID unless it has `CAP_SYS_ADMIN`, its real, effective
+    /// or saved-set user ID unless it has `CAP_SETUID`, and its real, effective or saved-set
+    /// group ID unless it has `CAP_SETGID`. Otherwise, `EPERM` is returned.
+    ///
+    /// Reference: <https://elixir.bootlin.com/linux/v6.10.4/source/net/core/scm.c#L55>.
+    pub fn check_current(&self) -> Result<()> {
+        let current = current_thread!();
+        let posix_thread = current.as_posix_thread().unwrap();
+        let credentials = posix_thread.credentials();
+        let capset = credentials.effective_capset();
+
+        if self.pid != posix_thread.process().pid() && !capset.contains(CapSet::SYS_ADMIN) {
+            return_errno_with_message!(Errno::EPERM, "the PID does not belong to the sender");
+        }
+
+        if self.uid != credentials.ruid()
+            && self.uid != credentials.euid()
+            && self.uid != credentials.suid()
+            && !capset.contains(CapSet::SETUID)
+        {
+            return_errno_with_message!(Errno::EPERM, "the UID does not belong to the sender");
+        }
+
+        if self.gid != credentials.rgid()
+            && self.gid != credentials.egid()
+            && self.gid != credentials.sgid()
+            && !capset.contains(CapSet::SETGID)
+        {
+            return_errno_with_message!(Errno::EPERM, "the GID does not belong to the sender");
+        }
+
+        Ok(())
+    }
+}
 
 /// Copies a message from user space.
 ///
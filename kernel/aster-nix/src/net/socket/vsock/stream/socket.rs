@@ -287,10 +287,10 @@ impl Socket for VsockStreamSocket {
         debug_assert!(flags.is_all_supported());
 
         let MessageHeader {
-            control_message, ..
+            control_messages, ..
         } = message_header;
 
-        if control_message.is_some() {
+        if !control_messages.is_empty() {
             // TODO: Support sending control message
             warn!("sending control message is not supported");
         }
@@ -314,7 +314,7 @@ impl Socket for VsockStreamSocket {
 
         // TODO: Receive control message
 
-        let messsge_header = MessageHeader::new(None, None);
+        let messsge_header = MessageHeader::new(None, Vec::new());
 
         Ok((copied_bytes, messsge_header))
     }
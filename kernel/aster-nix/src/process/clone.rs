@@ -102,7 +102,8 @@ impl From<u64> for CloneFlags {
 }
 
 impl CloneFlags {
-    fn check_unsupported_flags(&self) -> Result<()> {
+    /// Checks whether the flags are supported and consistent with each other.
+    fn check_flags(&self) -> Result<()> {
         let supported_flags = CloneFlags::CLONE_VM
             | CloneFlags::CLONE_FS
             | CloneFlags::CLONE_FILES
@@ -115,8 +116,30 @@ impl CloneFlags {
             | CloneFlags::CLONE_CHILD_CLEARTID;
         let unsupported_flags = *self - supported_flags;
         if !unsupported_flags.is_empty() {
-            panic!("contains unsupported clone flags: {:?}", unsupported_flags);
+            warn!("contains unsupported clone flags: {:?}", unsupported_flags);
+            return_errno_with_message!(Errno::EINVAL, "contains unsupported clone flags");
         }
+
+        // The threads in a thread group share the signal handlers, and the
+        // signal handlers can only be shared in the same address space.
+        if self.contains(CloneFlags::CLONE_THREAD) && !self.contains(CloneFlags::CLONE_SIGHAND) {
+            return_errno_with_message!(Errno::EINVAL, "CLONE_THREAD requires CLONE_SIGHAND");
+        }
+        if self.contains(CloneFlags::CLONE_SIGHAND) && !self.contains(CloneFlags::CLONE_VM) {
+            return_errno_with_message!(Errno::EINVAL, "CLONE_SIGHAND requires CLONE_VM");
+        }
+
+        // The file table and the file system information are kept per process. So the
+        // threads in a thread group cannot have their own copies.
+        if self.contains(CloneFlags::CLONE_THREAD)
+            && !self.contains(CloneFlags::CLONE_FILES | CloneFlags::CLONE_FS)
+        {
+            return_errno_with_message!(
+                Errno::EINVAL,
+                "CLONE_THREAD without CLONE_FILES or CLONE_FS is not supported"
+            );
+        }
+
         Ok(())
     }
 }
@@ -130,7 +153,7 @@ pub fn clone_child(
     parent_context: &UserContext,
     clone_args: CloneArgs,
 ) -> Result<Tid> {
-    clone_args.clone_flags.check_unsupported_flags()?;
+    clone_args.clone_flags.check_flags()?;
     if clone_args.clone_flags.contains(CloneFlags::CLONE_THREAD) {
         let child_thread = clone_child_thread(ctx, parent_context, clone_args)?;
         child_thread.run();
@@ -159,9 +182,12 @@ fn clone_child_thread(
     } = ctx;
 
     let clone_flags = clone_args.clone_flags;
-    debug_assert!(clone_flags.contains(CloneFlags::CLONE_VM));
-    debug_assert!(clone_flags.contains(CloneFlags::CLONE_FILES));
-    debug_assert!(clone_flags.contains(CloneFlags::CLONE_SIGHAND));
+    debug_assert!(clone_flags.contains(
+        CloneFlags::CLONE_VM
+            | CloneFlags::CLONE_FS
+            | CloneFlags::CLONE_FILES
+            | CloneFlags::CLONE_SIGHAND
+    ));
     let child_root_vmar = process.root_vmar();
 
     let child_user_space = {
@@ -180,7 +206,7 @@ fn clone_child_thread(
     // Inherit sigmask from current thread
     let sig_mask = posix_thread.sig_mask().load(Ordering::Relaxed).into();
 
-    let child_tid = allocate_tid();
+    let child_tid = allocate_tid()?;
     let child_thread = {
         let credentials = {
             let credentials = ctx.posix_thread.credentials();
@@ -193,7 +219,16 @@ fn clone_child_thread(
         thread_builder.build()
     };
 
-    process.threads().lock().push(child_thread.clone());
+    // Attach the child to the process, unless the process is exiting. The thread list stays
+    // locked during the check, so an exiting process either sees the child or never has it.
+    {
+        let mut threads = process.threads().lock();
+        if process.is_zombie() {
+            thread_table::remove_thread(child_tid);
+            return_errno_with_message!(Errno::EINTR, "the process is exiting");
+        }
+        threads.push(child_thread.clone());
+    }
 
     let child_posix_thread = child_thread.as_posix_thread().unwrap();
     clone_parent_settid(child_tid, clone_args.parent_tidptr, clone_flags)?;
@@ -262,13 +297,13 @@ fn clone_child_process(
     // inherit parent's nice value
     let child_nice = process.nice().load(Ordering::Relaxed);
 
-    let child_tid = allocate_tid();
+    let child_elf_path = process.executable_path();
+    let child_thread_name = ThreadName::new_from_executable_path(&child_elf_path)?;
+
+    let child_tid = allocate_tid()?;
 
     let child = {
-        let child_elf_path = process.executable_path();
         let child_thread_builder = {
-            let child_thread_name = ThreadName::new_from_executable_path(&child_elf_path)?;
-
             let credentials = {
                 let credentials = ctx.posix_thread.credentials();
                 Credentials::new_from(&credentials)
@@ -361,10 +396,10 @@ fn clone_cpu_context(
     // The return value of child thread is zero
     child_context.set_syscall_ret(0);
 
-    if clone_flags.contains(CloneFlags::CLONE_VM) {
-        // if parent and child shares the same address space, a new stack must be specified.
-        debug_assert!(new_sp != 0);
-    }
+    // If `new_sp` is zero, the child runs on the same stack as the parent. This is
+    // allowed even if the child shares the address space with the parent (i.e.,
+    // `CLONE_VM` is set), in which case the parent must not touch the stack until
+    // the child exits or executes a new program, as `vfork` does.
     if new_sp != 0 {
         // If stack size is not 0, the `new_sp` points to the BOTTOMMOST byte of stack.
         if stack_size != 0 {
@@ -441,3 +476,118 @@ fn set_parent_and_group(parent: &Process, child: &Arc<Process>) {
 
     process_table_mut.insert(child.pid(), child.clone());
 }
+
+#[cfg(ktest)]
+mod test {
+    use aster_rights::Full;
+    use ostd::{
+        mm::{Frame, VmIo, VmItem, PAGE_SIZE},
+        prelude::*,
+    };
+
+    use super::*;
+    use crate::vm::{
+        perms::VmPerms,
+        vmar::Vmar,
+        vmo::{VmoOptions, VmoRightsOp},
+    };
+
+    const OFFSET: usize = 0x1000_0000;
+
+    fn mapped_frame(vmar: &Vmar<Full>, addr: Vaddr) -> Frame {
+        let mut cursor = vmar.vm_space().cursor(&(addr..addr + PAGE_SIZE)).unwrap();
+        let VmItem::Mapped { frame, .. } = cursor.query().unwrap() else {
+            panic!("the page is not mapped");
+        };
+        frame
+    }
+
+    /// Creates a process VM with a private writable page at `OFFSET`.
+    fn new_process_vm() -> ProcessVm {
+        let process_vm = ProcessVm::alloc();
+        let vmo = VmoOptions::<Full>::new(PAGE_SIZE).alloc().unwrap().to_dyn();
+        process_vm
+            .root_vmar()
+            .new_map(PAGE_SIZE, VmPerms::READ | VmPerms::WRITE)
+            .unwrap()
+            .vmo(vmo)
+            .offset(OFFSET)
+            .build()
+            .unwrap();
+        process_vm
+            .root_vmar()
+            .handle_page_fault(OFFSET, true, true)
+            .unwrap();
+        process_vm
+    }
+
+    #[ktest]
+    fn clone_vm_shared() {
+        let parent_vm = new_process_vm();
+        let child_vm = clone_vm(&parent_vm, CloneFlags::CLONE_VM).unwrap();
+        assert!(Arc::ptr_eq(
+            parent_vm.root_vmar().vm_space(),
+            child_vm.root_vmar().vm_space()
+        ));
+
+        // A write by the child is seen by the parent, and vice versa.
+        mapped_frame(child_vm.root_vmar(), OFFSET)
+            .write_val(0, &1u32)
+            .unwrap();
+        let parent_frame = mapped_frame(parent_vm.root_vmar(), OFFSET);
+        assert_eq!(parent_frame.read_val::<u32>(0).unwrap(), 1);
+        parent_frame.write_val(0, &2u32).unwrap();
+        assert_eq!(
+            mapped_frame(child_vm.root_vmar(), OFFSET)
+                .read_val::<u32>(0)
+                .unwrap(),
+            2
+        );
+    }
+
+    #[ktest]
+    fn clone_vm_private() {
+        let parent_vm = new_process_vm();
+        mapped_frame(parent_vm.root_vmar(), OFFSET)
+            .write_val(0, &1u32)
+            .unwrap();
+
+        let child_vm = clone_vm(&parent_vm, CloneFlags::empty()).unwrap();
+        child_vm
+            .root_vmar()
+            .handle_page_fault(OFFSET, true, true)
+            .unwrap();
+
+        // The child sees the memory of the parent at the time of the fork, but
+        // its writes are not seen by the parent.
+        let child_frame = mapped_frame(child_vm.root_vmar(), OFFSET);
+        assert_eq!(child_frame.read_val::<u32>(0).unwrap(), 1);
+        child_frame.write_val(0, &2u32).unwrap();
+        assert_eq!(
+            mapped_frame(parent_vm.root_vmar(), OFFSET)
+                .read_val::<u32>(0)
+                .unwrap(),
+            1
+        );
+    }
+
+    #[ktest]
+    fn check_flags() {
+        let thread_flags = CloneFlags::CLONE_VM
+            | CloneFlags::CLONE_FS
+            | CloneFlags::CLONE_FILES
+            | CloneFlags::CLONE_SIGHAND
+            | CloneFlags::CLONE_THREAD;
+        assert!(thread_flags.check_flags().is_ok());
+        // `CLONE_VM` without `CLONE_THREAD` creates a process sharing the address space.
+        assert!(CloneFlags::CLONE_VM.check_flags().is_ok());
+
+        let errno_of = |flags: CloneFlags| flags.check_flags().unwrap_err().error();
+        assert_eq!(
+            errno_of(thread_flags - CloneFlags::CLONE_SIGHAND),
+            Errno::EINVAL
+        );
+        assert_eq!(errno_of(CloneFlags::CLONE_SIGHAND), Errno::EINVAL);
+        assert_eq!(errno_of(CloneFlags::CLONE_NEWNET), Errno::EINVAL);
+    }
+}
@@ -43,23 +43,42 @@ pub fn do_exit_group(term_status: TermStatus) {
     drop(files);
 
     // Move children to the init process
-    if !is_init_process(&current) {
-        if let Some(init_process) = get_init_process() {
-            let mut init_children = init_process.children().lock();
-            for (_, child_process) in current.children().lock().extract_if(|_, _| true) {
-                let mut parent = child_process.parent.lock();
-                init_children.insert(child_process.pid(), child_process.clone());
-                *parent = Arc::downgrade(&init_process);
-            }
-        }
+    if !is_init_process(&current)
+        && let Some(init_process) = get_init_process()
+        && reparent_children(&current, &init_process)
+    {
+        // The orphaned zombies are reaped by the init process.
+        notify_parent(&init_process);
     }
 
     if let Some(parent) = current.parent() {
-        // Notify parent
-        let signal = KernelSignal::new(SIGCHLD);
-        parent.enqueue_signal(signal);
-        parent.children_pauser().resume_all();
+        notify_parent(&parent);
+    }
+}
+
+/// Moves all the children of `process` to `new_parent`.
+///
+/// Returns whether any of the moved children is a zombie, in which case `new_parent`
+/// should be notified to reap it.
+pub(super) fn reparent_children(process: &Process, new_parent: &Arc<Process>) -> bool {
+    let mut has_zombie = false;
+
+    let mut new_children = new_parent.children().lock();
+    for (_, child_process) in process.children().lock().extract_if(|_, _| true) {
+        let mut parent = child_process.parent.lock();
+        has_zombie |= child_process.is_zombie();
+        new_children.insert(child_process.pid(), child_process.clone());
+        *parent = Arc::downgrade(new_parent);
     }
+
+    has_zombie
+}
+
+/// Notifies `parent` that the status of a child has changed.
+fn notify_parent(parent: &Process) {
+    let signal = KernelSignal::new(SIGCHLD);
+    parent.enqueue_signal(signal);
+    parent.children_pauser().resume_all();
 }
 
 const INIT_PROCESS_PID: Pid = 1;
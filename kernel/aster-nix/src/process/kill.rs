@@ -75,7 +75,7 @@ pub fn tgkill(tid: Tid, tgid: Pid, signal: Option<UserSignal>) -> Result<()> {
     posix_thread.check_signal_perm(signum.as_ref(), &sender)?;
 
     if let Some(signal) = signal {
-        posix_thread.enqueue_signal(Box::new(signal));
+        posix_thread.try_enqueue_signal(Box::new(signal))?;
     }
 
     Ok(())
@@ -100,41 +100,24 @@ pub fn kill_all(signal: Option<UserSignal>) -> Result<()> {
 }
 
 fn kill_process(process: &Process, signal: Option<UserSignal>) -> Result<()> {
-    let threads = process.threads().lock();
-    let posix_threads = threads
-        .iter()
-        .map(|thread| thread.as_posix_thread().unwrap());
-
     // First check permission
     let signum = signal.map(|signal| signal.num());
     let sender_ids = current_thread_sender_ids();
-    let mut permitted_threads = {
-        posix_threads.clone().filter(|posix_thread| {
-            posix_thread
-                .check_signal_perm(signum.as_ref(), &sender_ids)
-                .is_ok()
-        })
-    };
-
-    if permitted_threads.clone().count() == 0 {
+    let is_permitted = process.threads().lock().iter().any(|thread| {
+        thread
+            .as_posix_thread()
+            .unwrap()
+            .check_signal_perm(signum.as_ref(), &sender_ids)
+            .is_ok()
+    });
+
+    if !is_permitted {
         return_errno_with_message!(Errno::EPERM, "cannot send signal to the target process");
     }
 
     let Some(signal) = signal else { return Ok(()) };
 
-    // Send signal to any thread that does not blocks the signal.
-    for thread in permitted_threads.clone() {
-        if !thread.has_signal_blocked(&signal) {
-            thread.enqueue_signal(Box::new(signal));
-            return Ok(());
-        }
-    }
-
-    // If all threads block the signal, send signal to the first thread.
-    let first_thread = permitted_threads.next().unwrap();
-    first_thread.enqueue_signal(Box::new(signal));
-
-    Ok(())
+    process.try_enqueue_signal(Box::new(signal))
 }
 
 fn current_thread_sender_ids() -> SignalSenderIds {
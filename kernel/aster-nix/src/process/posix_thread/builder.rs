@@ -2,6 +2,8 @@
 
 #![allow(dead_code)]
 
+use core::sync::atomic::Ordering;
+
 use ostd::user::UserSpace;
 
 use super::PosixThread;
@@ -12,6 +14,7 @@ use crate::{
         signal::{sig_mask::AtomicSigMask, sig_queues::SigQueues},
         Credentials, Process,
     },
+    sched::nice_to_priority,
     thread::{status::ThreadStatus, task, thread_table, Thread, Tid},
     time::{clocks::ProfClock, TimerManager},
 };
@@ -85,8 +88,19 @@ impl PosixThreadBuilder {
             sig_queues,
         } = self;
 
+        // The new thread starts with the normal policy, so its priority follows the
+        // nice value of the process.
+        let nice = process
+            .upgrade()
+            .map(|process| process.nice().load(Ordering::Relaxed))
+            .unwrap_or_default();
+
         let thread = Arc::new_cyclic(|thread_ref| {
             let task = task::create_new_user_task(user_space, thread_ref.clone());
+            if let Some(thread_name) = &thread_name {
+                task.set_name(&thread_name.to_string_lossy());
+            }
+            task.set_priority(nice_to_priority(nice));
             let status = ThreadStatus::Init;
 
             let prof_clock = ProfClock::new();
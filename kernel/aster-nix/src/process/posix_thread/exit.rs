@@ -1,6 +1,9 @@
 // SPDX-License-Identifier: MPL-2.0
 
-use super::{futex::futex_wake, robust_list::wake_robust_futex, PosixThread, PosixThreadExt};
+use super::{
+    futex::{futex_wake, futex_wake_robust},
+    PosixThread, PosixThreadExt, RobustListHead,
+};
 use crate::{
     prelude::*,
     process::{do_exit_group, TermStatus},
@@ -25,7 +28,8 @@ pub fn do_exit(thread: Arc<Thread>, term_status: TermStatus) -> Result<()> {
     let mut clear_ctid = posix_thread.clear_child_tid().lock();
     // If clear_ctid !=0 ,do a futex wake and write zero to the clear_ctid addr.
     if *clear_ctid != 0 {
-        futex_wake(*clear_ctid, 1)?;
+        // The thread is exiting anyway, so a bad address is simply ignored.
+        let _ = futex_wake(*clear_ctid, 1, false);
         // FIXME: the correct write length?
         CurrentUserSpace::get()
             .write_val(*clear_ctid, &0u32)
@@ -41,26 +45,30 @@ pub fn do_exit(thread: Arc<Thread>, term_status: TermStatus) -> Result<()> {
         thread_table::remove_thread(tid);
     }
 
-    if posix_thread.is_main_thread(tid) || posix_thread.is_last_thread() {
-        // exit current process.
+    // The process lives on if the main thread exits while other threads are running. It
+    // exits when its last thread exits.
+    if posix_thread.is_last_thread() {
         do_exit_group(term_status);
     }
 
-    futex_wake(Arc::as_ptr(&posix_thread.process()) as Vaddr, 1)?;
     Ok(())
 }
 
 /// Walks the robust futex list, marking futex dead and wake waiters.
 /// It corresponds to Linux's exit_robust_list(), errors are silently ignored.
 fn wake_robust_list(thread: &PosixThread, tid: Tid) {
-    let mut robust_list = thread.robust_list.lock();
-    let list_head = match *robust_list {
-        Some(robust_list_head) => robust_list_head,
-        None => return,
+    let Some(head_addr) = thread.robust_list.lock().take() else {
+        return;
+    };
+
+    let user_space = CurrentUserSpace::get();
+    let Ok(list_head) = user_space.read_val::<RobustListHead>(head_addr) else {
+        return;
     };
     trace!("wake the rubust_list: {:?}", list_head);
-    for futex_addr in list_head.futexes() {
-        wake_robust_futex(futex_addr, tid).unwrap();
+
+    let read_next = |entry_ptr: Vaddr| user_space.read_val::<Vaddr>(entry_ptr);
+    for futex_addr in list_head.futexes(head_addr, read_next) {
+        let _ = futex_wake_robust(futex_addr, tid);
     }
-    *robust_list = None;
 }
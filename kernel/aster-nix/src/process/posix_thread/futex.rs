@@ -9,7 +9,7 @@ use ostd::{
 };
 use spin::Once;
 
-use crate::prelude::*;
+use crate::{prelude::*, thread::Tid};
 
 type FutexBitSet = u32;
 type FutexBucketRef = Arc<Mutex<FutexBucket>>;
@@ -18,9 +18,24 @@ const FUTEX_OP_MASK: u32 = 0x0000_000F;
 const FUTEX_FLAGS_MASK: u32 = 0xFFFF_FFF0;
 const FUTEX_BITSET_MATCH_ANY: FutexBitSet = 0xFFFF_FFFF;
 
+const FUTEX_WAITERS: u32 = 0x8000_0000;
+const FUTEX_OWNER_DIED: u32 = 0x4000_0000;
+const FUTEX_TID_MASK: u32 = 0x3FFF_FFFF;
+
 /// do futex wait
-pub fn futex_wait(futex_addr: u64, futex_val: i32, timeout: &Option<FutexTimeout>) -> Result<()> {
-    futex_wait_bitset(futex_addr as _, futex_val, timeout, FUTEX_BITSET_MATCH_ANY)
+pub fn futex_wait(
+    futex_addr: u64,
+    futex_val: i32,
+    timeout: &Option<FutexTimeout>,
+    is_private: bool,
+) -> Result<()> {
+    futex_wait_bitset(
+        futex_addr as _,
+        futex_val,
+        timeout,
+        FUTEX_BITSET_MATCH_ANY,
+        is_private,
+    )
 }
 
 /// do futex wait bitset
@@ -29,19 +44,34 @@ pub fn futex_wait_bitset(
     futex_val: i32,
     timeout: &Option<FutexTimeout>,
     bitset: FutexBitSet,
+    is_private: bool,
 ) -> Result<()> {
     debug!(
         "futex_wait_bitset addr: {:#x}, val: {}, timeout: {:?}, bitset: {:#x}",
         futex_addr, futex_val, timeout, bitset
     );
-    let futex_key = FutexKey::new(futex_addr, bitset);
+    let futex_key = FutexKey::new(FutexAddr::new(futex_addr, is_private)?, bitset);
+    futex_wait_on_key(futex_key, futex_val, || {
+        CurrentUserSpace::get().read_val(futex_addr)
+    })
+}
+
+/// Waits on the futex with `futex_key` if the futex value loaded by
+/// `load_val` equals `futex_val`.
+fn futex_wait_on_key(
+    futex_key: FutexKey,
+    futex_val: i32,
+    load_val: impl FnOnce() -> Result<i32>,
+) -> Result<()> {
     let (futex_item, waiter) = FutexItem::create(futex_key);
 
     let (_, futex_bucket_ref) = get_futex_bucket(futex_key);
     // lock futex bucket ref here to avoid data race
     let mut futex_bucket = futex_bucket_ref.lock();
 
-    if futex_key.load_val() != futex_val {
+    // The value must be checked again with the bucket locked. Otherwise, a
+    // wakeup between the check and the enqueuing of the item would be lost.
+    if load_val()? != futex_val {
         return_errno_with_message!(Errno::EAGAIN, "futex value does not match");
     }
 
@@ -57,8 +87,8 @@ pub fn futex_wait_bitset(
 }
 
 /// do futex wake
-pub fn futex_wake(futex_addr: Vaddr, max_count: usize) -> Result<usize> {
-    futex_wake_bitset(futex_addr, max_count, FUTEX_BITSET_MATCH_ANY)
+pub fn futex_wake(futex_addr: Vaddr, max_count: usize, is_private: bool) -> Result<usize> {
+    futex_wake_bitset(futex_addr, max_count, FUTEX_BITSET_MATCH_ANY, is_private)
 }
 
 /// Do futex wake with bitset
@@ -66,19 +96,66 @@ pub fn futex_wake_bitset(
     futex_addr: Vaddr,
     max_count: usize,
     bitset: FutexBitSet,
+    is_private: bool,
 ) -> Result<usize> {
     debug!(
         "futex_wake_bitset addr: {:#x}, max_count: {}, bitset: {:#x}",
         futex_addr, max_count, bitset
     );
 
-    let futex_key = FutexKey::new(futex_addr, bitset);
+    let futex_key = FutexKey::new(FutexAddr::new(futex_addr, is_private)?, bitset);
+    Ok(futex_wake_on_key(futex_key, max_count))
+}
+
+/// Wakes at most `max_count` waiters of the futex with `futex_key`.
+fn futex_wake_on_key(futex_key: FutexKey, max_count: usize) -> usize {
     let (_, futex_bucket_ref) = get_futex_bucket(futex_key);
     let mut futex_bucket = futex_bucket_ref.lock();
-    let res = futex_bucket.remove_and_wake_items(futex_key, max_count);
-    drop(futex_bucket);
+    futex_bucket.remove_and_wake_items(futex_key, max_count)
+}
+
+/// Marks the robust futex at `futex_addr` as dead if it is held by the thread
+/// of `tid`, and wakes one waiter of the futex, if any.
+pub fn futex_wake_robust(futex_addr: Vaddr, tid: Tid) -> Result<()> {
+    // The robust futexes may be shared with other processes.
+    let futex_key = FutexKey::new(FutexAddr::new(futex_addr, false)?, FUTEX_BITSET_MATCH_ANY);
+    let user_space = CurrentUserSpace::get();
+    futex_wake_robust_on_key(
+        futex_key,
+        tid,
+        || user_space.read_val(futex_addr),
+        |val| user_space.write_val(futex_addr, &val),
+    )
+}
+
+/// Marks the robust futex with `futex_key` as dead if it is held by the thread
+/// of `tid`, and wakes one waiter of the futex, if any.
+fn futex_wake_robust_on_key(
+    futex_key: FutexKey,
+    tid: Tid,
+    load_val: impl FnOnce() -> Result<u32>,
+    store_val: impl FnOnce(u32) -> Result<()>,
+) -> Result<()> {
+    let (_, futex_bucket_ref) = get_futex_bucket(futex_key);
+    // The futex word is updated with the bucket locked, so a waiter that is
+    // checking the word cannot miss the wakeup.
+    let mut futex_bucket = futex_bucket_ref.lock();
+
+    let futex_val = load_val()?;
+    // The futex is held by another thread, or is not held at all.
+    if futex_val & FUTEX_TID_MASK != tid {
+        return Ok(());
+    }
 
-    Ok(res)
+    // FIXME: The user space may change the futex word without locking the
+    // bucket, so the update should be an atomic compare-and-exchange.
+    store_val((futex_val & FUTEX_WAITERS) | FUTEX_OWNER_DIED)?;
+
+    if futex_val & FUTEX_WAITERS != 0 {
+        futex_bucket.remove_and_wake_items(futex_key, 1);
+    }
+
+    Ok(())
 }
 
 /// Do futex requeue
@@ -87,13 +164,20 @@ pub fn futex_requeue(
     max_nwakes: usize,
     max_nrequeues: usize,
     futex_new_addr: Vaddr,
+    is_private: bool,
 ) -> Result<usize> {
     if futex_new_addr == futex_addr {
-        return futex_wake(futex_addr, max_nwakes);
+        return futex_wake(futex_addr, max_nwakes, is_private);
     }
 
-    let futex_key = FutexKey::new(futex_addr, FUTEX_BITSET_MATCH_ANY);
-    let futex_new_key = FutexKey::new(futex_new_addr, FUTEX_BITSET_MATCH_ANY);
+    let futex_key = FutexKey::new(
+        FutexAddr::new(futex_addr, is_private)?,
+        FUTEX_BITSET_MATCH_ANY,
+    );
+    let futex_new_key = FutexKey::new(
+        FutexAddr::new(futex_new_addr, is_private)?,
+        FUTEX_BITSET_MATCH_ANY,
+    );
     let (bucket_idx, futex_bucket_ref) = get_futex_bucket(futex_key);
     let (new_bucket_idx, futex_new_bucket_ref) = get_futex_bucket(futex_new_key);
 
@@ -176,12 +260,7 @@ impl FutexBucketVec {
     }
 
     pub fn get_bucket(&self, key: FutexKey) -> (usize, FutexBucketRef) {
-        let index = (self.vec.len() - 1) & {
-            // The addr is the multiples of 4, so we ignore the last 2 bits
-            let addr = key.addr() >> 2;
-            // simple hash
-            addr / self.size()
-        };
+        let index = (self.vec.len() - 1) & key.addr().hash();
         (index, self.vec[index].clone())
     }
 
@@ -315,25 +394,70 @@ impl FutexItem {
     }
 }
 
-// The addr of a futex, it should be used to mark different futex word
+/// The identity of a futex word.
+///
+/// Futexes are not keyed by the physical addresses of the futex words, since
+/// the frame that backs a word changes if the page is copied on write or is
+/// remapped, and the untouched private pages may all share the zero frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FutexAddr {
+    /// A private futex, which is identified by the address space and the
+    /// virtual address of the futex word.
+    Private { vm_space: usize, addr: Vaddr },
+    /// A futex in shared memory, which is identified by the VMO and the offset
+    /// of the futex word in the VMO. So the processes that share the memory
+    /// can synchronize with each other even if the memory is mapped at
+    /// different virtual addresses.
+    Shared { vmo: usize, offset: usize },
+}
+
+impl FutexAddr {
+    /// Returns the identity of the futex word at `futex_addr` in the current
+    /// user space.
+    ///
+    /// If `is_private` is true, the futex word is assumed to be accessed only
+    /// by the threads of the current process. Otherwise, the futex word is
+    /// identified by the shared memory that contains it, unless it is in a
+    /// private mapping.
+    fn new(futex_addr: Vaddr, is_private: bool) -> Result<Self> {
+        if futex_addr % core::mem::align_of::<u32>() != 0 {
+            return_errno_with_message!(Errno::EINVAL, "the futex address is not aligned");
+        }
+
+        let current = current!();
+        let root_vmar = current.root_vmar();
+        if !is_private && let Some((vmo, offset)) = root_vmar.shared_memory_key(futex_addr) {
+            return Ok(Self::Shared { vmo, offset });
+        }
+
+        Ok(Self::Private {
+            vm_space: Arc::as_ptr(root_vmar.vm_space()) as usize,
+            addr: futex_addr,
+        })
+    }
+
+    fn hash(&self) -> usize {
+        let (object, offset) = match *self {
+            Self::Private { vm_space, addr } => (vm_space, addr),
+            Self::Shared { vmo, offset } => (vmo, offset),
+        };
+        // The offset is a multiple of 4, so we ignore the last 2 bits.
+        (offset >> 2) ^ (object >> 4).wrapping_mul(0x9E37_79B9)
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 struct FutexKey {
-    addr: Vaddr,
+    addr: FutexAddr,
     bitset: FutexBitSet,
 }
 
 impl FutexKey {
-    pub fn new(addr: Vaddr, bitset: FutexBitSet) -> Self {
+    pub fn new(addr: FutexAddr, bitset: FutexBitSet) -> Self {
         Self { addr, bitset }
     }
 
-    pub fn load_val(&self) -> i32 {
-        // FIXME: how to implement a atomic load?
-        warn!("implement an atomic load");
-        CurrentUserSpace::get().read_val(self.addr).unwrap()
-    }
-
-    pub fn addr(&self) -> Vaddr {
+    pub fn addr(&self) -> FutexAddr {
         self.addr
     }
 
@@ -408,3 +532,100 @@ pub fn futex_op_and_flags_from_u32(bits: u32) -> Result<(FutexOp, FutexFlags)> {
     };
     Ok((op, flags))
 }
+
+#[cfg(ktest)]
+mod test {
+    use core::sync::atomic::{AtomicI32, AtomicU32, Ordering};
+
+    use ostd::prelude::*;
+
+    use super::*;
+    use crate::thread::{
+        kernel_thread::{KernelThreadExt, ThreadOptions},
+        Thread,
+    };
+
+    /// Returns a private futex address for a futex word in the kernel.
+    fn test_addr<T>(word: &T) -> FutexAddr {
+        FutexAddr::Private {
+            vm_space: 0,
+            addr: word as *const T as Vaddr,
+        }
+    }
+
+    #[ktest]
+    fn wait_with_mismatched_value() {
+        init();
+        let word = AtomicI32::new(1);
+        let key = FutexKey::new(test_addr(&word), FUTEX_BITSET_MATCH_ANY);
+
+        let err = futex_wait_on_key(key, 0, || Ok(word.load(Ordering::Relaxed))).unwrap_err();
+        assert_eq!(err.error(), Errno::EAGAIN);
+    }
+
+    #[ktest]
+    fn wait_and_wake() {
+        init();
+        let word = Arc::new(AtomicI32::new(0));
+        let key = FutexKey::new(test_addr(&*word), FUTEX_BITSET_MATCH_ANY);
+
+        let word_cloned = word.clone();
+        Thread::spawn_kernel_thread(ThreadOptions::new(move || {
+            Thread::yield_now();
+            word_cloned.store(1, Ordering::Relaxed);
+            futex_wake_on_key(key, 1);
+        }));
+
+        // If the value is changed before the waiter is enqueued, `EAGAIN` is
+        // returned instead of sleeping forever.
+        while word.load(Ordering::Relaxed) == 0 {
+            let _ = futex_wait_on_key(key, 0, || Ok(word.load(Ordering::Relaxed)));
+        }
+        assert_eq!(word.load(Ordering::Relaxed), 1);
+    }
+
+    #[ktest]
+    fn wake_robust() {
+        const TID: Tid = 42;
+
+        init();
+        let word = Arc::new(AtomicU32::new(TID | FUTEX_WAITERS));
+        let key = FutexKey::new(test_addr(&*word), FUTEX_BITSET_MATCH_ANY);
+
+        // The waiter is woken once the owner dies.
+        let word_cloned = word.clone();
+        let waiter = Thread::spawn_kernel_thread(ThreadOptions::new(move || {
+            while word_cloned.load(Ordering::Relaxed) & FUTEX_OWNER_DIED == 0 {
+                let _ = futex_wait_on_key(key, (TID | FUTEX_WAITERS) as i32, || {
+                    Ok(word_cloned.load(Ordering::Relaxed) as i32)
+                });
+            }
+        }));
+
+        Thread::yield_now();
+        let wake_robust = |tid| {
+            futex_wake_robust_on_key(
+                key,
+                tid,
+                || Ok(word.load(Ordering::Relaxed)),
+                |val| {
+                    word.store(val, Ordering::Relaxed);
+                    Ok(())
+                },
+            )
+            .unwrap()
+        };
+
+        // The futex is not held by the thread.
+        wake_robust(TID + 1);
+        assert_eq!(word.load(Ordering::Relaxed), TID | FUTEX_WAITERS);
+
+        wake_robust(TID);
+        assert_eq!(
+            word.load(Ordering::Relaxed),
+            FUTEX_WAITERS | FUTEX_OWNER_DIED
+        );
+
+        waiter.join();
+    }
+}
@@ -9,6 +9,7 @@ use aster_rights::{ReadOp, WriteOp};
 use super::{
     kill::SignalSenderIds,
     signal::{
+        sig_action::SigAction,
         sig_mask::{AtomicSigMask, SigMask, SigSet},
         sig_num::SigNum,
         sig_queues::SigQueues,
@@ -21,7 +22,6 @@ use crate::{
     events::Observer,
     prelude::*,
     process::signal::constants::SIGCONT,
-    thread::Tid,
     time::{clocks::ProfClock, Timer, TimerManager},
 };
 
@@ -50,7 +50,9 @@ pub struct PosixThread {
     set_child_tid: Mutex<Vaddr>,
     clear_child_tid: Mutex<Vaddr>,
 
-    robust_list: Mutex<Option<RobustListHead>>,
+    /// The address of the head of the robust futex list.
+    /// https://man7.org/linux/man-pages/man2/set_robust_list.2.html
+    robust_list: Mutex<Option<Vaddr>>,
 
     /// Process credentials. At the kernel level, credentials are a per-thread attribute.
     credentials: Credentials,
@@ -105,20 +107,17 @@ impl PosixThread {
         &self.sig_mask
     }
 
+    /// Returns the pending signals, including both the thread-directed signals and the
+    /// process-directed signals.
     pub fn sig_pending(&self) -> SigSet {
-        self.sig_queues.sig_pending()
+        self.sig_queues.sig_pending() | self.process().sig_queues().sig_pending()
     }
 
     /// Returns whether the thread has some pending signals
     /// that are not blocked.
     pub fn has_pending(&self) -> bool {
         let blocked = self.sig_mask().load(Ordering::Relaxed);
-        self.sig_queues.has_pending(blocked)
-    }
-
-    /// Returns whether the signal is blocked by the thread.
-    pub(in crate::process) fn has_signal_blocked(&self, signal: &dyn Signal) -> bool {
-        self.sig_mask.contains(signal.num(), Ordering::Relaxed)
+        self.sig_queues.has_pending(blocked) || self.process().sig_queues().has_pending(blocked)
     }
 
     /// Checks whether the signal can be delivered to the thread.
@@ -176,6 +175,46 @@ impl PosixThread {
         self.sig_queues.enqueue(signal);
     }
 
+    /// Enqueues a thread-directed signal that cannot be blocked or ignored.
+    ///
+    /// This method should be used for the fault signals caused by the thread itself, since
+    /// the thread would fault again if the signal were not delivered. Like Linux's
+    /// `force_sig_fault`, a blocked or ignored signal is unblocked and its disposition is
+    /// reset to the default.
+    pub fn force_enqueue_signal(&self, signal: Box<dyn Signal>) {
+        let signum = signal.num();
+        let is_blocked = self.sig_mask.contains(signum, Ordering::Relaxed);
+
+        let process = self.process();
+        let mut sig_dispositions = process.sig_dispositions().lock();
+        let is_ignored = matches!(sig_dispositions.get(signum), SigAction::Ign);
+        if is_blocked || is_ignored {
+            sig_dispositions.set_default(signum);
+        }
+        drop(sig_dispositions);
+
+        if is_blocked {
+            let _ = self
+                .sig_mask
+                .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |mask| {
+                    Some(mask - signum)
+                });
+        }
+
+        self.enqueue_signal(signal);
+    }
+
+    /// Enqueues a signal, failing with `EAGAIN` if the signal is a real-time
+    /// signal and the thread has too many pending real-time signals.
+    pub fn try_enqueue_signal(&self, signal: Box<dyn Signal>) -> Result<()> {
+        self.sig_queues.try_enqueue(signal)
+    }
+
+    /// Notifies the observers of the thread that a process-directed signal is pending.
+    pub(in crate::process) fn notify_signal(&self, signum: SigNum) {
+        self.sig_queues.notify_observers(signum);
+    }
+
     /// Returns a reference to the profiling clock of the current thread.
     pub fn prof_clock(&self) -> &Arc<ProfClock> {
         &self.prof_clock
@@ -203,8 +242,13 @@ impl PosixThread {
         self.prof_timer_manager.process_expired_timers();
     }
 
+    /// Dequeues a signal that is not blocked by `mask`.
+    ///
+    /// The thread-directed signals are dequeued before the process-directed signals.
     pub fn dequeue_signal(&self, mask: &SigMask) -> Option<Box<dyn Signal>> {
-        self.sig_queues.dequeue(mask)
+        self.sig_queues
+            .dequeue(mask)
+            .or_else(|| self.process().sig_queues().dequeue(mask))
     }
 
     pub fn register_sigqueue_observer(
@@ -227,16 +271,10 @@ impl PosixThread {
         &self.sig_stack
     }
 
-    pub fn robust_list(&self) -> &Mutex<Option<RobustListHead>> {
+    pub fn robust_list(&self) -> &Mutex<Option<Vaddr>> {
         &self.robust_list
     }
 
-    fn is_main_thread(&self, tid: Tid) -> bool {
-        let process = self.process();
-        let pid = process.pid();
-        tid == pid
-    }
-
     fn is_last_thread(&self) -> bool {
         let process = self.process.upgrade().unwrap();
         let threads = process.threads().lock();
@@ -53,4 +53,12 @@ impl ThreadName {
     pub fn name(&self) -> Result<Option<&CStr>> {
         Ok(Some(CStr::from_bytes_until_nul(&self.inner)?))
     }
+
+    /// Returns the name as a string, with invalid UTF-8 sequences replaced.
+    pub fn to_string_lossy(&self) -> String {
+        match self.name() {
+            Ok(Some(name)) => name.to_string_lossy().into_owned(),
+            _ => String::new(),
+        }
+    }
 }
@@ -2,10 +2,7 @@
 
 //! The implementation of robust list is from occlum.
 
-use crate::{
-    prelude::*,
-    process::{posix_thread::futex::futex_wake, Pid},
-};
+use crate::prelude::*;
 
 #[repr(C)]
 #[derive(Clone, Copy, Debug, Pod)]
@@ -33,18 +30,26 @@ pub struct RobustListHead {
 impl RobustListHead {
     /// Return an iterator for all futexes in the robust list.
     ///
+    /// The head of the list lives at `head_addr` in the user space, and
+    /// `read_next` reads the `next` pointer of the lock entry at the given
+    /// address.
+    ///
     /// The futex refered to by `list_op_pending`, if any, will be returned as
     /// the last item.
-    pub fn futexes(&self) -> FutexIter<'_> {
-        FutexIter::new(self)
+    pub fn futexes<F>(&self, head_addr: Vaddr, read_next: F) -> FutexIter<'_, F>
+    where
+        F: FnMut(Vaddr) -> Result<Vaddr>,
+    {
+        FutexIter::new(self, head_addr, read_next)
     }
 
     /// Return the pending futex address if exist
     fn pending_futex_addr(&self) -> Option<Vaddr> {
-        if self.list_op_pending == 0 {
+        let entry_ptr = entry_addr(self.list_op_pending);
+        if entry_ptr == 0 {
             None
         } else {
-            Some(self.futex_addr(self.list_op_pending))
+            Some(self.futex_addr(entry_ptr))
         }
     }
 
@@ -54,105 +59,148 @@ impl RobustListHead {
     }
 }
 
-pub struct FutexIter<'a> {
+/// Strips the flag in the lowest bit of a lock entry pointer.
+///
+/// The bit is set by the user space for PI futexes, while the entry itself is
+/// always aligned.
+fn entry_addr(entry_ptr: Vaddr) -> Vaddr {
+    entry_ptr & !1
+}
+
+pub struct FutexIter<'a, F> {
     robust_list: &'a RobustListHead,
+    head_addr: Vaddr,
+    read_next: F,
     entry_ptr: Vaddr,
-    count: isize,
+    count: usize,
+    is_end: bool,
 }
 
-impl<'a> FutexIter<'a> {
-    pub fn new(robust_list: &'a RobustListHead) -> Self {
+impl<'a, F> FutexIter<'a, F>
+where
+    F: FnMut(Vaddr) -> Result<Vaddr>,
+{
+    fn new(robust_list: &'a RobustListHead, head_addr: Vaddr, read_next: F) -> Self {
         Self {
             robust_list,
-            entry_ptr: robust_list.list.next,
+            head_addr,
+            read_next,
+            entry_ptr: entry_addr(robust_list.list.next),
             count: 0,
+            is_end: false,
         }
     }
-
-    // The `self.count` is normally a positive value used to iterate the list
-    // to avoid excessively long or circular list, we use a special value -1
-    // to represent the end of the Iterator.
-    fn set_end(&mut self) {
-        self.count = -1;
-    }
-
-    fn is_end(&self) -> bool {
-        self.count < 0
-    }
 }
 
-const ROBUST_LIST_LIMIT: isize = 2048;
-
-impl<'a> Iterator for FutexIter<'a> {
+/// The maximum number of lock entries to walk.
+///
+/// The list is maintained by the user space, so it can be corrupted, e.g., be
+/// circular without going back to the head. The limit avoids walking such a
+/// list forever.
+const ROBUST_LIST_LIMIT: usize = 2048;
+
+impl<'a, F> Iterator for FutexIter<'a, F>
+where
+    F: FnMut(Vaddr) -> Result<Vaddr>,
+{
     type Item = Vaddr;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if self.is_end() {
+        if self.is_end {
             return None;
         }
 
-        let end_ptr = self.robust_list.list.next;
-        while self.entry_ptr != end_ptr || self.count == 0 {
-            if self.count == ROBUST_LIST_LIMIT {
-                break;
-            }
-            if self.entry_ptr == 0 {
+        let pending_ptr = entry_addr(self.robust_list.list_op_pending);
+        while self.entry_ptr != self.head_addr
+            && self.entry_ptr != 0
+            && self.count < ROBUST_LIST_LIMIT
+        {
+            let entry_ptr = self.entry_ptr;
+            // The next pointer must be read before the futex is handled,
+            // since the entry may be freed once the futex is released.
+            let Ok(next_ptr) = (self.read_next)(entry_ptr) else {
+                self.is_end = true;
                 return None;
-            }
-            let futex_addr = if self.entry_ptr != self.robust_list.list_op_pending {
-                Some(self.robust_list.futex_addr(self.entry_ptr))
-            } else {
-                None
             };
-            let Ok(robust_list) = CurrentUserSpace::get().read_val::<RobustList>(self.entry_ptr)
-            else {
-                return None;
-            };
-            self.entry_ptr = robust_list.next;
+            self.entry_ptr = entry_addr(next_ptr);
             self.count += 1;
-            if futex_addr.is_some() {
-                return futex_addr;
+
+            // The pending futex is returned as the last item.
+            if entry_ptr != pending_ptr {
+                return Some(self.robust_list.futex_addr(entry_ptr));
             }
         }
-        self.set_end();
+
+        self.is_end = true;
         self.robust_list.pending_futex_addr()
     }
 }
 
-const FUTEX_WAITERS: u32 = 0x8000_0000;
-const FUTEX_OWNER_DIED: u32 = 0x4000_0000;
-const FUTEX_TID_MASK: u32 = 0x3FFF_FFFF;
-
-/// Wakeup one robust futex owned by the thread
-/// FIXME: requires atomic operations here
-pub fn wake_robust_futex(futex_addr: Vaddr, tid: Pid) -> Result<()> {
-    let user_space = CurrentUserSpace::get();
-    let futex_val = {
-        if futex_addr == 0 {
-            return_errno_with_message!(Errno::EINVAL, "invalid futext addr");
-        }
-        user_space.read_val::<u32>(futex_addr)?
-    };
-    let mut old_val = futex_val;
-    loop {
-        // This futex may held by another thread, do nothing
-        if old_val & FUTEX_TID_MASK != tid {
-            break;
-        }
-        let new_val = (old_val & FUTEX_WAITERS) | FUTEX_OWNER_DIED;
-        let cur_val = user_space.read_val(futex_addr)?;
-        if cur_val != new_val {
-            // The futex value has changed, let's retry with current value
-            old_val = cur_val;
-            user_space.write_val(futex_addr, &new_val)?;
-            continue;
-        }
-        // Wakeup one waiter
-        if cur_val & FUTEX_WAITERS != 0 {
-            debug!("wake robust futex addr: {:?}", futex_addr);
-            futex_wake(futex_addr, 1)?;
+#[cfg(ktest)]
+mod test {
+    use ostd::prelude::*;
+
+    use super::*;
+
+    const HEAD_ADDR: Vaddr = 0x1000;
+    const FUTEX_OFFSET: isize = -0x10;
+
+    fn new_head(next: Vaddr, list_op_pending: Vaddr) -> RobustListHead {
+        RobustListHead {
+            list: RobustList { next },
+            futex_offset: FUTEX_OFFSET,
+            list_op_pending,
         }
-        break;
     }
-    Ok(())
+
+    /// Walks the list in which the entry at `addr` links to `next(addr)`.
+    fn walk(head: &RobustListHead, next: impl Fn(Vaddr) -> Vaddr) -> Vec<Vaddr> {
+        head.futexes(HEAD_ADDR, |addr| Ok(next(addr)))
+            .map(|futex_addr| (futex_addr as isize - FUTEX_OFFSET) as Vaddr)
+            .collect()
+    }
+
+    #[ktest]
+    fn empty_list() {
+        let head = new_head(HEAD_ADDR, 0);
+        assert_eq!(walk(&head, |_| unreachable!()), vec![]);
+    }
+
+    #[ktest]
+    fn list_with_pending_entry() {
+        // HEAD -> 0x2000 -> 0x3000 (PI) -> 0x4000 -> HEAD
+        let next = |addr| match addr {
+            0x2000 => 0x3001,
+            0x3000 => 0x4000,
+            0x4000 => HEAD_ADDR,
+            _ => unreachable!(),
+        };
+
+        let head = new_head(0x2000, 0);
+        assert_eq!(walk(&head, next), vec![0x2000, 0x3000, 0x4000]);
+
+        let head = new_head(0x2000, 0x3000);
+        assert_eq!(walk(&head, next), vec![0x2000, 0x4000, 0x3000]);
+    }
+
+    #[ktest]
+    fn corrupted_list() {
+        // The list loops without going back to the head.
+        let head = new_head(0x2000, 0);
+        let futexes = walk(&head, |addr| if addr == 0x2000 { 0x3000 } else { 0x2000 });
+        assert_eq!(futexes.len(), ROBUST_LIST_LIMIT);
+
+        // The list contains a bad pointer.
+        let head = new_head(0x2000, 0x5000);
+        let futexes = head
+            .futexes(HEAD_ADDR, |addr| {
+                if addr == 0x2000 {
+                    Ok(0x3000)
+                } else {
+                    return_errno_with_message!(Errno::EFAULT, "bad pointer")
+                }
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(futexes, vec![(0x2000 + FUTEX_OFFSET) as Vaddr]);
+    }
 }
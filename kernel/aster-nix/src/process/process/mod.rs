@@ -1,5 +1,7 @@
 // SPDX-License-Identifier: MPL-2.0
 
+use core::sync::atomic::Ordering;
+
 use self::timer_manager::PosixTimerManager;
 use super::{
     posix_thread::PosixThreadExt,
@@ -10,6 +12,7 @@ use super::{
         constants::SIGCHLD,
         sig_disposition::SigDispositions,
         sig_num::{AtomicSigNum, SigNum},
+        sig_queues::SigQueues,
         signals::Signal,
         Pauser,
     },
@@ -92,6 +95,8 @@ pub struct Process {
     // Signal
     /// Sig dispositions
     sig_dispositions: Arc<Mutex<SigDispositions>>,
+    /// The process-directed signals, which are pending until any one of the threads handles them
+    sig_queues: SigQueues,
     /// The signal that the process should receive when parent process exits.
     parent_death_signal: AtomicSigNum,
 
@@ -148,6 +153,7 @@ impl Process {
             fs,
             umask,
             sig_dispositions,
+            sig_queues: SigQueues::new(),
             parent_death_signal: AtomicSigNum::new_empty(),
             resource_limits: Mutex::new(resource_limits),
             nice: Atomic::new(nice),
@@ -178,7 +184,7 @@ impl Process {
         envp: Vec<CString>,
     ) -> Result<Arc<Self>> {
         let process_builder = {
-            let pid = allocate_tid();
+            let pid = allocate_tid()?;
             let parent = Weak::new();
 
             let credentials = Credentials::new_root();
@@ -573,32 +579,56 @@ impl Process {
     /// Enqueues a process-directed signal. This method should only be used for enqueue kernel
     /// signal and fault signal.
     ///
-    /// The signal may be delivered to any one of the threads that does not currently have the
-    /// signal blocked.  If more than one of the threads has the signal unblocked, then this method
-    /// chooses an arbitrary thread to which to deliver the signal.
+    /// See [`Process::try_enqueue_signal`] for how the signal is delivered.
     ///
     /// TODO: restrict these method with access control tool.
     pub fn enqueue_signal(&self, signal: impl Signal + Clone + 'static) {
+        // TODO: check that the signal is not user signal
+
+        let _ = self.try_enqueue_signal(Box::new(signal));
+    }
+
+    /// Enqueues a process-directed signal, failing with `EAGAIN` if the signal is a real-time
+    /// signal and the process has too many pending real-time signals.
+    ///
+    /// The signal is pending for the whole process, so it may be handled by any one of the
+    /// threads that does not currently have the signal blocked. This method wakes up the first
+    /// such thread to handle the signal. If all threads block the signal, it stays pending until
+    /// one of them unblocks the signal.
+    pub fn try_enqueue_signal(&self, signal: Box<dyn Signal>) -> Result<()> {
         if self.is_zombie() {
-            return;
+            return Ok(());
         }
 
-        // TODO: check that the signal is not user signal
+        let signum = signal.num();
+        self.sig_queues.try_enqueue(signal)?;
 
-        // Enqueue signal to the first thread that does not block the signal
         let threads = self.threads.lock();
-        for thread in threads.iter() {
-            let posix_thread = thread.as_posix_thread().unwrap();
-            if !posix_thread.has_signal_blocked(&signal) {
-                posix_thread.enqueue_signal(Box::new(signal));
-                return;
-            }
+        let live_threads = threads
+            .iter()
+            .filter(|thread| !thread.status().is_exited())
+            .map(|thread| thread.as_posix_thread().unwrap());
+
+        if let Some(thread) = live_threads
+            .clone()
+            .find(|thread| !thread.sig_mask().contains(signum, Ordering::Relaxed))
+        {
+            thread.notify_signal(signum);
+            return Ok(());
         }
 
-        // If all threads block the signal, enqueue signal to the first thread
-        let thread = threads.iter().next().unwrap();
-        let posix_thread = thread.as_posix_thread().unwrap();
-        posix_thread.enqueue_signal(Box::new(signal));
+        // No thread will handle the signal now, but those waiting for the blocked signal (e.g.,
+        // via a signalfd) should still be notified.
+        for thread in live_threads {
+            thread.notify_signal(signum);
+        }
+
+        Ok(())
+    }
+
+    /// Returns the signal queues that are shared by all threads of the process.
+    pub(super) fn sig_queues(&self) -> &SigQueues {
+        &self.sig_queues
     }
 
     /// Clears the parent death signal.
@@ -648,14 +678,21 @@ impl Process {
 #[cfg(ktest)]
 mod test {
 
-    use ostd::prelude::*;
+    use ostd::{cpu::UserContext, prelude::*, user::UserSpace};
 
     use super::*;
+    use crate::{
+        process::{
+            posix_thread::PosixThreadBuilder,
+            signal::{constants::SIGUSR1, sig_mask::SigMask, signals::kernel::KernelSignal},
+        },
+        thread::thread_table,
+    };
 
     fn new_process(parent: Option<Arc<Process>>) -> Arc<Process> {
         crate::util::random::init();
         crate::fs::rootfs::init_root_mount();
-        let pid = allocate_tid();
+        let pid = allocate_tid().unwrap();
         let parent = if let Some(parent) = parent {
             Arc::downgrade(&parent)
         } else {
@@ -748,4 +785,99 @@ mod test {
             .to_new_session()
             .is_err_and(|e| e.error() == Errno::EPERM));
     }
+
+    /// Creates a child of `parent` that has exited with `code`.
+    fn new_zombie_child(parent: &Arc<Process>, code: u8) -> Arc<Process> {
+        let child = new_process_in_session(Some(parent.clone()));
+        parent.children().lock().insert(child.pid(), child.clone());
+        child.set_zombie(TermStatus::Exited(code));
+        child
+    }
+
+    #[ktest]
+    fn reap_exited_child() {
+        crate::time::clocks::init_for_ktest();
+        let parent = new_process_in_session(None);
+        let child = new_zombie_child(&parent, 42);
+
+        let exit_code = super::super::wait::reap_zombie_child(&parent, child.pid());
+        assert_eq!(exit_code >> 8, 42);
+        assert!(parent.children().lock().is_empty());
+        assert!(process_table::get_process(child.pid()).is_none());
+
+        remove_session_and_group(parent);
+    }
+
+    #[ktest]
+    fn reap_orphaned_child() {
+        crate::time::clocks::init_for_ktest();
+        let init = new_process_in_session(None);
+        let parent = new_process_in_session(Some(init.clone()));
+        let child = new_zombie_child(&parent, 42);
+
+        // The zombie is moved to the new parent, which has to be notified to reap it.
+        assert!(super::super::exit::reparent_children(&parent, &init));
+        assert!(parent.children().lock().is_empty());
+        assert!(Arc::ptr_eq(&child.parent().unwrap(), &init));
+
+        let exit_code = super::super::wait::reap_zombie_child(&init, child.pid());
+        assert_eq!(exit_code >> 8, 42);
+        assert!(init.children().lock().is_empty());
+
+        remove_session_and_group(parent);
+        remove_session_and_group(init);
+    }
+
+    /// Creates a thread that belongs to `process`.
+    fn new_thread(process: &Arc<Process>) -> Arc<Thread> {
+        let user_space = Arc::new(UserSpace::new(
+            process.root_vmar().vm_space().clone(),
+            UserContext::default(),
+        ));
+        let tid = allocate_tid().unwrap();
+        let thread = PosixThreadBuilder::new(tid, user_space, Credentials::new_root())
+            .process(Arc::downgrade(process))
+            .build();
+        process.threads().lock().push(thread.clone());
+        thread
+    }
+
+    #[ktest]
+    fn process_directed_signal() {
+        crate::time::clocks::init_for_ktest();
+        let process = new_process(None);
+        let threads = [new_thread(&process), new_thread(&process)];
+        let posix_threads = threads
+            .each_ref()
+            .map(|thread| thread.as_posix_thread().unwrap());
+
+        // The signal is pending for both threads, no matter which one handles it.
+        process.enqueue_signal(KernelSignal::new(SIGUSR1));
+        for thread in posix_threads {
+            assert!(thread.sig_pending().contains(SIGUSR1));
+            assert!(thread.has_pending());
+        }
+
+        // A thread that blocks the signal cannot handle it.
+        posix_threads[0]
+            .sig_mask()
+            .store(SIGUSR1, Ordering::Relaxed);
+        assert!(!posix_threads[0].has_pending());
+        assert!(posix_threads[0]
+            .dequeue_signal(&SigMask::from(SIGUSR1))
+            .is_none());
+
+        // Once a thread handles the signal, it is no longer pending for either thread.
+        let signal = posix_threads[1]
+            .dequeue_signal(&SigMask::new_empty())
+            .unwrap();
+        assert_eq!(signal.num(), SIGUSR1);
+        for thread in posix_threads {
+            assert!(!thread.sig_pending().contains(SIGUSR1));
+        }
+
+        for thread in threads {
+            thread_table::remove_thread(thread.tid());
+        }
+    }
 }
@@ -3,7 +3,11 @@
 use alloc::collections::btree_map::Values;
 
 use super::{Pgid, Pid, Process, Session};
-use crate::{prelude::*, process::signal::signals::Signal};
+use crate::{
+    prelude::*,
+    process::signal::signals::Signal,
+    thread::{hold_tid, release_tid},
+};
 
 /// `ProcessGroup` represents a set of processes. Each `ProcessGroup` has a unique
 /// identifier `pgid`.
@@ -43,6 +47,8 @@ impl ProcessGroup {
     /// The caller needs to ensure that the process does not belong to any group.
     pub(in crate::process) fn new(process: Arc<Process>) -> Arc<Self> {
         let pid = process.pid();
+        // The pgid is not reused by a new process until the group is dropped.
+        hold_tid(pid);
 
         let inner = {
             let mut processes = BTreeMap::new();
@@ -99,6 +105,12 @@ impl ProcessGroup {
     }
 }
 
+impl Drop for ProcessGroup {
+    fn drop(&mut self) {
+        release_tid(self.pgid);
+    }
+}
+
 /// A scoped lock for a process group.
 ///
 /// It provides some public methods to prevent the exposure of the inner type.
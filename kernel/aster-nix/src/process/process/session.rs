@@ -1,7 +1,10 @@
 // SPDX-License-Identifier: MPL-2.0
 
 use super::{Pgid, Process, ProcessGroup, Sid, Terminal};
-use crate::prelude::*;
+use crate::{
+    prelude::*,
+    thread::{hold_tid, release_tid},
+};
 
 /// A `Session` is a collection of related process groups. Each session has a
 /// unique identifier `sid`. Process groups and sessions form a two-level
@@ -49,6 +52,9 @@ impl Session {
     /// should set the leader process after creating the session.
     pub(in crate::process) fn new(group: Arc<ProcessGroup>) -> Arc<Self> {
         let sid = group.pgid();
+        // The sid is not reused by a new process until the session is dropped.
+        hold_tid(sid);
+
         let inner = {
             let mut process_groups = BTreeMap::new();
             process_groups.insert(group.pgid(), group);
@@ -134,3 +140,9 @@ impl Session {
         self.inner.lock().terminal.clone()
     }
 }
+
+impl Drop for Session {
+    fn drop(&mut self) {
+        release_tid(self.sid);
+    }
+}
@@ -10,7 +10,7 @@ use core::time::Duration;
 use id_alloc::IdAlloc;
 use ostd::{
     arch::{
-        timer::{self, TIMER_FREQ},
+        timer::{self, timer_freq},
         x86::trap::is_kernel_interrupted,
     },
     sync::Mutex,
@@ -46,7 +46,7 @@ fn update_cpu_time() {
     };
     let process = posix_thread.process();
     let timer_manager = process.timer_manager();
-    let jiffies_interval = Duration::from_millis(1000 / TIMER_FREQ);
+    let jiffies_interval = Duration::from_nanos(1_000_000_000 / timer_freq());
     // Based on whether the timer interrupt occurs in kernel mode or user mode,
     // the function will add the duration of one timer interrupt interval to the
     // corresponding CPU clocks.
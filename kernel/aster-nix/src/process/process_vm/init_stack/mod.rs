@@ -15,6 +15,7 @@
 
 use core::{
     mem,
+    ops::Range,
     sync::atomic::{AtomicUsize, Ordering},
 };
 
@@ -38,6 +39,16 @@ pub mod aux_vec;
 /// Set the initial stack size to 8 megabytes, following the default Linux stack size limit.
 pub const INIT_STACK_SIZE: usize = 8 * 1024 * 1024; // 8 MB
 
+/// The size of the user stack that is mapped when the process starts.
+///
+/// It must be large enough to hold the `argv` and `envp` strings, their pointers and
+/// the auxiliary vectors. The rest of the stack is mapped on demand.
+const INIT_STACK_MAPPED_SIZE: usize = 512 * 1024; // 512 KB
+
+/// The minimum gap between the user stack and the mappings below it, which the stack
+/// cannot grow into.
+const STACK_GUARD_GAP: usize = 256 * PAGE_SIZE;
+
 /// The max number of arguments that can be used to creating a new process.
 pub const MAX_ARGV_NUMBER: usize = 128;
 /// The max number of environmental variables that can be used to creating a new process.
@@ -88,7 +99,7 @@ pub const MAX_ENV_LEN: usize = 128;
  *  +---------------------+
  *  |                     |
  *  |                     |
- *  +---------------------+
+ *  +---------------------+ <------+ The bottom of the mapped stack (grows on page faults)
  *  |                     |
  *  +---------------------+ <------+ User stack default rlimit
  *  (low address)
@@ -101,6 +112,8 @@ pub struct InitStack {
     initial_top: Vaddr,
     /// The max allowed stack size
     max_size: usize,
+    /// The lowest address of the stack that has been mapped.
+    mapped_bottom: Mutex<Vaddr>,
     /// The current stack pointer.
     /// Before initialized, `pos` points to the `initial_top`,
     /// After initialized, `pos` points to the user stack pointer(rsp)
@@ -114,6 +127,7 @@ impl Clone for InitStack {
         Self {
             initial_top: self.initial_top,
             max_size: self.max_size,
+            mapped_bottom: Mutex::new(*self.mapped_bottom.lock()),
             pos: self.pos.clone(),
             vmo: self.vmo.dup(),
         }
@@ -137,29 +151,78 @@ impl InitStack {
         Self {
             initial_top,
             max_size,
+            mapped_bottom: Mutex::new(initial_top),
             pos: Arc::new(AtomicUsize::new(initial_top)),
             vmo,
         }
     }
 
     /// Maps the vmo of the init stack.
+    ///
+    /// Only the highest [`INIT_STACK_MAPPED_SIZE`] bytes are mapped. The rest of the stack
+    /// will be mapped by [`Self::grow`] when the stack grows.
     pub(super) fn map_init_stack_vmo(&self, root_vmar: &Vmar<Full>) -> Result<()> {
-        let vmar_map_options = {
-            let perms = VmPerms::READ | VmPerms::WRITE;
-            let map_addr = self.initial_top - self.max_size;
-            debug_assert!(map_addr % PAGE_SIZE == 0);
-            root_vmar
-                .new_map(self.max_size, perms)?
-                .offset(map_addr)
-                .vmo(self.vmo.dup().to_dyn())
-        };
-
-        vmar_map_options.build()?;
+        let mut mapped_bottom = self.mapped_bottom.lock();
+        let bottom = self.initial_top - INIT_STACK_MAPPED_SIZE.min(self.max_size);
+        self.map_range(root_vmar, bottom..self.initial_top)?;
+        *mapped_bottom = bottom;
 
         self.set_uninitialized();
         Ok(())
     }
 
+    /// Grows the stack downward so that `addr` is mapped.
+    ///
+    /// Like Linux, the stack grows only if the size of the grown stack does not exceed
+    /// `size_limit`, and the grown stack keeps a guard gap from the mappings below it.
+    pub(super) fn grow(
+        &self,
+        root_vmar: &Vmar<Full>,
+        addr: Vaddr,
+        size_limit: usize,
+    ) -> Result<()> {
+        let mut mapped_bottom = self.mapped_bottom.lock();
+        if addr >= self.initial_top {
+            return_errno_with_message!(Errno::EFAULT, "the address is above the stack");
+        }
+        if addr >= *mapped_bottom {
+            // The stack has been grown by another thread.
+            return Ok(());
+        }
+
+        let new_bottom = addr.align_down(PAGE_SIZE);
+        if self.initial_top - new_bottom > size_limit.min(self.max_size) {
+            return_errno_with_message!(Errno::ENOMEM, "the stack size limit is reached");
+        }
+        let guard_range = new_bottom.saturating_sub(STACK_GUARD_GAP)..*mapped_bottom;
+        if !root_vmar.is_range_free(&guard_range) {
+            return_errno_with_message!(Errno::ENOMEM, "the stack is too close to another mapping");
+        }
+
+        self.map_range(root_vmar, new_bottom..*mapped_bottom)?;
+        *mapped_bottom = new_bottom;
+        Ok(())
+    }
+
+    fn map_range(&self, root_vmar: &Vmar<Full>, range: Range<Vaddr>) -> Result<()> {
+        debug_assert!(range.start % PAGE_SIZE == 0);
+        debug_assert!(range.end % PAGE_SIZE == 0);
+
+        let perms = VmPerms::READ | VmPerms::WRITE;
+        root_vmar
+            .new_map(range.len(), perms)?
+            .offset(range.start)
+            .vmo(self.vmo.dup().to_dyn())
+            .vmo_offset(range.start - self.map_addr())
+            .build()?;
+        Ok(())
+    }
+
+    /// Returns the address where the start of the vmo is mapped.
+    fn map_addr(&self) -> Vaddr {
+        self.initial_top - self.max_size
+    }
+
     /// Returns the user stack top(highest address), used to setup rsp.
     ///
     /// This method should only be called after the stack is initialized.
@@ -185,7 +248,7 @@ impl InitStack {
             argv,
             envp,
             auxvec,
-            map_addr: self.initial_top - self.max_size,
+            map_addr: self.map_addr(),
         }
     }
 
@@ -196,7 +259,7 @@ impl InitStack {
         InitStackReader {
             base: self.pos(),
             vmo: &self.vmo,
-            map_addr: self.initial_top - self.max_size,
+            map_addr: self.map_addr(),
         }
     }
 
@@ -12,6 +12,8 @@
 mod heap;
 mod init_stack;
 
+use core::sync::atomic::{AtomicBool, Ordering};
+
 use aster_rights::Full;
 pub use heap::Heap;
 
@@ -66,6 +68,8 @@ pub struct ProcessVm {
     root_vmar: Vmar<Full>,
     init_stack: InitStack,
     heap: Heap,
+    /// Whether each `mmap` mapping is followed by a guard page.
+    is_mmap_guarded: AtomicBool,
 }
 
 impl Clone for ProcessVm {
@@ -74,6 +78,7 @@ impl Clone for ProcessVm {
             root_vmar: self.root_vmar.dup().unwrap(),
             init_stack: self.init_stack.clone(),
             heap: self.heap.clone(),
+            is_mmap_guarded: AtomicBool::new(self.is_mmap_guarded()),
         }
     }
 }
@@ -90,6 +95,7 @@ impl ProcessVm {
             root_vmar,
             heap,
             init_stack,
+            is_mmap_guarded: AtomicBool::new(false),
         }
     }
 
@@ -102,6 +108,7 @@ impl ProcessVm {
             root_vmar,
             heap: other.heap.clone(),
             init_stack: other.init_stack.clone(),
+            is_mmap_guarded: AtomicBool::new(other.is_mmap_guarded()),
         })
     }
 
@@ -109,12 +116,36 @@ impl ProcessVm {
         &self.root_vmar
     }
 
+    /// Returns whether each `mmap` mapping is followed by a guard page.
+    ///
+    /// This is a debugging aid that makes a buffer overrun past the end of a
+    /// mapping fault immediately.
+    pub fn is_mmap_guarded(&self) -> bool {
+        self.is_mmap_guarded.load(Ordering::Relaxed)
+    }
+
+    /// Sets whether each `mmap` mapping is followed by a guard page.
+    ///
+    /// Only the mappings created afterwards are affected.
+    pub fn set_mmap_guarded(&self, is_mmap_guarded: bool) {
+        self.is_mmap_guarded
+            .store(is_mmap_guarded, Ordering::Relaxed);
+    }
+
     /// Returns a reader for reading contents from
     /// the `InitStack`.
     pub fn init_stack_reader(&self) -> InitStackReader {
         self.init_stack.reader()
     }
 
+    /// Grows the user stack downward so that `addr` is mapped.
+    ///
+    /// This method fails if the size of the grown stack would exceed `size_limit`,
+    /// or the grown stack would be too close to the mappings below it.
+    pub fn grow_user_stack(&self, addr: Vaddr, size_limit: usize) -> Result<()> {
+        self.init_stack.grow(&self.root_vmar, addr, size_limit)
+    }
+
     /// Returns the top address of the user stack.
     pub fn user_stack_top(&self) -> Vaddr {
         self.init_stack.user_stack_top()
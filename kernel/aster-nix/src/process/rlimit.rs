@@ -24,6 +24,10 @@ impl Default for ResourceLimits {
         let stack_size = RLimit64::new(INIT_STACK_SIZE as u64);
         let heap_size = RLimit64::new(USER_HEAP_SIZE_LIMIT as u64);
         let open_files = RLimit64::new(1024);
+        // Like Linux, an unprivileged process cannot raise its priority by default.
+        let nice = RLimit64 { cur: 0, max: 0 };
+        // Nor can it set a real-time policy.
+        let rt_priority = RLimit64 { cur: 0, max: 0 };
 
         let mut rlimits = Self {
             rlimits: [RLimit64::default(); RLIMIT_COUNT],
@@ -31,6 +35,8 @@ impl Default for ResourceLimits {
         *rlimits.get_rlimit_mut(ResourceType::RLIMIT_STACK) = stack_size;
         *rlimits.get_rlimit_mut(ResourceType::RLIMIT_DATA) = heap_size;
         *rlimits.get_rlimit_mut(ResourceType::RLIMIT_NOFILE) = open_files;
+        *rlimits.get_rlimit_mut(ResourceType::RLIMIT_NICE) = nice;
+        *rlimits.get_rlimit_mut(ResourceType::RLIMIT_RTPRIO) = rt_priority;
         rlimits
     }
 }
@@ -58,6 +58,30 @@ impl siginfo_t {
         // let siginfo = *self;
         read_union_fields!(self.siginfo_fields.sigfault.addr)
     }
+
+    pub fn set_si_pid(&mut self, si_pid: Pid) {
+        self.siginfo_fields.common.first.piduid.pid = si_pid;
+    }
+
+    pub fn si_pid(&self) -> Pid {
+        read_union_fields!(self.siginfo_fields.common.first.piduid.pid)
+    }
+
+    pub fn set_si_uid(&mut self, si_uid: Uid) {
+        self.siginfo_fields.common.first.piduid.uid = si_uid;
+    }
+
+    pub fn si_uid(&self) -> Uid {
+        read_union_fields!(self.siginfo_fields.common.first.piduid.uid)
+    }
+
+    pub fn set_si_value(&mut self, si_value: sigval_t) {
+        self.siginfo_fields.common.second.value = si_value;
+    }
+
+    pub fn si_value(&self) -> sigval_t {
+        read_union_fields!(self.siginfo_fields.common.second.value)
+    }
 }
 
 #[derive(Clone, Copy, Pod)]
@@ -78,7 +102,7 @@ impl siginfo_fields_t {
 
 #[derive(Clone, Copy, Pod)]
 #[repr(C)]
-union siginfo_common_t {
+struct siginfo_common_t {
     first: siginfo_common_first_t,
     second: siginfo_common_second_t,
 }
@@ -118,6 +142,14 @@ pub union sigval_t {
     sigval_ptr: Vaddr, //*mut c_void
 }
 
+impl Debug for sigval_t {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("sigval_t")
+            .field("sigval_ptr", &self.read_ptr())
+            .finish()
+    }
+}
+
 impl sigval_t {
     pub fn read_int(&self) -> i32 {
         read_union_fields!(self.sigval_int)
@@ -36,14 +36,24 @@ use crate::{
 pub trait SignalContext {
     /// Set signal handler arguments
     fn set_arguments(&mut self, sig_num: SigNum, siginfo_addr: usize, ucontext_addr: usize);
+
+    /// Rewinds the context so that the syscall with `syscall_num` will be
+    /// executed again after returning to the user space.
+    fn restart_syscall(&mut self, syscall_num: usize);
 }
 
 // TODO: This interface of this method is error prone.
 // The method takes an argument for the current thread to optimize its efficiency.
 /// Handle pending signal for current process.
+///
+/// If `interrupted_syscall` is `Some`, the syscall with that number has just
+/// been interrupted by a signal and failed with `EINTR`. The syscall will be
+/// restarted unless the signal is handled by a user handler without the
+/// `SA_RESTART` flag.
 pub fn handle_pending_signal(
     context: &mut UserContext,
     current_thread: &Arc<Thread>,
+    interrupted_syscall: Option<usize>,
 ) -> Result<()> {
     // We first deal with signal in current thread, then signal in current process.
     let posix_thread = current_thread.as_posix_thread().unwrap();
@@ -52,6 +62,9 @@ pub fn handle_pending_signal(
         if let Some(signal) = posix_thread.dequeue_signal(&sig_mask) {
             signal
         } else {
+            if let Some(syscall_num) = interrupted_syscall {
+                context.restart_syscall(syscall_num);
+            }
             return Ok(());
         }
     };
@@ -65,6 +78,9 @@ pub fn handle_pending_signal(
     match sig_action {
         SigAction::Ign => {
             trace!("Ignore signal {:?}", sig_num);
+            if let Some(syscall_num) = interrupted_syscall {
+                context.restart_syscall(syscall_num);
+            }
         }
         SigAction::User {
             handler_addr,
@@ -81,6 +97,13 @@ pub fn handle_pending_signal(
 
             drop(sig_dispositions);
 
+            // The syscall is restarted after the handler returns.
+            if let Some(syscall_num) = interrupted_syscall
+                && flags.contains(SigActionFlags::SA_RESTART)
+            {
+                context.restart_syscall(syscall_num);
+            }
+
             handle_user_signal(
                 posix_thread,
                 sig_num,
@@ -106,6 +129,7 @@ pub fn handle_pending_signal(
                     );
                     // We should exit current here, since we cannot restore a valid status from trap now.
                     do_exit_group(TermStatus::Killed(sig_num));
+                    return Ok(());
                 }
                 SigDefaultAction::Ign => {}
                 SigDefaultAction::Stop => {
@@ -125,6 +149,11 @@ pub fn handle_pending_signal(
                     );
                 }
             }
+
+            // The signal is not handled by any user handler.
+            if let Some(syscall_num) = interrupted_syscall {
+                context.restart_syscall(syscall_num);
+            }
         }
     }
     Ok(())
@@ -180,8 +209,9 @@ pub fn handle_user_signal(
 
     // 2. write ucontext_t.
     stack_pointer = alloc_aligned_in_user_stack(stack_pointer, mem::size_of::<ucontext_t>(), 16)?;
+    // The mask is restored when the handler returns.
     let mut ucontext = ucontext_t {
-        uc_sigmask: mask.into(),
+        uc_sigmask: old_mask.into(),
         ..Default::default()
     };
     ucontext
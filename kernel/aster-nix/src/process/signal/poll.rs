@@ -5,6 +5,8 @@ use core::{
     time::Duration,
 };
 
+use ostd::arch::timer::Jiffies;
+
 use crate::{
     events::{IoEvents, Observer, Subject},
     prelude::*,
@@ -21,6 +23,10 @@ pub struct Pollee {
 struct PolleeInner {
     // A subject which is monitored with pollers.
     subject: Subject<IoEvents, IoEvents>,
+    // The exclusive pollers and their event masks, in the order to be woken up.
+    exclusive_pollers: Mutex<VecDeque<(Weak<EventCounter>, IoEvents)>>,
+    // A copy of `exclusive_pollers.len()`, used for the lock-free fast path in `add_events`.
+    num_exclusive_pollers: AtomicUsize,
     // For efficient manipulation, we use AtomicU32 instead of RwLock<IoEvents>.
     events: AtomicU32,
 }
@@ -30,6 +36,8 @@ impl Pollee {
     pub fn new(init_events: IoEvents) -> Self {
         let inner = PolleeInner {
             subject: Subject::new(),
+            exclusive_pollers: Mutex::new(VecDeque::new()),
+            num_exclusive_pollers: AtomicUsize::new(0),
             events: AtomicU32::new(init_events.bits()),
         };
         Self {
@@ -62,11 +70,22 @@ impl Pollee {
     }
 
     fn register_poller(&self, poller: &mut Poller, mask: IoEvents) {
-        self.inner
-            .subject
-            .register_observer(poller.observer(), mask);
+        if poller.is_exclusive {
+            self.inner
+                .register_exclusive_poller(Arc::downgrade(&poller.event_counter), mask);
+        } else {
+            self.inner
+                .subject
+                .register_observer(poller.observer(), mask);
+        }
 
-        poller.pollees.push(Arc::downgrade(&self.inner));
+        if !poller
+            .pollees
+            .iter()
+            .any(|pollee| pollee.as_ptr() == Arc::as_ptr(&self.inner))
+        {
+            poller.pollees.push(Arc::downgrade(&self.inner));
+        }
     }
 
     /// Register an IoEvents observer.
@@ -99,11 +118,30 @@ impl Pollee {
 
     /// Add some events to the pollee's state.
     ///
-    /// This method wakes up all registered pollers that are interested in
-    /// the added events.
+    /// This method wakes up all registered non-exclusive pollers and observers
+    /// that are interested in the added events, and then at most one exclusive
+    /// poller. So the events should be added once per available resource, e.g.,
+    /// once per incoming connection, to wake up one exclusive poller for each.
+    ///
+    /// Events in `IoEvents::ALWAYS_POLL` wake up all exclusive pollers, since
+    /// all of them need to see the error or the hang-up.
     pub fn add_events(&self, events: IoEvents) {
         self.inner.events.fetch_or(events.bits(), Ordering::Release);
         self.inner.subject.notify_observers(&events);
+        if events.intersects(IoEvents::ALWAYS_POLL) {
+            self.inner.wake_all_exclusive_pollers(events);
+        } else {
+            self.inner.wake_one_exclusive_poller(events);
+        }
+    }
+
+    /// Add some events to the pollee's state without waking up anyone.
+    ///
+    /// The events are seen by later polls at once, but the registered pollers and
+    /// observers are not notified until the events are added by [`Self::add_events`].
+    /// This defers the wakeups while keeping the state accurate.
+    pub fn add_events_quietly(&self, events: IoEvents) {
+        self.inner.events.fetch_or(events.bits(), Ordering::Release);
     }
 
     /// Remove some events from the pollee's state.
@@ -126,9 +164,98 @@ impl Pollee {
     }
 
     fn events(&self) -> IoEvents {
-        let event_bits = self.inner.events.load(Ordering::Acquire);
+        self.inner.events()
+    }
+}
+
+impl PolleeInner {
+    fn events(&self) -> IoEvents {
+        let event_bits = self.events.load(Ordering::Acquire);
         IoEvents::from_bits(event_bits).unwrap()
     }
+
+    fn register_exclusive_poller(&self, event_counter: Weak<EventCounter>, mask: IoEvents) {
+        let mut pollers = self.exclusive_pollers.lock();
+        if let Some((_, registered_mask)) = pollers
+            .iter_mut()
+            .find(|(registered, _)| registered.ptr_eq(&event_counter))
+        {
+            *registered_mask = mask;
+            return;
+        }
+
+        // Like Linux, exclusive pollers are woken up in the FIFO order.
+        pollers.push_back((event_counter, mask));
+        self.num_exclusive_pollers.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn unregister_exclusive_poller(&self, event_counter: &Weak<EventCounter>) -> Option<IoEvents> {
+        let mut pollers = self.exclusive_pollers.lock();
+        let index = pollers
+            .iter()
+            .position(|(registered, _)| registered.ptr_eq(event_counter))?;
+        let (_, mask) = pollers.remove(index).unwrap();
+        self.num_exclusive_pollers.fetch_sub(1, Ordering::Relaxed);
+        Some(mask)
+    }
+
+    /// Wakes up one exclusive poller that is interested in `events`.
+    ///
+    /// A poller that has been woken up but has not waited yet is skipped, so that
+    /// each wakeup goes to a different poller.
+    fn wake_one_exclusive_poller(&self, events: IoEvents) {
+        // Fast path.
+        if self.num_exclusive_pollers.load(Ordering::Relaxed) == 0 {
+            return;
+        }
+
+        let mut pollers = self.exclusive_pollers.lock();
+        self.remove_dead_exclusive_pollers(&mut pollers);
+
+        let Some(index) = pollers.iter().position(|(event_counter, mask)| {
+            mask.intersects(events)
+                && event_counter
+                    .upgrade()
+                    .is_some_and(|event_counter| event_counter.write_if_idle())
+        }) else {
+            return;
+        };
+
+        // Move the woken poller to the back so that the others are woken up first
+        // next time.
+        let woken = pollers.remove(index).unwrap();
+        pollers.push_back(woken);
+    }
+
+    /// Wakes up all exclusive pollers that are interested in `events`.
+    fn wake_all_exclusive_pollers(&self, events: IoEvents) {
+        // Fast path.
+        if self.num_exclusive_pollers.load(Ordering::Relaxed) == 0 {
+            return;
+        }
+
+        let mut pollers = self.exclusive_pollers.lock();
+        self.remove_dead_exclusive_pollers(&mut pollers);
+
+        pollers
+            .iter()
+            .filter(|(_, mask)| mask.intersects(events))
+            .filter_map(|(event_counter, _)| event_counter.upgrade())
+            .for_each(|event_counter| event_counter.write());
+    }
+
+    fn remove_dead_exclusive_pollers(
+        &self,
+        pollers: &mut VecDeque<(Weak<EventCounter>, IoEvents)>,
+    ) {
+        pollers.retain(|(event_counter, _)| {
+            let is_alive = event_counter.strong_count() > 0;
+            if !is_alive {
+                self.num_exclusive_pollers.fetch_sub(1, Ordering::Relaxed);
+            }
+            is_alive
+        });
+    }
 }
 
 /// A poller gets notified when its associated pollees have interesting events.
@@ -137,6 +264,8 @@ pub struct Poller {
     event_counter: Arc<EventCounter>,
     // All pollees that are interesting to this poller
     pollees: Vec<Weak<PolleeInner>>,
+    // Whether the poller competes with the other exclusive pollers for the events
+    is_exclusive: bool,
 }
 
 impl Default for Poller {
@@ -151,6 +280,24 @@ impl Poller {
         Self {
             event_counter: Arc::new(EventCounter::new()),
             pollees: Vec::new(),
+            is_exclusive: false,
+        }
+    }
+
+    /// Constructs a new exclusive `Poller`.
+    ///
+    /// Adding events to a pollee wakes up only one of its exclusive pollers (see
+    /// [`Pollee::add_events`]). This avoids the thundering herd problem when many
+    /// threads wait for a resource that only one of them can take, e.g., a pending
+    /// connection to accept.
+    ///
+    /// When an exclusive poller is dropped while the pollee still has interesting
+    /// events, another exclusive poller is woken up, so that no event is lost if the
+    /// woken poller stops polling without consuming the resource.
+    pub fn new_exclusive() -> Self {
+        Self {
+            is_exclusive: true,
+            ..Self::new()
         }
     }
 
@@ -175,6 +322,11 @@ impl Poller {
 
 impl Drop for Poller {
     fn drop(&mut self) {
+        if self.is_exclusive {
+            self.drop_exclusive();
+            return;
+        }
+
         let observer = self.observer();
 
         self.pollees
@@ -186,6 +338,25 @@ impl Drop for Poller {
     }
 }
 
+impl Poller {
+    fn drop_exclusive(&self) {
+        let event_counter = Arc::downgrade(&self.event_counter);
+
+        for pollee in self.pollees.iter().filter_map(Weak::upgrade) {
+            let Some(mask) = pollee.unregister_exclusive_poller(&event_counter) else {
+                continue;
+            };
+            // The wakeup may not be consumed, e.g., because the poller was interrupted by a
+            // signal, or there are more resources than wakeups. Pass it on to another
+            // exclusive poller, so that the remaining events are not lost.
+            let events = pollee.events() & mask;
+            if !events.is_empty() {
+                pollee.wake_one_exclusive_poller(events);
+            }
+        }
+    }
+}
+
 /// A counter for wait and wakeup.
 struct EventCounter {
     counter: AtomicUsize,
@@ -223,6 +394,20 @@ impl EventCounter {
         self.counter.fetch_add(1, Ordering::Relaxed);
         self.pauser.resume_one();
     }
+
+    /// Writes the counter only if there is no pending wakeup, returning whether
+    /// the counter was written.
+    pub fn write_if_idle(&self) -> bool {
+        if self
+            .counter
+            .compare_exchange(0, 1, Ordering::Relaxed, Ordering::Relaxed)
+            .is_err()
+        {
+            return false;
+        }
+        self.pauser.resume_one();
+        true
+    }
 }
 
 impl Observer<IoEvents> for EventCounter {
@@ -257,26 +442,187 @@ pub trait Pollable {
     /// The user must ensure that a call to `cond()` does not fail with `EAGAIN` when the
     /// interesting events occur. However, it is allowed to have spurious `EAGAIN` failures due to
     /// race conditions where the events are consumed by another thread.
-    fn wait_events<F, R>(&self, mask: IoEvents, mut cond: F) -> Result<R>
+    fn wait_events<F, R>(&self, mask: IoEvents, cond: F) -> Result<R>
     where
         Self: Sized,
         F: FnMut() -> Result<R>,
     {
-        let mut poller = Poller::new();
+        wait_events_with_poller(self, Poller::new(), mask, None, cond)
+    }
 
-        loop {
-            match cond() {
-                Err(err) if err.error() == Errno::EAGAIN => (),
-                result => return result,
-            };
+    /// Waits for events and performs event-based operations, with a timeout.
+    ///
+    /// This method is the same as [`Pollable::wait_events`], except that it fails with `EAGAIN`
+    /// if `cond()` still fails with `EAGAIN` after `timeout`. If `timeout` is `None`, the method
+    /// waits forever.
+    fn wait_events_with_timeout<F, R>(
+        &self,
+        mask: IoEvents,
+        timeout: Option<&Duration>,
+        cond: F,
+    ) -> Result<R>
+    where
+        Self: Sized,
+        F: FnMut() -> Result<R>,
+    {
+        wait_events_with_poller(self, Poller::new(), mask, timeout, cond)
+    }
 
-            let events = self.poll(mask, Some(&mut poller));
-            if !events.is_empty() {
-                continue;
-            }
+    /// Waits for events exclusively and performs event-based operations.
+    ///
+    /// This method is the same as [`Pollable::wait_events`], except that an exclusive
+    /// [`Poller`] is used (see [`Poller::new_exclusive`]). So `cond()` must consume the
+    /// resource indicated by the events when it succeeds, as `accept()` consumes a pending
+    /// connection.
+    fn wait_events_exclusive<F, R>(&self, mask: IoEvents, cond: F) -> Result<R>
+    where
+        Self: Sized,
+        F: FnMut() -> Result<R>,
+    {
+        wait_events_with_poller(self, Poller::new_exclusive(), mask, None, cond)
+    }
+}
+
+fn wait_events_with_poller<P, F, R>(
+    pollable: &P,
+    mut poller: Poller,
+    mask: IoEvents,
+    timeout: Option<&Duration>,
+    mut cond: F,
+) -> Result<R>
+where
+    P: Pollable,
+    F: FnMut() -> Result<R>,
+{
+    let deadline = timeout.map(|timeout| Jiffies::elapsed().as_duration() + *timeout);
+
+    loop {
+        match cond() {
+            Err(err) if err.error() == Errno::EAGAIN => (),
+            result => return result,
+        };
 
-            // TODO: Support timeout
+        let events = pollable.poll(mask, Some(&mut poller));
+        if !events.is_empty() {
+            continue;
+        }
+
+        let Some(deadline) = deadline else {
             poller.wait()?;
+            continue;
+        };
+
+        let remaining = deadline.saturating_sub(Jiffies::elapsed().as_duration());
+        if remaining.is_zero() {
+            return_errno_with_message!(Errno::EAGAIN, "the timeout expires");
+        }
+        match poller.wait_timeout(&remaining) {
+            Err(err) if err.error() == Errno::ETIME => {
+                // Check again before reporting the timeout, since the events may have
+                // happened just before the timeout.
+                return cond();
+            }
+            result => result?,
+        }
+    }
+}
+
+#[cfg(ktest)]
+mod test {
+    use ostd::prelude::*;
+
+    use super::*;
+    use crate::thread::{
+        kernel_thread::{KernelThreadExt, ThreadOptions},
+        Thread,
+    };
+
+    struct FakeBacklog {
+        pollee: Pollee,
+        nr_pending: AtomicUsize,
+        nr_tries: AtomicUsize,
+    }
+
+    impl FakeBacklog {
+        fn push(&self) {
+            self.nr_pending.fetch_add(1, Ordering::Relaxed);
+            self.pollee.add_events(IoEvents::IN);
+        }
+
+        fn try_accept(&self) -> Result<()> {
+            self.nr_tries.fetch_add(1, Ordering::Relaxed);
+            if self
+                .nr_pending
+                .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |n| n.checked_sub(1))
+                .is_err()
+            {
+                return_errno!(Errno::EAGAIN);
+            }
+            if self.nr_pending.load(Ordering::Relaxed) == 0 {
+                self.pollee.del_events(IoEvents::IN);
+            }
+            Ok(())
+        }
+
+        fn nr_exclusive_pollers(&self) -> usize {
+            self.pollee.inner.exclusive_pollers.lock().len()
+        }
+    }
+
+    impl Pollable for FakeBacklog {
+        fn poll(&self, mask: IoEvents, poller: Option<&mut Poller>) -> IoEvents {
+            self.pollee.poll(mask, poller)
+        }
+    }
+
+    #[ktest]
+    fn exclusive_wakeup() {
+        const NR_THREADS: usize = 8;
+
+        let backlog = Arc::new(FakeBacklog {
+            pollee: Pollee::new(IoEvents::empty()),
+            nr_pending: AtomicUsize::new(0),
+            nr_tries: AtomicUsize::new(0),
+        });
+
+        let threads: Vec<_> = (0..NR_THREADS)
+            .map(|_| {
+                let backlog = backlog.clone();
+                Thread::spawn_kernel_thread(ThreadOptions::new(move || {
+                    backlog
+                        .wait_events_exclusive(IoEvents::IN, || backlog.try_accept())
+                        .unwrap();
+                }))
+            })
+            .collect();
+        while backlog.nr_exclusive_pollers() < NR_THREADS {
+            Thread::yield_now();
+        }
+        let nr_tries = backlog.nr_tries.load(Ordering::Relaxed);
+
+        // A non-exclusive observer sees the event as well.
+        let mut poller = Poller::new();
+        assert!(backlog.poll(IoEvents::IN, Some(&mut poller)).is_empty());
+
+        // Only one thread wakes up to accept the single connection.
+        backlog.push();
+        while backlog.nr_exclusive_pollers() == NR_THREADS {
+            Thread::yield_now();
+        }
+        for _ in 0..100 {
+            Thread::yield_now();
+        }
+        assert_eq!(backlog.nr_tries.load(Ordering::Relaxed), nr_tries + 1);
+        assert_eq!(backlog.nr_exclusive_pollers(), NR_THREADS - 1);
+        assert!(poller.event_counter.counter.load(Ordering::Relaxed) > 0);
+
+        // Each connection wakes up one of the other threads.
+        for _ in 1..NR_THREADS {
+            backlog.push();
+        }
+        for thread in threads {
+            thread.join();
         }
+        assert_eq!(backlog.nr_pending.load(Ordering::Relaxed), 0);
     }
 }
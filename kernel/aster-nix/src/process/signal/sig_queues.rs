@@ -35,16 +35,27 @@ impl SigQueues {
         self.count.load(Ordering::Relaxed) == 0
     }
 
+    /// Enqueues a signal.
+    ///
+    /// The signal is silently dropped if it is a real-time signal and
+    /// too many real-time signals are pending.
     pub fn enqueue(&self, signal: Box<dyn Signal>) {
+        let _ = self.try_enqueue(signal);
+    }
+
+    /// Enqueues a signal, failing with `EAGAIN` if it is a real-time signal
+    /// and too many real-time signals are pending.
+    pub fn try_enqueue(&self, signal: Box<dyn Signal>) -> Result<()> {
         let signum = signal.num();
 
         let mut queues = self.queues.lock();
-        if queues.enqueue(signal) {
+        if queues.enqueue(signal)? {
             self.count.fetch_add(1, Ordering::Relaxed);
             // Avoid holding lock when notifying observers
             drop(queues);
             self.subject.notify_observers(&SigEvents::new(signum));
         }
+        Ok(())
     }
 
     pub fn dequeue(&self, blocked: &SigMask) -> Option<Box<dyn Signal>> {
@@ -72,6 +83,12 @@ impl SigQueues {
         self.queues.lock().has_pending(blocked)
     }
 
+    /// Notifies the observers of a signal that is enqueued somewhere else, e.g.,
+    /// to the signal queues shared by the threads of a process.
+    pub fn notify_observers(&self, signum: SigNum) {
+        self.subject.notify_observers(&SigEvents::new(signum));
+    }
+
     pub fn register_observer(
         &self,
         observer: Weak<dyn Observer<SigEvents>>,
@@ -91,9 +108,17 @@ impl Default for SigQueues {
     }
 }
 
+/// The maximum number of pending real-time signals.
+///
+/// Linux limits the number with `RLIMIT_SIGPENDING`, whose default value
+/// depends on the memory size. We use a fixed limit instead.
+const MAX_PENDING_RT_SIGS: usize = 1024;
+
 struct Queues {
     std_queues: Vec<Option<Box<dyn Signal>>>,
     rt_queues: Vec<VecDeque<Box<dyn Signal>>>,
+    /// The total number of signals in `rt_queues`.
+    rt_count: usize,
 }
 
 impl Queues {
@@ -103,10 +128,12 @@ impl Queues {
         Self {
             std_queues,
             rt_queues,
+            rt_count: 0,
         }
     }
 
-    fn enqueue(&mut self, signal: Box<dyn Signal>) -> bool {
+    /// Enqueues a signal and returns whether it is added to the queues.
+    fn enqueue(&mut self, signal: Box<dyn Signal>) -> Result<bool> {
         let signum = signal.num();
         if signum.is_std() {
             // Standard signals
@@ -125,16 +152,27 @@ impl Queues {
             let queue = self.get_std_queue_mut(signum);
             if queue.is_some() {
                 // If there is already a signal pending, just ignore all subsequent signals
-                return false;
+                return Ok(false);
             }
             *queue = Some(signal);
         } else {
             // Real-time signals
+            //
+            // From signal(7):
+            //
+            // Multiple instances of real-time signals can be queued.  By
+            // contrast, if multiple instances of a standard signal are
+            // delivered while that signal is currently blocked, then only one
+            // instance is queued.
+            if self.rt_count >= MAX_PENDING_RT_SIGS {
+                return_errno_with_message!(Errno::EAGAIN, "too many real-time signals are pending");
+            }
             let queue = self.get_rt_queue_mut(signum);
             queue.push_back(signal);
+            self.rt_count += 1;
         }
 
-        true
+        Ok(true)
     }
 
     fn dequeue(&mut self, blocked: &SigMask) -> Option<Box<dyn Signal>> {
@@ -187,6 +225,7 @@ impl Queues {
             let queue = self.get_rt_queue_mut(signum);
             let signal = queue.pop_front();
             if signal.is_some() {
+                self.rt_count -= 1;
                 return signal;
             }
         }
@@ -201,7 +240,11 @@ impl Queues {
             signal
                 .as_ref()
                 .is_some_and(|signal| !blocked.contains(signal.num()))
-        }) || self.rt_queues.iter().any(|rt_queue| !rt_queue.is_empty())
+        }) || self.rt_queues.iter().any(|rt_queue| {
+            rt_queue
+                .front()
+                .is_some_and(|signal| !blocked.contains(signal.num()))
+        })
     }
 
     fn get_std_queue_mut(&mut self, signum: SigNum) -> &mut Option<Box<dyn Signal>> {
@@ -45,6 +45,16 @@ impl FaultSignal {
         };
         FaultSignal { num, code, addr }
     }
+
+    /// Creates a `SIGBUS` for an access to a mapped page that does not
+    /// exist in the mapped object, e.g., a page beyond the EOF of a file.
+    pub fn new_bus_error(addr: Vaddr) -> FaultSignal {
+        FaultSignal {
+            num: SIGBUS,
+            code: BUS_ADRERR,
+            addr: Some(addr as u64),
+        }
+    }
 }
 
 impl Signal for FaultSignal {
@@ -53,8 +63,10 @@ impl Signal for FaultSignal {
     }
 
     fn to_info(&self) -> siginfo_t {
-        siginfo_t::new(self.num, self.code)
-        // info.set_si_addr(self.addr.unwrap_or_default() as *const c_void);
-        // info
+        let mut info = siginfo_t::new(self.num, self.code);
+        if let Some(addr) = self.addr {
+            info.set_si_addr(addr as Vaddr);
+        }
+        info
     }
 }
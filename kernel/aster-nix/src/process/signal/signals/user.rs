@@ -5,7 +5,7 @@
 use super::Signal;
 use crate::process::{
     signal::{
-        c_types::siginfo_t,
+        c_types::{siginfo_t, sigval_t},
         constants::{SI_QUEUE, SI_TKILL, SI_USER},
         sig_num::SigNum,
     },
@@ -24,7 +24,7 @@ pub struct UserSignal {
 pub enum UserSignalKind {
     Kill,
     Tkill,
-    Sigqueue,
+    Sigqueue(sigval_t),
 }
 
 impl UserSignal {
@@ -55,14 +55,15 @@ impl Signal for UserSignal {
         let code = match self.kind {
             UserSignalKind::Kill => SI_USER,
             UserSignalKind::Tkill => SI_TKILL,
-            UserSignalKind::Sigqueue => SI_QUEUE,
+            UserSignalKind::Sigqueue(_) => SI_QUEUE,
         };
 
-        siginfo_t::new(self.num, code)
-        // info.set_si_pid(self.pid);
-        // info.set_si_uid(self.uid);
-        // if let UserSignalKind::Sigqueue(val) = self.kind {
-        //     info.set_si_value(val);
-        // }
+        let mut info = siginfo_t::new(self.num, code);
+        info.set_si_pid(self.pid);
+        info.set_si_uid(self.uid);
+        if let UserSignalKind::Sigqueue(val) = self.kind {
+            info.set_si_value(val);
+        }
+        info
     }
 }
@@ -75,7 +75,7 @@ pub fn wait_child_exit(
 }
 
 /// Free zombie child with pid, returns the exit code of child process.
-fn reap_zombie_child(process: &Process, pid: Pid) -> ExitCode {
+pub(super) fn reap_zombie_child(process: &Process, pid: Pid) -> ExitCode {
     let child_process = process.children().lock().remove(&pid).unwrap();
     assert!(child_process.is_zombie());
     for thread in &*child_process.threads().lock() {
@@ -0,0 +1,116 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The recent CPU usage of tasks.
+//!
+//! The usage is an exponential moving average of the ticks that a task runs. It
+//! is charged for each tick that the task runs and decays for each tick that
+//! passes, whether the task is running or sleeping. So a task that sleeps most
+//! of the time has a low usage, and the usage of a task that alternates bursts
+//! and sleeps converges to a value proportional to the fraction of time that
+//! it runs.
+
+/// The decaying CPU usage of a task.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CpuUsage {
+    load: u32,
+    /// The tick that the load is decayed to.
+    last_update: u64,
+}
+
+impl CpuUsage {
+    /// The load retains `1 - 2^(-DECAY_SHIFT)` of itself after each tick, so the
+    /// half-life of the load is about 22 ticks.
+    const DECAY_SHIFT: u32 = 5;
+    /// The load charged for each tick that the task runs.
+    const TICK_LOAD: u32 = 1 << 10;
+    /// The load of a task that runs all the time.
+    const MAX_LOAD: u32 = Self::TICK_LOAD << Self::DECAY_SHIFT;
+    /// The tasks whose load is below this value are considered interactive.
+    const INTERACTIVE_LOAD: u32 = Self::MAX_LOAD / 4;
+    /// The number of ticks after which the maximum load decays to zero.
+    const MAX_DECAY_TICKS: u64 = 32 << Self::DECAY_SHIFT;
+
+    /// Charges the usage for running the tick of `now`.
+    pub fn charge_tick(&mut self, now: u64) {
+        self.decay_to(now);
+        self.load = (self.load + Self::TICK_LOAD).min(Self::MAX_LOAD);
+    }
+
+    /// Decays the usage for the ticks passed until `now`.
+    pub fn decay_to(&mut self, now: u64) {
+        let nr_ticks = now.saturating_sub(self.last_update);
+        self.last_update = self.last_update.max(now);
+
+        if nr_ticks >= Self::MAX_DECAY_TICKS {
+            self.load = 0;
+            return;
+        }
+        for _ in 0..nr_ticks {
+            if self.load == 0 {
+                break;
+            }
+            self.load -= self.load.div_ceil(1 << Self::DECAY_SHIFT);
+        }
+    }
+
+    /// Returns whether the task has consumed little CPU recently.
+    pub fn is_interactive(&self) -> bool {
+        self.load < Self::INTERACTIVE_LOAD
+    }
+}
+
+#[cfg(ktest)]
+mod test {
+    use ostd::prelude::*;
+
+    use super::*;
+
+    fn percentage(usage: &CpuUsage) -> u32 {
+        usage.load * 100 / CpuUsage::MAX_LOAD
+    }
+
+    #[ktest]
+    fn decay() {
+        let mut usage = CpuUsage::default();
+        for now in 1..=CpuUsage::MAX_DECAY_TICKS {
+            usage.charge_tick(now);
+        }
+        assert!(percentage(&usage) >= 90);
+        assert!(!usage.is_interactive());
+
+        // The usage decays while the task is sleeping.
+        usage.decay_to(CpuUsage::MAX_DECAY_TICKS * 3 / 2);
+        assert!(usage.is_interactive());
+        usage.decay_to(CpuUsage::MAX_DECAY_TICKS * 2);
+        assert_eq!(percentage(&usage), 0);
+    }
+
+    #[ktest]
+    fn bursts_and_sleeps_converge() {
+        const BURST_TICKS: u64 = 2;
+        const PERIOD_TICKS: u64 = 10;
+
+        let mut usage = CpuUsage::default();
+        let mut now = 0;
+        let mut run_period = |usage: &mut CpuUsage| {
+            for _ in 0..BURST_TICKS {
+                now += 1;
+                usage.charge_tick(now);
+            }
+            now += PERIOD_TICKS - BURST_TICKS;
+            usage.decay_to(now);
+            percentage(usage)
+        };
+
+        for _ in 0..100 {
+            run_period(&mut usage);
+        }
+        // The usage settles around the fraction of time that the task runs, and it
+        // does not cross the interactive threshold back and forth.
+        for _ in 0..100 {
+            let percentage = run_period(&mut usage);
+            assert!((15..=25).contains(&percentage));
+            assert!(usage.is_interactive());
+        }
+    }
+}
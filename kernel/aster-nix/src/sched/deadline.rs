@@ -0,0 +1,135 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The parameters and the admission control of deadline tasks.
+//!
+//! A deadline task requests `runtime` nanoseconds of CPU time in every
+//! `period` nanoseconds, which must be given within `deadline` nanoseconds
+//! after the period starts. The fraction `runtime / period` is the bandwidth
+//! of the task. A task is admitted only if the total bandwidth of all the
+//! deadline tasks does not exceed one CPU, so that the earliest deadline
+//! first (EDF) scheduling can meet all the deadlines.
+
+use crate::prelude::*;
+
+/// The parameters of a deadline task, in nanoseconds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DeadlineParams {
+    runtime: u64,
+    deadline: u64,
+    period: u64,
+}
+
+impl DeadlineParams {
+    /// Creates new deadline parameters.
+    ///
+    /// The parameters must satisfy `0 < runtime <= deadline <= period`.
+    pub fn new(runtime: u64, deadline: u64, period: u64) -> Result<Self> {
+        if runtime == 0 || runtime > deadline || deadline > period {
+            return_errno_with_message!(Errno::EINVAL, "invalid deadline parameters");
+        }
+        Ok(Self {
+            runtime,
+            deadline,
+            period,
+        })
+    }
+
+    /// Returns the CPU time that the task requests in each period.
+    pub fn runtime(&self) -> u64 {
+        self.runtime
+    }
+
+    /// Returns the relative deadline.
+    pub fn deadline(&self) -> u64 {
+        self.deadline
+    }
+
+    /// Returns the period.
+    pub fn period(&self) -> u64 {
+        self.period
+    }
+
+    /// Returns the bandwidth in the fixed-point format, where
+    /// [`BW_UNIT`] stands for one CPU.
+    fn bandwidth(&self) -> u64 {
+        ((self.runtime as u128) << BW_SHIFT).div_ceil(self.period as u128) as u64
+    }
+}
+
+const BW_SHIFT: u32 = 20;
+const BW_UNIT: u64 = 1 << BW_SHIFT;
+
+/// The bandwidth admission control of deadline tasks.
+pub(super) struct DeadlineBandwidth {
+    total: SpinLock<u64>,
+}
+
+impl DeadlineBandwidth {
+    pub(super) const fn new() -> Self {
+        Self {
+            total: SpinLock::new(0),
+        }
+    }
+
+    /// Replaces the reserved bandwidth of `old` with that of `new`.
+    ///
+    /// Fails with `EBUSY` if the total bandwidth would exceed one CPU.
+    pub(super) fn change(
+        &self,
+        old: Option<&DeadlineParams>,
+        new: Option<&DeadlineParams>,
+    ) -> Result<()> {
+        let old_bw = old.map_or(0, DeadlineParams::bandwidth);
+        let new_bw = new.map_or(0, DeadlineParams::bandwidth);
+
+        let mut total = self.total.lock_irq_disabled();
+        let new_total = *total - old_bw + new_bw;
+        if new_bw > old_bw && new_total > BW_UNIT {
+            return_errno_with_message!(Errno::EBUSY, "the deadline tasks are over-committed");
+        }
+        *total = new_total;
+        Ok(())
+    }
+}
+
+/// The scheduling state of a deadline task.
+#[derive(Debug, Clone, Copy, Default)]
+pub(super) struct DeadlineState {
+    /// The absolute deadline of the current period, in nanoseconds since boot.
+    pub(super) deadline: u64,
+    /// The runtime left in the current period.
+    pub(super) runtime_left: u64,
+}
+
+/// The bandwidth reserved by all the deadline tasks in the system.
+pub(super) static DEADLINE_BANDWIDTH: DeadlineBandwidth = DeadlineBandwidth::new();
+
+#[cfg(ktest)]
+mod test {
+    use ostd::prelude::*;
+
+    use super::*;
+
+    #[ktest]
+    fn invalid_params() {
+        assert!(DeadlineParams::new(0, 10, 10).is_err());
+        assert!(DeadlineParams::new(20, 10, 30).is_err());
+        assert!(DeadlineParams::new(10, 30, 20).is_err());
+    }
+
+    #[ktest]
+    fn admission_control() {
+        let bandwidth = DeadlineBandwidth::new();
+        let half = DeadlineParams::new(50, 100, 100).unwrap();
+        let quarter = DeadlineParams::new(25, 100, 100).unwrap();
+
+        bandwidth.change(None, Some(&half)).unwrap();
+        bandwidth.change(None, Some(&quarter)).unwrap();
+        let err = bandwidth.change(None, Some(&half)).unwrap_err();
+        assert_eq!(err.error(), Errno::EBUSY);
+
+        // The bandwidth is available again after being released.
+        bandwidth.change(Some(&quarter), None).unwrap();
+        bandwidth.change(None, Some(&half)).unwrap();
+    }
+}
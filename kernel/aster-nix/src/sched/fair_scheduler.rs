@@ -0,0 +1,998 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use core::sync::atomic::Ordering;
+
+use ostd::{
+    arch::timer::{timer_freq, Jiffies},
+    cpu::{num_cpus, this_cpu},
+    task::{
+        scheduler::{inject_scheduler, EnqueueFlags, LocalRunQueue, Scheduler, UpdateFlags},
+        AtomicCpuId, Task,
+    },
+};
+use spin::Once;
+
+use super::{
+    deadline::{DeadlineParams, DeadlineState},
+    nice::Nice,
+    priority_scheduler::TimeSlice,
+    select_cpu::select_cpu,
+    task_group, task_thread,
+};
+use crate::{
+    prelude::*,
+    process::{posix_thread::PosixThreadExt, Pid},
+};
+
+static FAIR_SCHEDULER: Once<&'static FairScheduler<Task>> = Once::new();
+
+pub fn init() {
+    let fair_scheduler = Box::new(FairScheduler::default());
+    let scheduler: &'static FairScheduler<Task> = Box::<FairScheduler<Task>>::leak(fair_scheduler);
+    FAIR_SCHEDULER.call_once(|| scheduler);
+    inject_scheduler(scheduler);
+}
+
+/// Applies the changed scheduling parameters of a runnable task.
+///
+/// This should be called after the priority, the nice value, or the deadline
+/// parameters of the task are changed. A queued task is moved to the queue that
+/// matches its new parameters. A running task gets its new weight at once and is
+/// put back to the right queue when it is switched out. Nothing is done if the
+/// fair scheduler is not in use.
+pub fn requeue(task: &Arc<Task>) {
+    if let Some(scheduler) = FAIR_SCHEDULER.get() {
+        scheduler.requeue(task);
+    }
+}
+
+/// The fair scheduler.
+///
+/// Deadline tasks are placed in the `deadline_entities` tree and are
+/// scheduled with the earliest deadline first (EDF) policy. They take
+/// precedence over all the other tasks. A deadline task that exhausts its
+/// runtime is throttled until its next period starts.
+/// Real-time tasks are placed in the `real_time_entities` queue and
+/// are prioritized over normal tasks during scheduling.
+/// Normal tasks are ordered by their virtual runtime (vruntime) in the
+/// `normal_entities` tree, and the one with the smallest vruntime is
+/// always picked to run next. The vruntime of a running task grows at
+/// a rate inversely proportional to its weight, which is determined by
+/// its nice value.
+struct FairScheduler<T: FairSchedInfo> {
+    rq: Vec<SpinLock<FairRunQueue<T>>>,
+}
+
+impl<T: FairSchedInfo> FairScheduler<T> {
+    fn new(nr_cpus: u32) -> Self {
+        let mut rq = Vec::with_capacity(nr_cpus as usize);
+        for _ in 0..nr_cpus {
+            rq.push(SpinLock::new(FairRunQueue::new()));
+        }
+        Self { rq }
+    }
+
+    /// Selects a cpu for task to run on.
+    fn select_cpu(&self, runnable: &Arc<T>) -> u32 {
+        let group = runnable.group();
+        select_cpu(
+            runnable.last_cpu(),
+            self.rq.len() as u32,
+            |cpu_id| self.rq[cpu_id as usize].lock_irq_disabled().load(),
+            |cpu_id| {
+                group.map_or(0, |group| {
+                    self.rq[cpu_id as usize]
+                        .lock_irq_disabled()
+                        .nr_group_members(group)
+                })
+            },
+        )
+    }
+
+    /// Applies the changed scheduling parameters of a runnable task.
+    fn requeue(&self, runnable: &Arc<T>) {
+        let Some(cpu_id) = runnable.cpu().get() else {
+            return;
+        };
+        self.rq[cpu_id as usize]
+            .lock_irq_disabled()
+            .requeue(runnable);
+    }
+}
+
+impl<T: Sync + Send + FairSchedInfo> Scheduler<T> for FairScheduler<T> {
+    fn enqueue(&self, runnable: Arc<T>, flags: EnqueueFlags) -> Option<u32> {
+        let mut still_in_rq = false;
+        let target_cpu = {
+            let mut cpu_id = self.select_cpu(&runnable);
+            if let Err(task_cpu_id) = runnable.cpu().set_if_is_none(cpu_id) {
+                debug_assert!(flags != EnqueueFlags::Spawn);
+                still_in_rq = true;
+                cpu_id = task_cpu_id;
+            }
+
+            cpu_id
+        };
+
+        let mut rq = self.rq[target_cpu as usize].lock_irq_disabled();
+        if still_in_rq && let Err(_) = runnable.cpu().set_if_is_none(target_cpu) {
+            return None;
+        }
+
+        let now = Jiffies::elapsed().as_u64() * tick_ns();
+        let should_preempt = rq.enqueue_entity(FairSchedEntity::new(runnable), flags, now);
+        should_preempt.then_some(target_cpu)
+    }
+
+    fn local_rq_with(&self, f: &mut dyn FnMut(&dyn LocalRunQueue<T>)) {
+        let local_rq: &FairRunQueue<T> = &self.rq[this_cpu() as usize].lock_irq_disabled();
+        f(local_rq);
+    }
+
+    fn local_mut_rq_with(&self, f: &mut dyn FnMut(&mut dyn LocalRunQueue<T>)) {
+        let local_rq: &mut FairRunQueue<T> = &mut self.rq[this_cpu() as usize].lock_irq_disabled();
+        f(local_rq);
+    }
+}
+
+impl Default for FairScheduler<Task> {
+    fn default() -> Self {
+        Self::new(num_cpus())
+    }
+}
+
+/// Returns the length of a scheduling tick in nanoseconds.
+fn tick_ns() -> u64 {
+    1_000_000_000 / timer_freq()
+}
+
+/// Returns the period in which every runnable normal task is expected to run once.
+///
+/// A woken task is placed at most half of this period before the
+/// `min_vruntime` of the runqueue, so that a task that has slept for a
+/// long time cannot monopolize the CPU after being woken up.
+fn sched_latency_ns() -> u64 {
+    6 * tick_ns()
+}
+
+/// Returns the minimum vruntime lead that the current task must have over the
+/// leftmost task before being preempted.
+fn sched_granularity_ns() -> u64 {
+    tick_ns()
+}
+
+struct FairRunQueue<T: FairSchedInfo> {
+    current: Option<FairSchedEntity<T>>,
+    /// The deadline entities, keyed by their absolute deadline and a unique
+    /// sequence number that breaks ties in FIFO order.
+    deadline_entities: BTreeMap<(u64, u64), FairSchedEntity<T>>,
+    /// The deadline entities that have exhausted their runtime and wait for
+    /// their next periods.
+    throttled_entities: Vec<FairSchedEntity<T>>,
+    real_time_entities: VecDeque<FairSchedEntity<T>>,
+    /// The normal entities, keyed by their vruntime and a unique sequence
+    /// number that breaks ties in FIFO order.
+    normal_entities: BTreeMap<(u64, u64), FairSchedEntity<T>>,
+    /// The number of entities in this runqueue, including the current one, of each
+    /// task group.
+    nr_group_members: BTreeMap<Pid, usize>,
+    /// The monotonically increasing lower bound of the vruntime of the
+    /// normal entities in this runqueue.
+    min_vruntime: u64,
+    /// The time of this runqueue in nanoseconds since boot, which is advanced
+    /// by the ticks.
+    clock: u64,
+    next_seq: u64,
+}
+
+impl<T: FairSchedInfo> FairRunQueue<T> {
+    pub fn new() -> Self {
+        Self {
+            current: None,
+            deadline_entities: BTreeMap::new(),
+            throttled_entities: Vec::new(),
+            real_time_entities: VecDeque::new(),
+            normal_entities: BTreeMap::new(),
+            nr_group_members: BTreeMap::new(),
+            min_vruntime: 0,
+            clock: 0,
+            next_seq: 0,
+        }
+    }
+
+    /// Enqueues a new entity at time `now`, in nanoseconds since boot.
+    ///
+    /// This method returns whether the current entity should be preempted.
+    fn enqueue_entity(
+        &mut self,
+        mut entity: FairSchedEntity<T>,
+        flags: EnqueueFlags,
+        now: u64,
+    ) -> bool {
+        self.clock = self.clock.max(now);
+        if let Some(group) = entity.group {
+            *self.nr_group_members.entry(group).or_default() += 1;
+        }
+
+        if let Some(ref mut dl) = entity.deadline {
+            dl.replenish_if_needed(self.clock);
+            if dl.is_throttled() {
+                self.throttled_entities.push(entity);
+                return false;
+            }
+            let deadline = dl.state.deadline;
+            let should_preempt = self
+                .current
+                .as_ref()
+                .and_then(|current| current.deadline.as_ref())
+                .map_or(true, |current_dl| {
+                    current_dl.is_throttled() || deadline < current_dl.state.deadline
+                });
+            self.push_deadline_entity(entity);
+            return should_preempt;
+        }
+
+        if entity.is_real_time() {
+            self.real_time_entities.push_back(entity);
+            return self.current.as_ref().map_or(true, |current| {
+                current.is_throttled() || (!current.is_deadline() && !current.is_real_time())
+            });
+        }
+
+        let min_vruntime = match flags {
+            EnqueueFlags::Spawn => self.min_vruntime,
+            EnqueueFlags::Wake => self.min_vruntime.saturating_sub(sched_latency_ns() / 2),
+        };
+        entity.vruntime = entity.vruntime.max(min_vruntime);
+
+        let should_preempt = match &self.current {
+            None => true,
+            Some(current) => {
+                current.is_throttled()
+                    || (!current.is_deadline()
+                        && !current.is_real_time()
+                        && entity.vruntime + sched_granularity_ns() < current.vruntime)
+            }
+        };
+
+        self.push_normal_entity(entity);
+        should_preempt
+    }
+
+    /// Returns the number of entities in this runqueue.
+    fn load(&self) -> usize {
+        self.current.iter().count()
+            + self.deadline_entities.len()
+            + self.throttled_entities.len()
+            + self.real_time_entities.len()
+            + self.normal_entities.len()
+    }
+
+    /// Returns the number of entities in this runqueue that belong to `group`.
+    fn nr_group_members(&self, group: Pid) -> usize {
+        self.nr_group_members.get(&group).copied().unwrap_or(0)
+    }
+
+    fn push_deadline_entity(&mut self, entity: FairSchedEntity<T>) {
+        let seq = self.next_seq;
+        self.next_seq += 1;
+        let deadline = entity.deadline.as_ref().unwrap().state.deadline;
+        self.deadline_entities.insert((deadline, seq), entity);
+    }
+
+    fn push_entity(&mut self, entity: FairSchedEntity<T>) {
+        if entity.is_throttled() {
+            self.throttled_entities.push(entity);
+        } else if entity.is_deadline() {
+            self.push_deadline_entity(entity);
+        } else if entity.is_real_time() {
+            self.real_time_entities.push_back(entity);
+        } else {
+            self.push_normal_entity(entity);
+        }
+    }
+
+    fn leftmost_deadline(&self) -> Option<u64> {
+        self.deadline_entities
+            .first_key_value()
+            .map(|((deadline, _), _)| *deadline)
+    }
+
+    fn push_normal_entity(&mut self, entity: FairSchedEntity<T>) {
+        let seq = self.next_seq;
+        self.next_seq += 1;
+        self.normal_entities.insert((entity.vruntime, seq), entity);
+    }
+
+    fn leftmost_vruntime(&self) -> Option<u64> {
+        self.normal_entities
+            .first_key_value()
+            .map(|((vruntime, _), _)| *vruntime)
+    }
+
+    fn update_min_vruntime(&mut self) {
+        let current_vruntime = self
+            .current
+            .as_ref()
+            .filter(|current| !current.is_deadline() && !current.is_real_time())
+            .map(|current| current.vruntime);
+
+        let min_vruntime = match (current_vruntime, self.leftmost_vruntime()) {
+            (Some(current), Some(leftmost)) => current.min(leftmost),
+            (Some(vruntime), None) | (None, Some(vruntime)) => vruntime,
+            (None, None) => return,
+        };
+
+        self.min_vruntime = self.min_vruntime.max(min_vruntime);
+    }
+
+    /// Moves the throttled entities whose next periods have started back to
+    /// the deadline tree.
+    fn replenish_throttled(&mut self) {
+        let clock = self.clock;
+        let mut i = 0;
+        while i < self.throttled_entities.len() {
+            let dl = self.throttled_entities[i].deadline.as_mut().unwrap();
+            if dl.replenish_time() > clock {
+                i += 1;
+                continue;
+            }
+            dl.replenish(clock);
+            let entity = self.throttled_entities.swap_remove(i);
+            self.push_deadline_entity(entity);
+        }
+    }
+
+    /// Applies the deadline parameters that are changed while the current
+    /// entity is running.
+    fn refresh_current_deadline(&mut self) {
+        let (clock, min_vruntime) = (self.clock, self.min_vruntime);
+        if let Some(ref mut current) = self.current {
+            current.refresh_deadline(clock, min_vruntime);
+        }
+    }
+
+    /// Applies the changed scheduling parameters of `runnable`.
+    ///
+    /// A queued entity is moved to the queue that matches its new parameters.
+    /// The current entity only gets its new weight and deadline parameters, since
+    /// it is put back to the right queue when it is switched out. Nothing is done
+    /// if `runnable` is not in this runqueue.
+    fn requeue(&mut self, runnable: &Arc<T>) {
+        let (clock, min_vruntime) = (self.clock, self.min_vruntime);
+        if let Some(ref mut current) = self.current
+            && Arc::ptr_eq(&current.runnable, runnable)
+        {
+            current.refresh_weight();
+            current.refresh_deadline(clock, min_vruntime);
+            return;
+        }
+
+        let Some(mut entity) = self.remove_queued_entity(runnable) else {
+            return;
+        };
+        entity.refresh_weight();
+        entity.refresh_deadline(clock, min_vruntime);
+        if !entity.is_deadline() && !entity.is_real_time() {
+            // The vruntime has not grown while the entity was a real-time entity.
+            entity.vruntime = entity.vruntime.max(min_vruntime);
+        }
+        self.push_entity(entity);
+    }
+
+    /// Removes the queued entity of `runnable`, which is not the current entity.
+    fn remove_queued_entity(&mut self, runnable: &Arc<T>) -> Option<FairSchedEntity<T>> {
+        let is_runnable = |entity: &FairSchedEntity<T>| Arc::ptr_eq(&entity.runnable, runnable);
+
+        if let Some(index) = self.real_time_entities.iter().position(is_runnable) {
+            return self.real_time_entities.remove(index);
+        }
+        if let Some(index) = self.throttled_entities.iter().position(is_runnable) {
+            return Some(self.throttled_entities.swap_remove(index));
+        }
+        for entities in [&mut self.normal_entities, &mut self.deadline_entities] {
+            let key = entities
+                .iter()
+                .find(|(_, entity)| is_runnable(entity))
+                .map(|(key, _)| *key);
+            if let Some(key) = key {
+                return entities.remove(&key);
+            }
+        }
+
+        None
+    }
+}
+
+impl<T: Sync + Send + FairSchedInfo> LocalRunQueue<T> for FairRunQueue<T> {
+    fn current(&self) -> Option<&Arc<T>> {
+        self.current.as_ref().map(|entity| &entity.runnable)
+    }
+
+    fn update_current(&mut self, flags: UpdateFlags) -> bool {
+        self.refresh_current_deadline();
+
+        match flags {
+            UpdateFlags::Tick => {
+                self.clock += tick_ns();
+                self.replenish_throttled();
+
+                let Some(ref mut current_entity) = self.current else {
+                    return false;
+                };
+
+                if let Some(ref mut dl) = current_entity.deadline {
+                    let is_throttled = dl.consume(tick_ns(), self.clock);
+                    let current_deadline = dl.state.deadline;
+                    return is_throttled
+                        || self
+                            .leftmost_deadline()
+                            .is_some_and(|leftmost| leftmost < current_deadline);
+                }
+
+                if current_entity.is_real_time() {
+                    return current_entity.tick() || !self.deadline_entities.is_empty();
+                }
+
+                current_entity.tick();
+                let current_vruntime = current_entity.vruntime;
+                self.update_min_vruntime();
+
+                !self.deadline_entities.is_empty()
+                    || !self.real_time_entities.is_empty()
+                    || self.leftmost_vruntime().is_some_and(|leftmost| {
+                        leftmost + sched_granularity_ns() < current_vruntime
+                    })
+            }
+            _ => true,
+        }
+    }
+
+    fn pick_next_current(&mut self) -> Option<&Arc<T>> {
+        let next_entity = if !self.deadline_entities.is_empty() {
+            self.deadline_entities.pop_first().map(|(_, entity)| entity)
+        } else if !self.real_time_entities.is_empty() {
+            self.real_time_entities.pop_front()
+        } else {
+            self.normal_entities.pop_first().map(|(_, entity)| entity)
+        }?;
+        if let Some(prev_entity) = self.current.replace(next_entity) {
+            self.push_entity(prev_entity);
+        }
+        self.update_min_vruntime();
+
+        Some(&self.current.as_ref().unwrap().runnable)
+    }
+
+    fn dequeue_current(&mut self) -> Option<Arc<T>> {
+        self.current.take().map(|entity| {
+            if let Some(group) = entity.group {
+                let nr_members = self.nr_group_members.get_mut(&group).unwrap();
+                *nr_members -= 1;
+                if *nr_members == 0 {
+                    self.nr_group_members.remove(&group);
+                }
+            }
+
+            let runnable = entity.runnable;
+            runnable.set_vruntime(entity.vruntime);
+            if let Some(dl) = entity.deadline {
+                runnable.set_deadline_state(dl.state);
+            }
+            if let Some(cpu_id) = runnable.cpu().get() {
+                runnable.set_last_cpu(cpu_id);
+            }
+            runnable.cpu().set_to_none();
+
+            runnable
+        })
+    }
+}
+
+struct FairSchedEntity<T: FairSchedInfo> {
+    runnable: Arc<T>,
+    /// The time slice of real-time entities.
+    time_slice: TimeSlice,
+    /// The virtual runtime of normal entities, in nanoseconds.
+    vruntime: u64,
+    /// The vruntime increment of each tick.
+    vruntime_per_tick: u64,
+    /// The parameters and the state of deadline entities.
+    deadline: Option<DeadlineEntity>,
+    /// The task group, which is resolved once so that the CPU selection is cheap.
+    group: Option<Pid>,
+}
+
+impl<T: FairSchedInfo> FairSchedEntity<T> {
+    fn new(runnable: Arc<T>) -> Self {
+        let vruntime = runnable.vruntime();
+        let vruntime_per_tick = vruntime_per_tick(runnable.nice());
+        let deadline = runnable.deadline_params().map(|params| DeadlineEntity {
+            params,
+            state: runnable.deadline_state(),
+        });
+        let group = runnable.group();
+        Self {
+            runnable,
+            time_slice: TimeSlice::default(),
+            vruntime,
+            vruntime_per_tick,
+            deadline,
+            group,
+        }
+    }
+
+    fn is_deadline(&self) -> bool {
+        self.deadline.is_some()
+    }
+
+    fn is_throttled(&self) -> bool {
+        self.deadline
+            .as_ref()
+            .is_some_and(DeadlineEntity::is_throttled)
+    }
+
+    fn is_real_time(&self) -> bool {
+        self.runnable.is_real_time()
+    }
+
+    fn tick(&mut self) -> bool {
+        self.vruntime += self.vruntime_per_tick;
+        self.time_slice.elapse()
+    }
+
+    /// Applies the nice value of the runnable, which may have been changed since
+    /// the entity was created.
+    fn refresh_weight(&mut self) {
+        self.vruntime_per_tick = vruntime_per_tick(self.runnable.nice());
+    }
+
+    /// Applies the deadline parameters of the runnable, which may have been
+    /// changed since the entity was created, at time `now`.
+    fn refresh_deadline(&mut self, now: u64, min_vruntime: u64) {
+        let params = self.runnable.deadline_params();
+        if params == self.deadline.as_ref().map(|dl| dl.params) {
+            return;
+        }
+
+        self.deadline = params.map(|params| {
+            let mut dl = DeadlineEntity {
+                params,
+                state: DeadlineState::default(),
+            };
+            dl.replenish_if_needed(now);
+            dl
+        });
+        if self.deadline.is_none() {
+            // The vruntime has not grown while the entity was a deadline entity.
+            self.vruntime = self.vruntime.max(min_vruntime);
+        }
+    }
+}
+
+/// Returns the vruntime increment of each tick for a task with the nice value.
+fn vruntime_per_tick(nice: Nice) -> u64 {
+    tick_ns() * NICE_0_WEIGHT / nice_to_weight(nice)
+}
+
+struct DeadlineEntity {
+    params: DeadlineParams,
+    state: DeadlineState,
+}
+
+impl DeadlineEntity {
+    /// Starts a new period at time `now` if the entity cannot keep its
+    /// current deadline without exceeding its bandwidth.
+    ///
+    /// This is the wake-up rule of the constant bandwidth server (CBS).
+    fn replenish_if_needed(&mut self, now: u64) {
+        let state = &mut self.state;
+        let overflows = state.deadline <= now
+            || state.runtime_left as u128 * self.params.period() as u128
+                > (state.deadline - now) as u128 * self.params.runtime() as u128;
+        if overflows {
+            state.deadline = now + self.params.deadline();
+            state.runtime_left = self.params.runtime();
+        }
+    }
+
+    /// Checks if the entity has exhausted its runtime in the current period.
+    fn is_throttled(&self) -> bool {
+        self.state.runtime_left == 0
+    }
+
+    /// Returns the time when the next period starts.
+    fn replenish_time(&self) -> u64 {
+        self.state.deadline - self.params.deadline() + self.params.period()
+    }
+
+    /// Starts the next period at time `now`, which must not be earlier than
+    /// [`Self::replenish_time`].
+    fn replenish(&mut self, now: u64) {
+        self.state.deadline += self.params.period();
+        self.state.runtime_left = self.params.runtime();
+        self.replenish_if_needed(now);
+    }
+
+    /// Consumes the runtime at time `now` and returns whether the entity is
+    /// throttled.
+    ///
+    /// A throttled entity keeps running only if there is no other entity to
+    /// run, since the CPU cannot be idled while its current task is runnable.
+    fn consume(&mut self, runtime: u64, now: u64) -> bool {
+        if self.is_throttled() {
+            if now < self.replenish_time() {
+                return true;
+            }
+            self.replenish(now);
+        }
+
+        self.state.runtime_left = self.state.runtime_left.saturating_sub(runtime);
+        self.is_throttled()
+    }
+}
+
+/// The weight of a task whose nice value is 0.
+const NICE_0_WEIGHT: u64 = 1024;
+
+/// Converts a nice value to the weight of the task.
+///
+/// A task with one less nice value gets about 10% more CPU time. The table
+/// is from <https://elixir.bootlin.com/linux/v6.10.4/source/kernel/sched/core.c#L11381>.
+fn nice_to_weight(nice: Nice) -> u64 {
+    const NICE_TO_WEIGHT: [u64; 40] = [
+        88761, 71755, 56483, 46273, 36291, 29154, 23254, 18705, 14949, 11916, 9548, 7620, 6100,
+        4904, 3906, 3121, 2501, 1991, 1586, 1277, 1024, 820, 655, 526, 423, 335, 272, 215, 172,
+        137, 110, 87, 70, 56, 45, 36, 29, 23, 18, 15,
+    ];
+
+    NICE_TO_WEIGHT[(nice.to_raw() - Nice::MIN.to_raw()) as usize]
+}
+
+impl FairSchedInfo for Task {
+    fn cpu(&self) -> &AtomicCpuId {
+        self.cpu()
+    }
+
+    fn is_real_time(&self) -> bool {
+        self.is_real_time()
+    }
+
+    fn nice(&self) -> Nice {
+        let Some(thread) = task_thread(self) else {
+            return Nice::default();
+        };
+
+        match thread.as_posix_thread() {
+            Some(posix_thread) => posix_thread.process().nice().load(Ordering::Relaxed),
+            None => Nice::default(),
+        }
+    }
+
+    fn vruntime(&self) -> u64 {
+        task_thread(self).map_or(0, |thread| thread.sched_attr().vruntime())
+    }
+
+    fn set_vruntime(&self, vruntime: u64) {
+        if let Some(thread) = task_thread(self) {
+            thread.sched_attr().set_vruntime(vruntime);
+        }
+    }
+
+    fn last_cpu(&self) -> Option<u32> {
+        task_thread(self).and_then(|thread| thread.sched_attr().last_cpu())
+    }
+
+    fn set_last_cpu(&self, cpu_id: u32) {
+        if let Some(thread) = task_thread(self) {
+            thread.sched_attr().set_last_cpu(cpu_id);
+        }
+    }
+
+    fn deadline_params(&self) -> Option<DeadlineParams> {
+        task_thread(self).and_then(|thread| thread.sched_attr().deadline_params())
+    }
+
+    fn deadline_state(&self) -> DeadlineState {
+        task_thread(self).map_or(DeadlineState::default(), |thread| {
+            thread.sched_attr().deadline_state()
+        })
+    }
+
+    fn set_deadline_state(&self, state: DeadlineState) {
+        if let Some(thread) = task_thread(self) {
+            thread.sched_attr().set_deadline_state(state);
+        }
+    }
+
+    fn group(&self) -> Option<Pid> {
+        task_group(self)
+    }
+}
+
+trait FairSchedInfo {
+    fn cpu(&self) -> &AtomicCpuId;
+
+    fn is_real_time(&self) -> bool;
+
+    fn nice(&self) -> Nice;
+
+    /// Returns the vruntime saved when the task left the runqueue last time.
+    fn vruntime(&self) -> u64;
+
+    /// Saves the vruntime when the task leaves the runqueue.
+    fn set_vruntime(&self, vruntime: u64);
+
+    /// Returns the CPU that the task ran on last time.
+    fn last_cpu(&self) -> Option<u32>;
+
+    /// Saves the CPU that the task ran on when it leaves the runqueue.
+    fn set_last_cpu(&self, cpu_id: u32);
+
+    /// Returns the deadline parameters if the task is a deadline task.
+    fn deadline_params(&self) -> Option<DeadlineParams>;
+
+    /// Returns the deadline state saved when the task left the runqueue last time.
+    fn deadline_state(&self) -> DeadlineState;
+
+    /// Saves the deadline state when the task leaves the runqueue.
+    fn set_deadline_state(&self, state: DeadlineState);
+
+    /// Returns the task group that the task belongs to, if any.
+    ///
+    /// The members of a task group are preferentially run on distinct CPUs.
+    fn group(&self) -> Option<Pid>;
+}
+
+#[cfg(ktest)]
+mod test {
+    use core::sync::atomic::{AtomicBool, AtomicI8, AtomicU64};
+
+    use ostd::prelude::*;
+
+    use super::*;
+    use crate::sched::deadline::DeadlineBandwidth;
+
+    struct MockTask {
+        cpu: AtomicCpuId,
+        is_real_time: AtomicBool,
+        nice: AtomicI8,
+        vruntime: AtomicU64,
+        deadline_params: Option<DeadlineParams>,
+        group: Option<Pid>,
+    }
+
+    impl MockTask {
+        fn new(nice: Nice, vruntime: u64) -> Arc<Self> {
+            Arc::new(Self {
+                cpu: AtomicCpuId::default(),
+                is_real_time: AtomicBool::new(false),
+                nice: AtomicI8::new(nice.to_raw()),
+                vruntime: AtomicU64::new(vruntime),
+                deadline_params: None,
+                group: None,
+            })
+        }
+
+        fn new_in_group(group: Pid) -> Arc<Self> {
+            Arc::new(Self {
+                cpu: AtomicCpuId::default(),
+                is_real_time: AtomicBool::new(false),
+                nice: AtomicI8::new(0),
+                vruntime: AtomicU64::new(0),
+                deadline_params: None,
+                group: Some(group),
+            })
+        }
+
+        fn new_deadline(params: DeadlineParams) -> Arc<Self> {
+            Arc::new(Self {
+                cpu: AtomicCpuId::default(),
+                is_real_time: AtomicBool::new(false),
+                nice: AtomicI8::new(0),
+                vruntime: AtomicU64::new(0),
+                deadline_params: Some(params),
+                group: None,
+            })
+        }
+    }
+
+    impl FairSchedInfo for MockTask {
+        fn cpu(&self) -> &AtomicCpuId {
+            &self.cpu
+        }
+
+        fn is_real_time(&self) -> bool {
+            self.is_real_time.load(Ordering::Relaxed)
+        }
+
+        fn nice(&self) -> Nice {
+            Nice::new(self.nice.load(Ordering::Relaxed))
+        }
+
+        fn vruntime(&self) -> u64 {
+            self.vruntime.load(Ordering::Relaxed)
+        }
+
+        fn set_vruntime(&self, vruntime: u64) {
+            self.vruntime.store(vruntime, Ordering::Relaxed);
+        }
+
+        fn last_cpu(&self) -> Option<u32> {
+            None
+        }
+
+        fn set_last_cpu(&self, _cpu_id: u32) {}
+
+        fn deadline_params(&self) -> Option<DeadlineParams> {
+            self.deadline_params
+        }
+
+        fn deadline_state(&self) -> DeadlineState {
+            DeadlineState::default()
+        }
+
+        fn set_deadline_state(&self, _state: DeadlineState) {}
+
+        fn group(&self) -> Option<Pid> {
+            self.group
+        }
+    }
+
+    /// Runs `nr_ticks` ticks and returns the number of ticks that `task` runs.
+    fn run_ticks(rq: &mut FairRunQueue<MockTask>, task: &Arc<MockTask>, nr_ticks: usize) -> usize {
+        let mut task_ticks = 0;
+        for _ in 0..nr_ticks {
+            if Arc::ptr_eq(rq.current().unwrap(), task) {
+                task_ticks += 1;
+            }
+            if rq.update_current(UpdateFlags::Tick) {
+                rq.pick_next_current();
+            }
+        }
+        task_ticks
+    }
+
+    #[ktest]
+    fn equal_weight_fairness() {
+        const NR_TICKS: usize = 1000;
+
+        let mut rq = FairRunQueue::new();
+        let task_a = MockTask::new(Nice::default(), 0);
+        let task_b = MockTask::new(Nice::default(), 0);
+        rq.enqueue_entity(FairSchedEntity::new(task_a.clone()), EnqueueFlags::Spawn, 0);
+        rq.enqueue_entity(FairSchedEntity::new(task_b), EnqueueFlags::Spawn, 0);
+        rq.pick_next_current();
+
+        let ticks_a = run_ticks(&mut rq, &task_a, NR_TICKS);
+        let ticks_b = NR_TICKS - ticks_a;
+        assert!(ticks_a.abs_diff(ticks_b) <= NR_TICKS / 20);
+    }
+
+    #[ktest]
+    fn count_group_members() {
+        let mut rq = FairRunQueue::new();
+        let task_a = MockTask::new_in_group(1);
+        let task_b = MockTask::new_in_group(1);
+        let task_c = MockTask::new_in_group(2);
+        let task_d = MockTask::new(Nice::default(), 0);
+        for task in [&task_a, &task_b, &task_c, &task_d] {
+            rq.enqueue_entity(FairSchedEntity::new(task.clone()), EnqueueFlags::Spawn, 0);
+        }
+        assert_eq!(rq.nr_group_members(1), 2);
+        assert_eq!(rq.nr_group_members(2), 1);
+        assert_eq!(rq.nr_group_members(3), 0);
+
+        // The current entity is counted until it leaves the runqueue.
+        assert!(Arc::ptr_eq(rq.pick_next_current().unwrap(), &task_a));
+        assert_eq!(rq.nr_group_members(1), 2);
+        rq.dequeue_current();
+        assert_eq!(rq.nr_group_members(1), 1);
+        assert_eq!(rq.nr_group_members(2), 1);
+    }
+
+    #[ktest]
+    fn woken_task_vruntime_is_clamped() {
+        let mut rq = FairRunQueue::new();
+        let task_a = MockTask::new(Nice::default(), 0);
+        rq.enqueue_entity(FairSchedEntity::new(task_a.clone()), EnqueueFlags::Spawn, 0);
+        rq.pick_next_current();
+        run_ticks(&mut rq, &task_a, 1000);
+
+        // A task that has slept for a long time must not be far behind.
+        let task_b = MockTask::new(Nice::default(), 0);
+        rq.enqueue_entity(FairSchedEntity::new(task_b.clone()), EnqueueFlags::Wake, 0);
+        let ticks_b = run_ticks(&mut rq, &task_b, 100);
+        assert!(ticks_b <= 50 + (sched_latency_ns() / tick_ns()) as usize);
+    }
+
+    #[ktest]
+    fn earliest_deadline_first() {
+        let bandwidth = DeadlineBandwidth::new();
+        let late_params =
+            DeadlineParams::new(2 * tick_ns(), 15 * tick_ns(), 20 * tick_ns()).unwrap();
+        let early_params =
+            DeadlineParams::new(2 * tick_ns(), 10 * tick_ns(), 10 * tick_ns()).unwrap();
+        bandwidth.change(None, Some(&late_params)).unwrap();
+        bandwidth.change(None, Some(&early_params)).unwrap();
+
+        let mut rq = FairRunQueue::new();
+        let normal_task = MockTask::new(Nice::default(), 0);
+        let late_task = MockTask::new_deadline(late_params);
+        let early_task = MockTask::new_deadline(early_params);
+        rq.enqueue_entity(FairSchedEntity::new(normal_task), EnqueueFlags::Spawn, 0);
+        rq.enqueue_entity(
+            FairSchedEntity::new(late_task.clone()),
+            EnqueueFlags::Spawn,
+            0,
+        );
+        rq.enqueue_entity(
+            FairSchedEntity::new(early_task.clone()),
+            EnqueueFlags::Spawn,
+            0,
+        );
+
+        assert!(Arc::ptr_eq(rq.pick_next_current().unwrap(), &early_task));
+        // `early_task` is throttled after its runtime is exhausted.
+        assert_eq!(run_ticks(&mut rq, &early_task, 2), 2);
+        assert!(Arc::ptr_eq(rq.current().unwrap(), &late_task));
+    }
+
+    #[ktest]
+    fn runtime_is_enforced() {
+        const NR_PERIODS: usize = 10;
+        const PERIOD_TICKS: usize = 10;
+
+        let bandwidth = DeadlineBandwidth::new();
+        let params = DeadlineParams::new(
+            2 * tick_ns(),
+            PERIOD_TICKS as u64 * tick_ns(),
+            PERIOD_TICKS as u64 * tick_ns(),
+        )
+        .unwrap();
+        bandwidth.change(None, Some(&params)).unwrap();
+
+        let mut rq = FairRunQueue::new();
+        let normal_task = MockTask::new(Nice::default(), 0);
+        let deadline_task = MockTask::new_deadline(params);
+        rq.enqueue_entity(FairSchedEntity::new(normal_task), EnqueueFlags::Spawn, 0);
+        rq.enqueue_entity(
+            FairSchedEntity::new(deadline_task.clone()),
+            EnqueueFlags::Spawn,
+            0,
+        );
+        rq.pick_next_current();
+
+        // The deadline task runs for exactly its runtime in each period, and the
+        // normal task runs for the rest of the time.
+        let deadline_ticks = run_ticks(&mut rq, &deadline_task, NR_PERIODS * PERIOD_TICKS);
+        assert_eq!(deadline_ticks, NR_PERIODS * 2);
+    }
+
+    #[ktest]
+    fn requeue_after_priority_change() {
+        let mut rq = FairRunQueue::new();
+        let task_a = MockTask::new(Nice::default(), 0);
+        let task_b = MockTask::new(Nice::default(), 0);
+        rq.enqueue_entity(FairSchedEntity::new(task_a.clone()), EnqueueFlags::Spawn, 0);
+        rq.enqueue_entity(FairSchedEntity::new(task_b.clone()), EnqueueFlags::Spawn, 0);
+
+        // `task_b` becomes real-time while it is queued behind `task_a`.
+        task_b.is_real_time.store(true, Ordering::Relaxed);
+        rq.requeue(&task_b);
+        assert_eq!(rq.real_time_entities.len(), 1);
+        assert!(Arc::ptr_eq(rq.pick_next_current().unwrap(), &task_b));
+
+        // The current entity gets its new weight at once.
+        task_b.is_real_time.store(false, Ordering::Relaxed);
+        task_b.nice.store(Nice::MAX.to_raw(), Ordering::Relaxed);
+        rq.requeue(&task_b);
+        let current = rq.current.as_ref().unwrap();
+        assert_eq!(current.vruntime_per_tick, vruntime_per_tick(Nice::MAX));
+
+        // It goes back to the normal tree when it is switched out.
+        assert!(Arc::ptr_eq(rq.pick_next_current().unwrap(), &task_a));
+        assert!(rq.real_time_entities.is_empty());
+        assert_eq!(rq.normal_entities.len(), 1);
+    }
+}
@@ -1,8 +1,198 @@
 // SPDX-License-Identifier: MPL-2.0
 
+mod cpu_usage;
+mod deadline;
+mod fair_scheduler;
 pub mod nice;
 mod priority_scheduler;
+mod sched_attr;
+mod select_cpu;
+
+use core::{ops::RangeInclusive, sync::atomic::Ordering};
+
+use ostd::{
+    boot::{kcmdline::ModuleArg, kernel_cmdline},
+    task::{Priority, Task},
+};
+
+pub use self::{
+    deadline::DeadlineParams,
+    sched_attr::{SchedAttr, SchedPolicy},
+};
+use crate::{
+    prelude::*,
+    process::{posix_thread::PosixThreadExt, Pid, Process},
+    sched::nice::Nice,
+    thread::Thread,
+};
 
 // There may be multiple scheduling policies in the system,
 // and subsequent schedulers can be placed under this module.
-pub use self::priority_scheduler::init;
+//
+// The scheduler can be selected with the `sched.policy` kernel command-line
+// argument. The preempt scheduler is used by default.
+pub fn init() {
+    match scheduler_policy() {
+        Some("fair") => fair_scheduler::init(),
+        _ => priority_scheduler::init(),
+    }
+}
+
+fn scheduler_policy() -> Option<&'static str> {
+    let module_args = kernel_cmdline().get_module_args("sched")?;
+
+    module_args.iter().find_map(|arg| match arg {
+        ModuleArg::KeyVal(name, value) if name.as_bytes() == "policy".as_bytes() => {
+            value.as_c_str().to_str().ok()
+        }
+        _ => None,
+    })
+}
+
+/// Returns the state of the runqueues in a format similar to Linux's `/proc/sched_debug`.
+///
+/// Returns `None` if the current scheduler does not support it.
+pub fn sched_debug() -> Option<String> {
+    let snapshot = priority_scheduler::snapshot()?;
+    Some(snapshot.iter().map(ToString::to_string).collect())
+}
+
+/// The range of the static priorities of the real-time policies.
+///
+/// Like Linux, a larger value represents a higher priority.
+pub const RT_PRIORITY_RANGE: RangeInclusive<u32> = 1..=99;
+
+/// Sets the scheduling policy and the real-time priority of a thread.
+///
+/// `rt_priority` must be in [`RT_PRIORITY_RANGE`] for the real-time policies,
+/// and must be zero for [`SchedPolicy::Normal`]. Otherwise, this function fails
+/// with `EINVAL`.
+///
+/// If the thread is runnable, it is moved to the queue that matches its new
+/// priority.
+///
+/// [`SchedPolicy::Deadline`] is not accepted, since it requires the deadline
+/// parameters. Use [`set_deadline`] instead.
+pub fn set_scheduler(thread: &Thread, policy: SchedPolicy, rt_priority: u32) -> Result<()> {
+    if policy == SchedPolicy::Deadline {
+        return_errno_with_message!(Errno::EINVAL, "the deadline parameters are missing");
+    }
+
+    let priority = if policy.is_real_time() {
+        if !RT_PRIORITY_RANGE.contains(&rt_priority) {
+            return_errno_with_message!(Errno::EINVAL, "the real-time priority is out of range");
+        }
+        // Map the highest real-time priority (99) to the highest task priority (0).
+        Priority::new((RT_PRIORITY_RANGE.end() - rt_priority) as u16)
+    } else {
+        if rt_priority != 0 {
+            return_errno_with_message!(Errno::EINVAL, "the priority must be zero");
+        }
+        let nice = thread
+            .as_posix_thread()
+            .map(|posix_thread| posix_thread.process().nice().load(Ordering::Relaxed))
+            .unwrap_or_default();
+        nice_to_priority(nice)
+    };
+
+    // A deadline task switching to another policy gives up its reserved bandwidth.
+    thread.sched_attr().set_deadline_params(None)?;
+    thread.sched_attr().set_policy(policy);
+
+    let task = thread.task();
+    task.set_priority(priority);
+    requeue(task);
+
+    Ok(())
+}
+
+/// Makes a thread a deadline task with the parameters.
+///
+/// This function fails with `EBUSY` if the deadline tasks in the system would be
+/// over-committed, and with `EINVAL` if the scheduler in use does not support
+/// deadline tasks.
+pub fn set_deadline(thread: &Thread, params: DeadlineParams) -> Result<()> {
+    // Only the fair scheduler enforces the deadlines and the runtime budgets.
+    if scheduler_policy() != Some("fair") {
+        return_errno_with_message!(
+            Errno::EINVAL,
+            "the deadline policy is not supported by the scheduler"
+        );
+    }
+
+    thread.sched_attr().set_deadline_params(Some(params))?;
+    thread.sched_attr().set_policy(SchedPolicy::Deadline);
+
+    Ok(())
+}
+
+/// Returns the task priority of a [`SchedPolicy::Normal`] thread with the nice value.
+///
+/// Like Linux, the nice values from -20 to 19 are mapped to the task priorities
+/// from 100 to 139, which are lower than all the real-time priorities.
+pub fn nice_to_priority(nice: Nice) -> Priority {
+    Priority::new((120 + nice.to_raw() as i16) as u16)
+}
+
+/// Sets the nice value of a process.
+///
+/// The priorities of its [`SchedPolicy::Normal`] threads are updated accordingly,
+/// while its real-time threads are not affected until they return to the normal
+/// policy.
+pub fn set_nice(process: &Process, nice: Nice) {
+    process.nice().store(nice, Ordering::Relaxed);
+
+    let priority = nice_to_priority(nice);
+    for thread in process.threads().lock().iter() {
+        if thread.sched_attr().policy().is_real_time() {
+            continue;
+        }
+        let task = thread.task();
+        task.set_priority(priority);
+        requeue(task);
+    }
+}
+
+/// Applies the changed scheduling parameters of a task to the scheduler in use.
+fn requeue(task: &Arc<Task>) {
+    match scheduler_policy() {
+        Some("fair") => fair_scheduler::requeue(task),
+        _ => priority_scheduler::requeue(task),
+    }
+}
+
+/// Returns the thread that a task belongs to.
+fn task_thread(task: &Task) -> Option<Arc<Thread>> {
+    task.data().downcast_ref::<Weak<Thread>>()?.upgrade()
+}
+
+/// Returns the task group that a task belongs to.
+///
+/// The threads of a process share an address space, so they form a task group
+/// that is identified by the process ID.
+fn task_group(task: &Task) -> Option<Pid> {
+    let thread = task_thread(task)?;
+    let posix_thread = thread.as_posix_thread()?;
+    Some(posix_thread.process().pid())
+}
+
+#[cfg(ktest)]
+mod test {
+    use ostd::prelude::*;
+
+    use super::*;
+
+    #[ktest]
+    fn nice_to_priority_mapping() {
+        assert_eq!(nice_to_priority(Nice::MIN).get(), 100);
+        assert_eq!(nice_to_priority(Nice::default()).get(), 120);
+        assert_eq!(nice_to_priority(Nice::MAX).get(), 139);
+
+        // A smaller nice value means a higher priority, i.e., a smaller task priority.
+        for raw in Nice::MIN.to_raw()..Nice::MAX.to_raw() {
+            assert!(nice_to_priority(Nice::new(raw)) < nice_to_priority(Nice::new(raw + 1)));
+        }
+        // No nice value gives a real-time priority.
+        assert!(nice_to_priority(Nice::MIN) >= Priority::normal());
+    }
+}
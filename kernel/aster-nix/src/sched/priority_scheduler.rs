@@ -1,27 +1,60 @@
 // SPDX-License-Identifier: MPL-2.0
 
+use core::fmt;
+
 use ostd::{
+    arch::timer::Jiffies,
     cpu::{num_cpus, this_cpu},
     task::{
         scheduler::{inject_scheduler, EnqueueFlags, LocalRunQueue, Scheduler, UpdateFlags},
-        AtomicCpuId, Priority, Task,
+        AtomicCpuId, Priority, Task, TaskName,
     },
 };
+use spin::Once;
+
+use super::{cpu_usage::CpuUsage, select_cpu::select_cpu, task_group, task_thread, SchedPolicy};
+use crate::{prelude::*, process::Pid, thread::Tid};
 
-use crate::prelude::*;
+static PREEMPT_SCHEDULER: Once<&'static PreemptScheduler<Task>> = Once::new();
 
 pub fn init() {
     let preempt_scheduler = Box::new(PreemptScheduler::default());
-    let scheduler = Box::<PreemptScheduler<Task>>::leak(preempt_scheduler);
+    let scheduler: &'static PreemptScheduler<Task> =
+        Box::<PreemptScheduler<Task>>::leak(preempt_scheduler);
+    PREEMPT_SCHEDULER.call_once(|| scheduler);
     inject_scheduler(scheduler);
 }
 
+/// Takes a snapshot of the runqueues of all CPUs.
+///
+/// Returns `None` if the preempt scheduler is not in use.
+pub fn snapshot() -> Option<Vec<RunQueueSnapshot>> {
+    PREEMPT_SCHEDULER
+        .get()
+        .map(|scheduler| scheduler.snapshot())
+}
+
+/// Moves a runnable task to the queue that matches its priority.
+///
+/// This should be called after the priority of the task is changed. A running
+/// task is put back to the right queue when it is switched out, so it is not
+/// touched here. Nothing is done if the preempt scheduler is not in use.
+pub fn requeue(task: &Arc<Task>) {
+    if let Some(scheduler) = PREEMPT_SCHEDULER.get() {
+        scheduler.requeue(task);
+    }
+}
+
 /// The preempt scheduler.
 ///
 /// Real-time tasks are placed in the `real_time_entities` queue and
 /// are always prioritized during scheduling.
 /// Normal tasks are placed in the `normal_entities` queue and are only
 /// scheduled for execution when there are no real-time tasks.
+///
+/// To keep interactive tasks responsive, a normal task that has consumed
+/// little CPU recently is placed at the head of the `normal_entities` queue
+/// when it wakes up, instead of the tail.
 struct PreemptScheduler<T: PreemptSchedInfo> {
     rq: Vec<SpinLock<PreemptRunQueue<T>>>,
 }
@@ -36,9 +69,43 @@ impl<T: PreemptSchedInfo> PreemptScheduler<T> {
     }
 
     /// Selects a cpu for task to run on.
-    fn select_cpu(&self, _runnable: &Arc<T>) -> u32 {
-        // FIXME: adopt more reasonable policy once we fully enable SMP.
-        0
+    fn select_cpu(&self, runnable: &Arc<T>) -> u32 {
+        let group = runnable.group();
+        select_cpu(
+            runnable.last_cpu(),
+            self.rq.len() as u32,
+            |cpu_id| self.rq[cpu_id as usize].lock_irq_disabled().load(),
+            |cpu_id| {
+                group.map_or(0, |group| {
+                    self.rq[cpu_id as usize]
+                        .lock_irq_disabled()
+                        .nr_group_members(group)
+                })
+            },
+        )
+    }
+
+    /// Moves a runnable task to the queue that matches its priority.
+    fn requeue(&self, runnable: &Arc<T>) {
+        let Some(cpu_id) = runnable.cpu().get() else {
+            return;
+        };
+        self.rq[cpu_id as usize]
+            .lock_irq_disabled()
+            .requeue(runnable);
+    }
+
+    /// Takes a snapshot of the runqueues of all CPUs.
+    ///
+    /// All the runqueues are locked during the snapshot so that it is consistent. The locks are
+    /// always acquired in the ascending order of the CPU IDs to avoid deadlocks.
+    fn snapshot(&self) -> Vec<RunQueueSnapshot> {
+        let rqs: Vec<_> = self.rq.iter().map(|rq| rq.lock_irq_disabled()).collect();
+
+        rqs.iter()
+            .enumerate()
+            .map(|(cpu_id, rq)| rq.snapshot(cpu_id as u32))
+            .collect()
     }
 }
 
@@ -63,6 +130,8 @@ impl<T: Sync + Send + PreemptSchedInfo> Scheduler<T> for PreemptScheduler<T> {
         let entity = PreemptSchedEntity::new(runnable);
         if entity.is_real_time() {
             rq.real_time_entities.push_back(entity);
+        } else if flags == EnqueueFlags::Wake && entity.cpu_usage.is_interactive() {
+            rq.normal_entities.push_front(entity);
         } else {
             rq.normal_entities.push_back(entity);
         }
@@ -102,6 +171,54 @@ impl<T: PreemptSchedInfo> PreemptRunQueue<T> {
             normal_entities: VecDeque::new(),
         }
     }
+
+    /// Returns the number of entities in this runqueue.
+    fn load(&self) -> usize {
+        self.current.iter().count() + self.real_time_entities.len() + self.normal_entities.len()
+    }
+
+    /// Returns the number of entities in this runqueue that belong to `group`.
+    fn nr_group_members(&self, group: Pid) -> usize {
+        self.current
+            .iter()
+            .chain(self.real_time_entities.iter())
+            .chain(self.normal_entities.iter())
+            .filter(|entity| entity.group == Some(group))
+            .count()
+    }
+
+    /// Moves the queued entity of `runnable` to the queue that matches its priority.
+    ///
+    /// Nothing is done if `runnable` is not queued in this runqueue.
+    fn requeue(&mut self, runnable: &Arc<T>) {
+        let (from, to) = if runnable.is_real_time() {
+            (&mut self.normal_entities, &mut self.real_time_entities)
+        } else {
+            (&mut self.real_time_entities, &mut self.normal_entities)
+        };
+
+        let Some(index) = from
+            .iter()
+            .position(|entity| Arc::ptr_eq(&entity.runnable, runnable))
+        else {
+            return;
+        };
+        let entity = from.remove(index).unwrap();
+        to.push_back(entity);
+    }
+
+    fn snapshot(&self, cpu_id: u32) -> RunQueueSnapshot {
+        RunQueueSnapshot {
+            cpu_id,
+            current: self.current.as_ref().map(PreemptSchedEntity::snapshot),
+            queued: self
+                .real_time_entities
+                .iter()
+                .chain(self.normal_entities.iter())
+                .map(PreemptSchedEntity::snapshot)
+                .collect(),
+        }
+    }
 }
 
 impl<T: Sync + Send + PreemptSchedInfo> LocalRunQueue<T> for PreemptRunQueue<T> {
@@ -142,6 +259,10 @@ impl<T: Sync + Send + PreemptSchedInfo> LocalRunQueue<T> for PreemptRunQueue<T>
     fn dequeue_current(&mut self) -> Option<Arc<T>> {
         self.current.take().map(|entity| {
             let runnable = entity.runnable;
+            runnable.set_cpu_usage(entity.cpu_usage);
+            if let Some(cpu_id) = runnable.cpu().get() {
+                runnable.set_last_cpu(cpu_id);
+            }
             runnable.cpu().set_to_none();
 
             runnable
@@ -152,13 +273,23 @@ impl<T: Sync + Send + PreemptSchedInfo> LocalRunQueue<T> for PreemptRunQueue<T>
 struct PreemptSchedEntity<T: PreemptSchedInfo> {
     runnable: Arc<T>,
     time_slice: TimeSlice,
+    /// The task group, which is resolved once so that the CPU selection is cheap.
+    group: Option<Pid>,
+    /// The recent CPU usage, which is saved to the task when it leaves the runqueue.
+    cpu_usage: CpuUsage,
 }
 
 impl<T: PreemptSchedInfo> PreemptSchedEntity<T> {
     fn new(runnable: Arc<T>) -> Self {
+        let group = runnable.group();
+        // The usage decays for the time that the task has been sleeping.
+        let mut cpu_usage = runnable.cpu_usage();
+        cpu_usage.decay_to(Jiffies::elapsed().as_u64());
         Self {
             runnable,
             time_slice: TimeSlice::default(),
+            group,
+            cpu_usage,
         }
     }
 
@@ -167,8 +298,23 @@ impl<T: PreemptSchedInfo> PreemptSchedEntity<T> {
     }
 
     fn tick(&mut self) -> bool {
+        self.cpu_usage.charge_tick(Jiffies::elapsed().as_u64());
+
+        // A FIFO task is not limited by a time slice.
+        if self.runnable.policy() == SchedPolicy::Fifo {
+            return false;
+        }
         self.time_slice.elapse()
     }
+
+    fn snapshot(&self) -> EntitySnapshot {
+        EntitySnapshot {
+            tid: self.runnable.tid(),
+            name: self.runnable.name(),
+            priority: self.runnable.raw_priority(),
+            remaining_ticks: self.time_slice.remaining_ticks(),
+        }
+    }
 }
 
 impl<T: PreemptSchedInfo> Clone for PreemptSchedEntity<T> {
@@ -176,6 +322,8 @@ impl<T: PreemptSchedInfo> Clone for PreemptSchedEntity<T> {
         Self {
             runnable: self.runnable.clone(),
             time_slice: self.time_slice,
+            group: self.group,
+            cpu_usage: self.cpu_usage,
         }
     }
 }
@@ -197,6 +345,11 @@ impl TimeSlice {
 
         self.elapsed_ticks == 0
     }
+
+    /// Returns the number of ticks left before the time slice is used up.
+    pub fn remaining_ticks(&self) -> u32 {
+        Self::DEFAULT_TIME_SLICE - self.elapsed_ticks
+    }
 }
 
 impl Default for TimeSlice {
@@ -214,9 +367,51 @@ impl PreemptSchedInfo for Task {
         self.priority()
     }
 
+    fn raw_priority(&self) -> u16 {
+        self.priority().get()
+    }
+
+    fn tid(&self) -> Option<Tid> {
+        task_thread(self).map(|thread| thread.tid())
+    }
+
+    fn name(&self) -> TaskName {
+        self.name()
+    }
+
     fn cpu(&self) -> &AtomicCpuId {
         self.cpu()
     }
+
+    fn last_cpu(&self) -> Option<u32> {
+        task_thread(self).and_then(|thread| thread.sched_attr().last_cpu())
+    }
+
+    fn set_last_cpu(&self, cpu_id: u32) {
+        if let Some(thread) = task_thread(self) {
+            thread.sched_attr().set_last_cpu(cpu_id);
+        }
+    }
+
+    fn group(&self) -> Option<Pid> {
+        task_group(self)
+    }
+
+    fn policy(&self) -> SchedPolicy {
+        task_thread(self).map_or(SchedPolicy::Normal, |thread| thread.sched_attr().policy())
+    }
+
+    fn cpu_usage(&self) -> CpuUsage {
+        task_thread(self).map_or(CpuUsage::default(), |thread| {
+            thread.sched_attr().cpu_usage()
+        })
+    }
+
+    fn set_cpu_usage(&self, usage: CpuUsage) {
+        if let Some(thread) = task_thread(self) {
+            thread.sched_attr().set_cpu_usage(usage);
+        }
+    }
 }
 
 trait PreemptSchedInfo {
@@ -226,9 +421,365 @@ trait PreemptSchedInfo {
 
     fn priority(&self) -> Self::PRIORITY;
 
+    /// Returns the priority as a raw value for debugging purposes.
+    fn raw_priority(&self) -> u16;
+
+    /// Returns the ID of the thread that the task belongs to, if any.
+    fn tid(&self) -> Option<Tid>;
+
+    /// Returns the name of the task for debugging purposes.
+    fn name(&self) -> TaskName;
+
     fn cpu(&self) -> &AtomicCpuId;
 
+    /// Returns the CPU that the task ran on last time.
+    fn last_cpu(&self) -> Option<u32>;
+
+    /// Saves the CPU that the task ran on when it leaves the runqueue.
+    fn set_last_cpu(&self, cpu_id: u32);
+
+    /// Returns the task group that the task belongs to, if any.
+    ///
+    /// The members of a task group are preferentially run on distinct CPUs.
+    fn group(&self) -> Option<Pid>;
+
+    /// Returns the scheduling policy of the task.
+    fn policy(&self) -> SchedPolicy;
+
+    /// Returns the CPU usage saved when the task left the runqueue.
+    fn cpu_usage(&self) -> CpuUsage;
+
+    /// Saves the CPU usage when the task leaves the runqueue.
+    fn set_cpu_usage(&self, usage: CpuUsage);
+
     fn is_real_time(&self) -> bool {
         self.priority() < Self::REAL_TIME_TASK_PRIORITY
     }
 }
+
+/// A snapshot of the runqueue of a CPU.
+///
+/// It is formatted in a style similar to Linux's `/proc/sched_debug`.
+pub struct RunQueueSnapshot {
+    pub cpu_id: u32,
+    pub current: Option<EntitySnapshot>,
+    /// The queued entities in the order that they will be picked.
+    pub queued: Vec<EntitySnapshot>,
+}
+
+/// A snapshot of a scheduling entity.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct EntitySnapshot {
+    pub tid: Option<Tid>,
+    pub name: TaskName,
+    pub priority: u16,
+    pub remaining_ticks: u32,
+}
+
+impl fmt::Display for RunQueueSnapshot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let nr_running = self.current.iter().count() + self.queued.len();
+
+        writeln!(f, "cpu#{}", self.cpu_id)?;
+        writeln!(f, "  .nr_running: {}", nr_running)?;
+        match &self.current {
+            Some(current) => writeln!(f, "  .curr: {}", current)?,
+            None => writeln!(f, "  .curr: -")?,
+        }
+        writeln!(f, "runnable tasks:")?;
+        for entity in self.queued.iter() {
+            writeln!(f, "  {}", entity)?;
+        }
+        writeln!(f)
+    }
+}
+
+impl fmt::Display for EntitySnapshot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.tid {
+            Some(tid) => write!(f, "tid={}", tid)?,
+            None => write!(f, "tid=-")?,
+        }
+        write!(
+            f,
+            " name={} prio={} slice_left={}",
+            self.name, self.priority, self.remaining_ticks
+        )
+    }
+}
+
+#[cfg(ktest)]
+mod test {
+    use core::sync::atomic::{AtomicU16, Ordering};
+
+    use ostd::{prelude::*, task::TaskOptions};
+
+    use super::*;
+
+    struct MockTask {
+        tid: Tid,
+        priority: AtomicU16,
+        policy: SchedPolicy,
+        cpu: AtomicCpuId,
+        group: Option<Pid>,
+        cpu_usage: SpinLock<CpuUsage>,
+    }
+
+    impl MockTask {
+        fn new(tid: Tid, priority: u16) -> Arc<Self> {
+            Self::new_in_group(tid, priority, None)
+        }
+
+        fn new_in_group(tid: Tid, priority: u16, group: Option<Pid>) -> Arc<Self> {
+            Arc::new(Self {
+                tid,
+                priority: AtomicU16::new(priority),
+                policy: SchedPolicy::Normal,
+                cpu: AtomicCpuId::default(),
+                group,
+                cpu_usage: SpinLock::new(CpuUsage::default()),
+            })
+        }
+
+        fn new_fifo(tid: Tid, priority: u16) -> Arc<Self> {
+            Arc::new(Self {
+                tid,
+                priority: AtomicU16::new(priority),
+                policy: SchedPolicy::Fifo,
+                cpu: AtomicCpuId::default(),
+                group: None,
+                cpu_usage: SpinLock::new(CpuUsage::default()),
+            })
+        }
+
+        fn set_priority(&self, priority: u16) {
+            self.priority.store(priority, Ordering::Relaxed);
+        }
+    }
+
+    impl PreemptSchedInfo for MockTask {
+        type PRIORITY = Priority;
+
+        const REAL_TIME_TASK_PRIORITY: Self::PRIORITY = Priority::new(100);
+
+        fn priority(&self) -> Self::PRIORITY {
+            Priority::new(self.raw_priority())
+        }
+
+        fn raw_priority(&self) -> u16 {
+            self.priority.load(Ordering::Relaxed)
+        }
+
+        fn tid(&self) -> Option<Tid> {
+            Some(self.tid)
+        }
+
+        fn name(&self) -> TaskName {
+            TaskName::new("mock")
+        }
+
+        fn cpu(&self) -> &AtomicCpuId {
+            &self.cpu
+        }
+
+        fn last_cpu(&self) -> Option<u32> {
+            None
+        }
+
+        fn set_last_cpu(&self, _cpu_id: u32) {}
+
+        fn group(&self) -> Option<Pid> {
+            self.group
+        }
+
+        fn policy(&self) -> SchedPolicy {
+            self.policy
+        }
+
+        fn cpu_usage(&self) -> CpuUsage {
+            *self.cpu_usage.lock()
+        }
+
+        fn set_cpu_usage(&self, usage: CpuUsage) {
+            *self.cpu_usage.lock() = usage;
+        }
+    }
+
+    fn push(scheduler: &PreemptScheduler<MockTask>, cpu_id: usize, task: Arc<MockTask>) {
+        let mut rq = scheduler.rq[cpu_id].lock_irq_disabled();
+        let entity = PreemptSchedEntity::new(task);
+        if entity.is_real_time() {
+            rq.real_time_entities.push_back(entity);
+        } else {
+            rq.normal_entities.push_back(entity);
+        }
+    }
+
+    fn tids(entities: &[EntitySnapshot]) -> Vec<Option<Tid>> {
+        entities.iter().map(|entity| entity.tid).collect()
+    }
+
+    #[ktest]
+    fn snapshot_lists_tasks_per_cpu() {
+        let scheduler = PreemptScheduler::new(2);
+        push(&scheduler, 0, MockTask::new(1, 120));
+        push(&scheduler, 0, MockTask::new(2, 120));
+        push(&scheduler, 0, MockTask::new(3, 10));
+        push(&scheduler, 1, MockTask::new(4, 110));
+
+        {
+            let mut rq = scheduler.rq[0].lock_irq_disabled();
+            rq.pick_next_current();
+            rq.update_current(UpdateFlags::Tick);
+        }
+
+        let snapshot = scheduler.snapshot();
+        assert_eq!(snapshot.len(), 2);
+
+        // The real-time task is picked first.
+        let rq0 = &snapshot[0];
+        assert_eq!(rq0.cpu_id, 0);
+        assert_eq!(
+            rq0.current,
+            Some(EntitySnapshot {
+                tid: Some(3),
+                name: TaskName::new("mock"),
+                priority: 10,
+                remaining_ticks: TimeSlice::DEFAULT_TIME_SLICE - 1,
+            })
+        );
+        assert_eq!(tids(&rq0.queued), [Some(1), Some(2)]);
+
+        let rq1 = &snapshot[1];
+        assert_eq!(rq1.cpu_id, 1);
+        assert!(rq1.current.is_none());
+        assert_eq!(tids(&rq1.queued), [Some(4)]);
+        assert_eq!(rq1.queued[0].priority, 110);
+    }
+
+    #[ktest]
+    fn snapshot_format() {
+        let scheduler = PreemptScheduler::new(1);
+        push(&scheduler, 0, MockTask::new(7, 100));
+
+        let output = scheduler.snapshot()[0].to_string();
+        assert_eq!(
+            output,
+            "cpu#0\n  .nr_running: 1\n  .curr: -\nrunnable tasks:\n  tid=7 name=mock prio=100 slice_left=100\n\n"
+        );
+    }
+
+    #[ktest]
+    fn snapshot_shows_task_name() {
+        let task = TaskOptions::new(|| {})
+            .data(())
+            .name("sched-test")
+            .build()
+            .unwrap();
+        assert_eq!(task.name().as_str(), "sched-test");
+
+        let scheduler = PreemptScheduler::<Task>::new(1);
+        scheduler.rq[0]
+            .lock_irq_disabled()
+            .normal_entities
+            .push_back(PreemptSchedEntity::new(task));
+
+        let snapshot = scheduler.snapshot();
+        assert_eq!(snapshot[0].queued[0].name.as_str(), "sched-test");
+        assert!(snapshot[0].to_string().contains("name=sched-test"));
+    }
+
+    #[ktest]
+    fn co_schedule_group_members() {
+        let scheduler = PreemptScheduler::new(2);
+        // Without the group, the second member would join the first one on CPU 0,
+        // since both CPUs are equally loaded then.
+        push(&scheduler, 1, MockTask::new(1, 120));
+
+        let member_a = MockTask::new_in_group(2, 120, Some(2));
+        let member_b = MockTask::new_in_group(3, 120, Some(2));
+        let cpu_a = scheduler.enqueue(member_a, EnqueueFlags::Spawn);
+        let cpu_b = scheduler.enqueue(member_b, EnqueueFlags::Spawn);
+        assert_eq!(cpu_a, Some(0));
+        assert_eq!(cpu_b, Some(1));
+
+        // The group is larger than the CPU count, so the members share the CPUs.
+        let member_c = MockTask::new_in_group(4, 120, Some(2));
+        assert!(scheduler.enqueue(member_c, EnqueueFlags::Spawn).is_some());
+        let snapshot = scheduler.snapshot();
+        assert_eq!(snapshot[0].queued.len() + snapshot[1].queued.len(), 4);
+    }
+
+    #[ktest]
+    fn requeue_after_priority_change() {
+        let scheduler = PreemptScheduler::new(1);
+        let task = MockTask::new(2, 120);
+        push(&scheduler, 0, MockTask::new(1, 120));
+        push(&scheduler, 0, task.clone());
+        task.cpu().set_if_is_none(0).unwrap();
+
+        // The task becomes real-time while it is queued behind a normal task.
+        task.set_priority(50);
+        scheduler.requeue(&task);
+
+        let mut rq = scheduler.rq[0].lock_irq_disabled();
+        assert_eq!(rq.real_time_entities.len(), 1);
+        let next = rq.pick_next_current().unwrap();
+        assert!(Arc::ptr_eq(next, &task));
+    }
+
+    #[ktest]
+    fn fifo_ignores_time_slice() {
+        let scheduler = PreemptScheduler::new(1);
+        push(&scheduler, 0, MockTask::new_fifo(1, 50));
+        push(&scheduler, 0, MockTask::new(2, 50));
+
+        let mut rq = scheduler.rq[0].lock_irq_disabled();
+        assert_eq!(rq.pick_next_current().unwrap().tid, 1);
+        for _ in 0..TimeSlice::DEFAULT_TIME_SLICE * 2 {
+            assert!(!rq.update_current(UpdateFlags::Tick));
+        }
+
+        // A task of another policy is preempted once its time slice is used up.
+        rq.update_current(UpdateFlags::Yield);
+        assert_eq!(rq.pick_next_current().unwrap().tid, 2);
+        let nr_ticks = (0..TimeSlice::DEFAULT_TIME_SLICE)
+            .take_while(|_| !rq.update_current(UpdateFlags::Tick))
+            .count();
+        assert_eq!(nr_ticks as u32, TimeSlice::DEFAULT_TIME_SLICE - 1);
+    }
+
+    /// Runs `task` as the current task for `nr_ticks` ticks and then puts it to sleep.
+    fn run_and_sleep(scheduler: &PreemptScheduler<MockTask>, task: &Arc<MockTask>, nr_ticks: u32) {
+        scheduler.enqueue(task.clone(), EnqueueFlags::Spawn);
+        let mut rq = scheduler.rq[0].lock_irq_disabled();
+        assert!(Arc::ptr_eq(rq.pick_next_current().unwrap(), task));
+        for _ in 0..nr_ticks {
+            rq.update_current(UpdateFlags::Tick);
+        }
+        rq.dequeue_current();
+    }
+
+    #[ktest]
+    fn boost_interactive_task() {
+        let scheduler = PreemptScheduler::new(1);
+        let hog = MockTask::new(1, 120);
+        let sleeper = MockTask::new(2, 120);
+        run_and_sleep(&scheduler, &hog, TimeSlice::DEFAULT_TIME_SLICE - 1);
+        run_and_sleep(&scheduler, &sleeper, 1);
+        assert!(!hog.cpu_usage().is_interactive());
+        assert!(sleeper.cpu_usage().is_interactive());
+
+        // Both tasks wake up behind a task that is already queued, but only the one
+        // that has mostly been sleeping is dispatched before it.
+        push(&scheduler, 0, MockTask::new(3, 120));
+        scheduler.enqueue(hog, EnqueueFlags::Wake);
+        scheduler.enqueue(sleeper, EnqueueFlags::Wake);
+
+        let mut rq = scheduler.rq[0].lock_irq_disabled();
+        let order: Vec<_> = (0..3)
+            .map(|_| rq.pick_next_current().unwrap().tid)
+            .collect();
+        assert_eq!(order, [2, 3, 1]);
+    }
+}
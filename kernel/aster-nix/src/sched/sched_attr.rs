@@ -0,0 +1,151 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use core::sync::atomic::{AtomicU64, Ordering};
+
+use atomic::Atomic;
+use bytemuck_derive::NoUninit;
+use ostd::task::AtomicCpuId;
+
+use super::{
+    cpu_usage::CpuUsage,
+    deadline::{DeadlineParams, DeadlineState, DEADLINE_BANDWIDTH},
+};
+use crate::prelude::*;
+
+/// The scheduling policy of a thread.
+///
+/// The values are the same as those used by Linux.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, TryFromInt, NoUninit)]
+#[repr(u32)]
+pub enum SchedPolicy {
+    /// The default time-sharing policy (`SCHED_NORMAL`).
+    Normal = 0,
+    /// The real-time first-in-first-out policy (`SCHED_FIFO`).
+    ///
+    /// A thread runs until it blocks or yields, without being limited by a time slice.
+    Fifo = 1,
+    /// The real-time round-robin policy (`SCHED_RR`).
+    RoundRobin = 2,
+    /// The earliest deadline first policy (`SCHED_DEADLINE`).
+    ///
+    /// The thread runs with the [`DeadlineParams`], which can only be set by
+    /// [`super::set_deadline`].
+    Deadline = 6,
+}
+
+impl SchedPolicy {
+    /// Checks if the policy is a real-time policy.
+    pub fn is_real_time(self) -> bool {
+        matches!(self, Self::Fifo | Self::RoundRobin)
+    }
+}
+
+/// The scheduling attributes of a thread.
+///
+/// The attributes are maintained by the scheduler, and they are kept
+/// even when the thread is not in any runqueue (e.g., when it is sleeping).
+pub struct SchedAttr {
+    vruntime: AtomicU64,
+    last_cpu: AtomicCpuId,
+    policy: Atomic<SchedPolicy>,
+    deadline_params: SpinLock<Option<DeadlineParams>>,
+    deadline_state: SpinLock<DeadlineState>,
+    cpu_usage: SpinLock<CpuUsage>,
+}
+
+impl SchedAttr {
+    /// Returns the virtual runtime used by the fair scheduler.
+    pub fn vruntime(&self) -> u64 {
+        self.vruntime.load(Ordering::Relaxed)
+    }
+
+    /// Sets the virtual runtime used by the fair scheduler.
+    pub fn set_vruntime(&self, vruntime: u64) {
+        self.vruntime.store(vruntime, Ordering::Relaxed);
+    }
+
+    /// Returns the CPU that the thread ran on last time.
+    pub fn last_cpu(&self) -> Option<u32> {
+        self.last_cpu.get()
+    }
+
+    /// Sets the CPU that the thread ran on last time.
+    pub fn set_last_cpu(&self, cpu_id: u32) {
+        self.last_cpu.set(cpu_id);
+    }
+
+    /// Returns the scheduling policy.
+    pub fn policy(&self) -> SchedPolicy {
+        self.policy.load(Ordering::Relaxed)
+    }
+
+    /// Sets the scheduling policy.
+    ///
+    /// The priority of the task should be updated accordingly, which is done by
+    /// [`super::set_scheduler`].
+    pub(super) fn set_policy(&self, policy: SchedPolicy) {
+        self.policy.store(policy, Ordering::Relaxed);
+    }
+
+    /// Returns the deadline parameters if the thread is a deadline task.
+    pub fn deadline_params(&self) -> Option<DeadlineParams> {
+        *self.deadline_params.lock_irq_disabled()
+    }
+
+    /// Sets the deadline parameters of the thread.
+    ///
+    /// Passing `None` turns the thread back into a non-deadline task. The new
+    /// parameters take effect at the next tick if the thread is running, or the
+    /// next time the thread is enqueued otherwise.
+    ///
+    /// This method fails with `EBUSY` if the deadline tasks in the system
+    /// would be over-committed.
+    pub fn set_deadline_params(&self, params: Option<DeadlineParams>) -> Result<()> {
+        let mut deadline_params = self.deadline_params.lock_irq_disabled();
+        DEADLINE_BANDWIDTH.change(deadline_params.as_ref(), params.as_ref())?;
+        *deadline_params = params;
+        *self.deadline_state.lock_irq_disabled() = DeadlineState::default();
+        Ok(())
+    }
+
+    /// Returns the deadline state saved when the thread left the runqueue.
+    pub(super) fn deadline_state(&self) -> DeadlineState {
+        *self.deadline_state.lock_irq_disabled()
+    }
+
+    /// Saves the deadline state when the thread leaves the runqueue.
+    pub(super) fn set_deadline_state(&self, state: DeadlineState) {
+        *self.deadline_state.lock_irq_disabled() = state;
+    }
+
+    /// Returns the CPU usage saved when the thread left the runqueue.
+    pub(super) fn cpu_usage(&self) -> CpuUsage {
+        *self.cpu_usage.lock_irq_disabled()
+    }
+
+    /// Saves the CPU usage when the thread leaves the runqueue.
+    pub(super) fn set_cpu_usage(&self, usage: CpuUsage) {
+        *self.cpu_usage.lock_irq_disabled() = usage;
+    }
+}
+
+impl Default for SchedAttr {
+    fn default() -> Self {
+        Self {
+            vruntime: AtomicU64::new(0),
+            last_cpu: AtomicCpuId::default(),
+            policy: Atomic::new(SchedPolicy::Normal),
+            deadline_params: SpinLock::new(None),
+            deadline_state: SpinLock::new(DeadlineState::default()),
+            cpu_usage: SpinLock::new(CpuUsage::default()),
+        }
+    }
+}
+
+impl Drop for SchedAttr {
+    fn drop(&mut self) {
+        // Releasing the bandwidth reserved by the deadline task never fails.
+        let params = self.deadline_params.lock_irq_disabled().take();
+        DEADLINE_BANDWIDTH.change(params.as_ref(), None).unwrap();
+    }
+}
@@ -0,0 +1,120 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! CPU selection for tasks being enqueued.
+//!
+//! Moving a task to another CPU is not free: the caches of the new CPU are
+//! cold for the task. So a task stays on the CPU it ran on last time unless
+//! the load of that CPU exceeds the load of the least loaded CPU by more
+//! than [`MIGRATION_IMBALANCE_THRESHOLD`].
+//!
+//! Tasks that cooperate, e.g., threads sharing an address space, form a task
+//! group and benefit from being co-scheduled. So a task is preferentially
+//! placed on the CPUs that run the fewest members of its group. If a group has
+//! more members than CPUs, its members share the CPUs evenly.
+
+/// The minimum load imbalance that is worth a migration.
+///
+/// The load of a CPU is measured by the number of tasks in its runqueue.
+const MIGRATION_IMBALANCE_THRESHOLD: usize = 2;
+
+/// Selects a CPU for a task to run on.
+///
+/// `last_cpu` is the CPU that the task ran on last time, if any. `load_of`
+/// returns the load of a CPU. `group_load_of` returns the number of the other
+/// members of the task's group on a CPU, which is always zero if the task does
+/// not belong to any group.
+pub(super) fn select_cpu(
+    last_cpu: Option<u32>,
+    nr_cpus: u32,
+    load_of: impl Fn(u32) -> usize,
+    group_load_of: impl Fn(u32) -> usize,
+) -> u32 {
+    let last_cpu = last_cpu.filter(|cpu_id| *cpu_id < nr_cpus);
+
+    // The CPUs are compared by their group loads first and then by their loads, so the
+    // best CPU is the least loaded one among those that run the fewest group members.
+    // This is done in one pass, which queries each CPU only once and allocates nothing.
+    let mut best: Option<(u32, (usize, usize))> = None;
+    let mut last_cpu_loads = None;
+    for cpu_id in 0..nr_cpus {
+        let loads = (group_load_of(cpu_id), load_of(cpu_id));
+        if Some(cpu_id) == last_cpu {
+            last_cpu_loads = Some(loads);
+        }
+        if best.map_or(true, |(_, best_loads)| loads < best_loads) {
+            best = Some((cpu_id, loads));
+        }
+    }
+    let (least_loaded_cpu, (min_group_load, least_load)) = best.unwrap();
+
+    // The last CPU is a candidate only if it runs the fewest group members as well.
+    let Some((last_cpu, (_, last_load))) = last_cpu
+        .zip(last_cpu_loads)
+        .filter(|(_, (group_load, _))| *group_load == min_group_load)
+    else {
+        return least_loaded_cpu;
+    };
+
+    if last_load > least_load + MIGRATION_IMBALANCE_THRESHOLD {
+        least_loaded_cpu
+    } else {
+        last_cpu
+    }
+}
+
+#[cfg(ktest)]
+mod test {
+    use ostd::prelude::*;
+
+    use super::*;
+
+    fn no_group(_cpu_id: u32) -> usize {
+        0
+    }
+
+    #[ktest]
+    fn prefer_last_cpu() {
+        let loads = [1, 3, 0, 2];
+        let load_of = |cpu_id: u32| loads[cpu_id as usize];
+
+        assert_eq!(select_cpu(None, 4, load_of, no_group), 2);
+        assert_eq!(select_cpu(Some(0), 4, load_of, no_group), 0);
+        assert_eq!(select_cpu(Some(3), 4, load_of, no_group), 3);
+    }
+
+    #[ktest]
+    fn migrate_off_overloaded_cpu() {
+        let loads = [5, 3, 2, 3];
+        let load_of = |cpu_id: u32| loads[cpu_id as usize];
+
+        // The imbalance is just the threshold, so the task stays.
+        assert_eq!(select_cpu(Some(1), 4, load_of, no_group), 1);
+        // The imbalance exceeds the threshold, so the task moves.
+        assert_eq!(select_cpu(Some(0), 4, load_of, no_group), 2);
+    }
+
+    #[ktest]
+    fn spread_group_members() {
+        let loads = [0, 1, 1, 3];
+        let load_of = |cpu_id: u32| loads[cpu_id as usize];
+
+        // The least loaded CPU runs a member of the group, so the task goes elsewhere.
+        let group_loads = [1, 0, 0, 0];
+        let group_load_of = |cpu_id: u32| group_loads[cpu_id as usize];
+        assert_eq!(select_cpu(None, 4, load_of, group_load_of), 1);
+        assert_eq!(select_cpu(Some(0), 4, load_of, group_load_of), 1);
+        assert_eq!(select_cpu(Some(2), 4, load_of, group_load_of), 2);
+    }
+
+    #[ktest]
+    fn time_share_large_group() {
+        let loads = [2, 2, 3, 2];
+        let load_of = |cpu_id: u32| loads[cpu_id as usize];
+
+        // All the CPUs run members of the group, so the task goes to a CPU that runs
+        // the fewest of them.
+        let group_loads = [2, 2, 1, 2];
+        let group_load_of = |cpu_id: u32| group_loads[cpu_id as usize];
+        assert_eq!(select_cpu(Some(0), 4, load_of, group_load_of), 2);
+    }
+}
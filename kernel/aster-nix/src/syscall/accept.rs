@@ -68,7 +68,7 @@ fn do_accept(
 
     let fd = {
         let mut file_table = ctx.process.file_table().lock();
-        file_table.insert(connected_socket, fd_flags)
+        file_table.insert(connected_socket, fd_flags)?
     };
 
     Ok(fd)
@@ -14,6 +14,7 @@ use crate::syscall::{
     chown::{sys_chown, sys_fchown, sys_fchownat, sys_lchown},
     chroot::sys_chroot,
     clock_gettime::sys_clock_gettime,
+    clock_settime::sys_clock_settime,
     clone::{sys_clone, sys_clone3},
     close::sys_close,
     connect::sys_connect,
@@ -58,9 +59,11 @@ use crate::syscall::{
     madvise::sys_madvise,
     mkdir::{sys_mkdir, sys_mkdirat},
     mknod::{sys_mknod, sys_mknodat},
+    mlock::{sys_mlock, sys_munlock},
     mmap::sys_mmap,
     mount::sys_mount,
     mprotect::sys_mprotect,
+    mremap::sys_mremap,
     msync::sys_msync,
     munmap::sys_munmap,
     nanosleep::{sys_clock_nanosleep, sys_nanosleep},
@@ -84,9 +87,14 @@ use crate::syscall::{
     rt_sigaction::sys_rt_sigaction,
     rt_sigpending::sys_rt_sigpending,
     rt_sigprocmask::sys_rt_sigprocmask,
+    rt_sigqueueinfo::sys_rt_sigqueueinfo,
     rt_sigreturn::sys_rt_sigreturn,
     rt_sigsuspend::sys_rt_sigsuspend,
     sched_getaffinity::sys_sched_getaffinity,
+    sched_getattr::sys_sched_getattr,
+    sched_getscheduler::sys_sched_getscheduler,
+    sched_setattr::sys_sched_setattr,
+    sched_setscheduler::sys_sched_setscheduler,
     sched_yield::sys_sched_yield,
     select::sys_select,
     semctl::sys_semctl,
@@ -113,6 +121,7 @@ use crate::syscall::{
     setuid::sys_setuid,
     shutdown::sys_shutdown,
     sigaltstack::sys_sigaltstack,
+    signalfd::{sys_signalfd, sys_signalfd4},
     socket::sys_socket,
     socketpair::sys_socketpair,
     stat::{sys_fstat, sys_fstatat, sys_lstat, sys_stat},
@@ -123,6 +132,7 @@ use crate::syscall::{
     time::sys_time,
     timer_create::{sys_timer_create, sys_timer_delete},
     timer_settime::{sys_timer_gettime, sys_timer_settime},
+    timerfd::{sys_timerfd_create, sys_timerfd_gettime, sys_timerfd_settime},
     truncate::{sys_ftruncate, sys_truncate},
     umask::sys_umask,
     umount::sys_umount,
@@ -132,6 +142,11 @@ use crate::syscall::{
     wait4::sys_wait4,
     waitid::sys_waitid,
     write::sys_write,
+    xattr::{
+        sys_fgetxattr, sys_flistxattr, sys_fremovexattr, sys_fsetxattr, sys_getxattr,
+        sys_lgetxattr, sys_listxattr, sys_llistxattr, sys_lremovexattr, sys_lsetxattr,
+        sys_removexattr, sys_setxattr,
+    },
 };
 
 impl_syscall_nums_and_dispatch_fn! {
@@ -161,6 +176,7 @@ impl_syscall_nums_and_dispatch_fn! {
     SYS_SELECT = 23            => sys_select(args[..5]);
     SYS_MSYNC = 26             => sys_msync(args[..3]);
     SYS_SCHED_YIELD = 24       => sys_sched_yield(args[..0]);
+    SYS_MREMAP = 25            => sys_mremap(args[..5]);
     SYS_MADVISE = 28           => sys_madvise(args[..3]);
     SYS_DUP = 32               => sys_dup(args[..1]);
     SYS_DUP2 = 33              => sys_dup2(args[..2]);
@@ -246,6 +262,7 @@ impl_syscall_nums_and_dispatch_fn! {
     SYS_CAPGET = 125           => sys_capget(args[..2]);
     SYS_CAPSET = 126           => sys_capset(args[..2]);
     SYS_RT_SIGPENDING = 127    => sys_rt_sigpending(args[..2]);
+    SYS_RT_SIGQUEUEINFO = 129  => sys_rt_sigqueueinfo(args[..3]);
     SYS_RT_SIGSUSPEND = 130    => sys_rt_sigsuspend(args[..2]);
     SYS_SIGALTSTACK = 131      => sys_sigaltstack(args[..2]);
     SYS_UTIME = 132            => sys_utime(args[..2]);
@@ -254,6 +271,10 @@ impl_syscall_nums_and_dispatch_fn! {
     SYS_FSTATFS = 138          => sys_fstatfs(args[..2]);
     SYS_GET_PRIORITY = 140     => sys_get_priority(args[..2]);
     SYS_SET_PRIORITY = 141     => sys_set_priority(args[..3]);
+    SYS_SCHED_SETSCHEDULER = 144 => sys_sched_setscheduler(args[..3]);
+    SYS_SCHED_GETSCHEDULER = 145 => sys_sched_getscheduler(args[..1]);
+    SYS_MLOCK = 149            => sys_mlock(args[..2]);
+    SYS_MUNLOCK = 150          => sys_munlock(args[..2]);
     SYS_PRCTL = 157            => sys_prctl(args[..5]);
     SYS_ARCH_PRCTL = 158       => sys_arch_prctl(args[..2], &mut user_ctx);
     SYS_CHROOT = 161           => sys_chroot(args[..1]);
@@ -261,6 +282,18 @@ impl_syscall_nums_and_dispatch_fn! {
     SYS_MOUNT = 165            => sys_mount(args[..5]);
     SYS_UMOUNT2 = 166           => sys_umount(args[..2]);
     SYS_GETTID = 186           => sys_gettid(args[..0]);
+    SYS_SETXATTR = 188         => sys_setxattr(args[..5]);
+    SYS_LSETXATTR = 189        => sys_lsetxattr(args[..5]);
+    SYS_FSETXATTR = 190        => sys_fsetxattr(args[..5]);
+    SYS_GETXATTR = 191         => sys_getxattr(args[..4]);
+    SYS_LGETXATTR = 192        => sys_lgetxattr(args[..4]);
+    SYS_FGETXATTR = 193        => sys_fgetxattr(args[..4]);
+    SYS_LISTXATTR = 194        => sys_listxattr(args[..3]);
+    SYS_LLISTXATTR = 195       => sys_llistxattr(args[..3]);
+    SYS_FLISTXATTR = 196       => sys_flistxattr(args[..3]);
+    SYS_REMOVEXATTR = 197      => sys_removexattr(args[..2]);
+    SYS_LREMOVEXATTR = 198     => sys_lremovexattr(args[..2]);
+    SYS_FREMOVEXATTR = 199     => sys_fremovexattr(args[..2]);
     SYS_TIME = 201             => sys_time(args[..1]);
     SYS_FUTEX = 202            => sys_futex(args[..6]);
     SYS_SCHED_GETAFFINITY = 204 => sys_sched_getaffinity(args[..3]);
@@ -272,6 +305,7 @@ impl_syscall_nums_and_dispatch_fn! {
     SYS_TIMER_SETTIME = 223    => sys_timer_settime(args[..4]);
     SYS_TIMER_GETTIME = 224    => sys_timer_gettime(args[..2]);
     SYS_TIMER_DELETE = 226     => sys_timer_delete(args[..1]);
+    SYS_CLOCK_SETTIME = 227    => sys_clock_settime(args[..2]);
     SYS_CLOCK_GETTIME = 228    => sys_clock_gettime(args[..2]);
     SYS_CLOCK_NANOSLEEP = 230  => sys_clock_nanosleep(args[..4]);
     SYS_EXIT_GROUP = 231       => sys_exit_group(args[..1]);
@@ -297,9 +331,14 @@ impl_syscall_nums_and_dispatch_fn! {
     SYS_SET_ROBUST_LIST = 273  => sys_set_robust_list(args[..2]);
     SYS_UTIMENSAT = 280        => sys_utimensat(args[..4]);
     SYS_EPOLL_PWAIT = 281      => sys_epoll_pwait(args[..6]);
+    SYS_SIGNALFD = 282         => sys_signalfd(args[..3]);
+    SYS_TIMERFD_CREATE = 283   => sys_timerfd_create(args[..2]);
     SYS_EVENTFD = 284          => sys_eventfd(args[..1]);
     SYS_FALLOCATE = 285        => sys_fallocate(args[..4]);
+    SYS_TIMERFD_SETTIME = 286  => sys_timerfd_settime(args[..4]);
+    SYS_TIMERFD_GETTIME = 287  => sys_timerfd_gettime(args[..2]);
     SYS_ACCEPT4 = 288          => sys_accept4(args[..4]);
+    SYS_SIGNALFD4 = 289        => sys_signalfd4(args[..4]);
     SYS_EVENTFD2 = 290         => sys_eventfd2(args[..2]);
     SYS_EPOLL_CREATE1 = 291    => sys_epoll_create1(args[..1]);
     SYS_DUP3 = 292             => sys_dup3(args[..3]);
@@ -307,9 +346,35 @@ impl_syscall_nums_and_dispatch_fn! {
     SYS_PREADV = 295           => sys_preadv(args[..4]);
     SYS_PWRITEV = 296          => sys_pwritev(args[..4]);
     SYS_PRLIMIT64 = 302        => sys_prlimit64(args[..4]);
+    SYS_SCHED_SETATTR = 314    => sys_sched_setattr(args[..3]);
+    SYS_SCHED_GETATTR = 315    => sys_sched_getattr(args[..4]);
     SYS_GETRANDOM = 318        => sys_getrandom(args[..3]);
     SYS_EXECVEAT = 322         => sys_execveat(args[..5], &mut user_ctx);
     SYS_PREADV2 = 327          => sys_preadv2(args[..5]);
     SYS_PWRITEV2 = 328         => sys_pwritev2(args[..5]);
     SYS_CLONE3 = 435           => sys_clone3(args[..2], &user_ctx);
 }
+
+/// Returns whether the syscall can be restarted after being interrupted by
+/// a signal.
+///
+/// The syscalls that are never restarted, regardless of `SA_RESTART`, are
+/// listed in signal(7). They include the syscalls that wait with a timeout,
+/// since restarting them would wait for the whole timeout again.
+pub fn is_restartable(syscall_number: u64, args: &[u64; 6]) -> bool {
+    match syscall_number {
+        SYS_POLL
+        | SYS_SELECT
+        | SYS_PSELECT6
+        | SYS_EPOLL_WAIT
+        | SYS_EPOLL_PWAIT
+        | SYS_NANOSLEEP
+        | SYS_CLOCK_NANOSLEEP
+        | SYS_PAUSE
+        | SYS_RT_SIGSUSPEND
+        | SYS_SEMOP
+        | SYS_SEMTIMEDOP => false,
+        SYS_FUTEX => !crate::syscall::futex::is_timed_wait(args[1] as _, args[3]),
+        _ => true,
+    }
+}
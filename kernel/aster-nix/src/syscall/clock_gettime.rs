@@ -132,7 +132,7 @@ pub fn read_clock(clockid: clockid_t, ctx: &Context) -> Result<Duration> {
                     DynamicClockType::Profiling => Ok(process.prof_clock().read_time()),
                     DynamicClockType::Virtual => Ok(process.prof_clock().user_clock().read_time()),
                     // TODO: support scheduling clock and fd clock.
-                    _ => unimplemented!(),
+                    _ => return_errno_with_message!(Errno::EINVAL, "unsupported clock type"),
                 }
             }
             DynamicClockIdInfo::Tid(tid, clock_type) => {
@@ -144,10 +144,12 @@ pub fn read_clock(clockid: clockid_t, ctx: &Context) -> Result<Duration> {
                     DynamicClockType::Virtual => {
                         Ok(posix_thread.prof_clock().user_clock().read_time())
                     }
-                    _ => unimplemented!(),
+                    _ => return_errno_with_message!(Errno::EINVAL, "unsupported clock type"),
                 }
             }
-            DynamicClockIdInfo::Fd(_) => unimplemented!(),
+            DynamicClockIdInfo::Fd(_) => {
+                return_errno_with_message!(Errno::EINVAL, "fd clocks are not supported")
+            }
         }
     }
 }
@@ -0,0 +1,36 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use core::time::Duration;
+
+use super::{clock_gettime::ClockId, SyscallReturn};
+use crate::{
+    prelude::*,
+    process::credentials::capabilities::CapSet,
+    time::{clockid_t, timespec_t, SystemTime},
+};
+
+pub fn sys_clock_settime(
+    clockid: clockid_t,
+    timespec_addr: Vaddr,
+    ctx: &Context,
+) -> Result<SyscallReturn> {
+    debug!("clockid = {:?}", clockid);
+
+    // Only the real time clock can be set. Like Linux, the other clocks, including the
+    // dynamic clocks, are rejected with `EINVAL`.
+    if clockid < 0 || ClockId::try_from(clockid)? != ClockId::CLOCK_REALTIME {
+        return_errno_with_message!(Errno::EINVAL, "the clock cannot be set");
+    }
+
+    let timespec = ctx.get_user_space().read_val::<timespec_t>(timespec_addr)?;
+    let time = Duration::try_from(timespec)?;
+
+    let credentials = ctx.posix_thread.credentials();
+    if !credentials.effective_capset().contains(CapSet::SYS_TIME) {
+        return_errno_with_message!(Errno::EPERM, "setting the system time is not permitted");
+    }
+
+    SystemTime::set_now(time)?;
+
+    Ok(SyscallReturn::Return(0))
+}
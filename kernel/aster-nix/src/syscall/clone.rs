@@ -22,7 +22,7 @@ pub fn sys_clone(
     let clone_flags = CloneFlags::from(clone_flags);
     debug!("flags = {:?}, child_stack_ptr = 0x{:x}, parent_tid_ptr = 0x{:x}, child tid ptr = 0x{:x}, tls = 0x{:x}", clone_flags, new_sp, parent_tidptr, child_tidptr, tls);
     let clone_args = CloneArgs::new(new_sp, 0, parent_tidptr, child_tidptr, tls, clone_flags);
-    let child_pid = clone_child(ctx, parent_context, clone_args).unwrap();
+    let child_pid = clone_child(ctx, parent_context, clone_args)?;
     Ok(SyscallReturn::Return(child_pid as _))
 }
 
@@ -44,6 +44,10 @@ pub fn sys_clone3(
     let clone_args = {
         let args: Clone3Args = ctx.get_user_space().read_val(clong_args_addr)?;
         trace!("clone3 args = {:x?}", args);
+        // The stack is given by both its lowest address and its size, or not given at all.
+        if (args.stack == 0) != (args.stack_size == 0) {
+            return_errno_with_message!(Errno::EINVAL, "invalid stack");
+        }
         CloneArgs::from(args)
     };
     debug!("clone args = {:x?}", clone_args);
@@ -66,9 +66,15 @@ fn do_dup3(
         return_errno!(Errno::EBADF);
     }
 
-    let mut file_table = current.file_table().lock();
-    let _ = file_table.close_file(new_fd);
-    let new_fd = file_table.dup(old_fd, new_fd, flags)?;
+    let replaced_file = {
+        let mut file_table = current.file_table().lock();
+        let file = file_table.get_file(old_fd)?.clone();
+        // Replacing the entry closes the file previously at `new_fd` (if any) while the file
+        // table is locked, so no other thread can observe `new_fd` as free in between.
+        file_table.insert_at(new_fd, file, flags)
+    };
+    // Dropping the file may sleep, which is done after the file table is unlocked.
+    drop(replaced_file);
 
     Ok(SyscallReturn::Return(new_fd as _))
 }
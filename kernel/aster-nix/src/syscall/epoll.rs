@@ -39,7 +39,7 @@ pub fn sys_epoll_create1(flags: u32, ctx: &Context) -> Result<SyscallReturn> {
 
     let epoll_file: Arc<EpollFile> = EpollFile::new();
     let mut file_table = ctx.process.file_table().lock();
-    let fd = file_table.insert(epoll_file, fd_flags);
+    let fd = file_table.insert(epoll_file, fd_flags)?;
     Ok(SyscallReturn::Return(fd as _))
 }
 
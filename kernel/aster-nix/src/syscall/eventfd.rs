@@ -33,7 +33,7 @@ use crate::{
 pub fn sys_eventfd(init_val: u64, ctx: &Context) -> Result<SyscallReturn> {
     debug!("init_val = 0x{:x}", init_val);
 
-    let fd = do_sys_eventfd2(init_val, Flags::empty(), ctx);
+    let fd = do_sys_eventfd2(init_val, Flags::empty(), ctx)?;
 
     Ok(SyscallReturn::Return(fd as _))
 }
@@ -44,12 +44,12 @@ pub fn sys_eventfd2(init_val: u64, flags: u32, ctx: &Context) -> Result<SyscallR
         .ok_or_else(|| Error::with_message(Errno::EINVAL, "unknown flags"))?;
     debug!("init_val = 0x{:x}, flags = {:?}", init_val, flags);
 
-    let fd = do_sys_eventfd2(init_val, flags, ctx);
+    let fd = do_sys_eventfd2(init_val, flags, ctx)?;
 
     Ok(SyscallReturn::Return(fd as _))
 }
 
-fn do_sys_eventfd2(init_val: u64, flags: Flags, ctx: &Context) -> FileDesc {
+fn do_sys_eventfd2(init_val: u64, flags: Flags, ctx: &Context) -> Result<FileDesc> {
     let event_file = EventFile::new(init_val, flags);
     let fd = {
         let mut file_table = ctx.process.file_table().lock();
@@ -58,9 +58,9 @@ fn do_sys_eventfd2(init_val: u64, flags: Flags, ctx: &Context) -> FileDesc {
         } else {
             FdFlags::empty()
         };
-        file_table.insert(Arc::new(event_file), fd_flags)
+        file_table.insert(Arc::new(event_file), fd_flags)?
     };
-    fd
+    Ok(fd)
 }
 
 bitflags! {
@@ -204,6 +204,9 @@ impl FileLike for EventFile {
         }
 
         let supplied_value = u64::from_bytes(buf);
+        if supplied_value == u64::MAX {
+            return_errno_with_message!(Errno::EINVAL, "the value to write is too large");
+        }
 
         // Try to add counter val at first
         if self.add_counter_val(supplied_value).is_ok() {
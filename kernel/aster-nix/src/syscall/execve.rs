@@ -101,8 +101,9 @@ fn do_execve(
         executable_path, argv, envp
     );
     // FIXME: should we set thread name in execve?
-    *posix_thread.thread_name().lock() =
-        Some(ThreadName::new_from_executable_path(&executable_path)?);
+    let thread_name = ThreadName::new_from_executable_path(&executable_path)?;
+    ctx.task.set_name(&thread_name.to_string_lossy());
+    *posix_thread.thread_name().lock() = Some(thread_name);
     // clear ctid
     // FIXME: should we clear ctid when execve?
     *posix_thread.clear_child_tid().lock() = 0;
@@ -4,7 +4,7 @@ use crate::{
     prelude::*,
     process::posix_thread::futex::{
         futex_op_and_flags_from_u32, futex_requeue, futex_wait, futex_wait_bitset, futex_wake,
-        futex_wake_bitset, FutexOp, FutexTimeout,
+        futex_wake_bitset, FutexFlags, FutexOp, FutexTimeout,
     },
     syscall::SyscallReturn,
 };
@@ -18,12 +18,13 @@ pub fn sys_futex(
     bitset: u64,
     ctx: &Context,
 ) -> Result<SyscallReturn> {
-    // FIXME: we current ignore futex flags
     let (futex_op, futex_flags) = futex_op_and_flags_from_u32(futex_op as _)?;
     debug!(
         "futex_op = {:?}, futex_flags = {:?}, futex_addr = 0x{:x}",
         futex_op, futex_flags, futex_addr
     );
+    // FIXME: `FUTEX_CLOCK_REALTIME` is ignored.
+    let is_private = futex_flags.contains(FutexFlags::FUTEX_PRIVATE);
 
     let get_futex_val = |val: i32| -> Result<usize> {
         if val < 0 {
@@ -43,19 +44,27 @@ pub fn sys_futex(
     let res = match futex_op {
         FutexOp::FUTEX_WAIT => {
             let timeout = get_futex_timeout(utime_addr)?;
-            futex_wait(futex_addr as _, futex_val as _, &timeout).map(|_| 0)
+            futex_wait(futex_addr as _, futex_val as _, &timeout, is_private).map(|_| 0)
         }
         FutexOp::FUTEX_WAIT_BITSET => {
             let timeout = get_futex_timeout(utime_addr)?;
-            futex_wait_bitset(futex_addr as _, futex_val as _, &timeout, bitset as _).map(|_| 0)
+            futex_wait_bitset(
+                futex_addr as _,
+                futex_val as _,
+                &timeout,
+                bitset as _,
+                is_private,
+            )
+            .map(|_| 0)
         }
         FutexOp::FUTEX_WAKE => {
             let max_count = get_futex_val(futex_val as i32)?;
-            futex_wake(futex_addr as _, max_count).map(|count| count as isize)
+            futex_wake(futex_addr as _, max_count, is_private).map(|count| count as isize)
         }
         FutexOp::FUTEX_WAKE_BITSET => {
             let max_count = get_futex_val(futex_val as i32)?;
-            futex_wake_bitset(futex_addr as _, max_count, bitset as _).map(|count| count as isize)
+            futex_wake_bitset(futex_addr as _, max_count, bitset as _, is_private)
+                .map(|count| count as isize)
         }
         FutexOp::FUTEX_REQUEUE => {
             let max_nwakes = get_futex_val(futex_val as i32)?;
@@ -65,6 +74,7 @@ pub fn sys_futex(
                 max_nwakes,
                 max_nrequeues,
                 futex_new_addr as _,
+                is_private,
             )
             .map(|nwakes| nwakes as _)
         }
@@ -74,3 +84,12 @@ pub fn sys_futex(
     debug!("futex returns, tid= {} ", ctx.thread.tid());
     Ok(SyscallReturn::Return(res as _))
 }
+
+/// Returns whether the futex operation waits with a timeout.
+pub(super) fn is_timed_wait(futex_op: i32, utime_addr: u64) -> bool {
+    let Ok((futex_op, _)) = futex_op_and_flags_from_u32(futex_op as _) else {
+        return false;
+    };
+
+    matches!(futex_op, FutexOp::FUTEX_WAIT | FutexOp::FUTEX_WAIT_BITSET) && utime_addr != 0
+}
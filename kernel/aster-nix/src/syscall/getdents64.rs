@@ -23,22 +23,8 @@ pub fn sys_getdents(
         fd, buf_addr, buf_len
     );
 
-    let file = {
-        let file_table = ctx.process.file_table().lock();
-        file_table.get_file(fd)?.clone()
-    };
-    let inode_handle = file
-        .downcast_ref::<InodeHandle>()
-        .ok_or(Error::with_message(Errno::EBADF, "not inode"))?;
-    if inode_handle.dentry().type_() != InodeType::Dir {
-        return_errno!(Errno::ENOTDIR);
-    }
-    let mut buffer = vec![0u8; buf_len];
-    let mut reader = DirentBufferReader::<Dirent>::new(&mut buffer); // Use the non-64-bit reader
-    let _ = inode_handle.readdir(&mut reader)?;
-    let read_len = reader.read_len();
-    ctx.get_user_space()
-        .write_bytes(buf_addr, &mut VmReader::from(&buffer[..read_len]))?;
+    // Use the non-64-bit serializer
+    let read_len = do_getdents::<Dirent>(fd, buf_addr, buf_len, ctx)?;
     Ok(SyscallReturn::Return(read_len as _))
 }
 
@@ -53,6 +39,21 @@ pub fn sys_getdents64(
         fd, buf_addr, buf_len
     );
 
+    let read_len = do_getdents::<Dirent64>(fd, buf_addr, buf_len, ctx)?;
+    Ok(SyscallReturn::Return(read_len as _))
+}
+
+/// Reads the directory entries from the current position of the directory `fd`
+/// into the user buffer, and advances the position past the entries read.
+///
+/// Returns the number of bytes written, which is zero at the end of the directory.
+/// Fails with `EINVAL` if the buffer cannot hold the next entry.
+fn do_getdents<T: DirentSerializer>(
+    fd: FileDesc,
+    buf_addr: Vaddr,
+    buf_len: usize,
+    ctx: &Context,
+) -> Result<usize> {
     let file = {
         let file_table = ctx.process.file_table().lock();
         file_table.get_file(fd)?.clone()
@@ -63,15 +64,24 @@ pub fn sys_getdents64(
     if inode_handle.dentry().type_() != InodeType::Dir {
         return_errno!(Errno::ENOTDIR);
     }
-    let mut buffer = vec![0u8; buf_len];
-    let mut reader = DirentBufferReader::<Dirent64>::new(&mut buffer);
+
+    // The user buffer may be much larger than needed, so the kernel buffer is
+    // capped to avoid allocating excessive memory.
+    let mut buffer = vec![0u8; buf_len.min(MAX_BUF_LEN)];
+    let mut reader = DirentBufferReader::<T>::new(&mut buffer);
     let _ = inode_handle.readdir(&mut reader)?;
     let read_len = reader.read_len();
     ctx.get_user_space()
         .write_bytes(buf_addr, &mut VmReader::from(&buffer[..read_len]))?;
-    Ok(SyscallReturn::Return(read_len as _))
+    Ok(read_len)
 }
 
+/// The maximum length of the kernel buffer used to serialize the directory entries.
+///
+/// It can hold hundreds of entries even with the longest names, so a caller with a
+/// larger buffer simply gets fewer entries per call.
+const MAX_BUF_LEN: usize = 64 * 1024;
+
 /// The DirentSerializer can decide how to serialize the data.
 trait DirentSerializer {
     /// Create a DirentSerializer.
@@ -272,3 +282,90 @@ impl From<InodeType> for DirentType {
 fn align_up(size: usize, align: usize) -> usize {
     (size + align - 1) & !(align - 1)
 }
+
+#[cfg(ktest)]
+mod test {
+    use alloc::format;
+
+    use ostd::prelude::*;
+
+    use super::*;
+    use crate::fs::{
+        ramfs::RamFS,
+        utils::{FileSystem, Inode, InodeMode, NAME_MAX},
+    };
+
+    /// Parses the `linux_dirent64` entries in `buf` into the names and types.
+    fn parse_dirents64(mut buf: &[u8]) -> Vec<(String, u8)> {
+        let mut entries = Vec::new();
+        while !buf.is_empty() {
+            let d_reclen = u16::from_ne_bytes([buf[16], buf[17]]) as usize;
+            let d_type = buf[18];
+            let name = CStr::from_bytes_until_nul(&buf[19..d_reclen]).unwrap();
+            entries.push((name.to_str().unwrap().to_string(), d_type));
+            buf = &buf[d_reclen..];
+        }
+        entries
+    }
+
+    #[ktest]
+    fn page_through_entries() {
+        let fs = RamFS::new();
+        let dir = fs
+            .root_inode()
+            .create("dir", InodeType::Dir, InodeMode::from_bits_truncate(0o755))
+            .unwrap();
+
+        let long_name = "x".repeat(NAME_MAX);
+        let mut names = vec![String::from("."), String::from("..")];
+        for i in 0..16 {
+            names.push(format!("file{}", i));
+        }
+        names.push(long_name);
+        for name in names.iter().skip(2) {
+            dir.create(name, InodeType::File, InodeMode::from_bits_truncate(0o644))
+                .unwrap();
+        }
+
+        // A buffer that is too small for even one entry.
+        let mut buffer = [0u8; 8];
+        let mut reader = DirentBufferReader::<Dirent64>::new(&mut buffer);
+        assert_eq!(
+            dir.readdir_at(0, &mut reader).unwrap_err().error(),
+            Errno::EINVAL
+        );
+
+        // A buffer that holds the longest entry but not all the entries.
+        let mut offset = 0;
+        let mut nr_calls = 0;
+        let mut entries = Vec::new();
+        loop {
+            let mut buffer = [0u8; 512];
+            let mut reader = DirentBufferReader::<Dirent64>::new(&mut buffer);
+            let read_cnt = dir.readdir_at(offset, &mut reader).unwrap();
+            if read_cnt == 0 {
+                break;
+            }
+            offset += read_cnt;
+            nr_calls += 1;
+
+            let read_len = reader.read_len();
+            entries.extend(parse_dirents64(&buffer[..read_len]));
+        }
+        assert!(nr_calls > 1);
+
+        let mut expected = names.clone();
+        expected.sort();
+        let mut actual: Vec<_> = entries.iter().map(|(name, _)| name.clone()).collect();
+        actual.sort();
+        assert_eq!(actual, expected);
+
+        for (name, d_type) in entries {
+            let expected_type = match name.as_str() {
+                "." | ".." => DirentType::DT_DIR,
+                _ => DirentType::DT_REG,
+            };
+            assert_eq!(d_type, expected_type as u8);
+        }
+    }
+}
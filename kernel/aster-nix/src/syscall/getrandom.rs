@@ -1,25 +1,70 @@
 // SPDX-License-Identifier: MPL-2.0
 
 use super::SyscallReturn;
-use crate::{device, prelude::*};
+use crate::{device, prelude::*, util::random};
 
 pub fn sys_getrandom(buf: Vaddr, count: usize, flags: u32, ctx: &Context) -> Result<SyscallReturn> {
-    let flags = GetRandomFlags::from_bits_truncate(flags);
+    let flags = GetRandomFlags::from_bits(flags)
+        .ok_or_else(|| Error::with_message(Errno::EINVAL, "invalid flags"))?;
     debug!(
         "buf = 0x{:x}, count = 0x{:x}, flags = {:?}",
         buf, count, flags
     );
-    // TODO: support nonblock flag.
-    // Currently our getrandom implementation relies on x86-specific `rdrand` instruction, so it will never block.
-    let mut buffer = vec![0u8; count];
-    let read_len = if flags.contains(GetRandomFlags::GRND_RANDOM) {
-        device::Random::getrandom(&mut buffer)?
-    } else {
-        device::Urandom::getrandom(&mut buffer)?
-    };
-    ctx.get_user_space()
-        .write_bytes(buf, &mut VmReader::from(buffer.as_slice()))?;
-    Ok(SyscallReturn::Return(read_len as isize))
+
+    if flags.contains(GetRandomFlags::GRND_INSECURE | GetRandomFlags::GRND_RANDOM) {
+        return_errno_with_message!(
+            Errno::EINVAL,
+            "GRND_INSECURE cannot be used with GRND_RANDOM"
+        );
+    }
+
+    // Like Linux, no random bytes are returned until the RNG is seeded, unless
+    // `GRND_INSECURE` is specified.
+    if !flags.contains(GetRandomFlags::GRND_INSECURE) {
+        while !random::is_seeded() {
+            if flags.contains(GetRandomFlags::GRND_NONBLOCK) {
+                return_errno_with_message!(
+                    Errno::EAGAIN,
+                    "the RNG is not seeded with enough entropy"
+                );
+            }
+            if ctx.posix_thread.has_pending() {
+                return_errno_with_message!(
+                    Errno::EINTR,
+                    "the waiting for the RNG to be seeded is interrupted by a signal"
+                );
+            }
+            random::collect_jitter_entropy();
+        }
+    }
+
+    if count == 0 {
+        return Ok(SyscallReturn::Return(0));
+    }
+
+    // Fill the user buffer page by page to avoid allocating a huge kernel buffer.
+    let mut buffer = vec![0u8; count.min(PAGE_SIZE)];
+    let mut written = 0;
+    while written < count {
+        let chunk = &mut buffer[..(count - written).min(PAGE_SIZE)];
+        if flags.contains(GetRandomFlags::GRND_RANDOM) {
+            device::Random::getrandom(chunk)?;
+        } else {
+            device::Urandom::getrandom(chunk)?;
+        }
+
+        let res = ctx
+            .get_user_space()
+            .write_bytes(buf + written, &mut VmReader::from(&*chunk));
+        match res {
+            Ok(()) => written += chunk.len(),
+            // Bytes that have been written cannot be taken back, so report a short read.
+            Err(_) if written > 0 => break,
+            Err(err) => return Err(err),
+        }
+    }
+
+    Ok(SyscallReturn::Return(written as isize))
 }
 
 bitflags::bitflags! {
@@ -1,5 +1,7 @@
 // SPDX-License-Identifier: MPL-2.0
 
+use align_ext::AlignExt;
+
 use super::SyscallReturn;
 use crate::prelude::*;
 
@@ -27,6 +29,7 @@ pub fn sys_madvise(
             warn!("MADV_DONTNEED isn't implemented, do nothing for now.");
         }
         MadviseBehavior::MADV_FREE => madv_free(start, len, ctx)?,
+        MadviseBehavior::MADV_PAGEOUT => madv_pageout(start, len, ctx)?,
         _ => todo!(),
     }
     Ok(SyscallReturn::Return(0))
@@ -43,6 +46,19 @@ fn madv_free(start: Vaddr, len: usize, ctx: &Context) -> Result<()> {
     Ok(())
 }
 
+fn madv_pageout(start: Vaddr, len: usize, ctx: &Context) -> Result<()> {
+    if start % PAGE_SIZE != 0 {
+        return_errno_with_message!(Errno::EINVAL, "the start address is not page-aligned");
+    }
+    let Some(end) = start.checked_add(len.align_up(PAGE_SIZE)) else {
+        return_errno_with_message!(Errno::EINVAL, "the range overflows");
+    };
+
+    // The locked pages are skipped.
+    let root_vmar = ctx.process.root_vmar();
+    root_vmar.reclaim(start..end)
+}
+
 #[repr(i32)]
 #[derive(Debug, Clone, Copy, TryFromInt)]
 #[allow(non_camel_case_types)]
@@ -0,0 +1,82 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use core::ops::Range;
+
+use align_ext::AlignExt;
+use aster_rights::Full;
+
+use super::SyscallReturn;
+use crate::{
+    prelude::*,
+    process::{credentials::capabilities::CapSet, posix_thread::PosixThreadExt, ResourceType},
+    vm::vmar::{Vmar, ROOT_VMAR_CAP_ADDR, ROOT_VMAR_LOWEST_ADDR},
+};
+
+pub fn sys_mlock(start: Vaddr, len: usize, ctx: &Context) -> Result<SyscallReturn> {
+    debug!("start = 0x{:x}, len = 0x{:x}", start, len);
+
+    let range = lock_range(start, len)?;
+    let root_vmar = ctx.process.root_vmar();
+    check_memlock_limit(root_vmar, &range, ctx)?;
+    root_vmar.lock(range)?;
+
+    Ok(SyscallReturn::Return(0))
+}
+
+pub fn sys_munlock(start: Vaddr, len: usize, ctx: &Context) -> Result<SyscallReturn> {
+    debug!("start = 0x{:x}, len = 0x{:x}", start, len);
+
+    let range = lock_range(start, len)?;
+    ctx.process.root_vmar().unlock(range)?;
+
+    Ok(SyscallReturn::Return(0))
+}
+
+/// Returns the range of the pages that contain `start..start + len`.
+fn lock_range(start: Vaddr, len: usize) -> Result<Range<Vaddr>> {
+    let Some(end) = start.checked_add(len) else {
+        return_errno_with_message!(Errno::EINVAL, "the range overflows");
+    };
+
+    let range = start.align_down(PAGE_SIZE)..end.align_up(PAGE_SIZE);
+    // Like Linux, locking an empty range always succeeds.
+    if !range.is_empty()
+        && (range.start < ROOT_VMAR_LOWEST_ADDR || range.end > ROOT_VMAR_CAP_ADDR)
+    {
+        return_errno_with_message!(Errno::ENOMEM, "the range is not in the user space");
+    }
+
+    Ok(range)
+}
+
+/// Checks whether locking `range` keeps the locked memory within `RLIMIT_MEMLOCK`.
+fn check_memlock_limit(
+    root_vmar: &Vmar<Full>,
+    range: &Range<Vaddr>,
+    ctx: &Context,
+) -> Result<()> {
+    let credentials = ctx.posix_thread.credentials();
+    if credentials.effective_capset().contains(CapSet::IPC_LOCK) {
+        return Ok(());
+    }
+
+    let memlock_limit = ctx
+        .process
+        .resource_limits()
+        .lock()
+        .get_rlimit(ResourceType::RLIMIT_MEMLOCK)
+        .get_cur();
+    if memlock_limit == 0 {
+        return_errno_with_message!(Errno::EPERM, "locking memory is not permitted");
+    }
+
+    // The pages that are already locked are not counted twice.
+    let vmar_range = root_vmar.base()..root_vmar.base() + root_vmar.size();
+    let locked_size =
+        root_vmar.locked_size(&vmar_range) - root_vmar.locked_size(range) + range.len();
+    if locked_size as u64 > memlock_limit {
+        return_errno_with_message!(Errno::ENOMEM, "the locked memory exceeds RLIMIT_MEMLOCK");
+    }
+
+    Ok(())
+}
@@ -70,6 +70,10 @@ fn do_sys_mmap(
             // TODO: support MAP_32BIT. MAP_32BIT requires the map range to be below 2GB
             warn!("MAP_32BIT is not supported");
         }
+        if ctx.process.vm().is_mmap_guarded() {
+            // It is ignored for a fixed mapping.
+            options = options.guard_page(true);
+        }
 
         if option.typ() == MMapType::Shared {
             options = options.is_shared(true);
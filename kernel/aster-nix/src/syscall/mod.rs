@@ -21,6 +21,7 @@ mod chmod;
 mod chown;
 mod chroot;
 mod clock_gettime;
+mod clock_settime;
 mod clone;
 mod close;
 mod connect;
@@ -65,9 +66,11 @@ mod lseek;
 mod madvise;
 mod mkdir;
 mod mknod;
+mod mlock;
 mod mmap;
 mod mount;
 mod mprotect;
+mod mremap;
 mod msync;
 mod munmap;
 mod nanosleep;
@@ -91,9 +94,14 @@ mod rmdir;
 mod rt_sigaction;
 mod rt_sigpending;
 mod rt_sigprocmask;
+mod rt_sigqueueinfo;
 mod rt_sigreturn;
 mod rt_sigsuspend;
 mod sched_getaffinity;
+mod sched_getattr;
+mod sched_getscheduler;
+mod sched_setattr;
+mod sched_setscheduler;
 mod sched_yield;
 mod select;
 mod semctl;
@@ -120,6 +128,7 @@ mod setsockopt;
 mod setuid;
 mod shutdown;
 mod sigaltstack;
+mod signalfd;
 mod socket;
 mod socketpair;
 mod stat;
@@ -130,6 +139,7 @@ mod tgkill;
 mod time;
 mod timer_create;
 mod timer_settime;
+mod timerfd;
 mod truncate;
 mod umask;
 mod umount;
@@ -139,6 +149,7 @@ mod utimens;
 mod wait4;
 mod waitid;
 mod write;
+mod xattr;
 
 /// This macro is used to define syscall handler.
 /// The first param is ths number of parameters,
@@ -316,7 +327,12 @@ impl SyscallArgument {
     }
 }
 
-pub fn handle_syscall(ctx: &Context, user_ctx: &mut UserContext) {
+/// Handles the syscall from the user space.
+///
+/// If the syscall is interrupted by a signal and it can be restarted, this
+/// method returns the syscall number, so that the syscall can be restarted
+/// after the signal is handled.
+pub fn handle_syscall(ctx: &Context, user_ctx: &mut UserContext) -> Option<usize> {
     let syscall_frame = SyscallArgument::new_from_context(user_ctx);
     let syscall_return = arch::syscall_dispatch(
         syscall_frame.syscall_number,
@@ -334,9 +350,17 @@ pub fn handle_syscall(ctx: &Context, user_ctx: &mut UserContext) {
         Err(err) => {
             debug!("syscall return error: {:?}", err);
             let errno = err.error() as i32;
-            user_ctx.set_syscall_ret((-errno) as usize)
+            user_ctx.set_syscall_ret((-errno) as usize);
+
+            if err.error() == Errno::EINTR
+                && arch::is_restartable(syscall_frame.syscall_number, &syscall_frame.args)
+            {
+                return Some(syscall_frame.syscall_number as usize);
+            }
         }
     }
+
+    None
 }
 
 #[macro_export]
@@ -0,0 +1,91 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use align_ext::AlignExt;
+
+use super::SyscallReturn;
+use crate::prelude::*;
+
+pub fn sys_mremap(
+    old_addr: Vaddr,
+    old_size: usize,
+    new_size: usize,
+    flags: i32,
+    new_addr: Vaddr,
+    ctx: &Context,
+) -> Result<SyscallReturn> {
+    let flags =
+        MremapFlags::from_bits(flags).ok_or(Error::with_message(Errno::EINVAL, "invalid flags"))?;
+    let new_addr = do_sys_mremap(old_addr, old_size, new_size, flags, new_addr, ctx)?;
+    Ok(SyscallReturn::Return(new_addr as _))
+}
+
+fn do_sys_mremap(
+    old_addr: Vaddr,
+    old_size: usize,
+    new_size: usize,
+    flags: MremapFlags,
+    new_addr: Vaddr,
+    ctx: &Context,
+) -> Result<Vaddr> {
+    debug!(
+        "mremap: old_addr = 0x{:x}, old_size = 0x{:x}, new_size = 0x{:x}, flags = {:?}, new_addr = 0x{:x}",
+        old_addr, old_size, new_size, flags, new_addr
+    );
+
+    if old_addr % PAGE_SIZE != 0 {
+        return_errno_with_message!(Errno::EINVAL, "mremap: `old_addr` must be page-aligned");
+    }
+    if new_size == 0 {
+        return_errno_with_message!(Errno::EINVAL, "mremap: `new_size` cannot be zero");
+    }
+    if old_size == 0 {
+        // TODO: Support duplicating a shared mapping when `old_size` is zero.
+        return_errno_with_message!(Errno::EINVAL, "mremap: `old_size` cannot be zero");
+    }
+    if flags.contains(MremapFlags::MREMAP_DONTUNMAP) {
+        return_errno_with_message!(Errno::EINVAL, "mremap: MREMAP_DONTUNMAP is not supported");
+    }
+    if flags.contains(MremapFlags::MREMAP_FIXED) && !flags.contains(MremapFlags::MREMAP_MAYMOVE) {
+        return_errno_with_message!(
+            Errno::EINVAL,
+            "mremap: MREMAP_FIXED must be used with MREMAP_MAYMOVE"
+        );
+    }
+
+    let old_size = old_size.align_up(PAGE_SIZE);
+    let new_size = new_size.align_up(PAGE_SIZE);
+
+    let new_addr = if flags.contains(MremapFlags::MREMAP_FIXED) {
+        if new_addr % PAGE_SIZE != 0 {
+            return_errno_with_message!(Errno::EINVAL, "mremap: `new_addr` must be page-aligned");
+        }
+        let old_range = old_addr..old_addr + old_size;
+        let new_range = new_addr..new_addr + new_size;
+        if old_range.start < new_range.end && new_range.start < old_range.end {
+            return_errno_with_message!(
+                Errno::EINVAL,
+                "mremap: the new range overlaps with the old range"
+            );
+        }
+        Some(new_addr)
+    } else {
+        None
+    };
+
+    let root_vmar = ctx.process.root_vmar();
+    root_vmar.remap(
+        old_addr,
+        old_size,
+        new_size,
+        new_addr,
+        flags.contains(MremapFlags::MREMAP_MAYMOVE),
+    )
+}
+
+bitflags! {
+    struct MremapFlags: i32 {
+        const MREMAP_MAYMOVE = 1 << 0;
+        const MREMAP_FIXED = 1 << 1;
+        const MREMAP_DONTUNMAP = 1 << 2;
+    }
+}
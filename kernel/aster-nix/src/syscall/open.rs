@@ -42,7 +42,7 @@ pub fn sys_openat(
             } else {
                 FdFlags::empty()
             };
-        file_table.insert(file_handle, fd_flags)
+        file_table.insert(file_handle, fd_flags)?
     };
     Ok(SyscallReturn::Return(fd as _))
 }
@@ -13,6 +13,13 @@ use crate::{
 pub fn sys_pipe2(fds: Vaddr, flags: u32, ctx: &Context) -> Result<SyscallReturn> {
     debug!("flags: {:?}", flags);
 
+    // The packet mode (`O_DIRECT`) is not supported. Like older Linux kernels, this is reported
+    // with `EINVAL`.
+    let valid_flags = CreationFlags::O_CLOEXEC.bits() | StatusFlags::O_NONBLOCK.bits();
+    if flags & !valid_flags != 0 {
+        return_errno_with_message!(Errno::EINVAL, "invalid flags");
+    }
+
     let (pipe_reader, pipe_writer) = {
         let (producer, consumer) = Channel::new(PIPE_BUF_SIZE).split();
 
@@ -32,9 +39,17 @@ pub fn sys_pipe2(fds: Vaddr, flags: u32, ctx: &Context) -> Result<SyscallReturn>
 
     let mut file_table = ctx.process.file_table().lock();
 
+    let reader_fd = file_table.insert(pipe_reader, fd_flags)?;
+    let writer_fd = match file_table.insert(pipe_writer, fd_flags) {
+        Ok(writer_fd) => writer_fd,
+        Err(err) => {
+            file_table.close_file(reader_fd).unwrap();
+            return Err(err);
+        }
+    };
     let pipe_fds = PipeFds {
-        reader_fd: file_table.insert(pipe_reader, fd_flags),
-        writer_fd: file_table.insert(pipe_writer, fd_flags),
+        reader_fd,
+        writer_fd,
     };
     debug!("pipe_fds: {:?}", pipe_fds);
 
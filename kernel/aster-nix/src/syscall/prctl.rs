@@ -59,8 +59,17 @@ pub fn sys_prctl(
                     .get_user_space()
                     .read_cstring(read_addr, MAX_THREAD_NAME_LEN)?;
                 thread_name.set_name(&new_thread_name)?;
+                ctx.task.set_name(&thread_name.to_string_lossy());
             }
         }
+        PrctlCmd::PR_SET_MMAP_GUARD(is_mmap_guarded) => {
+            ctx.process.vm().set_mmap_guarded(is_mmap_guarded);
+        }
+        PrctlCmd::PR_GET_MMAP_GUARD => {
+            return Ok(SyscallReturn::Return(
+                ctx.process.vm().is_mmap_guarded() as _
+            ));
+        }
         _ => todo!(),
     }
     Ok(SyscallReturn::Return(0))
@@ -74,6 +83,9 @@ const PR_SET_NAME: i32 = 15;
 const PR_GET_NAME: i32 = 16;
 const PR_SET_TIMERSLACK: i32 = 29;
 const PR_GET_TIMERSLACK: i32 = 30;
+// Asterinas-specific options that are not defined by Linux.
+const PR_SET_MMAP_GUARD: i32 = 0x4153_0001;
+const PR_GET_MMAP_GUARD: i32 = 0x4153_0002;
 
 #[allow(non_camel_case_types)]
 #[derive(Debug, Clone, Copy)]
@@ -88,6 +100,9 @@ pub enum PrctlCmd {
     PR_GET_TIMERSLACK,
     PR_SET_DUMPABLE(Dumpable),
     PR_GET_DUMPABLE,
+    /// Sets whether each `mmap` mapping is followed by a guard page.
+    PR_SET_MMAP_GUARD(bool),
+    PR_GET_MMAP_GUARD,
 }
 
 #[repr(u64)]
@@ -110,6 +125,12 @@ impl PrctlCmd {
             PR_SET_DUMPABLE => Ok(PrctlCmd::PR_SET_DUMPABLE(Dumpable::try_from(arg2)?)),
             PR_SET_NAME => Ok(PrctlCmd::PR_SET_NAME(arg2 as _)),
             PR_GET_NAME => Ok(PrctlCmd::PR_GET_NAME(arg2 as _)),
+            PR_SET_MMAP_GUARD => match arg2 {
+                0 => Ok(PrctlCmd::PR_SET_MMAP_GUARD(false)),
+                1 => Ok(PrctlCmd::PR_SET_MMAP_GUARD(true)),
+                _ => return_errno_with_message!(Errno::EINVAL, "invalid mmap guard mode"),
+            },
+            PR_GET_MMAP_GUARD => Ok(PrctlCmd::PR_GET_MMAP_GUARD),
             PR_GET_TIMERSLACK => todo!(),
             PR_SET_TIMERSLACK => todo!(),
             _ => {
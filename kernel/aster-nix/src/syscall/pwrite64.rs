@@ -29,9 +29,8 @@ pub fn sys_pwrite64(
         return_errno_with_message!(Errno::EINVAL, "offset + user_buf_len overflow");
     }
 
-    let mut buffer = vec![0u8; user_buf_len];
-    ctx.get_user_space()
-        .read_bytes(user_buf_ptr, &mut VmWriter::from(buffer.as_mut_slice()))?;
-    let write_len = file.write_at(offset as _, &buffer)?;
+    let user_space = ctx.get_user_space();
+    let mut reader = user_space.reader(user_buf_ptr, user_buf_len)?;
+    let write_len = file.write_user_at(offset as _, &mut reader)?;
     Ok(SyscallReturn::Return(write_len as _))
 }
@@ -1,5 +1,7 @@
 // SPDX-License-Identifier: MPL-2.0
 
+use core::mem::offset_of;
+
 use super::SyscallReturn;
 use crate::{
     fs::file_table::FileDesc,
@@ -15,7 +17,10 @@ pub fn sys_recvmsg(
     ctx: &Context,
 ) -> Result<SyscallReturn> {
     let c_user_msghdr: CUserMsgHdr = ctx.get_user_space().read_val(user_msghdr_ptr)?;
-    let flags = SendRecvFlags::from_bits_truncate(flags);
+    let mut flags = SendRecvFlags::from_bits_truncate(flags);
+    // The flag only affects the received files, which are installed here.
+    let is_cloexec = flags.contains(SendRecvFlags::MSG_CMSG_CLOEXEC);
+    flags.remove(SendRecvFlags::MSG_CMSG_CLOEXEC);
 
     debug!(
         "sockfd = {}, user_msghdr = {:x?}, flags = {:?}",
@@ -32,8 +37,22 @@ pub fn sys_recvmsg(
         c_user_msghdr.write_socket_addr_to_user(addr)?;
     }
 
+    let (control_len, is_truncated) = c_user_msghdr
+        .write_control_messages_to_user(message_header.control_messages(), is_cloexec)?;
+
+    let user_space = ctx.get_user_space();
     if c_user_msghdr.msg_control != 0 {
-        warn!("receiving control message is not supported");
+        user_space.write_val(
+            user_msghdr_ptr + offset_of!(CUserMsgHdr, msg_controllen),
+            &control_len,
+        )?;
+    }
+    if is_truncated {
+        let msg_flags = c_user_msghdr.msg_flags | SendRecvFlags::MSG_CTRUNC.bits() as u32;
+        user_space.write_val(
+            user_msghdr_ptr + offset_of!(CUserMsgHdr, msg_flags),
+            &msg_flags,
+        )?;
     }
 
     Ok(SyscallReturn::Return(total_bytes as _))
@@ -0,0 +1,53 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use super::SyscallReturn;
+use crate::{
+    prelude::*,
+    process::{
+        kill,
+        signal::{
+            c_types::siginfo_t,
+            constants::SI_TKILL,
+            sig_num::SigNum,
+            signals::user::{UserSignal, UserSignalKind},
+        },
+        Pid,
+    },
+};
+
+/// Sends a signal with the accompanying data to a process.
+pub fn sys_rt_sigqueueinfo(
+    tgid: Pid,
+    sig_num: u8,
+    siginfo_addr: Vaddr,
+    ctx: &Context,
+) -> Result<SyscallReturn> {
+    let siginfo = ctx.get_user_space().read_val::<siginfo_t>(siginfo_addr)?;
+    debug!(
+        "tgid = {}, sig_num = {}, si_code = {}",
+        tgid, sig_num, siginfo.si_code
+    );
+
+    // From the Linux man page:
+    // The caller cannot impersonate the kernel or `kill()`/`tgkill()` when
+    // sending a signal to a process other than itself.
+    if (siginfo.si_code >= 0 || siginfo.si_code == SI_TKILL) && tgid != ctx.process.pid() {
+        return_errno_with_message!(Errno::EPERM, "the si_code cannot be used by the caller");
+    }
+
+    let sig_num = if sig_num == 0 {
+        None
+    } else {
+        Some(SigNum::try_from(sig_num)?)
+    };
+
+    let signal = sig_num.map(|sig_num| {
+        let pid = ctx.process.pid();
+        let uid = ctx.posix_thread.credentials().ruid();
+        let kind = UserSignalKind::Sigqueue(siginfo.si_value());
+        UserSignal::new(sig_num, kind, pid, uid)
+    });
+    kill(tgid, signal)?;
+
+    Ok(SyscallReturn::Return(0))
+}
@@ -5,7 +5,14 @@ use core::sync::atomic::Ordering;
 use ostd::{cpu::UserContext, user::UserContextApi};
 
 use super::SyscallReturn;
-use crate::{prelude::*, process::signal::c_types::ucontext_t};
+use crate::{
+    prelude::*,
+    process::signal::{
+        c_types::ucontext_t,
+        constants::{SIGKILL, SIGSTOP},
+        sig_mask::SigMask,
+    },
+};
 
 pub fn sys_rt_sigreturn(ctx: &Context, user_ctx: &mut UserContext) -> Result<SyscallReturn> {
     let Context {
@@ -47,12 +54,11 @@ pub fn sys_rt_sigreturn(ctx: &Context, user_ctx: &mut UserContext) -> Result<Sys
         .inner
         .gp_regs
         .copy_to_raw(user_ctx.general_regs_mut());
-    // unblock sig mask
-    let sig_mask = ucontext.uc_sigmask;
-    let old_mask = posix_thread.sig_mask().load(Ordering::Relaxed);
-    posix_thread
-        .sig_mask()
-        .store(old_mask - sig_mask, Ordering::Relaxed);
+    // Restore the sig mask before the signal handler runs
+    let mut sig_mask = SigMask::from(ucontext.uc_sigmask);
+    sig_mask -= SIGKILL;
+    sig_mask -= SIGSTOP;
+    posix_thread.sig_mask().store(sig_mask, Ordering::Relaxed);
 
     Ok(SyscallReturn::NoReturn)
 }
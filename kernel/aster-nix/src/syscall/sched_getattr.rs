@@ -0,0 +1,60 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use core::sync::atomic::Ordering;
+
+use super::{sched_setattr::CSchedAttr, sched_setscheduler::thread_of, SyscallReturn};
+use crate::{
+    prelude::*,
+    process::posix_thread::PosixThreadExt,
+    sched::{SchedPolicy, RT_PRIORITY_RANGE},
+};
+
+pub fn sys_sched_getattr(
+    tid: i32,
+    attr_addr: Vaddr,
+    size: u32,
+    flags: u32,
+    ctx: &Context,
+) -> Result<SyscallReturn> {
+    debug!("tid = {}, attr_addr = {:#x}, size = {}", tid, attr_addr, size);
+
+    if flags != 0 {
+        return_errno_with_message!(Errno::EINVAL, "the flags must be zero");
+    }
+    if attr_addr == 0 || (size as usize) < size_of::<CSchedAttr>() {
+        return_errno_with_message!(Errno::EINVAL, "the sched_attr is NULL or too small");
+    }
+
+    let thread = thread_of(tid)?;
+    let sched_attr = thread.sched_attr();
+    let policy = sched_attr.policy();
+
+    let mut attr = CSchedAttr {
+        size: size_of::<CSchedAttr>() as u32,
+        sched_policy: policy as u32,
+        ..Default::default()
+    };
+    match policy {
+        SchedPolicy::Normal => {
+            if let Some(posix_thread) = thread.as_posix_thread() {
+                let nice = posix_thread.process().nice().load(Ordering::Relaxed);
+                attr.sched_nice = nice.to_raw() as i32;
+            }
+        }
+        SchedPolicy::Fifo | SchedPolicy::RoundRobin => {
+            let priority = thread.task().priority().get() as u32;
+            attr.sched_priority = RT_PRIORITY_RANGE.end() - priority;
+        }
+        SchedPolicy::Deadline => {
+            if let Some(params) = sched_attr.deadline_params() {
+                attr.sched_runtime = params.runtime();
+                attr.sched_deadline = params.deadline();
+                attr.sched_period = params.period();
+            }
+        }
+    }
+
+    ctx.get_user_space().write_val(attr_addr, &attr)?;
+
+    Ok(SyscallReturn::Return(0))
+}
@@ -0,0 +1,13 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use super::{sched_setscheduler::thread_of, SyscallReturn};
+use crate::prelude::*;
+
+pub fn sys_sched_getscheduler(tid: i32, _ctx: &Context) -> Result<SyscallReturn> {
+    debug!("tid = {}", tid);
+
+    let thread = thread_of(tid)?;
+    let policy = thread.sched_attr().policy();
+
+    Ok(SyscallReturn::Return(policy as _))
+}
@@ -0,0 +1,113 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use core::sync::atomic::Ordering;
+
+use super::{
+    sched_setscheduler::{check_real_time_permission, thread_of},
+    set_get_priority::check_set_nice_permission,
+    SyscallReturn,
+};
+use crate::{
+    prelude::*,
+    process::{credentials::capabilities::CapSet, posix_thread::PosixThreadExt},
+    sched::{nice::Nice, set_deadline, set_nice, set_scheduler, DeadlineParams, SchedPolicy},
+};
+
+pub fn sys_sched_setattr(
+    tid: i32,
+    attr_addr: Vaddr,
+    flags: u32,
+    ctx: &Context,
+) -> Result<SyscallReturn> {
+    if flags != 0 {
+        return_errno_with_message!(Errno::EINVAL, "the flags must be zero");
+    }
+    if attr_addr == 0 {
+        return_errno_with_message!(Errno::EINVAL, "the sched_attr is NULL");
+    }
+
+    let user_space = ctx.get_user_space();
+    // Like Linux, a zero size stands for the first version of the structure.
+    let size = user_space.read_val::<u32>(attr_addr)?;
+    if size != 0 && (size as usize) < size_of::<CSchedAttr>() {
+        user_space.write_val(attr_addr, &(size_of::<CSchedAttr>() as u32))?;
+        return_errno_with_message!(Errno::E2BIG, "the sched_attr is too small");
+    }
+    let attr = user_space.read_val::<CSchedAttr>(attr_addr)?;
+
+    debug!("tid = {}, attr = {:?}", tid, attr);
+
+    if attr.sched_flags != 0 {
+        return_errno_with_message!(Errno::EINVAL, "the scheduling flags are not supported");
+    }
+    let policy = SchedPolicy::try_from(attr.sched_policy)
+        .map_err(|_| Error::with_message(Errno::EINVAL, "invalid scheduling policy"))?;
+
+    let thread = thread_of(tid)?;
+
+    if policy == SchedPolicy::Deadline {
+        // Unlike the other real-time policies, no resource limit allows the deadline policy.
+        let is_privileged = ctx
+            .posix_thread
+            .credentials()
+            .effective_capset()
+            .contains(CapSet::SYS_NICE);
+        if !is_privileged {
+            return_errno_with_message!(
+                Errno::EPERM,
+                "setting the deadline policy is not permitted"
+            );
+        }
+        if attr.sched_priority != 0 {
+            return_errno_with_message!(Errno::EINVAL, "the priority must be zero");
+        }
+        // Like Linux, a zero period means that the period equals the deadline.
+        let period = if attr.sched_period == 0 {
+            attr.sched_deadline
+        } else {
+            attr.sched_period
+        };
+        let params = DeadlineParams::new(attr.sched_runtime, attr.sched_deadline, period)?;
+        set_deadline(&thread, params)?;
+        return Ok(SyscallReturn::Return(0));
+    }
+
+    // The nice value only matters to the normal policy.
+    let process = thread.as_posix_thread().map(|posix_thread| posix_thread.process());
+    let new_nice = Nice::new(attr.sched_nice.clamp(i8::MIN as i32, i8::MAX as i32) as i8);
+    let nice_to_set = process.filter(|process| {
+        policy == SchedPolicy::Normal && process.nice().load(Ordering::Relaxed) != new_nice
+    });
+    if let Some(process) = &nice_to_set {
+        check_set_nice_permission(process, new_nice, ctx)?;
+    }
+
+    check_real_time_permission(&thread, policy, attr.sched_priority, ctx)?;
+
+    set_scheduler(&thread, policy, attr.sched_priority)?;
+    if let Some(process) = &nice_to_set {
+        set_nice(process, new_nice);
+    }
+
+    Ok(SyscallReturn::Return(0))
+}
+
+/// The `sched_attr` structure in Linux.
+///
+/// Only the first version of the structure is supported, which has no fields
+/// for the utilization clamping.
+#[derive(Debug, Clone, Copy, Default, Pod)]
+#[repr(C)]
+pub(super) struct CSchedAttr {
+    pub(super) size: u32,
+    pub(super) sched_policy: u32,
+    pub(super) sched_flags: u64,
+    /// The nice value of [`SchedPolicy::Normal`].
+    pub(super) sched_nice: i32,
+    /// The priority of the real-time policies.
+    pub(super) sched_priority: u32,
+    /// The parameters of [`SchedPolicy::Deadline`], in nanoseconds.
+    pub(super) sched_runtime: u64,
+    pub(super) sched_deadline: u64,
+    pub(super) sched_period: u64,
+}
@@ -0,0 +1,104 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use super::SyscallReturn;
+use crate::{
+    prelude::*,
+    process::{credentials::capabilities::CapSet, ResourceType},
+    sched::{set_scheduler, SchedPolicy, RT_PRIORITY_RANGE},
+    thread::{thread_table, Thread, Tid},
+};
+
+pub fn sys_sched_setscheduler(
+    tid: i32,
+    policy: i32,
+    param_addr: Vaddr,
+    ctx: &Context,
+) -> Result<SyscallReturn> {
+    let policy = u32::try_from(policy)
+        .ok()
+        .and_then(|policy| SchedPolicy::try_from(policy).ok())
+        .ok_or_else(|| Error::with_message(Errno::EINVAL, "invalid scheduling policy"))?;
+    if param_addr == 0 {
+        return_errno_with_message!(Errno::EINVAL, "the sched_param is NULL");
+    }
+    let sched_param = ctx.get_user_space().read_val::<SchedParam>(param_addr)?;
+    let rt_priority = u32::try_from(sched_param.sched_priority)
+        .map_err(|_| Error::with_message(Errno::EINVAL, "invalid priority"))?;
+
+    debug!(
+        "tid = {}, policy = {:?}, rt_priority = {}",
+        tid, policy, rt_priority
+    );
+
+    let thread = thread_of(tid)?;
+
+    check_real_time_permission(&thread, policy, rt_priority, ctx)?;
+
+    set_scheduler(&thread, policy, rt_priority)?;
+
+    Ok(SyscallReturn::Return(0))
+}
+
+/// Returns the thread that `tid` refers to, where zero refers to the calling thread.
+pub(super) fn thread_of(tid: i32) -> Result<Arc<Thread>> {
+    if tid < 0 {
+        return_errno_with_message!(Errno::EINVAL, "the thread ID is negative");
+    }
+    if tid == 0 {
+        return Ok(Thread::current().unwrap());
+    }
+
+    thread_table::get_thread(tid as Tid)
+        .ok_or_else(|| Error::with_message(Errno::ESRCH, "the thread does not exist"))
+}
+
+/// Checks whether the caller may give `thread` the real-time `policy` with `rt_priority`.
+///
+/// Like Linux, a caller without `CAP_SYS_NICE` can switch to a real-time policy only if
+/// `RLIMIT_RTPRIO` is nonzero, and can raise the real-time priority only up to that limit.
+pub(super) fn check_real_time_permission(
+    thread: &Thread,
+    policy: SchedPolicy,
+    rt_priority: u32,
+    ctx: &Context,
+) -> Result<()> {
+    if !policy.is_real_time()
+        || ctx
+            .posix_thread
+            .credentials()
+            .effective_capset()
+            .contains(CapSet::SYS_NICE)
+    {
+        return Ok(());
+    }
+
+    let rt_priority_limit = ctx
+        .process
+        .resource_limits()
+        .lock()
+        .get_rlimit(ResourceType::RLIMIT_RTPRIO)
+        .get_cur();
+
+    let old_policy = thread.sched_attr().policy();
+    if old_policy != policy && rt_priority_limit == 0 {
+        return_errno_with_message!(Errno::EPERM, "setting a real-time policy is not permitted");
+    }
+
+    let old_rt_priority = if old_policy.is_real_time() {
+        RT_PRIORITY_RANGE.end() - thread.task().priority().get() as u32
+    } else {
+        0
+    };
+    if rt_priority > old_rt_priority && rt_priority as u64 > rt_priority_limit {
+        return_errno_with_message!(Errno::EPERM, "raising the real-time priority is not permitted");
+    }
+
+    Ok(())
+}
+
+/// The `sched_param` structure in Linux.
+#[derive(Debug, Clone, Copy, Pod)]
+#[repr(C)]
+struct SchedParam {
+    sched_priority: i32,
+}
@@ -32,7 +32,7 @@ pub fn sys_sendto(
     let socket = get_socket_from_fd(sockfd)?;
 
     let io_vecs = [IoVec::new(buf, len)];
-    let message_header = MessageHeader::new(socket_addr, None);
+    let message_header = MessageHeader::new(socket_addr, Vec::new());
 
     let send_size = socket.sendmsg(&io_vecs, message_header, flags)?;
 
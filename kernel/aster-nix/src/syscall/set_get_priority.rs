@@ -2,11 +2,16 @@
 
 use core::sync::atomic::Ordering;
 
+use aster_rights::ReadOp;
+
 use super::SyscallReturn;
 use crate::{
     prelude::*,
-    process::{posix_thread::PosixThreadExt, process_table, Pgid, Pid, Process, Uid},
-    sched::nice::Nice,
+    process::{
+        credentials::capabilities::CapSet, posix_thread::PosixThreadExt, process_table,
+        Credentials, Pgid, Pid, Process, ResourceType, Uid,
+    },
+    sched::{nice::Nice, set_nice},
 };
 
 pub fn sys_set_priority(which: i32, who: u32, prio: i32, ctx: &Context) -> Result<SyscallReturn> {
@@ -21,10 +26,17 @@ pub fn sys_set_priority(which: i32, who: u32, prio: i32, ctx: &Context) -> Resul
         prio_target, new_nice
     );
 
+    // Like Linux, the nice value is set for all the permitted processes, and the
+    // error of the last process that is not permitted (if any) is returned.
     let processes = get_processes(prio_target)?;
+    let mut result: Result<()> = Ok(());
     for process in processes.iter() {
-        process.nice().store(new_nice, Ordering::Relaxed);
+        match check_set_nice_permission(process, new_nice, ctx) {
+            Ok(()) => set_nice(process, new_nice),
+            Err(err) => result = Err(err),
+        }
     }
+    result?;
 
     Ok(SyscallReturn::Return(0))
 }
@@ -52,6 +64,40 @@ pub fn sys_get_priority(which: i32, who: u32, ctx: &Context) -> Result<SyscallRe
     Ok(SyscallReturn::Return(highest_prio as _))
 }
 
+pub(super) fn check_set_nice_permission(
+    process: &Process,
+    new_nice: Nice,
+    ctx: &Context,
+) -> Result<()> {
+    let credentials = ctx.posix_thread.credentials();
+    let is_privileged = credentials.effective_capset().contains(CapSet::SYS_NICE);
+
+    // The process must belong to the caller, unless the caller is privileged.
+    let euid = credentials.euid();
+    let is_owner = process_credentials(process)
+        .is_some_and(|target| target.ruid() == euid || target.euid() == euid);
+    if !is_owner && !is_privileged {
+        return_errno_with_message!(Errno::EPERM, "the process belongs to another user");
+    }
+
+    // Raising the priority is limited by `RLIMIT_NICE`, which is in the range of
+    // 1 (for nice 19) to 40 (for nice -20), unless the caller is privileged.
+    let old_nice = process.nice().load(Ordering::Relaxed);
+    if new_nice < old_nice && !is_privileged {
+        let nice_limit = ctx
+            .process
+            .resource_limits()
+            .lock()
+            .get_rlimit(ResourceType::RLIMIT_NICE)
+            .get_cur();
+        if ((20 - new_nice.to_raw()) as u64) > nice_limit {
+            return_errno_with_message!(Errno::EACCES, "raising the priority is not permitted");
+        }
+    }
+
+    Ok(())
+}
+
 fn get_processes(prio_target: PriorityTarget) -> Result<Vec<Arc<Process>>> {
     Ok(match prio_target {
         PriorityTarget::Process(pid) => {
@@ -72,13 +118,8 @@ fn get_processes(prio_target: PriorityTarget) -> Result<Vec<Arc<Process>>> {
             let processes: Vec<Arc<Process>> = process_table::process_table()
                 .iter()
                 .filter(|process| {
-                    let Some(main_thread) = process.main_thread() else {
-                        return false;
-                    };
-                    let Some(posix_thread) = main_thread.as_posix_thread() else {
-                        return false;
-                    };
-                    uid == posix_thread.credentials().ruid()
+                    process_credentials(process)
+                        .is_some_and(|credentials| uid == credentials.ruid())
                 })
                 .cloned()
                 .collect();
@@ -90,6 +131,13 @@ fn get_processes(prio_target: PriorityTarget) -> Result<Vec<Arc<Process>>> {
     })
 }
 
+/// Returns the credentials of a process, i.e., those of its main thread.
+fn process_credentials(process: &Process) -> Option<Credentials<ReadOp>> {
+    let main_thread = process.main_thread()?;
+    let posix_thread = main_thread.as_posix_thread()?;
+    Some(posix_thread.credentials())
+}
+
 #[derive(Debug)]
 enum PriorityTarget {
     Process(Pid),
@@ -18,9 +18,9 @@ pub fn sys_set_robust_list(
             "The len is not equal to the size of robust list head"
         );
     }
-    let robust_list_head: RobustListHead = ctx.get_user_space().read_val(robust_list_head_ptr)?;
-    debug!("{:x?}", robust_list_head);
+    // The list is maintained by the user space after the registration, so only
+    // the address of the head is recorded. The list is read when the thread exits.
     let mut robust_list = ctx.posix_thread.robust_list().lock();
-    *robust_list = Some(robust_list_head);
+    *robust_list = Some(robust_list_head_ptr);
     Ok(SyscallReturn::Return(0))
 }
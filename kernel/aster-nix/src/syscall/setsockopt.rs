@@ -15,7 +15,9 @@ pub fn sys_setsockopt(
     optlen: u32,
     _ctx: &Context,
 ) -> Result<SyscallReturn> {
-    let level = CSocketOptionLevel::try_from(level)?;
+    let level = CSocketOptionLevel::try_from(level).map_err(|_| {
+        Error::with_message(Errno::ENOPROTOOPT, "the socket option level is unknown")
+    })?;
     if optval == 0 {
         return_errno_with_message!(Errno::EINVAL, "optval is null pointer");
     }
@@ -0,0 +1,395 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! `signalfd()` creates a file descriptor that can be used to accept signals
+//! targeted at the caller (we name it as `SignalFile`).
+//!
+//! `SignalFile` holds a signal mask. Reading from `SignalFile` dequeues the
+//! pending signals that are in the mask and returns one `signalfd_siginfo`
+//! structure for each of them. The pending signals that are not in the mask
+//! are left pending, so they will be handled as usual.
+//! If no signals in the mask are pending, the read blocks until one arrives
+//! or fails with `EAGAIN` if the file is nonblocking.
+//!
+//! The signals in the mask should normally be blocked with `sigprocmask()`,
+//! otherwise they may be delivered to the signal handlers before being read.
+//!
+//! For more detailed information about this syscall,
+//! refer to the man 2 signalfd documentation.
+//!
+
+use core::sync::atomic::Ordering;
+
+use ostd::trap::in_interrupt_context;
+
+use super::SyscallReturn;
+use crate::{
+    events::{IoEvents, Observer},
+    fs::{
+        file_handle::FileLike,
+        file_table::{FdFlags, FileDesc},
+        utils::{CreationFlags, InodeMode, InodeType, Metadata, StatusFlags},
+    },
+    prelude::*,
+    process::{
+        posix_thread::PosixThreadExt,
+        signal::{
+            constants::{SIGBUS, SIGFPE, SIGILL, SIGKILL, SIGSEGV, SIGSTOP, SIGTRAP},
+            sig_mask::{AtomicSigMask, SigMask},
+            signals::Signal,
+            Pollable, Pollee, Poller, SigEvents, SigEventsFilter,
+        },
+        Gid, Uid,
+    },
+    thread::{
+        work_queue::{submit_work_item, work_item::WorkItem, WorkPriority},
+        Thread,
+    },
+    time::clocks::RealTimeClock,
+};
+
+pub fn sys_signalfd(
+    fd: FileDesc,
+    mask_addr: Vaddr,
+    sizemask: usize,
+    ctx: &Context,
+) -> Result<SyscallReturn> {
+    debug!(
+        "fd = {}, mask_addr = 0x{:x}, sizemask = {}",
+        fd, mask_addr, sizemask
+    );
+
+    do_sys_signalfd4(fd, mask_addr, sizemask, Flags::empty(), ctx)
+}
+
+pub fn sys_signalfd4(
+    fd: FileDesc,
+    mask_addr: Vaddr,
+    sizemask: usize,
+    flags: u32,
+    ctx: &Context,
+) -> Result<SyscallReturn> {
+    trace!("raw flags = {}", flags);
+    let flags = Flags::from_bits(flags)
+        .ok_or_else(|| Error::with_message(Errno::EINVAL, "unknown flags"))?;
+    debug!(
+        "fd = {}, mask_addr = 0x{:x}, sizemask = {}, flags = {:?}",
+        fd, mask_addr, sizemask, flags
+    );
+
+    do_sys_signalfd4(fd, mask_addr, sizemask, flags, ctx)
+}
+
+fn do_sys_signalfd4(
+    fd: FileDesc,
+    mask_addr: Vaddr,
+    sizemask: usize,
+    flags: Flags,
+    ctx: &Context,
+) -> Result<SyscallReturn> {
+    if sizemask != core::mem::size_of::<SigMask>() {
+        return_errno_with_message!(Errno::EINVAL, "sigset size is not equal to 8");
+    }
+
+    // According to the man pages, "it is not possible to receive SIGKILL or SIGSTOP signals
+    // via a signalfd file descriptor; these signals are silently ignored if specified in mask."
+    let mask = {
+        let mask = SigMask::from(ctx.get_user_space().read_val::<u64>(mask_addr)?);
+        mask - SIGKILL - SIGSTOP
+    };
+
+    // Update the mask of an existing signalfd.
+    if fd != -1 {
+        let file = {
+            let file_table = ctx.process.file_table().lock();
+            file_table.get_file(fd)?.clone()
+        };
+        let signal_file = file
+            .downcast_ref::<SignalFile>()
+            .ok_or_else(|| Error::with_message(Errno::EINVAL, "the fd is not a signalfd"))?;
+        signal_file.set_mask(mask);
+        return Ok(SyscallReturn::Return(fd as _));
+    }
+
+    let signal_file = SignalFile::new(mask, flags, &Thread::current().unwrap());
+    let fd = {
+        let mut file_table = ctx.process.file_table().lock();
+        let fd_flags = if flags.contains(Flags::SFD_CLOEXEC) {
+            FdFlags::CLOEXEC
+        } else {
+            FdFlags::empty()
+        };
+        file_table.insert(signal_file, fd_flags)?
+    };
+
+    Ok(SyscallReturn::Return(fd as _))
+}
+
+bitflags! {
+    struct Flags: u32 {
+        const SFD_CLOEXEC = CreationFlags::O_CLOEXEC.bits();
+        const SFD_NONBLOCK = StatusFlags::O_NONBLOCK.bits();
+    }
+}
+
+struct SignalFile {
+    /// The signals that can be read from the file.
+    mask: AtomicSigMask,
+    pollee: Pollee,
+    flags: Mutex<Flags>,
+    /// The thread whose signal queues are observed to wake up the pollers.
+    ///
+    /// TODO: Reading the file dequeues signals from the queues of the current
+    /// thread (as Linux does), but only the signals sent to the creating thread
+    /// make the file readable for now.
+    owner: Weak<Thread>,
+    weak_self: Weak<Self>,
+}
+
+impl SignalFile {
+    fn new(mask: SigMask, flags: Flags, owner: &Arc<Thread>) -> Arc<Self> {
+        let signal_file = Arc::new_cyclic(|weak_self| Self {
+            mask: AtomicSigMask::from(mask),
+            pollee: Pollee::new(IoEvents::empty()),
+            flags: Mutex::new(flags),
+            owner: Arc::downgrade(owner),
+            weak_self: weak_self.clone(),
+        });
+
+        let posix_thread = owner.as_posix_thread().unwrap();
+        posix_thread.register_sigqueue_observer(
+            signal_file.weak_self.clone() as _,
+            SigEventsFilter::new(SigMask::new_empty()),
+        );
+        // Check pending signals after registering the observer to avoid race conditions.
+        signal_file.update_io_state();
+
+        signal_file
+    }
+
+    fn is_nonblocking(&self) -> bool {
+        self.flags.lock().contains(Flags::SFD_NONBLOCK)
+    }
+
+    fn set_mask(&self, mask: SigMask) {
+        self.mask.store(mask, Ordering::Relaxed);
+        self.update_io_state();
+    }
+
+    /// Returns the signals that are both pending and in the mask.
+    fn pending_signals(&self) -> SigMask {
+        let Some(thread) = Thread::current() else {
+            return SigMask::new_empty();
+        };
+        let Some(posix_thread) = thread.as_posix_thread() else {
+            return SigMask::new_empty();
+        };
+
+        posix_thread.sig_pending() & self.mask.load(Ordering::Relaxed)
+    }
+
+    fn update_io_state(&self) {
+        if self.pending_signals().is_empty() {
+            self.pollee.del_events(IoEvents::IN);
+        } else {
+            self.pollee.add_events(IoEvents::IN);
+        }
+    }
+
+    /// Dequeues at most `max_count` signals that are in the mask.
+    fn try_dequeue(&self, max_count: usize) -> Result<Vec<Box<dyn Signal>>> {
+        let current = Thread::current().unwrap();
+        let posix_thread = current.as_posix_thread().unwrap();
+
+        // The signals that are not in the mask are "blocked" from the dequeue.
+        let blocked = SigMask::new_full() - self.mask.load(Ordering::Relaxed);
+        let mut signals = Vec::new();
+        while signals.len() < max_count {
+            let Some(signal) = posix_thread.dequeue_signal(&blocked) else {
+                break;
+            };
+            signals.push(signal);
+        }
+
+        self.update_io_state();
+
+        if signals.is_empty() {
+            return_errno_with_message!(Errno::EAGAIN, "no signals in the mask are pending");
+        }
+        Ok(signals)
+    }
+}
+
+impl Drop for SignalFile {
+    fn drop(&mut self) {
+        let Some(owner) = self.owner.upgrade() else {
+            return;
+        };
+        if let Some(posix_thread) = owner.as_posix_thread() {
+            posix_thread.unregiser_sigqueue_observer(&(self.weak_self.clone() as _));
+        }
+    }
+}
+
+impl Observer<SigEvents> for SignalFile {
+    fn on_events(&self, events: &SigEvents) {
+        if !self.mask.contains(events.sig_num(), Ordering::Relaxed) {
+            return;
+        }
+
+        // Signals may be sent in the interrupt context (e.g., by timer callbacks), where
+        // notifying the observers of the pollee (e.g., epoll files) is not allowed. So the
+        // notification is deferred to a work item in that case.
+        if !in_interrupt_context() {
+            self.pollee.add_events(IoEvents::IN);
+            return;
+        }
+        let pollee = self.pollee.clone();
+        let work_item = Arc::new(WorkItem::new(Box::new(move || {
+            pollee.add_events(IoEvents::IN);
+        })));
+        submit_work_item(work_item, WorkPriority::High);
+    }
+}
+
+impl Pollable for SignalFile {
+    fn poll(&self, mask: IoEvents, poller: Option<&mut Poller>) -> IoEvents {
+        // The pending signals may have been dequeued by the signal handling routine.
+        self.update_io_state();
+        self.pollee.poll(mask, poller)
+    }
+}
+
+impl FileLike for SignalFile {
+    fn read(&self, buf: &mut [u8]) -> Result<usize> {
+        const SIGINFO_LEN: usize = core::mem::size_of::<signalfd_siginfo>();
+
+        let max_count = buf.len() / SIGINFO_LEN;
+        if max_count == 0 {
+            return_errno_with_message!(
+                Errno::EINVAL,
+                "buf len is less than the size of signalfd_siginfo"
+            );
+        }
+
+        let signals = if self.is_nonblocking() {
+            self.try_dequeue(max_count)?
+        } else {
+            self.wait_events(IoEvents::IN, || self.try_dequeue(max_count))?
+        };
+
+        for (signal, chunk) in signals.iter().zip(buf.chunks_exact_mut(SIGINFO_LEN)) {
+            chunk.copy_from_slice(signalfd_siginfo::from(signal.as_ref()).as_bytes());
+        }
+
+        Ok(signals.len() * SIGINFO_LEN)
+    }
+
+    fn status_flags(&self) -> StatusFlags {
+        if self.is_nonblocking() {
+            StatusFlags::O_NONBLOCK
+        } else {
+            StatusFlags::empty()
+        }
+    }
+
+    fn set_status_flags(&self, new_flags: StatusFlags) -> Result<()> {
+        let mut flags = self.flags.lock();
+
+        if new_flags.contains(StatusFlags::O_NONBLOCK) {
+            *flags |= Flags::SFD_NONBLOCK;
+        } else {
+            *flags &= !Flags::SFD_NONBLOCK;
+        }
+
+        Ok(())
+    }
+
+    fn register_observer(
+        &self,
+        observer: Weak<dyn Observer<IoEvents>>,
+        mask: IoEvents,
+    ) -> Result<()> {
+        self.pollee.register_observer(observer, mask);
+        Ok(())
+    }
+
+    fn unregister_observer(
+        &self,
+        observer: &Weak<dyn Observer<IoEvents>>,
+    ) -> Option<Weak<dyn Observer<IoEvents>>> {
+        self.pollee.unregister_observer(observer)
+    }
+
+    fn metadata(&self) -> Metadata {
+        let now = RealTimeClock::get().read_time();
+        Metadata {
+            dev: 0,
+            ino: 0,
+            size: 0,
+            blk_size: 0,
+            blocks: 0,
+            atime: now,
+            mtime: now,
+            ctime: now,
+            type_: InodeType::File,
+            mode: InodeMode::from_bits_truncate(0o600),
+            nlinks: 1,
+            uid: Uid::new_root(),
+            gid: Gid::new_root(),
+            rdev: 0,
+        }
+    }
+}
+
+/// The structure returned by reading a signalfd.
+///
+/// The layout is the same as `struct signalfd_siginfo` in Linux.
+#[derive(Debug, Clone, Copy, Pod)]
+#[repr(C)]
+#[allow(non_camel_case_types)]
+struct signalfd_siginfo {
+    ssi_signo: u32,
+    ssi_errno: i32,
+    ssi_code: i32,
+    ssi_pid: u32,
+    ssi_uid: u32,
+    ssi_fd: i32,
+    ssi_tid: u32,
+    ssi_band: u32,
+    ssi_overrun: u32,
+    ssi_trapno: u32,
+    ssi_status: i32,
+    ssi_int: i32,
+    ssi_ptr: u64,
+    ssi_utime: u64,
+    ssi_stime: u64,
+    ssi_addr: u64,
+    ssi_addr_lsb: u16,
+    __pad2: u16,
+    ssi_syscall: i32,
+    ssi_call_addr: u64,
+    ssi_arch: u32,
+    __pad: [u8; 28],
+}
+
+impl From<&dyn Signal> for signalfd_siginfo {
+    fn from(signal: &dyn Signal) -> Self {
+        let info = signal.to_info();
+
+        // TODO: Fill in the fields that are specific to `SIGCHLD`, `SIGIO` and timer signals.
+        let mut siginfo = Self::new_zeroed();
+        siginfo.ssi_signo = info.si_signo as u32;
+        siginfo.ssi_errno = info.si_errno;
+        siginfo.ssi_code = info.si_code;
+        if matches!(signal.num(), SIGSEGV | SIGBUS | SIGILL | SIGFPE | SIGTRAP) {
+            siginfo.ssi_addr = info.si_addr() as u64;
+        } else {
+            let value = info.si_value();
+            siginfo.ssi_pid = info.si_pid();
+            siginfo.ssi_uid = info.si_uid().as_u32();
+            siginfo.ssi_int = value.read_int();
+            siginfo.ssi_ptr = value.read_ptr() as u64;
+        }
+        siginfo
+    }
+}
@@ -5,7 +5,7 @@ use crate::{
     fs::{file_handle::FileLike, file_table::FdFlags},
     net::socket::{
         ip::{DatagramSocket, StreamSocket},
-        unix::UnixStreamSocket,
+        unix::{UnixDatagramSocket, UnixStreamSocket},
         vsock::VsockStreamSocket,
     },
     prelude::*,
@@ -27,6 +27,9 @@ pub fn sys_socket(domain: i32, type_: i32, protocol: i32, ctx: &Context) -> Resu
         (CSocketAddrFamily::AF_UNIX, SockType::SOCK_STREAM | SockType::SOCK_SEQPACKET, _) => {
             UnixStreamSocket::new(nonblocking) as Arc<dyn FileLike>
         }
+        (CSocketAddrFamily::AF_UNIX, SockType::SOCK_DGRAM, _) => {
+            UnixDatagramSocket::new(nonblocking) as Arc<dyn FileLike>
+        }
         (
             CSocketAddrFamily::AF_INET,
             SockType::SOCK_STREAM,
@@ -49,7 +52,7 @@ pub fn sys_socket(domain: i32, type_: i32, protocol: i32, ctx: &Context) -> Resu
         } else {
             FdFlags::empty()
         };
-        file_table.insert(file_like, fd_flags)
+        file_table.insert(file_like, fd_flags)?
     };
     Ok(SyscallReturn::Return(fd as _))
 }
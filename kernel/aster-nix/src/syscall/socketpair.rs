@@ -43,8 +43,14 @@ pub fn sys_socketpair(
         } else {
             FdFlags::empty()
         };
-        let fd_a = file_table.insert(socket_a, fd_flags);
-        let fd_b = file_table.insert(socket_b, fd_flags);
+        let fd_a = file_table.insert(socket_a, fd_flags)?;
+        let fd_b = match file_table.insert(socket_b, fd_flags) {
+            Ok(fd_b) => fd_b,
+            Err(err) => {
+                file_table.close_file(fd_a).unwrap();
+                return Err(err);
+            }
+        };
         SocketFds(fd_a, fd_b)
     };
 
@@ -0,0 +1,316 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! `timerfd_create()` creates a timer that delivers timer expiration notifications
+//! via a file descriptor (we name it as `TimerFile`).
+//!
+//! `TimerFile` holds a u64 counter of the expirations since the timer was last set
+//! or the file was last read. Reading from `TimerFile` returns the counter and resets it.
+//! If no expirations have occurred, the read blocks until the next expiration
+//! or fails with `EAGAIN` if the file is nonblocking.
+//!
+//! For more detailed information about this syscall,
+//! refer to the man 2 timerfd_create documentation.
+//!
+
+use core::{
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+
+use super::SyscallReturn;
+use crate::{
+    events::{IoEvents, Observer},
+    fs::{
+        file_handle::FileLike,
+        file_table::{FdFlags, FileDesc},
+        utils::{CreationFlags, InodeMode, InodeType, Metadata, StatusFlags},
+    },
+    prelude::*,
+    process::{
+        signal::{Pollable, Pollee, Poller},
+        Gid, Uid,
+    },
+    syscall::ClockId,
+    thread::work_queue::{submit_work_item, work_item::WorkItem, WorkPriority},
+    time::{
+        clockid_t,
+        clocks::{BootTimeClock, MonotonicClock, RealTimeClock},
+        itimerspec_t,
+        timer::Timeout,
+        timespec_t, Timer,
+    },
+};
+
+pub fn sys_timerfd_create(clockid: clockid_t, flags: u32, ctx: &Context) -> Result<SyscallReturn> {
+    trace!("raw flags = {}", flags);
+    let flags = Flags::from_bits(flags)
+        .ok_or_else(|| Error::with_message(Errno::EINVAL, "unknown flags"))?;
+    debug!("clockid = {}, flags = {:?}", clockid, flags);
+
+    let timer_file = TimerFile::new(clockid, flags)?;
+    let fd = {
+        let mut file_table = ctx.process.file_table().lock();
+        let fd_flags = if flags.contains(Flags::TFD_CLOEXEC) {
+            FdFlags::CLOEXEC
+        } else {
+            FdFlags::empty()
+        };
+        file_table.insert(timer_file, fd_flags)?
+    };
+
+    Ok(SyscallReturn::Return(fd as _))
+}
+
+pub fn sys_timerfd_settime(
+    fd: FileDesc,
+    flags: i32,
+    new_itimerspec_addr: Vaddr,
+    old_itimerspec_addr: Vaddr,
+    ctx: &Context,
+) -> Result<SyscallReturn> {
+    debug!(
+        "fd = {}, flags = {}, new_itimerspec_addr = 0x{:x}, old_itimerspec_addr = 0x{:x}",
+        fd, flags, new_itimerspec_addr, old_itimerspec_addr
+    );
+
+    let flags = SetTimeFlags::from_bits(flags)
+        .ok_or_else(|| Error::with_message(Errno::EINVAL, "unknown flags"))?;
+
+    let user_space = ctx.get_user_space();
+    let new_itimerspec = user_space.read_val::<itimerspec_t>(new_itimerspec_addr)?;
+    let interval = Duration::try_from(new_itimerspec.it_interval)?;
+    let expire_time = Duration::try_from(new_itimerspec.it_value)?;
+
+    let file = {
+        let file_table = ctx.process.file_table().lock();
+        file_table.get_file(fd)?.clone()
+    };
+    let timer_file = file
+        .downcast_ref::<TimerFile>()
+        .ok_or_else(|| Error::with_message(Errno::EINVAL, "the fd is not a timerfd"))?;
+
+    if old_itimerspec_addr != 0 {
+        user_space.write_val(old_itimerspec_addr, &timer_file.itimerspec())?;
+    }
+
+    // TODO: Support `TFD_TIMER_CANCEL_ON_SET`.
+    let timeout = if expire_time == Duration::ZERO {
+        None
+    } else if flags.contains(SetTimeFlags::TFD_TIMER_ABSTIME) {
+        Some(Timeout::When(expire_time))
+    } else {
+        Some(Timeout::After(expire_time))
+    };
+    timer_file.set_timer(interval, timeout);
+
+    Ok(SyscallReturn::Return(0))
+}
+
+pub fn sys_timerfd_gettime(
+    fd: FileDesc,
+    itimerspec_addr: Vaddr,
+    ctx: &Context,
+) -> Result<SyscallReturn> {
+    debug!("fd = {}, itimerspec_addr = 0x{:x}", fd, itimerspec_addr);
+
+    let file = {
+        let file_table = ctx.process.file_table().lock();
+        file_table.get_file(fd)?.clone()
+    };
+    let timer_file = file
+        .downcast_ref::<TimerFile>()
+        .ok_or_else(|| Error::with_message(Errno::EINVAL, "the fd is not a timerfd"))?;
+
+    ctx.get_user_space()
+        .write_val(itimerspec_addr, &timer_file.itimerspec())?;
+
+    Ok(SyscallReturn::Return(0))
+}
+
+bitflags! {
+    struct Flags: u32 {
+        const TFD_CLOEXEC = CreationFlags::O_CLOEXEC.bits();
+        const TFD_NONBLOCK = StatusFlags::O_NONBLOCK.bits();
+    }
+}
+
+bitflags! {
+    struct SetTimeFlags: i32 {
+        const TFD_TIMER_ABSTIME = 1;
+        const TFD_TIMER_CANCEL_ON_SET = 2;
+    }
+}
+
+struct TimerFile {
+    timer: Arc<Timer>,
+    ticks: Arc<AtomicU64>,
+    pollee: Pollee,
+    flags: Mutex<Flags>,
+}
+
+impl TimerFile {
+    fn new(clockid: clockid_t, flags: Flags) -> Result<Arc<Self>> {
+        let ticks = Arc::new(AtomicU64::new(0));
+        let pollee = Pollee::new(IoEvents::empty());
+
+        // The timer callback is invoked in the interrupt context, where notifying the observers
+        // of the pollee (e.g., epoll files) is not allowed. So the notification is deferred to a
+        // work item.
+        let work_item = {
+            let ticks = ticks.clone();
+            let pollee = pollee.clone();
+            Arc::new(WorkItem::new(Box::new(move || {
+                if ticks.load(Ordering::Acquire) != 0 {
+                    pollee.add_events(IoEvents::IN);
+                }
+            })))
+        };
+        let func = {
+            let ticks = ticks.clone();
+            move || {
+                ticks.fetch_add(1, Ordering::AcqRel);
+                submit_work_item(work_item.clone(), WorkPriority::High);
+            }
+        };
+
+        let timer = match ClockId::try_from(clockid)? {
+            ClockId::CLOCK_REALTIME => RealTimeClock::timer_manager().create_timer(func),
+            ClockId::CLOCK_MONOTONIC => MonotonicClock::timer_manager().create_timer(func),
+            ClockId::CLOCK_BOOTTIME => BootTimeClock::timer_manager().create_timer(func),
+            _ => return_errno_with_message!(Errno::EINVAL, "unsupported clock ID"),
+        };
+
+        Ok(Arc::new(Self {
+            timer,
+            ticks,
+            pollee,
+            flags: Mutex::new(flags),
+        }))
+    }
+
+    fn is_nonblocking(&self) -> bool {
+        self.flags.lock().contains(Flags::TFD_NONBLOCK)
+    }
+
+    /// Arms the timer with `timeout` and `interval`, or disarms it if `timeout` is `None`.
+    ///
+    /// The expirations that have not been read are discarded.
+    fn set_timer(&self, interval: Duration, timeout: Option<Timeout>) {
+        self.timer.cancel();
+        self.ticks.store(0, Ordering::Release);
+        self.pollee.del_events(IoEvents::IN);
+
+        self.timer.set_interval(interval);
+        if let Some(timeout) = timeout {
+            self.timer.set_timeout(timeout);
+        }
+    }
+
+    fn itimerspec(&self) -> itimerspec_t {
+        itimerspec_t {
+            it_interval: timespec_t::from(self.timer.interval()),
+            it_value: timespec_t::from(self.timer.remain()),
+        }
+    }
+
+    fn try_read(&self) -> Result<u64> {
+        let ticks = self.ticks.swap(0, Ordering::AcqRel);
+
+        self.pollee.del_events(IoEvents::IN);
+        // The timer may have expired after the swap and before the deletion of the events.
+        if self.ticks.load(Ordering::Acquire) != 0 {
+            self.pollee.add_events(IoEvents::IN);
+        }
+
+        if ticks == 0 {
+            return_errno_with_message!(Errno::EAGAIN, "the timer has not expired");
+        }
+        Ok(ticks)
+    }
+}
+
+impl Drop for TimerFile {
+    fn drop(&mut self) {
+        self.timer.cancel();
+    }
+}
+
+impl Pollable for TimerFile {
+    fn poll(&self, mask: IoEvents, poller: Option<&mut Poller>) -> IoEvents {
+        self.pollee.poll(mask, poller)
+    }
+}
+
+impl FileLike for TimerFile {
+    fn read(&self, buf: &mut [u8]) -> Result<usize> {
+        let read_len = core::mem::size_of::<u64>();
+        if buf.len() < read_len {
+            return_errno_with_message!(Errno::EINVAL, "buf len is less than the size of u64");
+        }
+
+        let ticks = if self.is_nonblocking() {
+            self.try_read()?
+        } else {
+            self.wait_events(IoEvents::IN, || self.try_read())?
+        };
+        buf[..read_len].copy_from_slice(ticks.as_bytes());
+
+        Ok(read_len)
+    }
+
+    fn status_flags(&self) -> StatusFlags {
+        if self.is_nonblocking() {
+            StatusFlags::O_NONBLOCK
+        } else {
+            StatusFlags::empty()
+        }
+    }
+
+    fn set_status_flags(&self, new_flags: StatusFlags) -> Result<()> {
+        let mut flags = self.flags.lock();
+
+        if new_flags.contains(StatusFlags::O_NONBLOCK) {
+            *flags |= Flags::TFD_NONBLOCK;
+        } else {
+            *flags &= !Flags::TFD_NONBLOCK;
+        }
+
+        Ok(())
+    }
+
+    fn register_observer(
+        &self,
+        observer: Weak<dyn Observer<IoEvents>>,
+        mask: IoEvents,
+    ) -> Result<()> {
+        self.pollee.register_observer(observer, mask);
+        Ok(())
+    }
+
+    fn unregister_observer(
+        &self,
+        observer: &Weak<dyn Observer<IoEvents>>,
+    ) -> Option<Weak<dyn Observer<IoEvents>>> {
+        self.pollee.unregister_observer(observer)
+    }
+
+    fn metadata(&self) -> Metadata {
+        let now = RealTimeClock::get().read_time();
+        Metadata {
+            dev: 0,
+            ino: 0,
+            size: 0,
+            blk_size: 0,
+            blocks: 0,
+            atime: now,
+            mtime: now,
+            ctime: now,
+            type_: InodeType::File,
+            mode: InodeMode::from_bits_truncate(0o600),
+            nlinks: 1,
+            uid: Uid::new_root(),
+            gid: Gid::new_root(),
+            rdev: 0,
+        }
+    }
+}
@@ -5,7 +5,7 @@ use crate::{
     fs::{
         file_table::FileDesc,
         fs_resolver::{FsPath, AT_FDCWD},
-        utils::PATH_MAX,
+        utils::{InodeType, PATH_MAX},
     },
     prelude::*,
     process::ResourceType,
@@ -28,7 +28,7 @@ pub fn sys_truncate(path_ptr: Vaddr, len: isize, ctx: &Context) -> Result<Syscal
 
     check_length(len, ctx)?;
 
-    let dir_dentry = {
+    let dentry = {
         let path = path.to_string_lossy();
         if path.is_empty() {
             return_errno_with_message!(Errno::ENOENT, "path is empty");
@@ -36,7 +36,16 @@ pub fn sys_truncate(path_ptr: Vaddr, len: isize, ctx: &Context) -> Result<Syscal
         let fs_path = FsPath::new(AT_FDCWD, path.as_ref())?;
         ctx.process.fs().read().lookup(&fs_path)?
     };
-    dir_dentry.resize(len as usize)?;
+    match dentry.type_() {
+        InodeType::File => (),
+        InodeType::Dir => return_errno_with_message!(Errno::EISDIR, "the file is a directory"),
+        _ => return_errno_with_message!(Errno::EINVAL, "the file is not a regular file"),
+    }
+    // FIXME: The current implementation is dummy
+    if !dentry.mode()?.is_writable() {
+        return_errno_with_message!(Errno::EACCES, "the file is not writable");
+    }
+    dentry.resize(len as usize)?;
     Ok(SyscallReturn::Return(0))
 }
 
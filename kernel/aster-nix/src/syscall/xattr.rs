@@ -0,0 +1,350 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use super::SyscallReturn;
+use crate::{
+    fs::{
+        file_table::FileDesc,
+        fs_resolver::FsPath,
+        inode_handle::InodeHandle,
+        utils::{
+            Inode, InodeMode, InodeType, XattrName, XattrNamespace, XattrSetFlags, PATH_MAX,
+            XATTR_LIST_MAX_LEN, XATTR_NAME_MAX_LEN, XATTR_VALUE_MAX_LEN,
+        },
+    },
+    prelude::*,
+    process::credentials::capabilities::CapSet,
+};
+
+pub fn sys_setxattr(
+    path_ptr: Vaddr,
+    name_ptr: Vaddr,
+    value_ptr: Vaddr,
+    size: usize,
+    flags: u32,
+    ctx: &Context,
+) -> Result<SyscallReturn> {
+    let inode = lookup_path(path_ptr, true, ctx)?;
+    set_xattr(&inode, name_ptr, value_ptr, size, flags, ctx)
+}
+
+pub fn sys_lsetxattr(
+    path_ptr: Vaddr,
+    name_ptr: Vaddr,
+    value_ptr: Vaddr,
+    size: usize,
+    flags: u32,
+    ctx: &Context,
+) -> Result<SyscallReturn> {
+    let inode = lookup_path(path_ptr, false, ctx)?;
+    set_xattr(&inode, name_ptr, value_ptr, size, flags, ctx)
+}
+
+pub fn sys_fsetxattr(
+    fd: FileDesc,
+    name_ptr: Vaddr,
+    value_ptr: Vaddr,
+    size: usize,
+    flags: u32,
+    ctx: &Context,
+) -> Result<SyscallReturn> {
+    let inode = lookup_fd(fd, ctx)?;
+    set_xattr(&inode, name_ptr, value_ptr, size, flags, ctx)
+}
+
+pub fn sys_getxattr(
+    path_ptr: Vaddr,
+    name_ptr: Vaddr,
+    value_ptr: Vaddr,
+    size: usize,
+    ctx: &Context,
+) -> Result<SyscallReturn> {
+    let inode = lookup_path(path_ptr, true, ctx)?;
+    get_xattr(&inode, name_ptr, value_ptr, size, ctx)
+}
+
+pub fn sys_lgetxattr(
+    path_ptr: Vaddr,
+    name_ptr: Vaddr,
+    value_ptr: Vaddr,
+    size: usize,
+    ctx: &Context,
+) -> Result<SyscallReturn> {
+    let inode = lookup_path(path_ptr, false, ctx)?;
+    get_xattr(&inode, name_ptr, value_ptr, size, ctx)
+}
+
+pub fn sys_fgetxattr(
+    fd: FileDesc,
+    name_ptr: Vaddr,
+    value_ptr: Vaddr,
+    size: usize,
+    ctx: &Context,
+) -> Result<SyscallReturn> {
+    let inode = lookup_fd(fd, ctx)?;
+    get_xattr(&inode, name_ptr, value_ptr, size, ctx)
+}
+
+pub fn sys_listxattr(
+    path_ptr: Vaddr,
+    list_ptr: Vaddr,
+    size: usize,
+    ctx: &Context,
+) -> Result<SyscallReturn> {
+    let inode = lookup_path(path_ptr, true, ctx)?;
+    list_xattr(&inode, list_ptr, size, ctx)
+}
+
+pub fn sys_llistxattr(
+    path_ptr: Vaddr,
+    list_ptr: Vaddr,
+    size: usize,
+    ctx: &Context,
+) -> Result<SyscallReturn> {
+    let inode = lookup_path(path_ptr, false, ctx)?;
+    list_xattr(&inode, list_ptr, size, ctx)
+}
+
+pub fn sys_flistxattr(
+    fd: FileDesc,
+    list_ptr: Vaddr,
+    size: usize,
+    ctx: &Context,
+) -> Result<SyscallReturn> {
+    let inode = lookup_fd(fd, ctx)?;
+    list_xattr(&inode, list_ptr, size, ctx)
+}
+
+pub fn sys_removexattr(path_ptr: Vaddr, name_ptr: Vaddr, ctx: &Context) -> Result<SyscallReturn> {
+    let inode = lookup_path(path_ptr, true, ctx)?;
+    remove_xattr(&inode, name_ptr, ctx)
+}
+
+pub fn sys_lremovexattr(path_ptr: Vaddr, name_ptr: Vaddr, ctx: &Context) -> Result<SyscallReturn> {
+    let inode = lookup_path(path_ptr, false, ctx)?;
+    remove_xattr(&inode, name_ptr, ctx)
+}
+
+pub fn sys_fremovexattr(fd: FileDesc, name_ptr: Vaddr, ctx: &Context) -> Result<SyscallReturn> {
+    let inode = lookup_fd(fd, ctx)?;
+    remove_xattr(&inode, name_ptr, ctx)
+}
+
+fn set_xattr(
+    inode: &Arc<dyn Inode>,
+    name_ptr: Vaddr,
+    value_ptr: Vaddr,
+    size: usize,
+    flags: u32,
+    ctx: &Context,
+) -> Result<SyscallReturn> {
+    let name = read_name(name_ptr, ctx)?;
+    let flags = XattrSetFlags::from_bits(flags)
+        .ok_or_else(|| Error::with_message(Errno::EINVAL, "invalid flags"))?;
+    debug!(
+        "name = {:?}, value_ptr = 0x{:x}, size = {}, flags = {:?}",
+        name, value_ptr, size, flags
+    );
+
+    let name = XattrName::try_from_full_name(&name)?;
+    check_namespace_access(name, ctx)?;
+    check_write_access(inode, name, ctx)?;
+
+    if size > XATTR_VALUE_MAX_LEN {
+        return_errno_with_message!(Errno::E2BIG, "the xattr value is too long");
+    }
+    let mut value = vec![0u8; size];
+    ctx.get_user_space()
+        .read_bytes(value_ptr, &mut VmWriter::from(value.as_mut_slice()))?;
+
+    inode.set_xattr(name, &value, flags)?;
+    Ok(SyscallReturn::Return(0))
+}
+
+fn get_xattr(
+    inode: &Arc<dyn Inode>,
+    name_ptr: Vaddr,
+    value_ptr: Vaddr,
+    size: usize,
+    ctx: &Context,
+) -> Result<SyscallReturn> {
+    let name = read_name(name_ptr, ctx)?;
+    debug!(
+        "name = {:?}, value_ptr = 0x{:x}, size = {}",
+        name, value_ptr, size
+    );
+
+    let name = XattrName::try_from_full_name(&name)?;
+    check_namespace_access(name, ctx)?;
+    if name.namespace() == XattrNamespace::User && !is_user_xattr_supported(inode) {
+        return_errno_with_message!(Errno::ENODATA, "the inode has no user xattrs");
+    }
+
+    let value = inode.get_xattr(name)?;
+    write_to_user(&value, value_ptr, size, ctx)
+}
+
+fn list_xattr(
+    inode: &Arc<dyn Inode>,
+    list_ptr: Vaddr,
+    size: usize,
+    ctx: &Context,
+) -> Result<SyscallReturn> {
+    debug!("list_ptr = 0x{:x}, size = {}", list_ptr, size);
+
+    // The names are listed one after another, each terminated by a null byte. The names
+    // that the caller cannot access are silently skipped.
+    let mut list = Vec::new();
+    for name in inode.list_xattr()? {
+        let Ok(xattr_name) = XattrName::try_from_full_name(&name) else {
+            continue;
+        };
+        if check_namespace_access(xattr_name, ctx).is_err() {
+            continue;
+        }
+        list.extend_from_slice(name.as_bytes());
+        list.push(0);
+    }
+
+    if list.len() > XATTR_LIST_MAX_LEN {
+        return_errno_with_message!(Errno::E2BIG, "the xattr list is too long");
+    }
+    write_to_user(&list, list_ptr, size, ctx)
+}
+
+fn remove_xattr(inode: &Arc<dyn Inode>, name_ptr: Vaddr, ctx: &Context) -> Result<SyscallReturn> {
+    let name = read_name(name_ptr, ctx)?;
+    debug!("name = {:?}", name);
+
+    let name = XattrName::try_from_full_name(&name)?;
+    check_namespace_access(name, ctx)?;
+    check_write_access(inode, name, ctx)?;
+
+    inode.remove_xattr(name)?;
+    Ok(SyscallReturn::Return(0))
+}
+
+fn lookup_path(path_ptr: Vaddr, follow_symlink: bool, ctx: &Context) -> Result<Arc<dyn Inode>> {
+    let path = ctx.get_user_space().read_cstring(path_ptr, PATH_MAX)?;
+    debug!("path = {:?}", path);
+
+    let dentry = {
+        let path = path.to_string_lossy();
+        let fs_path = FsPath::try_from(path.as_ref())?;
+        let fs = ctx.process.fs().read();
+        if follow_symlink {
+            fs.lookup(&fs_path)?
+        } else {
+            fs.lookup_no_follow(&fs_path)?
+        }
+    };
+    Ok(dentry.inode().clone())
+}
+
+fn lookup_fd(fd: FileDesc, ctx: &Context) -> Result<Arc<dyn Inode>> {
+    debug!("fd = {}", fd);
+
+    let file_table = ctx.process.file_table().lock();
+    let file = file_table.get_file(fd)?;
+    let inode_handle = file
+        .downcast_ref::<InodeHandle>()
+        .ok_or(Error::with_message(Errno::EBADF, "not inode"))?;
+    Ok(inode_handle.dentry().inode().clone())
+}
+
+fn read_name(name_ptr: Vaddr, ctx: &Context) -> Result<String> {
+    // Read one more byte for the null terminator, so that a name that is too long
+    // can be told apart from one with the maximum length.
+    let name = ctx
+        .get_user_space()
+        .read_cstring(name_ptr, XATTR_NAME_MAX_LEN + 1)
+        .map_err(|err| {
+            if err.error() == Errno::E2BIG {
+                Error::with_message(Errno::ERANGE, "the xattr name is too long")
+            } else {
+                err
+            }
+        })?;
+    Ok(name.to_string_lossy().into_owned())
+}
+
+fn check_namespace_access(name: XattrName, ctx: &Context) -> Result<()> {
+    if name.namespace() == XattrNamespace::Trusted && !has_sys_admin(ctx) {
+        return_errno_with_message!(Errno::EPERM, "accessing a trusted xattr is not permitted");
+    }
+    Ok(())
+}
+
+/// Checks whether the current thread can set or remove the xattr on the inode.
+///
+/// A security xattr can only be modified with `CAP_SYS_ADMIN`. A user xattr is only
+/// supported on regular files and directories, and it can only be modified by those
+/// who can write to the inode.
+fn check_write_access(inode: &Arc<dyn Inode>, name: XattrName, ctx: &Context) -> Result<()> {
+    match name.namespace() {
+        XattrNamespace::Security if !has_sys_admin(ctx) => {
+            return_errno_with_message!(Errno::EPERM, "modifying a security xattr is not permitted");
+        }
+        XattrNamespace::User => {
+            if !is_user_xattr_supported(inode) {
+                return_errno_with_message!(
+                    Errno::EPERM,
+                    "user xattrs are only supported on regular files and directories"
+                );
+            }
+            check_inode_writable(inode, ctx)?;
+        }
+        _ => (),
+    }
+    Ok(())
+}
+
+fn is_user_xattr_supported(inode: &Arc<dyn Inode>) -> bool {
+    matches!(inode.type_(), InodeType::File | InodeType::Dir)
+}
+
+/// Checks the write permission of the inode against the file system credentials of
+/// the current thread.
+fn check_inode_writable(inode: &Arc<dyn Inode>, ctx: &Context) -> Result<()> {
+    let credentials = ctx.posix_thread.credentials();
+    let capset = credentials.effective_capset();
+    if credentials.fsuid().is_root() || capset.contains(CapSet::DAC_OVERRIDE) {
+        return Ok(());
+    }
+
+    let mode = inode.mode()?;
+    let is_writable = if inode.owner()? == credentials.fsuid() {
+        mode.contains(InodeMode::S_IWUSR)
+    } else if inode.group()? == credentials.fsgid() {
+        mode.contains(InodeMode::S_IWGRP)
+    } else {
+        mode.contains(InodeMode::S_IWOTH)
+    };
+    if !is_writable {
+        return_errno_with_message!(Errno::EACCES, "the inode is not writable");
+    }
+    Ok(())
+}
+
+fn has_sys_admin(ctx: &Context) -> bool {
+    ctx.posix_thread
+        .credentials()
+        .effective_capset()
+        .contains(CapSet::SYS_ADMIN)
+}
+
+/// Writes the bytes to the user buffer of `size` bytes.
+///
+/// If `size` is zero, nothing is written and the length of the bytes is returned, so
+/// that the caller can find out the size of the buffer that is needed.
+fn write_to_user(bytes: &[u8], ptr: Vaddr, size: usize, ctx: &Context) -> Result<SyscallReturn> {
+    if size == 0 {
+        return Ok(SyscallReturn::Return(bytes.len() as _));
+    }
+    if size < bytes.len() {
+        return_errno_with_message!(Errno::ERANGE, "the buffer is too small");
+    }
+
+    ctx.get_user_space()
+        .write_bytes(ptr, &mut VmReader::from(bytes))?;
+    Ok(SyscallReturn::Return(bytes.len() as _))
+}
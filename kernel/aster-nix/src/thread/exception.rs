@@ -5,7 +5,8 @@
 use ostd::{cpu::*, mm::VmSpace};
 
 use crate::{
-    prelude::*, process::signal::signals::fault::FaultSignal,
+    prelude::*,
+    process::{signal::signals::fault::FaultSignal, ResourceType},
     vm::page_fault_handler::PageFaultHandler,
 };
 
@@ -18,13 +19,20 @@ pub fn handle_exception(ctx: &Context, context: &UserContext) {
 
     match *exception {
         PAGE_FAULT => {
-            if handle_page_fault(root_vmar.vm_space(), trap_info).is_err() {
-                generate_fault_signal(trap_info);
+            if let Err(err) = handle_user_page_fault(root_vmar.vm_space(), trap_info) {
+                if err.error() == Errno::EIO {
+                    // The page cannot be provided by the mapped object, e.g., it is
+                    // beyond the EOF of the mapped file.
+                    let signal = FaultSignal::new_bus_error(trap_info.page_fault_addr as Vaddr);
+                    ctx.posix_thread.force_enqueue_signal(Box::new(signal));
+                } else {
+                    generate_fault_signal(ctx, trap_info);
+                }
             }
         }
         _ => {
             // We current do nothing about other exceptions
-            generate_fault_signal(trap_info);
+            generate_fault_signal(ctx, trap_info);
         }
     }
 }
@@ -34,6 +42,12 @@ pub(crate) fn handle_page_fault(
     vm_space: &VmSpace,
     trap_info: &CpuExceptionInfo,
 ) -> core::result::Result<(), ()> {
+    handle_user_page_fault(vm_space, trap_info).map_err(|_| ())
+}
+
+/// Handles the page fault occurs in the input `VmSpace`, returning the reason
+/// if the page fault cannot be handled.
+fn handle_user_page_fault(vm_space: &VmSpace, trap_info: &CpuExceptionInfo) -> Result<()> {
     const PAGE_NOT_PRESENT_ERROR_MASK: usize = 0x1 << 0;
     const WRITE_ACCESS_MASK: usize = 0x1 << 1;
     let page_fault_addr = trap_info.page_fault_addr as Vaddr;
@@ -54,25 +68,43 @@ pub(crate) fn handle_page_fault(
             vm_space as *const VmSpace
         );
 
-        if let Err(e) = root_vmar.handle_page_fault(page_fault_addr, not_present, write) {
-            error!(
+        let res = root_vmar
+            .handle_page_fault(page_fault_addr, not_present, write)
+            .or_else(|err| {
+                if !not_present {
+                    return Err(err);
+                }
+
+                // The address may be below the user stack, which grows on demand.
+                let stack_limit = current
+                    .resource_limits()
+                    .lock()
+                    .get_rlimit(ResourceType::RLIMIT_STACK)
+                    .get_cur() as usize;
+                current
+                    .vm()
+                    .grow_user_stack(page_fault_addr, stack_limit)
+                    .map_err(|_| err)?;
+                root_vmar.handle_page_fault(page_fault_addr, not_present, write)
+            });
+        if let Err(e) = res {
+            debug!(
                 "page fault handler failed: addr: 0x{:x}, err: {:?}",
                 page_fault_addr, e
             );
-            return Err(());
+            return Err(e);
         }
         Ok(())
     } else {
         // Otherwise, the page fault cannot be handled
-        Err(())
+        return_errno_with_message!(Errno::EFAULT, "the page fault cannot be handled");
     }
 }
 
-/// generate a fault signal for current process.
-fn generate_fault_signal(trap_info: &CpuExceptionInfo) {
-    let current = current!();
+/// Generates a fault signal for the current thread.
+fn generate_fault_signal(ctx: &Context, trap_info: &CpuExceptionInfo) {
     let signal = FaultSignal::new(trap_info);
-    current.enqueue_signal(signal);
+    ctx.posix_thread.force_enqueue_signal(Box::new(signal));
 }
 
 macro_rules! log_trap_common {
@@ -2,12 +2,13 @@
 
 //! Posix thread implementation
 
-use core::sync::atomic::{AtomicU32, Ordering};
+use core::sync::atomic::Ordering;
 
-use ostd::task::Task;
+use id_alloc::IdAlloc;
+use ostd::task::{Task, TaskName};
 
 use self::status::{AtomicThreadStatus, ThreadStatus};
-use crate::prelude::*;
+use crate::{prelude::*, sched::SchedAttr};
 
 pub mod exception;
 pub mod kernel_thread;
@@ -18,7 +19,12 @@ pub mod work_queue;
 
 pub type Tid = u32;
 
-static TID_ALLOCATOR: AtomicU32 = AtomicU32::new(0);
+/// The maximum number of tids, which is `PID_MAX_LIMIT` in Linux.
+const MAX_NR_TIDS: usize = 4 * 1024 * 1024;
+
+lazy_static! {
+    static ref TID_ALLOCATOR: SpinLock<TidAllocator> = SpinLock::new(TidAllocator::new());
+}
 
 /// A thread is a wrapper on top of task.
 pub struct Thread {
@@ -32,6 +38,8 @@ pub struct Thread {
 
     // mutable part
     status: AtomicThreadStatus,
+    /// Scheduling attributes
+    sched_attr: SchedAttr,
 }
 
 impl Thread {
@@ -47,6 +55,7 @@ impl Thread {
             task,
             data: Box::new(data),
             status: AtomicThreadStatus::new(status),
+            sched_attr: SchedAttr::default(),
         }
     }
 
@@ -61,10 +70,15 @@ impl Thread {
             .upgrade()
     }
 
-    pub(in crate::thread) fn task(&self) -> &Arc<Task> {
+    pub(crate) fn task(&self) -> &Arc<Task> {
         &self.task
     }
 
+    /// Returns the name of the underlying task.
+    pub fn task_name(&self) -> TaskName {
+        self.task.name()
+    }
+
     /// Runs this thread at once.
     pub fn run(&self) {
         self.set_status(ThreadStatus::Running);
@@ -98,6 +112,11 @@ impl Thread {
         self.tid
     }
 
+    /// Returns the scheduling attributes.
+    pub(crate) fn sched_attr(&self) -> &SchedAttr {
+        &self.sched_attr
+    }
+
     /// Returns the associated data.
     ///
     /// The return type must be borrowed box, otherwise the `downcast_ref` will fail.
@@ -107,7 +126,66 @@ impl Thread {
     }
 }
 
-/// Allocates a new tid for the new thread
-pub fn allocate_tid() -> Tid {
-    TID_ALLOCATOR.fetch_add(1, Ordering::SeqCst)
+impl Drop for Thread {
+    fn drop(&mut self) {
+        release_tid(self.tid);
+    }
+}
+
+/// Allocates a new tid for the new thread.
+///
+/// The lowest unused tid is allocated. The tid is released when the thread is dropped.
+pub fn allocate_tid() -> Result<Tid> {
+    TID_ALLOCATOR
+        .lock_irq_disabled()
+        .alloc()
+        .ok_or_else(|| Error::with_message(Errno::EAGAIN, "no tids are available"))
+}
+
+/// Holds `tid` so that it is not reused until [`release_tid`] is called.
+///
+/// The tids of the exited processes are held as the ids of the process groups and the
+/// sessions that remain.
+pub(crate) fn hold_tid(tid: Tid) {
+    TID_ALLOCATOR.lock_irq_disabled().hold(tid);
+}
+
+/// Releases `tid`, which is allocated by [`allocate_tid`] or held by [`hold_tid`].
+pub(crate) fn release_tid(tid: Tid) {
+    TID_ALLOCATOR.lock_irq_disabled().release(tid);
+}
+
+struct TidAllocator {
+    ids: IdAlloc,
+    /// The number of the holders of each allocated tid.
+    nr_holders: BTreeMap<Tid, usize>,
+}
+
+impl TidAllocator {
+    fn new() -> Self {
+        Self {
+            ids: IdAlloc::with_capacity(0),
+            nr_holders: BTreeMap::new(),
+        }
+    }
+
+    fn alloc(&mut self) -> Option<Tid> {
+        let tid = self.ids.alloc_or_grow(MAX_NR_TIDS)? as Tid;
+        self.nr_holders.insert(tid, 1);
+        Some(tid)
+    }
+
+    fn hold(&mut self, tid: Tid) {
+        let nr_holders = self.nr_holders.get_mut(&tid).unwrap();
+        *nr_holders += 1;
+    }
+
+    fn release(&mut self, tid: Tid) {
+        let nr_holders = self.nr_holders.get_mut(&tid).unwrap();
+        *nr_holders -= 1;
+        if *nr_holders == 0 {
+            self.nr_holders.remove(&tid);
+            self.ids.free(tid as usize);
+        }
+    }
 }
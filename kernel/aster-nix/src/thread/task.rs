@@ -64,21 +64,24 @@ pub fn create_new_user_task(user_space: Arc<UserSpace>, thread_ref: Weak<Thread>
             let return_reason = user_mode.execute(has_kernel_event_fn);
             let user_ctx = user_mode.context_mut();
             // handle user event:
-            match return_reason {
-                ReturnReason::UserException => handle_exception(&ctx, user_ctx),
+            let interrupted_syscall = match return_reason {
+                ReturnReason::UserException => {
+                    handle_exception(&ctx, user_ctx);
+                    None
+                }
                 ReturnReason::UserSyscall => handle_syscall(&ctx, user_ctx),
-                ReturnReason::KernelEvent => {}
+                ReturnReason::KernelEvent => None,
             };
 
             if current_thread.status().is_exited() {
                 break;
             }
-            handle_pending_signal(user_ctx, &current_thread).unwrap();
+            handle_pending_signal(user_ctx, &current_thread, interrupted_syscall).unwrap();
             // If current is suspended, wait for a signal to wake up self
             while current_thread.status().is_stopped() {
                 Thread::yield_now();
                 debug!("{} is suspended.", current_thread.tid());
-                handle_pending_signal(user_ctx, &current_thread).unwrap();
+                handle_pending_signal(user_ctx, &current_thread, None).unwrap();
             }
             if current_thread.status().is_exited() {
                 debug!("exit due to signal");
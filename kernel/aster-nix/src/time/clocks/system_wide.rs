@@ -8,9 +8,7 @@ use ostd::{arch::timer::Jiffies, cpu_local, sync::SpinLock};
 use paste::paste;
 use spin::Once;
 
-use crate::time::{
-    self, system_time::START_TIME_AS_DURATION, timer::TimerManager, Clock, SystemTime,
-};
+use crate::time::{self, system_time, timer::TimerManager, Clock, SystemTime};
 
 /// The Clock that reads the jiffies, and turn the counter into `Duration`.
 pub struct JiffiesClock {
@@ -159,7 +157,11 @@ impl Clock for RealTimeCoarseClock {
 
 impl Clock for MonotonicCoarseClock {
     fn read_time(&self) -> Duration {
-        RealTimeCoarseClock::get().read_time() - *START_TIME_AS_DURATION.get().unwrap()
+        // The coarse real time is updated at the next tick after the system time is set, so
+        // it may be earlier than the start time for a while.
+        RealTimeCoarseClock::get()
+            .read_time()
+            .saturating_sub(system_time::start_time_as_duration())
     }
 }
 
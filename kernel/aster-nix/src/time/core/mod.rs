@@ -3,7 +3,7 @@
 use core::time::Duration;
 
 use aster_time::NANOS_PER_SECOND;
-use ostd::arch::timer::TIMER_FREQ;
+use ostd::arch::timer::timer_freq;
 
 pub mod timer;
 
@@ -21,6 +21,6 @@ pub trait Clock: Send + Sync {
     where
         Self: Sized,
     {
-        NANOS_PER_SECOND as u64 / TIMER_FREQ
+        NANOS_PER_SECOND as u64 / timer_freq()
     }
 }
@@ -5,6 +5,7 @@
 pub use core::{timer, Clock};
 
 use ::core::time::Duration;
+pub(crate) use system_time::start_time_as_duration;
 pub use system_time::{SystemTime, START_TIME};
 pub use timer::{Timer, TimerManager};
 
@@ -60,7 +61,7 @@ impl TryFrom<timespec_t> for Duration {
             return_errno_with_message!(Errno::EINVAL, "timesepc_t cannot be negative");
         }
 
-        if value.nsec > 1_000_000_000 {
+        if value.nsec >= 1_000_000_000 {
             // The value of nanoseconds cannot exceed 10^9,
             // otherwise the value for seconds should be set.
             return_errno_with_message!(Errno::EINVAL, "nsec is not normalized");
@@ -1,6 +1,9 @@
 // SPDX-License-Identifier: MPL-2.0
 
-use core::time::Duration;
+use core::{
+    sync::atomic::{AtomicI64, Ordering},
+    time::Duration,
+};
 
 use aster_time::{read_monotonic_time, read_start_time};
 use spin::Once;
@@ -13,7 +16,13 @@ use crate::prelude::*;
 pub struct SystemTime(PrimitiveDateTime);
 
 pub static START_TIME: Once<SystemTime> = Once::new();
-pub(super) static START_TIME_AS_DURATION: Once<Duration> = Once::new();
+static START_TIME_AS_DURATION: Once<Duration> = Once::new();
+
+/// The offset of the system time set by the user, in nanoseconds.
+///
+/// Without an offset, the system time is counted from `START_TIME`, which is read from the
+/// RTC at boot.
+static REALTIME_OFFSET_NS: AtomicI64 = AtomicI64::new(0);
 
 pub(super) fn init() {
     let start_time = convert_system_time(read_start_time()).unwrap();
@@ -40,7 +49,31 @@ impl SystemTime {
 
     /// Returns the current system time
     pub fn now() -> Self {
+        let offset = time::Duration::nanoseconds(REALTIME_OFFSET_NS.load(Ordering::Relaxed));
         // The get real time result should always be valid
+        let now = Self::now_without_offset().0.checked_add(offset).unwrap();
+        SystemTime(now)
+    }
+
+    /// Sets the current system time, which is the duration since the unix epoch.
+    ///
+    /// The monotonic time is not affected.
+    pub fn set_now(since_epoch: Duration) -> Result<()> {
+        let Some(now) = SystemTime::UNIX_EPOCH.checked_add(since_epoch) else {
+            return_errno_with_message!(Errno::EINVAL, "the system time is out of range");
+        };
+        let offset = now.0 - Self::now_without_offset().0;
+        let Ok(offset_ns) = i64::try_from(offset.whole_nanoseconds()) else {
+            return_errno_with_message!(Errno::EINVAL, "the system time is out of range");
+        };
+
+        REALTIME_OFFSET_NS.store(offset_ns, Ordering::Relaxed);
+        // The user space reads the realtime from the VDSO data without a syscall.
+        crate::vdso::update_vdso_realtime();
+        Ok(())
+    }
+
+    fn now_without_offset() -> Self {
         START_TIME
             .get()
             .unwrap()
@@ -81,6 +114,19 @@ impl SystemTime {
     }
 }
 
+/// Returns the system time at boot as the duration since the unix epoch.
+///
+/// The result changes if the system time is set.
+pub(crate) fn start_time_as_duration() -> Duration {
+    let start_time = *START_TIME_AS_DURATION.get().unwrap();
+    let offset_ns = REALTIME_OFFSET_NS.load(Ordering::Relaxed);
+    if offset_ns >= 0 {
+        start_time + Duration::from_nanos(offset_ns as u64)
+    } else {
+        start_time.saturating_sub(Duration::from_nanos(offset_ns.unsigned_abs()))
+    }
+}
+
 /// convert ostd::time::Time to System time
 fn convert_system_time(system_time: aster_time::SystemTime) -> Result<SystemTime> {
     let month = match Month::try_from(system_time.month) {
@@ -0,0 +1,194 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use core::ops::Range;
+
+use crate::prelude::*;
+
+/// An interval is associated with a range of values (`K`).
+pub trait Interval<K> {
+    /// Returns the range of the interval.
+    fn range(&self) -> Range<K>;
+}
+
+/// A map of non-overlapping intervals, which supports finding the interval
+/// that contains a point or the intervals that overlap with a range in
+/// `O(log n)` time (plus the number of the returned intervals).
+///
+/// The intervals are indexed by their start points. Since they never overlap,
+/// the interval that contains a point can only be the last one starting at or
+/// before the point, so no augmented tree is needed.
+///
+/// The range of an interval is obtained from the interval itself (see
+/// [`Interval::range`]) when querying. So an interval may be shrunk or grown
+/// in place, as long as its start point is unchanged and it does not overlap
+/// with the others. An interval whose start point changes must be removed and
+/// inserted again.
+pub struct IntervalMap<K, V> {
+    map: BTreeMap<K, V>,
+}
+
+impl<K: Ord + Copy, V: Interval<K>> IntervalMap<K, V> {
+    /// Creates an empty map.
+    pub const fn new() -> Self {
+        Self {
+            map: BTreeMap::new(),
+        }
+    }
+
+    /// Inserts an interval and returns the old interval with the same start
+    /// point, if any.
+    ///
+    /// The caller must ensure that the interval does not overlap with the
+    /// other intervals in the map.
+    pub fn insert(&mut self, interval: V) -> Option<V> {
+        let range = interval.range();
+        debug_assert!(self
+            .overlapping(&range)
+            .all(|other| other.range().start == range.start));
+
+        self.map.insert(range.start, interval)
+    }
+
+    /// Removes the interval that starts at `start` and returns it, if any.
+    pub fn remove(&mut self, start: &K) -> Option<V> {
+        self.map.remove(start)
+    }
+
+    /// Returns the interval that starts at `start`, if any.
+    pub fn get(&self, start: &K) -> Option<&V> {
+        self.map.get(start)
+    }
+
+    /// Finds the interval that contains `point`, if any.
+    pub fn find(&self, point: &K) -> Option<&V> {
+        let (_, interval) = self.map.range(..=*point).next_back()?;
+        (interval.range().end > *point).then_some(interval)
+    }
+
+    /// Returns the intervals that overlap with `range`, in ascending order.
+    pub fn overlapping(&self, range: &Range<K>) -> impl Iterator<Item = &V> {
+        let start = range.start;
+        // An empty range overlaps with nothing.
+        let end = range.end.max(start);
+
+        // Among the intervals starting before `range`, only the last one may
+        // overlap with it.
+        let prev = self
+            .map
+            .range(..start)
+            .next_back()
+            .map(|(_, interval)| interval)
+            .filter(|interval| start < end && interval.range().end > start);
+        let rest = self.map.range(start..end).map(|(_, interval)| interval);
+
+        prev.into_iter().chain(rest)
+    }
+
+    /// Returns an iterator over the intervals, in ascending order.
+    pub fn iter(&self) -> impl Iterator<Item = &V> {
+        self.map.values()
+    }
+
+    /// Retains only the intervals for which `f` returns `true`.
+    pub fn retain(&mut self, mut f: impl FnMut(&V) -> bool) {
+        self.map.retain(|_, interval| f(interval));
+    }
+
+    /// Removes all the intervals.
+    pub fn clear(&mut self) {
+        self.map.clear();
+    }
+
+    /// Returns the number of the intervals.
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Returns whether the map contains no intervals.
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+}
+
+impl<K: Ord + Copy, V: Interval<K>> Default for IntervalMap<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(ktest)]
+mod test {
+    use ostd::prelude::*;
+
+    use super::*;
+
+    #[derive(Debug, PartialEq, Eq)]
+    struct Vma(Range<usize>);
+
+    impl Interval<usize> for Vma {
+        fn range(&self) -> Range<usize> {
+            self.0.clone()
+        }
+    }
+
+    fn new_map() -> IntervalMap<usize, Vma> {
+        let mut map = IntervalMap::new();
+        // The first two are adjacent.
+        for range in [0x1000..0x3000, 0x3000..0x4000, 0x8000..0x10000] {
+            assert!(map.insert(Vma(range)).is_none());
+        }
+        map
+    }
+
+    #[ktest]
+    fn find() {
+        let map = new_map();
+        assert_eq!(map.len(), 3);
+
+        let find = |point| map.find(&point).map(|vma| vma.0.clone());
+        assert_eq!(find(0x0), None);
+        assert_eq!(find(0x1000), Some(0x1000..0x3000));
+        assert_eq!(find(0x2fff), Some(0x1000..0x3000));
+        assert_eq!(find(0x3000), Some(0x3000..0x4000));
+        assert_eq!(find(0x3fff), Some(0x3000..0x4000));
+        assert_eq!(find(0x4000), None);
+        assert_eq!(find(0x7fff), None);
+        assert_eq!(find(0x8000), Some(0x8000..0x10000));
+        assert_eq!(find(0x10000), None);
+    }
+
+    #[ktest]
+    fn overlapping() {
+        let map = new_map();
+
+        let overlapping = |range| {
+            map.overlapping(&range)
+                .map(|vma| vma.0.start)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(overlapping(0x0..0x1000), vec![]);
+        assert_eq!(overlapping(0x0..0x1001), vec![0x1000]);
+        assert_eq!(overlapping(0x2000..0x3000), vec![0x1000]);
+        assert_eq!(overlapping(0x2fff..0x3001), vec![0x1000, 0x3000]);
+        assert_eq!(overlapping(0x4000..0x8000), vec![]);
+        assert_eq!(overlapping(0x3fff..0x8001), vec![0x3000, 0x8000]);
+        assert_eq!(overlapping(0x0..0x20000), vec![0x1000, 0x3000, 0x8000]);
+        assert_eq!(overlapping(0x2000..0x2000), vec![]);
+    }
+
+    #[ktest]
+    fn remove() {
+        let mut map = new_map();
+
+        assert_eq!(map.remove(&0x2000), None);
+        assert_eq!(map.remove(&0x1000), Some(Vma(0x1000..0x3000)));
+        assert_eq!(map.find(&0x2000), None);
+        assert_eq!(map.get(&0x3000), Some(&Vma(0x3000..0x4000)));
+
+        map.retain(|vma| vma.0.start != 0x3000);
+        assert_eq!(map.iter().collect::<Vec<_>>(), vec![&Vma(0x8000..0x10000)]);
+
+        map.clear();
+        assert!(map.is_empty());
+    }
+}
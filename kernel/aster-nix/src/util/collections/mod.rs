@@ -0,0 +1,9 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Specialized collections.
+
+mod interval_map;
+mod sharded_map;
+
+pub use interval_map::{Interval, IntervalMap};
+pub use sharded_map::{Entry, EntryRef, ShardedMap};
@@ -0,0 +1,307 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use core::{
+    borrow::Borrow,
+    hash::{BuildHasher, Hash, Hasher},
+};
+
+use hashbrown::HashMap;
+use ostd::sync::RwLockWriteGuard;
+
+use crate::prelude::*;
+
+/// A concurrent hash map whose keys are partitioned into `NR_SHARDS` shards.
+///
+/// Each shard is protected by its own lock, so the operations on the keys in
+/// different shards do not contend with each other.
+///
+/// Since the values cannot outlive the lock of the shard, the lookup methods
+/// return clones of the values. Use [`Self::entry`] to inspect and modify an
+/// entry in place.
+pub struct ShardedMap<K, V, const NR_SHARDS: usize = 16> {
+    shards: [RwLock<HashMap<K, V, FnvBuildHasher>>; NR_SHARDS],
+}
+
+impl<K: Hash + Eq, V, const NR_SHARDS: usize> ShardedMap<K, V, NR_SHARDS> {
+    /// Creates an empty map.
+    pub const fn new() -> Self {
+        Self {
+            shards: [const { RwLock::new(HashMap::with_hasher(FnvBuildHasher)) }; NR_SHARDS],
+        }
+    }
+
+    /// Returns a clone of the value of the key.
+    pub fn get<Q>(&self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+        V: Clone,
+    {
+        self.shard_of(key).read().get(key).cloned()
+    }
+
+    /// Returns whether the map contains the key.
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.shard_of(key).read().contains_key(key)
+    }
+
+    /// Inserts a key-value pair and returns the old value of the key, if any.
+    pub fn insert(&self, key: K, value: V) -> Option<V> {
+        self.shard_of(&key).write().insert(key, value)
+    }
+
+    /// Removes the key and returns its value, if any.
+    pub fn remove<Q>(&self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.shard_of(key).write().remove(key)
+    }
+
+    /// Returns the entry of the key.
+    ///
+    /// The shard of the key is locked until the entry is dropped, so the entry
+    /// can be inspected and modified atomically. Do not access the map while
+    /// holding the entry, or it may deadlock.
+    pub fn entry(&self, key: K) -> Entry<'_, K, V> {
+        let shard = self.shard_of(&key).write();
+        Entry { shard, key }
+    }
+
+    /// Calls `f` with each key-value pair in the map.
+    ///
+    /// The shards are locked one after another. So each pair is either visited
+    /// with its latest value or not visited at all, but the pairs in different
+    /// shards may be visited at different points in time, i.e., the visited
+    /// pairs may not form a snapshot of the whole map.
+    pub fn for_each(&self, mut f: impl FnMut(&K, &V)) {
+        for shard in self.shards.iter() {
+            shard.read().iter().for_each(|(key, value)| f(key, value));
+        }
+    }
+
+    /// Returns the number of the key-value pairs.
+    ///
+    /// Like [`Self::for_each`], the result may be inaccurate if the map is
+    /// modified concurrently.
+    pub fn len(&self) -> usize {
+        self.shards.iter().map(|shard| shard.read().len()).sum()
+    }
+
+    /// Returns whether the map is empty.
+    pub fn is_empty(&self) -> bool {
+        self.shards.iter().all(|shard| shard.read().is_empty())
+    }
+
+    fn shard_of<Q: Hash + ?Sized>(&self, key: &Q) -> &RwLock<HashMap<K, V, FnvBuildHasher>> {
+        // The map of each shard uses the same hasher, so the low bits of the hash are
+        // not used to pick the shard. Otherwise, the keys in a shard would share the
+        // same low bits and collide in the map of the shard.
+        let hash = FnvBuildHasher.hash_one(key);
+        &self.shards[((hash >> 32) % NR_SHARDS as u64) as usize]
+    }
+}
+
+impl<K: Hash + Eq, V, const NR_SHARDS: usize> Default for ShardedMap<K, V, NR_SHARDS> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// An entry of a [`ShardedMap`], which may be vacant or occupied.
+///
+/// The shard of the entry is locked while the entry is alive.
+pub struct Entry<'a, K, V> {
+    shard: RwLockWriteGuard<'a, HashMap<K, V, FnvBuildHasher>>,
+    key: K,
+}
+
+impl<'a, K: Hash + Eq, V> Entry<'a, K, V> {
+    /// Returns the key used to look up the entry.
+    pub fn key(&self) -> &K {
+        &self.key
+    }
+
+    /// Returns the value of the entry, if it is occupied.
+    pub fn get(&self) -> Option<&V> {
+        self.shard.get(&self.key)
+    }
+
+    /// Returns the stored key and the value of the entry, if it is occupied.
+    ///
+    /// The stored key is equal to [`Self::key`], but it may be a different object.
+    pub fn get_key_value(&self) -> Option<(&K, &V)> {
+        self.shard.get_key_value(&self.key)
+    }
+
+    /// Returns the mutable value of the entry, if it is occupied.
+    pub fn get_mut(&mut self) -> Option<&mut V> {
+        self.shard.get_mut(&self.key)
+    }
+
+    /// Returns the mutable value of the entry, inserting the value returned by
+    /// `f` if the entry is vacant.
+    pub fn or_insert_with(self, f: impl FnOnce() -> V) -> EntryRef<'a, K, V>
+    where
+        K: Clone,
+    {
+        let Self { mut shard, key } = self;
+        if !shard.contains_key(&key) {
+            shard.insert(key.clone(), f());
+        }
+        EntryRef { shard, key }
+    }
+
+    /// Sets the value of the entry and returns the old value, if any.
+    ///
+    /// The stored key is replaced with [`Self::key`].
+    pub fn insert(mut self, value: V) -> Option<V> {
+        let old_value = self.shard.remove(&self.key);
+        self.shard.insert(self.key, value);
+        old_value
+    }
+
+    /// Removes the entry and returns its value, if it is occupied.
+    pub fn remove(mut self) -> Option<V> {
+        self.shard.remove(&self.key)
+    }
+}
+
+/// An occupied entry of a [`ShardedMap`], returned by [`Entry::or_insert_with`].
+///
+/// The shard of the entry is locked while the entry is alive.
+pub struct EntryRef<'a, K, V> {
+    shard: RwLockWriteGuard<'a, HashMap<K, V, FnvBuildHasher>>,
+    key: K,
+}
+
+impl<'a, K: Hash + Eq, V> core::ops::Deref for EntryRef<'a, K, V> {
+    type Target = V;
+
+    fn deref(&self) -> &V {
+        self.shard.get(&self.key).unwrap()
+    }
+}
+
+impl<'a, K: Hash + Eq, V> core::ops::DerefMut for EntryRef<'a, K, V> {
+    fn deref_mut(&mut self) -> &mut V {
+        self.shard.get_mut(&self.key).unwrap()
+    }
+}
+
+/// The builder of [`FnvHasher`].
+///
+/// Unlike the default hasher of `hashbrown`, it can be built in a `const`
+/// context, so the map can be placed in a `static`.
+#[derive(Clone, Copy, Default)]
+struct FnvBuildHasher;
+
+impl BuildHasher for FnvBuildHasher {
+    type Hasher = FnvHasher;
+
+    fn build_hasher(&self) -> FnvHasher {
+        FnvHasher(FnvHasher::OFFSET_BASIS)
+    }
+}
+
+/// The 64-bit FNV-1a hasher.
+struct FnvHasher(u64);
+
+impl FnvHasher {
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0100_0000_01b3;
+}
+
+impl Hasher for FnvHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 = (self.0 ^ *byte as u64).wrapping_mul(Self::PRIME);
+        }
+    }
+}
+
+#[cfg(ktest)]
+mod test {
+    use core::sync::atomic::{AtomicUsize, Ordering};
+
+    use ostd::{
+        cpu::num_cpus,
+        prelude::*,
+        task::{Task, TaskOptions},
+    };
+
+    use super::*;
+
+    #[ktest]
+    fn entry() {
+        let map = ShardedMap::<u32, u32>::new();
+        assert!(map.is_empty());
+
+        assert_eq!(map.insert(1, 10), None);
+        assert_eq!(map.insert(1, 11), Some(10));
+        assert_eq!(map.get(&1), Some(11));
+
+        *map.entry(2).or_insert_with(|| 20) += 1;
+        *map.entry(2).or_insert_with(|| 0) += 1;
+        assert_eq!(map.get(&2), Some(22));
+
+        let entry = map.entry(3);
+        assert!(entry.get().is_none());
+        assert_eq!(entry.insert(30), None);
+        assert_eq!(map.entry(3).remove(), Some(30));
+
+        assert_eq!(map.len(), 2);
+        assert_eq!(map.remove(&1), Some(11));
+        assert!(!map.contains_key(&1));
+    }
+
+    #[ktest]
+    fn concurrent_insert() {
+        const NR_KEYS_PER_TASK: usize = 1000;
+
+        static MAP: ShardedMap<usize, usize> = ShardedMap::new();
+
+        let nr_tasks = (num_cpus() as usize).max(2) * 2;
+        let nr_done = Arc::new(AtomicUsize::new(0));
+        for i in 0..nr_tasks {
+            let nr_done = nr_done.clone();
+            TaskOptions::new(move || {
+                // Each task inserts a disjoint range of keys.
+                for key in i * NR_KEYS_PER_TASK..(i + 1) * NR_KEYS_PER_TASK {
+                    assert_eq!(MAP.insert(key, key * 2), None);
+                    if key % 100 == 0 {
+                        Task::yield_now();
+                    }
+                }
+                nr_done.fetch_add(1, Ordering::Release);
+            })
+            .data(())
+            .spawn()
+            .unwrap();
+        }
+        while nr_done.load(Ordering::Acquire) < nr_tasks {
+            Task::yield_now();
+        }
+
+        assert_eq!(MAP.len(), nr_tasks * NR_KEYS_PER_TASK);
+        for key in 0..nr_tasks * NR_KEYS_PER_TASK {
+            assert_eq!(MAP.get(&key), Some(key * 2));
+        }
+
+        let mut nr_visited = 0;
+        MAP.for_each(|key, value| {
+            assert_eq!(*value, *key * 2);
+            nr_visited += 1;
+        });
+        assert_eq!(nr_visited, nr_tasks * NR_KEYS_PER_TASK);
+    }
+}
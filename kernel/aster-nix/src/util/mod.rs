@@ -1,7 +1,28 @@
 // SPDX-License-Identifier: MPL-2.0
 
+pub mod collections;
 mod iovec;
 pub mod net;
 pub mod random;
 
 pub use iovec::{copy_iovs_from_user, IoVec};
+use ostd::mm::UserSpace;
+
+use crate::prelude::*;
+
+/// Reads all the remaining data of a user space reader into a new buffer.
+///
+/// If a page fault occurs, the data read so far is returned. If nothing can be read,
+/// `EFAULT` is returned.
+pub fn read_all_from_user(reader: &mut VmReader<'_, UserSpace>) -> Result<Vec<u8>> {
+    let mut buf = vec![0u8; reader.remain()];
+    let read_len = match reader.read_fallible(&mut VmWriter::from(buf.as_mut_slice())) {
+        Ok(read_len) => read_len,
+        Err((_, 0)) if !buf.is_empty() => {
+            return_errno_with_message!(Errno::EFAULT, "the user buffer is not accessible")
+        }
+        Err((_, read_len)) => read_len,
+    };
+    buf.truncate(read_len);
+    Ok(buf)
+}
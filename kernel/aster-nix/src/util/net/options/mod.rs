@@ -48,6 +48,10 @@
 //! impl_raw_socket_option!(TcpNodeley);
 //! ```
 //!
+//! Finally, the option should be created in `new_raw_socket_option` according to its level and name,
+//! e.g., in `new_tcp_option` for the TCP level. An option that is unknown at this point is rejected
+//! with `ENOPROTOOPT`.
+//!
 //! At the syscall level, the interface is unified for all options and does not need to be modified.
 //!
 
@@ -134,7 +138,10 @@ pub fn new_raw_socket_option(
     match level {
         CSocketOptionLevel::SOL_SOCKET => new_socket_option(name),
         CSocketOptionLevel::SOL_TCP => new_tcp_option(name),
-        _ => todo!(),
+        _ => return_errno_with_message!(
+            Errno::ENOPROTOOPT,
+            "the socket option level is not supported"
+        ),
     }
 }
 
@@ -4,7 +4,8 @@ use super::RawSocketOption;
 use crate::{
     impl_raw_sock_option_get_only, impl_raw_socket_option,
     net::socket::options::{
-        Error, KeepAlive, Linger, RecvBuf, ReuseAddr, ReusePort, SendBuf, SocketOption,
+        Error, KeepAlive, Linger, PassCred, RecvBuf, RecvTimeout, ReuseAddr, ReusePort, SendBuf,
+        SendLowat, SendTimeout, SocketOption,
     },
     prelude::*,
 };
@@ -34,12 +35,18 @@ enum CSocketOptionName {
     LINGER = 13,
     BSDCOMPAT = 14,
     REUSEPORT = 15,
+    PASSCRED = 16,
+    SNDLOWAT = 19,
+    RCVTIMEO_OLD = 20,
+    SNDTIMEO_OLD = 21,
     RCVTIMEO_NEW = 66,
     SNDTIMEO_NEW = 67,
 }
 
 pub fn new_socket_option(name: i32) -> Result<Box<dyn RawSocketOption>> {
-    let name = CSocketOptionName::try_from(name)?;
+    let name = CSocketOptionName::try_from(name).map_err(|_| {
+        crate::error::Error::with_message(Errno::ENOPROTOOPT, "the socket option name is unknown")
+    })?;
     match name {
         CSocketOptionName::SNDBUF => Ok(Box::new(SendBuf::new())),
         CSocketOptionName::RCVBUF => Ok(Box::new(RecvBuf::new())),
@@ -48,7 +55,16 @@ pub fn new_socket_option(name: i32) -> Result<Box<dyn RawSocketOption>> {
         CSocketOptionName::REUSEPORT => Ok(Box::new(ReusePort::new())),
         CSocketOptionName::LINGER => Ok(Box::new(Linger::new())),
         CSocketOptionName::KEEPALIVE => Ok(Box::new(KeepAlive::new())),
-        _ => todo!(),
+        CSocketOptionName::SNDLOWAT => Ok(Box::new(SendLowat::new())),
+        CSocketOptionName::PASSCRED => Ok(Box::new(PassCred::new())),
+        // On 64-bit platforms, the old and new options share the same `struct timeval`.
+        CSocketOptionName::RCVTIMEO_OLD | CSocketOptionName::RCVTIMEO_NEW => {
+            Ok(Box::new(RecvTimeout::new()))
+        }
+        CSocketOptionName::SNDTIMEO_OLD | CSocketOptionName::SNDTIMEO_NEW => {
+            Ok(Box::new(SendTimeout::new()))
+        }
+        _ => return_errno_with_message!(Errno::ENOPROTOOPT, "the socket option is not supported"),
     }
 }
 
@@ -59,3 +75,7 @@ impl_raw_sock_option_get_only!(Error);
 impl_raw_socket_option!(ReusePort);
 impl_raw_socket_option!(Linger);
 impl_raw_socket_option!(KeepAlive);
+impl_raw_socket_option!(SendLowat);
+impl_raw_socket_option!(SendTimeout);
+impl_raw_socket_option!(RecvTimeout);
+impl_raw_socket_option!(PassCred);
@@ -3,7 +3,7 @@
 use super::RawSocketOption;
 use crate::{
     impl_raw_socket_option,
-    net::socket::ip::stream::options::{Congestion, MaxSegment, NoDelay, WindowClamp},
+    net::socket::ip::stream::options::{Congestion, Cork, MaxSegment, NoDelay, WindowClamp},
     prelude::*,
     util::net::options::SocketOption,
 };
@@ -26,17 +26,20 @@ pub enum CTcpOptionName {
 }
 
 pub fn new_tcp_option(name: i32) -> Result<Box<dyn RawSocketOption>> {
-    let name = CTcpOptionName::try_from(name)?;
+    let name = CTcpOptionName::try_from(name)
+        .map_err(|_| Error::with_message(Errno::ENOPROTOOPT, "the TCP option name is unknown"))?;
     match name {
         CTcpOptionName::NODELAY => Ok(Box::new(NoDelay::new())),
+        CTcpOptionName::CORK => Ok(Box::new(Cork::new())),
         CTcpOptionName::CONGESTION => Ok(Box::new(Congestion::new())),
         CTcpOptionName::MAXSEG => Ok(Box::new(MaxSegment::new())),
         CTcpOptionName::WINDOW_CLAMP => Ok(Box::new(WindowClamp::new())),
-        _ => todo!(),
+        _ => return_errno_with_message!(Errno::ENOPROTOOPT, "the TCP option is not supported"),
     }
 }
 
 impl_raw_socket_option!(NoDelay);
+impl_raw_socket_option!(Cork);
 impl_raw_socket_option!(Congestion);
 impl_raw_socket_option!(MaxSegment);
 impl_raw_socket_option!(WindowClamp);
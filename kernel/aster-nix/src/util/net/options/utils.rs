@@ -5,6 +5,7 @@ use core::time::Duration;
 use crate::{
     net::socket::{ip::stream::CongestionControl, LingerOption},
     prelude::*,
+    time::timeval_t,
 };
 
 /// Create an object by reading its C counterpart from the user space.
@@ -137,12 +138,50 @@ impl WriteToUser for LingerOption {
     }
 }
 
+impl ReadFromUser for Duration {
+    fn read_from_user(addr: Vaddr, max_len: u32) -> Result<Self> {
+        if (max_len as usize) < core::mem::size_of::<timeval_t>() {
+            return_errno_with_message!(Errno::EINVAL, "max_len is too short");
+        }
+
+        let timeval = CurrentUserSpace::get().read_val::<timeval_t>(addr)?;
+        if timeval.usec < 0 || timeval.usec >= 1_000_000 {
+            return_errno_with_message!(Errno::EDOM, "the microseconds are out of range");
+        }
+        // Linux treats a negative timeout as an immediate timeout, which is not supported here.
+        if timeval.sec < 0 {
+            return_errno_with_message!(Errno::EINVAL, "the timeout is negative");
+        }
+
+        Ok(Duration::from(timeval))
+    }
+}
+
+impl WriteToUser for Duration {
+    fn write_to_user(&self, addr: Vaddr, max_len: u32) -> Result<usize> {
+        let write_len = core::mem::size_of::<timeval_t>();
+
+        if (max_len as usize) < write_len {
+            return_errno_with_message!(Errno::EINVAL, "max_len is too short");
+        }
+
+        let timeval = timeval_t::from(*self);
+        CurrentUserSpace::get().write_val(addr, &timeval)?;
+        Ok(write_len)
+    }
+}
+
 impl ReadFromUser for CongestionControl {
     fn read_from_user(addr: Vaddr, max_len: u32) -> Result<Self> {
-        let mut bytes = vec![0; max_len as usize];
+        // The maximum length of a congestion control name, including the null terminator.
+        const TCP_CA_NAME_MAX: usize = 16;
+
+        let mut bytes = vec![0; (max_len as usize).min(TCP_CA_NAME_MAX - 1)];
         CurrentUserSpace::get().read_bytes(addr, &mut VmWriter::from(bytes.as_mut_slice()))?;
-        let name = String::from_utf8(bytes).unwrap();
-        CongestionControl::new(&name)
+        let len = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+        let name = core::str::from_utf8(&bytes[..len])
+            .map_err(|_| Error::with_message(Errno::EINVAL, "the congestion name is invalid"))?;
+        CongestionControl::new(name)
     }
 }
 
@@ -1,8 +1,13 @@
 // SPDX-License-Identifier: MPL-2.0
 
-use super::read_socket_addr_from_user;
+use core::mem::size_of;
+
+use align_ext::AlignExt;
+
+use super::{read_socket_addr_from_user, CSocketOptionLevel};
 use crate::{
-    net::socket::SocketAddr,
+    fs::file_table::{FdFlags, FileDesc},
+    net::socket::{ControlMessage, SocketAddr, UnixCredentials, UnixRights},
     prelude::*,
     util::{copy_iovs_from_user, net::write_socket_addr_with_max_len, IoVec},
 };
@@ -88,7 +93,7 @@ pub struct CUserMsgHdr {
     /// Ancillary data
     pub msg_control: Vaddr,
     /// Ancillary data buffer length
-    pub msg_controllen: u32,
+    pub msg_controllen: usize,
     /// Flags on received message
     pub msg_flags: u32,
 }
@@ -115,4 +120,257 @@ impl CUserMsgHdr {
     pub fn copy_iovs_from_user(&self) -> Result<Box<[IoVec]>> {
         copy_iovs_from_user(self.msg_iov, self.msg_iovlen as usize)
     }
+
+    /// Reads the control messages from user space.
+    ///
+    /// Currently, only `SCM_CREDENTIALS` and `SCM_RIGHTS` are supported. Unsupported control
+    /// messages are ignored.
+    pub fn read_control_messages_from_user(&self) -> Result<Vec<ControlMessage>> {
+        if self.msg_control == 0 {
+            return Ok(Vec::new());
+        }
+
+        let user_space = CurrentUserSpace::get();
+        let mut control_messages = Vec::new();
+
+        let mut offset = 0;
+        while offset + size_of::<CControlMsgHdr>() <= self.msg_controllen {
+            let hdr_addr = self.msg_control + offset;
+            let hdr: CControlMsgHdr = user_space.read_val(hdr_addr)?;
+
+            if hdr.cmsg_len < size_of::<CControlMsgHdr>()
+                || hdr.cmsg_len > self.msg_controllen - offset
+            {
+                return_errno_with_message!(Errno::EINVAL, "the control message length is invalid");
+            }
+
+            let data_addr = hdr_addr + size_of::<CControlMsgHdr>();
+            let data_len = hdr.cmsg_len - size_of::<CControlMsgHdr>();
+
+            match (
+                CSocketOptionLevel::try_from(hdr.cmsg_level),
+                CControlMsgType::try_from(hdr.cmsg_type),
+            ) {
+                (Ok(CSocketOptionLevel::SOL_SOCKET), Ok(CControlMsgType::SCM_CREDENTIALS)) => {
+                    if data_len != size_of::<UnixCredentials>() {
+                        return_errno_with_message!(
+                            Errno::EINVAL,
+                            "the credentials have an invalid length"
+                        );
+                    }
+                    let credentials: UnixCredentials = user_space.read_val(data_addr)?;
+                    control_messages.push(ControlMessage::Credentials(credentials));
+                }
+                (Ok(CSocketOptionLevel::SOL_SOCKET), Ok(CControlMsgType::SCM_RIGHTS)) => {
+                    let rights = read_rights_from_user(data_addr, data_len)?;
+                    control_messages.push(ControlMessage::Rights(rights));
+                }
+                _ => warn!("unsupported control message: {:?}", hdr),
+            }
+
+            offset += hdr.cmsg_len.align_up(CONTROL_MSG_ALIGN);
+        }
+
+        Ok(control_messages)
+    }
+
+    /// Writes the control messages to user space.
+    ///
+    /// The control messages are written one after another until the buffer is full. A control
+    /// message that does not fit is truncated, and the files in an `SCM_RIGHTS` message that
+    /// do not fit are not installed in the file table, so they are closed when the message is
+    /// dropped. The installed files get the `FD_CLOEXEC` flag if `is_cloexec` is true.
+    ///
+    /// This method returns the number of bytes written and whether any control message is
+    /// truncated.
+    ///
+    /// Reference: <https://elixir.bootlin.com/linux/v6.10.4/source/net/core/scm.c#L229>.
+    pub fn write_control_messages_to_user(
+        &self,
+        control_messages: &[ControlMessage],
+        is_cloexec: bool,
+    ) -> Result<(usize, bool)> {
+        let control_len = if self.msg_control == 0 {
+            0
+        } else {
+            self.msg_controllen
+        };
+
+        let mut offset = 0;
+        let mut is_truncated = false;
+        for control_message in control_messages {
+            let buf_addr = self.msg_control + offset;
+            let buf_len = control_len - offset;
+
+            let written_len = match control_message {
+                ControlMessage::Credentials(credentials) => write_control_message_to_user(
+                    buf_addr,
+                    buf_len,
+                    CControlMsgType::SCM_CREDENTIALS,
+                    credentials.as_bytes(),
+                    &mut is_truncated,
+                )?,
+                ControlMessage::Rights(rights) => {
+                    write_rights_to_user(buf_addr, buf_len, rights, is_cloexec, &mut is_truncated)?
+                }
+            };
+
+            offset += written_len;
+        }
+
+        Ok((offset, is_truncated))
+    }
+}
+
+/// Reads the file descriptors in an `SCM_RIGHTS` control message and looks up the files.
+fn read_rights_from_user(data_addr: Vaddr, data_len: usize) -> Result<UnixRights> {
+    let nr_files = data_len / size_of::<FileDesc>();
+    if data_len % size_of::<FileDesc>() != 0 || nr_files == 0 || nr_files > UnixRights::MAX_FILES {
+        return_errno_with_message!(Errno::EINVAL, "the number of files is invalid");
+    }
+
+    let user_space = CurrentUserSpace::get();
+    let fds = (0..nr_files)
+        .map(|i| user_space.read_val::<FileDesc>(data_addr + i * size_of::<FileDesc>()))
+        .collect::<Result<Vec<_>>>()?;
+
+    let current = current!();
+    let file_table = current.file_table().lock();
+    let files = fds
+        .into_iter()
+        .map(|fd| file_table.get_file(fd).cloned())
+        .collect::<Result<Vec<_>>>()?;
+    Ok(UnixRights::new(files))
+}
+
+/// Writes a control message at the `SOL_SOCKET` level to the user buffer.
+///
+/// If the buffer is too small, the data is truncated and `is_truncated` is set. This method
+/// returns the number of bytes consumed from the buffer.
+fn write_control_message_to_user(
+    buf_addr: Vaddr,
+    buf_len: usize,
+    cmsg_type: CControlMsgType,
+    data: &[u8],
+    is_truncated: &mut bool,
+) -> Result<usize> {
+    if buf_len < size_of::<CControlMsgHdr>() {
+        *is_truncated = true;
+        return Ok(0);
+    }
+
+    let mut cmsg_len = size_of::<CControlMsgHdr>() + data.len();
+    if buf_len < cmsg_len {
+        *is_truncated = true;
+        cmsg_len = buf_len;
+    }
+
+    let hdr = CControlMsgHdr {
+        cmsg_len,
+        cmsg_level: CSocketOptionLevel::SOL_SOCKET as i32,
+        cmsg_type: cmsg_type as i32,
+    };
+
+    let user_space = CurrentUserSpace::get();
+    user_space.write_val(buf_addr, &hdr)?;
+    user_space.write_bytes(
+        buf_addr + size_of::<CControlMsgHdr>(),
+        &mut VmReader::from(&data[..cmsg_len - size_of::<CControlMsgHdr>()]),
+    )?;
+
+    Ok(cmsg_len.align_up(CONTROL_MSG_ALIGN).min(buf_len))
+}
+
+/// Installs the files in an `SCM_RIGHTS` control message and writes the file descriptors to
+/// the user buffer.
+///
+/// Only the files whose descriptors fit in the buffer and the file table are installed. If some
+/// files are left out, `is_truncated` is set. This method returns the number of bytes consumed
+/// from the buffer.
+fn write_rights_to_user(
+    buf_addr: Vaddr,
+    buf_len: usize,
+    rights: &UnixRights,
+    is_cloexec: bool,
+    is_truncated: &mut bool,
+) -> Result<usize> {
+    let files = rights.files();
+    let max_files = buf_len.saturating_sub(size_of::<CControlMsgHdr>()) / size_of::<FileDesc>();
+    let nr_files = files.len().min(max_files);
+    if nr_files < files.len() {
+        *is_truncated = true;
+    }
+    if nr_files == 0 {
+        return Ok(0);
+    }
+
+    let fd_flags = if is_cloexec {
+        FdFlags::CLOEXEC
+    } else {
+        FdFlags::empty()
+    };
+    let current = current!();
+    let mut file_table = current.file_table().lock();
+    let fds: Vec<FileDesc> = files[..nr_files]
+        .iter()
+        .map_while(|file| file_table.insert(file.clone(), fd_flags).ok())
+        .collect();
+    // Like Linux, the files that cannot be installed for lack of fds are left out.
+    if fds.len() < nr_files {
+        *is_truncated = true;
+    }
+    if fds.is_empty() {
+        return Ok(0);
+    }
+
+    let cmsg_len = size_of::<CControlMsgHdr>() + fds.len() * size_of::<FileDesc>();
+    let hdr = CControlMsgHdr {
+        cmsg_len,
+        cmsg_level: CSocketOptionLevel::SOL_SOCKET as i32,
+        cmsg_type: CControlMsgType::SCM_RIGHTS as i32,
+    };
+
+    let user_space = CurrentUserSpace::get();
+    let res = user_space.write_val(buf_addr, &hdr).and_then(|_| {
+        fds.iter().enumerate().try_for_each(|(i, fd)| {
+            let fd_addr = buf_addr + size_of::<CControlMsgHdr>() + i * size_of::<FileDesc>();
+            user_space.write_val(fd_addr, fd)
+        })
+    });
+    if let Err(err) = res {
+        // The process cannot learn the file descriptors, so they should not be left open.
+        for fd in fds {
+            file_table.close_file(fd);
+        }
+        return Err(err);
+    }
+
+    Ok(cmsg_len.align_up(CONTROL_MSG_ALIGN).min(buf_len))
+}
+
+/// The header of a control message (`struct cmsghdr` in Linux).
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+struct CControlMsgHdr {
+    /// Data byte count, including the header
+    cmsg_len: usize,
+    /// Originating protocol
+    cmsg_level: i32,
+    /// Protocol-specific type
+    cmsg_type: i32,
+}
+
+/// Control messages are aligned to the size of `long` in Linux.
+const CONTROL_MSG_ALIGN: usize = size_of::<usize>();
+
+/// Types of control messages at the `SOL_SOCKET` level.
+/// From https://elixir.bootlin.com/linux/v6.0.9/source/include/linux/socket.h#L163
+#[repr(i32)]
+#[derive(Debug, Clone, Copy, TryFromInt)]
+#[allow(non_camel_case_types)]
+enum CControlMsgType {
+    /// Transfer file descriptors
+    SCM_RIGHTS = 1,
+    /// Credentials passing
+    SCM_CREDENTIALS = 2,
 }
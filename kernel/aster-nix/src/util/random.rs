@@ -2,50 +2,202 @@
 
 #![allow(unused_variables)]
 
-use rand::{rngs::StdRng, Error as RandError, RngCore};
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use ostd::arch::timer::Jiffies;
+use rand::{rngs::StdRng, Error as RandError, RngCore, SeedableRng};
 use spin::Once;
 
 use crate::prelude::*;
 
 static RNG: Once<SpinLock<StdRng>> = Once::new();
 
+/// Whether the RNG is seeded from a hardware entropy source or the collected timing jitter.
+static IS_SEEDED: AtomicBool = AtomicBool::new(false);
+
+/// The timing jitter collected by [`collect_jitter_entropy`].
+static JITTER_POOL: SpinLock<JitterPool> = SpinLock::new(JitterPool::new());
+
+/// The number of jitter samples required to seed the RNG, each credited with one bit.
+const NR_JITTER_SAMPLES_TO_SEED: usize = 256;
+
 /// Fill `dest` with random bytes.
 ///
-/// It's cryptographically secure, as documented in [`rand::rngs::StdRng`].
+/// It's cryptographically secure, as documented in [`rand::rngs::StdRng`],
+/// if the RNG is seeded (see [`is_seeded`]).
 pub fn getrandom(dst: &mut [u8]) -> Result<()> {
     Ok(RNG.get().unwrap().lock().try_fill_bytes(dst)?)
 }
 
+/// Returns whether the RNG is seeded with enough entropy.
+///
+/// If no hardware entropy source is available, the RNG is seeded from the fallback
+/// entropy pool, which is not secure enough. In this case, this function returns `false`
+/// until enough entropy is collected by [`collect_jitter_entropy`].
+pub fn is_seeded() -> bool {
+    IS_SEEDED.load(Ordering::Relaxed)
+}
+
+/// Collects the timing jitter of the CPU until the next timer interrupt, and reseeds the RNG
+/// once enough jitter is collected.
+///
+/// Like `try_to_generate_entropy` in Linux, the jitter across each timer interrupt is credited
+/// with one bit of entropy. So this function is called repeatedly to wait for the RNG to be
+/// seeded.
+pub fn collect_jitter_entropy() {
+    // The arrival of the timer interrupt, as measured by the TSC, is hardly predictable.
+    let start = Jiffies::elapsed().as_u64();
+    let mut sample = ostd::arch::read_tsc();
+    while Jiffies::elapsed().as_u64() == start {
+        sample = mix(sample ^ ostd::arch::read_tsc());
+        core::hint::spin_loop();
+    }
+
+    let mut pool = JITTER_POOL.lock();
+    pool.add_sample(sample);
+    if pool.nr_samples < NR_JITTER_SAMPLES_TO_SEED || is_seeded() {
+        return;
+    }
+
+    // The collected jitter is mixed with the output of the RNG, so the entropy that the RNG
+    // has is kept.
+    let mut rng = RNG.get().unwrap().lock();
+    let mut seed = <StdRng as SeedableRng>::Seed::default();
+    rng.fill_bytes(seed.as_mut());
+    for (chunk, word) in seed.as_mut().chunks_mut(size_of::<u64>()).zip(pool.words) {
+        for (byte, jitter) in chunk.iter_mut().zip(word.to_ne_bytes()) {
+            *byte ^= jitter;
+        }
+    }
+    *rng = StdRng::from_seed(seed);
+    IS_SEEDED.store(true, Ordering::Relaxed);
+}
+
 pub fn init() {
     // The seed used to initialize the RNG is required to be secure and unpredictable.
 
     cfg_if::cfg_if! {
         if #[cfg(target_arch = "x86_64")] {
-            use rand::SeedableRng;
-            use ostd::arch::read_random;
+            use ostd::arch::{read_random, read_random_seed};
+
+            let read_hardware_random = || read_random_seed().or_else(read_random);
 
             let mut seed = <StdRng as SeedableRng>::Seed::default();
-            let mut chunks = seed.as_mut().chunks_exact_mut(size_of::<u64>());
-            for chunk in chunks.by_ref() {
-                let src = read_random().expect("read_random failed multiple times").to_ne_bytes();
-                chunk.copy_from_slice(&src);
+            let mut is_seeded = true;
+            let mut pool = FallbackEntropyPool::new();
+            for chunk in seed.as_mut().chunks_mut(size_of::<u64>()) {
+                let src = read_hardware_random().unwrap_or_else(|| {
+                    is_seeded = false;
+                    pool.next_u64()
+                });
+                chunk.copy_from_slice(&src.to_ne_bytes()[..chunk.len()]);
             }
-            let tail = chunks.into_remainder();
-            let n = tail.len();
-            if n > 0 {
-                let src = read_random().expect("read_random failed multiple times").to_ne_bytes();
-                tail.copy_from_slice(&src[..n]);
+            if !is_seeded {
+                warn!("no hardware entropy source, seeding the RNG from the fallback entropy pool");
             }
 
-            RNG.call_once(|| SpinLock::new(StdRng::from_seed(seed)));
+            RNG.call_once(|| {
+                IS_SEEDED.store(is_seeded, Ordering::Relaxed);
+                SpinLock::new(StdRng::from_seed(seed))
+            });
         } else {
             compile_error!("unsupported target");
         }
     }
 }
 
+/// An entropy pool that collects the timing jitter of the CPU.
+///
+/// It is only used when no hardware entropy source is available. The jitter is hardly
+/// predictable, but there is no guarantee on the amount of entropy it provides.
+struct FallbackEntropyPool {
+    state: u64,
+}
+
+impl FallbackEntropyPool {
+    fn new() -> Self {
+        Self {
+            state: ostd::arch::read_tsc(),
+        }
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        const NR_SAMPLES: usize = 64;
+
+        for _ in 0..NR_SAMPLES {
+            // The time taken by the loop varies with caches, interrupts, etc.
+            let start = ostd::arch::read_tsc();
+            for _ in 0..(self.state & 0xff) {
+                core::hint::spin_loop();
+            }
+            let jitter = ostd::arch::read_tsc().wrapping_sub(start);
+            self.state = mix(self.state ^ jitter);
+        }
+        self.state
+    }
+}
+
+/// The pool of the timing jitter that is collected across the timer interrupts.
+struct JitterPool {
+    words: [u64; 4],
+    nr_samples: usize,
+}
+
+impl JitterPool {
+    const fn new() -> Self {
+        Self {
+            words: [0; 4],
+            nr_samples: 0,
+        }
+    }
+
+    fn add_sample(&mut self, sample: u64) {
+        let idx = self.nr_samples % self.words.len();
+        self.words[idx] = mix(self.words[idx] ^ sample);
+        self.nr_samples += 1;
+    }
+}
+
+/// Mixes the bits of `x` with the finalizer of SplitMix64.
+fn mix(x: u64) -> u64 {
+    let mut z = x.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
 impl From<RandError> for Error {
     fn from(value: RandError) -> Self {
         Error::with_message(Errno::ENOSYS, "cannot generate random bytes")
     }
 }
+
+#[cfg(ktest)]
+mod test {
+    use ostd::prelude::*;
+
+    use super::*;
+
+    #[ktest]
+    fn random_bytes_differ() {
+        init();
+
+        let mut first = [0u8; 32];
+        let mut second = [0u8; 32];
+        getrandom(&mut first).unwrap();
+        getrandom(&mut second).unwrap();
+        assert_ne!(first, second);
+
+        getrandom(&mut []).unwrap();
+    }
+
+    #[ktest]
+    fn seeded_by_jitter() {
+        init();
+
+        for _ in 0..NR_JITTER_SAMPLES_TO_SEED {
+            collect_jitter_entropy();
+        }
+        assert!(is_seeded());
+    }
+}
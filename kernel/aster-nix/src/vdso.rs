@@ -11,8 +11,8 @@
 //! necessary time-related information, and a Virtual Memory Object (VMO) that encapsulates both the data and the
 //! VDSO routines. The VMO is intended to be mapped into the address space of every user space process for efficient access.
 //!
-//! The module is initialized with `init`, which sets up the `START_SECS_COUNT` and prepares the VDSO instance for
-//! use. It also hooks up the VDSO data update routine to the time management subsystem for periodic updates.
+//! The module is initialized with `init`, which prepares the VDSO instance for use. It also hooks up the VDSO
+//! data update routine to the time management subsystem for periodic updates.
 
 use alloc::{boxed::Box, sync::Arc};
 use core::{mem::ManuallyDrop, time::Duration};
@@ -30,7 +30,7 @@ use spin::Once;
 use crate::{
     fs::fs_resolver::{FsPath, FsResolver, AT_FDCWD},
     syscall::ClockId,
-    time::{clocks::MonotonicClock, timer::Timeout, SystemTime, START_TIME},
+    time::{clocks::MonotonicClock, start_time_as_duration, timer::Timeout},
     vm::vmo::{Vmo, VmoOptions},
 };
 
@@ -38,7 +38,6 @@ const CLOCK_TAI: usize = 11;
 const VDSO_BASES: usize = CLOCK_TAI + 1;
 const DEFAULT_CLOCK_MODE: VdsoClockMode = VdsoClockMode::Tsc;
 
-static START_SECS_COUNT: Once<u64> = Once::new();
 static VDSO: Once<Arc<Vdso>> = Once::new();
 
 #[derive(Debug, Copy, Clone)]
@@ -162,15 +161,15 @@ impl VdsoData {
     fn update_high_res_instant(&mut self, instant: Instant, instant_cycles: u64) {
         self.last_cycles = instant_cycles;
         for clock_id in HIGH_RES_CLOCK_IDS {
-            let secs = if clock_id == ClockId::CLOCK_REALTIME {
-                instant.secs() + START_SECS_COUNT.get().unwrap()
+            let instant = if clock_id == ClockId::CLOCK_REALTIME {
+                realtime_of(instant)
             } else {
-                instant.secs()
+                instant
             };
 
             self.update_clock_instant(
                 clock_id as usize,
-                secs,
+                instant.secs(),
                 (instant.nanos() as u64) << self.shift as u64,
             );
         }
@@ -178,16 +177,22 @@ impl VdsoData {
 
     fn update_coarse_res_instant(&mut self, instant: Instant) {
         for clock_id in COARSE_RES_CLOCK_IDS {
-            let secs = if clock_id == ClockId::CLOCK_REALTIME_COARSE {
-                instant.secs() + START_SECS_COUNT.get().unwrap()
+            let instant = if clock_id == ClockId::CLOCK_REALTIME_COARSE {
+                realtime_of(instant)
             } else {
-                instant.secs()
+                instant
             };
-            self.update_clock_instant(clock_id as usize, secs, instant.nanos() as u64);
+            self.update_clock_instant(clock_id as usize, instant.secs(), instant.nanos() as u64);
         }
     }
 }
 
+/// Converts a monotonic `instant` to the realtime, which reflects the system time set by the user.
+fn realtime_of(instant: Instant) -> Instant {
+    let monotonic_time = Duration::new(instant.secs(), instant.nanos());
+    Instant::from(start_time_as_duration() + monotonic_time)
+}
+
 /// Vdso (virtual dynamic shared object) is used to export some safe kernel space routines to user space applications
 /// so that applications can call these kernel space routines in-process, without context switching.
 ///
@@ -302,14 +307,17 @@ fn update_vdso_coarse_res_instant() {
     VDSO.get().unwrap().update_coarse_res_instant(instant);
 }
 
-/// Init `START_SECS_COUNT`, which is used to record the seconds passed since 1970-01-01 00:00:00.
-fn init_start_secs_count() {
-    let time_duration = START_TIME
-        .get()
-        .unwrap()
-        .duration_since(&SystemTime::UNIX_EPOCH)
-        .unwrap();
-    START_SECS_COUNT.call_once(|| time_duration.as_secs());
+/// Updates the realtime in Vdso after the system time is set.
+///
+/// Otherwise, the user space would read the old realtime until the next periodic update.
+pub(crate) fn update_vdso_realtime() {
+    let Some(vdso) = VDSO.get() else {
+        return;
+    };
+
+    let (last_instant, last_cycles) = aster_time::default_clocksource().last_record();
+    vdso.update_high_res_instant(last_instant, last_cycles);
+    vdso.update_coarse_res_instant(Instant::from(read_monotonic_time()));
 }
 
 fn init_vdso() {
@@ -319,7 +327,6 @@ fn init_vdso() {
 
 /// Init this module.
 pub(super) fn init() {
-    init_start_secs_count();
     init_vdso();
     aster_time::VDSO_DATA_HIGH_RES_UPDATE_FN.call_once(|| Arc::new(update_vdso_high_res_instant));
 
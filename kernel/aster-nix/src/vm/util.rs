@@ -1,6 +1,7 @@
 // SPDX-License-Identifier: MPL-2.0
 
 use ostd::mm::{Frame, FrameAllocOptions};
+use spin::Once;
 
 use crate::prelude::*;
 
@@ -10,3 +11,21 @@ pub fn duplicate_frame(src: &Frame) -> Result<Frame> {
     new_frame.copy_from(src);
     Ok(new_frame)
 }
+
+static ZERO_FRAME: Once<Frame> = Once::new();
+
+/// Returns the frame filled with zeros that is shared by all anonymous mappings.
+///
+/// The frame is mapped read-only on read accesses to untouched anonymous pages,
+/// so it must never be written to.
+pub fn zero_frame() -> Result<Frame> {
+    let frame = ZERO_FRAME.try_call_once(|| FrameAllocOptions::new(1).alloc_single())?;
+    Ok(frame.clone())
+}
+
+/// Returns whether `frame` is the frame returned by [`zero_frame`].
+pub fn is_zero_frame(frame: &Frame) -> bool {
+    ZERO_FRAME
+        .get()
+        .is_some_and(|zero_frame| zero_frame.start_paddr() == frame.start_paddr())
+}
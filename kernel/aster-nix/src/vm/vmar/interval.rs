@@ -4,11 +4,7 @@
 
 use core::ops::Range;
 
-/// An interval is associated with a range of values (`T`).
-pub trait Interval<T> {
-    /// Returns the range of the interval.
-    fn range(&self) -> Range<T>;
-}
+pub use crate::util::collections::Interval;
 
 /// A collection that contains intervals as items. In particular,
 /// the collection allows one to retrieve interval items that intersect with
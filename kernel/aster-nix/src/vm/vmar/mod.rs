@@ -14,14 +14,17 @@ use core::ops::Range;
 
 use align_ext::AlignExt;
 use aster_rights::Rights;
-use ostd::mm::{VmSpace, MAX_USERSPACE_VADDR};
+use ostd::mm::{vm_space::VmItem, VmSpace, MAX_USERSPACE_VADDR};
 
 use self::{
     interval::{Interval, IntervalSet},
     vm_mapping::VmMapping,
 };
 use super::page_fault_handler::PageFaultHandler;
-use crate::{prelude::*, thread::exception::handle_page_fault, vm::perms::VmPerms};
+use crate::{
+    prelude::*, thread::exception::handle_page_fault, util::collections::IntervalMap,
+    vm::perms::VmPerms,
+};
 
 /// Virtual Memory Address Regions (VMARs) are a type of capability that manages
 /// user address spaces.
@@ -101,6 +104,70 @@ impl<R> Vmar<R> {
     pub fn resize_mapping(&self, map_addr: Vaddr, old_size: usize, new_size: usize) -> Result<()> {
         self.0.resize_mapping(map_addr, old_size, new_size)
     }
+
+    /// Remaps the original mapping `old_addr..old_addr + old_size` to a mapping of `new_size`,
+    /// and returns the start address of the new mapping.
+    ///
+    /// The original range must be within a single `VmMapping`.
+    /// If `new_addr` is `Some`, the mapping is moved to the fixed address, replacing the
+    /// existing mappings there. Otherwise, the mapping is resized in place if possible,
+    /// or moved to a free region if `may_move` is true.
+    /// When moving, the mapped pages are moved along with the mapping so that the content
+    /// of private mappings is preserved.
+    pub fn remap(
+        &self,
+        old_addr: Vaddr,
+        old_size: usize,
+        new_size: usize,
+        new_addr: Option<Vaddr>,
+        may_move: bool,
+    ) -> Result<Vaddr> {
+        self.0
+            .remap(old_addr, old_size, new_size, new_addr, may_move)
+    }
+
+    /// Locks the pages of the mappings in the specified range in memory.
+    ///
+    /// The pages are populated immediately and are never reclaimed (see [`Self::reclaim`])
+    /// until they are unlocked. The range's start and end addresses must be page-aligned.
+    /// Also, the range must be completely mapped.
+    pub fn lock(&self, range: Range<usize>) -> Result<()> {
+        self.0.lock(range, true)
+    }
+
+    /// Unlocks the pages of the mappings in the specified range.
+    ///
+    /// The range's start and end addresses must be page-aligned.
+    /// Also, the range must be completely mapped.
+    pub fn unlock(&self, range: Range<usize>) -> Result<()> {
+        self.0.lock(range, false)
+    }
+
+    /// Returns the size in bytes of the locked pages in the specified range.
+    pub fn locked_size(&self, range: &Range<usize>) -> usize {
+        self.0.locked_size(range)
+    }
+
+    /// Reclaims the pages of the mappings in the specified range.
+    ///
+    /// Only the pages that can be faulted in again from the mapped VMOs are unmapped,
+    /// while the locked pages and the anonymous pages are kept.
+    pub fn reclaim(&self, range: Range<usize>) -> Result<()> {
+        self.0.reclaim(&range)
+    }
+
+    /// Checks if the range overlaps with no mapping or child VMAR.
+    pub fn is_range_free(&self, range: &Range<usize>) -> bool {
+        self.0.is_range_free(range)
+    }
+
+    /// Returns the VMO ID and the offset in the VMO of the shared memory at `addr`.
+    ///
+    /// They identify the memory no matter which address spaces it is mapped to and at
+    /// which addresses. `None` is returned if `addr` is not in a shared mapping.
+    pub fn shared_memory_key(&self, addr: Vaddr) -> Option<(usize, usize)> {
+        self.0.shared_memory_key(addr)
+    }
 }
 
 pub(super) struct Vmar_ {
@@ -121,8 +188,8 @@ struct VmarInner {
     is_destroyed: bool,
     /// The child VMARs. The key is offset relative to root VMAR
     child_vmar_s: BTreeMap<Vaddr, Arc<Vmar_>>,
-    /// The mapped VMOs. The key is offset relative to root VMAR
-    vm_mappings: BTreeMap<Vaddr, Arc<VmMapping>>,
+    /// The mapped VMOs, indexed by their offsets relative to root VMAR
+    vm_mappings: IntervalMap<Vaddr, Arc<VmMapping>>,
     /// Free regions that can be used for creating child VMAR or mapping VMOs
     free_regions: BTreeMap<Vaddr, FreeRegion>,
 }
@@ -132,7 +199,7 @@ impl VmarInner {
         Self {
             is_destroyed: false,
             child_vmar_s: BTreeMap::new(),
-            vm_mappings: BTreeMap::new(),
+            vm_mappings: IntervalMap::new(),
             free_regions: BTreeMap::new(),
         }
     }
@@ -172,10 +239,25 @@ impl VmarInner {
         }
         return_errno_with_message!(Errno::EACCES, "Cannot find free region for child")
     }
+
+    /// Returns the mappings whose guard pages intersect with `range`.
+    fn guard_page_owners<'a>(
+        &'a self,
+        range: &Range<Vaddr>,
+    ) -> impl Iterator<Item = &'a Arc<VmMapping>> + 'a {
+        let range = range.clone();
+        // A guard page is right after its mapping.
+        let search_range = range.start.saturating_sub(PAGE_SIZE)..range.end;
+        self.vm_mappings
+            .overlapping(&search_range)
+            .filter(move |vm_mapping| {
+                vm_mapping.has_guard_page() && range.contains(&vm_mapping.map_end())
+            })
+    }
 }
 
 pub const ROOT_VMAR_LOWEST_ADDR: Vaddr = 0x001_0000; // 64 KiB is the Linux configurable default
-const ROOT_VMAR_CAP_ADDR: Vaddr = MAX_USERSPACE_VADDR;
+pub const ROOT_VMAR_CAP_ADDR: Vaddr = MAX_USERSPACE_VADDR;
 
 /// Returns whether the input `vaddr` is a legal user space virtual address.
 pub fn is_userspace_vaddr(vaddr: Vaddr) -> bool {
@@ -218,7 +300,7 @@ impl Vmar_ {
         let vmar_inner = VmarInner {
             is_destroyed: false,
             child_vmar_s: BTreeMap::new(),
-            vm_mappings: BTreeMap::new(),
+            vm_mappings: IntervalMap::new(),
             free_regions,
         };
         let vm_space = VmSpace::new();
@@ -242,12 +324,7 @@ impl Vmar_ {
     fn do_protect_inner(&self, perms: VmPerms, range: Range<usize>) -> Result<()> {
         let protect_mappings: Vec<Arc<VmMapping>> = {
             let inner = self.inner.lock();
-            inner
-                .vm_mappings
-                .find(&range)
-                .into_iter()
-                .cloned()
-                .collect()
+            inner.vm_mappings.overlapping(&range).cloned().collect()
         };
 
         for vm_mapping in protect_mappings {
@@ -277,7 +354,9 @@ impl Vmar_ {
 
         // The protected range should not intersect with any free region
         let inner = self.inner.lock();
-        if inner.free_regions.find(range).into_iter().next().is_some() {
+        if inner.free_regions.find(range).into_iter().next().is_some()
+            || inner.guard_page_owners(range).next().is_some()
+        {
             return_errno_with_message!(Errno::EACCES, "protected range is not fully mapped");
         }
 
@@ -292,6 +371,107 @@ impl Vmar_ {
         Ok(())
     }
 
+    fn lock(&self, range: Range<usize>, is_locked: bool) -> Result<()> {
+        assert!(range.start % PAGE_SIZE == 0);
+        assert!(range.end % PAGE_SIZE == 0);
+        if self.ensure_range_mapped(&range).is_err() {
+            return_errno_with_message!(Errno::ENOMEM, "the locked range is not fully mapped");
+        }
+        self.do_lock_inner(is_locked, range)
+    }
+
+    // Do real lock. The locked range is ensured to be mapped.
+    fn do_lock_inner(&self, is_locked: bool, range: Range<usize>) -> Result<()> {
+        let lock_mappings: Vec<Arc<VmMapping>> = {
+            let inner = self.inner.lock();
+            inner.vm_mappings.overlapping(&range).cloned().collect()
+        };
+
+        for vm_mapping in lock_mappings {
+            let intersected_range = get_intersected_range(&range, &vm_mapping.range());
+            vm_mapping.set_locked(intersected_range, is_locked)?;
+        }
+
+        if is_locked {
+            // The mappings may have been split, so they are looked up again.
+            let populate_mappings: Vec<Arc<VmMapping>> = {
+                let inner = self.inner.lock();
+                inner.vm_mappings.overlapping(&range).cloned().collect()
+            };
+            for vm_mapping in populate_mappings {
+                let intersected_range = get_intersected_range(&range, &vm_mapping.range());
+                vm_mapping.populate(intersected_range).map_err(|err| {
+                    if err.error() == Errno::ENOMEM {
+                        Error::with_message(Errno::EAGAIN, "cannot populate the locked pages")
+                    } else {
+                        err
+                    }
+                })?;
+            }
+        }
+
+        for child_vmar_ in self.inner.lock().child_vmar_s.find(&range) {
+            let child_vmar_range = child_vmar_.range();
+            debug_assert!(is_intersected(&child_vmar_range, &range));
+            let intersected_range = get_intersected_range(&range, &child_vmar_range);
+            child_vmar_.do_lock_inner(is_locked, intersected_range)?;
+        }
+
+        Ok(())
+    }
+
+    fn locked_size(&self, range: &Range<usize>) -> usize {
+        let inner = self.inner.lock();
+        let mappings_size: usize = inner
+            .vm_mappings
+            .overlapping(range)
+            .filter(|vm_mapping| vm_mapping.is_locked())
+            .map(|vm_mapping| get_intersected_range(range, &vm_mapping.range()).len())
+            .sum();
+        let child_vmars_size: usize = inner
+            .child_vmar_s
+            .find(range)
+            .into_iter()
+            .map(|child_vmar_| {
+                child_vmar_.locked_size(&get_intersected_range(range, &child_vmar_.range()))
+            })
+            .sum();
+        mappings_size + child_vmars_size
+    }
+
+    fn is_range_free(&self, range: &Range<usize>) -> bool {
+        let inner = self.inner.lock();
+        inner.child_vmar_s.find(range).into_iter().next().is_none()
+            && inner.vm_mappings.overlapping(range).next().is_none()
+            && inner.guard_page_owners(range).next().is_none()
+    }
+
+    fn shared_memory_key(&self, addr: Vaddr) -> Option<(usize, usize)> {
+        let inner = self.inner.lock();
+        if let Some(child_vmar) = inner.child_vmar_s.find_one(&addr) {
+            return child_vmar.shared_memory_key(addr);
+        }
+
+        let vm_mapping = inner.vm_mappings.find(&addr)?;
+        if !vm_mapping.is_shared() {
+            return None;
+        }
+        let vmo_id = vm_mapping.vmo()?.id();
+        let vmo_offset = vm_mapping.vmo_offset()? + (addr - vm_mapping.map_to_addr());
+        Some((vmo_id, vmo_offset))
+    }
+
+    fn reclaim(&self, range: &Range<usize>) -> Result<()> {
+        let inner = self.inner.lock();
+        for vm_mapping in inner.vm_mappings.overlapping(range) {
+            vm_mapping.reclaim(get_intersected_range(range, &vm_mapping.range()))?;
+        }
+        for child_vmar_ in inner.child_vmar_s.find(range) {
+            child_vmar_.reclaim(&get_intersected_range(range, &child_vmar_.range()))?;
+        }
+        Ok(())
+    }
+
     /// Handles user space page fault, if the page fault is successfully handled ,return Ok(()).
     fn handle_page_fault(
         &self,
@@ -313,7 +493,7 @@ impl Vmar_ {
         }
 
         // FIXME: If multiple VMOs are mapped to the addr, should we allow all VMOs to handle page fault?
-        if let Some(vm_mapping) = inner.vm_mappings.find_one(&page_fault_addr) {
+        if let Some(vm_mapping) = inner.vm_mappings.find(&page_fault_addr) {
             debug_assert!(is_intersected(
                 &vm_mapping.range(),
                 &(page_fault_addr..page_fault_addr + 1)
@@ -359,29 +539,33 @@ impl Vmar_ {
         let mut mappings_to_remove = LinkedList::new();
         let mut mappings_to_append = LinkedList::new();
 
-        for vm_mapping in inner.vm_mappings.find(&range) {
+        for vm_mapping in inner.vm_mappings.overlapping(&range) {
             let vm_mapping_range = vm_mapping.range();
             debug_assert!(is_intersected(&vm_mapping_range, &range));
             let intersected_range = get_intersected_range(&vm_mapping_range, &range);
-            vm_mapping.trim_mapping(
+            let released_guard_page = vm_mapping.trim_mapping(
                 &intersected_range,
                 &mut mappings_to_remove,
                 &mut mappings_to_append,
             )?;
             let free_region = FreeRegion::new(intersected_range);
             free_regions.insert(free_region.start(), free_region);
+            if let Some(guard_page) = released_guard_page {
+                let free_region = FreeRegion::new(guard_page);
+                free_regions.insert(free_region.start(), free_region);
+            }
         }
 
         for mapping in mappings_to_remove {
             inner.vm_mappings.remove(&mapping);
         }
-        for (map_to_addr, mapping) in mappings_to_append {
-            inner.vm_mappings.insert(map_to_addr, mapping);
+        for (_, mapping) in mappings_to_append {
+            inner.vm_mappings.insert(mapping);
         }
 
         inner
             .vm_mappings
-            .retain(|_, vm_mapping| !vm_mapping.is_destroyed());
+            .retain(|vm_mapping| !vm_mapping.is_destroyed());
         inner.free_regions.append(&mut free_regions);
         drop(inner);
         self.merge_continuous_regions();
@@ -414,11 +598,21 @@ impl Vmar_ {
             let inner = self.inner.lock();
             inner
                 .vm_mappings
-                .find_one(&(old_map_end - 1))
+                .find(&(old_map_end - 1))
                 .unwrap()
                 .clone()
         };
 
+        // The mapping grows over its guard page, if any.
+        if let Some(guard_page) = last_mapping.release_guard_page() {
+            let free_region = FreeRegion::new(guard_page);
+            self.inner
+                .lock()
+                .free_regions
+                .insert(free_region.start(), free_region);
+            self.merge_continuous_regions();
+        }
+
         let extra_mapping_start = last_mapping.map_end();
         let free_region = self.allocate_free_region_for_mapping(
             new_map_end - extra_mapping_start,
@@ -430,6 +624,85 @@ impl Vmar_ {
         Ok(())
     }
 
+    fn remap(
+        &self,
+        old_addr: Vaddr,
+        old_size: usize,
+        new_size: usize,
+        new_addr: Option<Vaddr>,
+        may_move: bool,
+    ) -> Result<Vaddr> {
+        debug_assert!(old_addr % PAGE_SIZE == 0);
+        debug_assert!(old_size % PAGE_SIZE == 0);
+        debug_assert!(new_size % PAGE_SIZE == 0);
+
+        let old_range = old_addr..old_addr + old_size;
+        let old_mapping = {
+            let inner = self.inner.lock();
+            let Some(mapping) = inner.vm_mappings.find(&old_addr) else {
+                return_errno_with_message!(Errno::EFAULT, "the remapped range is not mapped");
+            };
+            if mapping.map_end() < old_range.end {
+                return_errno_with_message!(
+                    Errno::EFAULT,
+                    "the remapped range spans multiple mappings"
+                );
+            }
+            mapping.clone()
+        };
+
+        if new_addr.is_none() {
+            if new_size <= old_size {
+                self.resize_mapping(old_addr, old_size, new_size)?;
+                return Ok(old_addr);
+            }
+            // The mapping can only be expanded in place at its end.
+            if old_range.end == old_mapping.map_end()
+                && self.resize_mapping(old_addr, old_size, new_size).is_ok()
+            {
+                return Ok(old_addr);
+            }
+            if !may_move {
+                return_errno_with_message!(
+                    Errno::ENOMEM,
+                    "the mapping cannot be expanded in place"
+                );
+            }
+        }
+
+        let new_mapping = old_mapping.clone_remapped(old_range.clone(), new_size)?;
+        // Take out the pages that are mapped in the part of the old range to keep.
+        let kept_range = old_addr..old_addr + old_size.min(new_size);
+        let pages = self
+            .vm_space
+            .cursor(&kept_range)?
+            .filter_map(|item| match item {
+                VmItem::Mapped { va, frame, prop } => Some((va - old_addr, frame, prop)),
+                VmItem::NotMapped { .. } => None,
+            })
+            .collect::<Vec<_>>();
+
+        let new_addr = self.allocate_free_region_for_mapping(
+            new_size,
+            new_addr,
+            PAGE_SIZE,
+            new_addr.is_some(),
+        )?;
+        let new_range = new_addr..new_addr + new_size;
+        new_mapping.relocate(new_addr);
+        self.destroy(old_range)?;
+
+        let mut cursor = self.vm_space.cursor_mut(&new_range)?;
+        for (offset, frame, prop) in pages {
+            cursor.jump(new_addr + offset);
+            cursor.map(frame, prop);
+        }
+        drop(cursor);
+
+        self.add_mapping(new_mapping);
+        Ok(new_addr)
+    }
+
     fn check_destroy_range(&self, range: &Range<usize>) -> Result<()> {
         debug_assert!(range.start % PAGE_SIZE == 0);
         debug_assert!(range.end % PAGE_SIZE == 0);
@@ -501,7 +774,7 @@ impl Vmar_ {
         let child_vmar_inner = VmarInner {
             is_destroyed: false,
             child_vmar_s: BTreeMap::new(),
-            vm_mappings: BTreeMap::new(),
+            vm_mappings: IntervalMap::new(),
             free_regions: child_regions,
         };
         let child_vmar_ = Vmar_::new(
@@ -536,8 +809,7 @@ impl Vmar_ {
         if !can_overwrite
             && inner
                 .vm_mappings
-                .find(&mapping_range)
-                .into_iter()
+                .overlapping(&mapping_range)
                 .next()
                 .is_some()
         {
@@ -557,10 +829,7 @@ impl Vmar_ {
 
     /// Maps a `VmMapping` to this VMAR.
     fn add_mapping(&self, mapping: Arc<VmMapping>) {
-        self.inner
-            .lock()
-            .vm_mappings
-            .insert(mapping.map_to_addr(), mapping);
+        self.inner.lock().vm_mappings.insert(mapping);
     }
 
     fn allocate_free_region_for_mapping(
@@ -616,23 +885,60 @@ impl Vmar_ {
         }
     }
 
+    /// Allocates a free region for a mapping of `map_size` bytes, which is
+    /// followed by a guard page.
+    ///
+    /// The guard page is allocated along with the mapping but is never mapped.
+    /// If no free region can hold both, the mapping is placed without the guard
+    /// page, because many small mappings can run out of space due to the guard
+    /// pages. So this method returns the address of the mapping and whether the
+    /// guard page is allocated.
+    fn allocate_free_region_with_guard_page(
+        &self,
+        map_size: usize,
+        align: usize,
+    ) -> Result<(Vaddr, bool)> {
+        if let Ok(offset) =
+            self.allocate_free_region_for_mapping(map_size + PAGE_SIZE, None, align, false)
+        {
+            return Ok((offset, true));
+        }
+
+        debug!("no room for the guard page, map_size = 0x{:x}", map_size);
+        let offset = self.allocate_free_region_for_mapping(map_size, None, align, false)?;
+        Ok((offset, false))
+    }
+
     fn trim_existing_mappings(&self, trim_range: Range<usize>) -> Result<()> {
         let mut inner = self.inner.lock();
+        // The guard pages in the range are overwritten as well.
+        for vm_mapping in inner.guard_page_owners(&trim_range) {
+            vm_mapping.release_guard_page();
+        }
+
         let mut mappings_to_remove = LinkedList::new();
         let mut mappings_to_append = LinkedList::new();
-        for vm_mapping in inner.vm_mappings.find(&trim_range) {
-            vm_mapping.trim_mapping(
+        let mut released_guard_pages = Vec::new();
+        for vm_mapping in inner.vm_mappings.overlapping(&trim_range) {
+            let released_guard_page = vm_mapping.trim_mapping(
                 &trim_range,
                 &mut mappings_to_remove,
                 &mut mappings_to_append,
             )?;
+            released_guard_pages.extend(released_guard_page);
+        }
+
+        // The remaining guard pages are right after the range, so they become free.
+        for guard_page in released_guard_pages {
+            let free_region = FreeRegion::new(guard_page);
+            inner.free_regions.insert(free_region.start(), free_region);
         }
 
         for map_addr in mappings_to_remove {
             inner.vm_mappings.remove(&map_addr);
         }
-        for (map_addr, mapping) in mappings_to_append {
-            inner.vm_mappings.insert(map_addr, mapping);
+        for (_, mapping) in mappings_to_append {
+            inner.vm_mappings.insert(mapping);
         }
         Ok(())
     }
@@ -682,13 +988,9 @@ impl Vmar_ {
         }
 
         // Clone mappings.
-        for (vm_mapping_base, vm_mapping) in &inner.vm_mappings {
+        for vm_mapping in inner.vm_mappings.iter() {
             let new_mapping = Arc::new(vm_mapping.new_fork(&new_vmar_)?);
-            new_vmar_
-                .inner
-                .lock()
-                .vm_mappings
-                .insert(*vm_mapping_base, new_mapping);
+            new_vmar_.inner.lock().vm_mappings.insert(new_mapping);
         }
         Ok(new_vmar_)
     }
@@ -136,14 +136,20 @@ impl<R> VmarChildOptions<R> {
 #[cfg(ktest)]
 mod test {
     use aster_rights::Full;
-    use ostd::{mm::VmIo, prelude::*};
+    use ostd::{
+        mm::{Frame, VmIo, VmItem},
+        prelude::*,
+    };
 
     use super::*;
-    use crate::vm::{
-        page_fault_handler::PageFaultHandler,
-        perms::VmPerms,
-        vmar::ROOT_VMAR_CAP_ADDR,
-        vmo::{VmoOptions, VmoRightsOp},
+    use crate::{
+        error::Errno,
+        vm::{
+            page_fault_handler::PageFaultHandler,
+            perms::VmPerms,
+            vmar::ROOT_VMAR_CAP_ADDR,
+            vmo::{Vmo, VmoOptions, VmoRightsOp},
+        },
     };
 
     #[ktest]
@@ -206,4 +212,256 @@ mod test {
             .unwrap();
         root_vmar.handle_page_fault(OFFSET, true, false).unwrap();
     }
+
+    fn mapped_frame(vmar: &Vmar<Full>, addr: Vaddr) -> Frame {
+        let mut cursor = vmar.vm_space().cursor(&(addr..addr + PAGE_SIZE)).unwrap();
+        let VmItem::Mapped { frame, .. } = cursor.query().unwrap() else {
+            panic!("the page is not mapped");
+        };
+        frame
+    }
+
+    fn map_in_new_root(vmo: &Vmo, offset: usize, is_shared: bool) -> Vmar<Full> {
+        let vmar = Vmar::<Full>::new_root();
+        vmar.new_map(PAGE_SIZE, VmPerms::READ | VmPerms::WRITE)
+            .unwrap()
+            .vmo(vmo.dup().unwrap())
+            .offset(offset)
+            .is_shared(is_shared)
+            .build()
+            .unwrap();
+        vmar
+    }
+
+    #[ktest]
+    fn shared_mapping_in_two_vmars() {
+        const OFFSET: usize = 0x1000_0000;
+        let vmo = VmoOptions::<Full>::new(PAGE_SIZE).alloc().unwrap().to_dyn();
+        let vmar_a = map_in_new_root(&vmo, OFFSET, true);
+        let vmar_b = map_in_new_root(&vmo, OFFSET, true);
+
+        vmar_a.handle_page_fault(OFFSET, true, true).unwrap();
+        vmar_b.handle_page_fault(OFFSET, true, true).unwrap();
+
+        // Both address spaces map the same frame, so a write through one
+        // of them is visible through the other.
+        let frame_a = mapped_frame(&vmar_a, OFFSET);
+        let frame_b = mapped_frame(&vmar_b, OFFSET);
+        assert_eq!(frame_a.start_paddr(), frame_b.start_paddr());
+        frame_a.write_val(0, &0xdead_beef_u32).unwrap();
+        assert_eq!(frame_b.read_val::<u32>(0).unwrap(), 0xdead_beef);
+        assert_eq!(vmo.read_val::<u32>(0).unwrap(), 0xdead_beef);
+
+        // The frame outlives the address space that unmaps it first.
+        drop(frame_a);
+        vmar_a.clear().unwrap();
+        assert_eq!(
+            mapped_frame(&vmar_b, OFFSET).read_val::<u32>(0).unwrap(),
+            0xdead_beef
+        );
+    }
+
+    #[ktest]
+    fn private_mapping_copies_on_write() {
+        const OFFSET: usize = 0x1000_0000;
+        let vmo = VmoOptions::<Full>::new(PAGE_SIZE).alloc().unwrap().to_dyn();
+        vmo.write_val(0, &1u32).unwrap();
+        let vmar = map_in_new_root(&vmo, OFFSET, false);
+
+        vmar.handle_page_fault(OFFSET, true, true).unwrap();
+        let frame = mapped_frame(&vmar, OFFSET);
+        frame.write_val(0, &2u32).unwrap();
+        assert_eq!(vmo.read_val::<u32>(0).unwrap(), 1);
+    }
+
+    #[ktest]
+    fn anonymous_reads_share_zero_frame() {
+        const OFFSET: usize = 0x1000_0000;
+        const NR_PAGES: usize = 1024;
+        let vmar = Vmar::<Full>::new_root();
+        vmar.new_map(NR_PAGES * PAGE_SIZE, VmPerms::READ | VmPerms::WRITE)
+            .unwrap()
+            .offset(OFFSET)
+            .build()
+            .unwrap();
+
+        let mem_before = ostd::mm::stat::mem_available();
+        for i in 0..NR_PAGES {
+            vmar.handle_page_fault(OFFSET + i * PAGE_SIZE, true, false)
+                .unwrap();
+        }
+        let mem_after = ostd::mm::stat::mem_available();
+
+        // All the pages map the zero frame. Only the page table pages are allocated.
+        let zero_paddr = mapped_frame(&vmar, OFFSET).start_paddr();
+        for i in 0..NR_PAGES {
+            assert_eq!(
+                mapped_frame(&vmar, OFFSET + i * PAGE_SIZE).start_paddr(),
+                zero_paddr
+            );
+        }
+        assert!(mem_before.saturating_sub(mem_after) < NR_PAGES / 16 * PAGE_SIZE);
+        // The zero frame is not counted in the RSS.
+        assert_eq!(vmar.vm_space().rss().total(), 0);
+
+        // A write access replaces the zero frame with a private one.
+        vmar.handle_page_fault(OFFSET, false, true).unwrap();
+        let frame = mapped_frame(&vmar, OFFSET);
+        assert_ne!(frame.start_paddr(), zero_paddr);
+        assert_eq!(vmar.vm_space().rss().anon, 1);
+        assert_eq!(frame.read_val::<u64>(0).unwrap(), 0);
+        frame.write_val(0, &1u64).unwrap();
+        assert_eq!(
+            mapped_frame(&vmar, OFFSET + PAGE_SIZE)
+                .read_val::<u64>(0)
+                .unwrap(),
+            0
+        );
+    }
+
+    fn is_mapped(vmar: &Vmar<Full>, addr: Vaddr) -> bool {
+        let mut cursor = vmar.vm_space().cursor(&(addr..addr + PAGE_SIZE)).unwrap();
+        matches!(cursor.query().unwrap(), VmItem::Mapped { .. })
+    }
+
+    #[ktest]
+    fn lock_pins_pages() {
+        const OFFSET: usize = 0x1000_0000;
+        const NR_PAGES: usize = 4;
+        const END: usize = OFFSET + NR_PAGES * PAGE_SIZE;
+        let vmo = VmoOptions::<Full>::new(NR_PAGES * PAGE_SIZE)
+            .alloc()
+            .unwrap()
+            .to_dyn();
+        let vmar = Vmar::<Full>::new_root();
+        vmar.new_map(NR_PAGES * PAGE_SIZE, VmPerms::READ | VmPerms::WRITE)
+            .unwrap()
+            .vmo(vmo.dup().unwrap())
+            .offset(OFFSET)
+            .is_shared(true)
+            .build()
+            .unwrap();
+
+        // The range to lock must be fully mapped.
+        let err = vmar.lock(OFFSET..END + PAGE_SIZE).unwrap_err();
+        assert_eq!(err.error(), Errno::ENOMEM);
+        assert_eq!(vmar.locked_size(&(OFFSET..END)), 0);
+
+        // The locked pages are populated at once.
+        let locked_range = OFFSET..OFFSET + 2 * PAGE_SIZE;
+        vmar.lock(locked_range.clone()).unwrap();
+        assert_eq!(vmar.locked_size(&(OFFSET..END)), 2 * PAGE_SIZE);
+        assert!(is_mapped(&vmar, OFFSET) && is_mapped(&vmar, OFFSET + PAGE_SIZE));
+        assert_eq!(vmar.vm_space().rss().total(), 2);
+
+        // The reclaim skips the locked pages.
+        for i in 2..NR_PAGES {
+            vmar.handle_page_fault(OFFSET + i * PAGE_SIZE, true, false)
+                .unwrap();
+        }
+        assert_eq!(vmar.vm_space().rss().total(), NR_PAGES);
+        vmar.reclaim(OFFSET..END).unwrap();
+        assert!(is_mapped(&vmar, OFFSET) && is_mapped(&vmar, OFFSET + PAGE_SIZE));
+        assert!(!is_mapped(&vmar, OFFSET + 2 * PAGE_SIZE));
+        assert_eq!(vmar.vm_space().rss().total(), 2);
+
+        // The unlocked pages can be reclaimed.
+        vmar.unlock(locked_range).unwrap();
+        assert_eq!(vmar.locked_size(&(OFFSET..END)), 0);
+        vmar.reclaim(OFFSET..END).unwrap();
+        assert_eq!(vmar.vm_space().rss().total(), 0);
+    }
+
+    #[ktest]
+    fn guard_page_catches_overrun() {
+        const SIZE: usize = 3 * PAGE_SIZE;
+        let vmar = Vmar::<Full>::new_root();
+        let perms = VmPerms::READ | VmPerms::WRITE;
+        let addr = vmar
+            .new_map(SIZE, perms)
+            .unwrap()
+            .guard_page(true)
+            .build()
+            .unwrap();
+
+        // The next mapping is not placed right after the first one.
+        let next_addr = vmar.new_map(SIZE, perms).unwrap().build().unwrap();
+        assert_eq!(next_addr, addr + SIZE + PAGE_SIZE);
+
+        vmar.handle_page_fault(addr + SIZE - 1, true, true).unwrap();
+        // A one-byte overrun hits the guard page.
+        assert!(vmar.handle_page_fault(addr + SIZE, true, true).is_err());
+        assert!(vmar.handle_page_fault(addr + SIZE, true, false).is_err());
+    }
+
+    #[ktest]
+    fn guard_page_released_with_mapping() {
+        const SIZE: usize = 3 * PAGE_SIZE;
+        let vmar = Vmar::<Full>::new_root();
+        let perms = VmPerms::READ | VmPerms::WRITE;
+        let new_guarded_map = || {
+            vmar.new_map(SIZE, perms)
+                .unwrap()
+                .guard_page(true)
+                .build()
+                .unwrap()
+        };
+
+        // The guard page cannot be protected or locked since it is not mapped.
+        let addr = new_guarded_map();
+        assert!(vmar.protect(perms, addr..addr + SIZE + PAGE_SIZE).is_err());
+        assert!(vmar.lock(addr..addr + SIZE + PAGE_SIZE).is_err());
+
+        // Unmapping the end of the mapping releases the guard page.
+        vmar.destroy(addr + SIZE - PAGE_SIZE..addr + SIZE).unwrap();
+        let next_addr = vmar
+            .new_map(2 * PAGE_SIZE, perms)
+            .unwrap()
+            .offset(addr + SIZE - PAGE_SIZE)
+            .build()
+            .unwrap();
+        assert_eq!(next_addr, addr + SIZE - PAGE_SIZE);
+        vmar.destroy(addr..addr + SIZE + PAGE_SIZE).unwrap();
+
+        // Unmapping the start of the mapping keeps the guard page.
+        let addr = new_guarded_map();
+        vmar.destroy(addr..addr + PAGE_SIZE).unwrap();
+        assert!(!vmar.is_range_free(&(addr + SIZE..addr + SIZE + PAGE_SIZE)));
+        vmar.destroy(addr + PAGE_SIZE..addr + SIZE).unwrap();
+        assert!(vmar.is_range_free(&(addr..addr + SIZE + PAGE_SIZE)));
+
+        // Growing the mapping in place takes over the guard page.
+        let addr = new_guarded_map();
+        let new_addr = vmar
+            .remap(addr, SIZE, SIZE + PAGE_SIZE, None, false)
+            .unwrap();
+        assert_eq!(new_addr, addr);
+        vmar.handle_page_fault(addr + SIZE, true, true).unwrap();
+        vmar.destroy(addr..addr + SIZE + PAGE_SIZE).unwrap();
+
+        // Moving the mapping releases the guard page at the old place.
+        let addr = new_guarded_map();
+        let new_addr = vmar
+            .remap(addr, SIZE, SIZE, Some(addr + 4 * SIZE), false)
+            .unwrap();
+        assert_eq!(new_addr, addr + 4 * SIZE);
+        assert!(vmar.is_range_free(&(addr..addr + SIZE + PAGE_SIZE)));
+    }
+
+    #[ktest]
+    fn guard_page_without_room() {
+        let root_vmar = Vmar::<Full>::new_root();
+        let child_vmar = VmarChildOptions::new(root_vmar.dup().unwrap(), 2 * PAGE_SIZE)
+            .alloc()
+            .unwrap();
+
+        // There is no room for the guard page, so the mapping is created without it.
+        let addr = child_vmar
+            .new_map(2 * PAGE_SIZE, VmPerms::READ | VmPerms::WRITE)
+            .unwrap()
+            .guard_page(true)
+            .build()
+            .unwrap();
+        assert_eq!(addr, child_vmar.base());
+    }
 }
@@ -8,7 +8,8 @@ use core::ops::Range;
 use align_ext::AlignExt;
 use aster_rights::Rights;
 use ostd::mm::{
-    vm_space::VmItem, CachePolicy, Frame, FrameAllocOptions, PageFlags, PageProperty, VmSpace,
+    vm_space::VmItem, CachePolicy, Frame, FrameAllocOptions, PageFlags, PageProperty, RssType,
+    VmSpace,
 };
 
 use super::{interval::Interval, is_intersected, Vmar, Vmar_};
@@ -16,7 +17,7 @@ use crate::{
     prelude::*,
     vm::{
         perms::VmPerms,
-        util::duplicate_frame,
+        util::{duplicate_frame, is_zero_frame, zero_frame},
         vmo::{Vmo, VmoRightsOp},
     },
 };
@@ -25,6 +26,8 @@ use crate::{
 ///
 /// A `VmMapping` can bind with a `Vmo` which can provide physical pages for mapping.
 /// Otherwise, it must be an anonymous mapping and will map any empty physical page.
+/// Until a page of an anonymous mapping is written, reading it maps the shared
+/// [`zero_frame`] read-only, so such pages do not consume private memory.
 /// A `VmMapping` binding with a `Vmo` is called VMO-backed mapping. Generally, a VMO-backed
 /// mapping is a file-backed mapping. Yet there are also some situations where specific pages
 /// that are not in a file need to be mapped. e.g:
@@ -75,6 +78,15 @@ struct VmMappingInner {
     /// The permissions of pages in the mapping.
     /// All pages within the same `VmMapping` have the same permissions.
     perms: VmPerms,
+    /// Whether the pages in the mapping are locked in memory, i.e., they are populated
+    /// when locked and are never reclaimed.
+    is_locked: bool,
+    /// Whether the mapping is followed by an inaccessible guard page.
+    ///
+    /// The guard page is neither mapped nor in the free regions of the parent VMAR, so
+    /// nothing else can be placed there. It is released when the end of the mapping is
+    /// unmapped or remapped.
+    has_guard_page: bool,
 }
 
 impl Interval<usize> for Arc<VmMapping> {
@@ -96,10 +108,16 @@ impl VmMapping {
             align,
             can_overwrite,
             is_shared,
+            has_guard_page,
         } = option;
         let Vmar(parent_vmar, _) = parent;
-        let map_to_addr =
-            parent_vmar.allocate_free_region_for_mapping(size, offset, align, can_overwrite)?;
+        let (map_to_addr, has_guard_page) = if has_guard_page && offset.is_none() {
+            parent_vmar.allocate_free_region_with_guard_page(size, align)?
+        } else {
+            let map_to_addr =
+                parent_vmar.allocate_free_region_for_mapping(size, offset, align, can_overwrite)?;
+            (map_to_addr, false)
+        };
         trace!(
             "build mapping, map_range = 0x{:x}- 0x{:x}",
             map_to_addr,
@@ -123,6 +141,8 @@ impl VmMapping {
             map_to_addr,
             is_destroyed: false,
             perms,
+            is_locked: false,
+            has_guard_page,
         };
 
         Ok(Self {
@@ -138,21 +158,39 @@ impl VmMapping {
     ///
     /// Note: Since such new mappings will intersect with the current mapping,
     /// making sure that when adding the new mapping into a Vmar, the current mapping in the Vmar will be removed.
-    fn clone_partial(
+    fn clone_partial(&self, range: Range<usize>) -> Result<Arc<VmMapping>> {
+        let partial_mapping = Arc::new(self.try_clone()?);
+        // Adjust the mapping range.
+        partial_mapping.inner.lock().shrink_to(range);
+        Ok(partial_mapping)
+    }
+
+    /// Builds a new `VmMapping` that maps the part `range` of current `VmMapping`
+    /// with a new size. The new mapping starts at the same address as `range`
+    /// until it is relocated by [`VmMapping::relocate`].
+    ///
+    /// Note: The pages mapped in the current mapping are not moved to the new mapping.
+    pub(super) fn clone_remapped(
         &self,
         range: Range<usize>,
-        new_perms: Option<VmPerms>,
+        new_size: usize,
     ) -> Result<Arc<VmMapping>> {
-        let partial_mapping = Arc::new(self.try_clone()?);
-        // Adjust the mapping range and the permission.
+        let remapped_mapping = Arc::new(self.try_clone()?);
         {
-            let mut inner = partial_mapping.inner.lock();
+            let mut inner = remapped_mapping.inner.lock();
             inner.shrink_to(range);
-            if let Some(perms) = new_perms {
-                inner.perms = perms;
-            }
+            inner.map_size = new_size;
+            // The guard page stays with the original mapping.
+            inner.has_guard_page = false;
         }
-        Ok(partial_mapping)
+        Ok(remapped_mapping)
+    }
+
+    /// Relocates the mapping to start at `new_addr`.
+    ///
+    /// This should only be called on a mapping that has not been added to a Vmar.
+    pub(super) fn relocate(&self, new_addr: Vaddr) {
+        self.inner.lock().map_to_addr = new_addr;
     }
 
     pub fn vmo(&self) -> Option<&MappedVmo> {
@@ -161,12 +199,18 @@ impl VmMapping {
 
     /// Adds a new committed page and map it to vmspace. If copy on write is set, it's allowed to unmap the page at the same address.
     /// FIXME: This implementation based on the truth that we map one page at a time. If multiple pages are mapped together, this implementation may have problems
-    fn map_one_page(&self, map_addr: usize, frame: Frame, is_readonly: bool) -> Result<()> {
+    fn map_one_page(
+        &self,
+        map_addr: usize,
+        frame: Frame,
+        is_readonly: bool,
+        rss_type: Option<RssType>,
+    ) -> Result<()> {
         let parent = self.parent.upgrade().unwrap();
         let vm_space = parent.vm_space();
         self.inner
             .lock()
-            .map_one_page(vm_space, map_addr, frame, is_readonly)
+            .map_one_page(vm_space, map_addr, frame, is_readonly, rss_type)
     }
 
     /// Returns the mapping's start address.
@@ -201,11 +245,36 @@ impl VmMapping {
         self.inner.lock().is_destroyed
     }
 
+    /// Returns whether the pages in the mapping are locked in memory.
+    pub fn is_locked(&self) -> bool {
+        self.inner.lock().is_locked
+    }
+
     /// Returns whether the mapping is a shared mapping.
     pub fn is_shared(&self) -> bool {
         self.is_shared
     }
 
+    /// Returns whether the mapping is followed by a guard page.
+    pub fn has_guard_page(&self) -> bool {
+        self.inner.lock().has_guard_page
+    }
+
+    /// Detaches the guard page from the mapping.
+    ///
+    /// This method returns the range of the guard page if there is one. The caller
+    /// is responsible for returning the range to the free regions of the parent VMAR
+    /// or reusing it.
+    pub(super) fn release_guard_page(&self) -> Option<Range<Vaddr>> {
+        let mut inner = self.inner.lock();
+        if !inner.has_guard_page {
+            return None;
+        }
+        inner.has_guard_page = false;
+        let map_end = inner.map_to_addr + inner.map_size;
+        Some(map_end..map_end + PAGE_SIZE)
+    }
+
     pub fn enlarge(&self, extra_size: usize) {
         self.inner.lock().map_size += extra_size;
     }
@@ -221,6 +290,12 @@ impl VmMapping {
         let page_aligned_addr = page_fault_addr.align_down(PAGE_SIZE);
 
         if write && !not_present {
+            if self.is_shared {
+                // The page of a shared mapping backed by a pager is mapped read-only
+                // until the first write, which is where the page becomes dirty.
+                self.mark_page_dirty(page_aligned_addr)?;
+            }
+
             // Perform COW at page table.
             let root_vmar = self.parent.upgrade().unwrap();
             let mut cursor = root_vmar
@@ -238,51 +313,95 @@ impl VmMapping {
             if self.is_shared {
                 cursor.protect(PAGE_SIZE, |p| p.flags |= PageFlags::W);
             } else {
-                let new_frame = duplicate_frame(&frame)?;
+                let new_frame = if is_zero_frame(&frame) {
+                    FrameAllocOptions::new(1).alloc_single()?
+                } else {
+                    duplicate_frame(&frame)?
+                };
                 prop.flags |= PageFlags::W;
+                // The private copy is anonymous, even if the original page is file-backed
+                // or is the zero frame, which is not counted in the RSS.
+                prop.set_rss_type(Some(RssType::Anon));
                 cursor.map(new_frame, prop);
             }
             return Ok(());
         }
 
-        let (frame, is_readonly) = self.prepare_page(page_fault_addr, write)?;
+        let (frame, is_readonly, rss_type) = self.prepare_page(page_fault_addr, write)?;
 
-        self.map_one_page(page_aligned_addr, frame, is_readonly)
+        self.map_one_page(page_aligned_addr, frame, is_readonly, rss_type)
     }
 
-    fn prepare_page(&self, page_fault_addr: Vaddr, write: bool) -> Result<(Frame, bool)> {
+    /// Prepares the page to map at `page_fault_addr`.
+    ///
+    /// Returns the frame, whether it must be mapped read-only, and its type in the RSS
+    /// accounting.
+    fn prepare_page(
+        &self,
+        page_fault_addr: Vaddr,
+        write: bool,
+    ) -> Result<(Frame, bool, Option<RssType>)> {
         let mut is_readonly = false;
         let Some(vmo) = &self.vmo else {
-            return Ok((FrameAllocOptions::new(1).alloc_single()?, is_readonly));
+            // Read access to anonymous mapping. Maps the zero frame readonly and
+            // defers the allocation to the first write access. The zero frame is
+            // shared by everyone, so it is not counted in the RSS.
+            if !write {
+                return Ok((zero_frame()?, true, None));
+            }
+            let frame = FrameAllocOptions::new(1).alloc_single()?;
+            return Ok((frame, is_readonly, Some(RssType::Anon)));
+        };
+        // The pages of a VMO without a pager (e.g., shared anonymous memory) are
+        // counted as anonymous pages.
+        let rss_type = if vmo.has_pager() {
+            Some(RssType::File)
+        } else {
+            Some(RssType::Anon)
         };
 
         let vmo_offset = self.vmo_offset().unwrap() + page_fault_addr - self.map_to_addr();
         let page_idx = vmo_offset / PAGE_SIZE;
-        let Ok(page) = vmo.get_committed_frame(page_idx) else {
-            if !self.is_shared {
-                // The page index is outside the VMO. This is only allowed in private mapping.
-                return Ok((FrameAllocOptions::new(1).alloc_single()?, is_readonly));
-            } else {
-                return_errno_with_message!(
-                    Errno::EFAULT,
-                    "could not find a corresponding physical page"
-                );
-            }
-        };
+        if vmo_offset >= vmo.size() {
+            // Like Linux, an access beyond the end of the mapped object (e.g., the EOF of
+            // a file) is a bus error rather than a segmentation fault.
+            return_errno_with_message!(Errno::EIO, "the page is beyond the end of the VMO");
+        }
+        let page = vmo.get_committed_frame(page_idx)?;
 
         if !self.is_shared && write {
             // Write access to private VMO-backed mapping. Performs COW directly.
-            Ok((duplicate_frame(&page)?, is_readonly))
+            Ok((duplicate_frame(&page)?, is_readonly, Some(RssType::Anon)))
+        } else if self.is_shared {
+            // Operations to shared mapping. If the VMO is backed by a pager, the pager
+            // has to know which pages are modified. So a read access maps the page
+            // readonly and the next write access will trigger a page fault, which marks
+            // the page dirty.
+            if write {
+                vmo.mark_page_dirty(page_idx)?;
+            } else {
+                is_readonly = vmo.has_pager();
+            }
+            Ok((page, is_readonly, rss_type))
         } else {
-            // Operations to shared mapping or read access to private VMO-backed mapping.
+            // Read access to private VMO-backed mapping.
             // If read access to private VMO-backed mapping triggers a page fault,
             // the map should be readonly. If user next tries to write to the frame,
             // another page fault will be triggered which will performs a COW (Copy-On-Write).
-            is_readonly = !self.is_shared;
-            Ok((page, is_readonly))
+            is_readonly = true;
+            Ok((page, is_readonly, rss_type))
         }
     }
 
+    /// Marks the page mapped at `page_aligned_addr` dirty in the mapped VMO.
+    fn mark_page_dirty(&self, page_aligned_addr: Vaddr) -> Result<()> {
+        let Some(vmo) = &self.vmo else {
+            return Ok(());
+        };
+        let vmo_offset = self.vmo_offset().unwrap() + page_aligned_addr - self.map_to_addr();
+        vmo.mark_page_dirty(vmo_offset / PAGE_SIZE)
+    }
+
     /// Protects a specified range of pages in the mapping to the target perms.
     /// This `VmMapping` will split to maintain its property.
     ///
@@ -296,7 +415,7 @@ impl VmMapping {
         }
 
         // Protect permission for the perm in the VmMapping.
-        self.protect_with_subdivision(&range, new_perms)?;
+        self.modify_with_subdivision(&range, |inner| inner.perms = new_perms)?;
         // Protect permission in the VmSpace.
         let vmar = self.parent.upgrade().unwrap();
         let vm_space = vmar.vm_space();
@@ -305,8 +424,91 @@ impl VmMapping {
         Ok(())
     }
 
+    /// Locks or unlocks the pages of a specified range in the mapping.
+    /// This `VmMapping` will split to maintain its property.
+    ///
+    /// Like `protect()`, this method should not be called during the direct iteration
+    /// of the `vm_mappings` in the vmar.
+    pub(super) fn set_locked(&self, range: Range<usize>, is_locked: bool) -> Result<()> {
+        if self.is_locked() == is_locked {
+            return Ok(());
+        }
+
+        self.modify_with_subdivision(&range, |inner| inner.is_locked = is_locked)
+    }
+
+    /// Populates the pages of a specified range in the mapping as if they were accessed.
+    ///
+    /// The pages of a writable private mapping are populated as if they were written,
+    /// so that no copy-on-write will happen later. The pages of an inaccessible
+    /// mapping or beyond the end of the mapped VMO are left unpopulated.
+    pub(super) fn populate(&self, mut range: Range<usize>) -> Result<()> {
+        let perms = self.inner.lock().perms;
+        if !perms.contains(VmPerms::READ) {
+            return Ok(());
+        }
+        let write = perms.contains(VmPerms::WRITE) && !self.is_shared;
+
+        if let Some(vmo) = &self.vmo {
+            let vmo_size = vmo.size().saturating_sub(self.vmo_offset().unwrap());
+            let vmo_end = (self.map_to_addr() + vmo_size).align_up(PAGE_SIZE);
+            range.end = range.end.min(vmo_end);
+        }
+
+        let vmar = self.parent.upgrade().unwrap();
+        for page_addr in range.step_by(PAGE_SIZE) {
+            let item = vmar
+                .vm_space()
+                .cursor(&(page_addr..page_addr + PAGE_SIZE))?
+                .query()?;
+            match item {
+                VmItem::NotMapped { .. } => self.handle_page_fault(page_addr, true, write)?,
+                VmItem::Mapped { prop, .. } if write && !prop.flags.contains(PageFlags::W) => {
+                    self.handle_page_fault(page_addr, false, write)?
+                }
+                VmItem::Mapped { .. } => (),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Reclaims the pages of a specified range in the mapping that can be faulted in
+    /// again from the mapped VMO.
+    ///
+    /// The pages of a locked mapping are never reclaimed. Neither are the anonymous
+    /// pages, including the private copies of the VMO pages, since there is no swap
+    /// space to keep their contents.
+    pub(super) fn reclaim(&self, range: Range<usize>) -> Result<()> {
+        if self.vmo.is_none() || self.is_locked() {
+            return Ok(());
+        }
+
+        let vmar = self.parent.upgrade().unwrap();
+        let mut cursor = vmar.vm_space().cursor_mut(&range)?;
+        let mut va = range.start;
+        while va < range.end {
+            cursor.jump(va);
+            va = match cursor.query()? {
+                VmItem::Mapped { va, prop, .. } => {
+                    // The pages of a shared mapping are kept in the VMO, while a private
+                    // mapping maps the VMO pages only before they are copied.
+                    if self.is_shared || prop.rss_type() == Some(RssType::File) {
+                        cursor.unmap(PAGE_SIZE);
+                    }
+                    va + PAGE_SIZE
+                }
+                VmItem::NotMapped { va, len } => va.align_down(len) + len,
+            };
+        }
+
+        Ok(())
+    }
+
     pub(super) fn new_fork(&self, new_parent: &Arc<Vmar_>) -> Result<VmMapping> {
-        let new_inner = self.inner.lock().clone();
+        let mut new_inner = self.inner.lock().clone();
+        // Like Linux, the memory locks are not inherited by the child.
+        new_inner.is_locked = false;
 
         Ok(VmMapping {
             inner: Mutex::new(new_inner),
@@ -320,11 +522,13 @@ impl VmMapping {
         self.map_to_addr()..self.map_to_addr() + self.map_size()
     }
 
-    /// Protects the current `VmMapping` to enforce new permissions within a specified range.
+    /// Modifies the current `VmMapping` with `op` to enforce new properties (e.g., the
+    /// permissions) within a specified range.
     ///
     /// Due to the property of `VmMapping`, this operation may require subdividing the current
-    /// `VmMapping`. In this condition, it will generate a new `VmMapping` with the specified `perm` to protect the
-    /// target range, as well as additional `VmMappings` to preserve the mappings in the remaining ranges.
+    /// `VmMapping`. In this condition, it will generate a new `VmMapping` with the new properties to
+    /// cover the target range, as well as additional `VmMappings` to preserve the mappings in the
+    /// remaining ranges.
     ///
     /// There are four conditions:
     /// 1. |--------old perm--------| -> |-old-| + |------new------|
@@ -332,50 +536,45 @@ impl VmMapping {
     /// 3. |--------old perm--------| -> |-old-| + |-new-| + |-old-|
     /// 4. |--------old perm--------| -> |---------new perm--------|
     ///
-    /// Generally, this function is only used in `protect()` method.
+    /// Generally, this function is only used in `protect()` and `set_locked()` methods.
     /// This method modifies the parent `Vmar` in the end if subdividing is required.
     /// It removes current mapping and add splitted mapping to the Vmar.
-    fn protect_with_subdivision(
+    fn modify_with_subdivision(
         &self,
         intersect_range: &Range<usize>,
-        perms: VmPerms,
+        op: impl FnOnce(&mut VmMappingInner),
     ) -> Result<()> {
         let mut additional_mappings = Vec::new();
         let range = self.range();
         // Condition 4, the `additional_mappings` will be empty.
         if range.start == intersect_range.start && range.end == intersect_range.end {
-            self.inner.lock().perms = perms;
+            op(&mut self.inner.lock());
             return Ok(());
         }
         // Condition 1 or 3, which needs an additional new VmMapping with range (range.start..intersect_range.start)
         if range.start < intersect_range.start {
-            let additional_left_mapping =
-                self.clone_partial(range.start..intersect_range.start, None)?;
+            let additional_left_mapping = self.clone_partial(range.start..intersect_range.start)?;
             additional_mappings.push(additional_left_mapping);
         }
         // Condition 2 or 3, which needs an additional new VmMapping with range (intersect_range.end..range.end).
         if range.end > intersect_range.end {
-            let additional_right_mapping =
-                self.clone_partial(intersect_range.end..range.end, None)?;
+            let additional_right_mapping = self.clone_partial(intersect_range.end..range.end)?;
             additional_mappings.push(additional_right_mapping);
         }
-        // The protected VmMapping must exist and its range is `intersect_range`.
-        let protected_mapping = self.clone_partial(intersect_range.clone(), Some(perms))?;
+        // The modified VmMapping must exist and its range is `intersect_range`.
+        let modified_mapping = self.clone_partial(intersect_range.clone())?;
+        op(&mut modified_mapping.inner.lock());
 
         // Begin to modify the `Vmar`.
         let vmar = self.parent.upgrade().unwrap();
         let mut vmar_inner = vmar.inner.lock();
         // Remove the original mapping.
         vmar_inner.vm_mappings.remove(&self.map_to_addr());
-        // Add protected mappings to the vmar.
-        vmar_inner
-            .vm_mappings
-            .insert(protected_mapping.map_to_addr(), protected_mapping);
+        // Add the modified mapping to the vmar.
+        vmar_inner.vm_mappings.insert(modified_mapping);
         // Add additional mappings to the vmar.
         for mapping in additional_mappings {
-            vmar_inner
-                .vm_mappings
-                .insert(mapping.map_to_addr(), mapping);
+            vmar_inner.vm_mappings.insert(mapping);
         }
 
         Ok(())
@@ -389,23 +588,31 @@ impl VmMapping {
     ///     If we create a mapping with a new map addr, we will add it to mappings_to_append.
     ///     If the mapping with map addr does not exist ever, the map addr will be added to mappings_to_remove.
     ///     Otherwise, we will directly modify self.
+    ///
+    /// If the end of the mapping is trimmed, its guard page is released and the range of the
+    /// guard page is returned. See [`VmMapping::release_guard_page`].
     pub fn trim_mapping(
         self: &Arc<Self>,
         trim_range: &Range<usize>,
         mappings_to_remove: &mut LinkedList<Vaddr>,
         mappings_to_append: &mut LinkedList<(Vaddr, Arc<VmMapping>)>,
-    ) -> Result<()> {
+    ) -> Result<Option<Range<Vaddr>>> {
         let map_to_addr = self.map_to_addr();
         let map_size = self.map_size();
         let range = self.range();
         if !is_intersected(&range, trim_range) {
-            return Ok(());
+            return Ok(None);
         }
+        let released_guard_page = if trim_range.end >= range.end {
+            self.release_guard_page()
+        } else {
+            None
+        };
         if trim_range.start <= map_to_addr && trim_range.end >= map_to_addr + map_size {
             // Fast path: the whole mapping was trimed.
             self.unmap(trim_range, true)?;
             mappings_to_remove.push_back(map_to_addr);
-            return Ok(());
+            return Ok(released_guard_page);
         }
         if trim_range.start <= range.start {
             mappings_to_remove.push_back(map_to_addr);
@@ -428,7 +635,7 @@ impl VmMapping {
             self.trim_right(trim_range.start)?;
         }
 
-        Ok(())
+        Ok(released_guard_page)
     }
 
     /// Trims the mapping from left to a new address.
@@ -457,6 +664,7 @@ impl VmMappingInner {
         map_addr: usize,
         frame: Frame,
         is_readonly: bool,
+        rss_type: Option<RssType>,
     ) -> Result<()> {
         let map_range = map_addr..map_addr + PAGE_SIZE;
 
@@ -468,7 +676,8 @@ impl VmMappingInner {
             }
             perms
         };
-        let map_prop = PageProperty::new(vm_perms.into(), CachePolicy::Writeback);
+        let mut map_prop = PageProperty::new(vm_perms.into(), CachePolicy::Writeback);
+        map_prop.set_rss_type(rss_type);
 
         let mut cursor = vm_space.cursor_mut(&map_range).unwrap();
         cursor.map(frame, map_prop);
@@ -499,6 +708,24 @@ impl VmMappingInner {
         debug_assert!(range.end % PAGE_SIZE == 0);
         let mut cursor = vm_space.cursor_mut(&range).unwrap();
         cursor.protect(range.len(), |p| p.flags = perms.into());
+
+        if perms.contains(VmPerms::WRITE) {
+            // The zero frame is shared, so it must stay readonly. The next write
+            // access will replace it with a private frame.
+            let mut va = range.start;
+            while va < range.end {
+                cursor.jump(va);
+                va = match cursor.query()? {
+                    VmItem::Mapped { va, frame, .. } => {
+                        if is_zero_frame(&frame) {
+                            cursor.protect(PAGE_SIZE, |p| p.flags -= PageFlags::W);
+                        }
+                        va + PAGE_SIZE
+                    }
+                    VmItem::NotMapped { va, len } => va.align_down(len) + len,
+                };
+            }
+        }
         Ok(())
     }
 
@@ -535,6 +762,8 @@ impl VmMappingInner {
         self.unmap(vm_space, &(vaddr..self.map_to_addr + self.map_size), true)?;
 
         self.map_size = vaddr - self.map_to_addr;
+        // The guard page is either released or kept by the part on the right.
+        self.has_guard_page = false;
         Ok(self.map_to_addr)
     }
 
@@ -543,6 +772,10 @@ impl VmMappingInner {
     fn shrink_to(&mut self, new_range: Range<usize>) {
         debug_assert!(self.map_to_addr <= new_range.start);
         debug_assert!(self.map_to_addr + self.map_size >= new_range.end);
+        // Only the part at the end of the mapping is followed by the guard page.
+        if new_range.end != self.map_to_addr + self.map_size {
+            self.has_guard_page = false;
+        }
         self.vmo_offset = self
             .vmo_offset
             .map(|vmo_offset| vmo_offset + new_range.start - self.map_to_addr);
@@ -577,6 +810,8 @@ pub struct VmarMapOptions<R1, R2> {
     can_overwrite: bool,
     // Whether the mapping is mapped with `MAP_SHARED`
     is_shared: bool,
+    // Whether the mapping is followed by an inaccessible guard page
+    has_guard_page: bool,
 }
 
 impl<R1, R2> VmarMapOptions<R1, R2> {
@@ -598,6 +833,7 @@ impl<R1, R2> VmarMapOptions<R1, R2> {
             align: PAGE_SIZE,
             can_overwrite: false,
             is_shared: false,
+            has_guard_page: false,
         }
     }
 
@@ -684,6 +920,22 @@ impl<R1, R2> VmarMapOptions<R1, R2> {
         self
     }
 
+    /// Sets whether the mapping is followed by an inaccessible guard page.
+    ///
+    /// The default value is false.
+    ///
+    /// With a guard page, an access right past the end of the mapping faults
+    /// instead of hitting another mapping, which helps to catch buffer overruns.
+    /// The guard page belongs to the mapping. It is released when the end of the
+    /// mapping is unmapped or remapped.
+    ///
+    /// This option only takes effect if the `offset` option is not set. If there
+    /// is no room for the guard page, the mapping is created without it.
+    pub fn guard_page(mut self, has_guard_page: bool) -> Self {
+        self.has_guard_page = has_guard_page;
+        self
+    }
+
     /// Creates the mapping.
     ///
     /// All options will be checked at this point.
@@ -786,6 +1038,28 @@ impl MappedVmo {
         self.vmo.commit_page(page_idx * PAGE_SIZE)
     }
 
+    /// Returns the size (in bytes) of the mapped VMO.
+    ///
+    /// The size may shrink after mapping, e.g., when the file is truncated.
+    pub fn size(&self) -> usize {
+        self.vmo.size()
+    }
+
+    /// Returns whether the mapped VMO is backed by a pager.
+    pub fn has_pager(&self) -> bool {
+        self.vmo.has_pager()
+    }
+
+    /// Marks the page at the input `page_idx` in the mapped VMO as dirty.
+    pub fn mark_page_dirty(&self, page_idx: usize) -> Result<()> {
+        self.vmo.mark_page_dirty(page_idx)
+    }
+
+    /// Returns the ID of the mapped VMO.
+    pub fn id(&self) -> usize {
+        self.vmo.id()
+    }
+
     /// Duplicates the capability.
     pub fn dup(&self) -> Result<Self> {
         Ok(Self {
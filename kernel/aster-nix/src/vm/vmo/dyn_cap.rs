@@ -3,7 +3,7 @@
 use core::ops::Range;
 
 use aster_rights::{Rights, TRights};
-use ostd::mm::{Frame, VmIo};
+use ostd::mm::{Frame, UserSpace, VmIo};
 
 use super::{CommitFlags, Vmo, VmoRightsOp};
 use crate::prelude::*;
@@ -102,6 +102,33 @@ impl Vmo<Rights> {
         self.0.replace(page, page_idx)
     }
 
+    /// Marks the page at the `page_idx` in the VMO as dirty, so that the pager
+    /// can write it back later.
+    ///
+    /// # Access rights
+    ///
+    /// The method requires the Write right.
+    pub fn mark_page_dirty(&self, page_idx: usize) -> Result<()> {
+        self.check_rights(Rights::WRITE)?;
+        self.0.mark_page_dirty(page_idx)
+    }
+
+    /// Writes the data from a user space reader to the VMO without an intermediate buffer.
+    ///
+    /// If a page fault occurs, the number of bytes written so far is returned.
+    ///
+    /// # Access rights
+    ///
+    /// The method requires the Write right.
+    pub fn write_from_user(
+        &self,
+        offset: usize,
+        reader: &mut VmReader<'_, UserSpace>,
+    ) -> Result<usize> {
+        self.check_rights(Rights::WRITE)?;
+        self.0.write_from_user(offset, reader)
+    }
+
     /// Restricts the access rights given the mask.
     pub fn restrict(mut self, mask: Rights) -> Self {
         self.1 |= mask;
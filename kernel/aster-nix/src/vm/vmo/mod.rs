@@ -11,7 +11,7 @@ use align_ext::AlignExt;
 use aster_rights::Rights;
 use ostd::{
     collections::xarray::{CursorMut, XArray},
-    mm::{Frame, FrameAllocOptions, VmReader, VmWriter},
+    mm::{Frame, FrameAllocOptions, UserSpace, VmReader, VmWriter},
 };
 
 use crate::prelude::*;
@@ -355,6 +355,51 @@ impl Vmo_ {
         Ok(())
     }
 
+    /// Writes the data from a user space reader to the VMO, starting from the target offset.
+    ///
+    /// Unlike [`Self::write_bytes`], the data is copied to the pages of the VMO directly
+    /// without an intermediate buffer. The pages are committed one by one, and the VMO is
+    /// not locked while copying, so a page fault on the user buffer is handled safely.
+    ///
+    /// If a page fault occurs, the number of bytes written so far is returned. If nothing is
+    /// written, `EFAULT` is returned.
+    pub fn write_from_user(
+        &self,
+        offset: usize,
+        reader: &mut VmReader<'_, UserSpace>,
+    ) -> Result<usize> {
+        let write_range = offset..(offset + reader.remain());
+
+        let mut write_offset = write_range.start;
+        while write_offset < write_range.end {
+            let page = self.commit_page(write_offset)?;
+            let page_offset = write_offset % PAGE_SIZE;
+            let len = (PAGE_SIZE - page_offset).min(write_range.end - write_offset);
+
+            let mut page_writer = page.writer().skip(page_offset).limit(len);
+            let (copied_len, is_faulted) = match reader.read_fallible(&mut page_writer) {
+                Ok(copied_len) => (copied_len, false),
+                Err((_, copied_len)) => (copied_len, true),
+            };
+            if copied_len > 0
+                && let Some(pager) = &self.pager
+            {
+                pager.update_page(write_offset / PAGE_SIZE)?;
+            }
+            write_offset += copied_len;
+
+            if is_faulted {
+                break;
+            }
+        }
+
+        let written_len = write_offset - write_range.start;
+        if written_len == 0 && !write_range.is_empty() {
+            return_errno_with_message!(Errno::EFAULT, "the user buffer is not accessible");
+        }
+        Ok(written_len)
+    }
+
     /// Clears the target range in current VMO.
     pub fn clear(&self, range: Range<usize>) -> Result<()> {
         let buffer = vec![0u8; range.end - range.start];
@@ -413,6 +458,20 @@ impl Vmo_ {
         self.flags
     }
 
+    /// Returns whether current VMO is backed by a pager.
+    pub fn has_pager(&self) -> bool {
+        self.pager.is_some()
+    }
+
+    /// Notifies the pager that the page at the target index has been modified
+    /// by means other than [`Vmo_::write_bytes`], e.g., through a shared mapping.
+    pub fn mark_page_dirty(&self, page_idx: usize) -> Result<()> {
+        if let Some(pager) = &self.pager {
+            pager.update_page(page_idx)?;
+        }
+        Ok(())
+    }
+
     fn replace(&self, page: Frame, page_idx: usize) -> Result<()> {
         self.pages.with(|pages, size| {
             if page_idx >= size / PAGE_SIZE {
@@ -434,6 +493,19 @@ impl<R> Vmo<R> {
     pub fn flags(&self) -> VmoFlags {
         self.0.flags()
     }
+
+    /// Returns whether a VMO is backed by a pager, e.g., the page cache of a file.
+    pub fn has_pager(&self) -> bool {
+        self.0.has_pager()
+    }
+
+    /// Returns an ID that is unique among the existing VMOs.
+    ///
+    /// The capabilities of the same VMO have the same ID. The ID may be reused
+    /// after the VMO is dropped.
+    pub fn id(&self) -> usize {
+        Arc::as_ptr(&self.0) as usize
+    }
 }
 
 /// Gets the page index range that contains the offset range of VMO.
@@ -4,7 +4,7 @@ use core::ops::Range;
 
 use aster_rights::{Dup, Rights, TRightSet, TRights, Write};
 use aster_rights_proc::require;
-use ostd::mm::{Frame, VmIo};
+use ostd::mm::{Frame, UserSpace, VmIo};
 
 use super::{CommitFlags, Vmo, VmoRightsOp};
 use crate::prelude::*;
@@ -60,6 +60,22 @@ impl<R: TRights> Vmo<TRightSet<R>> {
         self.0.resize(new_size)
     }
 
+    /// Writes the data from a user space reader to the VMO without an intermediate buffer.
+    ///
+    /// If a page fault occurs, the number of bytes written so far is returned.
+    ///
+    /// # Access rights
+    ///
+    /// The method requires the Write right.
+    #[require(R > Write)]
+    pub fn write_from_user(
+        &self,
+        offset: usize,
+        reader: &mut VmReader<'_, UserSpace>,
+    ) -> Result<usize> {
+        self.0.write_from_user(offset, reader)
+    }
+
     /// Clear the specified range by writing zeros.
     ///
     /// # Access rights
@@ -103,6 +119,17 @@ impl<R: TRights> Vmo<TRightSet<R>> {
         self.0.replace(page, page_idx)
     }
 
+    /// Marks the page at the `page_idx` in the VMO as dirty, so that the pager
+    /// can write it back later.
+    ///
+    /// # Access rights
+    ///
+    /// The method requires the Write right.
+    #[require(R > Write)]
+    pub fn mark_page_dirty(&self, page_idx: usize) -> Result<()> {
+        self.0.mark_page_dirty(page_idx)
+    }
+
     /// Strict the access rights.
     #[require(R > R1)]
     pub fn restrict<R1: TRights>(self) -> Vmo<TRightSet<R1>> {
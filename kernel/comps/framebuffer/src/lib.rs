@@ -36,8 +36,11 @@ pub(crate) static WRITER: Once<SpinLock<Writer>> = Once::new();
 #[allow(unreachable_code)]
 #[allow(clippy::diverging_sub_expression)]
 pub(crate) fn init() {
+    let Some(framebuffer) = boot::framebuffer_arg() else {
+        log::debug!("No framebuffer found");
+        return;
+    };
     let mut writer = {
-        let framebuffer = boot::framebuffer_arg();
         let mut size = 0;
         for i in ostd::mm::FRAMEBUFFER_REGIONS.get().unwrap().iter() {
             size = i.len();
@@ -8,7 +8,7 @@ use core::sync::atomic::{AtomicU64, Ordering};
 
 use ostd::arch::{
     read_tsc,
-    timer::{self, TIMER_FREQ},
+    timer::{self, timer_freq},
     tsc_freq,
 };
 use spin::Once;
@@ -75,7 +75,7 @@ fn init_timer() {
     // If without KVM, the delayed time will be larger.
     // TODO: This is a temporary solution, and should be modified in the future.
     let max_delay_secs = CLOCK.get().unwrap().max_delay_secs() >> 1;
-    let delay_counts = TIMER_FREQ * max_delay_secs;
+    let delay_counts = timer_freq() * max_delay_secs;
 
     let update = move || {
         let counter = TSC_UPDATE_COUNTER.fetch_add(1, Ordering::Relaxed);
@@ -25,7 +25,7 @@ use device::{
 use log::{error, warn};
 use transport::{mmio::VIRTIO_MMIO_DRIVER, pci::VIRTIO_PCI_DRIVER, DeviceStatus};
 
-use crate::transport::VirtioTransport;
+use crate::transport::{VirtioTransport, VirtioTransportError};
 
 pub mod device;
 mod dma_buf;
@@ -46,15 +46,15 @@ fn virtio_component_init() -> Result<(), ComponentInitError> {
             .set_device_status(DeviceStatus::ACKNOWLEDGE | DeviceStatus::DRIVER)
             .unwrap();
         // negotiate features
-        negotiate_features(&mut transport);
-
-        // change to features ok status
-        transport
-            .set_device_status(
-                DeviceStatus::ACKNOWLEDGE | DeviceStatus::DRIVER | DeviceStatus::FEATURES_OK,
-            )
-            .unwrap();
         let device_type = transport.device_type();
+        if let Err(err) = negotiate_features(&mut transport) {
+            error!(
+                "[Virtio]: Feature negotiation error:{:?}, device type:{:?}",
+                err, device_type
+            );
+            continue;
+        }
+
         let res = match transport.device_type() {
             VirtioDeviceType::Block => BlockDevice::init(transport),
             VirtioDeviceType::Input => InputDevice::init(transport),
@@ -86,7 +86,9 @@ fn pop_device_transport() -> Option<Box<dyn VirtioTransport>> {
     None
 }
 
-fn negotiate_features(transport: &mut Box<dyn VirtioTransport>) {
+fn negotiate_features(
+    transport: &mut Box<dyn VirtioTransport>,
+) -> Result<u64, VirtioTransportError> {
     let features = transport.device_features();
     let mask = ((1u64 << 24) - 1) | (((1u64 << 24) - 1) << 50);
     let device_specified_features = features & mask;
@@ -100,9 +102,7 @@ fn negotiate_features(transport: &mut Box<dyn VirtioTransport>) {
     };
     let mut support_feature = Feature::from_bits_truncate(features);
     support_feature.remove(Feature::RING_EVENT_IDX);
-    transport
-        .set_driver_features(features & (support_feature.bits | device_support_features))
-        .unwrap();
+    transport.negotiate_features(support_feature.bits | device_support_features)
 }
 
 bitflags! {
@@ -413,3 +413,71 @@ pub struct UsedElem {
     id: u32,
     len: u32,
 }
+
+#[cfg(ktest)]
+mod test {
+    use ostd::{
+        mm::{DmaCoherent, FrameAllocOptions, HasDaddr, VmIo},
+        prelude::*,
+    };
+
+    use super::*;
+    use crate::transport::mock::MockTransport;
+
+    fn alloc_dma_buf() -> DmaCoherent {
+        let segment = FrameAllocOptions::new(1).alloc_contiguous().unwrap();
+        DmaCoherent::map(segment, true).unwrap()
+    }
+
+    #[ktest]
+    fn round_trip() {
+        const QUEUE_IDX: u16 = 1;
+
+        let mut transport = MockTransport::new(0, 0);
+        let mut queue = VirtQueue::new(QUEUE_IDX, 2, &mut transport).unwrap();
+
+        let request = alloc_dma_buf();
+        let response = alloc_dma_buf();
+        request.write_val(0, &0xdead_beef_u32).unwrap();
+        let token = queue.add_dma_buf(&[&request], &[&response]).unwrap();
+        queue.notify();
+        assert_eq!(transport.notification(QUEUE_IDX), QUEUE_IDX as u32);
+        assert!(!queue.can_pop());
+
+        // Act as the device, which takes the descriptor chain from the available ring.
+        let device_queue = transport.queue(QUEUE_IDX).unwrap();
+        assert_eq!(device_queue.size, 2);
+        let avail = device_queue.avail.read().unwrap();
+        assert_eq!(avail.idx, 1);
+        let head = avail.ring[0];
+        assert_eq!(head, token);
+
+        let mut desc_ptr = device_queue.descs.clone();
+        desc_ptr.add(head as usize);
+        let request_desc = desc_ptr.read().unwrap();
+        assert_eq!(request_desc.addr, request.daddr() as u64);
+        assert_eq!(request_desc.flags, DescFlags::NEXT);
+
+        let mut desc_ptr = device_queue.descs.clone();
+        desc_ptr.add(request_desc.next as usize);
+        let response_desc = desc_ptr.read().unwrap();
+        assert_eq!(response_desc.addr, response.daddr() as u64);
+        assert_eq!(response_desc.flags, DescFlags::WRITE);
+
+        // Reply with the request, then return the chain in the used ring.
+        let value = request.read_val::<u32>(0).unwrap();
+        response.write_val(0, &value).unwrap();
+        let mut used = device_queue.used.read().unwrap();
+        used.ring[0] = UsedElem {
+            id: head as u32,
+            len: size_of::<u32>() as u32,
+        };
+        used.idx = 1;
+        device_queue.used.write(&used).unwrap();
+
+        assert!(queue.can_pop());
+        assert_eq!(queue.pop_used_with_token(token).unwrap(), 4);
+        assert_eq!(response.read_val::<u32>(0).unwrap(), 0xdead_beef);
+        assert_eq!(queue.available_desc(), 2);
+    }
+}
@@ -0,0 +1,177 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! A transport that emulates a virtio device in memory, which is used in tests.
+
+use alloc::{boxed::Box, vec::Vec};
+use core::mem::size_of;
+
+use aster_util::safe_ptr::SafePtr;
+use ostd::{
+    io_mem::IoMem,
+    mm::{DmaCoherent, VmIo},
+    trap::IrqCallbackFunction,
+};
+
+use super::{DeviceStatus, VirtioTransport, VirtioTransportError};
+use crate::{
+    queue::{AvailRing, Descriptor, UsedRing},
+    VirtioDeviceType,
+};
+
+/// The number of the virtqueues of the device.
+const NUM_QUEUES: u16 = 2;
+
+/// The maximum size of the virtqueues, which is limited by the ring sizes of
+/// [`AvailRing`] and [`UsedRing`].
+const MAX_QUEUE_SIZE: u16 = 64;
+
+/// A virtqueue that is set up by the driver.
+#[derive(Debug)]
+pub(crate) struct MockQueue {
+    pub(crate) size: u16,
+    pub(crate) descs: SafePtr<Descriptor, DmaCoherent>,
+    pub(crate) avail: SafePtr<AvailRing, DmaCoherent>,
+    pub(crate) used: SafePtr<UsedRing, DmaCoherent>,
+}
+
+/// A transport that emulates the feature bits, the device status, the device
+/// config space and the virtqueues.
+///
+/// The device config space and the notification registers reside in RAM. The
+/// device side of the virtqueues is left to the tests.
+#[derive(Debug)]
+pub(crate) struct MockTransport {
+    device_features: u64,
+    /// The features that the device accepts. Other features are rejected.
+    acceptable_features: u64,
+    pub(crate) driver_features: u64,
+    status: DeviceStatus,
+    config: IoMem,
+    /// The notification registers, one `u32` for each virtqueue.
+    notify: IoMem,
+    queues: Vec<Option<MockQueue>>,
+}
+
+impl MockTransport {
+    pub(crate) fn new(device_features: u64, acceptable_features: u64) -> Self {
+        Self {
+            device_features,
+            acceptable_features,
+            driver_features: 0,
+            status: DeviceStatus::ACKNOWLEDGE | DeviceStatus::DRIVER,
+            config: IoMem::alloc_for_test(1),
+            notify: IoMem::alloc_for_test(1),
+            queues: (0..NUM_QUEUES).map(|_| None).collect(),
+        }
+    }
+
+    /// Returns the virtqueue at `idx` if the driver has set it up.
+    pub(crate) fn queue(&self, idx: u16) -> Option<&MockQueue> {
+        self.queues.get(idx as usize)?.as_ref()
+    }
+
+    /// Returns the value last written to the notification register of the
+    /// virtqueue at `idx`.
+    pub(crate) fn notification(&self, idx: u16) -> u32 {
+        self.notify
+            .read_val(idx as usize * size_of::<u32>())
+            .unwrap()
+    }
+
+    fn check_queue_idx(&self, idx: u16) -> Result<(), VirtioTransportError> {
+        if idx >= NUM_QUEUES {
+            return Err(VirtioTransportError::InvalidArgs);
+        }
+        Ok(())
+    }
+}
+
+impl VirtioTransport for MockTransport {
+    fn device_type(&self) -> VirtioDeviceType {
+        VirtioDeviceType::Network
+    }
+
+    fn device_features(&self) -> u64 {
+        self.device_features
+    }
+
+    fn set_driver_features(&mut self, features: u64) -> Result<(), VirtioTransportError> {
+        self.driver_features = features;
+        Ok(())
+    }
+
+    fn device_status(&self) -> DeviceStatus {
+        self.status
+    }
+
+    fn set_device_status(&mut self, status: DeviceStatus) -> Result<(), VirtioTransportError> {
+        self.status = status;
+        if self.driver_features & !self.acceptable_features != 0 {
+            self.status.remove(DeviceStatus::FEATURES_OK);
+        }
+        Ok(())
+    }
+
+    fn device_config_memory(&self) -> IoMem {
+        self.config.clone()
+    }
+
+    fn num_queues(&self) -> u16 {
+        NUM_QUEUES
+    }
+
+    fn set_queue(
+        &mut self,
+        idx: u16,
+        queue_size: u16,
+        descriptor_ptr: &SafePtr<Descriptor, DmaCoherent>,
+        avail_ring_ptr: &SafePtr<AvailRing, DmaCoherent>,
+        used_ring_ptr: &SafePtr<UsedRing, DmaCoherent>,
+    ) -> Result<(), VirtioTransportError> {
+        self.check_queue_idx(idx)?;
+        if queue_size > MAX_QUEUE_SIZE {
+            return Err(VirtioTransportError::InvalidArgs);
+        }
+
+        self.queues[idx as usize] = Some(MockQueue {
+            size: queue_size,
+            descs: descriptor_ptr.clone(),
+            avail: avail_ring_ptr.clone(),
+            used: used_ring_ptr.clone(),
+        });
+        Ok(())
+    }
+
+    fn max_queue_size(&self, idx: u16) -> Result<u16, VirtioTransportError> {
+        self.check_queue_idx(idx)?;
+        Ok(MAX_QUEUE_SIZE)
+    }
+
+    fn get_notify_ptr(&self, idx: u16) -> Result<SafePtr<u32, IoMem>, VirtioTransportError> {
+        self.check_queue_idx(idx)?;
+        Ok(SafePtr::new(
+            self.notify.clone(),
+            idx as usize * size_of::<u32>(),
+        ))
+    }
+
+    fn is_legacy_version(&self) -> bool {
+        false
+    }
+
+    fn register_queue_callback(
+        &mut self,
+        _index: u16,
+        _func: Box<IrqCallbackFunction>,
+        _single_interrupt: bool,
+    ) -> Result<(), VirtioTransportError> {
+        Err(VirtioTransportError::NotEnoughResources)
+    }
+
+    fn register_cfg_callback(
+        &mut self,
+        _func: Box<IrqCallbackFunction>,
+    ) -> Result<(), VirtioTransportError> {
+        Err(VirtioTransportError::NotEnoughResources)
+    }
+}
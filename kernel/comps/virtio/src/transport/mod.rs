@@ -13,6 +13,8 @@ use crate::{
 };
 
 pub mod mmio;
+#[cfg(ktest)]
+pub(crate) mod mock;
 pub mod pci;
 
 /// The transport of virtio device. Virtio device can use this transport to:
@@ -38,6 +40,30 @@ pub trait VirtioTransport: Sync + Send + Debug {
     /// Set device status.
     fn set_device_status(&mut self, status: DeviceStatus) -> Result<(), VirtioTransportError>;
 
+    /// Negotiate features with the device.
+    ///
+    /// The features offered by the device and supported by the driver (i.e., `driver_features`)
+    /// are written back to the device, and then the `FEATURES_OK` status bit is set. A modern
+    /// device clears the bit if it does not accept the features, in which case the device is
+    /// reset and [`VirtioTransportError::FeatureNegotiationFailed`] is returned.
+    ///
+    /// Returns the negotiated features.
+    fn negotiate_features(&mut self, driver_features: u64) -> Result<u64, VirtioTransportError> {
+        let features = self.device_features() & driver_features;
+        self.set_driver_features(features)?;
+        self.set_device_status(
+            DeviceStatus::ACKNOWLEDGE | DeviceStatus::DRIVER | DeviceStatus::FEATURES_OK,
+        )?;
+
+        // Legacy devices do not support the `FEATURES_OK` status bit.
+        if !self.is_legacy_version() && !self.device_status().contains(DeviceStatus::FEATURES_OK) {
+            self.set_device_status(DeviceStatus::empty())?;
+            return Err(VirtioTransportError::FeatureNegotiationFailed);
+        }
+
+        Ok(features)
+    }
+
     // Set to driver ok status
     fn finish_init(&mut self) {
         self.set_device_status(
@@ -99,6 +125,7 @@ pub enum VirtioTransportError {
     DeviceStatusError,
     InvalidArgs,
     NotEnoughResources,
+    FeatureNegotiationFailed,
 }
 
 bitflags::bitflags! {
@@ -134,3 +161,43 @@ pub fn init() {
     virtio_pci_init();
     virtio_mmio_init();
 }
+
+#[cfg(ktest)]
+mod test {
+    use ostd::{mm::VmIo, prelude::*};
+
+    use super::{mock::MockTransport, *};
+
+    #[ktest]
+    fn negotiate_features() {
+        let mut transport = MockTransport::new(0b1011, 0b1111);
+        assert_eq!(transport.negotiate_features(0b0110), Ok(0b0010));
+        assert_eq!(transport.driver_features, 0b0010);
+        assert!(transport
+            .device_status()
+            .contains(DeviceStatus::FEATURES_OK));
+    }
+
+    #[ktest]
+    fn rejected_features_reset_device() {
+        let mut transport = MockTransport::new(0b1011, 0b0011);
+        assert_eq!(
+            transport.negotiate_features(0b1111),
+            Err(VirtioTransportError::FeatureNegotiationFailed)
+        );
+        assert!(transport.device_status().is_empty());
+    }
+
+    #[ktest]
+    fn device_config_memory() {
+        let transport = MockTransport::new(0, 0);
+        transport
+            .device_config_memory()
+            .write_val(8, &0x1234u32)
+            .unwrap();
+        assert_eq!(
+            transport.device_config_memory().read_val::<u32>(8).unwrap(),
+            0x1234
+        );
+    }
+}
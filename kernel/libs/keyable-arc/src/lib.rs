@@ -260,6 +260,19 @@ impl<T: ?Sized> KeyableWeak<T> {
         self.0.upgrade().map(|arc| arc.into())
     }
 
+    /// Attempts to upgrade the Weak pointer to an Arc and calls `f` with it.
+    ///
+    /// The inner value is not dropped until `f` returns. Returns None without
+    /// calling `f` if the inner value has since been dropped.
+    #[inline]
+    pub fn with_upgraded<R, F>(&self, f: F) -> Option<R>
+    where
+        F: FnOnce(&KeyableArc<T>) -> R,
+    {
+        let arc = self.upgrade()?;
+        Some(f(&arc))
+    }
+
     /// Gets the number of strong pointers pointing to this allocation.
     #[inline]
     pub fn strong_count(&self) -> usize {
@@ -337,6 +350,18 @@ mod tests {
         assert!(weak == KeyableArc::downgrade(&arc));
     }
 
+    #[test]
+    fn with_upgraded() {
+        let arc = KeyableArc::new(1);
+        let weak = KeyableArc::downgrade(&arc);
+        assert!(weak.with_upgraded(|arc| **arc + 1) == Some(2));
+
+        drop(arc);
+        let mut is_called = false;
+        assert!(weak.with_upgraded(|_| is_called = true).is_none());
+        assert!(!is_called);
+    }
+
     #[test]
     fn debug_format() {
         println!("{:?}", KeyableArc::new(1u32));
@@ -66,6 +66,19 @@ impl IdAlloc {
         }
     }
 
+    /// Allocates and returns a new `id`, growing the capacity if all `id`s are allocated.
+    ///
+    /// The capacity is doubled each time it grows, but never exceeds `max_capacity`.
+    ///
+    /// If allocation is not possible, it returns `None`.
+    pub fn alloc_or_grow(&mut self, max_capacity: usize) -> Option<usize> {
+        let capacity = self.capacity();
+        if self.first_available_id == capacity && capacity < max_capacity {
+            self.grow((capacity * 2).clamp(1, max_capacity));
+        }
+        self.alloc()
+    }
+
     /// Allocates a consecutive range of new `id`s.
     ///
     /// The `count` is the number of consecutive `id`s to allocate. If it is 0, return `None`.
@@ -169,6 +182,24 @@ impl IdAlloc {
         Some(id)
     }
 
+    /// Grows the capacity of the id allocator to `new_capacity`.
+    ///
+    /// The added `id`s are not allocated. If `new_capacity` is not larger than
+    /// the current capacity, this method does nothing.
+    pub fn grow(&mut self, new_capacity: usize) {
+        if new_capacity <= self.capacity() {
+            return;
+        }
+        // If all the `id`s were allocated, `first_available_id` is the old capacity,
+        // which is exactly the first added `id`.
+        self.bitset.resize(new_capacity, false);
+    }
+
+    /// Returns the capacity of the id allocator, i.e., the number of `id`s it manages.
+    pub fn capacity(&self) -> usize {
+        self.bitset.len()
+    }
+
     /// Returns true if the `id` is allocated.
     ///
     /// # Panics
@@ -192,3 +223,46 @@ impl Debug for IdAlloc {
             .finish()
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn alloc_lowest_free_id() {
+        let mut id_alloc = IdAlloc::with_capacity(4);
+        assert_eq!(id_alloc.alloc(), Some(0));
+        assert_eq!(id_alloc.alloc(), Some(1));
+        assert_eq!(id_alloc.alloc(), Some(2));
+
+        id_alloc.free(1);
+        assert!(!id_alloc.is_allocated(1));
+        assert_eq!(id_alloc.alloc(), Some(1));
+        assert_eq!(id_alloc.alloc(), Some(3));
+        assert_eq!(id_alloc.alloc(), None);
+    }
+
+    #[test]
+    fn alloc_or_grow() {
+        let mut id_alloc = IdAlloc::with_capacity(0);
+        for id in 0..5 {
+            assert_eq!(id_alloc.alloc_or_grow(5), Some(id));
+        }
+        assert_eq!(id_alloc.capacity(), 5);
+        assert_eq!(id_alloc.alloc_or_grow(5), None);
+
+        id_alloc.free(2);
+        assert_eq!(id_alloc.alloc_or_grow(5), Some(2));
+        assert_eq!(id_alloc.capacity(), 5);
+    }
+
+    #[test]
+    fn alloc_consecutive_after_grow() {
+        let mut id_alloc = IdAlloc::with_capacity(2);
+        assert_eq!(id_alloc.alloc_consecutive(2), Some(0..2));
+        assert_eq!(id_alloc.alloc_consecutive(2), None);
+
+        id_alloc.grow(4);
+        assert_eq!(id_alloc.alloc_consecutive(2), Some(2..4));
+    }
+}
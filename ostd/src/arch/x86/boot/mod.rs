@@ -13,9 +13,9 @@
 //!
 //! Asterinas diffrentiates the boot protocol by the entry point
 //! chosen by the boot loader. In each entry point function,
-//! the universal callback registeration method from
-//! `crate::boot` will be called. Thus the initialization of
-//! boot information is transparent for the upper level kernel.
+//! the boot protocol, which implements `crate::boot::BootProtocol`,
+//! is registered. Thus the initialization of boot information is
+//! transparent for the upper level kernel.
 //!
 
 mod linux_boot;
@@ -7,9 +7,8 @@ use spin::Once;
 
 use crate::{
     boot::{
-        kcmdline::KCmdlineArg,
-        memory_region::{non_overlapping_regions_from, MemoryRegion, MemoryRegionType},
-        BootloaderAcpiArg, BootloaderFramebufferArg,
+        memory_region::{MemoryRegion, MemoryRegionType},
+        BootProtocol, BootloaderAcpiArg, BootloaderFramebufferArg,
     },
     mm::{
         kspace::{paddr_to_vaddr, LINEAR_MAPPING_BASE_VADDR},
@@ -21,8 +20,11 @@ global_asm!(include_str!("header.S"));
 
 pub(super) const MULTIBOOT_ENTRY_MAGIC: u32 = 0x2BADB002;
 
-fn init_bootloader_name(bootloader_name: &'static Once<String>) {
-    bootloader_name.call_once(|| {
+/// The Multiboot (v1) boot protocol.
+struct Multiboot;
+
+impl BootProtocol for Multiboot {
+    fn bootloader_name(&self) -> String {
         let mut name = "";
         let info = MB1_INFO.get().unwrap();
         if info.boot_loader_name != 0 {
@@ -39,11 +41,9 @@ fn init_bootloader_name(bootloader_name: &'static Once<String>) {
             }
         }
         name.into()
-    });
-}
+    }
 
-fn init_kernel_commandline(kernel_cmdline: &'static Once<KCmdlineArg>) {
-    kernel_cmdline.call_once(|| {
+    fn kernel_cmdline(&self) -> &str {
         let mut cmdline = "";
         let info = MB1_INFO.get().unwrap();
         if info.cmdline != 0 {
@@ -59,84 +59,15 @@ fn init_kernel_commandline(kernel_cmdline: &'static Once<KCmdlineArg>) {
                     .expect("cmdline is not a utf-8 string");
             }
         }
-        cmdline.into()
-    });
-}
-
-fn init_initramfs(initramfs: &'static Once<&'static [u8]>) {
-    let info = MB1_INFO.get().unwrap();
-    // FIXME: We think all modules are initramfs, can this cause problems?
-    if info.mods_count == 0 {
-        return;
+        cmdline
     }
-    let modules_addr = info.mods_addr as usize;
-    // We only use one module
-    let (start, end) = unsafe {
-        (
-            (*(paddr_to_vaddr(modules_addr) as *const u32)) as usize,
-            (*(paddr_to_vaddr(modules_addr + 4) as *const u32)) as usize,
-        )
-    };
-    // We must return a slice composed by VA since kernel should read every in VA.
-    let base_va = if start < LINEAR_MAPPING_BASE_VADDR {
-        paddr_to_vaddr(start)
-    } else {
-        start
-    };
-    let length = end - start;
-    initramfs.call_once(|| unsafe { core::slice::from_raw_parts(base_va as *const u8, length) });
-}
-
-fn init_acpi_arg(acpi: &'static Once<BootloaderAcpiArg>) {
-    // The multiboot protocol does not contain RSDP address.
-    // TODO: What about UEFI?
-    acpi.call_once(|| BootloaderAcpiArg::NotProvided);
-}
-
-fn init_framebuffer_info(framebuffer_arg: &'static Once<BootloaderFramebufferArg>) {
-    let info = MB1_INFO.get().unwrap();
-    framebuffer_arg.call_once(|| BootloaderFramebufferArg {
-        address: info.framebuffer_table.addr as usize,
-        width: info.framebuffer_table.width as usize,
-        height: info.framebuffer_table.height as usize,
-        bpp: info.framebuffer_table.bpp as usize,
-    });
-}
 
-fn init_memory_regions(memory_regions: &'static Once<Vec<MemoryRegion>>) {
-    let mut regions = Vec::<MemoryRegion>::new();
-
-    let info = MB1_INFO.get().unwrap();
-
-    // Add the regions in the multiboot protocol.
-    for entry in info.get_memory_map() {
-        let start = entry.base_addr();
-        let region = MemoryRegion::new(
-            start.try_into().unwrap(),
-            entry.length().try_into().unwrap(),
-            entry.memory_type(),
-        );
-        regions.push(region);
-    }
-
-    // Add the framebuffer region.
-    let fb = BootloaderFramebufferArg {
-        address: info.framebuffer_table.addr as usize,
-        width: info.framebuffer_table.width as usize,
-        height: info.framebuffer_table.height as usize,
-        bpp: info.framebuffer_table.bpp as usize,
-    };
-    regions.push(MemoryRegion::new(
-        fb.address,
-        (fb.width * fb.height * fb.bpp + 7) / 8, // round up when divide with 8 (bits/Byte)
-        MemoryRegionType::Framebuffer,
-    ));
-
-    // Add the kernel region.
-    regions.push(MemoryRegion::kernel());
-
-    // Add the initramfs area.
-    if info.mods_count != 0 {
+    fn initramfs(&self) -> Option<&'static [u8]> {
+        let info = MB1_INFO.get().unwrap();
+        // FIXME: We think all modules are initramfs, can this cause problems?
+        if info.mods_count == 0 {
+            return None;
+        }
         let modules_addr = info.mods_addr as usize;
         // We only use one module
         let (start, end) = unsafe {
@@ -145,22 +76,82 @@ fn init_memory_regions(memory_regions: &'static Once<Vec<MemoryRegion>>) {
                 (*(paddr_to_vaddr(modules_addr + 4) as *const u32)) as usize,
             )
         };
-        regions.push(MemoryRegion::new(
-            start,
-            end - start,
-            MemoryRegionType::Module,
-        ));
+        // We must return a slice composed by VA since kernel should read every in VA.
+        let base_va = if start < LINEAR_MAPPING_BASE_VADDR {
+            paddr_to_vaddr(start)
+        } else {
+            start
+        };
+        let length = end - start;
+        Some(unsafe { core::slice::from_raw_parts(base_va as *const u8, length) })
+    }
+
+    fn acpi_arg(&self) -> BootloaderAcpiArg {
+        // The multiboot protocol does not contain RSDP address.
+        // TODO: What about UEFI?
+        BootloaderAcpiArg::NotProvided
     }
 
-    // Add the AP boot code region that will be copied into by the BSP.
-    regions.push(MemoryRegion::new(
-        super::smp::AP_BOOT_START_PA,
-        super::smp::ap_boot_code_size(),
-        MemoryRegionType::Reclaimable,
-    ));
+    fn framebuffer_arg(&self) -> Option<BootloaderFramebufferArg> {
+        let info = MB1_INFO.get().unwrap();
+        // The framebuffer table is present only if flags[12] is set.
+        if info.flags & (1 << 12) == 0 {
+            return None;
+        }
+        Some(BootloaderFramebufferArg {
+            address: info.framebuffer_table.addr as usize,
+            width: info.framebuffer_table.width as usize,
+            height: info.framebuffer_table.height as usize,
+            pitch: info.framebuffer_table.pitch as usize,
+            bpp: info.framebuffer_table.bpp as usize,
+        })
+    }
+
+    fn memory_regions(&self) -> Vec<MemoryRegion> {
+        let mut regions = Vec::<MemoryRegion>::new();
+
+        let info = MB1_INFO.get().unwrap();
+
+        // Add the regions in the multiboot protocol.
+        for entry in info.get_memory_map() {
+            let start = entry.base_addr();
+            let region = MemoryRegion::new(
+                start.try_into().unwrap(),
+                entry.length().try_into().unwrap(),
+                entry.memory_type(),
+            );
+            regions.push(region);
+        }
+
+        // Add the kernel region.
+        regions.push(MemoryRegion::kernel());
+
+        // Add the initramfs area.
+        if info.mods_count != 0 {
+            let modules_addr = info.mods_addr as usize;
+            // We only use one module
+            let (start, end) = unsafe {
+                (
+                    (*(paddr_to_vaddr(modules_addr) as *const u32)) as usize,
+                    (*(paddr_to_vaddr(modules_addr + 4) as *const u32)) as usize,
+                )
+            };
+            regions.push(MemoryRegion::new(
+                start,
+                end - start,
+                MemoryRegionType::Module,
+            ));
+        }
 
-    // Initialize with non-overlapping regions.
-    memory_regions.call_once(move || non_overlapping_regions_from(regions.as_ref()));
+        // Add the AP boot code region that will be copied into by the BSP.
+        regions.push(MemoryRegion::new(
+            super::smp::AP_BOOT_START_PA,
+            super::smp::ap_boot_code_size(),
+            MemoryRegionType::Reclaimable,
+        ));
+
+        regions
+    }
 }
 
 /// Representation of Multiboot Information according to specification.
@@ -403,13 +394,6 @@ static MB1_INFO: Once<&'static MultibootLegacyInfo> = Once::new();
 unsafe extern "sysv64" fn __multiboot_entry(boot_magic: u32, boot_params: u64) -> ! {
     assert_eq!(boot_magic, MULTIBOOT_ENTRY_MAGIC);
     MB1_INFO.call_once(|| &*(paddr_to_vaddr(boot_params as usize) as *const MultibootLegacyInfo));
-    crate::boot::register_boot_init_callbacks(
-        init_bootloader_name,
-        init_kernel_commandline,
-        init_initramfs,
-        init_acpi_arg,
-        init_framebuffer_info,
-        init_memory_regions,
-    );
+    crate::boot::register_boot_protocol(&Multiboot);
     crate::boot::call_ostd_main();
 }
@@ -3,6 +3,7 @@
 //! CPU.
 
 pub mod local;
+mod topology;
 
 use core::{
     arch::x86_64::{_fxrstor, _fxsave},
@@ -16,6 +17,8 @@ pub use trapframe::GeneralRegs as RawGeneralRegs;
 use trapframe::UserContext as RawUserContext;
 use x86_64::registers::rflags::RFlags;
 
+pub(crate) use self::topology::init as init_topology;
+pub use self::topology::{cache_line_size, topology, CpuTopology};
 use crate::{
     task::scheduler,
     trap::call_irq_callback_functions,
@@ -157,7 +160,9 @@ impl UserContextApiInternal for UserContext {
             trap_num: self.user_context.trap_num,
             error_code: self.user_context.error_code,
             rip: self.user_context.general.rip,
-            cs: 0,
+            // Only the privilege level of the selector is meaningful, which indicates
+            // that the frame is saved in the user mode.
+            cs: 3,
             rflags: self.user_context.general.rflags,
         }
     }
@@ -0,0 +1,196 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Cache line size and CPU topology detection.
+//!
+//! The information is detected with the `CPUID` instruction. Hypervisors may
+//! report incomplete or bogus values, in which case conservative defaults are
+//! used instead.
+
+use core::arch::x86_64::{__cpuid, __cpuid_count};
+
+use spin::Once;
+
+/// The cache line size assumed if the detection fails.
+const DEFAULT_CACHE_LINE_SIZE: usize = 64;
+
+/// The range of cache line sizes that are considered sane.
+const CACHE_LINE_SIZE_RANGE: core::ops::RangeInclusive<usize> = 16..=512;
+
+/// The topology of the CPUs in a socket (a physical package).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CpuTopology {
+    /// The number of hardware threads (logical CPUs) per core.
+    pub threads_per_core: u32,
+    /// The number of cores per socket.
+    pub cores_per_socket: u32,
+}
+
+impl CpuTopology {
+    /// The topology assumed if the detection fails, i.e., every logical CPU
+    /// is a separate core in a single socket.
+    const fn fallback() -> Self {
+        Self {
+            threads_per_core: 1,
+            cores_per_socket: 1,
+        }
+    }
+
+    /// Returns the number of hardware threads (logical CPUs) per socket.
+    pub fn threads_per_socket(&self) -> u32 {
+        self.threads_per_core * self.cores_per_socket
+    }
+}
+
+static CACHE_LINE_SIZE: Once<usize> = Once::new();
+static TOPOLOGY: Once<CpuTopology> = Once::new();
+
+/// Returns the size of a cache line in bytes.
+///
+/// It is always a power of two.
+pub fn cache_line_size() -> usize {
+    *CACHE_LINE_SIZE.call_once(detect_cache_line_size)
+}
+
+/// Returns the topology of the CPUs in a socket.
+pub fn topology() -> CpuTopology {
+    *TOPOLOGY.call_once(detect_topology)
+}
+
+/// Detects the cache line size and the CPU topology.
+///
+/// The information is detected lazily if this function is not called, but it
+/// is better to do it once at boot.
+pub(crate) fn init() {
+    let cache_line_size = cache_line_size();
+    let topology = topology();
+    log::info!(
+        "cache line size: {} bytes, topology: {:?}",
+        cache_line_size,
+        topology
+    );
+}
+
+fn max_leaf() -> u32 {
+    // SAFETY: The `CPUID` instruction is always available on x86-64.
+    unsafe { __cpuid(0) }.eax
+}
+
+fn detect_cache_line_size() -> usize {
+    // SAFETY: The `CPUID` instruction is always available on x86-64.
+    let leaf1 = unsafe { __cpuid(1) };
+    // CPUID.01H:EBX[15:8] is the `CLFLUSH` line size in 8-byte units.
+    let size = ((leaf1.ebx >> 8) & 0xff) as usize * 8;
+
+    if size.is_power_of_two() && CACHE_LINE_SIZE_RANGE.contains(&size) {
+        size
+    } else {
+        log::warn!(
+            "bogus cache line size {} reported by CPUID, assuming {}",
+            size,
+            DEFAULT_CACHE_LINE_SIZE
+        );
+        DEFAULT_CACHE_LINE_SIZE
+    }
+}
+
+fn detect_topology() -> CpuTopology {
+    detect_extended_topology()
+        .or_else(detect_legacy_topology)
+        .unwrap_or_else(|| {
+            log::warn!("incomplete CPU topology reported by CPUID, assuming no SMT");
+            CpuTopology::fallback()
+        })
+}
+
+/// Detects the topology with the extended topology enumeration leaf (0BH).
+fn detect_extended_topology() -> Option<CpuTopology> {
+    const LEAF: u32 = 0xb;
+    const LEVEL_TYPE_SMT: u32 = 1;
+    const LEVEL_TYPE_CORE: u32 = 2;
+
+    if max_leaf() < LEAF {
+        return None;
+    }
+
+    let mut threads_per_core = None;
+    let mut threads_per_socket = None;
+    // A subleaf with an invalid level type terminates the enumeration. A few
+    // subleaves are enough, while a bound protects against broken hypervisors.
+    for subleaf in 0..8 {
+        // SAFETY: The leaf is supported as checked above.
+        let res = unsafe { __cpuid_count(LEAF, subleaf) };
+        let level_type = (res.ecx >> 8) & 0xff;
+        // EBX[15:0] is the number of logical processors at this level and below.
+        let nr_logical = res.ebx & 0xffff;
+        match level_type {
+            LEVEL_TYPE_SMT => threads_per_core = Some(nr_logical),
+            LEVEL_TYPE_CORE => threads_per_socket = Some(nr_logical),
+            0 => break,
+            _ => (),
+        }
+    }
+
+    let threads_per_core = threads_per_core.filter(|nr| *nr > 0)?;
+    let threads_per_socket = threads_per_socket.filter(|nr| *nr >= threads_per_core)?;
+    Some(CpuTopology {
+        threads_per_core,
+        cores_per_socket: threads_per_socket / threads_per_core,
+    })
+}
+
+/// Detects the topology with the legacy leaves (01H and 04H).
+fn detect_legacy_topology() -> Option<CpuTopology> {
+    // SAFETY: The `CPUID` instruction is always available on x86-64.
+    let leaf1 = unsafe { __cpuid(1) };
+    // CPUID.01H:EDX[28] indicates that CPUID.01H:EBX[23:16] is valid.
+    if leaf1.edx & (1 << 28) == 0 {
+        return Some(CpuTopology::fallback());
+    }
+    let threads_per_socket = (leaf1.ebx >> 16) & 0xff;
+
+    if max_leaf() < 4 {
+        return None;
+    }
+    // SAFETY: The leaf is supported as checked above.
+    let leaf4 = unsafe { __cpuid_count(4, 0) };
+    // The leaf is reserved on some processors, e.g., the AMD ones.
+    if leaf4.eax == 0 {
+        return None;
+    }
+    // CPUID.04H:EAX[31:26] is the maximum number of cores per socket minus one.
+    let cores_per_socket = (leaf4.eax >> 26) + 1;
+
+    if threads_per_socket < cores_per_socket || threads_per_socket % cores_per_socket != 0 {
+        return None;
+    }
+    Some(CpuTopology {
+        threads_per_core: threads_per_socket / cores_per_socket,
+        cores_per_socket,
+    })
+}
+
+#[cfg(ktest)]
+mod test {
+    use super::*;
+    use crate::prelude::*;
+
+    #[ktest]
+    fn cache_line_size_is_sane() {
+        let size = cache_line_size();
+        assert!(size.is_power_of_two());
+        assert!(CACHE_LINE_SIZE_RANGE.contains(&size));
+        // The value is detected once and stays the same.
+        assert_eq!(cache_line_size(), size);
+    }
+
+    #[ktest]
+    fn topology_is_consistent() {
+        let topology = topology();
+        assert!(topology.threads_per_core >= 1);
+        assert!(topology.cores_per_socket >= 1);
+        assert_eq!(
+            topology.threads_per_socket(),
+            topology.threads_per_core * topology.cores_per_socket
+        );
+    }
+}
@@ -41,6 +41,11 @@ pub(crate) fn init() {
 }
 
 pub(crate) fn enable_local() {
+    debug_assert_eq!(
+        crate::trap::irq_disable_depth(),
+        0,
+        "enabling local IRQs while a `DisabledLocalIrqGuard` is held"
+    );
     x86_64::instructions::interrupts::enable();
     // When emulated with QEMU, interrupts may not be delivered if a STI instruction is immediately
     // followed by a RET instruction. It is a BUG of QEMU, see the following patch for details.
@@ -56,6 +61,47 @@ pub(crate) fn is_local_enabled() -> bool {
     x86_64::instructions::interrupts::are_enabled()
 }
 
+/// Enables local IRQs and halts the CPU until the next IRQ arrives.
+///
+/// The two steps are done atomically, so an IRQ that becomes pending after
+/// local IRQs are disabled still wakes up the CPU.
+pub(crate) fn enable_local_and_halt() {
+    debug_assert_eq!(
+        crate::trap::irq_disable_depth(),
+        0,
+        "halting while a `DisabledLocalIrqGuard` is held"
+    );
+    x86_64::instructions::interrupts::enable_and_hlt();
+}
+
+/// Sends an IPI with the vector `irq_num` to the CPU `cpu_id`.
+///
+/// Without local APICs there is only one CPU, so no IPI is needed.
+pub(crate) fn send_ipi(cpu_id: u32, irq_num: u8) {
+    use super::kernel::apic::{
+        self, ApicId, DeliveryMode, DeliveryStatus, DestinationMode, DestinationShorthand, Icr,
+        Level, TriggerMode,
+    };
+
+    if !apic::exists() {
+        return;
+    }
+
+    // The ID of a CPU is its local APIC ID.
+    let icr = Icr::new(
+        ApicId::from(cpu_id),
+        DestinationShorthand::NoShorthand,
+        TriggerMode::Egde,
+        Level::Assert,
+        DeliveryStatus::Idle,
+        DestinationMode::Physical,
+        DeliveryMode::Fixed,
+        irq_num,
+    );
+    // SAFETY: The fixed IPI only triggers the callbacks of the allocated IRQ line.
+    apic::borrow(|apic| unsafe { apic.send_ipi(icr) });
+}
+
 static CALLBACK_ID_ALLOCATOR: Once<Mutex<IdAlloc>> = Once::new();
 
 pub struct CallbackElement {
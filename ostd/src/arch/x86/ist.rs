@@ -0,0 +1,350 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Interrupt stacks for the critical exceptions.
+//!
+//! A double fault or a machine check may occur when the kernel stack is corrupted
+//! or overflows into its guard page, and an NMI may arrive at any instruction,
+//! including the ones that are switching the stack. So the handlers of these
+//! exceptions cannot depend on the current stack. Instead, each of them is given
+//! a dedicated stack in the interrupt stack table (IST) of the per-CPU TSS, to
+//! which the CPU switches unconditionally before pushing the exception frame.
+//!
+//! Each exception has its own IST slot, so that a critical exception nested in
+//! the handler of another one (e.g., an NMI in the double fault handler) does not
+//! overwrite the stack of the outer handler. A nested double fault or machine
+//! check shuts down the CPU. NMIs are blocked until the handler returns, but the
+//! blocking ends early if the handler returns from a nested exception with IRET.
+//! So the NMI handler moves its IST slot below the current stack pointer with
+//! [`enter_nested`] before doing anything else.
+//!
+//! When NMIs and machine checks are taken from the user mode, the trap entry
+//! expects the exception frame at the top of the kernel stack in `TSS.rsp0`,
+//! where the user context is saved. So their IDT gates point to the entries in
+//! `ist.S`, which move the exception frame there first. A double fault is an
+//! abort that never returns to the user mode, so it is not subject to this.
+
+use core::sync::atomic::{AtomicU64, Ordering};
+
+use align_ext::AlignExt;
+use x86_64::{
+    instructions::tables::{sgdt, sidt},
+    structures::tss::TaskStateSegment,
+    VirtAddr,
+};
+
+use crate::{
+    cpu::{DOUBLE_FAULT, MACHINE_CHECK, NON_MASKABLE_INTERRUPT},
+    mm::{kspace::KERNEL_PAGE_TABLE, paddr_to_vaddr, FrameAllocOptions, PageFlags, PAGE_SIZE},
+};
+
+/// The size of each interrupt stack, excluding its guard page.
+const IST_STACK_SIZE: usize = 4 * PAGE_SIZE;
+
+/// The vectors of the critical exceptions and their slots in the IST.
+const IST_VECTORS: [(u16, usize); 3] = [
+    (DOUBLE_FAULT.number, 0),
+    (NON_MASKABLE_INTERRUPT.number, 1),
+    (MACHINE_CHECK.number, 2),
+];
+
+/// The original entries of the exceptions whose IDT gates are redirected to `ist.S`.
+static NMI_ENTRY: AtomicU64 = AtomicU64::new(0);
+static MACHINE_CHECK_ENTRY: AtomicU64 = AtomicU64::new(0);
+
+core::arch::global_asm!(
+    include_str!("ist.S"),
+    nmi_entry = sym NMI_ENTRY,
+    machine_check_entry = sym MACHINE_CHECK_ENTRY,
+);
+
+extern "C" {
+    fn __ist_nmi_entry();
+    fn __ist_machine_check_entry();
+}
+
+/// Sets up the interrupt stacks on the current CPU.
+///
+/// # Safety
+///
+/// This function must be called once on each CPU, after the GDT, the TSS and
+/// the IDT of the CPU are loaded.
+pub(crate) unsafe fn init() {
+    // SAFETY: The TSS is loaded and it is only accessed by the current CPU.
+    let tss = unsafe { current_tss() };
+    let idt = sidt().base.as_mut_ptr::<u8>();
+
+    for (vector, slot) in IST_VECTORS {
+        // The address of the TSS is stored at the top of the stack for `ist.S`.
+        let stack_top = alloc_stack() - 16;
+        // SAFETY: The top 16 bytes of the stack are reserved for the address.
+        unsafe { (stack_top as *mut u64).write(core::ptr::addr_of_mut!(*tss) as u64) };
+        tss.interrupt_stack_table[slot] = VirtAddr::new(stack_top as u64);
+
+        // SAFETY: The IDT is loaded and it has a 16-byte descriptor for each vector.
+        unsafe {
+            let ist_index = ist_index_ptr(idt, vector);
+            ist_index.write_volatile((ist_index.read_volatile() & !0b111) | (slot as u8 + 1));
+        }
+    }
+
+    // SAFETY: The IDT is loaded and it has a 16-byte descriptor for each vector.
+    unsafe {
+        redirect_gate(idt, NON_MASKABLE_INTERRUPT.number, __ist_nmi_entry, &NMI_ENTRY);
+        redirect_gate(
+            idt,
+            MACHINE_CHECK.number,
+            __ist_machine_check_entry,
+            &MACHINE_CHECK_ENTRY,
+        );
+    }
+}
+
+/// Allocates an interrupt stack below a guard page, and returns the top of the stack.
+///
+/// The stack is never freed, since the CPU may switch to it at any time.
+fn alloc_stack() -> usize {
+    let segment = FrameAllocOptions::new(IST_STACK_SIZE / PAGE_SIZE + 1)
+        .alloc_contiguous()
+        .unwrap();
+    let guard_page_vaddr = paddr_to_vaddr(segment.start_paddr());
+    let stack_top = paddr_to_vaddr(segment.end_paddr());
+
+    // FIXME: modifying the the linear mapping is bad.
+    let page_table = KERNEL_PAGE_TABLE.get().unwrap();
+    // SAFETY: The segment is not used by others, so we can protect it.
+    unsafe {
+        let vaddr_range = guard_page_vaddr..guard_page_vaddr + PAGE_SIZE;
+        page_table
+            .protect_flush_tlb(&vaddr_range, |p| p.flags -= PageFlags::RW)
+            .unwrap();
+    }
+    core::mem::forget(segment);
+
+    stack_top
+}
+
+/// Redirects the IDT gate of `vector` to `new_entry`, saving the original entry in `entry`.
+///
+/// # Safety
+///
+/// `idt` must point to a loaded IDT that has a 16-byte descriptor for each vector.
+unsafe fn redirect_gate(
+    idt: *mut u8,
+    vector: u16,
+    new_entry: unsafe extern "C" fn(),
+    entry: &AtomicU64,
+) {
+    // SAFETY: The caller guarantees that the descriptor is in the IDT.
+    let gate = unsafe { idt.add(vector as usize * 16) };
+
+    // The offset of the handler is scattered in the descriptor.
+    // SAFETY: The offset fields are in the descriptor.
+    let offset = unsafe {
+        (gate as *const u16).read_unaligned() as u64
+            | ((gate.add(6) as *const u16).read_unaligned() as u64) << 16
+            | ((gate.add(8) as *const u32).read_unaligned() as u64) << 32
+    };
+    let new_offset = new_entry as usize as u64;
+    // The IDT may be shared by the CPUs, in which case it is redirected already.
+    if offset == new_offset {
+        return;
+    }
+    entry.store(offset, Ordering::Relaxed);
+
+    // SAFETY: The offset fields are in the descriptor.
+    unsafe {
+        (gate as *mut u16).write_unaligned(new_offset as u16);
+        (gate.add(6) as *mut u16).write_unaligned((new_offset >> 16) as u16);
+        (gate.add(8) as *mut u32).write_unaligned((new_offset >> 32) as u32);
+    }
+}
+
+/// Moves the interrupt stack of `vector` below the current stack pointer on the current
+/// CPU, until the returned guard is dropped.
+///
+/// The handler of a critical exception calls this function so that a nested exception
+/// of the same vector does not overwrite its stack. Returns `None` if `vector` does not
+/// use the IST.
+pub(crate) fn enter_nested(vector: u16) -> Option<NestedIstGuard> {
+    let &(_, slot) = IST_VECTORS.iter().find(|&&(v, _)| v == vector)?;
+
+    let stack_pointer: usize;
+    // SAFETY: Reading the stack pointer has no side effect.
+    unsafe {
+        core::arch::asm!("mov {0}, rsp", out(reg) stack_pointer, options(nomem, nostack));
+    }
+
+    // SAFETY: The TSS is loaded. The current CPU runs the handler of a critical exception,
+    // so no one else on the CPU is accessing the TSS.
+    let tss = unsafe { current_tss() };
+    let saved = tss.interrupt_stack_table[slot];
+    tss.interrupt_stack_table[slot] = VirtAddr::new(stack_pointer.align_down(16) as u64);
+
+    Some(NestedIstGuard { slot, saved })
+}
+
+/// A guard that restores the interrupt stack moved by [`enter_nested`].
+pub(crate) struct NestedIstGuard {
+    slot: usize,
+    saved: VirtAddr,
+}
+
+impl Drop for NestedIstGuard {
+    fn drop(&mut self) {
+        // SAFETY: The TSS is loaded and the handler of the critical exception still owns it.
+        let tss = unsafe { current_tss() };
+        tss.interrupt_stack_table[self.slot] = self.saved;
+    }
+}
+
+/// Returns a pointer to the IST index in the IDT gate descriptor of `vector`.
+///
+/// The low 3 bits of the fifth byte of an IDT gate descriptor is the index of the
+/// IST entry, where 1 refers to the first entry and 0 means that the IST is unused.
+///
+/// # Safety
+///
+/// `idt` must point to a loaded IDT that has a 16-byte descriptor for each vector.
+unsafe fn ist_index_ptr(idt: *mut u8, vector: u16) -> *mut u8 {
+    // SAFETY: The caller guarantees that the descriptor is in the IDT.
+    unsafe { idt.add(vector as usize * 16 + 4) }
+}
+
+/// Returns the TSS of the current CPU.
+///
+/// # Safety
+///
+/// The TSS must be loaded and the caller must ensure that there are no other
+/// references to the TSS.
+unsafe fn current_tss() -> &'static mut TaskStateSegment {
+    let selector: u16;
+    // SAFETY: Storing the task register has no side effect.
+    unsafe {
+        core::arch::asm!("str {0:x}", out(reg) selector, options(nomem, nostack, preserves_flags));
+    }
+
+    // A TSS descriptor takes two entries in the GDT, which contain the scattered bits of
+    // the base address.
+    let gdt = sgdt().base.as_ptr::<u64>();
+    let index = (selector >> 3) as usize;
+    // SAFETY: The selector refers to the TSS descriptor in the loaded GDT.
+    let (low, high) = unsafe { (gdt.add(index).read(), gdt.add(index + 1).read()) };
+    let base =
+        ((low >> 16) & 0xff_ffff) | (((low >> 56) & 0xff) << 24) | ((high & 0xffff_ffff) << 32);
+
+    // SAFETY: The base address refers to the loaded TSS and the caller guarantees
+    // the exclusive access.
+    unsafe { &mut *(base as *mut TaskStateSegment) }
+}
+
+#[cfg(ktest)]
+mod test {
+    use core::sync::atomic::{AtomicBool, Ordering};
+
+    use super::*;
+    use crate::{
+        arch::trap::LAST_KERNEL_NMI_FRAME,
+        cpu::{CpuException, UserContext, INVALID_OPCODE},
+        mm::{CachePolicy, FrameAllocOptions, PageFlags, PageProperty, VmIo, VmSpace},
+        prelude::*,
+        sync::SpinLock,
+        task::{Task, TaskOptions},
+        user::{ReturnReason, UserContextApi, UserMode, UserSpace},
+    };
+
+    fn ist_index(vector: u16) -> u8 {
+        let idt = sidt().base.as_mut_ptr::<u8>();
+        // SAFETY: The IDT is loaded and it has a 16-byte descriptor for each vector.
+        unsafe { ist_index_ptr(idt, vector).read_volatile() & 0b111 }
+    }
+
+    #[ktest]
+    fn critical_exceptions_use_ist() {
+        let slots = IST_VECTORS.map(|(vector, _)| ist_index(vector));
+        assert!(slots.iter().all(|slot| *slot != 0));
+        assert!(slots[0] != slots[1] && slots[1] != slots[2] && slots[0] != slots[2]);
+    }
+
+    #[ktest]
+    fn nmi_on_ist() {
+        let irq_guard = crate::trap::disable_local();
+        // SAFETY: The TSS is loaded and the local IRQs are disabled.
+        let stack_top = unsafe { current_tss() }.interrupt_stack_table[1].as_u64() as usize;
+
+        // A non-canonical address, which can never be used as a stack.
+        const BAD_STACK_TOP: usize = 0x8000_0000_0000_0000;
+
+        // SAFETY: The NMI handler runs on its interrupt stack and returns to the next
+        // instruction, where the original stack is restored. The local IRQs are disabled,
+        // so nothing else can use the bad stack.
+        unsafe {
+            core::arch::asm!(
+                "mov {saved}, rsp",
+                "mov rsp, {bad}",
+                "int 2",
+                "mov rsp, {saved}",
+                saved = out(reg) _,
+                bad = in(reg) BAD_STACK_TOP,
+            );
+        }
+
+        // Had the handler used the current stack, pushing the exception frame would
+        // have faulted and escalated into a double fault.
+        let frame_addr = LAST_KERNEL_NMI_FRAME.load();
+        assert!((stack_top - IST_STACK_SIZE..stack_top).contains(&frame_addr));
+
+        // The interrupt stack moved by the handler is restored after the handler returns.
+        // SAFETY: The TSS is loaded and the local IRQs are disabled.
+        let new_stack_top = unsafe { current_tss() }.interrupt_stack_table[1].as_u64() as usize;
+        assert_eq!(new_stack_top, stack_top);
+        drop(irq_guard);
+    }
+
+    #[ktest]
+    fn exception_from_user_mode() {
+        const CODE_VADDR: Vaddr = 0x40_0000;
+        // The `ud2` instruction, which raises an invalid opcode exception.
+        const UD2: [u8; 2] = [0x0f, 0x0b];
+
+        let frame = FrameAllocOptions::new(1).alloc_single().unwrap();
+        frame.write_bytes(0, &UD2).unwrap();
+        let vm_space = Arc::new(VmSpace::new());
+        vm_space
+            .cursor_mut(&(CODE_VADDR..CODE_VADDR + PAGE_SIZE))
+            .unwrap()
+            .map(frame, PageProperty::new(PageFlags::RX, CachePolicy::Writeback));
+
+        let mut user_ctx = UserContext::default();
+        user_ctx.set_instruction_pointer(CODE_VADDR);
+        let user_space = Arc::new(UserSpace::new(vm_space, user_ctx));
+
+        let result = Arc::new(SpinLock::new(None));
+        let is_done = Arc::new(AtomicBool::new(false));
+        let _task = {
+            let result = result.clone();
+            let is_done = is_done.clone();
+            TaskOptions::new(move || {
+                let current = Task::current().unwrap();
+                let user_space = current.user_space().unwrap();
+                let mut user_mode = UserMode::new(user_space);
+                let reason = user_mode.execute(|| false);
+                let ctx = user_mode.context();
+                *result.lock() = Some((reason, ctx.trap_number(), ctx.instruction_pointer()));
+                is_done.store(true, Ordering::Release);
+            })
+            .user_space(Some(user_space))
+            .data(())
+            .spawn()
+            .unwrap()
+        };
+        while !is_done.load(Ordering::Acquire) {
+            Task::yield_now();
+        }
+
+        // The exception is returned to the kernel with the user context intact.
+        let (reason, trap_num, ip) = result.lock().take().unwrap();
+        assert_eq!(reason, ReturnReason::UserException);
+        assert_eq!(CpuException::to_cpu_exception(trap_num as u16), Some(&INVALID_OPCODE));
+        assert_eq!(ip, CODE_VADDR);
+    }
+}
@@ -14,7 +14,7 @@ use x86::cpuid::cpuid;
 use crate::{
     arch::timer::{
         pit::{self, OperatingMode},
-        TIMER_FREQ,
+        timer_freq,
     },
     trap::IrqLine,
 };
@@ -94,9 +94,9 @@ pub fn determine_tsc_freq_via_pit() -> u64 {
         static IN_TIME: AtomicU64 = AtomicU64::new(0);
         static TSC_FIRST_COUNT: AtomicU64 = AtomicU64::new(0);
         // Set a certain times of callbacks to calculate the frequency
-        const CALLBACK_TIMES: u64 = TIMER_FREQ / 10;
+        let callback_times = timer_freq() / 10;
 
-        if IN_TIME.load(Ordering::Relaxed) < CALLBACK_TIMES || IS_FINISH.load(Ordering::Acquire) {
+        if IN_TIME.load(Ordering::Relaxed) < callback_times || IS_FINISH.load(Ordering::Acquire) {
             if IN_TIME.load(Ordering::Relaxed) == 0 {
                 unsafe {
                     TSC_FIRST_COUNT.store(_rdtsc(), Ordering::Relaxed);
@@ -109,7 +109,7 @@ pub fn determine_tsc_freq_via_pit() -> u64 {
         pit::disable_ioapic_line();
         let tsc_count = unsafe { _rdtsc() };
         let freq =
-            (tsc_count - TSC_FIRST_COUNT.load(Ordering::Relaxed)) * (TIMER_FREQ / CALLBACK_TIMES);
+            (tsc_count - TSC_FIRST_COUNT.load(Ordering::Relaxed)) * timer_freq() / callback_times;
         FREQUENCY.store(freq, Ordering::Release);
         IS_FINISH.store(true, Ordering::Release);
     }
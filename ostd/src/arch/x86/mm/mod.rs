@@ -58,6 +58,10 @@ bitflags::bitflags! {
         /// Indicates that the mapping is present in all address spaces, so it isn't flushed from
         /// the TLB on an address space switch.
         const GLOBAL =          1 << 8;
+        /// Ignored by the hardware, and is available for the software.
+        const AVAIL1 =          1 << 9;
+        /// Ignored by the hardware, and is available for the software.
+        const AVAIL2 =          1 << 10;
         /// TDX shared bit.
         #[cfg(feature = "cvm_guest")]
         const SHARED =          1 << 51;
@@ -129,6 +133,50 @@ pub unsafe fn activate_page_table(root_paddr: Paddr, root_pt_cache: CachePolicy)
     );
 }
 
+/// Writes back and invalidates all the cache lines that cover the given
+/// virtual address range.
+///
+/// The addresses in the range must be mapped.
+pub(crate) fn flush_dcache_range(range: Range<Vaddr>) {
+    for va in cache_lines(range) {
+        // SAFETY: `clflush` has no effect on the memory content; it only
+        // evicts the cache line, and the caller guarantees the address is
+        // mapped.
+        unsafe { core::arch::x86_64::_mm_clflush(va as *const u8) };
+    }
+    // SAFETY: `mfence` is always safe to execute.
+    unsafe { core::arch::x86_64::_mm_mfence() };
+}
+
+/// Invalidates all the cache lines that cover the given virtual address range.
+///
+/// x86 has no instruction that invalidates a cache line without writing it
+/// back, so this is the same as [`flush_dcache_range`].
+pub(crate) fn invalidate_dcache_range(range: Range<Vaddr>) {
+    flush_dcache_range(range);
+}
+
+/// Makes the instructions written to the given virtual address range visible
+/// to the instruction fetches of the current CPU.
+///
+/// The instruction caches of x86 are coherent with the data caches, so only
+/// a serializing instruction is needed to discard the instructions that may
+/// have been prefetched.
+pub(crate) fn flush_icache_range(_range: Range<Vaddr>) {
+    // `cpuid` is a serializing instruction.
+    let _ = x86::cpuid::cpuid!(0);
+}
+
+/// Returns the start addresses of the cache lines that cover `range`.
+///
+/// The range is rounded outward to the cache line boundaries.
+fn cache_lines(range: Range<Vaddr>) -> impl Iterator<Item = Vaddr> {
+    let cache_line_size = crate::arch::cpu::cache_line_size();
+    let start = range.start & !(cache_line_size - 1);
+    let end = if range.is_empty() { start } else { range.end };
+    (start..end).step_by(cache_line_size)
+}
+
 pub fn current_page_table_paddr() -> Paddr {
     x86_64::registers::control::Cr3::read()
         .0
@@ -190,7 +238,9 @@ impl PageTableEntryTrait for PageTableEntry {
             | parse_flags!(self.0, PageTableFlags::ACCESSED, PageFlags::ACCESSED)
             | parse_flags!(self.0, PageTableFlags::DIRTY, PageFlags::DIRTY);
         let priv_flags = parse_flags!(self.0, PageTableFlags::USER, PrivFlags::USER)
-            | parse_flags!(self.0, PageTableFlags::GLOBAL, PrivFlags::GLOBAL);
+            | parse_flags!(self.0, PageTableFlags::GLOBAL, PrivFlags::GLOBAL)
+            | parse_flags!(self.0, PageTableFlags::AVAIL1, PrivFlags::FILE_BACKED)
+            | parse_flags!(self.0, PageTableFlags::AVAIL2, PrivFlags::NO_RSS);
         #[cfg(feature = "cvm_guest")]
         let priv_flags =
             priv_flags | parse_flags!(self.0, PageTableFlags::SHARED, PrivFlags::SHARED);
@@ -227,6 +277,16 @@ impl PageTableEntryTrait for PageTableEntry {
                 prop.priv_flags.bits(),
                 PrivFlags::GLOBAL,
                 PageTableFlags::GLOBAL
+            )
+            | parse_flags!(
+                prop.priv_flags.bits(),
+                PrivFlags::FILE_BACKED,
+                PageTableFlags::AVAIL1
+            )
+            | parse_flags!(
+                prop.priv_flags.bits(),
+                PrivFlags::NO_RSS,
+                PageTableFlags::AVAIL2
             );
         #[cfg(feature = "cvm_guest")]
         {
@@ -8,8 +8,10 @@ pub mod device;
 pub(crate) mod ex_table;
 pub mod iommu;
 pub(crate) mod irq;
+pub(crate) mod ist;
 pub(crate) mod kernel;
 pub(crate) mod mm;
+pub mod msr;
 pub(crate) mod pci;
 pub mod qemu;
 pub mod serial;
@@ -31,7 +33,7 @@ cfg_if! {
 }
 
 use core::{
-    arch::x86_64::{_rdrand64_step, _rdtsc},
+    arch::x86_64::{__cpuid, __cpuid_count, _rdrand64_step, _rdseed64_step, _rdtsc},
     sync::atomic::Ordering,
 };
 
@@ -65,6 +67,7 @@ pub(crate) fn init_on_bsp() {
 
     // SAFETY: it is only called once and ACPI has been initialized.
     unsafe { crate::cpu::init() };
+    cpu::init_topology();
 
     match kernel::apic::init() {
         Ok(_) => {
@@ -82,8 +85,10 @@ pub(crate) fn init_on_bsp() {
     unsafe { crate::cpu::local::init_on_bsp() };
 
     crate::boot::smp::boot_all_aps();
+    crate::mm::tlb::init();
 
     timer::init();
+    crate::task::scheduler::init_tick_on_cpu();
 
     cfg_if! {
         if #[cfg(feature = "cvm_guest")] {
@@ -127,15 +132,21 @@ pub fn read_tsc() -> u64 {
 
 /// Reads a hardware generated 64-bit random value.
 ///
-/// Returns None if no random value was generated.
+/// Returns None if no random value was generated, or if the CPU does not
+/// support the RDRAND instruction.
 pub fn read_random() -> Option<u64> {
     // Recommendation from "Intel® Digital Random Number Generator (DRNG) Software
     // Implementation Guide" - Section 5.2.1 and "Intel® 64 and IA-32 Architectures
     // Software Developer’s Manual" - Volume 1 - Section 7.3.17.1.
     const RETRY_LIMIT: usize = 10;
 
+    if !has_rdrand() {
+        return None;
+    }
+
     for _ in 0..RETRY_LIMIT {
         let mut val = 0;
+        // SAFETY: The CPU supports the RDRAND instruction.
         let generated = unsafe { _rdrand64_step(&mut val) };
         if generated == 1 {
             return Some(val);
@@ -144,9 +155,61 @@ pub fn read_random() -> Option<u64> {
     None
 }
 
-fn has_avx512() -> bool {
-    use core::arch::x86_64::{__cpuid, __cpuid_count};
+/// Reads a hardware generated 64-bit random value that is suitable to seed
+/// a pseudo-random number generator.
+///
+/// Unlike [`read_random`], the value comes directly from the entropy source.
+/// It is slower to generate and is more likely to fail when the entropy is
+/// exhausted.
+///
+/// Returns None if no random value was generated, or if the CPU does not
+/// support the RDSEED instruction.
+pub fn read_random_seed() -> Option<u64> {
+    // Unlike RDRAND, RDSEED may fail for a longer time, so the retry limit is larger.
+    // See "Intel® Digital Random Number Generator (DRNG) Software Implementation
+    // Guide" - Section 5.3.1.
+    const RETRY_LIMIT: usize = 100;
 
+    if !has_rdseed() {
+        return None;
+    }
+
+    for _ in 0..RETRY_LIMIT {
+        let mut val = 0;
+        // SAFETY: The CPU supports the RDSEED instruction.
+        let generated = unsafe { _rdseed64_step(&mut val) };
+        if generated == 1 {
+            return Some(val);
+        }
+        core::hint::spin_loop();
+    }
+    None
+}
+
+/// Returns whether the CPU supports the RDRAND instruction.
+pub fn has_rdrand() -> bool {
+    // SAFETY: The CPUID instruction is always available on x86-64 CPUs.
+    let cpuid_result = unsafe { __cpuid(1) };
+    // Check for RDRAND (bit 30 of ecx)
+    cpuid_result.ecx & (1 << 30) != 0
+}
+
+/// Returns whether the CPU supports the RDSEED instruction.
+pub fn has_rdseed() -> bool {
+    // SAFETY: The CPUID instruction is always available on x86-64 CPUs.
+    let cpuid_result = unsafe { __cpuid(0) };
+    if cpuid_result.eax < 7 {
+        // CPUID function 7 is not supported
+        return false;
+    }
+
+    // SAFETY: The CPUID function 7 is supported.
+    let cpuid_result = unsafe { __cpuid_count(7, 0) };
+    // Check for RDSEED (bit 18 of ebx)
+    cpuid_result.ebx & (1 << 18) != 0
+}
+
+fn has_avx512() -> bool {
     let cpuid_result = unsafe { __cpuid(0) };
     if cpuid_result.eax < 7 {
         // CPUID function 7 is not supported
@@ -159,7 +222,21 @@ fn has_avx512() -> bool {
 }
 
 pub(crate) fn enable_cpu_features() {
-    use x86_64::registers::{control::Cr4Flags, model_specific::EferFlags, xcontrol::XCr0Flags};
+    use x86_64::registers::{
+        control::{Cr0Flags, Cr4Flags},
+        model_specific::EferFlags,
+        xcontrol::XCr0Flags,
+    };
+
+    // Make the kernel respect the read-only pages as well. Otherwise, a write on behalf of the
+    // user to a read-only page (e.g., a page shared until copy-on-write) would not fault, and it
+    // would silently modify the page seen by others.
+    unsafe {
+        x86_64::registers::control::Cr0::update(|cr0| {
+            *cr0 |= Cr0Flags::WRITE_PROTECT;
+        });
+    }
+
     let mut cr4 = x86_64::registers::control::Cr4::read();
     cr4 |= Cr4Flags::FSGSBASE
         | Cr4Flags::OSXSAVE
@@ -0,0 +1,130 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Model-specific registers (MSRs).
+//!
+//! Accessing an MSR that the CPU does not implement raises a general protection
+//! fault (#GP). The raw accessors, [`read_msr`] and [`write_msr`], leave it to
+//! the caller to make sure that the MSR exists, so they are `unsafe`. The typed
+//! helpers check the CPU features with CPUID first, and return `None` if the
+//! MSR is not available.
+
+use core::arch::x86_64::__cpuid;
+
+use spin::Once;
+pub use x86::msr::{IA32_APIC_BASE, IA32_EFER, IA32_TSC_DEADLINE};
+use x86_64::registers::model_specific::EferFlags;
+
+/// Reads the MSR with the given ID.
+///
+/// # Safety
+///
+/// The caller must ensure that the MSR exists on the current CPU, e.g., by
+/// checking the CPU features. Otherwise, a #GP is raised.
+pub unsafe fn read_msr(id: u32) -> u64 {
+    x86::msr::rdmsr(id)
+}
+
+/// Writes a value to the MSR with the given ID.
+///
+/// # Safety
+///
+/// The caller must ensure that the MSR exists on the current CPU and that the
+/// value is valid for it. Otherwise, a #GP is raised. Moreover, writing an MSR
+/// may change the behavior of the CPU, so the caller must ensure that the
+/// change does not break the memory safety of the kernel.
+pub unsafe fn write_msr(id: u32, value: u64) {
+    x86::msr::wrmsr(id, value)
+}
+
+// The feature bits reported by `CPUID.01H:ECX` and `CPUID.01H:EDX`.
+const CPUID_1_ECX_TSC_DEADLINE: u32 = 1 << 24;
+const CPUID_1_EDX_MSR: u32 = 1 << 5;
+const CPUID_1_EDX_APIC: u32 = 1 << 9;
+
+fn cpuid_1() -> (u32, u32) {
+    // SAFETY: The CPUID instruction is always available on x86-64 CPUs.
+    let result = unsafe { __cpuid(1) };
+    (result.ecx, result.edx)
+}
+
+/// Returns whether the CPU supports the RDMSR and WRMSR instructions.
+pub fn has_msr() -> bool {
+    cpuid_1().1 & CPUID_1_EDX_MSR != 0
+}
+
+/// Reads the extended feature enable register (`IA32_EFER`).
+///
+/// The register is always present on x86-64 CPUs, because it controls the
+/// long mode.
+pub fn read_efer() -> EferFlags {
+    // SAFETY: `IA32_EFER` is always present on x86-64 CPUs.
+    let value = unsafe { read_msr(IA32_EFER) };
+    EferFlags::from_bits_truncate(value)
+}
+
+/// Reads the physical base address of the local APIC from `IA32_APIC_BASE`.
+///
+/// Returns `None` if the CPU does not have a local APIC.
+pub fn read_apic_base() -> Option<u64> {
+    let (_, edx) = cpuid_1();
+    if edx & CPUID_1_EDX_MSR == 0 || edx & CPUID_1_EDX_APIC == 0 {
+        return None;
+    }
+
+    // SAFETY: The CPU has a local APIC, so `IA32_APIC_BASE` is present.
+    let value = unsafe { read_msr(IA32_APIC_BASE) };
+    // Bits 12 to 51 are the base address.
+    Some(value & 0x000f_ffff_ffff_f000)
+}
+
+/// Returns whether the local APIC timer supports the TSC-deadline mode.
+///
+/// The result is queried with CPUID only once, since the timer is re-armed on
+/// each tick in the TSC-deadline mode.
+pub fn has_tsc_deadline() -> bool {
+    static HAS_TSC_DEADLINE: Once<bool> = Once::new();
+
+    *HAS_TSC_DEADLINE.call_once(|| cpuid_1().0 & CPUID_1_ECX_TSC_DEADLINE != 0)
+}
+
+/// Arms the local APIC timer in the TSC-deadline mode to fire when the TSC
+/// reaches `deadline`. Writing zero disarms the timer.
+///
+/// Returns `None` if the TSC-deadline mode is not supported.
+///
+/// Note that the timer must be configured in the TSC-deadline mode for the
+/// deadline to take effect. The timer is owned by the system timer, which
+/// re-arms it on each tick, so this function is not exposed outside OSTD.
+pub(crate) fn write_tsc_deadline(deadline: u64) -> Option<()> {
+    if !has_tsc_deadline() {
+        return None;
+    }
+
+    // SAFETY: The TSC-deadline mode is supported, so `IA32_TSC_DEADLINE` is
+    // present. Arming the timer only causes a timer interrupt, which does not
+    // affect the memory safety.
+    unsafe { write_msr(IA32_TSC_DEADLINE, deadline) };
+    Some(())
+}
+
+#[cfg(ktest)]
+mod test {
+    use super::*;
+    use crate::prelude::*;
+
+    #[ktest]
+    fn efer_in_long_mode() {
+        let efer = read_efer();
+        // The kernel runs in the long mode, with the non-executable pages enabled.
+        assert!(efer.contains(EferFlags::LONG_MODE_ENABLE | EferFlags::LONG_MODE_ACTIVE));
+        assert!(efer.contains(EferFlags::NO_EXECUTE_ENABLE));
+    }
+
+    #[ktest]
+    fn apic_base_is_aligned() {
+        assert!(has_msr());
+        let base = read_apic_base().unwrap();
+        assert_ne!(base, 0);
+        assert_eq!(base % 4096, 0);
+    }
+}
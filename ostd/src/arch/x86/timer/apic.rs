@@ -2,7 +2,6 @@
 
 #![allow(unused_variables)]
 
-use alloc::sync::Arc;
 use core::{
     arch::x86_64::_rdtsc,
     sync::atomic::{AtomicBool, AtomicU64, Ordering},
@@ -11,15 +10,12 @@ use core::{
 use log::info;
 use spin::Once;
 use trapframe::TrapFrame;
-use x86::{
-    cpuid::cpuid,
-    msr::{wrmsr, IA32_TSC_DEADLINE},
-};
 
-use super::TIMER_FREQ;
+use super::timer_freq;
 use crate::{
     arch::{
         kernel::tsc::init_tsc_freq,
+        msr::{has_tsc_deadline, write_tsc_deadline},
         timer::pit::OperatingMode,
         x86::kernel::{
             apic::{self, DivideConfig},
@@ -29,49 +25,78 @@ use crate::{
     trap::IrqLine,
 };
 
+/// The mode of the local APIC timers, which is the same on all CPUs.
+enum TimerMode {
+    /// The timer fires once the TSC reaches the deadline, and is re-armed on each tick.
+    TscDeadline { tsc_step: u64 },
+    /// The timer fires periodically, every `init_count` APIC timer ticks.
+    Periodic { init_count: u64 },
+}
+
+static TIMER_MODE: Once<TimerMode> = Once::new();
+
 /// Initializes APIC with tsc deadline mode or periodic mode.
 /// Return the corresponding [`IrqLine`] for the System Timer.
 pub(super) fn init() -> IrqLine {
     init_tsc_freq();
-    if is_tsc_deadline_mode_supported() {
+    let mode = if has_tsc_deadline() {
         info!("[Timer]: Enable APIC TSC deadline mode.");
-        init_tsc_mode()
+        TimerMode::TscDeadline {
+            tsc_step: TSC_FREQ.load(Ordering::Relaxed) / timer_freq(),
+        }
     } else {
         info!("[Timer]: Enable APIC periodic mode.");
-        init_periodic_mode()
-    }
-}
-
-pub(super) static APIC_TIMER_CALLBACK: Once<Arc<dyn Fn() + Sync + Send>> = Once::new();
+        TimerMode::Periodic {
+            init_count: calibrate_periodic_mode(),
+        }
+    };
+    TIMER_MODE.call_once(|| mode);
 
-/// Determines if the current system supports tsc_deadline mode APIC timer
-fn is_tsc_deadline_mode_supported() -> bool {
-    const TSC_DEADLINE_MODE_SUPPORT: u32 = 1 << 24;
-    let cpuid = cpuid!(1);
-    (cpuid.ecx & TSC_DEADLINE_MODE_SUPPORT) > 0
+    let timer_irq = IrqLine::alloc().unwrap();
+    enable_local_timer(timer_irq.num());
+    timer_irq
 }
 
-fn init_tsc_mode() -> IrqLine {
-    let timer_irq = IrqLine::alloc().unwrap();
-    // Enable tsc deadline mode
-    apic::borrow(|apic| {
-        apic.set_lvt_timer(timer_irq.num() as u64 | (1 << 18));
-    });
-    let tsc_step = TSC_FREQ.load(Ordering::Relaxed) / TIMER_FREQ;
+/// Starts the local APIC timer of the current AP, with the mode chosen by the BSP.
+///
+/// The timer interrupts are delivered to the same IRQ line as those of the BSP.
+pub(super) fn init_on_ap(timer_irq: &IrqLine) {
+    enable_local_timer(timer_irq.num());
+}
 
-    let callback = move || unsafe {
-        let tsc_value = _rdtsc();
-        let next_tsc_value = tsc_step + tsc_value;
-        wrmsr(IA32_TSC_DEADLINE, next_tsc_value);
-    };
+/// Re-arms the local APIC timer of the current CPU for the next tick, if needed.
+pub(super) fn on_tick() {
+    if let Some(TimerMode::TscDeadline { tsc_step }) = TIMER_MODE.get() {
+        arm_tsc_deadline(*tsc_step);
+    }
+}
 
-    callback.call(());
-    APIC_TIMER_CALLBACK.call_once(|| Arc::new(callback));
+fn enable_local_timer(vector: u8) {
+    match TIMER_MODE.get().unwrap() {
+        TimerMode::TscDeadline { tsc_step } => {
+            apic::borrow(|apic| {
+                apic.set_lvt_timer(vector as u64 | (1 << 18));
+            });
+            arm_tsc_deadline(*tsc_step);
+        }
+        TimerMode::Periodic { init_count } => {
+            apic::borrow(|apic| {
+                apic.set_timer_init_count(*init_count);
+                apic.set_lvt_timer(vector as u64 | (1 << 17));
+                apic.set_timer_div_config(DivideConfig::Divide64);
+            });
+        }
+    }
+}
 
-    timer_irq
+fn arm_tsc_deadline(tsc_step: u64) {
+    // SAFETY: Reading the TSC has no side effects.
+    let tsc_value = unsafe { _rdtsc() };
+    write_tsc_deadline(tsc_value + tsc_step).unwrap();
 }
 
-fn init_periodic_mode() -> IrqLine {
+/// Measures the number of APIC timer ticks in a timer period with the PIT.
+fn calibrate_periodic_mode() -> u64 {
     // Allocate IRQ
     let mut irq = IrqLine::alloc().unwrap();
     irq.on_active(pit_callback);
@@ -96,24 +121,15 @@ fn init_periodic_mode() -> IrqLine {
     x86_64::instructions::interrupts::disable();
     drop(irq);
 
-    // Init APIC Timer
-    let timer_irq = IrqLine::alloc().unwrap();
-
-    apic::borrow(|apic| {
-        apic.set_timer_init_count(INIT_COUNT.load(Ordering::Relaxed));
-        apic.set_lvt_timer(timer_irq.num() as u64 | (1 << 17));
-        apic.set_timer_div_config(DivideConfig::Divide64);
-    });
-
-    return timer_irq;
+    return INIT_COUNT.load(Ordering::Relaxed);
 
     fn pit_callback(trap_frame: &TrapFrame) {
         static IN_TIME: AtomicU64 = AtomicU64::new(0);
         static APIC_FIRST_COUNT: AtomicU64 = AtomicU64::new(0);
         // Set a certain times of callbacks to calculate the frequency
-        const CALLBACK_TIMES: u64 = TIMER_FREQ / 10;
+        let callback_times = timer_freq() / 10;
 
-        if IN_TIME.load(Ordering::Relaxed) < CALLBACK_TIMES || IS_FINISH.load(Ordering::Acquire) {
+        if IN_TIME.load(Ordering::Relaxed) < callback_times || IS_FINISH.load(Ordering::Acquire) {
             if IN_TIME.load(Ordering::Relaxed) == 0 {
                 let remain_ticks = apic::borrow(|apic| apic.timer_current_count());
                 APIC_FIRST_COUNT.store(0xFFFF_FFFF - remain_ticks, Ordering::Relaxed);
@@ -130,10 +146,12 @@ fn init_periodic_mode() -> IrqLine {
             remain_ticks
         });
         let ticks = (0xFFFF_FFFF - remain_ticks - APIC_FIRST_COUNT.load(Ordering::Relaxed))
-            / CALLBACK_TIMES;
+            / callback_times;
         info!(
             "APIC Timer ticks count:{:x}, remain ticks: {:x},Timer Freq:{} Hz",
-            ticks, remain_ticks, TIMER_FREQ
+            ticks,
+            remain_ticks,
+            timer_freq()
         );
         INIT_COUNT.store(ticks, Ordering::Release);
         IS_FINISH.store(true, Ordering::Release);
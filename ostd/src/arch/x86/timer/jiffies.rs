@@ -5,12 +5,12 @@ use core::{
     time::Duration,
 };
 
-use super::TIMER_FREQ;
+use super::timer_freq;
 
 /// Jiffies is a term used to denote the units of time measurement by the kernel.
 ///
 /// A jiffy represents one tick of the system timer interrupt,
-/// whose frequency is equal to [`TIMER_FREQ`] Hz.
+/// whose frequency is [`timer_freq`] Hz.
 #[derive(Copy, Clone, Debug)]
 pub struct Jiffies(u64);
 
@@ -34,7 +34,9 @@ impl Jiffies {
 
     /// Gets the [`Duration`] calculated from the jiffies counts.
     pub fn as_duration(self) -> Duration {
-        Duration::from_millis(self.0 * 1000 / TIMER_FREQ)
+        let freq = timer_freq();
+        Duration::from_secs(self.0 / freq)
+            + Duration::from_nanos(self.0 % freq * 1_000_000_000 / freq)
     }
 }
 
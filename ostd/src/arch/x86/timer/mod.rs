@@ -7,30 +7,60 @@ mod hpet;
 mod jiffies;
 pub(crate) mod pit;
 
-use alloc::{boxed::Box, vec::Vec};
-use core::{cell::RefCell, sync::atomic::Ordering};
+use alloc::{boxed::Box, sync::Arc, vec::Vec};
+use core::{
+    cell::RefCell,
+    sync::atomic::{AtomicBool, AtomicU64, Ordering},
+};
 
 pub use jiffies::Jiffies;
+use log::warn;
 use spin::Once;
 use trapframe::TrapFrame;
 
-use self::apic::APIC_TIMER_CALLBACK;
-use crate::{arch::x86::kernel, cpu_local, trap::IrqLine};
+use crate::{
+    arch::x86::kernel,
+    boot::{self, kcmdline::ModuleArg},
+    cpu::this_cpu,
+    cpu_local,
+    trap::IrqLine,
+};
 
-/// The timer frequency (Hz). Here we choose 1000Hz since 1000Hz is easier for unit conversion and
-/// convenient for timer. What's more, the frequency cannot be set too high or too low, 1000Hz is
-/// a modest choice.
+/// The default timer frequency (Hz). Here we choose 1000Hz since 1000Hz is easier for unit
+/// conversion and convenient for timer. What's more, the frequency cannot be set too high or too
+/// low, 1000Hz is a modest choice.
 ///
-/// For system performance reasons, this rate cannot be set too high, otherwise most of the time
-/// is spent executing timer code.
-///
-/// Due to hardware limitations, this value cannot be set too low; for example, PIT cannot accept
-/// frequencies lower than 19Hz = 1193182 / 65536 (Timer rate / Divider)
+/// The frequency can be overridden with the `ostd.timer_freq=<Hz>` kernel command line argument,
+/// so the actual frequency should be obtained with [`timer_freq`].
 pub const TIMER_FREQ: u64 = 1000;
 
+/// The minimum timer frequency (Hz).
+///
+/// Due to hardware limitations, the frequency cannot be set too low; for example, PIT cannot
+/// accept frequencies lower than 19Hz = 1193182 / 65536 (Timer rate / Divider)
+const MIN_TIMER_FREQ: u64 = 20;
+
+/// The maximum timer frequency (Hz).
+///
+/// For system performance reasons, the frequency cannot be set too high, otherwise most of the
+/// time is spent executing timer code.
+const MAX_TIMER_FREQ: u64 = 10_000;
+
+static ACTUAL_TIMER_FREQ: AtomicU64 = AtomicU64::new(TIMER_FREQ);
+
+/// Returns the frequency (Hz) of the timer interrupts, which is also the frequency of [`Jiffies`].
+///
+/// Each CPU receives the timer interrupts at this frequency if the local APIC is present.
+/// Otherwise, only the BSP receives them from the PIT.
+pub fn timer_freq() -> u64 {
+    ACTUAL_TIMER_FREQ.load(Ordering::Relaxed)
+}
+
 static TIMER_IRQ: Once<IrqLine> = Once::new();
 
 pub(super) fn init() {
+    init_timer_freq();
+
     /// In PIT mode, channel 0 is connected directly to IRQ0, which is
     /// the `IrqLine` with the `irq_num` 32 (0-31 `IrqLine`s are reserved).
     ///
@@ -48,31 +78,173 @@ pub(super) fn init() {
     TIMER_IRQ.call_once(|| timer_irq);
 }
 
+/// Starts the timer of the current AP.
+///
+/// The timer of the BSP must be initialized first, because the APs share its mode and frequency.
+/// This function waits until it is done.
+pub(crate) fn init_on_ap() {
+    let timer_irq = TIMER_IRQ.wait();
+    if kernel::apic::exists() {
+        apic::init_on_ap(timer_irq);
+    }
+}
+
+fn init_timer_freq() {
+    let Some(args) = boot::kernel_cmdline().get_module_args("ostd") else {
+        return;
+    };
+
+    for arg in args {
+        let ModuleArg::KeyVal(key, value) = arg else {
+            continue;
+        };
+        if key.as_bytes() != b"timer_freq" {
+            continue;
+        }
+
+        let Some(freq) = value
+            .to_str()
+            .ok()
+            .and_then(|value| value.parse::<u64>().ok())
+            .filter(|freq| (MIN_TIMER_FREQ..=MAX_TIMER_FREQ).contains(freq))
+        else {
+            warn!(
+                "[Timer]: Invalid timer frequency {:?}, use {} Hz instead",
+                value, TIMER_FREQ
+            );
+            continue;
+        };
+        ACTUAL_TIMER_FREQ.store(freq, Ordering::Relaxed);
+    }
+}
+
 cpu_local! {
-    static INTERRUPT_CALLBACKS: RefCell<Vec<Box<dyn Fn() + Sync + Send>>> = RefCell::new(Vec::new());
+    static INTERRUPT_CALLBACKS: RefCell<Vec<TimerCallback>> = RefCell::new(Vec::new());
+}
+
+/// A function that is executed during the system timer interruption.
+struct TimerCallback {
+    func: Box<dyn Fn() + Sync + Send>,
+    /// Whether the function has been unregistered, if it can be.
+    is_removed: Option<Arc<AtomicBool>>,
+}
+
+impl TimerCallback {
+    fn is_removed(&self) -> bool {
+        self.is_removed
+            .as_ref()
+            .is_some_and(|is_removed| is_removed.load(Ordering::Acquire))
+    }
 }
 
 /// Registers a function that will be executed during the system timer interruption.
+///
+/// The function is only executed on the current CPU.
 pub fn register_callback<F>(func: F)
 where
     F: Fn() + Sync + Send + 'static,
 {
+    push_callback(TimerCallback {
+        func: Box::new(func),
+        is_removed: None,
+    });
+}
+
+/// Registers a function like [`register_callback`], which is unregistered when the
+/// returned handle is dropped.
+pub fn register_removable_callback<F>(func: F) -> TimerCallbackHandle
+where
+    F: Fn() + Sync + Send + 'static,
+{
+    let is_removed = Arc::new(AtomicBool::new(false));
+    push_callback(TimerCallback {
+        func: Box::new(func),
+        is_removed: Some(is_removed.clone()),
+    });
+    TimerCallbackHandle { is_removed }
+}
+
+fn push_callback(callback: TimerCallback) {
     INTERRUPT_CALLBACKS
         .borrow_irq_disabled()
         .borrow_mut()
-        .push(Box::new(func));
+        .push(callback);
+}
+
+/// A handle of a function registered by [`register_removable_callback`].
+///
+/// The handle can be dropped on any CPU. The function is not executed in the timer
+/// interruptions that begin after the handle is dropped, and it is freed in the next one
+/// on the CPU that it is registered on.
+#[must_use]
+pub struct TimerCallbackHandle {
+    is_removed: Arc<AtomicBool>,
+}
+
+impl Drop for TimerCallbackHandle {
+    fn drop(&mut self) {
+        self.is_removed.store(true, Ordering::Release);
+    }
 }
 
 fn timer_callback(_: &TrapFrame) {
-    jiffies::ELAPSED.fetch_add(1, Ordering::SeqCst);
+    // All CPUs receive the timer interrupts, but the jiffies are only counted by the BSP.
+    if this_cpu() == 0 {
+        jiffies::ELAPSED.fetch_add(1, Ordering::SeqCst);
+    }
 
     let callbacks_guard = INTERRUPT_CALLBACKS.borrow_irq_disabled();
+    callbacks_guard
+        .borrow_mut()
+        .retain(|callback| !callback.is_removed());
     for callback in callbacks_guard.borrow().iter() {
-        (callback)();
+        (callback.func)();
     }
     drop(callbacks_guard);
 
-    if APIC_TIMER_CALLBACK.is_completed() {
-        APIC_TIMER_CALLBACK.get().unwrap().call(());
+    apic::on_tick();
+}
+
+#[cfg(ktest)]
+mod test {
+    use core::sync::atomic::AtomicUsize;
+
+    use super::*;
+    use crate::{
+        arch::{read_tsc, tsc_freq},
+        prelude::*,
+        task::disable_preempt,
+    };
+
+    #[ktest]
+    fn tick_rate() {
+        const INTERVAL_MS: u64 = 200;
+
+        static NR_TICKS: AtomicUsize = AtomicUsize::new(0);
+
+        // Stay on the CPU where the callback is registered.
+        let preempt_guard = disable_preempt();
+        let callback = register_removable_callback(|| {
+            NR_TICKS.fetch_add(1, Ordering::Relaxed);
+        });
+
+        let start_ticks = NR_TICKS.load(Ordering::Relaxed);
+        let start_jiffies = Jiffies::elapsed().as_u64();
+        let start_tsc = read_tsc();
+        while read_tsc() - start_tsc < tsc_freq() * INTERVAL_MS / 1000 {
+            core::hint::spin_loop();
+        }
+        let nr_ticks = (NR_TICKS.load(Ordering::Relaxed) - start_ticks) as u64;
+        let nr_jiffies = Jiffies::elapsed().as_u64() - start_jiffies;
+        drop(callback);
+        let is_bsp = this_cpu() == 0;
+        drop(preempt_guard);
+
+        // Allow a 20% error, since the timer interrupts may be delayed.
+        let expected = timer_freq() * INTERVAL_MS / 1000;
+        assert!(nr_ticks * 10 >= expected * 8 && nr_ticks * 10 <= expected * 12);
+        if is_bsp {
+            assert!(nr_jiffies.abs_diff(nr_ticks) <= 1);
+        }
     }
 }
@@ -12,7 +12,7 @@
 use crate::{
     arch::{
         kernel::IO_APIC,
-        timer::TIMER_FREQ,
+        timer::timer_freq,
         x86::device::io_port::{IoPort, WriteOnlyAccess},
     },
     trap::IrqLine,
@@ -173,9 +173,9 @@ pub(crate) fn init(operating_mode: OperatingMode) {
     );
 
     // Set timer frequency
-    const CYCLE: u32 = TIMER_RATE / TIMER_FREQ as u32;
-    CHANNEL0_PORT.write((CYCLE & 0xFF) as _);
-    CHANNEL0_PORT.write((CYCLE >> 8) as _);
+    let cycle = TIMER_RATE / timer_freq() as u32;
+    CHANNEL0_PORT.write((cycle & 0xFF) as _);
+    CHANNEL0_PORT.write((cycle >> 8) as _);
 }
 
 /// Enable the IOAPIC line that connected to PIC
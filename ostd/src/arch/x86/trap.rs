@@ -9,15 +9,18 @@ use trapframe::TrapFrame;
 
 use super::ex_table::ExTable;
 use crate::{
-    cpu::{CpuException, CpuExceptionInfo, PageFaultErrorCode, PAGE_FAULT},
+    cpu::{CpuException, CpuExceptionInfo, PageFaultErrorCode, NON_MASKABLE_INTERRUPT, PAGE_FAULT},
     cpu_local_cell,
     mm::{
-        kspace::{KERNEL_PAGE_TABLE, LINEAR_MAPPING_BASE_VADDR, LINEAR_MAPPING_VADDR_RANGE},
+        kspace::{
+            vmalloc, KERNEL_PAGE_TABLE, LINEAR_MAPPING_BASE_VADDR, LINEAR_MAPPING_VADDR_RANGE,
+            VMALLOC_VADDR_RANGE,
+        },
         page_prop::{CachePolicy, PageProperty},
         PageFlags, PrivilegedPageFlags as PrivFlags, MAX_USERSPACE_VADDR, PAGE_SIZE,
     },
     task::Task,
-    trap::call_irq_callback_functions,
+    trap::{call_irq_callback_functions, call_kernel_exception_hook, ExceptionFrame},
 };
 
 cfg_if! {
@@ -29,6 +32,8 @@ cfg_if! {
 
 cpu_local_cell! {
     static IS_KERNEL_INTERRUPTED: bool = false;
+    /// The address of the trap frame of the last NMI received in the kernel mode.
+    pub(super) static LAST_KERNEL_NMI_FRAME: usize = 0;
 }
 
 /// Returns true if this function is called within the context of an IRQ handler
@@ -50,6 +55,14 @@ extern "sysv64" fn trap_handler(f: &mut TrapFrame) {
                 handle_virtual_exception(&mut trapframe_wrapper, &ve_info);
                 *f = *trapframe_wrapper.0;
             }
+            &NON_MASKABLE_INTERRUPT => {
+                let _nested = super::ist::enter_nested(NON_MASKABLE_INTERRUPT.number);
+
+                // OSTD does not configure any source of NMIs, so there is nothing to do for
+                // an NMI. Note that the handler runs on its own interrupt stack and may
+                // interrupt any code, so it must not take any locks (even for logging).
+                LAST_KERNEL_NMI_FRAME.store(f as *const TrapFrame as usize);
+            }
             &PAGE_FAULT => {
                 let page_fault_addr = x86_64::registers::control::Cr2::read().as_u64();
                 // The actual user space implementation should be responsible
@@ -61,9 +74,13 @@ extern "sysv64" fn trap_handler(f: &mut TrapFrame) {
                 }
             }
             exception => {
+                let frame = ExceptionFrame::new(f);
+                if call_kernel_exception_hook(&frame) {
+                    return;
+                }
                 panic!(
-                    "Cannot handle kernel cpu exception:{:?}. Error code:{:x?}; Trapframe:{:#x?}.",
-                    exception, f.error_code, f
+                    "Cannot handle kernel cpu exception:{:?}.\n{:?}",
+                    exception, frame
                 );
             }
         }
@@ -102,7 +119,9 @@ fn handle_user_page_fault(f: &mut TrapFrame, page_fault_addr: u64) {
     }
 }
 
-/// FIXME: this is a hack because we don't allocate kernel space for IO memory. We are currently
+/// Populates the vmalloc areas on demand, and maps the linear mapping lazily for IO memory.
+///
+/// FIXME: the latter is a hack because we don't allocate kernel space for IO memory. We are currently
 /// using the linear mapping for IO memory. This is not a good practice.
 fn handle_kernel_page_fault(f: &TrapFrame, page_fault_vaddr: u64) {
     let error_code = PageFaultErrorCode::from_bits_truncate(f.error_code);
@@ -111,6 +130,18 @@ fn handle_kernel_page_fault(f: &TrapFrame, page_fault_vaddr: u64) {
         page_fault_vaddr as *const (), error_code
     );
 
+    if VMALLOC_VADDR_RANGE.contains(&(page_fault_vaddr as usize)) {
+        assert!(
+            !error_code.contains(PageFaultErrorCode::PRESENT),
+            "kernel page fault: the vmalloc area is accessed with wrong permissions",
+        );
+        assert!(
+            vmalloc::handle_page_fault(page_fault_vaddr as usize),
+            "kernel page fault: the address is not in a live vmalloc area or out of memory",
+        );
+        return;
+    }
+
     assert!(
         LINEAR_MAPPING_VADDR_RANGE.contains(&(page_fault_vaddr as usize)),
         "kernel page fault: the address is outside the range of the linear mapping",
@@ -40,7 +40,7 @@ pub struct MemoryRegion {
 
 impl MemoryRegion {
     /// Constructs a valid memory region.
-    pub fn new(base: usize, len: usize, typ: MemoryRegionType) -> Self {
+    pub const fn new(base: usize, len: usize, typ: MemoryRegionType) -> Self {
         MemoryRegion { base, len, typ }
     }
 
@@ -128,7 +128,11 @@ impl MemoryRegion {
 /// Truncates regions, resulting in a set of regions that does not overlap.
 ///
 /// The truncation will be done according to the type of the regions, that
-/// usable and reclaimable regions will be truncated by the unusable regions.
+/// usable and reclaimable regions will be truncated by the unusable regions,
+/// and reclaimable regions will be truncated by the usable regions. The usable
+/// or reclaimable regions that overlap or are adjacent are merged.
+///
+/// The returned regions are sorted by their base addresses.
 pub fn non_overlapping_regions_from(regions: &[MemoryRegion]) -> Vec<MemoryRegion> {
     // We should later use regions in `regions_unusable` to truncate all
     // regions in `regions_usable`.
@@ -162,8 +166,43 @@ pub fn non_overlapping_regions_from(regions: &[MemoryRegion]) -> Vec<MemoryRegio
         swap(regions_src, regions_dst);
     }
 
+    // Merge the usable regions of the same type, since the boot protocols may
+    // report the same memory more than once.
+    let (usable, reclaimable): (Vec<_>, Vec<_>) = regions_usable
+        .into_iter()
+        .partition(|r| r.typ == MemoryRegionType::Usable);
+    let usable = merged_regions_from(usable);
+    let mut reclaimable = merged_regions_from(reclaimable);
+    // The memory reported as both usable and reclaimable is usable.
+    for r_usable in &usable {
+        reclaimable = reclaimable
+            .iter()
+            .flat_map(|r_reclaimable| r_reclaimable.truncate(r_usable))
+            .collect();
+    }
+
     // Combine all the regions processed.
     let mut all_regions = regions_unusable;
-    all_regions.append(&mut regions_usable);
+    all_regions.extend(usable);
+    all_regions.extend(reclaimable);
+    all_regions.retain(|r| !r.is_empty());
+    all_regions.sort_by_key(|r| (r.base, r.typ));
     all_regions
 }
+
+/// Merges the overlapping or adjacent regions, which must be of the same type.
+fn merged_regions_from(mut regions: Vec<MemoryRegion>) -> Vec<MemoryRegion> {
+    regions.sort_by_key(|r| r.base);
+
+    let mut merged = Vec::<MemoryRegion>::with_capacity(regions.len());
+    for r in regions {
+        match merged.last_mut() {
+            Some(last) if r.base <= last.base + last.len => {
+                debug_assert_eq!(last.typ, r.typ);
+                last.len = last.len.max(r.base + r.len - last.base);
+            }
+            _ => merged.push(r),
+        }
+    }
+    merged
+}
@@ -4,7 +4,7 @@
 
 //! The architecture-independent boot module, which provides
 //!  1. a universal information getter interface from the bootloader to the
-//!     rest of OSTD;
+//!     rest of OSTD, which is built on the [`BootProtocol`] abstraction;
 //!  2. the routine booting into the actual kernel;
 //!  3. the routine booting the other processors in the SMP context.
 
@@ -17,7 +17,7 @@ use alloc::{string::String, vec::Vec};
 use kcmdline::KCmdlineArg;
 use spin::Once;
 
-use self::memory_region::MemoryRegion;
+use self::memory_region::{non_overlapping_regions_from, MemoryRegion, MemoryRegionType};
 
 /// ACPI information from the bootloader.
 ///
@@ -37,7 +37,7 @@ pub enum BootloaderAcpiArg {
 }
 
 /// The framebuffer arguments.
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub struct BootloaderFramebufferArg {
     /// The address of the buffer.
     pub address: usize,
@@ -45,62 +45,140 @@ pub struct BootloaderFramebufferArg {
     pub width: usize,
     /// The height of the buffer.
     pub height: usize,
+    /// The number of bytes in each row of the buffer.
+    pub pitch: usize,
     /// Bits per pixel of the buffer.
     pub bpp: usize,
 }
 
-macro_rules! define_global_static_boot_arguments {
-    ( $( $lower:ident, $upper:ident, $typ:ty; )* ) => {
-        // Define statics and corresponding public getter APIs.
-        $(
-            static $upper: Once<$typ> = Once::new();
-            /// Macro generated public getter API.
-            pub fn $lower() -> &'static $typ {
-                $upper.get().unwrap()
-            }
-        )*
+/// The boot information passed by the bootloader.
+///
+/// The information is normalized from the [`BootProtocol`] that the kernel
+/// is booted with, so the rest of the kernel does not need to care about the
+/// differences between the boot protocols.
+#[derive(Debug)]
+pub struct BootInfo {
+    /// The name of the bootloader.
+    pub bootloader_name: String,
+    /// The kernel command line arguments.
+    pub kernel_cmdline: KCmdlineArg,
+    /// The initramfs, if loaded by the bootloader.
+    pub initramfs: Option<&'static [u8]>,
+    /// The ACPI information.
+    pub acpi_arg: BootloaderAcpiArg,
+    /// The framebuffer, if set up by the bootloader.
+    pub framebuffer_arg: Option<BootloaderFramebufferArg>,
+    /// The non-overlapping physical memory regions.
+    pub memory_regions: Vec<MemoryRegion>,
+}
 
-        struct BootInitCallBacks {
-            $( $lower: fn(&'static Once<$typ>) -> (), )*
-        }
+/// A boot protocol that the kernel can be booted with.
+///
+/// Each boot protocol has its own entry point, which registers the protocol
+/// with [`register_boot_protocol`] before calling [`call_ostd_main`]. The
+/// methods are called after the heap is initialized to build the [`BootInfo`].
+pub trait BootProtocol: Sync {
+    /// Returns the name of the bootloader.
+    fn bootloader_name(&self) -> String;
+
+    /// Returns the kernel command line.
+    fn kernel_cmdline(&self) -> &str;
+
+    /// Returns the initramfs, if any.
+    ///
+    /// The slice must be composed of virtual addresses since the kernel reads
+    /// everything in virtual addresses.
+    fn initramfs(&self) -> Option<&'static [u8]>;
+
+    /// Returns the ACPI information.
+    fn acpi_arg(&self) -> BootloaderAcpiArg;
 
-        static BOOT_INIT_CALLBACKS: Once<BootInitCallBacks> = Once::new();
-
-        /// The macro generated boot init callbacks registering interface.
-        ///
-        /// For the introduction of a new boot protocol, the entry point could be a novel
-        /// one. The entry point function should register all the boot initialization
-        /// methods before `ostd::main` is called. A boot initialization method takes a
-        /// reference of the global static boot information variable and initialize it,
-        /// so that the boot information it represents could be accessed in the kernel
-        /// anywhere.
-        ///
-        /// The reason why the entry point function is not designed to directly initialize
-        /// the boot information variables is simply that the heap is not initialized at
-        /// that moment.
-        pub fn register_boot_init_callbacks($( $lower: fn(&'static Once<$typ>) -> (), )* ) {
-            BOOT_INIT_CALLBACKS.call_once(|| {
-                BootInitCallBacks { $( $lower, )* }
-            });
+    /// Returns the framebuffer, if any.
+    fn framebuffer_arg(&self) -> Option<BootloaderFramebufferArg>;
+
+    /// Returns the physical memory regions.
+    ///
+    /// The regions may overlap. The region of the framebuffer returned by
+    /// [`Self::framebuffer_arg`] does not need to be included.
+    fn memory_regions(&self) -> Vec<MemoryRegion>;
+}
+
+impl BootInfo {
+    /// Collects the boot information from a boot protocol.
+    pub fn from_protocol(protocol: &dyn BootProtocol) -> Self {
+        // Some bootloaders fill zeros if there is no framebuffer.
+        let framebuffer_arg = protocol
+            .framebuffer_arg()
+            .filter(|fb| fb.address != 0 && fb.width != 0 && fb.height != 0);
+
+        let mut regions = protocol.memory_regions();
+        if let Some(fb) = framebuffer_arg {
+            regions.push(MemoryRegion::new(
+                fb.address,
+                fb.pitch * fb.height,
+                MemoryRegionType::Framebuffer,
+            ));
         }
 
-        fn call_all_boot_init_callbacks() {
-            let callbacks = &BOOT_INIT_CALLBACKS.get().unwrap();
-            $( (callbacks.$lower)(&$upper); )*
+        Self {
+            bootloader_name: protocol.bootloader_name(),
+            kernel_cmdline: protocol.kernel_cmdline().into(),
+            initramfs: protocol
+                .initramfs()
+                .filter(|initramfs| !initramfs.is_empty()),
+            acpi_arg: protocol.acpi_arg(),
+            framebuffer_arg,
+            memory_regions: non_overlapping_regions_from(&regions),
         }
-    };
+    }
+}
+
+static BOOT_PROTOCOL: Once<&'static dyn BootProtocol> = Once::new();
+static BOOT_INFO: Once<BootInfo> = Once::new();
+
+/// Registers the boot protocol that the kernel is booted with.
+///
+/// The entry point of each boot protocol should call this function before
+/// [`call_ostd_main`]. The boot information is not collected here because
+/// the heap is not initialized at that moment.
+pub fn register_boot_protocol(protocol: &'static dyn BootProtocol) {
+    BOOT_PROTOCOL.call_once(|| protocol);
+}
+
+/// Returns the boot information.
+pub fn boot_info() -> &'static BootInfo {
+    BOOT_INFO.get().unwrap()
+}
+
+/// Returns the name of the bootloader.
+pub fn bootloader_name() -> &'static String {
+    &boot_info().bootloader_name
+}
+
+/// Returns the kernel command line arguments.
+pub fn kernel_cmdline() -> &'static KCmdlineArg {
+    &boot_info().kernel_cmdline
 }
 
-// Define a series of static variables and their getter APIs.
-define_global_static_boot_arguments!(
-    //  Getter Names     |  Static Variables  | Variable Types
-    bootloader_name,        BOOTLOADER_NAME,    String;
-    kernel_cmdline,         KERNEL_CMDLINE,     KCmdlineArg;
-    initramfs,              INITRAMFS,          &'static [u8];
-    acpi_arg,               ACPI_ARG,           BootloaderAcpiArg;
-    framebuffer_arg,        FRAMEBUFFER_ARG,    BootloaderFramebufferArg;
-    memory_regions,         MEMORY_REGIONS,     Vec<MemoryRegion>;
-);
+/// Returns the initramfs, if loaded by the bootloader.
+pub fn initramfs() -> Option<&'static [u8]> {
+    boot_info().initramfs
+}
+
+/// Returns the ACPI information.
+pub fn acpi_arg() -> &'static BootloaderAcpiArg {
+    &boot_info().acpi_arg
+}
+
+/// Returns the framebuffer, if set up by the bootloader.
+pub fn framebuffer_arg() -> Option<&'static BootloaderFramebufferArg> {
+    boot_info().framebuffer_arg.as_ref()
+}
+
+/// Returns the non-overlapping physical memory regions.
+pub fn memory_regions() -> &'static Vec<MemoryRegion> {
+    &boot_info().memory_regions
+}
 
 /// The initialization method of the boot module.
 ///
@@ -108,7 +186,8 @@ define_global_static_boot_arguments!(
 /// The initialization must be done after the heap is set and before physical
 /// mappings are cancelled.
 pub fn init() {
-    call_all_boot_init_callbacks();
+    let protocol = BOOT_PROTOCOL.get().unwrap();
+    BOOT_INFO.call_once(|| BootInfo::from_protocol(*protocol));
 }
 
 /// Calls the OSTD-user defined entrypoint of the actual kernel.
@@ -164,3 +243,141 @@ fn run_ktests(test_whitelist: Option<&[&str]>, crate_whitelist: Option<&[&str]>)
         KtestResult::Failed => exit_qemu(QemuExitCode::Failed),
     };
 }
+
+#[cfg(ktest)]
+mod test {
+    use alloc::vec;
+
+    use super::*;
+    use crate::prelude::*;
+
+    const USABLE: MemoryRegion = MemoryRegion::new(0, 0x10_0000, MemoryRegionType::Usable);
+    const RESERVED: MemoryRegion = MemoryRegion::new(0x8000, 0x1000, MemoryRegionType::Reserved);
+
+    struct MockProtocol {
+        initramfs: Option<&'static [u8]>,
+        framebuffer_arg: Option<BootloaderFramebufferArg>,
+    }
+
+    impl BootProtocol for MockProtocol {
+        fn bootloader_name(&self) -> String {
+            String::from("mock")
+        }
+
+        fn kernel_cmdline(&self) -> &str {
+            "init=/bin/sh"
+        }
+
+        fn initramfs(&self) -> Option<&'static [u8]> {
+            self.initramfs
+        }
+
+        fn acpi_arg(&self) -> BootloaderAcpiArg {
+            BootloaderAcpiArg::Rsdp(0xe0000)
+        }
+
+        fn framebuffer_arg(&self) -> Option<BootloaderFramebufferArg> {
+            self.framebuffer_arg
+        }
+
+        fn memory_regions(&self) -> Vec<MemoryRegion> {
+            vec![USABLE, RESERVED]
+        }
+    }
+
+    fn regions_of_type(info: &BootInfo, typ: MemoryRegionType) -> Vec<MemoryRegion> {
+        info.memory_regions
+            .iter()
+            .filter(|r| r.typ() == typ)
+            .copied()
+            .collect()
+    }
+
+    #[ktest]
+    fn normalize_boot_info() {
+        let framebuffer_arg = BootloaderFramebufferArg {
+            address: 0x8000_0000,
+            width: 4,
+            height: 2,
+            pitch: 16,
+            bpp: 32,
+        };
+        let info = BootInfo::from_protocol(&MockProtocol {
+            initramfs: Some(&[]),
+            framebuffer_arg: Some(framebuffer_arg),
+        });
+
+        assert_eq!(info.bootloader_name, "mock");
+        assert_eq!(info.kernel_cmdline.get_initproc_path(), Some("/bin/sh"));
+        // An empty initramfs is the same as no initramfs.
+        assert!(info.initramfs.is_none());
+        assert!(matches!(info.acpi_arg, BootloaderAcpiArg::Rsdp(0xe0000)));
+        assert_eq!(info.framebuffer_arg, Some(framebuffer_arg));
+
+        // The framebuffer region is added.
+        assert_eq!(
+            regions_of_type(&info, MemoryRegionType::Framebuffer),
+            vec![MemoryRegion::new(
+                0x8000_0000,
+                4 * 2 * 4,
+                MemoryRegionType::Framebuffer
+            )]
+        );
+        // The usable region is truncated by the reserved one.
+        assert_eq!(
+            regions_of_type(&info, MemoryRegionType::Usable),
+            vec![
+                MemoryRegion::new(0, 0x8000, MemoryRegionType::Usable),
+                MemoryRegion::new(0x9000, 0xf7000, MemoryRegionType::Usable),
+            ]
+        );
+        assert_eq!(
+            regions_of_type(&info, MemoryRegionType::Reserved),
+            vec![RESERVED]
+        );
+    }
+
+    #[ktest]
+    fn missing_framebuffer() {
+        let info = BootInfo::from_protocol(&MockProtocol {
+            initramfs: None,
+            framebuffer_arg: None,
+        });
+        assert!(info.framebuffer_arg.is_none());
+        assert!(regions_of_type(&info, MemoryRegionType::Framebuffer).is_empty());
+
+        // Some protocols report a zeroed framebuffer if there is none.
+        let info = BootInfo::from_protocol(&MockProtocol {
+            initramfs: None,
+            framebuffer_arg: Some(BootloaderFramebufferArg {
+                address: 0,
+                width: 0,
+                height: 0,
+                pitch: 0,
+                bpp: 0,
+            }),
+        });
+        assert!(info.framebuffer_arg.is_none());
+        assert!(regions_of_type(&info, MemoryRegionType::Framebuffer).is_empty());
+    }
+
+    #[ktest]
+    fn expose_initramfs() {
+        static INITRAMFS: [u8; 6] = *b"070701";
+
+        let info = BootInfo::from_protocol(&MockProtocol {
+            initramfs: Some(&INITRAMFS),
+            framebuffer_arg: None,
+        });
+        let initramfs = info.initramfs.unwrap();
+        assert_eq!(initramfs, b"070701");
+        // The slice refers to the memory passed by the bootloader without copying.
+        assert_eq!(initramfs.as_ptr(), INITRAMFS.as_ptr());
+
+        let info = BootInfo::from_protocol(&MockProtocol {
+            initramfs: None,
+            framebuffer_arg: None,
+        });
+        assert!(info.initramfs.is_none());
+    }
+}
@@ -130,6 +130,11 @@ fn ap_early_entry(local_apic_id: u32) -> ! {
         .is_started
         .store(true, Ordering::Release);
 
+    // The timer can only be started after the BSP has initialized its own timer,
+    // which happens after all the APs have been started.
+    crate::arch::timer::init_on_ap();
+    crate::task::scheduler::init_tick_on_cpu();
+
     log::info!("Processor {} started. Spinning for tasks.", local_apic_id);
 
     let ap_late_entry = AP_LATE_ENTRY.wait();
@@ -0,0 +1,249 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! A capacity-bounded cache that evicts the least recently used entries.
+//!
+//! The cache is designed to cache the results of lookups, such as the dentries
+//! found during path resolution. An entry that must stay in the cache, e.g., the
+//! dentry of an opened file, can be pinned so that it is never evicted.
+
+use alloc::collections::BTreeMap;
+
+/// A cache that holds at most `capacity` entries and evicts the least recently
+/// used (LRU) entry when it is full.
+///
+/// An entry becomes the most recently used (MRU) one when it is inserted, updated,
+/// or accessed with [`LruCache::get`] or [`LruCache::get_mut`]. Pinned entries are
+/// never evicted, but they still count toward the capacity.
+#[derive(Debug)]
+pub struct LruCache<K, V> {
+    capacity: usize,
+    entries: BTreeMap<K, Entry<V>>,
+    /// The keys of the unpinned entries, indexed by their stamps. So the first key is
+    /// that of the LRU entry.
+    lru_order: BTreeMap<u64, K>,
+    /// The stamp for the next access. It grows with each access.
+    next_stamp: u64,
+}
+
+#[derive(Debug)]
+struct Entry<V> {
+    value: V,
+    /// The stamp of the last access.
+    stamp: u64,
+    pin_count: usize,
+}
+
+impl<K: Ord + Clone, V> LruCache<K, V> {
+    /// Creates an empty cache that can hold at most `capacity` entries.
+    ///
+    /// If `capacity` is zero, the cache is disabled and nothing is cached.
+    pub const fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: BTreeMap::new(),
+            lru_order: BTreeMap::new(),
+            next_stamp: 0,
+        }
+    }
+
+    /// Returns the maximum number of entries.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Returns the number of entries.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns whether the cache is empty.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Returns whether the cache contains an entry for `key`.
+    ///
+    /// This method does not make the entry the MRU one.
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.entries.contains_key(key)
+    }
+
+    /// Returns a reference to the value of `key` and makes the entry the MRU one.
+    pub fn get(&mut self, key: &K) -> Option<&V> {
+        self.get_mut(key).map(|value| &*value)
+    }
+
+    /// Returns a mutable reference to the value of `key` and makes the entry the MRU one.
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        let stamp = self.alloc_stamp();
+        let entry = self.entries.get_mut(key)?;
+        Self::touch(&mut self.lru_order, key, entry, stamp);
+        Some(&mut entry.value)
+    }
+
+    /// Returns a reference to the value of `key` without making the entry the MRU one.
+    pub fn peek(&self, key: &K) -> Option<&V> {
+        self.entries.get(key).map(|entry| &entry.value)
+    }
+
+    /// Inserts an entry, or updates the value if an entry for `key` exists.
+    /// Either way, the entry becomes the MRU one.
+    ///
+    /// If the cache is full, the LRU entry that is not pinned is evicted and
+    /// returned. If all the entries are pinned, or the capacity is zero, the
+    /// new entry is not inserted and is returned instead.
+    pub fn put(&mut self, key: K, value: V) -> Option<(K, V)> {
+        let stamp = self.alloc_stamp();
+
+        if let Some(entry) = self.entries.get_mut(&key) {
+            entry.value = value;
+            Self::touch(&mut self.lru_order, &key, entry, stamp);
+            return None;
+        }
+
+        let mut evicted = None;
+        if self.entries.len() >= self.capacity {
+            let Some((_, lru_key)) = self.lru_order.pop_first() else {
+                return Some((key, value));
+            };
+            let lru_entry = self.entries.remove(&lru_key).unwrap();
+            evicted = Some((lru_key, lru_entry.value));
+        }
+
+        self.lru_order.insert(stamp, key.clone());
+        self.entries.insert(
+            key,
+            Entry {
+                value,
+                stamp,
+                pin_count: 0,
+            },
+        );
+        evicted
+    }
+
+    /// Removes the entry for `key` and returns its value, even if the entry is pinned.
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        let entry = self.entries.remove(key)?;
+        if entry.pin_count == 0 {
+            self.lru_order.remove(&entry.stamp);
+        }
+        Some(entry.value)
+    }
+
+    /// Pins the entry for `key` so that it will not be evicted.
+    ///
+    /// An entry can be pinned multiple times, and it can be evicted again after it
+    /// is unpinned as many times. Returns `false` if there is no entry for `key`.
+    pub fn pin(&mut self, key: &K) -> bool {
+        let Some(entry) = self.entries.get_mut(key) else {
+            return false;
+        };
+        if entry.pin_count == 0 {
+            self.lru_order.remove(&entry.stamp);
+        }
+        entry.pin_count += 1;
+        true
+    }
+
+    /// Unpins the entry for `key`.
+    ///
+    /// Returns `false` if there is no entry for `key` or the entry is not pinned.
+    pub fn unpin(&mut self, key: &K) -> bool {
+        let Some(entry) = self.entries.get_mut(key) else {
+            return false;
+        };
+        if entry.pin_count == 0 {
+            return false;
+        }
+        entry.pin_count -= 1;
+        if entry.pin_count == 0 {
+            self.lru_order.insert(entry.stamp, key.clone());
+        }
+        true
+    }
+
+    /// Removes all the entries, including the pinned ones.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.lru_order.clear();
+    }
+
+    fn alloc_stamp(&mut self) -> u64 {
+        let stamp = self.next_stamp;
+        self.next_stamp += 1;
+        stamp
+    }
+
+    /// Updates the stamp of `entry` to make it the MRU one.
+    fn touch(lru_order: &mut BTreeMap<u64, K>, key: &K, entry: &mut Entry<V>, stamp: u64) {
+        if entry.pin_count == 0 {
+            lru_order.remove(&entry.stamp);
+            lru_order.insert(stamp, key.clone());
+        }
+        entry.stamp = stamp;
+    }
+}
+
+#[cfg(ktest)]
+mod test {
+    use super::*;
+    use crate::prelude::*;
+
+    #[ktest]
+    fn evict_lru_entry() {
+        let mut cache = LruCache::new(3);
+        for key in 0..3 {
+            assert!(cache.put(key, key * 10).is_none());
+        }
+
+        // Accessing the oldest entry makes the second oldest one the LRU entry.
+        assert_eq!(cache.get(&0), Some(&0));
+        assert_eq!(cache.put(3, 30), Some((1, 10)));
+        assert_eq!(cache.put(4, 40), Some((2, 20)));
+
+        assert_eq!(cache.len(), 3);
+        assert!(!cache.contains_key(&1));
+        assert_eq!(cache.peek(&0), Some(&0));
+    }
+
+    #[ktest]
+    fn update_existing_key() {
+        let mut cache = LruCache::new(2);
+        cache.put(0, 0);
+        cache.put(1, 10);
+
+        // The update makes the entry the MRU one without evicting anything.
+        assert!(cache.put(0, 1).is_none());
+        assert_eq!(cache.put(2, 20), Some((1, 10)));
+        assert_eq!(cache.peek(&0), Some(&1));
+    }
+
+    #[ktest]
+    fn pinned_entry_is_not_evicted() {
+        let mut cache = LruCache::new(2);
+        cache.put(0, 0);
+        cache.put(1, 10);
+        assert!(cache.pin(&0));
+
+        assert_eq!(cache.put(2, 20), Some((1, 10)));
+        assert!(cache.pin(&2));
+        // All the entries are pinned, so the new entry cannot be cached.
+        assert_eq!(cache.put(3, 30), Some((3, 30)));
+
+        // After being unpinned, the entry can be evicted again.
+        assert!(cache.unpin(&0));
+        assert!(!cache.unpin(&0));
+        assert_eq!(cache.put(3, 30), Some((0, 0)));
+        assert_eq!(cache.remove(&2), Some(20));
+        assert_eq!(cache.len(), 1);
+    }
+
+    #[ktest]
+    fn zero_capacity() {
+        let mut cache = LruCache::new(0);
+        assert_eq!(cache.put(0, 0), Some((0, 0)));
+        assert!(cache.is_empty());
+        assert!(cache.get(&0).is_none());
+    }
+}
@@ -1,4 +1,6 @@
 // SPDX-License-Identifier: MPL-2.0
 
 //! This module provides some advanced collections.
+pub mod lru_cache;
+pub mod radix_tree;
 pub mod xarray;
@@ -0,0 +1,608 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! A radix tree that maps `u64` indices to values.
+//!
+//! The tree is designed to be used as the index structure of page caches,
+//! where the keys are page indices. Besides the basic map operations, each
+//! entry can be attached with tags (see [`RadixTreeTag`]), and the entries
+//! with a specific tag can be iterated efficiently, which is useful for
+//! scanning the dirty pages during writeback.
+//!
+//! The height of the tree grows and shrinks with the largest index in it, so
+//! small indices are reached with few levels. Only the nodes along the paths
+//! to the present entries are allocated, so a very large index in a sparse
+//! tree costs at most one node per level.
+
+use alloc::boxed::Box;
+use core::ops::{Bound, RangeBounds};
+
+/// The number of index bits consumed by each level of the tree.
+const BITS_PER_LEVEL: u32 = 6;
+/// The number of slots in each node.
+const SLOTS_PER_NODE: usize = 1 << BITS_PER_LEVEL;
+/// The maximum height of the tree, with which every `u64` index fits.
+const MAX_HEIGHT: u32 = u64::BITS.div_ceil(BITS_PER_LEVEL);
+/// The number of the tags supported.
+const NR_TAGS: usize = 2;
+/// All the tags supported.
+const ALL_TAGS: [RadixTreeTag; NR_TAGS] = [RadixTreeTag::Dirty, RadixTreeTag::Writeback];
+
+/// The tags that can be attached to the entries of a [`RadixTree`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RadixTreeTag {
+    /// The entry is dirty.
+    Dirty = 0,
+    /// The entry is under writeback.
+    Writeback = 1,
+}
+
+/// A radix tree keyed by `u64` indices.
+#[derive(Debug)]
+pub struct RadixTree<V> {
+    root: Option<Box<Node<V>>>,
+    /// The number of levels of the tree. It is zero if the tree is empty.
+    height: u32,
+    len: usize,
+}
+
+#[derive(Debug)]
+struct Node<V> {
+    slots: [Option<Slot<V>>; SLOTS_PER_NODE],
+    /// The number of occupied slots.
+    count: usize,
+    /// For each tag, the bitmap of the slots whose entries (or any entries
+    /// in whose subtrees) have the tag.
+    tags: [u64; NR_TAGS],
+}
+
+#[derive(Debug)]
+enum Slot<V> {
+    Node(Box<Node<V>>),
+    Value(V),
+}
+
+impl<V> RadixTree<V> {
+    /// Creates an empty radix tree.
+    pub const fn new() -> Self {
+        Self {
+            root: None,
+            height: 0,
+            len: 0,
+        }
+    }
+
+    /// Returns the number of entries.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns whether the tree is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Gets a reference to the value at `index`.
+    pub fn get(&self, index: u64) -> Option<&V> {
+        if index > max_index(self.height) {
+            return None;
+        }
+        let mut node = self.root.as_deref()?;
+        let mut height = self.height;
+        loop {
+            match node.slots[offset(index, height)].as_ref()? {
+                Slot::Node(child) => node = child,
+                Slot::Value(value) => return Some(value),
+            }
+            height -= 1;
+        }
+    }
+
+    /// Gets a mutable reference to the value at `index`.
+    pub fn get_mut(&mut self, index: u64) -> Option<&mut V> {
+        if index > max_index(self.height) {
+            return None;
+        }
+        let mut node = self.root.as_deref_mut()?;
+        let mut height = self.height;
+        loop {
+            match node.slots[offset(index, height)].as_mut()? {
+                Slot::Node(child) => node = child,
+                Slot::Value(value) => return Some(value),
+            }
+            height -= 1;
+        }
+    }
+
+    /// Inserts a value at `index`, returning the old value if there is one.
+    ///
+    /// The tags of the entry are preserved if the entry is replaced.
+    pub fn insert(&mut self, index: u64, value: V) -> Option<V> {
+        if self.root.is_none() {
+            self.root = Some(Box::new(Node::new()));
+            self.height = height_of(index);
+        }
+        while index > max_index(self.height) {
+            self.grow();
+        }
+
+        let old_value = self
+            .root
+            .as_mut()
+            .unwrap()
+            .insert(index, self.height, value);
+        if old_value.is_none() {
+            self.len += 1;
+        }
+        old_value
+    }
+
+    /// Removes the value at `index`, returning it if there is one.
+    ///
+    /// The tags of the entry are cleared as well.
+    pub fn remove(&mut self, index: u64) -> Option<V> {
+        if index > max_index(self.height) {
+            return None;
+        }
+        let value = self.root.as_mut()?.remove(index, self.height)?;
+        self.len -= 1;
+        self.shrink();
+        Some(value)
+    }
+
+    /// Removes all the entries.
+    pub fn clear(&mut self) {
+        self.root = None;
+        self.height = 0;
+        self.len = 0;
+    }
+
+    /// Attaches `tag` to the entry at `index`.
+    ///
+    /// Returns `false` if there is no entry at `index`.
+    pub fn set_tag(&mut self, index: u64, tag: RadixTreeTag) -> bool {
+        if index > max_index(self.height) {
+            return false;
+        }
+        match self.root.as_mut() {
+            Some(root) => root.set_tag(index, self.height, tag),
+            None => false,
+        }
+    }
+
+    /// Detaches `tag` from the entry at `index`.
+    pub fn clear_tag(&mut self, index: u64, tag: RadixTreeTag) {
+        if index > max_index(self.height) {
+            return;
+        }
+        if let Some(root) = self.root.as_mut() {
+            root.clear_tag(index, self.height, tag);
+        }
+    }
+
+    /// Returns whether the entry at `index` has `tag`.
+    pub fn is_tagged(&self, index: u64, tag: RadixTreeTag) -> bool {
+        if index > max_index(self.height) {
+            return false;
+        }
+        let Some(mut node) = self.root.as_deref() else {
+            return false;
+        };
+        let mut height = self.height;
+        loop {
+            let offset = offset(index, height);
+            if !node.has_tag(offset, tag) {
+                return false;
+            }
+            match node.slots[offset].as_ref() {
+                Some(Slot::Node(child)) => node = child,
+                Some(Slot::Value(_)) => return true,
+                None => return false,
+            }
+            height -= 1;
+        }
+    }
+
+    /// Returns whether any entry in the tree has `tag`.
+    pub fn any_tagged(&self, tag: RadixTreeTag) -> bool {
+        self.root.as_ref().is_some_and(|root| root.has_any_tag(tag))
+    }
+
+    /// Returns an iterator over all the entries in the ascending order of
+    /// the indices.
+    pub fn iter(&self) -> Iter<'_, V> {
+        self.range(..)
+    }
+
+    /// Returns an iterator over the entries whose indices are in `range`,
+    /// in the ascending order of the indices.
+    pub fn range(&self, range: impl RangeBounds<u64>) -> Iter<'_, V> {
+        Iter::new(self, range, None)
+    }
+
+    /// Returns an iterator over the entries with `tag`, in the ascending
+    /// order of the indices.
+    ///
+    /// The subtrees without any entry with `tag` are skipped as a whole.
+    pub fn iter_tagged(&self, tag: RadixTreeTag) -> Iter<'_, V> {
+        self.range_tagged(.., tag)
+    }
+
+    /// Returns an iterator over the entries with `tag` whose indices are in
+    /// `range`, in the ascending order of the indices.
+    pub fn range_tagged(&self, range: impl RangeBounds<u64>, tag: RadixTreeTag) -> Iter<'_, V> {
+        Iter::new(self, range, Some(tag))
+    }
+
+    /// Adds a new level above the root.
+    fn grow(&mut self) {
+        let old_root = self.root.take().unwrap();
+        let mut new_root = Box::new(Node::new());
+        for (tag, bits) in new_root.tags.iter_mut().enumerate() {
+            if old_root.tags[tag] != 0 {
+                *bits |= 1;
+            }
+        }
+        new_root.slots[0] = Some(Slot::Node(old_root));
+        new_root.count = 1;
+        self.root = Some(new_root);
+        self.height += 1;
+    }
+
+    /// Removes the unnecessary levels above the root after removal.
+    fn shrink(&mut self) {
+        if self.len == 0 {
+            self.clear();
+            return;
+        }
+        while self.height > 1 {
+            let root = self.root.as_mut().unwrap();
+            if root.count != 1 || root.slots[0].is_none() {
+                break;
+            }
+            let Some(Slot::Node(child)) = root.slots[0].take() else {
+                unreachable!("the slots of a non-leaf node must contain nodes");
+            };
+            self.root = Some(child);
+            self.height -= 1;
+        }
+    }
+
+    /// Finds the first entry whose index is no less than `from` and that
+    /// has `tag` (if specified).
+    fn find_next(&self, from: u64, tag: Option<RadixTreeTag>) -> Option<(u64, &V)> {
+        if from > max_index(self.height) {
+            return None;
+        }
+        self.root.as_ref()?.find_next(0, self.height, from, tag)
+    }
+}
+
+impl<V> Default for RadixTree<V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<V> Node<V> {
+    fn new() -> Self {
+        Self {
+            slots: core::array::from_fn(|_| None),
+            count: 0,
+            tags: [0; NR_TAGS],
+        }
+    }
+
+    fn has_tag(&self, offset: usize, tag: RadixTreeTag) -> bool {
+        self.tags[tag as usize] & (1 << offset) != 0
+    }
+
+    fn has_any_tag(&self, tag: RadixTreeTag) -> bool {
+        self.tags[tag as usize] != 0
+    }
+
+    fn update_tag(&mut self, offset: usize, tag: RadixTreeTag, is_set: bool) {
+        if is_set {
+            self.tags[tag as usize] |= 1 << offset;
+        } else {
+            self.tags[tag as usize] &= !(1 << offset);
+        }
+    }
+
+    fn insert(&mut self, index: u64, height: u32, value: V) -> Option<V> {
+        let offset = offset(index, height);
+        if height == 1 {
+            let old_slot = self.slots[offset].replace(Slot::Value(value));
+            return match old_slot {
+                Some(Slot::Value(old_value)) => Some(old_value),
+                Some(Slot::Node(_)) => unreachable!("the slots of a leaf node must contain values"),
+                None => {
+                    self.count += 1;
+                    None
+                }
+            };
+        }
+
+        let slot = &mut self.slots[offset];
+        if slot.is_none() {
+            *slot = Some(Slot::Node(Box::new(Node::new())));
+            self.count += 1;
+        }
+        let Some(Slot::Node(child)) = slot else {
+            unreachable!("the slots of a non-leaf node must contain nodes");
+        };
+        child.insert(index, height - 1, value)
+    }
+
+    fn remove(&mut self, index: u64, height: u32) -> Option<V> {
+        let offset = offset(index, height);
+        if height == 1 {
+            let Slot::Value(value) = self.slots[offset].take()? else {
+                unreachable!("the slots of a leaf node must contain values");
+            };
+            self.count -= 1;
+            for tag in ALL_TAGS {
+                self.update_tag(offset, tag, false);
+            }
+            return Some(value);
+        }
+
+        let Slot::Node(child) = self.slots[offset].as_mut()? else {
+            unreachable!("the slots of a non-leaf node must contain nodes");
+        };
+        let value = child.remove(index, height - 1)?;
+        let is_child_empty = child.count == 0;
+        let child_tags = child.tags;
+        for tag in ALL_TAGS {
+            self.update_tag(offset, tag, child_tags[tag as usize] != 0);
+        }
+        if is_child_empty {
+            self.slots[offset] = None;
+            self.count -= 1;
+        }
+        Some(value)
+    }
+
+    fn set_tag(&mut self, index: u64, height: u32, tag: RadixTreeTag) -> bool {
+        let offset = offset(index, height);
+        let is_present = match self.slots[offset].as_mut() {
+            Some(Slot::Node(child)) => child.set_tag(index, height - 1, tag),
+            Some(Slot::Value(_)) => true,
+            None => false,
+        };
+        if is_present {
+            self.update_tag(offset, tag, true);
+        }
+        is_present
+    }
+
+    fn clear_tag(&mut self, index: u64, height: u32, tag: RadixTreeTag) {
+        let offset = offset(index, height);
+        if !self.has_tag(offset, tag) {
+            return;
+        }
+        let is_set = match self.slots[offset].as_mut() {
+            Some(Slot::Node(child)) => {
+                child.clear_tag(index, height - 1, tag);
+                child.has_any_tag(tag)
+            }
+            Some(Slot::Value(_)) | None => false,
+        };
+        self.update_tag(offset, tag, is_set);
+    }
+
+    /// Finds the first entry in the subtree whose index is no less than
+    /// `from` and that has `tag` (if specified).
+    ///
+    /// `base` is the first index covered by this node.
+    fn find_next(
+        &self,
+        base: u64,
+        height: u32,
+        from: u64,
+        tag: Option<RadixTreeTag>,
+    ) -> Option<(u64, &V)> {
+        let shift = (height - 1) * BITS_PER_LEVEL;
+        let start_offset = if from > base {
+            ((from - base) >> shift) as usize
+        } else {
+            0
+        };
+        for offset in start_offset..SLOTS_PER_NODE {
+            if tag.is_some_and(|tag| !self.has_tag(offset, tag)) {
+                continue;
+            }
+            let Some(slot) = self.slots[offset].as_ref() else {
+                continue;
+            };
+            // This never overflows since the slot is occupied by a valid index.
+            let child_base = base + ((offset as u64) << shift);
+            match slot {
+                Slot::Value(value) => return Some((child_base, value)),
+                Slot::Node(child) => {
+                    if let Some(entry) = child.find_next(child_base, height - 1, from, tag) {
+                        return Some(entry);
+                    }
+                }
+            }
+        }
+        None
+    }
+}
+
+/// An iterator over the entries of a [`RadixTree`].
+pub struct Iter<'a, V> {
+    tree: &'a RadixTree<V>,
+    /// The index to start the next search. It is `None` if the iteration
+    /// is done.
+    next: Option<u64>,
+    /// The last index (inclusive) to iterate.
+    last: u64,
+    tag: Option<RadixTreeTag>,
+}
+
+impl<'a, V> Iter<'a, V> {
+    fn new(
+        tree: &'a RadixTree<V>,
+        range: impl RangeBounds<u64>,
+        tag: Option<RadixTreeTag>,
+    ) -> Self {
+        let next = match range.start_bound() {
+            Bound::Included(&start) => Some(start),
+            Bound::Excluded(&start) => start.checked_add(1),
+            Bound::Unbounded => Some(0),
+        };
+        let (next, last) = match range.end_bound() {
+            Bound::Included(&end) => (next, end),
+            Bound::Excluded(&end) => (next.filter(|_| end > 0), end.saturating_sub(1)),
+            Bound::Unbounded => (next, u64::MAX),
+        };
+        Self {
+            tree,
+            next,
+            last,
+            tag,
+        }
+    }
+}
+
+impl<'a, V> Iterator for Iter<'a, V> {
+    type Item = (u64, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let from = self.next.filter(|from| *from <= self.last)?;
+        let Some((index, value)) = self
+            .tree
+            .find_next(from, self.tag)
+            .filter(|(index, _)| *index <= self.last)
+        else {
+            self.next = None;
+            return None;
+        };
+        self.next = index.checked_add(1);
+        Some((index, value))
+    }
+}
+
+/// Returns the largest index that a tree of `height` levels can hold.
+fn max_index(height: u32) -> u64 {
+    if height == 0 {
+        // An empty tree has no root, so no index can be found in it anyway.
+        0
+    } else if height >= MAX_HEIGHT {
+        u64::MAX
+    } else {
+        (1 << (height * BITS_PER_LEVEL)) - 1
+    }
+}
+
+/// Returns the minimum height of the tree that can hold `index`.
+fn height_of(index: u64) -> u32 {
+    let bits = u64::BITS - index.leading_zeros();
+    bits.div_ceil(BITS_PER_LEVEL).max(1)
+}
+
+/// Returns the offset of the slot that `index` goes into at the node of
+/// `height` levels.
+fn offset(index: u64, height: u32) -> usize {
+    ((index >> ((height - 1) * BITS_PER_LEVEL)) as usize) & (SLOTS_PER_NODE - 1)
+}
+
+#[cfg(ktest)]
+mod test {
+    use alloc::vec::Vec;
+
+    use super::*;
+    use crate::prelude::*;
+
+    const SCATTERED_INDICES: [u64; 7] = [0, 5, 63, 64, 4096, 1 << 40, u64::MAX];
+
+    #[ktest]
+    fn insert_get_remove() {
+        let mut tree = RadixTree::new();
+        for index in SCATTERED_INDICES {
+            assert_eq!(tree.insert(index, index), None);
+        }
+        assert_eq!(tree.len(), SCATTERED_INDICES.len());
+        assert_eq!(tree.height, MAX_HEIGHT);
+
+        for index in SCATTERED_INDICES {
+            assert_eq!(tree.get(index), Some(&index));
+        }
+        assert_eq!(tree.get(1), None);
+        assert_eq!(tree.get((1 << 40) + 1), None);
+
+        assert_eq!(tree.insert(5, 50), Some(5));
+        assert_eq!(tree.get(5), Some(&50));
+
+        assert_eq!(tree.remove(u64::MAX), Some(u64::MAX));
+        assert_eq!(tree.remove(1 << 40), Some(1 << 40));
+        assert_eq!(tree.remove(1 << 40), None);
+        // The tree shrinks after the large indices are removed.
+        assert_eq!(tree.height, height_of(4096));
+
+        for index in [0, 5, 63, 64, 4096] {
+            assert!(tree.remove(index).is_some());
+        }
+        assert!(tree.is_empty());
+        assert!(tree.root.is_none());
+    }
+
+    #[ktest]
+    fn range_iteration() {
+        let mut tree = RadixTree::new();
+        for index in SCATTERED_INDICES {
+            tree.insert(index, ());
+        }
+
+        let all = tree.iter().map(|(index, _)| index).collect::<Vec<_>>();
+        assert_eq!(all, SCATTERED_INDICES);
+
+        let some = tree
+            .range(5..4096)
+            .map(|(index, _)| index)
+            .collect::<Vec<_>>();
+        assert_eq!(some, [5, 63, 64]);
+
+        let last = tree
+            .range(u64::MAX..)
+            .map(|(index, _)| index)
+            .collect::<Vec<_>>();
+        assert_eq!(last, [u64::MAX]);
+
+        assert_eq!(tree.range(6..63).count(), 0);
+    }
+
+    #[ktest]
+    fn iterate_tagged_entries() {
+        let mut tree = RadixTree::new();
+        for index in SCATTERED_INDICES {
+            tree.insert(index, ());
+        }
+        assert!(!tree.set_tag(1, RadixTreeTag::Dirty));
+
+        for index in [5, 4096, u64::MAX] {
+            assert!(tree.set_tag(index, RadixTreeTag::Dirty));
+        }
+        assert!(tree.set_tag(64, RadixTreeTag::Writeback));
+        assert!(tree.is_tagged(4096, RadixTreeTag::Dirty));
+        assert!(!tree.is_tagged(4096, RadixTreeTag::Writeback));
+
+        let dirty = tree
+            .iter_tagged(RadixTreeTag::Dirty)
+            .map(|(index, _)| index)
+            .collect::<Vec<_>>();
+        assert_eq!(dirty, [5, 4096, u64::MAX]);
+
+        tree.clear_tag(4096, RadixTreeTag::Dirty);
+        tree.remove(u64::MAX);
+        let dirty = tree
+            .iter_tagged(RadixTreeTag::Dirty)
+            .map(|(index, _)| index)
+            .collect::<Vec<_>>();
+        assert_eq!(dirty, [5]);
+
+        tree.clear_tag(5, RadixTreeTag::Dirty);
+        assert!(!tree.any_tagged(RadixTreeTag::Dirty));
+        assert!(tree.any_tagged(RadixTreeTag::Writeback));
+    }
+}
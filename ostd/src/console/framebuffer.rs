@@ -0,0 +1,588 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The early console backed by the framebuffer that the bootloader provides.
+
+use font8x8::UnicodeFonts;
+use spin::Once;
+
+use super::EarlyConsole;
+use crate::{boot, mm::kspace::paddr_to_vaddr, sync::SpinLock};
+
+/// The width and height of a glyph in pixels.
+const GLYPH_SIZE: usize = 8;
+
+/// The 16 colors that the SGR escape sequences can select.
+///
+/// The colors are in the byte order of the pixels, i.e., blue, green, red and
+/// the unused byte.
+const PALETTE: [[u8; 4]; 16] = [
+    [0x00, 0x00, 0x00, 0], // Black
+    [0x00, 0x00, 0xaa, 0], // Red
+    [0x00, 0xaa, 0x00, 0], // Green
+    [0x00, 0x55, 0xaa, 0], // Yellow
+    [0xaa, 0x00, 0x00, 0], // Blue
+    [0xaa, 0x00, 0xaa, 0], // Magenta
+    [0xaa, 0xaa, 0x00, 0], // Cyan
+    [0xaa, 0xaa, 0xaa, 0], // White
+    [0x55, 0x55, 0x55, 0], // Bright black
+    [0x55, 0x55, 0xff, 0], // Bright red
+    [0x55, 0xff, 0x55, 0], // Bright green
+    [0x55, 0xff, 0xff, 0], // Bright yellow
+    [0xff, 0x55, 0x55, 0], // Bright blue
+    [0xff, 0x55, 0xff, 0], // Bright magenta
+    [0xff, 0xff, 0x55, 0], // Bright cyan
+    [0xff, 0xff, 0xff, 0], // Bright white
+];
+/// The palette index of the default foreground color.
+const DEFAULT_FOREGROUND: u8 = 7;
+/// The palette index of the default background color.
+const DEFAULT_BACKGROUND: u8 = 0;
+
+/// The maximum number of parameters in a CSI sequence. Extra ones are ignored.
+const MAX_CSI_PARAMS: usize = 16;
+
+static FRAMEBUFFER_CONSOLE: Once<FramebufferConsole> = Once::new();
+
+/// Initializes the framebuffer console and registers it as an early console.
+///
+/// If the bootloader does not provide a usable framebuffer, this function
+/// does nothing.
+pub(super) fn init() {
+    let Some(arg) = boot::framebuffer_arg() else {
+        return;
+    };
+
+    let bytes_per_pixel = arg.bpp / 8;
+    if !(bytes_per_pixel == 3 || bytes_per_pixel == 4) {
+        log::warn!(
+            "Framebuffer console: unsupported {} bits per pixel",
+            arg.bpp
+        );
+        return;
+    }
+    if arg.pitch < arg.width * bytes_per_pixel {
+        log::warn!("Framebuffer console: the pitch is smaller than a row of pixels");
+        return;
+    }
+    let size = arg.pitch * arg.height;
+
+    // The framebuffer is accessed through the linear mapping, so it must be
+    // covered by the physical memory that is mapped.
+    let phys_mem_cap = boot::memory_regions()
+        .iter()
+        .map(|r| r.base() + r.len())
+        .max()
+        .unwrap();
+    if arg.address + size > phys_mem_cap {
+        log::warn!("Framebuffer console: the framebuffer is not linearly mapped");
+        return;
+    }
+
+    // SAFETY: The framebuffer is reserved by the bootloader and is linearly
+    // mapped. It is only accessed through this console.
+    let buffer =
+        unsafe { core::slice::from_raw_parts_mut(paddr_to_vaddr(arg.address) as *mut u8, size) };
+    let mut writer =
+        FramebufferWriter::new(buffer, arg.width, arg.height, arg.pitch, bytes_per_pixel);
+    writer.clear();
+
+    let console = FRAMEBUFFER_CONSOLE.call_once(|| FramebufferConsole {
+        writer: SpinLock::new(writer),
+    });
+    if super::register_early_console(console).is_err() {
+        log::warn!("Framebuffer console: too many early consoles");
+    }
+}
+
+struct FramebufferConsole {
+    writer: SpinLock<FramebufferWriter<'static>>,
+}
+
+impl EarlyConsole for FramebufferConsole {
+    fn write_str(&self, s: &str) {
+        // Do not spin if the lock is held, e.g., by the code that panics while
+        // printing. Losing the output is better than a deadlock.
+        if let Some(mut writer) = self.writer.try_lock_irq_disabled() {
+            writer.write_str(s);
+        }
+    }
+}
+
+/// A text renderer on a linear framebuffer.
+///
+/// It interprets the basic ANSI escape sequences, i.e., the CSI sequences that
+/// move the cursor, erase the text and set the colors. A sequence may be split
+/// across several writes, so the parsing state is kept in the writer.
+struct FramebufferWriter<'a> {
+    buffer: &'a mut [u8],
+    width: usize,
+    height: usize,
+    /// The number of bytes in each row of pixels, which may be larger than the
+    /// bytes of the pixels in the row.
+    pitch: usize,
+    bytes_per_pixel: usize,
+    /// The column of the next glyph in pixels.
+    x_pos: usize,
+    /// The row of the next glyph in pixels.
+    y_pos: usize,
+    /// The palette index of the foreground color.
+    foreground: u8,
+    /// The palette index of the background color.
+    background: u8,
+    /// Whether the bright variants of the first eight foreground colors are used.
+    is_bold: bool,
+    escape: EscapeState,
+}
+
+/// The state of parsing an escape sequence.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum EscapeState {
+    /// Not in an escape sequence.
+    None,
+    /// After an `ESC`.
+    Escape,
+    /// In a CSI sequence, i.e., after an `ESC [`.
+    Csi(CsiParams),
+}
+
+/// The parameters of a CSI sequence that is being parsed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct CsiParams {
+    /// The numeric parameters. An omitted parameter is zero.
+    params: [u16; MAX_CSI_PARAMS],
+    /// The index of the parameter being parsed.
+    index: usize,
+    /// Whether the sequence has private or intermediate bytes, e.g., `ESC [ ? 25 l`.
+    ///
+    /// Such sequences are not supported and are ignored when completed.
+    is_ignored: bool,
+}
+
+impl CsiParams {
+    const fn new() -> Self {
+        Self {
+            params: [0; MAX_CSI_PARAMS],
+            index: 0,
+            is_ignored: false,
+        }
+    }
+
+    /// Returns the parameters that are present.
+    fn params(&self) -> &[u16] {
+        &self.params[..=self.index]
+    }
+
+    /// Returns the first parameter, or `default` if it is omitted or zero.
+    fn first_or(&self, default: u16) -> usize {
+        match self.params[0] {
+            0 => default as usize,
+            n => n as usize,
+        }
+    }
+}
+
+impl<'a> FramebufferWriter<'a> {
+    fn new(
+        buffer: &'a mut [u8],
+        width: usize,
+        height: usize,
+        pitch: usize,
+        bytes_per_pixel: usize,
+    ) -> Self {
+        debug_assert!(pitch >= width * bytes_per_pixel);
+        debug_assert_eq!(buffer.len(), pitch * height);
+        Self {
+            buffer,
+            width,
+            height,
+            pitch,
+            bytes_per_pixel,
+            x_pos: 0,
+            y_pos: 0,
+            foreground: DEFAULT_FOREGROUND,
+            background: DEFAULT_BACKGROUND,
+            is_bold: false,
+            escape: EscapeState::None,
+        }
+    }
+
+    /// The number of glyphs in a line.
+    fn nr_columns(&self) -> usize {
+        self.width / GLYPH_SIZE
+    }
+
+    /// The number of lines on the screen.
+    fn nr_rows(&self) -> usize {
+        self.height / GLYPH_SIZE
+    }
+
+    /// Erases all the text on the screen.
+    fn clear(&mut self) {
+        self.fill_rect(0, 0, self.width, self.height);
+        self.x_pos = 0;
+        self.y_pos = 0;
+    }
+
+    fn write_str(&mut self, s: &str) {
+        for c in s.chars() {
+            self.write_char(c);
+        }
+    }
+
+    fn write_char(&mut self, c: char) {
+        match self.escape {
+            EscapeState::None => (),
+            EscapeState::Escape => {
+                self.escape = if c == '[' {
+                    EscapeState::Csi(CsiParams::new())
+                } else {
+                    // Other escape sequences are not supported.
+                    EscapeState::None
+                };
+                return;
+            }
+            EscapeState::Csi(ref mut csi) => {
+                match c {
+                    '0'..='9' => {
+                        let param = &mut csi.params[csi.index];
+                        *param = param
+                            .saturating_mul(10)
+                            .saturating_add(c as u16 - '0' as u16);
+                    }
+                    ';' => {
+                        if csi.index + 1 < MAX_CSI_PARAMS {
+                            csi.index += 1;
+                        }
+                    }
+                    '\x20'..='\x2f' | '<'..='?' => csi.is_ignored = true,
+                    '\x40'..='\x7e' => {
+                        let csi = *csi;
+                        self.escape = EscapeState::None;
+                        if !csi.is_ignored {
+                            self.handle_csi(c, &csi);
+                        }
+                    }
+                    _ => {
+                        // The sequence is malformed. Drop it and handle the
+                        // character as usual.
+                        self.escape = EscapeState::None;
+                        self.write_char(c);
+                    }
+                }
+                return;
+            }
+        }
+
+        match c {
+            '\x1b' => self.escape = EscapeState::Escape,
+            '\n' => self.newline(),
+            '\r' => self.x_pos = 0,
+            c => {
+                if self.nr_columns() == 0 || self.nr_rows() == 0 {
+                    // The screen is too small to hold a glyph.
+                    return;
+                }
+                if self.x_pos + GLYPH_SIZE > self.width {
+                    self.newline();
+                }
+                if self.y_pos + GLYPH_SIZE > self.height {
+                    self.scroll_up();
+                }
+                let glyph = font8x8::BASIC_FONTS
+                    .get(c)
+                    .or_else(|| font8x8::BASIC_FONTS.get('?'))
+                    .unwrap();
+                self.write_glyph(&glyph);
+            }
+        }
+    }
+
+    /// Handles a complete CSI sequence that ends with `command`.
+    fn handle_csi(&mut self, command: char, csi: &CsiParams) {
+        let column = self.x_pos / GLYPH_SIZE;
+        let row = self.y_pos / GLYPH_SIZE;
+        match command {
+            'A' => self.move_cursor(column, row.saturating_sub(csi.first_or(1))),
+            'B' => self.move_cursor(column, row + csi.first_or(1)),
+            'C' => self.move_cursor(column + csi.first_or(1), row),
+            'D' => self.move_cursor(column.saturating_sub(csi.first_or(1)), row),
+            'G' => self.move_cursor(csi.first_or(1) - 1, row),
+            'H' | 'f' => {
+                // The row and the column are one-based.
+                let column = csi.params().get(1).map_or(1, |&n| n.max(1) as usize);
+                self.move_cursor(column - 1, csi.first_or(1) - 1);
+            }
+            'J' => self.erase_display(csi.params[0]),
+            'K' => self.erase_line(csi.params[0]),
+            'm' => self.set_graphic_rendition(csi.params()),
+            // Other sequences are not supported.
+            _ => (),
+        }
+    }
+
+    /// Moves the cursor to the glyph cell at `(column, row)`, which is clamped to the screen.
+    fn move_cursor(&mut self, column: usize, row: usize) {
+        self.x_pos = column.min(self.nr_columns().saturating_sub(1)) * GLYPH_SIZE;
+        self.y_pos = row.min(self.nr_rows().saturating_sub(1)) * GLYPH_SIZE;
+    }
+
+    /// Erases the part of the screen selected by the parameter of `ESC [ n J`.
+    fn erase_display(&mut self, mode: u16) {
+        let line_end = self.y_pos + GLYPH_SIZE;
+        match mode {
+            // From the cursor to the end of the screen.
+            0 => {
+                self.erase_line(0);
+                self.fill_rect(
+                    0,
+                    line_end,
+                    self.width,
+                    self.height.saturating_sub(line_end),
+                );
+            }
+            // From the start of the screen to the cursor.
+            1 => {
+                self.fill_rect(0, 0, self.width, self.y_pos);
+                self.erase_line(1);
+            }
+            // The whole screen. The cursor does not move.
+            2 | 3 => self.fill_rect(0, 0, self.width, self.height),
+            _ => (),
+        }
+    }
+
+    /// Erases the part of the current line selected by the parameter of `ESC [ n K`.
+    fn erase_line(&mut self, mode: u16) {
+        let (x, y) = (self.x_pos.min(self.width), self.y_pos);
+        match mode {
+            // From the cursor to the end of the line.
+            0 => self.fill_rect(x, y, self.width - x, GLYPH_SIZE),
+            // From the start of the line to the cursor, inclusive.
+            1 => self.fill_rect(0, y, (x + GLYPH_SIZE).min(self.width), GLYPH_SIZE),
+            // The whole line.
+            2 => self.fill_rect(0, y, self.width, GLYPH_SIZE),
+            _ => (),
+        }
+    }
+
+    /// Sets the colors according to the parameters of `ESC [ ... m`.
+    fn set_graphic_rendition(&mut self, params: &[u16]) {
+        for &param in params {
+            match param {
+                0 => {
+                    self.foreground = DEFAULT_FOREGROUND;
+                    self.background = DEFAULT_BACKGROUND;
+                    self.is_bold = false;
+                }
+                1 => self.is_bold = true,
+                22 => self.is_bold = false,
+                30..=37 => self.foreground = (param - 30) as u8,
+                39 => self.foreground = DEFAULT_FOREGROUND,
+                40..=47 => self.background = (param - 40) as u8,
+                49 => self.background = DEFAULT_BACKGROUND,
+                90..=97 => self.foreground = (param - 90) as u8 + 8,
+                100..=107 => self.background = (param - 100) as u8 + 8,
+                // Other attributes are not supported.
+                _ => (),
+            }
+        }
+    }
+
+    fn newline(&mut self) {
+        self.x_pos = 0;
+        self.y_pos += GLYPH_SIZE;
+    }
+
+    /// Moves all the text up by one line and erases the last line.
+    fn scroll_up(&mut self) {
+        let Some(last_line) = self.nr_rows().checked_sub(1) else {
+            return;
+        };
+        let line_size = self.pitch * GLYPH_SIZE;
+        let text_size = (last_line + 1) * line_size;
+
+        self.buffer.copy_within(line_size..text_size, 0);
+        self.y_pos = last_line * GLYPH_SIZE;
+        self.fill_rect(0, self.y_pos, self.width, GLYPH_SIZE);
+    }
+
+    fn write_glyph(&mut self, glyph: &[u8; GLYPH_SIZE]) {
+        let mut foreground = self.foreground;
+        if self.is_bold && foreground < 8 {
+            foreground += 8;
+        }
+
+        for (y, row) in glyph.iter().enumerate() {
+            for x in 0..GLYPH_SIZE {
+                // The least significant bit is the leftmost pixel.
+                let is_set = *row & (1 << x) != 0;
+                let color = if is_set { foreground } else { self.background };
+                self.write_pixel(self.x_pos + x, self.y_pos + y, color);
+            }
+        }
+        self.x_pos += GLYPH_SIZE;
+    }
+
+    /// Fills the rectangle of pixels with the background color.
+    fn fill_rect(&mut self, x: usize, y: usize, width: usize, height: usize) {
+        for y in y..(y + height).min(self.height) {
+            for x in x..(x + width).min(self.width) {
+                self.write_pixel(x, y, self.background);
+            }
+        }
+    }
+
+    fn write_pixel(&mut self, x: usize, y: usize, color: u8) {
+        let color = &PALETTE[color as usize];
+        let offset = y * self.pitch + x * self.bytes_per_pixel;
+        self.buffer[offset..offset + self.bytes_per_pixel]
+            .copy_from_slice(&color[..self.bytes_per_pixel]);
+    }
+}
+
+#[cfg(ktest)]
+mod test {
+    use alloc::vec;
+
+    use super::*;
+    use crate::prelude::*;
+
+    const WIDTH: usize = 32;
+    const HEIGHT: usize = 16;
+    const BYTES_PER_PIXEL: usize = 4;
+    /// The rows are padded to test that the pitch is respected.
+    const PITCH: usize = WIDTH * BYTES_PER_PIXEL + 16;
+
+    const FOREGROUND: [u8; 4] = PALETTE[DEFAULT_FOREGROUND as usize];
+    const BACKGROUND: [u8; 4] = PALETTE[DEFAULT_BACKGROUND as usize];
+
+    fn new_writer(buffer: &mut [u8]) -> FramebufferWriter<'_> {
+        FramebufferWriter::new(buffer, WIDTH, HEIGHT, PITCH, BYTES_PER_PIXEL)
+    }
+
+    fn pixel(buffer: &[u8], x: usize, y: usize) -> &[u8] {
+        let offset = y * PITCH + x * BYTES_PER_PIXEL;
+        &buffer[offset..offset + BYTES_PER_PIXEL]
+    }
+
+    /// Checks that the glyph of `c` is rendered with its top-left corner at `(x, y)`.
+    fn assert_glyph_at(buffer: &[u8], c: char, x: usize, y: usize) {
+        assert_colored_glyph_at(buffer, c, x, y, &FOREGROUND, &BACKGROUND);
+    }
+
+    /// Checks that the glyph of `c` is rendered at `(x, y)` with the given colors.
+    fn assert_colored_glyph_at(
+        buffer: &[u8],
+        c: char,
+        x: usize,
+        y: usize,
+        foreground: &[u8; 4],
+        background: &[u8; 4],
+    ) {
+        let glyph = font8x8::BASIC_FONTS.get(c).unwrap();
+        for (dy, row) in glyph.iter().enumerate() {
+            for dx in 0..GLYPH_SIZE {
+                let expected = if *row & (1 << dx) != 0 {
+                    foreground
+                } else {
+                    background
+                };
+                assert_eq!(pixel(buffer, x + dx, y + dy), expected);
+            }
+        }
+    }
+
+    #[ktest]
+    fn render_chars() {
+        let mut buffer = vec![0u8; PITCH * HEIGHT];
+        let mut writer = new_writer(&mut buffer);
+        writer.write_str("AB\nC");
+        drop(writer);
+
+        assert_glyph_at(&buffer, 'A', 0, 0);
+        assert_glyph_at(&buffer, 'B', GLYPH_SIZE, 0);
+        assert_glyph_at(&buffer, 'C', 0, GLYPH_SIZE);
+        // Nothing is rendered after the last character.
+        assert_eq!(pixel(&buffer, 2 * GLYPH_SIZE, 0), &BACKGROUND);
+    }
+
+    #[ktest]
+    fn wrap_and_scroll() {
+        let mut buffer = vec![0u8; PITCH * HEIGHT];
+        let mut writer = new_writer(&mut buffer);
+        // Four glyphs fill a line. The fifth one wraps to the second line, and
+        // the ninth one scrolls the first line out.
+        writer.write_str("ABCDEFGHI");
+        drop(writer);
+
+        assert_glyph_at(&buffer, 'E', 0, 0);
+        assert_glyph_at(&buffer, 'H', 3 * GLYPH_SIZE, 0);
+        assert_glyph_at(&buffer, 'I', 0, GLYPH_SIZE);
+        assert_glyph_at(&buffer, ' ', GLYPH_SIZE, GLYPH_SIZE);
+    }
+
+    #[ktest]
+    fn set_colors() {
+        const RED: [u8; 4] = PALETTE[1];
+        const BRIGHT_GREEN: [u8; 4] = PALETTE[10];
+        const BLUE: [u8; 4] = PALETTE[4];
+
+        let mut buffer = vec![0u8; PITCH * HEIGHT];
+        let mut writer = new_writer(&mut buffer);
+        writer.write_str("\x1b[31mA\x1b[1;32;44mB\x1b[mC");
+        drop(writer);
+
+        assert_colored_glyph_at(&buffer, 'A', 0, 0, &RED, &BACKGROUND);
+        assert_colored_glyph_at(&buffer, 'B', GLYPH_SIZE, 0, &BRIGHT_GREEN, &BLUE);
+        assert_glyph_at(&buffer, 'C', 2 * GLYPH_SIZE, 0);
+    }
+
+    #[ktest]
+    fn split_escape_sequence() {
+        const RED: [u8; 4] = PALETTE[1];
+
+        let mut buffer = vec![0u8; PITCH * HEIGHT];
+        let mut writer = new_writer(&mut buffer);
+        // Nothing is rendered for the incomplete parts.
+        writer.write_str("\x1b");
+        writer.write_str("[3");
+        writer.write_str("1");
+        writer.write_str("mA");
+        drop(writer);
+
+        assert_colored_glyph_at(&buffer, 'A', 0, 0, &RED, &BACKGROUND);
+        assert_eq!(pixel(&buffer, GLYPH_SIZE, 0), &BACKGROUND);
+    }
+
+    #[ktest]
+    fn move_cursor_and_erase() {
+        let mut buffer = vec![0u8; PITCH * HEIGHT];
+        let mut writer = new_writer(&mut buffer);
+        // Unsupported sequences are ignored. Out-of-screen positions are clamped.
+        writer.write_str("ABCD\x1b[?25l\x1b[2;3HE\x1b[9;9HF\x1b[1;2H\x1b[K");
+        drop(writer);
+
+        assert_glyph_at(&buffer, 'A', 0, 0);
+        assert_glyph_at(&buffer, ' ', GLYPH_SIZE, 0);
+        assert_glyph_at(&buffer, ' ', 3 * GLYPH_SIZE, 0);
+        assert_glyph_at(&buffer, 'E', 2 * GLYPH_SIZE, GLYPH_SIZE);
+        assert_glyph_at(&buffer, 'F', 3 * GLYPH_SIZE, GLYPH_SIZE);
+
+        let mut writer = new_writer(&mut buffer);
+        writer.write_str("\x1b[2J");
+        drop(writer);
+        assert!(buffer.iter().all(|&byte| byte == 0));
+    }
+
+    #[ktest]
+    fn tiny_screen() {
+        let mut buffer = vec![0u8; PITCH * (GLYPH_SIZE - 1)];
+        let mut writer =
+            FramebufferWriter::new(&mut buffer, WIDTH, GLYPH_SIZE - 1, PITCH, BYTES_PER_PIXEL);
+        // Nothing is rendered, and nothing overflows.
+        writer.write_str("AB\nC\x1b[2;2HD\n\n");
+        drop(writer);
+
+        assert!(buffer.iter().all(|&byte| byte == 0));
+    }
+}
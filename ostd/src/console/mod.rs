@@ -0,0 +1,82 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Console output.
+//!
+//! The early console always writes to the serial port. Other backends, e.g.,
+//! the framebuffer provided by the bootloader, can be registered with
+//! [`register_early_console`] so that the output is mirrored to them.
+
+mod framebuffer;
+
+use core::{
+    fmt::{self, Arguments, Write},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use spin::Once;
+
+use crate::{Error, Result};
+
+/// A backend of the early console besides the serial port.
+pub trait EarlyConsole: Sync {
+    /// Writes a string to the console.
+    fn write_str(&self, s: &str);
+}
+
+const MAX_EARLY_CONSOLES: usize = 4;
+
+static EARLY_CONSOLES: [Once<&'static dyn EarlyConsole>; MAX_EARLY_CONSOLES] =
+    [const { Once::new() }; MAX_EARLY_CONSOLES];
+static NR_EARLY_CONSOLES: AtomicUsize = AtomicUsize::new(0);
+
+/// Registers a backend of the early console.
+///
+/// All the output printed after the registration is also written to the
+/// backend. At most four backends can be registered; otherwise,
+/// [`Error::NotEnoughResources`] is returned.
+pub fn register_early_console(console: &'static dyn EarlyConsole) -> Result<()> {
+    let index = NR_EARLY_CONSOLES.fetch_add(1, Ordering::Relaxed);
+    let slot = EARLY_CONSOLES.get(index).ok_or(Error::NotEnoughResources)?;
+    slot.call_once(|| console);
+    Ok(())
+}
+
+/// Prints formatted arguments to the console.
+pub fn early_print(args: Arguments) {
+    crate::arch::serial::print(args);
+
+    for console in EARLY_CONSOLES.iter().filter_map(Once::get) {
+        let _ = ConsoleWriter(*console).write_fmt(args);
+    }
+}
+
+struct ConsoleWriter(&'static dyn EarlyConsole);
+
+impl Write for ConsoleWriter {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.0.write_str(s);
+        Ok(())
+    }
+}
+
+/// Initializes the early console backends provided by the bootloader.
+pub(crate) fn init() {
+    framebuffer::init();
+}
+
+/// Prints to the console.
+#[macro_export]
+macro_rules! early_print {
+    ($fmt: literal $(, $($arg: tt)+)?) => {
+        $crate::console::early_print(format_args!($fmt $(, $($arg)+)?))
+    }
+}
+
+/// Prints to the console with a newline.
+#[macro_export]
+macro_rules! early_println {
+    () => { $crate::early_print!("\n") };
+    ($fmt: literal $(, $($arg: tt)+)?) => {
+        $crate::console::early_print(format_args!(concat!($fmt, "\n") $(, $($arg)+)?))
+    }
+}
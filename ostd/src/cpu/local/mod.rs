@@ -161,7 +161,7 @@ pub unsafe fn init_on_ap(cpu_id: u32) {
     }
 }
 
-mod has_init {
+pub(crate) mod has_init {
     //! This module is used to detect the programming error of using the CPU-local
     //! mechanism before it is initialized. Such bugs have been found before and we
     //! do not want to repeat this error again. This module is only incurs runtime
@@ -179,6 +179,10 @@ mod has_init {
             pub fn set_true() {
                 IS_INITIALIZED.store(true, Ordering::Relaxed);
             }
+
+            pub fn is_true() -> bool {
+                IS_INITIALIZED.load(Ordering::Relaxed)
+            }
         } else {
             pub fn assert_true() {}
 
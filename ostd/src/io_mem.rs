@@ -121,3 +121,19 @@ impl IoMem {
         }
     }
 }
+
+#[cfg(ktest)]
+impl IoMem {
+    /// Allocates zeroed RAM that emulates the I/O memory of a device in tests.
+    ///
+    /// The frames are never freed, so the returned `IoMem` is always valid.
+    pub fn alloc_for_test(nframes: usize) -> IoMem {
+        let segment = crate::mm::FrameAllocOptions::new(nframes)
+            .alloc_contiguous()
+            .unwrap();
+        let range = segment.start_paddr()..segment.end_paddr();
+        core::mem::forget(segment);
+        // SAFETY: The frames are leaked, so they are used as nothing else.
+        unsafe { Self::new(range) }
+    }
+}
@@ -86,6 +86,8 @@ pub fn init() {
 
     mm::kspace::activate_kernel_page_table();
 
+    console::init();
+
     arch::irq::enable_local();
 
     invoke_ffi_init_funcs();
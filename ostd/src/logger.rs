@@ -2,26 +2,57 @@
 
 //! Logging support.
 //!
-//! Currently the logger prints the logs to the console.
+//! Currently the logger prints the logs to the console. By default, each record is
+//! printed as plain text. With `ostd.log_format=json` on the kernel command line, each
+//! record is printed as a line of JSON for machines to ingest, e.g.,
+//!
+//! ```text
+//! {"timestamp":1.25,"level":"INFO","target":"aster_nix::fs","cpu":0,"message":"mounted"}
+//! ```
 //!
 //! This module guarantees _atomicity_ under concurrency: messages are always
 //! printed in their entirety without being mixed with messages generated
 //! concurrently on other cores.
 //!
 //! IRQs are disabled while printing. So do not print long log messages.
+//!
+//! Messages that may be emitted repeatedly (e.g., errors reported by a device that keeps
+//! retrying) can be rate-limited with [`log_ratelimited!`].
+//!
+//! [`log_ratelimited!`]: crate::log_ratelimited
 
-use alloc::format;
+use alloc::{format, string::ToString};
+use core::{
+    fmt::{self, Write},
+    time::Duration,
+};
 
+#[doc(hidden)]
+pub use log;
 use log::{LevelFilter, Metadata, Record};
 
 use crate::{
     arch::timer::Jiffies,
     boot::{kcmdline::ModuleArg, kernel_cmdline},
+    cpu::this_cpu,
     early_println,
+    sync::{OnceCell, SpinLock},
 };
 
 const LOGGER: Logger = Logger {};
 
+/// The format of the log records, which is selected by `ostd.log_format`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum LogFormat {
+    Text,
+    Json,
+}
+
+static LOG_FORMAT: OnceCell<LogFormat> = OnceCell::new();
+
+/// A global lock to prevent interleaving of log messages.
+static RECORD_LOCK: SpinLock<()> = SpinLock::new(());
+
 struct Logger {}
 
 impl log::Log for Logger {
@@ -34,14 +65,21 @@ impl log::Log for Logger {
             return;
         }
 
-        let timestamp = format!("[{:>10?}]", Jiffies::elapsed().as_duration().as_secs_f64());
+        let now = Jiffies::elapsed().as_duration();
+
+        if LOG_FORMAT.get() == Some(&LogFormat::Json) {
+            let json_record = JsonRecord::new(now, this_cpu(), record).to_string();
+            let _lock = RECORD_LOCK.lock_irq_disabled();
+            early_println!("{}", json_record);
+            return;
+        }
+
+        let timestamp = format!("[{:>10?}]", now.as_secs_f64());
         let level = format!("{:<5}", record.level());
         let record_str = format!("{}", record.args());
 
         #[cfg(feature = "log_color")]
         let (timestamp, level, record_str) = {
-            use alloc::string::ToString;
-
             use owo_colors::OwoColorize;
 
             let timestamp = timestamp.green();
@@ -56,9 +94,6 @@ impl log::Log for Logger {
             (timestamp, level, record_str)
         };
 
-        // Use a global lock to prevent interleaving of log messages.
-        use crate::sync::SpinLock;
-        static RECORD_LOCK: SpinLock<()> = SpinLock::new(());
         let _lock = RECORD_LOCK.lock_irq_disabled();
 
         early_println!("{} {}: {}", timestamp, level, record_str);
@@ -67,26 +102,188 @@ impl log::Log for Logger {
     fn flush(&self) {}
 }
 
+/// A log record that is formatted as a JSON object in a single line.
+struct JsonRecord<'a> {
+    timestamp: Duration,
+    cpu: u32,
+    record: &'a Record<'a>,
+}
+
+impl<'a> JsonRecord<'a> {
+    fn new(timestamp: Duration, cpu: u32, record: &'a Record<'a>) -> Self {
+        Self {
+            timestamp,
+            cpu,
+            record,
+        }
+    }
+}
+
+impl fmt::Display for JsonRecord<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{{\"timestamp\":{},\"level\":{},\"target\":{},\"cpu\":{},\"message\":{}}}",
+            self.timestamp.as_secs_f64(),
+            JsonStr(self.record.level()),
+            JsonStr(self.record.target()),
+            self.cpu,
+            JsonStr(self.record.args()),
+        )
+    }
+}
+
+/// A value that is formatted as a JSON string.
+struct JsonStr<T>(T);
+
+impl<T: fmt::Display> fmt::Display for JsonStr<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_char('"')?;
+        write!(JsonEscaper(f), "{}", self.0)?;
+        f.write_char('"')
+    }
+}
+
+/// A writer that escapes the characters that cannot appear in a JSON string as is.
+struct JsonEscaper<'a, 'b>(&'a mut fmt::Formatter<'b>);
+
+impl Write for JsonEscaper<'_, '_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for c in s.chars() {
+            match c {
+                '"' => self.0.write_str("\\\"")?,
+                '\\' => self.0.write_str("\\\\")?,
+                '\n' => self.0.write_str("\\n")?,
+                '\r' => self.0.write_str("\\r")?,
+                '\t' => self.0.write_str("\\t")?,
+                '\u{8}' => self.0.write_str("\\b")?,
+                '\u{c}' => self.0.write_str("\\f")?,
+                c if c < ' ' => write!(self.0, "\\u{:04x}", c as u32)?,
+                c => self.0.write_char(c)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Logs a message at the given level, with repeated messages from the same call site
+/// being rate-limited.
+///
+/// At most [`RateLimiter::DEFAULT_BURST`] messages are emitted from each call site in every
+/// [`RateLimiter::DEFAULT_INTERVAL`]. The rest are suppressed, and the number of suppressed
+/// messages is reported before the next message that is emitted.
+///
+/// # Example
+///
+/// ```rust
+/// use ostd::log_ratelimited;
+///
+/// log_ratelimited!(log::Level::Error, "device timed out: {}", 42);
+/// ```
+#[macro_export]
+macro_rules! log_ratelimited {
+    // An internal rule that logs with the given rate limiter.
+    (@limiter $limiter:expr, $lvl:expr, $($arg:tt)+) => {{
+        let lvl = $lvl;
+        // The messages filtered out by the log level do not count against the limit.
+        if $crate::logger::log::log_enabled!(lvl) {
+            if let Some(nr_suppressed) = $limiter.check() {
+                if nr_suppressed > 0 {
+                    $crate::logger::log::log!(lvl, "{} messages suppressed", nr_suppressed);
+                }
+                $crate::logger::log::log!(lvl, $($arg)+);
+            }
+        }
+    }};
+    ($lvl:expr, $($arg:tt)+) => {{
+        static RATE_LIMITER: $crate::logger::RateLimiter = $crate::logger::RateLimiter::new(
+            $crate::logger::RateLimiter::DEFAULT_INTERVAL,
+            $crate::logger::RateLimiter::DEFAULT_BURST,
+        );
+        $crate::log_ratelimited!(@limiter RATE_LIMITER, $lvl, $($arg)+);
+    }};
+}
+
+/// A rate limiter that allows at most `burst` events in every `interval`.
+pub struct RateLimiter {
+    interval: Duration,
+    burst: usize,
+    state: SpinLock<RateLimitState>,
+}
+
+struct RateLimitState {
+    /// The start time of the current interval.
+    begin: Option<Duration>,
+    /// The number of the allowed events in the current interval.
+    nr_allowed: usize,
+    /// The number of the suppressed events since the last allowed event.
+    nr_suppressed: usize,
+}
+
+impl RateLimiter {
+    /// The default interval of [`log_ratelimited!`].
+    ///
+    /// [`log_ratelimited!`]: crate::log_ratelimited
+    pub const DEFAULT_INTERVAL: Duration = Duration::from_secs(5);
+    /// The default burst of [`log_ratelimited!`].
+    ///
+    /// [`log_ratelimited!`]: crate::log_ratelimited
+    pub const DEFAULT_BURST: usize = 10;
+
+    /// Creates a rate limiter that allows at most `burst` events in every `interval`.
+    pub const fn new(interval: Duration, burst: usize) -> Self {
+        Self {
+            interval,
+            burst,
+            state: SpinLock::new(RateLimitState {
+                begin: None,
+                nr_allowed: 0,
+                nr_suppressed: 0,
+            }),
+        }
+    }
+
+    /// Checks whether an event is allowed now.
+    ///
+    /// If the event is allowed, this method returns the number of the events that have been
+    /// suppressed since the last allowed event. Otherwise, it returns `None`.
+    pub fn check(&self) -> Option<usize> {
+        self.check_at(Jiffies::elapsed().as_duration())
+    }
+
+    fn check_at(&self, now: Duration) -> Option<usize> {
+        let mut state = self.state.lock_irq_disabled();
+
+        let is_expired = state
+            .begin
+            .map_or(true, |begin| now.saturating_sub(begin) >= self.interval);
+        if is_expired {
+            state.begin = Some(now);
+            state.nr_allowed = 0;
+        }
+
+        if state.nr_allowed >= self.burst {
+            state.nr_suppressed += 1;
+            return None;
+        }
+
+        state.nr_allowed += 1;
+        Some(core::mem::take(&mut state.nr_suppressed))
+    }
+}
+
 /// Initialize the logger. Users should avoid using the log macros before this function is called.
 pub(crate) fn init() {
     let level = get_log_level().unwrap_or(LevelFilter::Off);
 
+    LOG_FORMAT.get_or_init(get_log_format);
+
     log::set_max_level(level);
     log::set_logger(&LOGGER).unwrap();
 }
 
 fn get_log_level() -> Option<LevelFilter> {
-    let module_args = kernel_cmdline().get_module_args("ostd")?;
-
-    let arg = module_args.iter().find(|arg| match arg {
-        ModuleArg::Arg(_) => false,
-        ModuleArg::KeyVal(name, _) => name.as_bytes() == "log_level".as_bytes(),
-    })?;
-
-    let ModuleArg::KeyVal(_, value) = arg else {
-        unreachable!()
-    };
-    let value = value.as_c_str().to_str().unwrap_or("off");
+    let value = get_module_arg("log_level")?;
     Some(match value {
         "error" => LevelFilter::Error,
         "warn" => LevelFilter::Warn,
@@ -97,3 +294,184 @@ fn get_log_level() -> Option<LevelFilter> {
         _ => LevelFilter::Off,
     })
 }
+
+fn get_log_format() -> LogFormat {
+    match get_module_arg("log_format") {
+        Some("json") => LogFormat::Json,
+        // Otherwise, plain text
+        _ => LogFormat::Text,
+    }
+}
+
+/// Returns the value of the `ostd.<name>` argument on the kernel command line.
+///
+/// A value that is not valid UTF-8 is returned as an empty string.
+fn get_module_arg(name: &str) -> Option<&'static str> {
+    let module_args = kernel_cmdline().get_module_args("ostd")?;
+
+    module_args.iter().find_map(|arg| match arg {
+        ModuleArg::Arg(_) => None,
+        ModuleArg::KeyVal(key, value) if key.as_bytes() == name.as_bytes() => {
+            Some(value.as_c_str().to_str().unwrap_or(""))
+        }
+        ModuleArg::KeyVal(..) => None,
+    })
+}
+
+#[cfg(ktest)]
+mod test {
+    use alloc::string::String;
+    use core::{iter::Peekable, str::Chars};
+
+    use super::*;
+    use crate::prelude::*;
+
+    #[ktest]
+    fn rate_limit() {
+        let rate_limiter = RateLimiter::new(Duration::from_secs(1), 3);
+        let mut now = Duration::from_secs(100);
+
+        // A flood of events in one interval.
+        let nr_allowed = (0..10)
+            .filter(|_| rate_limiter.check_at(now).is_some())
+            .count();
+        assert_eq!(nr_allowed, 3);
+
+        // The next allowed event reports the suppressed ones.
+        now += Duration::from_millis(999);
+        assert_eq!(rate_limiter.check_at(now), None);
+        now += Duration::from_millis(1);
+        assert_eq!(rate_limiter.check_at(now), Some(8));
+        assert_eq!(rate_limiter.check_at(now), Some(0));
+    }
+
+    #[ktest]
+    fn rate_limit_macro() {
+        static RATE_LIMITER: RateLimiter = RateLimiter::new(Duration::from_secs(3600), 3);
+
+        fn flood(level: log::Level) -> usize {
+            let mut nr_emitted = 0;
+            for _ in 0..10 {
+                crate::log_ratelimited!(@limiter RATE_LIMITER, level, "flood {}", {
+                    nr_emitted += 1;
+                    nr_emitted
+                });
+            }
+            nr_emitted
+        }
+
+        let max_level = log::max_level();
+
+        // The filtered messages are neither emitted nor counted.
+        log::set_max_level(LevelFilter::Off);
+        assert_eq!(flood(log::Level::Trace), 0);
+        assert_eq!(RATE_LIMITER.state.lock_irq_disabled().nr_allowed, 0);
+        assert_eq!(RATE_LIMITER.state.lock_irq_disabled().nr_suppressed, 0);
+
+        log::set_max_level(LevelFilter::Trace);
+        let nr_emitted = flood(log::Level::Trace);
+        log::set_max_level(max_level);
+
+        assert_eq!(nr_emitted, 3);
+        assert_eq!(RATE_LIMITER.state.lock_irq_disabled().nr_allowed, 3);
+        assert_eq!(RATE_LIMITER.state.lock_irq_disabled().nr_suppressed, 7);
+    }
+
+    fn format_json(args: fmt::Arguments) -> String {
+        let record = Record::builder()
+            .args(args)
+            .level(log::Level::Warn)
+            .target("ostd::logger")
+            .build();
+        JsonRecord::new(Duration::from_millis(1250), 1, &record).to_string()
+    }
+
+    /// Parses a JSON object whose values are strings or non-negative numbers.
+    fn parse_json_object(json: &str) -> Vec<(String, String)> {
+        let mut chars = json.chars().peekable();
+        let mut fields = Vec::new();
+
+        assert_eq!(chars.next(), Some('{'));
+        loop {
+            let key = parse_json_str(&mut chars);
+            assert_eq!(chars.next(), Some(':'));
+            let value = if chars.peek() == Some(&'"') {
+                parse_json_str(&mut chars)
+            } else {
+                let mut number = String::new();
+                while let Some(c) = chars.next_if(|c| c.is_ascii_digit() || *c == '.') {
+                    number.push(c);
+                }
+                number
+            };
+            fields.push((key, value));
+
+            match chars.next() {
+                Some(',') => continue,
+                Some('}') => break,
+                c => panic!("unexpected character {:?}", c),
+            }
+        }
+        assert_eq!(chars.next(), None);
+
+        fields
+    }
+
+    fn parse_json_str(chars: &mut Peekable<Chars>) -> String {
+        assert_eq!(chars.next(), Some('"'));
+
+        let mut s = String::new();
+        loop {
+            let c = match chars.next().unwrap() {
+                '"' => return s,
+                '\\' => match chars.next().unwrap() {
+                    '"' => '"',
+                    '\\' => '\\',
+                    'n' => '\n',
+                    'r' => '\r',
+                    't' => '\t',
+                    'b' => '\u{8}',
+                    'f' => '\u{c}',
+                    'u' => {
+                        let hex = chars.by_ref().take(4).collect::<String>();
+                        char::from_u32(u32::from_str_radix(&hex, 16).unwrap()).unwrap()
+                    }
+                    c => panic!("invalid escape {:?}", c),
+                },
+                c => {
+                    assert!(c >= ' ', "unescaped control character {:?}", c);
+                    c
+                }
+            };
+            s.push(c);
+        }
+    }
+
+    #[ktest]
+    fn json_record() {
+        let json = format_json(format_args!("plain {}", 42));
+        let fields = parse_json_object(&json);
+        let expected = [
+            ("timestamp", "1.25"),
+            ("level", "WARN"),
+            ("target", "ostd::logger"),
+            ("cpu", "1"),
+            ("message", "plain 42"),
+        ];
+        assert_eq!(fields.len(), expected.len());
+        for ((key, value), (expected_key, expected_value)) in fields.iter().zip(expected) {
+            assert_eq!((key.as_str(), value.as_str()), (expected_key, expected_value));
+        }
+    }
+
+    #[ktest]
+    fn json_record_escape() {
+        let message = "say \"hi\" \\ \n\r\t\u{8}\u{c}\u{1}\u{1f} ünïcödé";
+        let json = format_json(format_args!("{}", message));
+        assert!(!json.chars().any(|c| c < ' '));
+
+        let fields = parse_json_object(&json);
+        assert_eq!(fields[4].0, "message");
+        assert_eq!(fields[4].1, message);
+    }
+}
@@ -0,0 +1,96 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! CPU cache maintenance.
+//!
+//! These operations are needed when the memory is accessed without going
+//! through the CPU caches, e.g., by non-coherent DMA devices, or when the
+//! memory is written as data and then executed as instructions.
+//!
+//! All the operations work on whole cache lines. A range that is not aligned
+//! to the cache line boundaries is rounded outward, so the neighboring bytes
+//! that share the first or the last cache line are affected as well.
+
+use core::ops::Range;
+
+use super::Vaddr;
+use crate::arch::mm as arch;
+
+/// Writes back the dirty cache lines that cover the given virtual address
+/// range to the memory.
+///
+/// The cache lines may be invalidated as well, depending on the architecture.
+///
+/// # Panics
+///
+/// This function panics if a page in the range is not mapped.
+pub fn flush_dcache_range(range: Range<Vaddr>) {
+    arch::flush_dcache_range(range);
+}
+
+/// Invalidates the cache lines that cover the given virtual address range, so
+/// that the next reads fetch the data from the memory.
+///
+/// The dirty cache lines may be written back first, depending on the
+/// architecture.
+///
+/// # Safety
+///
+/// The dirty cache lines may be discarded without being written back. The
+/// caller must ensure that nothing relies on the data written to the range
+/// since the last [`flush_dcache_range`], including the data in the bytes
+/// that share the first or the last cache line with the range.
+///
+/// # Panics
+///
+/// This function panics if a page in the range is not mapped.
+pub unsafe fn invalidate_dcache_range(range: Range<Vaddr>) {
+    arch::invalidate_dcache_range(range);
+}
+
+/// Makes the instructions written to the given virtual address range visible
+/// to the instruction fetches of the current CPU.
+///
+/// This must be called after writing code to the memory and before executing
+/// it, e.g., when loading a module. Other CPUs must call this function as
+/// well before executing the code.
+pub fn flush_icache_range(range: Range<Vaddr>) {
+    arch::flush_dcache_range(range.clone());
+    arch::flush_icache_range(range);
+}
+
+#[cfg(ktest)]
+mod test {
+    use super::*;
+    use crate::{
+        mm::{paddr_to_vaddr, FrameAllocOptions, VmIo, PAGE_SIZE},
+        prelude::*,
+    };
+
+    #[ktest]
+    fn flush_and_invalidate() {
+        let frame = FrameAllocOptions::new(1).alloc_single().unwrap();
+        let start = paddr_to_vaddr(frame.start_paddr());
+        frame.write_val(PAGE_SIZE / 2, &0xdead_beef_u32).unwrap();
+
+        // Unaligned and empty ranges.
+        flush_dcache_range(start + 1..start + 3);
+        flush_dcache_range(start + 63..start + 65);
+        flush_dcache_range(start + 5..start + 5);
+        flush_dcache_range(start..start + PAGE_SIZE);
+        // SAFETY: The data are flushed, and nothing is written after that.
+        unsafe { invalidate_dcache_range(start + PAGE_SIZE / 2 - 1..start + PAGE_SIZE / 2 + 1) };
+
+        assert_eq!(frame.read_val::<u32>(PAGE_SIZE / 2).unwrap(), 0xdead_beef);
+    }
+
+    #[ktest]
+    fn flush_icache() {
+        let frame = FrameAllocOptions::new(1).alloc_single().unwrap();
+        let start = paddr_to_vaddr(frame.start_paddr());
+        frame.write_bytes(0, &[0xc3; 16]).unwrap();
+
+        flush_icache_range(start + 3..start + 9);
+
+        assert_eq!(frame.read_val::<u8>(8).unwrap(), 0xc3);
+    }
+}
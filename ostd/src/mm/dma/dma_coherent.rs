@@ -3,6 +3,7 @@
 use alloc::sync::Arc;
 use core::ops::Deref;
 
+use align_ext::AlignExt;
 use cfg_if::cfg_if;
 
 use super::{check_and_insert_dma_mapping, remove_dma_mapping, DmaError, HasDaddr};
@@ -13,7 +14,8 @@ use crate::{
         io::VmIoOnce,
         kspace::{paddr_to_vaddr, KERNEL_PAGE_TABLE},
         page_prop::CachePolicy,
-        HasPaddr, Paddr, PodOnce, Segment, VmIo, VmReader, VmWriter, PAGE_SIZE,
+        FrameAllocOptions, HasPaddr, Paddr, PodOnce, Segment, Vaddr, VmIo, VmReader, VmWriter,
+        PAGE_SIZE,
     },
     prelude::*,
 };
@@ -111,6 +113,34 @@ impl DmaCoherent {
     }
 }
 
+impl DmaCoherent {
+    /// Allocates physically contiguous memory of at least `size` bytes and
+    /// creates a coherent DMA mapping backed by it.
+    ///
+    /// The memory is zeroed. See [`DmaCoherent::map`] for the meaning of
+    /// `is_cache_coherent`.
+    ///
+    /// The CPU can access the memory through [`DmaCoherent::vaddr`], while the
+    /// device should use [`HasDaddr::daddr`], which may be different from the
+    /// physical address if there is an IOMMU.
+    pub fn alloc(size: usize, is_cache_coherent: bool) -> core::result::Result<Self, DmaError> {
+        if size == 0 {
+            return Err(DmaError::InvalidArgs);
+        }
+
+        let vm_segment = FrameAllocOptions::new(size.align_up(PAGE_SIZE) / PAGE_SIZE)
+            .is_contiguous(true)
+            .alloc_contiguous()
+            .map_err(|_| DmaError::NoMemory)?;
+        Self::map(vm_segment, is_cache_coherent)
+    }
+
+    /// Returns the kernel virtual address of the mapping.
+    pub fn vaddr(&self) -> Vaddr {
+        paddr_to_vaddr(self.inner.vm_segment.start_paddr())
+    }
+}
+
 impl HasDaddr for DmaCoherent {
     fn daddr(&self) -> Daddr {
         self.inner.start_daddr
@@ -238,6 +268,20 @@ mod test {
         assert!(page_table.query(vaddr).unwrap().1.cache == CachePolicy::Uncacheable);
     }
 
+    #[ktest]
+    fn alloc_and_access() {
+        let dma_coherent = DmaCoherent::alloc(PAGE_SIZE + 1, false).unwrap();
+        assert_eq!(dma_coherent.nframes(), 2);
+        assert_eq!(dma_coherent.vaddr(), paddr_to_vaddr(dma_coherent.paddr()));
+
+        dma_coherent.write_val(PAGE_SIZE, &0xdeadbeefu32).unwrap();
+        // SAFETY: The address is valid as it is mapped in the linear mapping.
+        let val = unsafe {
+            core::ptr::read_volatile(paddr_to_vaddr(dma_coherent.paddr() + PAGE_SIZE) as *const u32)
+        };
+        assert_eq!(val, 0xdeadbeef);
+    }
+
     #[ktest]
     fn duplicate_map() {
         let vm_segment_parent = FrameAllocOptions::new(2)
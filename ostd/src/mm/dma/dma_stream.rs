@@ -1,16 +1,20 @@
 // SPDX-License-Identifier: MPL-2.0
 
-use alloc::sync::Arc;
+use alloc::{sync::Arc, vec::Vec};
 use core::ops::Range;
 
 use cfg_if::cfg_if;
 
 use super::{check_and_insert_dma_mapping, remove_dma_mapping, DmaError, HasDaddr};
 use crate::{
-    arch::iommu,
+    arch::{
+        iommu,
+        mm::{flush_dcache_range, invalidate_dcache_range},
+    },
     error::Error,
     mm::{
         dma::{dma_type, Daddr, DmaType},
+        kspace::paddr_to_vaddr,
         HasPaddr, Paddr, Segment, VmIo, VmReader, VmWriter, PAGE_SIZE,
     },
 };
@@ -27,6 +31,12 @@ cfg_if! {
 ///
 /// The mapping is automatically destroyed when this object
 /// is dropped.
+///
+/// If the device is not cache coherent, the CPU caches covering the
+/// mapping are flushed when the mapping is established, and are
+/// invalidated when the mapping is destroyed if the device may have
+/// written to the memory (i.e., the direction is not
+/// [`DmaDirection::ToDevice`]).
 #[derive(Debug, Clone)]
 pub struct DmaStream {
     inner: Arc<DmaStreamInner>,
@@ -36,8 +46,6 @@ pub struct DmaStream {
 struct DmaStreamInner {
     vm_segment: Segment,
     start_daddr: Daddr,
-    /// TODO: remove this field when on x86.
-    #[allow(unused)]
     is_cache_coherent: bool,
     direction: DmaDirection,
 }
@@ -97,6 +105,13 @@ impl DmaStream {
             }
         };
 
+        // Write back the dirty cache lines, so the device can see the data
+        // written by the CPU, and no dirty cache line will overwrite the data
+        // written by the device later.
+        if !is_cache_coherent {
+            maintain_cache(&vm_segment, 0..vm_segment.nbytes(), CacheOp::Flush);
+        }
+
         Ok(Self {
             inner: Arc::new(DmaStreamInner {
                 vm_segment,
@@ -136,30 +151,23 @@ impl DmaStream {
     ///    (e.g., using [`write_bytes`]).
     ///    Before the CPU side notifies the device side to read, it must call the `sync` method first.
     ///
+    /// If the device is not cache coherent, the CPU caches covering `byte_range` are
+    /// written back and invalidated. The method fails if `byte_range` is out of bounds.
+    ///
     /// [`read_bytes`]: Self::read_bytes
     /// [`write_bytes`]: Self::write_bytes
-    pub fn sync(&self, _byte_range: Range<usize>) -> Result<(), Error> {
-        cfg_if::cfg_if! {
-            if #[cfg(target_arch = "x86_64")]{
-                // The streaming DMA mapping in x86_64 is cache coherent, and does not require synchronization.
-                // Reference: <https://lwn.net/Articles/855328/>, <https://lwn.net/Articles/2265/>
-                Ok(())
-            } else {
-                if _byte_range.end > self.nbytes() {
-                    return Err(Error::InvalidArgs);
-                }
-                if self.inner.is_cache_coherent {
-                    return Ok(());
-                }
-                let start_va = self.inner.vm_segment.as_ptr();
-                // TODO: Query the CPU for the cache line size via CPUID, we use 64 bytes as the cache line size here.
-                for i in _byte_range.step_by(64) {
-                    // TODO: Call the cache line flush command in the corresponding architecture.
-                    todo!()
-                }
-                Ok(())
-            }
+    pub fn sync(&self, byte_range: Range<usize>) -> Result<(), Error> {
+        if byte_range.start > byte_range.end || byte_range.end > self.nbytes() {
+            return Err(Error::InvalidArgs);
+        }
+        if self.inner.is_cache_coherent {
+            return Ok(());
         }
+
+        // Write back the data written by the CPU, and discard the cache lines so that
+        // the CPU will read the data written by the device from the memory.
+        maintain_cache(&self.inner.vm_segment, byte_range, CacheOp::Flush);
+        Ok(())
     }
 }
 
@@ -171,6 +179,13 @@ impl HasDaddr for DmaStream {
 
 impl Drop for DmaStreamInner {
     fn drop(&mut self) {
+        // Discard the cache lines that may be filled speculatively while the
+        // device was writing, so the CPU will not read stale data.
+        if !self.is_cache_coherent && self.direction != DmaDirection::ToDevice {
+            let byte_range = 0..self.vm_segment.nbytes();
+            maintain_cache(&self.vm_segment, byte_range, CacheOp::Invalidate);
+        }
+
         let frame_count = self.vm_segment.nframes();
         let start_paddr = self.vm_segment.start_paddr();
         // Ensure that the addresses used later will not overflow
@@ -242,6 +257,87 @@ impl HasPaddr for DmaStream {
     }
 }
 
+/// The cache maintenance operations for non-coherent streaming DMA.
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum CacheOp {
+    /// Writes back the dirty cache lines to the memory.
+    Flush,
+    /// Discards the cache lines.
+    Invalidate,
+}
+
+/// Performs the cache maintenance operation on `byte_range` of the segment.
+fn maintain_cache(vm_segment: &Segment, byte_range: Range<usize>, op: CacheOp) {
+    #[cfg(ktest)]
+    test::record_cache_op(op);
+
+    let start_va = paddr_to_vaddr(vm_segment.start_paddr());
+    let range = start_va + byte_range.start..start_va + byte_range.end;
+    match op {
+        CacheOp::Flush => flush_dcache_range(range),
+        CacheOp::Invalidate => invalidate_dcache_range(range),
+    }
+}
+
+/// A streaming DMA mapping for a buffer that spans several physically
+/// non-contiguous segments, i.e., a scatter list.
+///
+/// Each segment is mapped as an individual [`DmaStream`]. All of them
+/// are unmapped when this object is dropped.
+#[derive(Debug, Clone)]
+pub struct DmaScatterList {
+    streams: Vec<DmaStream>,
+}
+
+impl DmaScatterList {
+    /// Establishes DMA stream mappings for the given [`Segment`]s.
+    ///
+    /// The method fails if any of the segments already belongs to a DMA
+    /// mapping. In that case, the segments that have been mapped are
+    /// unmapped before returning.
+    pub fn map(
+        vm_segments: impl IntoIterator<Item = Segment>,
+        direction: DmaDirection,
+        is_cache_coherent: bool,
+    ) -> Result<Self, DmaError> {
+        let streams = vm_segments
+            .into_iter()
+            .map(|vm_segment| DmaStream::map(vm_segment, direction, is_cache_coherent))
+            .collect::<Result<Vec<_>, _>>()?;
+        if streams.is_empty() {
+            return Err(DmaError::InvalidArgs);
+        }
+        Ok(Self { streams })
+    }
+
+    /// Returns the device address and the length in bytes of each entry.
+    pub fn entries(&self) -> impl Iterator<Item = (Daddr, usize)> + '_ {
+        self.streams
+            .iter()
+            .map(|stream| (stream.daddr(), stream.nbytes()))
+    }
+
+    /// Returns the streaming DMA mappings of the entries.
+    pub fn streams(&self) -> &[DmaStream] {
+        &self.streams
+    }
+
+    /// Returns the total number of bytes.
+    pub fn nbytes(&self) -> usize {
+        self.streams.iter().map(DmaStream::nbytes).sum()
+    }
+
+    /// Synchronizes all the entries with the device.
+    ///
+    /// See [`DmaStream::sync`] for when this method should be called.
+    pub fn sync(&self) -> Result<(), Error> {
+        for stream in self.streams.iter() {
+            stream.sync(0..stream.nbytes())?;
+        }
+        Ok(())
+    }
+}
+
 /// A slice of streaming DMA mapping.
 #[derive(Debug)]
 pub struct DmaStreamSlice<'a> {
@@ -312,10 +408,21 @@ impl HasPaddr for DmaStreamSlice<'_> {
 #[cfg(ktest)]
 mod test {
     use alloc::vec;
+    use core::sync::atomic::{AtomicUsize, Ordering};
 
     use super::*;
     use crate::{mm::FrameAllocOptions, prelude::*};
 
+    static NR_FLUSHES: AtomicUsize = AtomicUsize::new(0);
+    static NR_INVALIDATIONS: AtomicUsize = AtomicUsize::new(0);
+
+    pub(super) fn record_cache_op(op: CacheOp) {
+        match op {
+            CacheOp::Flush => NR_FLUSHES.fetch_add(1, Ordering::Relaxed),
+            CacheOp::Invalidate => NR_INVALIDATIONS.fetch_add(1, Ordering::Relaxed),
+        };
+    }
+
     #[ktest]
     fn streaming_map() {
         let vm_segment = FrameAllocOptions::new(1)
@@ -375,4 +482,81 @@ mod test {
         reader.read(&mut buf_read.as_mut_slice().into());
         assert_eq!(buf_read, buf_write);
     }
+
+    #[ktest]
+    fn to_device_cache_ops() {
+        let vm_segment = FrameAllocOptions::new(1)
+            .is_contiguous(true)
+            .alloc_contiguous()
+            .unwrap();
+        let nr_flushes = NR_FLUSHES.load(Ordering::Relaxed);
+        let nr_invalidations = NR_INVALIDATIONS.load(Ordering::Relaxed);
+
+        let dma_stream = DmaStream::map(vm_segment, DmaDirection::ToDevice, false).unwrap();
+        assert_eq!(NR_FLUSHES.load(Ordering::Relaxed), nr_flushes + 1);
+
+        drop(dma_stream);
+        assert_eq!(NR_INVALIDATIONS.load(Ordering::Relaxed), nr_invalidations);
+    }
+
+    #[ktest]
+    fn from_device_cache_ops() {
+        let vm_segment = FrameAllocOptions::new(1)
+            .is_contiguous(true)
+            .alloc_contiguous()
+            .unwrap();
+        let nr_invalidations = NR_INVALIDATIONS.load(Ordering::Relaxed);
+
+        let dma_stream = DmaStream::map(vm_segment, DmaDirection::FromDevice, false).unwrap();
+        drop(dma_stream);
+        assert_eq!(
+            NR_INVALIDATIONS.load(Ordering::Relaxed),
+            nr_invalidations + 1
+        );
+    }
+
+    #[ktest]
+    fn scatter_list() {
+        let vm_segments = (0..3).map(|_| {
+            FrameAllocOptions::new(1)
+                .is_contiguous(true)
+                .alloc_contiguous()
+                .unwrap()
+        });
+        let scatter_list = DmaScatterList::map(vm_segments, DmaDirection::ToDevice, false).unwrap();
+        assert_eq!(scatter_list.entries().count(), 3);
+        assert_eq!(scatter_list.nbytes(), 3 * PAGE_SIZE);
+        for (stream, (daddr, len)) in scatter_list.streams().iter().zip(scatter_list.entries()) {
+            assert_eq!(daddr, stream.paddr() as Daddr);
+            assert_eq!(len, PAGE_SIZE);
+        }
+    }
+
+    #[ktest]
+    fn sync_cache_ops() {
+        let vm_segment = FrameAllocOptions::new(2)
+            .is_contiguous(true)
+            .alloc_contiguous()
+            .unwrap();
+        let dma_stream = DmaStream::map(vm_segment, DmaDirection::Bidirectional, false).unwrap();
+
+        let nr_flushes = NR_FLUSHES.load(Ordering::Relaxed);
+        dma_stream.sync(PAGE_SIZE..2 * PAGE_SIZE).unwrap();
+        assert_eq!(NR_FLUSHES.load(Ordering::Relaxed), nr_flushes + 1);
+
+        assert_eq!(
+            dma_stream.sync(0..2 * PAGE_SIZE + 1),
+            Err(Error::InvalidArgs)
+        );
+        assert_eq!(NR_FLUSHES.load(Ordering::Relaxed), nr_flushes + 1);
+
+        // A cache coherent mapping needs no synchronization.
+        let vm_segment = FrameAllocOptions::new(1)
+            .is_contiguous(true)
+            .alloc_contiguous()
+            .unwrap();
+        let dma_stream = DmaStream::map(vm_segment, DmaDirection::Bidirectional, true).unwrap();
+        dma_stream.sync(0..PAGE_SIZE).unwrap();
+        assert_eq!(NR_FLUSHES.load(Ordering::Relaxed), nr_flushes + 1);
+    }
 }
@@ -6,7 +6,7 @@ mod dma_stream;
 use alloc::collections::BTreeSet;
 
 pub use dma_coherent::DmaCoherent;
-pub use dma_stream::{DmaDirection, DmaStream, DmaStreamSlice};
+pub use dma_stream::{DmaDirection, DmaScatterList, DmaStream, DmaStreamSlice};
 use inherit_methods_macro::inherit_methods;
 use spin::Once;
 
@@ -31,6 +31,7 @@ pub enum DmaType {
 pub enum DmaError {
     InvalidArgs,
     AlreadyMapped,
+    NoMemory,
 }
 
 /// A trait for types that have mapped address in the device address space.
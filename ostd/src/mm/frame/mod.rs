@@ -57,6 +57,13 @@ impl Frame {
         self.page.size()
     }
 
+    /// Returns the number of handles to the page frame.
+    ///
+    /// The count may be changed concurrently by other handles, so it is only a snapshot.
+    pub fn reference_count(&self) -> u32 {
+        self.page.reference_count()
+    }
+
     /// Returns a raw pointer to the starting virtual address of the frame.
     pub fn as_ptr(&self) -> *const u8 {
         paddr_to_vaddr(self.start_paddr()) as *const u8
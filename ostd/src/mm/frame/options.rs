@@ -5,7 +5,7 @@
 use super::{Frame, Segment};
 use crate::{
     mm::{
-        page::{self, meta::FrameMeta},
+        page::{self, allocator::NodeId, meta::FrameMeta},
         PAGE_SIZE,
     },
     prelude::*,
@@ -23,6 +23,7 @@ pub struct FrameAllocOptions {
     nframes: usize,
     is_contiguous: bool,
     uninit: bool,
+    node: Option<NodeId>,
 }
 
 impl FrameAllocOptions {
@@ -32,6 +33,7 @@ impl FrameAllocOptions {
             nframes,
             is_contiguous: false,
             uninit: false,
+            node: None,
         }
     }
 
@@ -54,15 +56,28 @@ impl FrameAllocOptions {
         self
     }
 
+    /// Sets the NUMA node whose memory is preferred.
+    ///
+    /// If the node runs out of memory, the frames are allocated from other
+    /// nodes. By default, the node of the current CPU is preferred.
+    pub fn node(&mut self, node: NodeId) -> &mut Self {
+        self.node = Some(node);
+        self
+    }
+
     /// Allocates a collection of page frames according to the given options.
     pub fn alloc(&self) -> Result<Vec<Frame>> {
         let pages = if self.is_contiguous {
-            page::allocator::alloc(self.nframes * PAGE_SIZE, |_| FrameMeta::default())
-                .ok_or(Error::NoMemory)?
+            page::allocator::alloc_on_node(self.node, self.nframes * PAGE_SIZE, |_| {
+                FrameMeta::default()
+            })
+            .ok_or(Error::NoMemory)?
         } else {
-            page::allocator::alloc_contiguous(self.nframes * PAGE_SIZE, |_| FrameMeta::default())
-                .ok_or(Error::NoMemory)?
-                .into()
+            page::allocator::alloc_contiguous_on_node(self.node, self.nframes * PAGE_SIZE, |_| {
+                FrameMeta::default()
+            })
+            .ok_or(Error::NoMemory)?
+            .into()
         };
         let frames: Vec<_> = pages.into_iter().map(|page| Frame { page }).collect();
         if !self.uninit {
@@ -80,7 +95,8 @@ impl FrameAllocOptions {
             return Err(Error::InvalidArgs);
         }
 
-        let page = page::allocator::alloc_single(FrameMeta::default()).ok_or(Error::NoMemory)?;
+        let page = page::allocator::alloc_single_on_node(self.node, FrameMeta::default())
+            .ok_or(Error::NoMemory)?;
         let frame = Frame { page };
         if !self.uninit {
             frame.writer().fill(0);
@@ -99,9 +115,11 @@ impl FrameAllocOptions {
         }
 
         let segment: Segment =
-            page::allocator::alloc_contiguous(self.nframes * PAGE_SIZE, |_| FrameMeta::default())
-                .ok_or(Error::NoMemory)?
-                .into();
+            page::allocator::alloc_contiguous_on_node(self.node, self.nframes * PAGE_SIZE, |_| {
+                FrameMeta::default()
+            })
+            .ok_or(Error::NoMemory)?
+            .into();
         if !self.uninit {
             segment.writer().fill(0);
         }
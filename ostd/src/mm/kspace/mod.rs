@@ -21,7 +21,7 @@
 //! | |         For frame metadata, 1 TiB.
 //! | |         Mapped frames are untracked.
 //! +-+ <- 0xffff_fe00_0000_0000
-//! | |         For vm alloc/io mappings, 1 TiB.
+//! | |         For vm alloc/io mappings, 1 TiB. See [`vmalloc`].
 //! | |         Mapped frames are tracked with handles.
 //! +-+ <- 0xffff_fd00_0000_0000
 //! | |
@@ -38,6 +38,8 @@
 //! If the address width is (according to [`crate::arch::mm::PagingConsts`])
 //! 39 bits or 57 bits, the memory space just adjust porportionally.
 
+pub(crate) mod vmalloc;
+
 use alloc::vec::Vec;
 use core::{mem::ManuallyDrop, ops::Range};
 
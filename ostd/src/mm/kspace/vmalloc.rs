@@ -0,0 +1,306 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Virtually contiguous kernel memory.
+//!
+//! An area allocated by [`vmalloc`] occupies a virtually contiguous range in
+//! [`VMALLOC_VADDR_RANGE`], while the frames backing it can be scattered in the
+//! physical memory. So it can be much larger than the largest contiguous run
+//! of free frames.
+//!
+//! The frames are allocated and mapped lazily, when the kernel page fault
+//! handler finds that the faulting address belongs to a live area. The page
+//! allocator cannot be used in the interrupt context, so an area that is
+//! accessed by interrupt handlers must be populated beforehand with
+//! [`VmallocArea::populate`].
+//!
+//! Each area is followed by an unmapped guard page, so that overrunning an
+//! area faults instead of silently corrupting the next one.
+
+use alloc::{collections::BTreeMap, vec::Vec};
+use core::ops::Range;
+
+use align_ext::AlignExt;
+
+use super::{KERNEL_PAGE_TABLE, VMALLOC_VADDR_RANGE};
+use crate::{
+    mm::{
+        page_prop::{CachePolicy, PageFlags, PageProperty, PrivilegedPageFlags},
+        page_table::PageTableItem,
+        tlb::tlb_shootdown_kernel,
+        FrameAllocOptions, Vaddr, VmIo, VmReader, VmWriter, PAGE_SIZE,
+    },
+    sync::SpinLock,
+    Error, Result,
+};
+
+/// The live areas, indexed by their start addresses.
+///
+/// The value is the end address of an area, excluding its guard page.
+///
+/// The lock also serializes the population and the teardown of the areas, so
+/// that a page is never mapped into an area that is being freed.
+static AREAS: SpinLock<BTreeMap<Vaddr, Vaddr>> = SpinLock::new(BTreeMap::new());
+
+/// Allocates a virtually contiguous kernel memory area of at least `size` bytes.
+///
+/// The memory is zeroed. The area is not backed by any frames until it is
+/// accessed, see the [module-level documentation](self) for details.
+pub fn vmalloc(size: usize) -> Result<VmallocArea> {
+    if size == 0 {
+        return Err(Error::InvalidArgs);
+    }
+    let size = size.checked_add(PAGE_SIZE - 1).ok_or(Error::Overflow)? / PAGE_SIZE * PAGE_SIZE;
+    let size_with_guard = size.checked_add(PAGE_SIZE).ok_or(Error::Overflow)?;
+
+    let mut areas = AREAS.lock_irq_disabled();
+
+    // Find the first hole that is large enough.
+    let mut hole_start = VMALLOC_VADDR_RANGE.start;
+    for (&start, &end) in areas.iter() {
+        if start - hole_start >= size_with_guard {
+            break;
+        }
+        hole_start = end + PAGE_SIZE;
+    }
+    if VMALLOC_VADDR_RANGE.end - hole_start < size_with_guard {
+        return Err(Error::NotEnoughResources);
+    }
+
+    areas.insert(hole_start, hole_start + size);
+    Ok(VmallocArea {
+        range: hole_start..hole_start + size,
+    })
+}
+
+/// A virtually contiguous kernel memory area allocated by [`vmalloc`].
+///
+/// The area and its backing frames are freed when it is dropped.
+#[derive(Debug)]
+pub struct VmallocArea {
+    range: Range<Vaddr>,
+}
+
+impl VmallocArea {
+    /// Returns the start virtual address of the area.
+    pub fn start(&self) -> Vaddr {
+        self.range.start
+    }
+
+    /// Returns the length of the area in bytes.
+    pub fn len(&self) -> usize {
+        self.range.len()
+    }
+
+    /// Returns a raw pointer to the start of the area.
+    pub fn as_ptr(&self) -> *const u8 {
+        self.range.start as *const u8
+    }
+
+    /// Returns a mutable raw pointer to the start of the area.
+    pub fn as_mut_ptr(&self) -> *mut u8 {
+        self.range.start as *mut u8
+    }
+
+    /// Backs the whole area with frames now.
+    ///
+    /// A populated area never faults, so it can be accessed in the interrupt
+    /// context.
+    pub fn populate(&self) -> Result<()> {
+        let _areas = AREAS.lock_irq_disabled();
+        for vaddr in self.range.clone().step_by(PAGE_SIZE) {
+            map_page(vaddr)?;
+        }
+        Ok(())
+    }
+
+    /// Returns a reader to read data from it.
+    pub fn reader(&self) -> VmReader<'_> {
+        // SAFETY: The area is untyped kernel memory that is valid during the
+        // lifetime of `self`. It is never exposed as a typed slice.
+        unsafe { VmReader::from_kernel_space(self.as_ptr(), self.len()) }
+    }
+
+    /// Returns a writer to write data into it.
+    pub fn writer(&self) -> VmWriter<'_> {
+        // SAFETY: The area is untyped kernel memory that is valid during the
+        // lifetime of `self`. It is never exposed as a typed slice.
+        unsafe { VmWriter::from_kernel_space(self.as_mut_ptr(), self.len()) }
+    }
+}
+
+impl VmIo for VmallocArea {
+    fn read_bytes(&self, offset: usize, buf: &mut [u8]) -> Result<()> {
+        // Do bound check with potential integer overflow in mind
+        let max_offset = offset.checked_add(buf.len()).ok_or(Error::Overflow)?;
+        if max_offset > self.len() {
+            return Err(Error::InvalidArgs);
+        }
+        let len = self.reader().skip(offset).read(&mut buf.into());
+        debug_assert!(len == buf.len());
+        Ok(())
+    }
+
+    fn write_bytes(&self, offset: usize, buf: &[u8]) -> Result<()> {
+        // Do bound check with potential integer overflow in mind
+        let max_offset = offset.checked_add(buf.len()).ok_or(Error::Overflow)?;
+        if max_offset > self.len() {
+            return Err(Error::InvalidArgs);
+        }
+        let len = self.writer().skip(offset).write(&mut buf.into());
+        debug_assert!(len == buf.len());
+        Ok(())
+    }
+}
+
+impl Drop for VmallocArea {
+    fn drop(&mut self) {
+        let mut pages = Vec::new();
+        {
+            let _areas = AREAS.lock_irq_disabled();
+
+            let page_table = KERNEL_PAGE_TABLE.get().unwrap();
+            let mut cursor = page_table.cursor_mut(&self.range).unwrap();
+            loop {
+                let remaining = self.range.end - cursor.virt_addr();
+                // SAFETY: The area is owned by `self`, and nothing refers to it any more.
+                match unsafe { cursor.take_next(remaining) } {
+                    PageTableItem::NotMapped { .. } => break,
+                    PageTableItem::Mapped { page, .. } => pages.push(page),
+                    PageTableItem::MappedUntracked { .. } => {
+                        panic!("found untracked memory mapped in a vmalloc area");
+                    }
+                }
+            }
+        }
+
+        // Other CPUs may still cache the stale translations. Neither the
+        // frames nor the range can be reused before they are flushed. The
+        // shootdown waits for other CPUs, so it is done without the lock.
+        tlb_shootdown_kernel(&self.range);
+        drop(pages);
+
+        AREAS.lock_irq_disabled().remove(&self.range.start);
+    }
+}
+
+/// Handles a kernel page fault at `vaddr` in [`VMALLOC_VADDR_RANGE`].
+///
+/// Returns whether `vaddr` belongs to a live area and the page is mapped.
+pub(crate) fn handle_page_fault(vaddr: Vaddr) -> bool {
+    let areas = AREAS.lock_irq_disabled();
+    let Some((_, &end)) = areas.range(..=vaddr).next_back() else {
+        return false;
+    };
+    if vaddr >= end {
+        return false;
+    }
+
+    assert!(
+        !crate::trap::in_interrupt_context(),
+        "kernel page fault: a vmalloc area must be populated to be accessed in the interrupt context",
+    );
+    map_page(vaddr.align_down(PAGE_SIZE)).is_ok()
+}
+
+/// Maps a zeroed frame at `vaddr` if it is not mapped yet.
+///
+/// The caller must hold the lock of [`AREAS`], and `vaddr` must be a page in a
+/// live area.
+fn map_page(vaddr: Vaddr) -> Result<()> {
+    let page_table = KERNEL_PAGE_TABLE.get().unwrap();
+    let mut cursor = page_table.cursor_mut(&(vaddr..vaddr + PAGE_SIZE))?;
+    if !matches!(cursor.query()?, PageTableItem::NotMapped { .. }) {
+        return Ok(());
+    }
+
+    let frame = FrameAllocOptions::new(1).alloc_single()?;
+    let prop = PageProperty {
+        flags: PageFlags::RW,
+        cache: CachePolicy::Writeback,
+        priv_flags: PrivilegedPageFlags::GLOBAL,
+    };
+    // SAFETY: The page belongs to a live vmalloc area, which is only accessed
+    // through the pointers of the area.
+    unsafe {
+        cursor.map(frame.into(), prop);
+    }
+    Ok(())
+}
+
+#[cfg(ktest)]
+mod test {
+    use super::*;
+    use crate::{
+        mm::{
+            page::{allocator, meta::FrameMeta, Page},
+            Paddr,
+        },
+        prelude::*,
+    };
+
+    fn paddr_of(vaddr: Vaddr) -> Option<Paddr> {
+        KERNEL_PAGE_TABLE
+            .get()
+            .unwrap()
+            .query(vaddr)
+            .map(|(paddr, _)| paddr)
+    }
+
+    #[ktest]
+    fn scattered_frames() {
+        const NR_PAGES: usize = 64;
+
+        // Punch holes into the physical memory, so that no two free frames
+        // taken from the holes are contiguous.
+        let pages: Vec<Page<FrameMeta>> =
+            allocator::alloc_contiguous(NR_PAGES * 2 * PAGE_SIZE, |_| FrameMeta::default())
+                .unwrap()
+                .into();
+        let holding: Vec<_> = pages.into_iter().step_by(2).collect();
+
+        let area = vmalloc(NR_PAGES * PAGE_SIZE).unwrap();
+        assert_eq!(area.len(), NR_PAGES * PAGE_SIZE);
+        assert!(paddr_of(area.start()).is_none());
+
+        for i in 0..NR_PAGES {
+            let offset = i * PAGE_SIZE;
+            assert_eq!(area.read_val::<u64>(offset).unwrap(), 0);
+            area.write_val(offset + 8, &(i as u64)).unwrap();
+        }
+        for i in 0..NR_PAGES {
+            assert_eq!(area.read_val::<u64>(i * PAGE_SIZE + 8).unwrap(), i as u64);
+        }
+
+        let paddrs: Vec<_> = (0..NR_PAGES)
+            .map(|i| paddr_of(area.start() + i * PAGE_SIZE).unwrap())
+            .collect();
+        assert!(paddrs.windows(2).any(|w| w[1] != w[0] + PAGE_SIZE));
+
+        drop(holding);
+    }
+
+    #[ktest]
+    fn populate_and_free() {
+        let area = vmalloc(PAGE_SIZE * 3 + 1).unwrap();
+        assert_eq!(area.len(), PAGE_SIZE * 4);
+        area.populate().unwrap();
+        for i in 0..4 {
+            assert!(paddr_of(area.start() + i * PAGE_SIZE).is_some());
+        }
+        // The guard page is never mapped.
+        assert!(paddr_of(area.start() + area.len()).is_none());
+
+        let start = area.start();
+        drop(area);
+        assert!(paddr_of(start).is_none());
+
+        // The freed range is reused.
+        let area = vmalloc(PAGE_SIZE).unwrap();
+        assert_eq!(area.start(), start);
+    }
+
+    #[ktest]
+    fn zero_sized() {
+        assert_eq!(vmalloc(0).unwrap_err(), Error::InvalidArgs);
+    }
+}
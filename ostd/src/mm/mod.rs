@@ -8,6 +8,7 @@ pub type Vaddr = usize;
 /// Physical addresses.
 pub type Paddr = usize;
 
+pub mod cache;
 pub(crate) mod dma;
 pub mod frame;
 pub(crate) mod heap_allocator;
@@ -17,7 +18,9 @@ mod offset;
 pub(crate) mod page;
 pub(crate) mod page_prop;
 pub(crate) mod page_table;
+mod slab;
 pub mod stat;
+pub(crate) mod tlb;
 pub mod vm_space;
 
 use alloc::vec::Vec;
@@ -26,10 +29,17 @@ use core::{fmt::Debug, ops::Range};
 use spin::Once;
 
 pub use self::{
-    dma::{Daddr, DmaCoherent, DmaDirection, DmaStream, DmaStreamSlice, HasDaddr},
+    dma::{Daddr, DmaCoherent, DmaDirection, DmaScatterList, DmaStream, DmaStreamSlice, HasDaddr},
     frame::{options::FrameAllocOptions, Frame, Segment},
     io::{KernelSpace, PodOnce, UserSpace, VmIo, VmIoOnce, VmReader, VmWriter},
-    page_prop::{CachePolicy, PageFlags, PageProperty},
+    kspace::vmalloc::{vmalloc, VmallocArea},
+    page::{
+        allocator::{set_cpu_node, NodeId},
+        meta::PageUsage,
+        page_info, PageInfo,
+    },
+    page_prop::{CachePolicy, PageFlags, PageProperty, RssType},
+    slab::{SlabBox, SlabCache},
     vm_space::VmSpace,
 };
 pub(crate) use self::{
@@ -6,6 +6,7 @@
 //! allocating pages rather untyped memory from this module.
 
 use alloc::vec::Vec;
+use core::ops::Range;
 
 use align_ext::AlignExt;
 use buddy_system_allocator::FrameAllocator;
@@ -14,39 +15,126 @@ use spin::Once;
 
 use super::{cont_pages::ContPages, meta::PageMeta, Page};
 use crate::{
-    boot::memory_region::MemoryRegionType,
+    boot::memory_region::{MemoryRegion, MemoryRegionType},
+    cpu::this_cpu,
     mm::{Paddr, PAGE_SIZE},
     sync::SpinLock,
 };
 
+/// The ID of a NUMA node.
+pub type NodeId = usize;
+
 /// FrameAllocator with a counter for allocated memory
+///
+/// The free frames are kept in per-NUMA-node free lists. An allocation prefers
+/// the given node, or the node of the current CPU if no node is given, and falls
+/// back to the other nodes when the preferred node is exhausted.
 pub(in crate::mm) struct CountingFrameAllocator {
-    allocator: FrameAllocator,
+    nodes: Vec<NodeFrameAllocator>,
+    /// The node that each CPU belongs to, indexed by the CPU ID.
+    cpu_nodes: Vec<NodeId>,
     total: usize,
     allocated: usize,
 }
 
+struct NodeFrameAllocator {
+    allocator: FrameAllocator,
+    /// The ranges of the frame numbers that belong to the node.
+    ranges: Vec<Range<usize>>,
+}
+
 impl CountingFrameAllocator {
-    pub fn new(allocator: FrameAllocator, total: usize) -> Self {
+    pub fn new() -> Self {
         CountingFrameAllocator {
-            allocator,
-            total,
+            nodes: Vec::new(),
+            cpu_nodes: Vec::new(),
+            total: 0,
             allocated: 0,
         }
     }
 
-    pub fn alloc(&mut self, count: usize) -> Option<usize> {
-        match self.allocator.alloc(count) {
-            Some(value) => {
-                self.allocated += count * PAGE_SIZE;
-                Some(value)
+    /// Adds the free frames in `frames` to the given node.
+    pub fn add_frames(&mut self, node: NodeId, frames: Range<usize>) {
+        while self.nodes.len() <= node {
+            self.nodes.push(NodeFrameAllocator {
+                allocator: FrameAllocator::new(),
+                ranges: Vec::new(),
+            });
+        }
+
+        let node = &mut self.nodes[node];
+        node.allocator.add_frame(frames.start, frames.end);
+        self.total += frames.len() * PAGE_SIZE;
+        node.ranges.push(frames);
+    }
+
+    /// Adds the usable memory regions to the given node.
+    ///
+    /// The regions must not overlap, which is guaranteed by the normalized
+    /// memory regions in the boot information. The other types of regions,
+    /// e.g., the reserved, ACPI or MMIO regions, are skipped.
+    pub fn add_usable_regions(&mut self, node: NodeId, regions: &[MemoryRegion]) {
+        for region in regions.iter() {
+            if region.typ() != MemoryRegionType::Usable {
+                continue;
             }
-            None => None,
+            // Make the memory region page-aligned, and skip if it is too small.
+            let start = region.base().align_up(PAGE_SIZE) / PAGE_SIZE;
+            let region_end = region.base().checked_add(region.len()).unwrap();
+            let end = region_end.align_down(PAGE_SIZE) / PAGE_SIZE;
+            if end <= start {
+                continue;
+            }
+            // Add global free pages to the frame allocator.
+            self.add_frames(node, start..end);
+            info!(
+                "Found usable region, start:{:x}, end:{:x}",
+                region.base(),
+                region.base() + region.len()
+            );
         }
     }
 
+    /// Sets the node that the given CPU belongs to.
+    pub fn set_cpu_node(&mut self, cpu_id: u32, node: NodeId) {
+        let cpu_id = cpu_id as usize;
+        if self.cpu_nodes.len() <= cpu_id {
+            self.cpu_nodes.resize(cpu_id + 1, 0);
+        }
+        self.cpu_nodes[cpu_id] = node;
+    }
+
+    pub fn alloc(&mut self, count: usize) -> Option<usize> {
+        self.alloc_on_node(None, count)
+    }
+
+    /// Allocates frames, preferring the given node.
+    ///
+    /// If `node` is `None`, the node of the current CPU is preferred. If `node`
+    /// does not exist, e.g., on a single-node system, the hint is ignored.
+    pub fn alloc_on_node(&mut self, node: Option<NodeId>, count: usize) -> Option<usize> {
+        let preferred = match node {
+            Some(node) if node < self.nodes.len() => node,
+            Some(_) => 0,
+            None => self.current_node(),
+        };
+
+        let nr_nodes = self.nodes.len();
+        let value = (0..nr_nodes)
+            .map(|i| (preferred + i) % nr_nodes)
+            .find_map(|node| self.nodes[node].allocator.alloc(count))?;
+
+        self.allocated += count * PAGE_SIZE;
+        Some(value)
+    }
+
     pub fn dealloc(&mut self, start_frame: usize, count: usize) {
-        self.allocator.dealloc(start_frame, count);
+        let node = self
+            .nodes
+            .iter_mut()
+            .find(|node| node.ranges.iter().any(|range| range.contains(&start_frame)))
+            .expect("the frames do not belong to any node");
+        node.allocator.dealloc(start_frame, count);
         self.allocated -= count * PAGE_SIZE;
     }
 
@@ -57,6 +145,19 @@ impl CountingFrameAllocator {
     pub fn mem_available(&self) -> usize {
         self.total - self.allocated
     }
+
+    fn current_node(&self) -> NodeId {
+        // Avoid accessing the CPU ID if there is only one node, since the page
+        // allocator is used before the CPU-local storage is fully initialized.
+        if self.nodes.len() <= 1 {
+            return 0;
+        }
+
+        self.cpu_nodes
+            .get(this_cpu() as usize)
+            .copied()
+            .unwrap_or(0)
+    }
 }
 
 pub(in crate::mm) static PAGE_ALLOCATOR: Once<SpinLock<CountingFrameAllocator>> = Once::new();
@@ -65,10 +166,37 @@ pub(in crate::mm) static PAGE_ALLOCATOR: Once<SpinLock<CountingFrameAllocator>>
 ///
 /// The metadata of the page is initialized with the given metadata.
 pub(crate) fn alloc_single<M: PageMeta>(metadata: M) -> Option<Page<M>> {
-    PAGE_ALLOCATOR.get().unwrap().lock().alloc(1).map(|idx| {
-        let paddr = idx * PAGE_SIZE;
-        Page::from_unused(paddr, metadata)
-    })
+    alloc_single_on_node(None, metadata)
+}
+
+/// Allocate a single page, preferring the memory of the given NUMA node.
+///
+/// If `node` is `None`, the node of the current CPU is preferred. If the
+/// preferred node runs out of memory, the page is allocated from other nodes.
+pub(crate) fn alloc_single_on_node<M: PageMeta>(
+    node: Option<NodeId>,
+    metadata: M,
+) -> Option<Page<M>> {
+    PAGE_ALLOCATOR
+        .get()
+        .unwrap()
+        .lock()
+        .alloc_on_node(node, 1)
+        .map(|idx| {
+            let paddr = idx * PAGE_SIZE;
+            Page::from_unused(paddr, metadata)
+        })
+}
+
+/// Sets the NUMA node that the given CPU belongs to.
+///
+/// The allocations without node hints prefer the node of the current CPU.
+pub fn set_cpu_node(cpu_id: u32, node: NodeId) {
+    PAGE_ALLOCATOR
+        .get()
+        .unwrap()
+        .lock()
+        .set_cpu_node(cpu_id, node);
 }
 
 /// Allocate a contiguous range of pages of a given length in bytes.
@@ -81,6 +209,21 @@ pub(crate) fn alloc_single<M: PageMeta>(metadata: M) -> Option<Page<M>> {
 ///
 /// The function panics if the length is not base-page-aligned.
 pub(crate) fn alloc_contiguous<M: PageMeta, F>(len: usize, metadata_fn: F) -> Option<ContPages<M>>
+where
+    F: FnMut(Paddr) -> M,
+{
+    alloc_contiguous_on_node(None, len, metadata_fn)
+}
+
+/// Allocate a contiguous range of pages, preferring the memory of the given
+/// NUMA node.
+///
+/// See [`alloc_contiguous`] and [`alloc_single_on_node`] for details.
+pub(crate) fn alloc_contiguous_on_node<M: PageMeta, F>(
+    node: Option<NodeId>,
+    len: usize,
+    metadata_fn: F,
+) -> Option<ContPages<M>>
 where
     F: FnMut(Paddr) -> M,
 {
@@ -89,7 +232,7 @@ where
         .get()
         .unwrap()
         .lock()
-        .alloc(len / PAGE_SIZE)
+        .alloc_on_node(node, len / PAGE_SIZE)
         .map(|start| {
             ContPages::from_unused(start * PAGE_SIZE..start * PAGE_SIZE + len, metadata_fn)
         })
@@ -107,7 +250,21 @@ where
 /// # Panics
 ///
 /// The function panics if the length is not base-page-aligned.
-pub(crate) fn alloc<M: PageMeta, F>(len: usize, mut metadata_fn: F) -> Option<Vec<Page<M>>>
+pub(crate) fn alloc<M: PageMeta, F>(len: usize, metadata_fn: F) -> Option<Vec<Page<M>>>
+where
+    F: FnMut(Paddr) -> M,
+{
+    alloc_on_node(None, len, metadata_fn)
+}
+
+/// Allocate pages, preferring the memory of the given NUMA node.
+///
+/// See [`alloc`] and [`alloc_single_on_node`] for details.
+pub(crate) fn alloc_on_node<M: PageMeta, F>(
+    node: Option<NodeId>,
+    len: usize,
+    mut metadata_fn: F,
+) -> Option<Vec<Page<M>>>
 where
     F: FnMut(Paddr) -> M,
 {
@@ -116,7 +273,7 @@ where
     let mut allocator = PAGE_ALLOCATOR.get().unwrap().lock();
     let mut vector = Vec::new();
     for _ in 0..nframes {
-        let paddr = allocator.alloc(1)? * PAGE_SIZE;
+        let paddr = allocator.alloc_on_node(node, 1)? * PAGE_SIZE;
         let page = Page::<M>::from_unused(paddr, metadata_fn(paddr));
         vector.push(page);
     }
@@ -125,27 +282,101 @@ where
 
 pub(crate) fn init() {
     let regions = crate::boot::memory_regions();
-    let mut total: usize = 0;
-    let mut allocator = FrameAllocator::<32>::new();
-    for region in regions.iter() {
-        if region.typ() == MemoryRegionType::Usable {
-            // Make the memory region page-aligned, and skip if it is too small.
-            let start = region.base().align_up(PAGE_SIZE) / PAGE_SIZE;
-            let region_end = region.base().checked_add(region.len()).unwrap();
-            let end = region_end.align_down(PAGE_SIZE) / PAGE_SIZE;
-            if end <= start {
-                continue;
-            }
-            // Add global free pages to the frame allocator.
-            allocator.add_frame(start, end);
-            total += (end - start) * PAGE_SIZE;
-            info!(
-                "Found usable region, start:{:x}, end:{:x}",
-                region.base(),
-                region.base() + region.len()
-            );
+    // TODO: Parse the NUMA topology (e.g., from the ACPI SRAT table). Now all
+    // the memory is assumed to belong to node 0.
+    let mut allocator = CountingFrameAllocator::new();
+    allocator.add_usable_regions(0, regions);
+    PAGE_ALLOCATOR.call_once(|| SpinLock::new(allocator));
+}
+
+#[cfg(ktest)]
+mod test {
+    use alloc::vec;
+
+    use super::*;
+    use crate::{boot::memory_region::non_overlapping_regions_from, prelude::*};
+
+    const NODE0_FRAMES: Range<usize> = 0x1000..0x1010;
+    const NODE1_FRAMES: Range<usize> = 0x2000..0x2010;
+
+    #[ktest]
+    fn alloc_on_node() {
+        let mut allocator = CountingFrameAllocator::new();
+        allocator.add_frames(0, NODE0_FRAMES);
+        allocator.add_frames(1, NODE1_FRAMES);
+
+        let first = allocator.alloc_on_node(Some(1), 4).unwrap();
+        assert!(NODE1_FRAMES.contains(&first));
+        let second = allocator.alloc_on_node(Some(1), 8).unwrap();
+        assert!(NODE1_FRAMES.contains(&second));
+
+        // Node 1 is exhausted, so the frames come from node 0.
+        let remote = allocator.alloc_on_node(Some(1), 8).unwrap();
+        assert!(NODE0_FRAMES.contains(&remote));
+
+        // The frames are returned to the node that they belong to.
+        allocator.dealloc(second, 8);
+        let again = allocator.alloc_on_node(Some(1), 8).unwrap();
+        assert!(NODE1_FRAMES.contains(&again));
+        assert_eq!(allocator.mem_available(), (32 - 4 - 8 - 8) * PAGE_SIZE);
+    }
+
+    #[ktest]
+    fn alloc_on_cpu_node() {
+        let mut allocator = CountingFrameAllocator::new();
+        allocator.add_frames(0, NODE0_FRAMES);
+        allocator.add_frames(1, NODE1_FRAMES);
+        allocator.set_cpu_node(this_cpu(), 1);
+
+        let frame = allocator.alloc(1).unwrap();
+        assert!(NODE1_FRAMES.contains(&frame));
+    }
+
+    #[ktest]
+    fn single_node_ignores_hint() {
+        let mut allocator = CountingFrameAllocator::new();
+        allocator.add_frames(0, NODE0_FRAMES);
+
+        let frame = allocator.alloc_on_node(Some(3), 1).unwrap();
+        assert!(NODE0_FRAMES.contains(&frame));
+    }
+
+    #[ktest]
+    fn skip_unusable_regions() {
+        const HOLE: Range<usize> = 0x1004..0x1008;
+
+        // An unsorted memory map with overlapping usable regions and a reserved
+        // hole in the middle.
+        let regions = non_overlapping_regions_from(&[
+            MemoryRegion::new(0x1008 * PAGE_SIZE, 8 * PAGE_SIZE, MemoryRegionType::Usable),
+            MemoryRegion::new(0x1000 * PAGE_SIZE, 12 * PAGE_SIZE, MemoryRegionType::Usable),
+            MemoryRegion::new(
+                0x1004 * PAGE_SIZE,
+                4 * PAGE_SIZE,
+                MemoryRegionType::Reserved,
+            ),
+            MemoryRegion::new(0x1010 * PAGE_SIZE, PAGE_SIZE, MemoryRegionType::Reserved),
+            MemoryRegion::new(
+                0x1020 * PAGE_SIZE,
+                4 * PAGE_SIZE,
+                MemoryRegionType::Reclaimable,
+            ),
+        ]);
+        assert!(regions
+            .windows(2)
+            .all(|w| w[0].base() + w[0].len() <= w[1].base()));
+
+        let mut allocator = CountingFrameAllocator::new();
+        allocator.add_usable_regions(0, &regions);
+        // The overlapping part is added only once.
+        assert_eq!(allocator.mem_total(), (0x1010 - 0x1000 - 4) * PAGE_SIZE);
+
+        let mut frames = vec![];
+        while let Some(frame) = allocator.alloc(1) {
+            assert!(!HOLE.contains(&frame));
+            assert!((0x1000..0x1010).contains(&frame));
+            frames.push(frame);
         }
+        assert_eq!(frames.len(), 0x1010 - 0x1000 - 4);
     }
-    let counting_allocator = CountingFrameAllocator::new(allocator, total);
-    PAGE_ALLOCATOR.call_once(|| SpinLock::new(counting_allocator));
 }
@@ -60,7 +60,7 @@ use crate::{
 
 /// Represents the usage of a page.
 #[repr(u8)]
-#[derive(Debug, FromPrimitive, PartialEq)]
+#[derive(Debug, Clone, Copy, FromPrimitive, PartialEq, Eq)]
 pub enum PageUsage {
     // The zero variant is reserved for the unused type. Only an unused page
     // can be designated for one of the other purposes.
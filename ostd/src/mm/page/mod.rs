@@ -25,6 +25,7 @@ use core::{
     sync::atomic::{AtomicU32, AtomicUsize, Ordering},
 };
 
+use align_ext::AlignExt;
 pub use cont_pages::ContPages;
 use meta::{mapping, FrameMeta, MetaSlot, PageMeta, PageUsage};
 
@@ -33,6 +34,43 @@ use crate::mm::{Paddr, PagingConsts, Vaddr};
 
 static MAX_PADDR: AtomicUsize = AtomicUsize::new(0);
 
+/// A snapshot of the metadata of a page, which is returned by [`page_info`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PageInfo {
+    /// The usage of the page. It is [`PageUsage::Unused`] if the page is free.
+    pub usage: PageUsage,
+    /// The number of handles to the page.
+    pub ref_count: u32,
+}
+
+/// Gets the usage and the reference count of the page at `paddr`.
+///
+/// The page is not required to be in use, so this is suitable for inspecting the
+/// pages of the others, e.g., for debugging or accounting. The metadata can be
+/// changed concurrently, so the result is only a snapshot. To keep a page alive,
+/// hold a handle to it, e.g., a [`Frame`], instead.
+///
+/// Returns `None` if `paddr` is beyond the physical memory.
+pub fn page_info(paddr: Paddr) -> Option<PageInfo> {
+    if paddr >= MAX_PADDR.load(Ordering::Relaxed) {
+        return None;
+    }
+    let ptr = mapping::page_to_meta::<PagingConsts>(paddr.align_down(PAGE_SIZE)) as *const MetaSlot;
+
+    // SAFETY: The metadata slots of all the physical pages are initialized and never freed.
+    let (usage_raw, ref_count) = unsafe {
+        (
+            (*ptr).usage.load(Ordering::Relaxed),
+            (*ptr).ref_count.load(Ordering::Relaxed),
+        )
+    };
+
+    Some(PageInfo {
+        usage: num::FromPrimitive::from_u8(usage_raw).unwrap(),
+        ref_count,
+    })
+}
+
 /// A page with a statically-known usage, whose metadata is represented by `M`.
 #[derive(Debug)]
 pub struct Page<M: PageMeta> {
@@ -156,6 +194,13 @@ impl<M: PageMeta> Page<M> {
         unsafe { &*(self.ptr as *const M) }
     }
 
+    /// Get the number of handles to this page.
+    ///
+    /// The count may be changed concurrently by other handles, so it is only a snapshot.
+    pub fn reference_count(&self) -> u32 {
+        self.ref_count().load(Ordering::Relaxed)
+    }
+
     fn ref_count(&self) -> &AtomicU32 {
         unsafe { &(*self.ptr).ref_count }
     }
@@ -259,6 +304,13 @@ impl DynPage {
         num::FromPrimitive::from_u8(usage_raw).unwrap()
     }
 
+    /// Get the number of handles to this page.
+    ///
+    /// This is the same as [`Page::reference_count`].
+    pub fn reference_count(&self) -> u32 {
+        self.ref_count().load(Ordering::Relaxed)
+    }
+
     fn ref_count(&self) -> &AtomicU32 {
         unsafe { &(*self.ptr).ref_count }
     }
@@ -337,3 +389,49 @@ impl Drop for DynPage {
         }
     }
 }
+
+#[cfg(ktest)]
+mod test {
+    use super::*;
+    use crate::{mm::FrameAllocOptions, prelude::*, task::scope};
+
+    #[ktest]
+    fn concurrent_ref_count() {
+        const NR_TASKS: usize = 4;
+        const NR_ROUNDS: usize = 1000;
+
+        let frame = FrameAllocOptions::new(1).alloc_single().unwrap();
+        let paddr = frame.start_paddr();
+        assert_eq!(
+            page_info(paddr),
+            Some(PageInfo {
+                usage: PageUsage::Frame,
+                ref_count: 1
+            })
+        );
+
+        scope(|s| {
+            for _ in 0..NR_TASKS {
+                let frame = &frame;
+                s.spawn(move || {
+                    for _ in 0..NR_ROUNDS {
+                        let cloned = frame.clone();
+                        assert!(cloned.reference_count() >= 2);
+                        drop(cloned);
+                    }
+                });
+            }
+        });
+        assert_eq!(frame.reference_count(), 1);
+
+        // Dropping the last handle frees the page.
+        drop(frame);
+        assert_eq!(
+            page_info(paddr),
+            Some(PageInfo {
+                usage: PageUsage::Unused,
+                ref_count: 0
+            })
+        );
+    }
+}
@@ -33,6 +33,40 @@ impl PageProperty {
             priv_flags: PrivilegedPageFlags::empty(),
         }
     }
+
+    /// Returns the type of the page in the resident set size (RSS) accounting.
+    ///
+    /// Returns `None` if the page is not counted in the RSS.
+    pub fn rss_type(&self) -> Option<RssType> {
+        if self.priv_flags.contains(PrivilegedPageFlags::NO_RSS) {
+            None
+        } else if self.priv_flags.contains(PrivilegedPageFlags::FILE_BACKED) {
+            Some(RssType::File)
+        } else {
+            Some(RssType::Anon)
+        }
+    }
+
+    /// Sets the type of the page in the resident set size (RSS) accounting.
+    ///
+    /// A page that is shared by everyone, e.g., the zero page, should not be
+    /// counted in the RSS, for which the type is `None`. The type of a page
+    /// property is [`RssType::Anon`] by default.
+    pub fn set_rss_type(&mut self, rss_type: Option<RssType>) {
+        self.priv_flags
+            .set(PrivilegedPageFlags::NO_RSS, rss_type.is_none());
+        self.priv_flags
+            .set(PrivilegedPageFlags::FILE_BACKED, rss_type == Some(RssType::File));
+    }
+}
+
+/// The type of a page in the resident set size (RSS) accounting.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RssType {
+    /// An anonymous page, which is not backed by a file.
+    Anon,
+    /// A page backed by a file, e.g., a page of the page cache.
+    File,
 }
 
 // TODO: Make it more abstract when supporting other architectures.
@@ -125,6 +159,10 @@ bitflags! {
         const USER      = 0b00000001;
         /// Global page that won't be evicted from TLB with normal TLB flush.
         const GLOBAL    = 0b00000010;
+        /// (Software only) If the page is backed by a file. See [`RssType`].
+        const FILE_BACKED = 0b00000100;
+        /// (Software only) If the page is not counted in the RSS. See [`RssType`].
+        const NO_RSS    = 0b00001000;
 
         /// (TEE only) If the page is shared with the host.
         /// Otherwise the page is ensured confidential and not visible outside the guest.
@@ -239,6 +239,29 @@ where
         }
     }
 
+    /// Moves to the next present mapping and returns it, skipping the absent slots.
+    ///
+    /// The returned virtual address range covers the whole leaf PTE, which may be a huge page
+    /// mapped at an intermediate level. So the range may start before the current virtual
+    /// address or end after the end of the cursor's range. The physical address is the start
+    /// of the mapped page.
+    ///
+    /// Returns `None` if there are no more present mappings in the cursor's range.
+    pub fn next_mapped(&mut self) -> Option<(Range<Vaddr>, Paddr, PageProperty)> {
+        loop {
+            let item = self.query().ok()?;
+            self.move_forward();
+
+            let (va, pa, len, prop) = match item {
+                PageTableItem::NotMapped { .. } => continue,
+                PageTableItem::Mapped { va, page, prop } => (va, page.paddr(), page.size(), prop),
+                PageTableItem::MappedUntracked { va, pa, len, prop } => (va, pa, len, prop),
+            };
+            let start = va.align_down(len);
+            return Some((start..start + len, pa, prop));
+        }
+    }
+
     /// Traverses forward in the current level to the next PTE.
     ///
     /// If reached the end of a page table node, it leads itself up to the next page of the parent
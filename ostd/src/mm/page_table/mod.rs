@@ -20,6 +20,13 @@ mod test;
 
 pub(in crate::mm) mod boot_pt;
 
+/// The number of times that a page table is loaded by [`PageTable::activate`].
+///
+/// It is used to test that no page table is reloaded redundantly.
+#[cfg(ktest)]
+pub(crate) static NR_ACTIVATIONS: core::sync::atomic::AtomicUsize =
+    core::sync::atomic::AtomicUsize::new(0);
+
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub enum PageTableError {
     /// The provided virtual address range is invalid.
@@ -123,6 +130,16 @@ impl PageTable<UserMode> {
 }
 
 impl PageTable<KernelMode> {
+    /// Activates the kernel page table.
+    ///
+    /// It is used to stop using a user page table that is being torn down.
+    pub(in crate::mm) fn activate(&self) {
+        // SAFETY: The kernel page table has all the kernel mappings.
+        unsafe {
+            self.root.activate();
+        }
+    }
+
     /// Create a new user page table.
     ///
     /// This should be the only way to create the first user page table, that is
@@ -133,6 +133,8 @@ where
         }
 
         activate_page_table(self.raw, CachePolicy::Writeback);
+        #[cfg(ktest)]
+        super::NR_ACTIVATIONS.fetch_add(1, core::sync::atomic::Ordering::Relaxed);
 
         // Increment the reference count of the current page table.
         self.inc_ref_count();
@@ -285,3 +285,44 @@ fn test_untracked_large_protect_query() {
     // Since untracked mappings cannot be dropped, we just leak it here.
     let _ = ManuallyDrop::new(pt);
 }
+
+#[ktest]
+fn test_next_mapped_huge_pages() {
+    let pt = PageTable::<KernelMode, PageTableEntry, VeryHugePagingConsts>::empty();
+    const UNTRACKED_OFFSET: usize = crate::mm::kspace::LINEAR_MAPPING_BASE_VADDR;
+    const HUGE_PAGE_SIZE: usize = PAGE_SIZE * 512;
+
+    // Two 2M huge pages followed by two base pages.
+    let from = UNTRACKED_OFFSET + HUGE_PAGE_SIZE * 4
+        ..UNTRACKED_OFFSET + HUGE_PAGE_SIZE * 6 + PAGE_SIZE * 2;
+    let to = HUGE_PAGE_SIZE * 2..HUGE_PAGE_SIZE * 4 + PAGE_SIZE * 2;
+    let prop = PageProperty::new(PageFlags::RW, CachePolicy::Writeback);
+    unsafe { pt.map(&from, &to, prop).unwrap() };
+
+    // An unmapped range yields nothing.
+    let unmapped = from.end..from.end + HUGE_PAGE_SIZE;
+    assert!(pt.cursor(&unmapped).unwrap().next_mapped().is_none());
+
+    // The walk starts in the middle of the first huge page, which is still reported as a whole.
+    let mut cursor = pt.cursor(&(from.start + PAGE_SIZE * 3..from.end)).unwrap();
+    let mut mappings = Vec::new();
+    while let Some(mapping) = cursor.next_mapped() {
+        mappings.push(mapping);
+    }
+    let huge = |i: usize| {
+        let va = from.start + HUGE_PAGE_SIZE * i;
+        (va..va + HUGE_PAGE_SIZE, to.start + HUGE_PAGE_SIZE * i, prop)
+    };
+    let base = |i: usize| {
+        let va = from.start + HUGE_PAGE_SIZE * 2 + PAGE_SIZE * i;
+        (
+            va..va + PAGE_SIZE,
+            to.start + HUGE_PAGE_SIZE * 2 + PAGE_SIZE * i,
+            prop,
+        )
+    };
+    assert_eq!(mappings, [huge(0), huge(1), base(0), base(1)]);
+
+    // Since untracked mappings cannot be dropped, we just leak it here.
+    let _ = ManuallyDrop::new(pt);
+}
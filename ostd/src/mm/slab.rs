@@ -0,0 +1,317 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Slab caches for fixed-size kernel objects.
+//!
+//! A [`SlabCache<T>`] carves page frames (slabs) into slots that fit exactly
+//! one `T` each. Freed slots are kept in a free list and recycled by later
+//! allocations of the same cache, without going through the general-purpose
+//! heap allocator.
+
+use core::{
+    alloc::Layout,
+    fmt::Debug,
+    marker::PhantomData,
+    mem::ManuallyDrop,
+    ops::{Deref, DerefMut},
+    ptr::NonNull,
+};
+
+use super::{FrameAllocOptions, Segment, PAGE_SIZE};
+use crate::{prelude::*, sync::SpinLock, Error};
+
+/// A cache of fixed-size slots for objects of type `T`.
+///
+/// An object allocated with [`SlabCache::alloc`] is returned as a [`SlabBox`],
+/// which behaves like a `Box<T>`. When the box is dropped, the destructor of
+/// the object is run and the slot is recycled by the cache.
+///
+/// The cache never constructs objects on its own. A slot holds no valid object
+/// between two uses, so each allocation moves in a freshly constructed value and
+/// each free runs the destructor exactly once.
+///
+/// The memory of the slabs is returned to the frame allocator only when the
+/// cache is dropped.
+pub struct SlabCache<T> {
+    inner: SpinLock<SlabCacheInner>,
+    _marker: PhantomData<T>,
+}
+
+struct SlabCacheInner {
+    /// The slabs allocated so far, which are kept alive until the cache is dropped.
+    slabs: Vec<Segment>,
+    /// The head of the intrusive list of free slots.
+    free_head: Option<NonNull<FreeSlot>>,
+    nr_free: usize,
+}
+
+/// The link stored in a free slot.
+struct FreeSlot {
+    next: Option<NonNull<FreeSlot>>,
+}
+
+// SAFETY: The raw pointers only point to the slabs owned by the cache, which
+// are accessed with the lock held.
+unsafe impl Send for SlabCacheInner {}
+
+// SAFETY: The objects are only accessed through the `SlabBox`es, so the cache
+// can be shared between threads as long as the objects can be sent between them.
+unsafe impl<T: Send> Sync for SlabCache<T> {}
+unsafe impl<T: Send> Send for SlabCache<T> {}
+
+impl<T> SlabCache<T> {
+    /// The layout of a slot, which can hold either a `T` or a free list link.
+    const SLOT_LAYOUT: Layout = {
+        let size = if size_of::<T>() > size_of::<FreeSlot>() {
+            size_of::<T>()
+        } else {
+            size_of::<FreeSlot>()
+        };
+        let align = if align_of::<T>() > align_of::<FreeSlot>() {
+            align_of::<T>()
+        } else {
+            align_of::<FreeSlot>()
+        };
+        let size = size.next_multiple_of(align);
+        match Layout::from_size_align(size, align) {
+            Ok(layout) => layout,
+            Err(_) => panic!("invalid slot layout"),
+        }
+    };
+
+    /// The number of bytes of a slab, which holds at least one slot.
+    const SLAB_SIZE: usize = Self::SLOT_LAYOUT.size().next_multiple_of(PAGE_SIZE);
+
+    /// Creates an empty cache.
+    ///
+    /// No memory is allocated until the first object is allocated.
+    ///
+    /// # Panics
+    ///
+    /// This method panics if the alignment of `T` is larger than the page size.
+    pub const fn new() -> Self {
+        assert!(Self::SLOT_LAYOUT.align() <= PAGE_SIZE);
+
+        Self {
+            inner: SpinLock::new(SlabCacheInner {
+                slabs: Vec::new(),
+                free_head: None,
+                nr_free: 0,
+            }),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Allocates a slot and moves `value` into it.
+    ///
+    /// A recycled slot is preferred. A new slab is allocated only if there is
+    /// no free slot.
+    pub fn alloc(&self, value: T) -> Result<SlabBox<'_, T>> {
+        let slot = self.alloc_slot()?.cast::<T>();
+        // SAFETY: The slot is free, so it is valid for writes of `T` and not
+        // accessed by others.
+        unsafe { slot.as_ptr().write(value) };
+        Ok(SlabBox {
+            ptr: slot,
+            cache: self,
+        })
+    }
+
+    /// Returns the number of slabs allocated by this cache.
+    pub fn nr_slabs(&self) -> usize {
+        self.inner.lock_irq_disabled().slabs.len()
+    }
+
+    /// Returns the number of free slots in this cache.
+    pub fn nr_free(&self) -> usize {
+        self.inner.lock_irq_disabled().nr_free
+    }
+
+    fn alloc_slot(&self) -> Result<NonNull<FreeSlot>> {
+        let mut inner = self.inner.lock_irq_disabled();
+
+        if inner.free_head.is_none() {
+            let slab = FrameAllocOptions::new(Self::SLAB_SIZE / PAGE_SIZE)
+                .uninit(true)
+                .alloc_contiguous()?;
+            let base = slab.as_mut_ptr();
+            let nr_slots = Self::SLAB_SIZE / Self::SLOT_LAYOUT.size();
+            // Link the slots in the ascending order of their addresses.
+            for i in (0..nr_slots).rev() {
+                // SAFETY: The slot is within the slab, which is not used by others.
+                let slot = unsafe { base.add(i * Self::SLOT_LAYOUT.size()) }.cast::<FreeSlot>();
+                // SAFETY: The slot is aligned and valid for writes as shown above.
+                unsafe {
+                    slot.write(FreeSlot {
+                        next: inner.free_head,
+                    })
+                };
+                inner.free_head = NonNull::new(slot);
+            }
+            inner.nr_free += nr_slots;
+            inner.slabs.push(slab);
+        }
+
+        let slot = inner.free_head.ok_or(Error::NoMemory)?;
+        // SAFETY: The slot is in the free list, so it holds a valid link.
+        inner.free_head = unsafe { slot.as_ref() }.next;
+        inner.nr_free -= 1;
+        Ok(slot)
+    }
+
+    /// Puts a slot back to the free list.
+    ///
+    /// # Safety
+    ///
+    /// The slot must be allocated from this cache and hold no valid object.
+    unsafe fn free_slot(&self, slot: NonNull<T>) {
+        let mut inner = self.inner.lock_irq_disabled();
+        let slot = slot.cast::<FreeSlot>();
+        // SAFETY: The slot is owned by the caller and valid for writes.
+        unsafe {
+            slot.as_ptr().write(FreeSlot {
+                next: inner.free_head,
+            })
+        };
+        inner.free_head = Some(slot);
+        inner.nr_free += 1;
+    }
+}
+
+impl<T> Default for SlabCache<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Debug for SlabCache<T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let inner = self.inner.lock_irq_disabled();
+        f.debug_struct("SlabCache")
+            .field("slot_layout", &Self::SLOT_LAYOUT)
+            .field("nr_slabs", &inner.slabs.len())
+            .field("nr_free", &inner.nr_free)
+            .finish()
+    }
+}
+
+/// An object allocated from a [`SlabCache`].
+///
+/// The destructor of the object is run and the slot is recycled when the box
+/// is dropped.
+pub struct SlabBox<'a, T> {
+    ptr: NonNull<T>,
+    cache: &'a SlabCache<T>,
+}
+
+// SAFETY: The box owns the object exclusively, just like a `Box<T>`.
+unsafe impl<T: Send> Send for SlabBox<'_, T> {}
+unsafe impl<T: Sync> Sync for SlabBox<'_, T> {}
+
+impl<T> SlabBox<'_, T> {
+    /// Moves the object out of the box and recycles the slot.
+    pub fn into_inner(this: Self) -> T {
+        let this = ManuallyDrop::new(this);
+        // SAFETY: The slot holds a valid object, which is moved out exactly once
+        // since the box is not dropped.
+        let value = unsafe { this.ptr.as_ptr().read() };
+        // SAFETY: The slot is allocated from the cache and the object has been moved out.
+        unsafe { this.cache.free_slot(this.ptr) };
+        value
+    }
+
+    /// Returns the address of the object.
+    pub fn as_ptr(this: &Self) -> *const T {
+        this.ptr.as_ptr()
+    }
+}
+
+impl<T> Deref for SlabBox<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        // SAFETY: The slot holds a valid object owned by the box.
+        unsafe { self.ptr.as_ref() }
+    }
+}
+
+impl<T> DerefMut for SlabBox<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        // SAFETY: The slot holds a valid object owned by the box exclusively.
+        unsafe { self.ptr.as_mut() }
+    }
+}
+
+impl<T: Debug> Debug for SlabBox<'_, T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        Debug::fmt(&**self, f)
+    }
+}
+
+impl<T> Drop for SlabBox<'_, T> {
+    fn drop(&mut self) {
+        // SAFETY: The slot holds a valid object, which is dropped exactly once.
+        unsafe { self.ptr.as_ptr().drop_in_place() };
+        // SAFETY: The slot is allocated from the cache and the object has been dropped.
+        unsafe { self.cache.free_slot(self.ptr) };
+    }
+}
+
+#[cfg(ktest)]
+mod test {
+    use alloc::collections::BTreeSet;
+    use core::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+    use crate::prelude::*;
+
+    #[ktest]
+    fn recycle_slots() {
+        let cache = SlabCache::<[u64; 4]>::new();
+
+        let boxes: Vec<_> = (0..64).map(|i| cache.alloc([i; 4]).unwrap()).collect();
+        for (i, slab_box) in boxes.iter().enumerate() {
+            assert_eq!(**slab_box, [i as u64; 4]);
+        }
+        let addrs: BTreeSet<_> = boxes.iter().map(SlabBox::as_ptr).collect();
+        assert_eq!(addrs.len(), 64);
+        let nr_slabs = cache.nr_slabs();
+        drop(boxes);
+
+        // The freed slots are reused without allocating new slabs.
+        let boxes: Vec<_> = (0..64).map(|i| cache.alloc([i; 4]).unwrap()).collect();
+        assert!(boxes
+            .iter()
+            .all(|slab_box| addrs.contains(&SlabBox::as_ptr(slab_box))));
+        assert_eq!(cache.nr_slabs(), nr_slabs);
+    }
+
+    #[ktest]
+    fn destructor_runs_once() {
+        static NR_DROPS: AtomicUsize = AtomicUsize::new(0);
+
+        struct Object(u32);
+
+        impl Drop for Object {
+            fn drop(&mut self) {
+                NR_DROPS.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+
+        let cache = SlabCache::new();
+        let first = cache.alloc(Object(1)).unwrap();
+        let addr = SlabBox::as_ptr(&first);
+        drop(first);
+        assert_eq!(NR_DROPS.load(Ordering::Relaxed), 1);
+
+        // The recycled slot holds the new object, not the old one.
+        let second = cache.alloc(Object(2)).unwrap();
+        assert_eq!(SlabBox::as_ptr(&second), addr);
+        assert_eq!(second.0, 2);
+
+        // Moving the object out does not run the destructor.
+        let object = SlabBox::into_inner(second);
+        assert_eq!(NR_DROPS.load(Ordering::Relaxed), 1);
+        drop(object);
+        assert_eq!(NR_DROPS.load(Ordering::Relaxed), 2);
+    }
+}
@@ -0,0 +1,105 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! TLB shootdowns of the kernel address space.
+//!
+//! The kernel mappings are shared by all CPUs, so each of them may cache a
+//! translation of a kernel page. Before a kernel page is unmapped and its
+//! frame is reused, the stale translations must be flushed on every CPU.
+
+use core::{
+    ops::Range,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use spin::Once;
+
+use super::Vaddr;
+use crate::{
+    arch::{
+        irq::send_ipi,
+        mm::{tlb_flush_addr_range, tlb_flush_all_including_global},
+    },
+    cpu::{num_cpus, this_cpu},
+    sync::AtomicBits,
+    task::disable_preempt,
+    trap::IrqLine,
+};
+
+/// The IRQ line of the IPIs that ask a CPU to flush its TLB.
+static SHOOTDOWN_IRQ: Once<IrqLine> = Once::new();
+
+/// The CPUs that have not flushed their TLBs for the ongoing shootdown.
+static PENDING_CPUS: Once<AtomicBits> = Once::new();
+
+/// Whether a shootdown is ongoing. Only one shootdown is done at a time.
+static IS_SHOOTING_DOWN: AtomicBool = AtomicBool::new(false);
+
+/// Initializes the TLB shootdowns.
+///
+/// It must be called after all the APs are started. Before that, only the
+/// local TLB is flushed.
+pub(crate) fn init() {
+    PENDING_CPUS.call_once(|| AtomicBits::new_zeroes(num_cpus() as usize));
+    SHOOTDOWN_IRQ.call_once(|| {
+        let mut irq = IrqLine::alloc().unwrap();
+        irq.on_active(|_| flush_if_pending());
+        irq
+    });
+}
+
+/// Flushes the TLB entries of the kernel pages in `range` on all CPUs.
+///
+/// This function returns after every CPU has flushed its TLB. So the caller
+/// must not hold any lock that other CPUs may spin on with the local IRQs
+/// disabled, since those CPUs cannot respond to the IPIs.
+pub(crate) fn tlb_shootdown_kernel(range: &Range<Vaddr>) {
+    let _guard = disable_preempt();
+    tlb_flush_addr_range(range);
+
+    let (Some(irq), Some(pending)) = (SHOOTDOWN_IRQ.get(), PENDING_CPUS.get()) else {
+        return;
+    };
+    let nr_cpus = num_cpus();
+    if nr_cpus == 1 {
+        return;
+    }
+
+    // The CPU that is waiting for its turn may be asked to flush by the
+    // ongoing shootdown, possibly with its local IRQs disabled.
+    while IS_SHOOTING_DOWN
+        .compare_exchange_weak(false, true, Ordering::Acquire, Ordering::Relaxed)
+        .is_err()
+    {
+        flush_if_pending();
+        core::hint::spin_loop();
+    }
+
+    // The remote CPUs flush all the entries, since the range is not passed to
+    // them. The kernel pages are rarely unmapped, so this is not costly.
+    let this_cpu = this_cpu();
+    let remote_cpus = || (0..nr_cpus).filter(move |cpu| *cpu != this_cpu);
+    for cpu in remote_cpus() {
+        pending.set(cpu as usize, true);
+    }
+    for cpu in remote_cpus() {
+        send_ipi(cpu, irq.num());
+    }
+    while !pending.is_empty() {
+        core::hint::spin_loop();
+    }
+
+    IS_SHOOTING_DOWN.store(false, Ordering::Release);
+}
+
+/// Flushes the local TLB if the ongoing shootdown asks the current CPU to.
+fn flush_if_pending() {
+    let Some(pending) = PENDING_CPUS.get() else {
+        return;
+    };
+
+    let cpu = this_cpu() as usize;
+    if pending.get(cpu) {
+        tlb_flush_all_including_global();
+        pending.set(cpu, false);
+    }
+}
@@ -17,7 +17,7 @@ use super::{
     io::UserSpace,
     kspace::KERNEL_PAGE_TABLE,
     page_table::{PageTable, UserMode},
-    PageFlags, PageProperty, VmReader, VmWriter,
+    PageFlags, PageProperty, RssType, VmReader, VmWriter, PAGE_SIZE,
 };
 use crate::{
     arch::mm::{
@@ -30,6 +30,8 @@ use crate::{
         Frame, MAX_USERSPACE_VADDR,
     },
     prelude::*,
+    sync::PerCpuCounter,
+    trap::disable_local,
     Error,
 };
 
@@ -47,13 +49,46 @@ use crate::{
 ///
 /// A `VmSpace` can also attach a page fault handler, which will be invoked to
 /// handle page faults generated from user space.
+///
+/// The pages mapped into a `VmSpace` are accounted in its resident set size
+/// (RSS). See [`VmSpace::rss`] for details.
 #[allow(clippy::type_complexity)]
 #[derive(Debug)]
 pub struct VmSpace {
     pt: PageTable<UserMode>,
     page_fault_handler: Once<fn(&VmSpace, &CpuExceptionInfo) -> core::result::Result<(), ()>>,
+    rss: RssCounters,
+}
+
+impl Drop for VmSpace {
+    fn drop(&mut self) {
+        // In the lazy TLB mode, the page table may be still active after the user tasks
+        // using it have been switched out (see `switch_to_task`). Switch to the kernel page
+        // table in that case, so that the page table is no longer in use and the user
+        // memory can be released now.
+        //
+        // The page table may also be lazily active on other CPUs. It is kept alive by the
+        // reference held by each CPU, which is released when the CPU switches to another
+        // user page table.
+        let _irq_guard = disable_local();
+        // SAFETY: The physical address is only used for comparison.
+        if current_page_table_paddr() == unsafe { self.pt.root_paddr() } {
+            KERNEL_PAGE_TABLE.get().unwrap().activate();
+        }
+    }
 }
 
+/// The number of pages above which [`CursorMut::unmap`] flushes the entire TLB, instead of
+/// flushing the TLB entries of the unmapped pages one by one.
+const TLB_FLUSH_ALL_THRESHOLD: usize = 32;
+
+/// The number of TLB flushes issued by [`CursorMut::unmap`].
+///
+/// It is used to test that the TLB flushes are batched.
+#[cfg(ktest)]
+static NR_UNMAP_TLB_FLUSHES: core::sync::atomic::AtomicUsize =
+    core::sync::atomic::AtomicUsize::new(0);
+
 // Notes on TLB flushing:
 //
 // We currently assume that:
@@ -68,6 +103,7 @@ impl VmSpace {
         Self {
             pt: KERNEL_PAGE_TABLE.get().unwrap().create_user_page_table(),
             page_fault_handler: Once::new(),
+            rss: RssCounters::new(),
         }
     }
 
@@ -94,7 +130,23 @@ impl VmSpace {
     /// overlapping range is alive. The modification to the mapping by the
     /// cursor may also block or be overriden the mapping of another cursor.
     pub fn cursor_mut(&self, va: &Range<Vaddr>) -> Result<CursorMut<'_>> {
-        Ok(self.pt.cursor_mut(va).map(CursorMut)?)
+        let pt_cursor = self.pt.cursor_mut(va)?;
+        Ok(CursorMut {
+            pt_cursor,
+            rss: &self.rss,
+        })
+    }
+
+    /// Walks the present mappings in the virtual address range.
+    ///
+    /// The returned iterator yields the virtual address range, the physical
+    /// address and the property of each present mapping, in the ascending
+    /// order of the virtual addresses. See [`WalkRange`] for details.
+    ///
+    /// Like [`Self::cursor`], the iterator behaves like a lock guard of the
+    /// range, so be sure to drop it as soon as possible.
+    pub fn walk_range(&self, va: &Range<Vaddr>) -> Result<WalkRange<'_>> {
+        Ok(self.pt.cursor(va).map(WalkRange)?)
     }
 
     /// Activates the page table.
@@ -109,7 +161,8 @@ impl VmSpace {
             // SAFETY: It is safe to un-map memory in the userspace.
             let result = unsafe { cursor.take_next(MAX_USERSPACE_VADDR - cursor.virt_addr()) };
             match result {
-                PageTableItem::Mapped { page, .. } => {
+                PageTableItem::Mapped { page, prop, .. } => {
+                    self.rss.sub(prop.rss_type(), page.size() / PAGE_SIZE);
                     drop(page);
                 }
                 PageTableItem::NotMapped { .. } => {
@@ -182,12 +235,32 @@ impl VmSpace {
             new_handler
         };
 
+        // The child maps the same pages as the parent, so it has the same RSS.
+        let rss = RssCounters::new();
+        rss.add_all(self.rss());
+
         Self {
             pt: self.pt.clone_with(cursor),
             page_fault_handler,
+            rss,
         }
     }
 
+    /// Returns the resident set size (RSS).
+    ///
+    /// The RSS counts the pages mapped into this `VmSpace`. A page is counted
+    /// once for each of its mappings, like the RSS in Linux. So a page that is
+    /// shared among `VmSpace`s, e.g., after [`Self::fork_copy_on_write`], is
+    /// counted in all of them. The proportional set size (PSS), which divides
+    /// the shared pages among their mappers, is not tracked.
+    ///
+    /// The pages are classified by the [`RssType`] of the page property with
+    /// which they are mapped. The pages mapped without an RSS type are not
+    /// counted.
+    pub fn rss(&self) -> Rss {
+        self.rss.get()
+    }
+
     /// Creates a reader to read data from the user space of the current task.
     ///
     /// Returns `Err` if this `VmSpace` is not belonged to the user space of the current task
@@ -275,11 +348,33 @@ impl Cursor<'_> {
     }
 }
 
+/// The iterator over the present mappings in a VM space range.
+///
+/// It is created by [`VmSpace::walk_range`]. Each item is a tuple of the
+/// virtual address range, the physical address and the property of a leaf
+/// page table entry. The unmapped slots are skipped.
+///
+/// A leaf may be a huge page mapped at an intermediate level. Its virtual
+/// address range is reported as a whole, even if it only partially overlaps
+/// the range being walked.
+pub struct WalkRange<'a>(page_table::Cursor<'a, UserMode, PageTableEntry, PagingConsts>);
+
+impl Iterator for WalkRange<'_> {
+    type Item = (Range<Vaddr>, Paddr, PageProperty);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next_mapped()
+    }
+}
+
 /// The cursor for modifying the mappings in VM space.
 ///
 /// It exclusively owns a sub-tree of the page table, preventing others from
 /// reading or modifying the same sub-tree.
-pub struct CursorMut<'a>(page_table::CursorMut<'a, UserMode, PageTableEntry, PagingConsts>);
+pub struct CursorMut<'a> {
+    pt_cursor: page_table::CursorMut<'a, UserMode, PageTableEntry, PagingConsts>,
+    rss: &'a RssCounters,
+}
 
 impl CursorMut<'_> {
     /// Query about the current slot.
@@ -288,19 +383,19 @@ impl CursorMut<'_> {
     ///
     /// This function won't bring the cursor to the next slot.
     pub fn query(&mut self) -> Result<VmItem> {
-        Ok(self.0.query().map(|item| item.try_into().unwrap())?)
+        Ok(self.pt_cursor.query().map(|item| item.try_into().unwrap())?)
     }
 
     /// Jump to the virtual address.
     ///
     /// This is the same as [`Cursor::jump`].
     pub fn jump(&mut self, va: Vaddr) {
-        self.0.jump(va);
+        self.pt_cursor.jump(va);
     }
 
     /// Get the virtual address of the current slot.
     pub fn virt_addr(&self) -> Vaddr {
-        self.0.virt_addr()
+        self.pt_cursor.virt_addr()
     }
 
     /// Map a frame into the current slot.
@@ -310,9 +405,20 @@ impl CursorMut<'_> {
         let start_va = self.virt_addr();
         let end_va = start_va + frame.size();
 
+        // The frame may replace a mapped page, which is no longer resident.
+        if let Ok(PageTableItem::Mapped {
+            page,
+            prop: old_prop,
+            ..
+        }) = self.pt_cursor.query()
+        {
+            self.rss.sub(old_prop.rss_type(), page.size() / PAGE_SIZE);
+        }
+        self.rss.add(prop.rss_type(), frame.size() / PAGE_SIZE);
+
         // SAFETY: It is safe to map untyped memory into the userspace.
         unsafe {
-            self.0.map(frame.into(), prop);
+            self.pt_cursor.map(frame.into(), prop);
         }
 
         tlb_flush_addr_range(&(start_va..end_va));
@@ -333,15 +439,24 @@ impl CursorMut<'_> {
         assert!(len % super::PAGE_SIZE == 0);
         let end_va = self.virt_addr() + len;
 
+        // The TLB is flushed once after all the pages are unmapped. The pages must be kept
+        // alive until then, since the stale TLB entries may still refer to them.
+        let mut unmapped_range: Option<Range<Vaddr>> = None;
+        let mut unmapped_pages = Vec::new();
+
         loop {
             // SAFETY: It is safe to un-map memory in the userspace.
-            let result = unsafe { self.0.take_next(end_va - self.virt_addr()) };
+            let result = unsafe { self.pt_cursor.take_next(end_va - self.virt_addr()) };
             match result {
-                PageTableItem::Mapped { va, page, .. } => {
-                    // TODO: Ask other processors to flush the TLB before we
-                    // release the page back to the allocator.
-                    tlb_flush_addr(va);
-                    drop(page);
+                PageTableItem::Mapped { va, page, prop } => {
+                    self.rss.sub(prop.rss_type(), page.size() / PAGE_SIZE);
+
+                    let page_end = va + page.size();
+                    unmapped_range = Some(match unmapped_range {
+                        Some(range) => range.start..page_end,
+                        None => va..page_end,
+                    });
+                    unmapped_pages.push(page);
                 }
                 PageTableItem::NotMapped { .. } => {
                     break;
@@ -351,6 +466,21 @@ impl CursorMut<'_> {
                 }
             }
         }
+
+        if let Some(range) = unmapped_range {
+            // TODO: Ask other processors to flush the TLB before we
+            // release the pages back to the allocator.
+            if range.len() / super::PAGE_SIZE > TLB_FLUSH_ALL_THRESHOLD {
+                tlb_flush_all_excluding_global();
+            } else {
+                tlb_flush_addr_range(&range);
+            }
+
+            #[cfg(ktest)]
+            NR_UNMAP_TLB_FLUSHES.fetch_add(1, core::sync::atomic::Ordering::Relaxed);
+        }
+
+        drop(unmapped_pages);
     }
 
     /// Change the mapping property starting from the current slot.
@@ -365,15 +495,90 @@ impl CursorMut<'_> {
     /// This method will panic if `len` is not page-aligned.
     pub fn protect(&mut self, len: usize, mut op: impl FnMut(&mut PageProperty)) {
         assert!(len % super::PAGE_SIZE == 0);
-        let end = self.0.virt_addr() + len;
+        let end = self.pt_cursor.virt_addr() + len;
 
         // SAFETY: It is safe to protect memory in the userspace.
-        while let Some(range) = unsafe { self.0.protect_next(end - self.0.virt_addr(), &mut op) } {
+        while let Some(range) =
+            unsafe { self.pt_cursor.protect_next(end - self.pt_cursor.virt_addr(), &mut op) }
+        {
             tlb_flush_addr(range.start);
         }
     }
 }
 
+/// The resident set size (RSS) of a [`VmSpace`], in the number of base pages.
+///
+/// See [`VmSpace::rss`] for how the pages are counted.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Rss {
+    /// The number of the resident anonymous pages.
+    pub anon: usize,
+    /// The number of the resident file-backed pages.
+    pub file: usize,
+}
+
+impl Rss {
+    /// Returns the total number of the resident pages.
+    pub fn total(&self) -> usize {
+        self.anon + self.file
+    }
+}
+
+/// The RSS counters of a [`VmSpace`].
+///
+/// The pages are mapped and unmapped with cursors of disjoint ranges, which may
+/// run on many CPUs at the same time. So the counters are per-CPU.
+struct RssCounters {
+    anon: PerCpuCounter,
+    file: PerCpuCounter,
+}
+
+impl RssCounters {
+    fn new() -> Self {
+        Self {
+            anon: PerCpuCounter::new(),
+            file: PerCpuCounter::new(),
+        }
+    }
+
+    fn add(&self, rss_type: Option<RssType>, nr_pages: usize) {
+        if let Some(rss_type) = rss_type {
+            self.counter(rss_type).add(nr_pages);
+        }
+    }
+
+    fn sub(&self, rss_type: Option<RssType>, nr_pages: usize) {
+        if let Some(rss_type) = rss_type {
+            self.counter(rss_type).sub(nr_pages);
+        }
+    }
+
+    fn add_all(&self, rss: Rss) {
+        self.anon.add(rss.anon);
+        self.file.add(rss.file);
+    }
+
+    fn get(&self) -> Rss {
+        Rss {
+            anon: self.anon.sum(),
+            file: self.file.sum(),
+        }
+    }
+
+    fn counter(&self, rss_type: RssType) -> &PerCpuCounter {
+        match rss_type {
+            RssType::Anon => &self.anon,
+            RssType::File => &self.file,
+        }
+    }
+}
+
+impl core::fmt::Debug for RssCounters {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{:?}", self.get())
+    }
+}
+
 /// The result of a query over the VM space.
 #[derive(Debug)]
 pub enum VmItem {
@@ -414,3 +619,169 @@ impl TryFrom<PageTableItem> for VmItem {
         }
     }
 }
+
+#[cfg(ktest)]
+mod test {
+    use core::sync::atomic::{AtomicBool, Ordering};
+
+    use super::*;
+    use crate::{
+        cpu::{num_cpus, UserContext},
+        mm::{page_table::NR_ACTIVATIONS, CachePolicy, FrameAllocOptions, PAGE_SIZE},
+        task::{Task, TaskOptions},
+        user::UserSpace,
+    };
+
+    #[ktest]
+    fn walk_range() {
+        let vm_space = VmSpace::new();
+        let range = 0x40_0000..0x40_0000 + PAGE_SIZE * 8;
+
+        // Nothing is mapped yet.
+        assert_eq!(vm_space.walk_range(&range).unwrap().count(), 0);
+
+        let frames = FrameAllocOptions::new(3).alloc().unwrap();
+        let mut expected = Vec::new();
+        {
+            let mut cursor = vm_space.cursor_mut(&range).unwrap();
+            for (i, frame) in [1, 2, 6].into_iter().zip(frames.iter()) {
+                let va = range.start + i * PAGE_SIZE;
+                let flags = if i == 2 { PageFlags::R } else { PageFlags::RW };
+                let prop = PageProperty::new(flags, CachePolicy::Writeback);
+                cursor.jump(va);
+                cursor.map(frame.clone(), prop);
+                expected.push((va..va + PAGE_SIZE, frame.start_paddr(), prop));
+            }
+        }
+
+        let mappings: Vec<_> = vm_space.walk_range(&range).unwrap().collect();
+        assert_eq!(mappings, expected);
+
+        // A sub-range only yields the mappings inside it.
+        let sub_range = range.start + PAGE_SIZE * 2..range.start + PAGE_SIZE * 6;
+        let mappings: Vec<_> = vm_space.walk_range(&sub_range).unwrap().collect();
+        assert_eq!(mappings, expected[1..2]);
+    }
+
+    #[ktest]
+    fn unmap_flushes_tlb_once() {
+        for nr_pages in [4, TLB_FLUSH_ALL_THRESHOLD * 4] {
+            let vm_space = VmSpace::new();
+            let range = 0x40_0000..0x40_0000 + PAGE_SIZE * nr_pages;
+            let prop = PageProperty::new(PageFlags::RW, CachePolicy::Writeback);
+
+            let frames = FrameAllocOptions::new(nr_pages).alloc().unwrap();
+            let mut cursor = vm_space.cursor_mut(&range).unwrap();
+            for frame in frames.iter() {
+                cursor.map(frame.clone(), prop);
+            }
+
+            let nr_flushes = NR_UNMAP_TLB_FLUSHES.load(Ordering::Relaxed);
+            cursor.jump(range.start);
+            cursor.unmap(range.len());
+            assert_eq!(NR_UNMAP_TLB_FLUSHES.load(Ordering::Relaxed) - nr_flushes, 1);
+            drop(cursor);
+
+            assert_eq!(vm_space.walk_range(&range).unwrap().count(), 0);
+        }
+    }
+
+    #[ktest]
+    fn rss() {
+        let vm_space = VmSpace::new();
+        let range = 0x40_0000..0x40_0000 + PAGE_SIZE * 8;
+        let anon_prop = PageProperty::new(PageFlags::RW, CachePolicy::Writeback);
+        let mut file_prop = anon_prop;
+        file_prop.set_rss_type(Some(RssType::File));
+
+        let frames = FrameAllocOptions::new(4).alloc().unwrap();
+        {
+            let mut cursor = vm_space.cursor_mut(&range).unwrap();
+            for (i, frame) in frames.iter().enumerate() {
+                let prop = if i < 3 { anon_prop } else { file_prop };
+                cursor.map(frame.clone(), prop);
+            }
+        }
+        assert_eq!(vm_space.rss(), Rss { anon: 3, file: 1 });
+
+        // A frame shared by two `VmSpace`s is counted in both of them.
+        let child = vm_space.fork_copy_on_write();
+        assert_eq!(child.rss(), Rss { anon: 3, file: 1 });
+
+        // Replacing a page does not count it twice.
+        let mut cursor = vm_space.cursor_mut(&range).unwrap();
+        cursor.jump(range.start + PAGE_SIZE * 3);
+        cursor.map(frames.iter().next().unwrap().clone(), anon_prop);
+        assert_eq!(vm_space.rss(), Rss { anon: 4, file: 0 });
+
+        cursor.jump(range.start + PAGE_SIZE);
+        cursor.unmap(PAGE_SIZE * 2);
+        assert_eq!(vm_space.rss(), Rss { anon: 2, file: 0 });
+
+        // A page without an RSS type is not counted until it is replaced.
+        let mut shared_prop = anon_prop;
+        shared_prop.set_rss_type(None);
+        cursor.jump(range.start + PAGE_SIZE * 4);
+        cursor.map(frames.iter().next().unwrap().clone(), shared_prop);
+        assert_eq!(vm_space.rss(), Rss { anon: 2, file: 0 });
+        cursor.jump(range.start + PAGE_SIZE * 4);
+        cursor.map(frames.iter().next().unwrap().clone(), anon_prop);
+        assert_eq!(vm_space.rss(), Rss { anon: 3, file: 0 });
+        drop(cursor);
+
+        vm_space.clear();
+        assert_eq!(vm_space.rss().total(), 0);
+        assert_eq!(child.rss(), Rss { anon: 3, file: 1 });
+    }
+
+    #[ktest]
+    fn lazy_tlb() {
+        const NR_YIELDS: usize = 16;
+
+        let vm_space = Arc::new(VmSpace::new());
+        let user_space = Arc::new(UserSpace::new(vm_space.clone(), UserContext::default()));
+        let nr_activations = NR_ACTIVATIONS.load(Ordering::Relaxed);
+
+        let is_done = Arc::new(AtomicBool::new(false));
+        let task = {
+            let is_done = is_done.clone();
+            TaskOptions::new(move || {
+                while !is_done.load(Ordering::Acquire) {
+                    Task::yield_now();
+                }
+            })
+            .user_space(Some(user_space))
+            .data(())
+            .spawn()
+            .unwrap()
+        };
+        for _ in 0..NR_YIELDS {
+            Task::yield_now();
+        }
+        is_done.store(true, Ordering::Release);
+        drop(task);
+
+        // Switching back and forth between the user task and the kernel task loads the
+        // page table of the user task at most once on each CPU.
+        let nr_activations = NR_ACTIVATIONS.load(Ordering::Relaxed) - nr_activations;
+        assert!(nr_activations <= num_cpus() as usize);
+    }
+
+    #[ktest]
+    fn lazy_tlb_teardown() {
+        let _irq_guard = disable_local();
+
+        let vm_space = VmSpace::new();
+        vm_space.activate();
+        assert_eq!(current_page_table_paddr(), unsafe {
+            vm_space.pt.root_paddr()
+        });
+
+        // Tearing down the active page table switches to the kernel page table.
+        drop(vm_space);
+        let kernel_pt = KERNEL_PAGE_TABLE.get().unwrap();
+        assert_eq!(current_page_table_paddr(), unsafe {
+            kernel_pt.root_paddr()
+        });
+    }
+}
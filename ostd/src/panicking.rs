@@ -55,6 +55,13 @@ pub fn panic_handler(info: &core::panic::PanicInfo) -> ! {
         begin_panic(Box::new(throw_info.clone()));
     }
     early_println!("{}", info);
+    if let Some(task) = crate::task::Task::current() {
+        // The panic may occur while the name is locked, so the lock is not waited for.
+        match task.try_name() {
+            Some(name) => early_println!("Current task: {}", name),
+            None => early_println!("Current task: <name unavailable>"),
+        }
+    }
     early_println!("Printing stack trace:");
     print_stack_trace();
     abort();
@@ -71,7 +71,9 @@ impl AtomicBits {
 
     /// Clears all the bits.
     pub fn clear(&self) {
-        todo!()
+        for u64_atomic in self.u64s.iter() {
+            u64_atomic.store(0, Relaxed);
+        }
     }
 
     /// Are all bits ones.
@@ -85,7 +87,21 @@ impl AtomicBits {
     }
 
     fn match_pattern(&self, pattern: u64) -> bool {
-        todo!()
+        let Some((last, others)) = self.u64s.split_last() else {
+            return true;
+        };
+        if !others.iter().all(|u64_atomic| u64_atomic.load(Relaxed) == pattern) {
+            return false;
+        }
+
+        // The garbage bits in the last u64, if any, are not compared.
+        let num_valid_bits_in_last_u64 = self.num_bits - others.len() * 64;
+        let valid_bits_mask = if num_valid_bits_in_last_u64 == 64 {
+            !0
+        } else {
+            (1 << num_valid_bits_in_last_u64) - 1
+        };
+        (last.load(Relaxed) ^ pattern) & valid_bits_mask == 0
     }
 
     /// Gets an iterator for the bits.
@@ -386,6 +402,21 @@ mod test {
         assert!(bits.iter_zeroes().count() == 5);
     }
 
+    #[ktest]
+    fn clear_and_match() {
+        let bits = AtomicBits::new_ones(77);
+        assert!(bits.is_full() && !bits.is_empty());
+
+        bits.clear();
+        assert!(bits.is_empty() && !bits.is_full());
+
+        for i in 0..bits.len() {
+            assert!(!bits.is_full());
+            bits.set(i, true);
+        }
+        assert!(bits.is_full());
+    }
+
     #[ktest]
     fn iter() {
         let bits = AtomicBits::new_zeroes(7);
@@ -0,0 +1,368 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Lock-ordering validation for the sleeping and spinning locks.
+//!
+//! If debug assertions are enabled, every acquisition of a [`SpinLock`] or a
+//! [`Mutex`] is recorded in a stack of held locks. A spin lock is held with
+//! preemption disabled, so it is pushed onto the stack of the current CPU.
+//! A mutex may be held while the task sleeps or migrates, so it is pushed onto
+//! the stack of the current task instead.
+//!
+//! When a lock B is acquired while a lock A is held, the order "A before B" is
+//! recorded. If the opposite order "B before A" has been recorded before, the
+//! two code paths can deadlock with each other, so we panic with the call sites
+//! of both acquisitions.
+//!
+//! Locks are identified by their instances rather than by their types or their
+//! addresses, since the address of a freed lock may be reused by an unrelated
+//! one. The recorded orders are kept in a fixed-size table where a new order
+//! may evict an old one, so it is possible to miss a violation, but a reported
+//! violation is always a real one.
+//!
+//! Acquiring a lock that is already held by the same CPU or task does not
+//! record any order, so that a legitimate recursive acquisition of a reentrant
+//! lock is never reported.
+//!
+//! Without debug assertions, the validation is compiled out and all the types
+//! here are zero-sized or absent.
+//!
+//! [`SpinLock`]: super::SpinLock
+//! [`Mutex`]: super::Mutex
+
+/// The identity of a lock instance.
+pub(super) struct LockKey {
+    #[cfg(debug_assertions)]
+    id: core::sync::atomic::AtomicU64,
+}
+
+/// The kind of a lock, which decides where the held lock is recorded.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(super) enum LockKind {
+    /// A lock that is held with preemption disabled.
+    Spin,
+    /// A lock that may be held while sleeping.
+    Sleep,
+}
+
+/// The sleeping locks held by a task.
+#[cfg(debug_assertions)]
+pub(crate) struct TaskHeldLocks {
+    stack: core::cell::UnsafeCell<imp::HeldStack>,
+}
+
+#[cfg(debug_assertions)]
+impl TaskHeldLocks {
+    /// Creates an empty set of held locks.
+    pub(crate) const fn new() -> Self {
+        Self {
+            stack: core::cell::UnsafeCell::new(imp::HeldStack::new()),
+        }
+    }
+}
+
+impl LockKey {
+    /// Creates a key, whose identity is assigned on the first acquisition.
+    pub(super) const fn new() -> Self {
+        Self {
+            #[cfg(debug_assertions)]
+            id: core::sync::atomic::AtomicU64::new(0),
+        }
+    }
+}
+
+cfg_if::cfg_if! {
+    if #[cfg(debug_assertions)] {
+        pub(super) use imp::{acquire, acquired, release};
+    } else {
+        /// Validates and records the acquisition of a lock that is about to block.
+        #[inline(always)]
+        pub(super) fn acquire(_key: &LockKey, _kind: LockKind, _location: &'static core::panic::Location<'static>) {}
+
+        /// Records a lock that has been acquired without blocking.
+        #[inline(always)]
+        pub(super) fn acquired(_key: &LockKey, _kind: LockKind, _location: &'static core::panic::Location<'static>) {}
+
+        /// Records the release of a lock.
+        #[inline(always)]
+        pub(super) fn release(_key: &LockKey, _kind: LockKind) {}
+    }
+}
+
+#[cfg(debug_assertions)]
+mod imp {
+    use core::{
+        cell::UnsafeCell,
+        panic::Location,
+        sync::atomic::{AtomicBool, AtomicU64, Ordering},
+    };
+
+    use super::{LockKey, LockKind};
+    use crate::{cpu_local_cell, prelude::*, task::Task, trap::disable_local};
+
+    /// The maximum depth of the held locks that are tracked.
+    ///
+    /// Locks acquired beyond this depth are not validated.
+    const MAX_HELD_LOCKS: usize = 32;
+
+    /// The number of slots in the table of recorded orders.
+    const NR_ORDER_SLOTS: usize = 4096;
+
+    #[derive(Clone, Copy)]
+    struct HeldLock {
+        id: u64,
+        location: &'static Location<'static>,
+    }
+
+    pub(in crate::sync) struct HeldStack {
+        locks: [Option<HeldLock>; MAX_HELD_LOCKS],
+        len: usize,
+    }
+
+    impl HeldStack {
+        pub(in crate::sync) const fn new() -> Self {
+            Self {
+                locks: [None; MAX_HELD_LOCKS],
+                len: 0,
+            }
+        }
+
+        fn iter(&self) -> impl Iterator<Item = HeldLock> + '_ {
+            self.locks[..self.len].iter().flatten().copied()
+        }
+
+        fn push(&mut self, lock: HeldLock) {
+            if self.len < MAX_HELD_LOCKS {
+                self.locks[self.len] = Some(lock);
+                self.len += 1;
+            }
+        }
+
+        fn remove(&mut self, id: u64) {
+            // Guards are usually dropped in the reverse order of acquisition,
+            // so search from the top of the stack.
+            let Some(pos) = self.locks[..self.len]
+                .iter()
+                .rposition(|lock| lock.is_some_and(|lock| lock.id == id))
+            else {
+                return;
+            };
+            self.locks.copy_within(pos + 1..self.len, pos);
+            self.len -= 1;
+            self.locks[self.len] = None;
+        }
+    }
+
+    cpu_local_cell! {
+        /// The spinning locks held by the current CPU.
+        static CPU_HELD_LOCKS: HeldStack = HeldStack::new();
+    }
+
+    /// A recorded order that the lock `before` is held when acquiring the lock `after`.
+    #[derive(Clone, Copy)]
+    struct LockOrder {
+        before: u64,
+        after: u64,
+        before_location: &'static core::panic::Location<'static>,
+        after_location: &'static core::panic::Location<'static>,
+    }
+
+    /// The table of recorded orders.
+    ///
+    /// It is protected by a raw spin flag instead of a [`crate::sync::SpinLock`]
+    /// to avoid validating the validator itself.
+    struct OrderTable {
+        busy: AtomicBool,
+        slots: UnsafeCell<[Option<LockOrder>; NR_ORDER_SLOTS]>,
+    }
+
+    // SAFETY: The slots are only accessed with `busy` set.
+    unsafe impl Sync for OrderTable {}
+
+    static ORDERS: OrderTable = OrderTable {
+        busy: AtomicBool::new(false),
+        slots: UnsafeCell::new([None; NR_ORDER_SLOTS]),
+    };
+
+    static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+    impl OrderTable {
+        fn slot_of(before: u64, after: u64) -> usize {
+            let hash = before.wrapping_mul(0x9E37_79B9_7F4A_7C15).rotate_left(29)
+                ^ after.wrapping_mul(0xC2B2_AE3D_27D4_EB4F);
+            (hash % NR_ORDER_SLOTS as u64) as usize
+        }
+
+        /// Records the order and returns a recorded opposite order, if any.
+        fn record(&self, order: LockOrder) -> Option<LockOrder> {
+            while self
+                .busy
+                .compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed)
+                .is_err()
+            {
+                core::hint::spin_loop();
+            }
+
+            // SAFETY: We have set `busy`, so we have the exclusive access to the slots.
+            let slots = unsafe { &mut *self.slots.get() };
+            let opposite = slots[Self::slot_of(order.after, order.before)]
+                .filter(|old| old.before == order.after && old.after == order.before);
+            if opposite.is_none() {
+                slots[Self::slot_of(order.before, order.after)] = Some(order);
+            }
+
+            self.busy.store(false, Ordering::Release);
+            opposite
+        }
+    }
+
+    impl LockKey {
+        fn id(&self) -> u64 {
+            let id = self.id.load(Ordering::Relaxed);
+            if id != 0 {
+                return id;
+            }
+            let new_id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+            match self
+                .id
+                .compare_exchange(0, new_id, Ordering::Relaxed, Ordering::Relaxed)
+            {
+                Ok(_) => new_id,
+                Err(id) => id,
+            }
+        }
+    }
+
+    fn is_enabled() -> bool {
+        // The CPU-local stacks are not available before the CPU-local storage is initialized.
+        crate::cpu::local::has_init::is_true()
+    }
+
+    /// The stacks of the locks held by the current CPU and the current task.
+    ///
+    /// The local IRQs must be disabled when it is alive.
+    struct HeldStacks {
+        cpu: *mut HeldStack,
+        // Keep the current task alive so that its stack is valid.
+        task: Option<Arc<Task>>,
+    }
+
+    impl HeldStacks {
+        fn current() -> Self {
+            Self {
+                // SAFETY: The local IRQs are disabled, so the pointer refers to the stack of
+                // the current CPU, which no one else accesses.
+                cpu: unsafe { CPU_HELD_LOCKS.as_ptr_mut() },
+                task: Task::current(),
+            }
+        }
+
+        fn task_ptr(&self) -> Option<*mut HeldStack> {
+            self.task.as_ref().map(|task| task.held_locks().stack.get())
+        }
+
+        /// Returns the stack where a lock of the kind is recorded.
+        ///
+        /// A sleeping lock acquired in the bootstrap context is recorded in the stack of
+        /// the current CPU, since there is no current task.
+        fn of_kind(&mut self, kind: LockKind) -> &mut HeldStack {
+            let ptr = match kind {
+                LockKind::Spin => self.cpu,
+                LockKind::Sleep => self.task_ptr().unwrap_or(self.cpu),
+            };
+            // SAFETY: Only the current CPU or the current task accesses the stack, and
+            // the returned reference borrows `self` mutably so it cannot be aliased.
+            unsafe { &mut *ptr }
+        }
+
+        /// Iterates over all the held locks.
+        fn iter(&self) -> impl Iterator<Item = HeldLock> + '_ {
+            // SAFETY: Only the current CPU or the current task accesses the stacks, and
+            // the returned iterator borrows `self` so they cannot be mutated meanwhile.
+            let cpu = unsafe { &*self.cpu };
+            let task = self.task_ptr().map(|ptr| unsafe { &*ptr });
+            cpu.iter().chain(task.into_iter().flat_map(HeldStack::iter))
+        }
+    }
+
+    /// Validates and records the acquisition of a lock that is about to block.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if the lock has been acquired before some of the
+    /// currently held locks elsewhere.
+    pub(in crate::sync) fn acquire(
+        key: &LockKey,
+        kind: LockKind,
+        location: &'static Location<'static>,
+    ) {
+        if !is_enabled() {
+            return;
+        }
+
+        let id = key.id();
+        let irq_guard = disable_local();
+
+        let mut stacks = HeldStacks::current();
+
+        let violation = if stacks.iter().any(|lock| lock.id == id) {
+            None
+        } else {
+            stacks.iter().find_map(|lock| {
+                let order = LockOrder {
+                    before: lock.id,
+                    after: id,
+                    before_location: lock.location,
+                    after_location: location,
+                };
+                ORDERS.record(order).map(|opposite| (order, opposite))
+            })
+        };
+
+        if let Some((order, opposite)) = violation {
+            drop(stacks);
+            drop(irq_guard);
+            panic!(
+                "Lock order violation: acquiring a lock at {} while holding a lock acquired at {}, \
+                 but they have been acquired in the opposite order at {} and then at {}",
+                order.after_location,
+                order.before_location,
+                opposite.before_location,
+                opposite.after_location,
+            );
+        }
+
+        stacks.of_kind(kind).push(HeldLock { id, location });
+    }
+
+    /// Records a lock that has been acquired without blocking.
+    ///
+    /// A successful try-lock never deadlocks, so no order is validated or
+    /// recorded for it. But the locks acquired later while holding it are
+    /// validated against it.
+    pub(in crate::sync) fn acquired(
+        key: &LockKey,
+        kind: LockKind,
+        location: &'static Location<'static>,
+    ) {
+        if !is_enabled() {
+            return;
+        }
+
+        let id = key.id();
+        let _irq_guard = disable_local();
+        HeldStacks::current()
+            .of_kind(kind)
+            .push(HeldLock { id, location });
+    }
+
+    /// Records the release of a lock.
+    pub(in crate::sync) fn release(key: &LockKey, kind: LockKind) {
+        if !is_enabled() {
+            return;
+        }
+
+        let id = key.id();
+        let _irq_guard = disable_local();
+        HeldStacks::current().of_kind(kind).remove(id);
+    }
+}
@@ -3,19 +3,25 @@
 //! Useful synchronization primitives.
 
 mod atomic_bits;
+mod lockdep;
 mod mutex;
-// TODO: refactor this rcu implementation
-// Comment out this module since it raises lint error
-// mod rcu;
+mod once;
+mod per_cpu_counter;
+pub(crate) mod rcu;
 mod rwlock;
 mod rwmutex;
 mod spin;
 mod wait;
+mod weak;
 
-// pub use self::rcu::{pass_quiescent_state, OwnerPtr, Rcu, RcuReadGuard, RcuReclaimer};
+#[cfg(debug_assertions)]
+pub(crate) use self::lockdep::TaskHeldLocks;
 pub use self::{
     atomic_bits::AtomicBits,
     mutex::{ArcMutexGuard, Mutex, MutexGuard},
+    once::OnceCell,
+    per_cpu_counter::PerCpuCounter,
+    rcu::{synchronize_rcu, OwnerPtr, Rcu, RcuReadGuard, RcuReclaimer},
     rwlock::{
         ArcRwLockReadGuard, ArcRwLockUpgradeableGuard, ArcRwLockWriteGuard, RwLock,
         RwLockReadGuard, RwLockUpgradeableGuard, RwLockWriteGuard,
@@ -26,4 +32,5 @@ pub use self::{
     },
     spin::{ArcSpinLockGuard, SpinLock, SpinLockGuard},
     wait::{WaitQueue, Waiter, Waker},
+    weak::WeakExt,
 };
@@ -5,15 +5,20 @@ use core::{
     cell::UnsafeCell,
     fmt,
     ops::{Deref, DerefMut},
+    panic::Location,
     sync::atomic::{AtomicBool, Ordering},
 };
 
-use super::WaitQueue;
+use super::{
+    lockdep::{self, LockKey, LockKind},
+    WaitQueue,
+};
 
 /// A mutex with waitqueue.
 pub struct Mutex<T: ?Sized> {
     lock: AtomicBool,
     queue: WaitQueue,
+    key: LockKey,
     val: UnsafeCell<T>,
 }
 
@@ -23,6 +28,7 @@ impl<T> Mutex<T> {
         Self {
             lock: AtomicBool::new(false),
             queue: WaitQueue::new(),
+            key: LockKey::new(),
             val: UnsafeCell::new(val),
         }
     }
@@ -32,8 +38,11 @@ impl<T: ?Sized> Mutex<T> {
     /// Acquires the mutex.
     ///
     /// This method runs in a block way until the mutex can be acquired.
+    #[track_caller]
     pub fn lock(&self) -> MutexGuard<T> {
-        self.queue.wait_until(|| self.try_lock())
+        lockdep::acquire(&self.key, LockKind::Sleep, Location::caller());
+        self.queue
+            .wait_until(|| self.acquire_lock().then(|| MutexGuard { mutex: self }))
     }
 
     /// Acquires the mutex through an [`Arc`].
@@ -42,15 +51,26 @@ impl<T: ?Sized> Mutex<T> {
     /// for compile-time checked lifetimes of the mutex guard.
     ///
     /// [`lock`]: Self::lock
+    #[track_caller]
     pub fn lock_arc(self: &Arc<Self>) -> ArcMutexGuard<T> {
-        self.queue.wait_until(|| self.try_lock_arc())
+        lockdep::acquire(&self.key, LockKind::Sleep, Location::caller());
+        self.queue.wait_until(|| {
+            self.acquire_lock().then(|| ArcMutexGuard {
+                mutex: self.clone(),
+            })
+        })
     }
 
     /// Tries Acquire the mutex immedidately.
+    #[track_caller]
     pub fn try_lock(&self) -> Option<MutexGuard<T>> {
+        let location = Location::caller();
         // Cannot be reduced to `then_some`, or the possible dropping of the temporary
         // guard will cause an unexpected unlock.
-        self.acquire_lock().then_some(MutexGuard { mutex: self })
+        self.acquire_lock().then(|| {
+            lockdep::acquired(&self.key, LockKind::Sleep, location);
+            MutexGuard { mutex: self }
+        })
     }
 
     /// Tries acquire the mutex through an [`Arc`].
@@ -59,14 +79,20 @@ impl<T: ?Sized> Mutex<T> {
     /// for compile-time checked lifetimes of the mutex guard.
     ///
     /// [`try_lock`]: Self::try_lock
+    #[track_caller]
     pub fn try_lock_arc(self: &Arc<Self>) -> Option<ArcMutexGuard<T>> {
-        self.acquire_lock().then(|| ArcMutexGuard {
-            mutex: self.clone(),
+        let location = Location::caller();
+        self.acquire_lock().then(|| {
+            lockdep::acquired(&self.key, LockKind::Sleep, location);
+            ArcMutexGuard {
+                mutex: self.clone(),
+            }
         })
     }
 
     /// Releases the mutex and wake up one thread which is blocked on this mutex.
     fn unlock(&self) {
+        lockdep::release(&self.key, LockKind::Sleep);
         self.release_lock();
         self.queue.wake_one();
     }
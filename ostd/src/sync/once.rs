@@ -0,0 +1,244 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use alloc::sync::Arc;
+use core::{
+    cell::UnsafeCell,
+    fmt,
+    mem::MaybeUninit,
+    sync::atomic::{AtomicU8, AtomicUsize, Ordering},
+};
+
+use crate::{
+    task::{scheduler, Task},
+    trap::in_interrupt_context,
+};
+
+/// A cell that is initialized at most once, even if it is accessed from many CPUs.
+///
+/// The cell is usually initialized lazily by the first call to [`OnceCell::get_or_init`],
+/// which suits the global states that cannot be initialized at boot time. If other tasks
+/// access the cell during the initialization, they yield until the initialization
+/// completes, or spin if they cannot yield, e.g., in the interrupt context.
+///
+/// Accessing the cell from its own initialization closure can never succeed, so it
+/// causes a panic rather than a deadlock. The reentrancy is detected in the task context
+/// only. An IRQ handler that accesses the cell spins even if the interrupted task is the
+/// initializer, so a cell shared with IRQ handlers should be initialized with local IRQs
+/// disabled.
+pub struct OnceCell<T> {
+    state: AtomicU8,
+    /// The task that runs the initialization, which is zero if the task is unknown.
+    initializer: AtomicUsize,
+    value: UnsafeCell<MaybeUninit<T>>,
+}
+
+const INCOMPLETE: u8 = 0;
+const RUNNING: u8 = 1;
+const COMPLETE: u8 = 2;
+
+// SAFETY: The value is written only once, by the CPU that wins the race to initialize
+// the cell, and can only be read after the write is completed. So sharing the cell is
+// the same as sharing `T` (requiring `T: Sync`), and initializing the cell on another
+// CPU is the same as sending `T` (requiring `T: Send`).
+unsafe impl<T: Send + Sync> Sync for OnceCell<T> {}
+// SAFETY: The cell owns the value.
+unsafe impl<T: Send> Send for OnceCell<T> {}
+
+impl<T> OnceCell<T> {
+    /// Creates an uninitialized cell.
+    pub const fn new() -> Self {
+        Self {
+            state: AtomicU8::new(INCOMPLETE),
+            initializer: AtomicUsize::new(0),
+            value: UnsafeCell::new(MaybeUninit::uninit()),
+        }
+    }
+
+    /// Returns the value if the cell is initialized.
+    pub fn get(&self) -> Option<&T> {
+        if self.state.load(Ordering::Acquire) == COMPLETE {
+            // SAFETY: The value has been initialized and will never be written again.
+            Some(unsafe { self.value_unchecked() })
+        } else {
+            None
+        }
+    }
+
+    /// Returns the value, initializing the cell with `f` if it is not initialized.
+    ///
+    /// # Panics
+    ///
+    /// This method panics if it is called by `f` on the same cell.
+    pub fn get_or_init(&self, f: impl FnOnce() -> T) -> &T {
+        if let Some(value) = self.get() {
+            return value;
+        }
+        self.init_slow(f)
+    }
+
+    /// Initializes the cell with `value`.
+    ///
+    /// If the cell is already initialized, `value` is returned as an error.
+    pub fn set(&self, value: T) -> core::result::Result<(), T> {
+        let mut value = Some(value);
+        self.get_or_init(|| value.take().unwrap());
+        match value {
+            None => Ok(()),
+            Some(value) => Err(value),
+        }
+    }
+
+    /// Returns whether the cell is initialized.
+    pub fn is_initialized(&self) -> bool {
+        self.get().is_some()
+    }
+
+    #[cold]
+    fn init_slow(&self, f: impl FnOnce() -> T) -> &T {
+        // In the interrupt context, the current task is the interrupted one, which is not
+        // the one that accesses the cell.
+        let me = if in_interrupt_context() {
+            0
+        } else {
+            current_task_id()
+        };
+
+        loop {
+            match self.state.compare_exchange_weak(
+                INCOMPLETE,
+                RUNNING,
+                Ordering::Acquire,
+                Ordering::Acquire,
+            ) {
+                Ok(_) => break,
+                // SAFETY: The value has been initialized and will never be written again.
+                Err(COMPLETE) => return unsafe { self.value_unchecked() },
+                Err(RUNNING) => {
+                    if me != 0 && self.initializer.load(Ordering::Relaxed) == me {
+                        panic!("the once cell is accessed during its own initialization");
+                    }
+                    // The bootstrap context is not a task, so it cannot yield.
+                    if me != 0 && scheduler::can_yield() {
+                        Task::yield_now();
+                    } else {
+                        core::hint::spin_loop();
+                    }
+                }
+                Err(_) => (),
+            }
+        }
+
+        self.initializer.store(me, Ordering::Relaxed);
+        // If `f` panics, the cell is left uninitialized rather than blocking the others
+        // forever.
+        let guard = ResetOnDrop(self);
+        let value = f();
+        core::mem::forget(guard);
+
+        // SAFETY: Only the CPU that changes the state to `RUNNING` can write the value.
+        unsafe { (*self.value.get()).write(value) };
+        self.initializer.store(0, Ordering::Relaxed);
+        self.state.store(COMPLETE, Ordering::Release);
+
+        // SAFETY: The value has just been initialized and will never be written again.
+        unsafe { self.value_unchecked() }
+    }
+
+    /// # Safety
+    ///
+    /// The caller must ensure that the value has been initialized.
+    unsafe fn value_unchecked(&self) -> &T {
+        (*self.value.get()).assume_init_ref()
+    }
+}
+
+struct ResetOnDrop<'a, T>(&'a OnceCell<T>);
+
+impl<T> Drop for ResetOnDrop<'_, T> {
+    fn drop(&mut self) {
+        self.0.initializer.store(0, Ordering::Relaxed);
+        self.0.state.store(INCOMPLETE, Ordering::Release);
+    }
+}
+
+/// Returns an ID of the current task, or zero in the bootstrap context.
+fn current_task_id() -> usize {
+    Task::current().map_or(0, |task| Arc::as_ptr(&task) as usize)
+}
+
+impl<T> Default for OnceCell<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for OnceCell<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("OnceCell").field(&self.get()).finish()
+    }
+}
+
+impl<T> Drop for OnceCell<T> {
+    fn drop(&mut self) {
+        if *self.state.get_mut() == COMPLETE {
+            // SAFETY: The value has been initialized and is never accessed again.
+            unsafe { self.value.get_mut().assume_init_drop() };
+        }
+    }
+}
+
+#[cfg(ktest)]
+mod test {
+    use alloc::vec::Vec;
+
+    use super::*;
+    use crate::{prelude::*, task::scope};
+
+    #[ktest]
+    fn init_once() {
+        let cell = OnceCell::new();
+        assert_eq!(cell.get(), None);
+
+        assert_eq!(*cell.get_or_init(|| 1), 1);
+        assert_eq!(*cell.get_or_init(|| 2), 1);
+        assert_eq!(cell.set(3), Err(3));
+        assert_eq!(cell.get(), Some(&1));
+    }
+
+    #[ktest]
+    fn race_to_init() {
+        const NR_TASKS: usize = 8;
+
+        let cell = OnceCell::new();
+        let nr_inits = AtomicUsize::new(0);
+        let values = scope(|s| {
+            let handles = (0..NR_TASKS)
+                .map(|i| {
+                    let (cell, nr_inits) = (&cell, &nr_inits);
+                    s.spawn(move || {
+                        *cell.get_or_init(|| {
+                            nr_inits.fetch_add(1, Ordering::Relaxed);
+                            // Let the others wait for the initialization.
+                            Task::yield_now();
+                            i
+                        })
+                    })
+                })
+                .collect::<Vec<_>>();
+            handles
+                .into_iter()
+                .map(|handle| handle.join().unwrap())
+                .collect::<Vec<_>>()
+        });
+
+        assert_eq!(nr_inits.load(Ordering::Relaxed), 1);
+        assert!(values.iter().all(|value| Some(value) == cell.get()));
+    }
+
+    #[ktest]
+    #[should_panic(expected = "the once cell is accessed during its own initialization")]
+    fn reentrant_init() {
+        let cell = OnceCell::new();
+        cell.get_or_init(|| *cell.get_or_init(|| 1));
+    }
+}
@@ -0,0 +1,106 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use alloc::{boxed::Box, vec::Vec};
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::cpu::{num_cpus, this_cpu};
+
+/// A counter that is cheap to update from many CPUs at the same time.
+///
+/// Each CPU updates its own slot, which resides in its own cache line. So updates
+/// from different CPUs do not contend with each other. In exchange, reading the
+/// counter with [`PerCpuCounter::sum`] has to visit the slots of all the CPUs.
+///
+/// This suits statistics that are updated frequently but read rarely, e.g., the
+/// number of interrupts.
+///
+/// The slots are updated with wrapping arithmetic, so a slot may "underflow" when
+/// the counter is increased on one CPU and decreased on another, while the sum is
+/// still correct.
+pub struct PerCpuCounter {
+    slots: Box<[Slot]>,
+}
+
+#[repr(align(64))]
+struct Slot(AtomicUsize);
+
+impl PerCpuCounter {
+    /// Creates a counter whose value is zero.
+    pub fn new() -> Self {
+        let slots = (0..num_cpus())
+            .map(|_| Slot(AtomicUsize::new(0)))
+            .collect::<Vec<_>>();
+        Self {
+            slots: slots.into_boxed_slice(),
+        }
+    }
+
+    /// Adds `val` to the counter.
+    pub fn add(&self, val: usize) {
+        // The task may be migrated to another CPU after getting the CPU ID, which
+        // is fine because the slot is updated atomically.
+        self.slot().fetch_add(val, Ordering::Relaxed);
+    }
+
+    /// Subtracts `val` from the counter.
+    pub fn sub(&self, val: usize) {
+        self.slot().fetch_sub(val, Ordering::Relaxed);
+    }
+
+    /// Returns the value of the counter.
+    ///
+    /// If the counter is updated concurrently, the result is an approximate snapshot,
+    /// which may or may not include the concurrent updates.
+    pub fn sum(&self) -> usize {
+        self.slots.iter().fold(0, |sum, slot| {
+            sum.wrapping_add(slot.0.load(Ordering::Relaxed))
+        })
+    }
+
+    fn slot(&self) -> &AtomicUsize {
+        &self.slots[this_cpu() as usize].0
+    }
+}
+
+impl Default for PerCpuCounter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(ktest)]
+mod test {
+    use super::*;
+    use crate::{prelude::*, task::scope};
+
+    #[ktest]
+    fn sum_increments_from_tasks() {
+        const NR_TASKS: usize = 8;
+        const NR_INCREMENTS: usize = 1000;
+
+        let counter = PerCpuCounter::new();
+        scope(|s| {
+            for _ in 0..NR_TASKS {
+                let counter = &counter;
+                s.spawn(move || {
+                    for _ in 0..NR_INCREMENTS {
+                        counter.add(1);
+                    }
+                });
+            }
+        });
+
+        assert_eq!(counter.sum(), NR_TASKS * NR_INCREMENTS);
+    }
+
+    #[ktest]
+    fn sub_on_another_cpu() {
+        let counter = PerCpuCounter::new();
+        // Pretend that the increments are done on the first CPU. The slot of the
+        // CPU doing the subtraction may underflow.
+        counter.slots[0].0.store(3, Ordering::Relaxed);
+
+        counter.sub(2);
+        assert_eq!(counter.sum(), 1);
+    }
+}
@@ -1,29 +1,51 @@
 // SPDX-License-Identifier: MPL-2.0
 
 //! Read-copy update (RCU).
-
-use core::marker::PhantomData;
-use core::ops::Deref;
-use core::sync::atomic::{
-    AtomicPtr,
-    Ordering::{AcqRel, Acquire},
+//!
+//! A reader accesses the RCU-protected object with preemption disabled. So once
+//! every CPU has passed a _quiescent state_, i.e., has switched tasks, has been idle, or
+//! has been interrupted with preemption enabled, all the readers that might see an
+//! old object are gone and the old object can be reclaimed.
+
+use core::{
+    marker::PhantomData,
+    ops::Deref,
+    sync::atomic::{
+        AtomicPtr,
+        Ordering::{AcqRel, Acquire},
+    },
 };
 
 use self::monitor::RcuMonitor;
-use crate::prelude::*;
-use crate::sync::WaitQueue;
+use crate::{
+    cpu::num_cpus,
+    prelude::*,
+    sync::{OnceCell, Waiter},
+    task::{disable_preempt, DisablePreemptGuard},
+};
 
 mod monitor;
 mod owner_ptr;
 
 pub use owner_ptr::OwnerPtr;
 
+/// A Read-Copy Update (RCU) cell for sharing a pointer between CPUs.
+///
+/// Readers get the current object with [`Rcu::read`] without any locks, while a
+/// writer replaces the object with [`Rcu::update`] and reclaims the old one after
+/// all the readers that might see it are gone.
 pub struct Rcu<P: OwnerPtr> {
     ptr: AtomicPtr<<P as OwnerPtr>::Target>,
-    marker: PhantomData<P::Target>,
+    marker: PhantomData<P>,
 }
 
+// SAFETY: The cell behaves like `P`, which may be shared and dropped by any CPU.
+unsafe impl<P: OwnerPtr + Send + Sync> Sync for Rcu<P> {}
+// SAFETY: The cell owns the object that `P` refers to.
+unsafe impl<P: OwnerPtr + Send> Send for Rcu<P> {}
+
 impl<P: OwnerPtr> Rcu<P> {
+    /// Creates a new cell with the initial pointer.
     pub fn new(ptr: P) -> Self {
         let ptr = AtomicPtr::new(OwnerPtr::into_raw(ptr) as *mut _);
         Self {
@@ -32,28 +54,54 @@ impl<P: OwnerPtr> Rcu<P> {
         }
     }
 
-    pub fn get(&self) -> RcuReadGuard<'_, P> {
+    /// Returns a guard for reading the current object.
+    ///
+    /// Preemption is disabled until the guard is dropped, so the guard should not
+    /// be held across sleeps.
+    pub fn read(&self) -> RcuReadGuard<'_, P> {
+        let guard = disable_preempt();
+        // SAFETY: The object is not reclaimed until the current CPU passes a quiescent
+        // state, which cannot happen before the guard (and the preemption guard in it)
+        // is dropped.
         let obj = unsafe { &*self.ptr.load(Acquire) };
-        RcuReadGuard { obj, rcu: self }
+        RcuReadGuard {
+            obj,
+            _guard: guard,
+            marker: PhantomData,
+        }
     }
 }
 
 impl<P: OwnerPtr + Send> Rcu<P> {
-    pub fn replace(&self, new_ptr: P) -> RcuReclaimer<P> {
+    /// Replaces the current pointer with `new_ptr`.
+    ///
+    /// The old pointer is returned as a reclaimer, which waits for a grace period
+    /// before it gives the old pointer back or drops it.
+    pub fn update(&self, new_ptr: P) -> RcuReclaimer<P> {
         let new_ptr = <P as OwnerPtr>::into_raw(new_ptr) as *mut _;
-        let old_ptr = {
-            let old_raw_ptr = self.ptr.swap(new_ptr, AcqRel);
-            unsafe { <P as OwnerPtr>::from_raw(old_raw_ptr) }
-        };
-        RcuReclaimer { ptr: old_ptr }
+        let old_raw_ptr = self.ptr.swap(new_ptr, AcqRel);
+        // SAFETY: The raw pointer was returned by `into_raw` and is no longer in the cell.
+        let old_ptr = unsafe { <P as OwnerPtr>::from_raw(old_raw_ptr) };
+        RcuReclaimer { ptr: Some(old_ptr) }
+    }
+}
+
+impl<P: OwnerPtr> Drop for Rcu<P> {
+    fn drop(&mut self) {
+        let ptr = *self.ptr.get_mut();
+        // SAFETY: The raw pointer was returned by `into_raw`. No readers can exist since
+        // they borrow the cell.
+        drop(unsafe { <P as OwnerPtr>::from_raw(ptr) });
     }
 }
 
+/// A guard that allows reading the object in an [`Rcu`].
 #[clippy::has_significant_drop]
 #[must_use]
 pub struct RcuReadGuard<'a, P: OwnerPtr> {
     obj: &'a <P as OwnerPtr>::Target,
-    rcu: &'a Rcu<P>,
+    _guard: DisablePreemptGuard,
+    marker: PhantomData<&'a Rcu<P>>,
 }
 
 impl<'a, P: OwnerPtr> Deref for RcuReadGuard<'a, P> {
@@ -64,43 +112,105 @@ impl<'a, P: OwnerPtr> Deref for RcuReadGuard<'a, P> {
     }
 }
 
-#[repr(transparent)]
+/// A pointer that has been replaced in an [`Rcu`] and may still be used by readers.
+///
+/// Dropping the reclaimer waits for a grace period and then drops the pointer.
+#[must_use]
 pub struct RcuReclaimer<P> {
-    ptr: P,
+    ptr: Option<P>,
 }
 
-impl<P: Send + 'static> RcuReclaimer<P> {
-    pub fn delay(mut self) {
-        let ptr: P = unsafe {
-            let ptr = core::mem::replace(&mut self.ptr, core::mem::uninitialized());
-
-            core::mem::forget(self);
-
-            ptr
-        };
-        get_singleton().after_grace_period(move || {
-            drop(ptr);
-        });
+impl<P> RcuReclaimer<P> {
+    /// Waits for a grace period and returns the pointer, which is no longer used by
+    /// any readers.
+    pub fn wait(mut self) -> P {
+        synchronize_rcu();
+        self.ptr.take().unwrap()
     }
 }
 
 impl<P> Drop for RcuReclaimer<P> {
     fn drop(&mut self) {
-        let wq = Arc::new(WaitQueue::new());
-        get_singleton().after_grace_period({
-            let wq = wq.clone();
-            move || {
-                wq.wake_one();
-            }
-        });
-        wq.wait_until(|| Some(0u8));
+        if self.ptr.is_some() {
+            synchronize_rcu();
+        }
+    }
+}
+
+/// Waits for a grace period, after which all the readers that exist at the time of
+/// the call are gone.
+///
+/// This function sleeps, so it must not be called with an [`RcuReadGuard`] held.
+pub fn synchronize_rcu() {
+    let (waiter, waker) = Waiter::new_pair();
+    rcu_monitor().after_grace_period(move || {
+        waker.wake_up();
+    });
+    waiter.wait();
+}
+
+/// Reports that the current CPU has passed a quiescent state.
+///
+/// This function is called by the scheduler when the current CPU switches tasks, wakes up
+/// from being idle, or is interrupted with preemption enabled.
+pub(crate) fn note_context_switch() {
+    // No grace period can be in progress before the monitor is created.
+    if let Some(monitor) = RCU_MONITOR.get() {
+        monitor.pass_quiescent_state();
     }
 }
 
-pub unsafe fn pass_quiescent_state() {
-    get_singleton().pass_quiescent_state()
+static RCU_MONITOR: OnceCell<RcuMonitor> = OnceCell::new();
+
+fn rcu_monitor() -> &'static RcuMonitor {
+    RCU_MONITOR.get_or_init(|| RcuMonitor::new(num_cpus() as usize))
 }
 
-fn get_singleton() -> &'static RcuMonitor {
-    todo!()
+#[cfg(ktest)]
+mod test {
+    use core::sync::atomic::{AtomicBool, Ordering};
+
+    use super::*;
+    use crate::arch::timer::Jiffies;
+
+    #[ktest]
+    fn grace_period_waits_for_readers() {
+        static IS_COMPLETE: AtomicBool = AtomicBool::new(false);
+
+        let rcu = Rcu::new(Box::new(1));
+        let guard = rcu.read();
+
+        let old = rcu.update(Box::new(2));
+        assert_eq!(*rcu.read(), 2);
+        assert_eq!(*guard, 1);
+
+        rcu_monitor().after_grace_period(|| IS_COMPLETE.store(true, Ordering::Relaxed));
+
+        // The other CPUs pass quiescent states at the timer interrupts, but the grace
+        // period cannot complete while the current CPU is still reading.
+        let start = Jiffies::elapsed().as_u64();
+        while Jiffies::elapsed().as_u64() < start + 2 {
+            core::hint::spin_loop();
+        }
+        assert!(!IS_COMPLETE.load(Ordering::Relaxed));
+        assert_eq!(*guard, 1);
+
+        drop(guard);
+        assert_eq!(*old.wait(), 1);
+        assert!(IS_COMPLETE.load(Ordering::Relaxed));
+    }
+
+    #[ktest]
+    fn grace_period_on_all_cpus() {
+        if num_cpus() == 1 {
+            return;
+        }
+
+        // No tasks run on the other CPUs, so they only pass quiescent states at their
+        // own timer interrupts. The grace period never completes if any of them misses.
+        let rcu = Rcu::new(Box::new(1));
+        let old = rcu.update(Box::new(2));
+        assert_eq!(*old.wait(), 1);
+        synchronize_rcu();
+    }
 }
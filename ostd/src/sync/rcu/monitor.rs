@@ -1,35 +1,40 @@
 // SPDX-License-Identifier: MPL-2.0
 
-use alloc::collections::VecDeque;
-use core::sync::atomic::{
-    AtomicBool,
-    Ordering::{Acquire, Relaxed, Release},
-};
+use alloc::{boxed::Box, vec::Vec};
+use core::sync::atomic::{AtomicBool, Ordering};
 
-#[cfg(target_arch = "x86_64")]
-use crate::arch::x86::cpu;
-use crate::prelude::*;
-use crate::sync::AtomicBits;
-use crate::sync::SpinLock;
+use crate::{
+    cpu::this_cpu,
+    sync::{AtomicBits, SpinLock},
+};
 
 /// A RCU monitor ensures the completion of _grace periods_ by keeping track
 /// of each CPU's passing _quiescent states_.
-pub struct RcuMonitor {
+pub(super) struct RcuMonitor {
     is_monitoring: AtomicBool,
     state: SpinLock<State>,
 }
 
 impl RcuMonitor {
-    pub fn new(num_cpus: usize) -> Self {
+    pub(super) fn new(num_cpus: usize) -> Self {
         Self {
             is_monitoring: AtomicBool::new(false),
             state: SpinLock::new(State::new(num_cpus)),
         }
     }
 
-    pub unsafe fn pass_quiescent_state(&self) {
-        // Fast path
-        if !self.is_monitoring.load(Relaxed) {
+    /// Reports that the current CPU has passed a quiescent state.
+    ///
+    /// If the current grace period is completed, its callbacks are invoked.
+    pub(super) fn pass_quiescent_state(&self) {
+        self.pass_quiescent_state_on(this_cpu() as usize);
+    }
+
+    /// Reports that the CPU `cpu_id` has passed a quiescent state.
+    fn pass_quiescent_state_on(&self, cpu_id: usize) {
+        // Fast path. A grace period that starts concurrently is noticed at the next
+        // quiescent state.
+        if !self.is_monitoring.load(Ordering::Relaxed) {
             return;
         }
 
@@ -42,7 +47,7 @@ impl RcuMonitor {
                 return;
             }
 
-            state.current_gp.pass_quiescent_state();
+            state.current_gp.pass_quiescent_state(cpu_id);
             if !state.current_gp.is_complete() {
                 return;
             }
@@ -50,12 +55,12 @@ impl RcuMonitor {
             // Now that the current GP is complete, take its callbacks
             let current_callbacks = state.current_gp.take_callbacks();
 
-            // Check if we need to70G watch for a next GP
+            // Check if we need to watch for a next GP
             if !state.next_callbacks.is_empty() {
                 let callbacks = core::mem::take(&mut state.next_callbacks);
                 state.current_gp.restart(callbacks);
             } else {
-                self.is_monitoring.store(false, Relaxed);
+                self.is_monitoring.store(false, Ordering::Relaxed);
             }
 
             current_callbacks
@@ -67,21 +72,26 @@ impl RcuMonitor {
         }
     }
 
-    pub fn after_grace_period<F>(&self, f: F)
+    /// Registers a callback that is invoked after a grace period that starts after this call.
+    ///
+    /// The callback may be invoked in the interrupt context or in the middle of a context
+    /// switch, so it should be as simple as waking up a waiter.
+    pub(super) fn after_grace_period<F>(&self, f: F)
     where
-        F: FnOnce() -> () + Send + 'static,
+        F: FnOnce() + Send + 'static,
     {
         let mut state = self.state.lock_irq_disabled();
 
-        state.next_callbacks.push_back(Box::new(f));
+        state.next_callbacks.push(Box::new(f));
 
+        // The callbacks will be moved to the next GP when the current one completes.
         if !state.current_gp.is_complete() {
             return;
         }
 
         let callbacks = core::mem::take(&mut state.next_callbacks);
         state.current_gp.restart(callbacks);
-        self.is_monitoring.store(true, Relaxed);
+        self.is_monitoring.store(true, Ordering::Relaxed);
     }
 }
 
@@ -91,15 +101,15 @@ struct State {
 }
 
 impl State {
-    pub fn new(num_cpus: usize) -> Self {
+    fn new(num_cpus: usize) -> Self {
         Self {
             current_gp: GracePeriod::new(num_cpus),
-            next_callbacks: VecDeque::new(),
+            next_callbacks: Vec::new(),
         }
     }
 }
 
-type Callbacks = VecDeque<Box<dyn FnOnce() -> () + Send + 'static>>;
+type Callbacks = Vec<Box<dyn FnOnce() + Send + 'static>>;
 
 struct GracePeriod {
     callbacks: Callbacks,
@@ -108,34 +118,76 @@ struct GracePeriod {
 }
 
 impl GracePeriod {
-    pub fn new(num_cpus: usize) -> Self {
+    /// Creates a completed grace period, so that the first callback starts a new one.
+    fn new(num_cpus: usize) -> Self {
         Self {
-            callbacks: Default::default(),
-            cpu_mask: AtomicBits::new_zeroes(num_cpus),
-            is_complete: false,
+            callbacks: Vec::new(),
+            cpu_mask: AtomicBits::new_ones(num_cpus),
+            is_complete: true,
         }
     }
 
-    pub fn is_complete(&self) -> bool {
+    fn is_complete(&self) -> bool {
         self.is_complete
     }
 
-    pub unsafe fn pass_quiescent_state(&mut self) {
-        let this_cpu = cpu::this_cpu();
-        self.cpu_mask.set(this_cpu as usize, true);
+    fn pass_quiescent_state(&mut self, cpu_id: usize) {
+        self.cpu_mask.set(cpu_id, true);
 
         if self.cpu_mask.is_full() {
             self.is_complete = true;
         }
     }
 
-    pub fn take_callbacks(&mut self) -> Callbacks {
+    fn take_callbacks(&mut self) -> Callbacks {
         core::mem::take(&mut self.callbacks)
     }
 
-    pub fn restart(&mut self, callbacks: Callbacks) {
+    fn restart(&mut self, callbacks: Callbacks) {
         self.is_complete = false;
         self.cpu_mask.clear();
         self.callbacks = callbacks;
     }
 }
+
+#[cfg(ktest)]
+mod test {
+    use core::sync::atomic::AtomicUsize;
+
+    use super::*;
+    use crate::prelude::*;
+
+    #[ktest]
+    fn grace_period_waits_for_every_cpu() {
+        const NR_CPUS: usize = 4;
+
+        let monitor = RcuMonitor::new(NR_CPUS);
+        let nr_completed = Arc::new(AtomicUsize::new(0));
+        let add_completed = || {
+            let nr_completed = nr_completed.clone();
+            move || {
+                nr_completed.fetch_add(1, Ordering::Relaxed);
+            }
+        };
+
+        monitor.after_grace_period(add_completed());
+        for cpu in 0..NR_CPUS - 1 {
+            monitor.pass_quiescent_state_on(cpu);
+            // Passing quiescent states again on the same CPU does not help.
+            monitor.pass_quiescent_state_on(cpu);
+            assert_eq!(nr_completed.load(Ordering::Relaxed), 0);
+        }
+
+        // A callback registered in the middle of a grace period waits for the next one.
+        monitor.after_grace_period(add_completed());
+        monitor.pass_quiescent_state_on(NR_CPUS - 1);
+        assert_eq!(nr_completed.load(Ordering::Relaxed), 1);
+
+        for cpu in (0..NR_CPUS).rev() {
+            assert_eq!(nr_completed.load(Ordering::Relaxed), 1);
+            monitor.pass_quiescent_state_on(cpu);
+        }
+        assert_eq!(nr_completed.load(Ordering::Relaxed), 2);
+        assert!(!monitor.is_monitoring.load(Ordering::Relaxed));
+    }
+}
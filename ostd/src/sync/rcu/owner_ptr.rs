@@ -53,28 +53,3 @@ impl<T> OwnerPtr for Arc<T> {
         Arc::from_raw(ptr)
     }
 }
-
-impl<P> OwnerPtr for Option<P>
-where
-    P: OwnerPtr,
-    // We cannot support fat pointers, e.g., when `Target: dyn Trait`.
-    // This is because Rust does not allow fat null pointers. Yet,
-    // we need the null pointer to represent `None`.
-    // See https://github.com/rust-lang/rust/issues/66316.
-    <P as OwnerPtr>::Target: Sized,
-{
-    type Target = P::Target;
-
-    fn into_raw(self) -> *const Self::Target {
-        self.map(|p| <P as OwnerPtr>::into_raw(p))
-            .unwrap_or(core::ptr::null())
-    }
-
-    unsafe fn from_raw(ptr: *const Self::Target) -> Self {
-        if ptr.is_null() {
-            Some(<P as OwnerPtr>::from_raw(ptr))
-        } else {
-            None
-        }
-    }
-}
@@ -59,6 +59,18 @@ use crate::{
 /// This lock should not be used in scenarios where lock-holding times are
 /// long as it can lead to CPU resource wastage due to spinning.
 ///
+/// # Fairness
+///
+/// A lock created by [`new`] prefers readers, so a steady stream of readers
+/// can starve the writers. A lock created by [`new_writer_priority`] does not
+/// admit new readers or upreaders while a writer is spin-waiting, so the
+/// writer acquires the lock once the existing readers release it. With such a
+/// lock, a reader must not acquire the read lock recursively, or it may
+/// deadlock with a waiting writer.
+///
+/// [`new`]: Self::new
+/// [`new_writer_priority`]: Self::new_writer_priority
+///
 /// # Safety
 ///
 /// Use interrupt-disabled version methods when dealing with interrupt-related read-write locks,
@@ -106,6 +118,12 @@ pub struct RwLock<T: ?Sized> {
     /// - **Bit 61:** Indicates if an upgradeable reader is being upgraded.
     /// - **Bits 60-0:** Reader lock count.
     lock: AtomicUsize,
+    /// Whether new readers are blocked while writers are waiting.
+    is_writer_priority: bool,
+    /// The number of writers that are spin-waiting for the lock.
+    ///
+    /// It is only maintained for a lock with writer priority.
+    nr_waiting_writers: AtomicUsize,
     val: UnsafeCell<T>,
 }
 
@@ -121,11 +139,53 @@ impl<T> RwLock<T> {
         Self {
             val: UnsafeCell::new(val),
             lock: AtomicUsize::new(0),
+            is_writer_priority: false,
+            nr_waiting_writers: AtomicUsize::new(0),
+        }
+    }
+
+    /// Creates a new spin-based read-write lock with an initial value, which
+    /// prevents the writers from being starved by the readers.
+    ///
+    /// See the [fairness](Self#fairness) section for details.
+    pub const fn new_writer_priority(val: T) -> Self {
+        Self {
+            val: UnsafeCell::new(val),
+            lock: AtomicUsize::new(0),
+            is_writer_priority: true,
+            nr_waiting_writers: AtomicUsize::new(0),
         }
     }
 }
 
 impl<T: ?Sized> RwLock<T> {
+    /// Returns whether new readers and upreaders should back off for the
+    /// waiting writers.
+    fn has_waiting_writers(&self) -> bool {
+        self.is_writer_priority && self.nr_waiting_writers.load(Relaxed) != 0
+    }
+
+    /// Spin-waits until `try_write` succeeds.
+    ///
+    /// For a lock with writer priority, the writer is registered as waiting
+    /// during the spin-wait, so that no new readers can get the lock.
+    fn spin_write<G>(&self, mut try_write: impl FnMut() -> Option<G>) -> G {
+        if self.is_writer_priority {
+            self.nr_waiting_writers.fetch_add(1, Relaxed);
+        }
+        let guard = loop {
+            if let Some(guard) = try_write() {
+                break guard;
+            } else {
+                core::hint::spin_loop();
+            }
+        };
+        if self.is_writer_priority {
+            self.nr_waiting_writers.fetch_sub(1, Relaxed);
+        }
+        guard
+    }
+
     /// Acquires a read lock while disabling the local IRQs and spin-wait
     /// until it can be acquired.
     ///
@@ -153,13 +213,7 @@ impl<T: ?Sized> RwLock<T> {
     /// obtain the lock. Once this lock is acquired, the calling thread
     /// will not be interrupted.
     pub fn write_irq_disabled(&self) -> RwLockWriteGuard<T> {
-        loop {
-            if let Some(writeguard) = self.try_write_irq_disabled() {
-                return writeguard;
-            } else {
-                core::hint::spin_loop();
-            }
-        }
+        self.spin_write(|| self.try_write_irq_disabled())
     }
 
     /// Acquires an upgradeable reader (upreader) while disabling local IRQs
@@ -192,6 +246,9 @@ impl<T: ?Sized> RwLock<T> {
     /// when acquiring fails.
     pub fn try_read_irq_disabled(&self) -> Option<RwLockReadGuard<T>> {
         let irq_guard = disable_local();
+        if self.has_waiting_writers() {
+            return None;
+        }
         let lock = self.lock.fetch_add(READER, Acquire);
         if lock & (WRITER | MAX_READER | BEING_UPGRADED) == 0 {
             Some(RwLockReadGuard {
@@ -234,6 +291,9 @@ impl<T: ?Sized> RwLock<T> {
     /// when acquiring fails.
     pub fn try_upread_irq_disabled(&self) -> Option<RwLockUpgradeableGuard<T>> {
         let irq_guard = disable_local();
+        if self.has_waiting_writers() {
+            return None;
+        }
         let lock = self.lock.fetch_or(UPGRADEABLE_READER, Acquire) & (WRITER | UPGRADEABLE_READER);
         if lock == 0 {
             return Some(RwLockUpgradeableGuard {
@@ -301,13 +361,7 @@ impl<T: ?Sized> RwLock<T> {
     ///
     /// [`write_irq_disabled`]: Self::write_irq_disabled
     pub fn write(&self) -> RwLockWriteGuard<T> {
-        loop {
-            if let Some(writeguard) = self.try_write() {
-                return writeguard;
-            } else {
-                core::hint::spin_loop();
-            }
-        }
+        self.spin_write(|| self.try_write())
     }
 
     /// Acquires a write lock through an [`Arc`].
@@ -317,13 +371,7 @@ impl<T: ?Sized> RwLock<T> {
     ///
     /// [`write`]: Self::write
     pub fn write_arc(self: &Arc<Self>) -> ArcRwLockWriteGuard<T> {
-        loop {
-            if let Some(writeguard) = self.try_write_arc() {
-                return writeguard;
-            } else {
-                core::hint::spin_loop();
-            }
-        }
+        self.spin_write(|| self.try_write_arc())
     }
 
     /// Acquires an upreader and spin-wait until it can be acquired.
@@ -384,6 +432,9 @@ impl<T: ?Sized> RwLock<T> {
     /// [`try_read_irq_disabled`]: Self::try_read_irq_disabled
     pub fn try_read(&self) -> Option<RwLockReadGuard<T>> {
         let guard = disable_preempt();
+        if self.has_waiting_writers() {
+            return None;
+        }
         let lock = self.lock.fetch_add(READER, Acquire);
         if lock & (WRITER | MAX_READER | BEING_UPGRADED) == 0 {
             Some(RwLockReadGuard {
@@ -404,6 +455,9 @@ impl<T: ?Sized> RwLock<T> {
     /// [`try_read`]: Self::try_read
     pub fn try_read_arc(self: &Arc<Self>) -> Option<ArcRwLockReadGuard<T>> {
         let guard = disable_preempt();
+        if self.has_waiting_writers() {
+            return None;
+        }
         let lock = self.lock.fetch_add(READER, Acquire);
         if lock & (WRITER | MAX_READER | BEING_UPGRADED) == 0 {
             Some(ArcRwLockReadGuard {
@@ -480,6 +534,9 @@ impl<T: ?Sized> RwLock<T> {
     /// [`try_upread_irq_disabled`]: Self::try_upread_irq_disabled
     pub fn try_upread(&self) -> Option<RwLockUpgradeableGuard<T>> {
         let guard = disable_preempt();
+        if self.has_waiting_writers() {
+            return None;
+        }
         let lock = self.lock.fetch_or(UPGRADEABLE_READER, Acquire) & (WRITER | UPGRADEABLE_READER);
         if lock == 0 {
             return Some(RwLockUpgradeableGuard {
@@ -500,6 +557,9 @@ impl<T: ?Sized> RwLock<T> {
     /// [`try_upread`]: Self::try_upread
     pub fn try_upread_arc(self: &Arc<Self>) -> Option<ArcRwLockUpgradeableGuard<T>> {
         let guard = disable_preempt();
+        if self.has_waiting_writers() {
+            return None;
+        }
         let lock = self.lock.fetch_or(UPGRADEABLE_READER, Acquire) & (WRITER | UPGRADEABLE_READER);
         if lock == 0 {
             return Some(ArcRwLockUpgradeableGuard {
@@ -737,3 +797,86 @@ impl<T: ?Sized + fmt::Debug, R: Deref<Target = RwLock<T>> + Clone> fmt::Debug
         fmt::Debug::fmt(&**self, f)
     }
 }
+
+#[cfg(ktest)]
+mod test {
+    use core::sync::atomic::{AtomicBool, Ordering};
+
+    use super::*;
+    use crate::{
+        arch::timer::Jiffies,
+        cpu::num_cpus,
+        prelude::*,
+        task::{Task, TaskOptions},
+    };
+
+    #[ktest]
+    fn waiting_writer_blocks_new_readers() {
+        let lock = RwLock::new_writer_priority(0);
+        let reader = lock.read();
+
+        // The existing reader is allowed to finish.
+        lock.nr_waiting_writers.fetch_add(1, Relaxed);
+        assert!(lock.try_read().is_none());
+        assert!(lock.try_upread().is_none());
+        assert!(lock.try_write().is_none());
+        assert_eq!(*reader, 0);
+        drop(reader);
+        assert!(lock.try_write().is_some());
+        lock.nr_waiting_writers.fetch_sub(1, Relaxed);
+
+        assert!(lock.try_read().is_some());
+    }
+
+    #[ktest]
+    fn writer_is_not_starved() {
+        // The writer should get the lock once the readers that hold the lock
+        // finish, which takes far less than a second.
+        const MAX_WAIT_JIFFIES: u64 = 100;
+
+        let lock = Arc::new(RwLock::new_writer_priority(0usize));
+        let should_stop = Arc::new(AtomicBool::new(false));
+        let nr_started = Arc::new(AtomicUsize::new(0));
+        let nr_finished = Arc::new(AtomicUsize::new(0));
+
+        // Keep the readers overlapping, so that the lock is never free if new
+        // readers were admitted.
+        let nr_readers = num_cpus().max(2);
+        for _ in 0..nr_readers {
+            let lock = lock.clone();
+            let should_stop = should_stop.clone();
+            let nr_started = nr_started.clone();
+            let nr_finished = nr_finished.clone();
+            TaskOptions::new(move || {
+                nr_started.fetch_add(1, Ordering::Release);
+                while !should_stop.load(Ordering::Acquire) {
+                    let guard = lock.read();
+                    for _ in 0..100 {
+                        core::hint::spin_loop();
+                    }
+                    drop(guard);
+                    Task::yield_now();
+                }
+                nr_finished.fetch_add(1, Ordering::Release);
+            })
+            .data(())
+            .spawn()
+            .unwrap();
+        }
+
+        while nr_started.load(Ordering::Acquire) < nr_readers {
+            Task::yield_now();
+        }
+
+        let start = Jiffies::elapsed().as_u64();
+        *lock.write() += 1;
+        let waited = Jiffies::elapsed().as_u64() - start;
+
+        should_stop.store(true, Ordering::Release);
+        while nr_finished.load(Ordering::Acquire) < nr_readers {
+            Task::yield_now();
+        }
+        assert!(waited < MAX_WAIT_JIFFIES);
+        assert_eq!(*lock.read(), 1);
+    }
+}
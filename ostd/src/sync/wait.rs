@@ -64,7 +64,9 @@ impl WaitQueue {
     /// threads do not lose any wakeup notifications.
     ///
     /// By taking a condition closure, this wait-wakeup mechanism becomes
-    /// more efficient and robust.
+    /// more efficient and robust. In particular, the condition is checked again
+    /// after each wakeup, so a spurious wakeup (e.g., a `wake`-family method
+    /// invoked before the condition is met) only puts the thread back to sleep.
     pub fn wait_until<F, R>(&self, mut cond: F) -> R
     where
         F: FnMut() -> Option<R>,
@@ -318,6 +320,45 @@ mod test {
         });
     }
 
+    #[ktest]
+    fn queue_wake_spurious() {
+        let queue = Arc::new(WaitQueue::new());
+        let queue_cloned = queue.clone();
+
+        let cond = Arc::new(AtomicBool::new(false));
+        let cond_cloned = cond.clone();
+        let nr_checks = Arc::new(AtomicU32::new(0));
+        let nr_checks_cloned = nr_checks.clone();
+
+        TaskOptions::new(move || {
+            let wait_for_checks = |nr| {
+                while nr_checks_cloned.load(Ordering::Relaxed) < nr {
+                    Task::yield_now();
+                }
+            };
+
+            // The consumer is in the queue after checking the condition twice. Wake it up
+            // before the condition is met.
+            wait_for_checks(2);
+            queue_cloned.wake_all();
+            wait_for_checks(3);
+
+            cond_cloned.store(true, Ordering::Relaxed);
+            queue_cloned.wake_all();
+        })
+        .data(())
+        .spawn()
+        .unwrap();
+
+        queue.wait_until(|| {
+            nr_checks.fetch_add(1, Ordering::Relaxed);
+            cond.load(Ordering::Relaxed).then_some(())
+        });
+
+        assert!(cond.load(Ordering::Relaxed));
+        assert!(nr_checks.load(Ordering::Relaxed) >= 4);
+    }
+
     #[ktest]
     fn waiter_wake_twice() {
         let (_waiter, waker) = Waiter::new_pair();
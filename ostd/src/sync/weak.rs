@@ -0,0 +1,45 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use alloc::sync::{Arc, Weak};
+
+/// An extension trait for [`Weak`] pointers.
+pub trait WeakExt<T: ?Sized> {
+    /// Upgrades the weak pointer and calls `f` with the strong pointer.
+    ///
+    /// The strong pointer is held until `f` returns, so the object cannot
+    /// be dropped while it is being used. If the object has already been
+    /// dropped, `f` is not called and `None` is returned.
+    ///
+    /// This is a shorthand for `weak.upgrade().map(|arc| f(&arc))`.
+    fn with_upgraded<R, F>(&self, f: F) -> Option<R>
+    where
+        F: FnOnce(&Arc<T>) -> R;
+}
+
+impl<T: ?Sized> WeakExt<T> for Weak<T> {
+    fn with_upgraded<R, F>(&self, f: F) -> Option<R>
+    where
+        F: FnOnce(&Arc<T>) -> R,
+    {
+        let arc = self.upgrade()?;
+        Some(f(&arc))
+    }
+}
+
+#[cfg(ktest)]
+mod test {
+    use super::*;
+    use crate::prelude::*;
+
+    #[ktest]
+    fn with_upgraded() {
+        let arc = Arc::new(1);
+        let weak = Arc::downgrade(&arc);
+        assert_eq!(weak.with_upgraded(|arc| **arc + 1), Some(2));
+
+        drop(arc);
+        let mut is_called = false;
+        assert_eq!(weak.with_upgraded(|_| is_called = true), None);
+        assert!(!is_called);
+    }
+}
@@ -5,10 +5,16 @@
 mod preempt;
 mod processor;
 pub mod scheduler;
+mod scope;
+pub mod switch_trace;
 #[allow(clippy::module_inception)]
 mod task;
 
 pub use self::{
     preempt::{disable_preempt, DisablePreemptGuard},
-    task::{AtomicCpuId, Priority, Task, TaskAdapter, TaskContextApi, TaskOptions},
+    scope::{scope, Scope, ScopedJoinHandle},
+    task::{
+        AtomicCpuId, Priority, Task, TaskAdapter, TaskContextApi, TaskName, TaskOptions,
+        TASK_NAME_LEN,
+    },
 };
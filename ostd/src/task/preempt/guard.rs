@@ -1,6 +1,12 @@
 // SPDX-License-Identifier: MPL-2.0
 
 /// A guard for disable preempt.
+///
+/// While any guard is alive, the scheduler does not switch away from the current
+/// task, but interrupts are still handled. The guards can be nested. If a
+/// reschedule is requested while preemption is disabled, e.g., by a timer tick or
+/// by waking up a task, the reschedule is deferred and taken when the last guard
+/// is dropped.
 #[clippy::has_significant_drop]
 #[must_use]
 pub struct DisablePreemptGuard {
@@ -26,6 +32,16 @@ impl DisablePreemptGuard {
 impl Drop for DisablePreemptGuard {
     fn drop(&mut self) {
         super::cpu_local::dec_guard_count();
+
+        // Take the deferred reschedule, if any. Switching tasks is not allowed with
+        // local IRQs disabled or in the interrupt context, in which case the
+        // reschedule is taken at the next preemption point.
+        if super::cpu_local::should_preempt()
+            && crate::arch::irq::is_local_enabled()
+            && !crate::trap::in_interrupt_context()
+        {
+            crate::task::scheduler::might_preempt();
+        }
     }
 }
 
@@ -33,3 +49,41 @@ impl Drop for DisablePreemptGuard {
 pub fn disable_preempt() -> DisablePreemptGuard {
     DisablePreemptGuard::new()
 }
+
+#[cfg(ktest)]
+mod test {
+    use core::sync::atomic::{AtomicBool, Ordering};
+
+    use super::*;
+    use crate::{arch::timer::Jiffies, prelude::*, task::TaskOptions};
+
+    #[ktest]
+    fn defer_preemption_until_enabled() {
+        let has_run = Arc::new(AtomicBool::new(false));
+
+        let guard = disable_preempt();
+        let nested_guard = disable_preempt();
+
+        // Spawning a task requests a reschedule, which must be deferred.
+        let has_run_cloned = has_run.clone();
+        TaskOptions::new(move || has_run_cloned.store(true, Ordering::Release))
+            .data(())
+            .spawn()
+            .unwrap();
+        super::super::cpu_local::set_need_preempt();
+
+        // Wait across a few timer ticks.
+        let start = Jiffies::elapsed().as_u64();
+        while Jiffies::elapsed().as_u64() < start + 2 {
+            core::hint::spin_loop();
+        }
+        assert!(!has_run.load(Ordering::Acquire));
+
+        drop(nested_guard);
+        assert!(!has_run.load(Ordering::Acquire));
+
+        // The pending reschedule is taken once preemption is enabled.
+        drop(guard);
+        assert!(has_run.load(Ordering::Acquire));
+    }
+}
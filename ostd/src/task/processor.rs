@@ -4,9 +4,10 @@ use alloc::sync::Arc;
 
 use super::{
     preempt::cpu_local,
+    switch_trace,
     task::{context_switch, Task, TaskContext},
 };
-use crate::cpu_local_cell;
+use crate::{arch::read_tsc, cpu_local_cell};
 
 cpu_local_cell! {
     /// The `Arc<Task>` (casted by [`Arc::into_raw`]) that is the current task.
@@ -17,6 +18,8 @@ cpu_local_cell! {
     static PREVIOUS_TASK_PTR: *const Task = core::ptr::null();
     /// An unsafe cell to store the context of the bootstrap code.
     static BOOTSTRAP_CONTEXT: TaskContext = TaskContext::new();
+    /// The TSC value when the CPU time was charged to the current task last time.
+    static LAST_CHARGE_TSC: u64 = 0;
 }
 
 /// Retrieves a reference to the current task running on the processor.
@@ -36,6 +39,25 @@ pub(super) fn current_task() -> Option<Arc<Task>> {
     Some(restored)
 }
 
+/// Charges the CPU time elapsed since the last charge to the current task.
+///
+/// This function is called on each context switch and on each timer tick, so
+/// a task that runs without yielding is still accounted at the next tick.
+pub(super) fn charge_current_cpu_time() {
+    let now = read_tsc();
+    let elapsed = now.wrapping_sub(LAST_CHARGE_TSC.load());
+    LAST_CHARGE_TSC.store(now);
+
+    let current_task_ptr = CURRENT_TASK_PTR.load();
+    if current_task_ptr.is_null() {
+        return;
+    }
+    // SAFETY: The pointer is not NULL and set as the current task. The current task is alive
+    // as long as it is running on this processor.
+    let current_task = unsafe { &*current_task_ptr };
+    current_task.charge_cpu_time(elapsed);
+}
+
 /// Calls this function to switch to other task
 ///
 /// If current task is none, then it will use the default task context and it
@@ -59,8 +81,12 @@ pub(super) fn switch_to_task(next_task: Arc<Task>) {
         "Switching task with local IRQ disabled"
     );
 
+    crate::sync::rcu::note_context_switch();
+
     let irq_guard = crate::trap::disable_local();
 
+    charge_current_cpu_time();
+
     let current_task_ptr = CURRENT_TASK_PTR.load();
     let current_task_ctx_ptr = if current_task_ptr.is_null() {
         // SAFETY: Interrupts are disabled, so the pointer is safe to be fetched.
@@ -78,6 +104,10 @@ pub(super) fn switch_to_task(next_task: Arc<Task>) {
     };
 
     let next_task_ctx_ptr = next_task.ctx().get().cast_const();
+    // Kernel tasks never access the user space, so they borrow the page table of the
+    // previous task instead of switching to the kernel page table (the lazy TLB mode). The
+    // page table is switched only when a user task with a different address space is
+    // switched in, since activating the page table that is already active is a no-op.
     if let Some(next_user_space) = next_task.user_space() {
         next_user_space.vm_space().activate();
     }
@@ -97,6 +127,7 @@ pub(super) fn switch_to_task(next_task: Arc<Task>) {
         drop(unsafe { Arc::from_raw(old_prev) });
     }
 
+    switch_trace::on_switch_start();
     drop(irq_guard);
 
     // SAFETY:
@@ -109,6 +140,8 @@ pub(super) fn switch_to_task(next_task: Arc<Task>) {
         context_switch(current_task_ctx_ptr, next_task_ctx_ptr);
     }
 
+    switch_trace::on_task_entry();
+
     // Now it's fine to drop `prev_task`. However, we choose not to do this because it is not
     // always possible. For example, `context_switch` can switch directly to the entry point of the
     // next task. Not dropping is just fine because the only consequence is that we delay the drop
@@ -11,8 +11,15 @@ use core::sync::atomic::{AtomicBool, Ordering};
 
 use spin::Once;
 
-use super::{preempt::cpu_local, processor, task::Task};
-use crate::{arch::timer, cpu::this_cpu, prelude::*};
+use super::{preempt::cpu_local, processor, switch_trace, task::Task};
+use crate::{
+    arch::{irq::send_ipi, timer},
+    cpu::this_cpu,
+    cpu_local_cell,
+    prelude::*,
+    sync::rcu,
+    trap::{self, DisabledLocalIrqGuard, IrqLine},
+};
 
 /// Injects a scheduler implementation into framework.
 ///
@@ -20,17 +27,54 @@ use crate::{arch::timer, cpu::this_cpu, prelude::*};
 pub fn inject_scheduler(scheduler: &'static dyn Scheduler<Task>) {
     SCHEDULER.call_once(|| scheduler);
 
-    timer::register_callback(|| {
-        SCHEDULER.get().unwrap().local_mut_rq_with(&mut |local_rq| {
-            if local_rq.update_current(UpdateFlags::Tick) {
-                cpu_local::set_need_preempt();
-            }
-        })
+    RESCHED_IRQ.call_once(|| {
+        let mut irq = IrqLine::alloc().unwrap();
+        // The IPI wakes up the CPU if it is idle. Otherwise, the current task is
+        // preempted at the next preemption point.
+        irq.on_active(|_| cpu_local::set_need_preempt());
+        irq
     });
 }
 
+/// Registers the scheduler tick on the current CPU.
+///
+/// The timer callbacks are CPU-local, so this function must be called once on each
+/// CPU. The tick does nothing until a scheduler is injected.
+pub(crate) fn init_tick_on_cpu() {
+    timer::register_callback(on_tick);
+}
+
+fn on_tick() {
+    let Some(scheduler) = SCHEDULER.get() else {
+        return;
+    };
+
+    processor::charge_current_cpu_time();
+    // RCU readers disable preemption, so a CPU that is interrupted with preemption
+    // enabled is not reading. This covers the CPUs that seldom switch tasks.
+    if cpu_local::get_guard_count() == 0 {
+        rcu::note_context_switch();
+    }
+
+    scheduler.local_mut_rq_with(&mut |local_rq| {
+        // The switch is not done here. It is deferred to the next preemption point,
+        // and is further deferred until the preemption is enabled if it is disabled.
+        if local_rq.update_current(UpdateFlags::Tick) {
+            cpu_local::set_need_preempt();
+        }
+    })
+}
+
 static SCHEDULER: Once<&'static dyn Scheduler<Task>> = Once::new();
 
+/// The IRQ line of the IPIs that ask a CPU to reschedule.
+static RESCHED_IRQ: Once<IrqLine> = Once::new();
+
+cpu_local_cell! {
+    /// The number of times that the CPU has been halted for having nothing to run.
+    static NR_IDLE_HALTS: u32 = 0;
+}
+
 /// A per-CPU task scheduler.
 pub trait Scheduler<T = Task>: Sync + Send {
     /// Enqueues a runnable task.
@@ -108,6 +152,17 @@ pub(crate) fn might_preempt() {
     yield_now();
 }
 
+/// Returns whether the current task can yield.
+///
+/// Switching tasks is not allowed in the interrupt context, with preemption or local IRQs
+/// disabled, or before the scheduler is set.
+pub(crate) fn can_yield() -> bool {
+    SCHEDULER.get().is_some()
+        && cpu_local::get_guard_count() == 0
+        && crate::arch::irq::is_local_enabled()
+        && !crate::trap::in_interrupt_context()
+}
+
 /// Blocks the current task unless `has_woken` is `true`.
 pub(crate) fn park_current(has_woken: &AtomicBool) {
     let mut current = None;
@@ -138,12 +193,8 @@ pub(crate) fn unpark_target(runnable: Arc<Task>) {
         .get()
         .unwrap()
         .enqueue(runnable, EnqueueFlags::Wake);
-    if need_preempt_info.is_some() {
-        let cpu_id = need_preempt_info.unwrap();
-        // FIXME: send IPI to set remote CPU's need_preempt if needed.
-        if cpu_id == this_cpu() {
-            cpu_local::set_need_preempt();
-        }
+    if let Some(cpu_id) = need_preempt_info {
+        set_need_preempt(cpu_id);
     }
 }
 
@@ -161,17 +212,24 @@ pub(super) fn run_new_task(runnable: Arc<Task>) {
         .get()
         .unwrap()
         .enqueue(runnable, EnqueueFlags::Spawn);
-    if need_preempt_info.is_some() {
-        let cpu_id = need_preempt_info.unwrap();
-        // FIXME: send IPI to set remote CPU's need_preempt if needed.
-        if cpu_id == this_cpu() {
-            cpu_local::set_need_preempt();
-        }
+    if let Some(cpu_id) = need_preempt_info {
+        set_need_preempt(cpu_id);
     }
 
     might_preempt();
 }
 
+/// Asks the CPU `cpu_id` to reschedule.
+///
+/// A remote CPU is notified with an IPI, which also wakes it up if it is idle.
+fn set_need_preempt(cpu_id: u32) {
+    if cpu_id == this_cpu() {
+        cpu_local::set_need_preempt();
+    } else {
+        send_ipi(cpu_id, RESCHED_IRQ.get().unwrap().num());
+    }
+}
+
 /// Dequeues the current task from its runqueue.
 ///
 /// This should only be called if the current is to exit.
@@ -208,6 +266,11 @@ where
     F: FnMut(&mut dyn LocalRunQueue) -> ReschedAction,
 {
     let next_task = loop {
+        // Local IRQs are disabled until the CPU halts, so that a task woken up
+        // by an IRQ handler after the decision is made cannot be missed.
+        let irq_guard = trap::disable_local();
+        switch_trace::on_sched_entry();
+
         let mut action = ReschedAction::DoNothing;
         SCHEDULER.get().unwrap().local_mut_rq_with(&mut |rq| {
             action = f(rq);
@@ -215,21 +278,41 @@ where
 
         match action {
             ReschedAction::DoNothing => {
+                // The current task keeps running, so the request to preempt it has been
+                // handled. Otherwise, every preemption point would reschedule again.
+                cpu_local::clear_need_preempt();
                 return;
             }
-            ReschedAction::Retry => {
-                continue;
-            }
-            ReschedAction::SwitchTo(next_task) => {
-                break next_task;
-            }
-        };
+            ReschedAction::SwitchTo(next_task) => break next_task,
+            ReschedAction::Retry => (),
+        }
+
+        // There is no task to run. The runqueue is no longer locked, so other CPUs
+        // can enqueue tasks to it while this CPU is idle.
+        if idle(irq_guard) {
+            // The CPU has been idle, so it is not in any RCU read-side sections.
+            rcu::note_context_switch();
+        }
     };
 
     cpu_local::clear_need_preempt();
     processor::switch_to_task(next_task);
 }
 
+/// Halts the CPU until an IRQ arrives since there is no task to run.
+///
+/// The IRQ may be a timer interrupt or a rescheduling IPI sent by another CPU
+/// after enqueuing a task to this CPU. Returns whether the CPU has been halted,
+/// which is not the case if local IRQs cannot be enabled here (see
+/// [`DisabledLocalIrqGuard::enable_and_halt`]).
+fn idle(irq_guard: DisabledLocalIrqGuard) -> bool {
+    let has_halted = irq_guard.enable_and_halt();
+    if has_halted {
+        NR_IDLE_HALTS.add_assign(1);
+    }
+    has_halted
+}
+
 /// Possible actions of a rescheduling.
 enum ReschedAction {
     /// Keep running current task and do nothing.
@@ -239,3 +322,55 @@ enum ReschedAction {
     /// Switch to target task.
     SwitchTo(Arc<Task>),
 }
+
+#[cfg(ktest)]
+mod test {
+    use super::*;
+    use crate::sync::{SpinLock, Waiter, Waker};
+
+    #[ktest]
+    fn halt_when_idle() {
+        static WAKER: SpinLock<Option<Arc<Waker>>> = SpinLock::new(None);
+
+        let (waiter, waker) = Waiter::new_pair();
+        *WAKER.lock_irq_disabled() = Some(waker);
+
+        // Wake up the waiter only after the CPU has been halted for having nothing
+        // to run. The timer interrupt is what wakes up the CPU.
+        let nr_halts = NR_IDLE_HALTS.load();
+        let callback = timer::register_removable_callback(move || {
+            if NR_IDLE_HALTS.load() == nr_halts {
+                return;
+            }
+            if let Some(waker) = WAKER.lock_irq_disabled().take() {
+                waker.wake_up();
+            }
+        });
+
+        waiter.wait();
+        drop(callback);
+
+        assert!(NR_IDLE_HALTS.load() > nr_halts);
+        assert!(WAKER.lock_irq_disabled().is_none());
+    }
+
+    #[ktest]
+    fn no_halt_with_irqs_disabled() {
+        // No IRQ could wake up the CPU if it halted with the outer guard held.
+        let outer_guard = trap::disable_local();
+        let nr_halts = NR_IDLE_HALTS.load();
+        assert!(!idle(trap::disable_local()));
+        assert_eq!(NR_IDLE_HALTS.load(), nr_halts);
+        drop(outer_guard);
+    }
+
+    #[ktest]
+    fn do_nothing_clears_need_preempt() {
+        // The timer interrupt must not request another preemption during the test.
+        let _irq_guard = trap::disable_local();
+
+        cpu_local::set_need_preempt();
+        reschedule(&mut |_| ReschedAction::DoNothing);
+        assert!(!cpu_local::need_preempt());
+    }
+}
@@ -0,0 +1,252 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Scoped tasks.
+//!
+//! Similar to the scoped threads of the standard library, scoped tasks can
+//! borrow non-`'static` data from the enclosing stack frame, because all of
+//! them are guaranteed to finish before [`scope`] returns.
+
+use core::{
+    marker::PhantomData,
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+};
+
+use unwinding::panic::{begin_panic, catch_unwind};
+
+use super::TaskOptions;
+use crate::{
+    prelude::*,
+    sync::{SpinLock, WaitQueue},
+};
+
+/// Creates a scope for spawning scoped tasks.
+///
+/// The function `f` is given a [`Scope`], with which scoped tasks can be
+/// spawned. Before this function returns, all the spawned tasks that have not
+/// been joined manually are joined automatically.
+///
+/// # Panics
+///
+/// If `f` panics, the panic is propagated after all the tasks are joined.
+/// If any of the tasks that have not been joined manually panics, this
+/// function panics after all the tasks are joined.
+///
+/// # Examples
+///
+/// ```
+/// use ostd::task::scope;
+///
+/// let mut buf = [0u8; 4];
+/// let (left, right) = buf.split_at_mut(2);
+/// scope(|s| {
+///     s.spawn(|| left.fill(1));
+///     s.spawn(|| right.fill(2));
+/// });
+/// assert_eq!(buf, [1, 1, 2, 2]);
+/// ```
+pub fn scope<'env, F, T>(f: F) -> T
+where
+    F: for<'scope> FnOnce(&'scope Scope<'scope, 'env>) -> T,
+{
+    let scope = Scope {
+        data: Arc::new(ScopeData::new()),
+        scope: PhantomData,
+        env: PhantomData,
+    };
+
+    let result = catch_unwind(|| f(&scope));
+
+    scope.data.wait_all();
+
+    match result {
+        Ok(_) if scope.data.nr_panicked.load(Ordering::Relaxed) > 0 => {
+            panic!("a scoped task has panicked");
+        }
+        Ok(output) => output,
+        Err(payload) => {
+            let _ = begin_panic(payload);
+            crate::panicking::abort();
+        }
+    }
+}
+
+/// A scope to spawn scoped tasks in.
+///
+/// See [`scope`] for details.
+pub struct Scope<'scope, 'env: 'scope> {
+    data: Arc<ScopeData>,
+    /// Invariance over `'scope`, so that the scope cannot shrink.
+    scope: PhantomData<&'scope mut &'scope ()>,
+    /// Invariance over `'env`, so that the scope cannot grow.
+    env: PhantomData<&'env mut &'env ()>,
+}
+
+impl<'scope, 'env> Scope<'scope, 'env> {
+    /// Spawns a scoped task that runs `f`.
+    ///
+    /// The task is joined automatically at the end of the scope if it has not
+    /// been joined manually with the returned handle.
+    ///
+    /// # Panics
+    ///
+    /// This method panics if the task cannot be created.
+    pub fn spawn<F, T>(&'scope self, f: F) -> ScopedJoinHandle<'scope, T>
+    where
+        F: FnOnce() -> T + Send + 'scope,
+        T: Send + 'scope,
+    {
+        let packet = Arc::new(Packet {
+            output: SpinLock::new(None),
+            is_finished: AtomicBool::new(false),
+        });
+
+        let func: Box<dyn FnOnce() + Send + 'scope> = {
+            let data = self.data.clone();
+            let packet = packet.clone();
+            Box::new(move || {
+                match catch_unwind(f) {
+                    Ok(output) => *packet.output.lock() = Some(output),
+                    Err(_) => {
+                        data.nr_panicked.fetch_add(1, Ordering::Relaxed);
+                    }
+                }
+
+                // Nothing borrowed from the scope can be used after the task is marked as
+                // finished, so the packet must be dropped in advance.
+                packet.is_finished.store(true, Ordering::Release);
+                drop(packet);
+                data.finish_one();
+            })
+        };
+        // SAFETY: The closure is always finished before the end of `'scope`, because `scope`
+        // waits for all the tasks to finish before returning. So it is safe to extend its
+        // lifetime to `'static`.
+        let func: Box<dyn FnOnce() + Send + 'static> = unsafe { core::mem::transmute(func) };
+        let func = SpinLock::new(Some(func));
+
+        self.data.nr_running.fetch_add(1, Ordering::Relaxed);
+        let spawn_result = TaskOptions::new(move || {
+            let func = func.lock().take();
+            if let Some(func) = func {
+                func();
+            }
+        })
+        .data(())
+        .spawn();
+        if spawn_result.is_err() {
+            self.data.finish_one();
+            panic!("failed to spawn a scoped task");
+        }
+
+        ScopedJoinHandle {
+            data: self.data.clone(),
+            packet,
+            scope: PhantomData,
+        }
+    }
+}
+
+/// A handle to join a scoped task.
+pub struct ScopedJoinHandle<'scope, T> {
+    data: Arc<ScopeData>,
+    packet: Arc<Packet<T>>,
+    scope: PhantomData<&'scope ()>,
+}
+
+impl<'scope, T> ScopedJoinHandle<'scope, T> {
+    /// Waits for the task to finish and returns its output.
+    ///
+    /// Returns `None` if the task has panicked. A panic that is observed by
+    /// this method is not propagated at the end of the scope.
+    pub fn join(self) -> Option<T> {
+        self.data.wait_queue.wait_until(|| {
+            self.packet
+                .is_finished
+                .load(Ordering::Acquire)
+                .then_some(())
+        });
+
+        let output = self.packet.output.lock().take();
+        if output.is_none() {
+            self.data.nr_panicked.fetch_sub(1, Ordering::Relaxed);
+        }
+        output
+    }
+
+    /// Returns whether the task has finished.
+    pub fn is_finished(&self) -> bool {
+        self.packet.is_finished.load(Ordering::Acquire)
+    }
+}
+
+struct ScopeData {
+    nr_running: AtomicUsize,
+    /// The number of the panicked tasks that have not been joined.
+    nr_panicked: AtomicUsize,
+    wait_queue: WaitQueue,
+}
+
+impl ScopeData {
+    fn new() -> Self {
+        Self {
+            nr_running: AtomicUsize::new(0),
+            nr_panicked: AtomicUsize::new(0),
+            wait_queue: WaitQueue::new(),
+        }
+    }
+
+    fn finish_one(&self) {
+        self.nr_running.fetch_sub(1, Ordering::Release);
+        self.wait_queue.wake_all();
+    }
+
+    fn wait_all(&self) {
+        self.wait_queue
+            .wait_until(|| (self.nr_running.load(Ordering::Acquire) == 0).then_some(()));
+    }
+}
+
+struct Packet<T> {
+    output: SpinLock<Option<T>>,
+    is_finished: AtomicBool,
+}
+
+#[cfg(ktest)]
+mod test {
+    use super::*;
+
+    #[ktest]
+    fn borrow_local_buffer() {
+        let mut buf = [0u8; 64];
+        let nr_done = AtomicUsize::new(0);
+
+        scope(|s| {
+            for (i, chunk) in buf.chunks_mut(16).enumerate() {
+                let nr_done = &nr_done;
+                s.spawn(move || {
+                    chunk.fill(i as u8 + 1);
+                    nr_done.fetch_add(1, Ordering::Relaxed);
+                });
+            }
+        });
+
+        // All the tasks have completed when the scope returns.
+        assert_eq!(nr_done.load(Ordering::Relaxed), 4);
+        for (i, chunk) in buf.chunks(16).enumerate() {
+            assert!(chunk.iter().all(|byte| *byte == i as u8 + 1));
+        }
+    }
+
+    #[ktest]
+    fn join_returns_output() {
+        let values = [1, 2, 3, 4];
+
+        let sum = scope(|s| {
+            let left = s.spawn(|| values[..2].iter().sum::<i32>());
+            let right = s.spawn(|| values[2..].iter().sum::<i32>());
+            left.join().unwrap() + right.join().unwrap()
+        });
+
+        assert_eq!(sum, 10);
+    }
+}
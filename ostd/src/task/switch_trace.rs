@@ -0,0 +1,252 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Tracing of the context-switch latency.
+//!
+//! When the tracing is enabled, each task switch is timed with the TSC in two
+//! phases:
+//!  - the scheduling phase, from the entry to the scheduler to the point right
+//!    before the registers of the current task are saved;
+//!  - the switching phase, from that point to the entry to the next task, which
+//!    covers the saving and the restoring of the registers.
+//!
+//! The cycles of each phase are accumulated into a per-CPU histogram, which can
+//! be read with [`histogram`]. When the tracing is disabled, the only overhead
+//! on the switch path is the check of a global flag.
+
+use alloc::{boxed::Box, vec::Vec};
+use core::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+use spin::Once;
+
+use crate::{
+    arch::read_tsc,
+    cpu::{num_cpus, this_cpu},
+    cpu_local_cell,
+    trap::disable_local,
+};
+
+/// The number of buckets in a histogram.
+///
+/// The bucket `i` counts the latencies in `[2^i, 2^(i + 1))` cycles, except
+/// that the first bucket also counts zero and the last bucket also counts all
+/// the larger latencies.
+pub const NR_BUCKETS: usize = 32;
+
+/// A snapshot of the context-switch latency histogram of a CPU.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SwitchHistogram {
+    /// The latencies of the scheduling phase.
+    pub schedule: [u64; NR_BUCKETS],
+    /// The latencies of the switching phase.
+    pub switch: [u64; NR_BUCKETS],
+}
+
+impl SwitchHistogram {
+    /// Returns the number of the traced switches.
+    pub fn nr_switches(&self) -> u64 {
+        self.switch.iter().sum()
+    }
+
+    /// Returns whether no switch has been traced.
+    pub fn is_empty(&self) -> bool {
+        self.nr_switches() == 0
+    }
+}
+
+static IS_ENABLED: AtomicBool = AtomicBool::new(false);
+
+static HISTOGRAMS: Once<Box<[CpuHistogram]>> = Once::new();
+
+cpu_local_cell! {
+    /// The TSC value at the entry to the scheduler, or zero if it is not traced.
+    static SCHED_ENTRY_TSC: u64 = 0;
+    /// The TSC value before saving the registers, or zero if it is not traced.
+    static SWITCH_START_TSC: u64 = 0;
+}
+
+#[repr(align(64))]
+struct CpuHistogram {
+    schedule: [AtomicU64; NR_BUCKETS],
+    switch: [AtomicU64; NR_BUCKETS],
+}
+
+impl CpuHistogram {
+    fn new() -> Self {
+        Self {
+            schedule: core::array::from_fn(|_| AtomicU64::new(0)),
+            switch: core::array::from_fn(|_| AtomicU64::new(0)),
+        }
+    }
+
+    fn snapshot(&self) -> SwitchHistogram {
+        let load = |buckets: &[AtomicU64; NR_BUCKETS]| {
+            core::array::from_fn(|i| buckets[i].load(Ordering::Relaxed))
+        };
+        SwitchHistogram {
+            schedule: load(&self.schedule),
+            switch: load(&self.switch),
+        }
+    }
+
+    fn reset(&self) {
+        for bucket in self.schedule.iter().chain(self.switch.iter()) {
+            bucket.store(0, Ordering::Relaxed);
+        }
+    }
+}
+
+fn bucket_of(cycles: u64) -> usize {
+    (cycles.max(1).ilog2() as usize).min(NR_BUCKETS - 1)
+}
+
+/// Enables the tracing.
+pub fn enable() {
+    HISTOGRAMS.call_once(|| {
+        (0..num_cpus())
+            .map(|_| CpuHistogram::new())
+            .collect::<Vec<_>>()
+            .into_boxed_slice()
+    });
+    IS_ENABLED.store(true, Ordering::Release);
+}
+
+/// Disables the tracing.
+///
+/// The histograms are kept, so they can still be read.
+pub fn disable() {
+    IS_ENABLED.store(false, Ordering::Relaxed);
+}
+
+/// Returns whether the tracing is enabled.
+pub fn is_enabled() -> bool {
+    IS_ENABLED.load(Ordering::Relaxed)
+}
+
+/// Returns the histogram of the CPU.
+///
+/// This function returns `None` if the tracing has never been enabled or if
+/// the CPU does not exist.
+pub fn histogram(cpu: u32) -> Option<SwitchHistogram> {
+    let histograms = HISTOGRAMS.get()?;
+    histograms
+        .get(cpu as usize)
+        .map(|histogram| histogram.snapshot())
+}
+
+/// Clears the histograms of all the CPUs.
+pub fn reset() {
+    if let Some(histograms) = HISTOGRAMS.get() {
+        histograms.iter().for_each(CpuHistogram::reset);
+    }
+}
+
+/// Marks the entry to the scheduler.
+///
+/// The local IRQs must be disabled.
+pub(super) fn on_sched_entry() {
+    if !is_enabled() {
+        return;
+    }
+    SCHED_ENTRY_TSC.store(read_tsc());
+}
+
+/// Marks the point right before the registers of the current task are saved.
+///
+/// The local IRQs must be disabled.
+pub(super) fn on_switch_start() {
+    // The switch may not come from the scheduler, or the tracing may be enabled
+    // after the entry to the scheduler. Such switches are not traced.
+    if !is_enabled() || SCHED_ENTRY_TSC.load() == 0 {
+        return;
+    }
+    SWITCH_START_TSC.store(read_tsc());
+}
+
+/// Marks the entry to the next task and records the latencies of the switch.
+///
+/// It is called after the registers of the next task are restored, either
+/// when the next task returns from its last switch or when a new task starts
+/// running for the first time. In the latter case, the switch may come from
+/// the bootstrap context, where there is no previous task, but the latencies
+/// are recorded in the same way.
+pub(super) fn on_task_entry() {
+    if !is_enabled() {
+        return;
+    }
+    let _irq_guard = disable_local();
+
+    let sched_entry = SCHED_ENTRY_TSC.load();
+    let switch_start = SWITCH_START_TSC.load();
+    if sched_entry == 0 {
+        return;
+    }
+    SCHED_ENTRY_TSC.store(0);
+    SWITCH_START_TSC.store(0);
+
+    if switch_start == 0 {
+        return;
+    }
+    let Some(histograms) = HISTOGRAMS.get() else {
+        return;
+    };
+
+    let now = read_tsc();
+    let histogram = &histograms[this_cpu() as usize];
+    let schedule_cycles = switch_start.saturating_sub(sched_entry);
+    let switch_cycles = now.saturating_sub(switch_start);
+    histogram.schedule[bucket_of(schedule_cycles)].fetch_add(1, Ordering::Relaxed);
+    histogram.switch[bucket_of(switch_cycles)].fetch_add(1, Ordering::Relaxed);
+}
+
+#[cfg(ktest)]
+mod test {
+    use super::*;
+    use crate::{
+        prelude::*,
+        task::{Task, TaskOptions},
+    };
+
+    #[ktest]
+    fn trace_switches() {
+        const NR_YIELDS: usize = 16;
+
+        enable();
+        reset();
+
+        let is_done = Arc::new(AtomicBool::new(false));
+        let task = {
+            let is_done = is_done.clone();
+            TaskOptions::new(move || {
+                while !is_done.load(Ordering::Acquire) {
+                    Task::yield_now();
+                }
+            })
+            .data(())
+            .spawn()
+            .unwrap()
+        };
+        for _ in 0..NR_YIELDS {
+            Task::yield_now();
+        }
+        is_done.store(true, Ordering::Release);
+        drop(task);
+        disable();
+
+        let nr_switches: u64 = (0..num_cpus())
+            .filter_map(histogram)
+            .map(|histogram| histogram.nr_switches())
+            .sum();
+        assert!(nr_switches > 0);
+
+        // Each traced switch is recorded in both phases.
+        let cpu_histogram = (0..num_cpus())
+            .filter_map(histogram)
+            .find(|histogram| !histogram.is_empty())
+            .unwrap();
+        assert_eq!(
+            cpu_histogram.schedule.iter().sum::<u64>(),
+            cpu_histogram.nr_switches()
+        );
+        assert!(histogram(num_cpus()).is_none());
+    }
+}
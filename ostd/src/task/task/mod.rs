@@ -4,23 +4,27 @@
 // So we temporary allow missing_docs for this module.
 #![allow(missing_docs)]
 
+mod name;
 mod priority;
 
 use core::{
     any::Any,
     cell::UnsafeCell,
-    sync::atomic::{AtomicU32, Ordering},
+    sync::atomic::{AtomicU16, AtomicU32, AtomicU64, Ordering},
 };
 
 use intrusive_collections::{intrusive_adapter, LinkedListAtomicLink};
+pub use name::{TaskName, TASK_NAME_LEN};
 pub use priority::Priority;
 
-use super::{processor::current_task, scheduler};
+use super::{processor::current_task, scheduler, switch_trace};
 pub(crate) use crate::arch::task::{context_switch, TaskContext};
 use crate::{
+    arch::tsc_freq,
     cpu::CpuSet,
     mm::{kspace::KERNEL_PAGE_TABLE, FrameAllocOptions, Paddr, PageFlags, Segment, PAGE_SIZE},
     prelude::*,
+    sync::SpinLock,
     user::UserSpace,
 };
 
@@ -131,6 +135,19 @@ impl AtomicCpuId {
     pub fn set_to_none(&self) {
         self.0.store(Self::NONE, Ordering::Relaxed);
     }
+
+    /// Sets the inner value of an `AtomicCpuId` unconditionally.
+    pub fn set(&self, cpu_id: u32) {
+        self.0.store(cpu_id, Ordering::Relaxed);
+    }
+
+    /// Gets the inner value of an `AtomicCpuId`.
+    ///
+    /// This method returns `None` if the `AtomicCpuId` is empty.
+    pub fn get(&self) -> Option<u32> {
+        let cpu_id = self.0.load(Ordering::Relaxed);
+        (cpu_id != Self::NONE).then_some(cpu_id)
+    }
 }
 
 impl Default for AtomicCpuId {
@@ -153,7 +170,14 @@ pub struct Task {
     kstack: KernelStack,
     link: LinkedListAtomicLink,
     cpu: AtomicCpuId,
-    priority: Priority,
+    /// The raw value of the [`Priority`].
+    priority: AtomicU16,
+    name: SpinLock<TaskName>,
+    /// The CPU time that the task has spent running, in TSC cycles.
+    cpu_time: AtomicU64,
+    /// The sleeping locks held by the task, for the lock-ordering validation.
+    #[cfg(debug_assertions)]
+    held_locks: crate::sync::TaskHeldLocks,
     // TODO: add multiprocessor support
     #[allow(dead_code)]
     cpu_affinity: CpuSet,
@@ -214,7 +238,61 @@ impl Task {
 
     /// Returns the priority.
     pub fn priority(&self) -> Priority {
-        self.priority
+        Priority::new(self.priority.load(Ordering::Relaxed))
+    }
+
+    /// Sets the priority.
+    ///
+    /// The new priority is seen by the scheduler the next time it inspects the
+    /// task. It is up to the scheduler to move the task if it is in a runqueue.
+    pub fn set_priority(&self, priority: Priority) {
+        self.priority.store(priority.get(), Ordering::Relaxed);
+    }
+
+    /// Returns the name of the task.
+    pub fn name(&self) -> TaskName {
+        *self.name.lock()
+    }
+
+    /// Returns the name of the task, or `None` if the name is being accessed.
+    ///
+    /// Unlike [`Self::name`], this method never spins, so it can be called where
+    /// the lock may be held by the current CPU, e.g., in the panic handler.
+    pub(crate) fn try_name(&self) -> Option<TaskName> {
+        self.name.try_lock().map(|name| *name)
+    }
+
+    /// Sets the name of the task, truncating it if it is too long.
+    ///
+    /// See [`TaskName`] for the maximum length of the name.
+    pub fn set_name(&self, name: &str) {
+        *self.name.lock() = TaskName::new(name);
+    }
+
+    /// Returns the CPU time that the task has spent running, in nanoseconds.
+    ///
+    /// The CPU time is charged to the task on each context switch and on each
+    /// timer tick, so the time since the last tick of a running task is not
+    /// included.
+    pub fn cpu_time(&self) -> u64 {
+        let tsc_freq = tsc_freq();
+        if tsc_freq == 0 {
+            return 0;
+        }
+
+        let cycles = self.cpu_time.load(Ordering::Relaxed);
+        (cycles as u128 * 1_000_000_000 / tsc_freq as u128) as u64
+    }
+
+    /// Returns the sleeping locks held by the task.
+    #[cfg(debug_assertions)]
+    pub(crate) fn held_locks(&self) -> &crate::sync::TaskHeldLocks {
+        &self.held_locks
+    }
+
+    /// Charges the CPU time, in TSC cycles, to the task.
+    pub(super) fn charge_cpu_time(&self, cycles: u64) {
+        self.cpu_time.fetch_add(cycles, Ordering::Relaxed);
     }
 
     /// Exits the current task.
@@ -233,7 +311,7 @@ impl Task {
 
     /// Checks if the task has a real-time priority.
     pub fn is_real_time(&self) -> bool {
-        self.priority.is_real_time()
+        self.priority().is_real_time()
     }
 }
 
@@ -243,6 +321,7 @@ pub struct TaskOptions {
     data: Option<Box<dyn Any + Send + Sync>>,
     user_space: Option<Arc<UserSpace>>,
     priority: Priority,
+    name: TaskName,
     cpu_affinity: CpuSet,
 }
 
@@ -257,6 +336,7 @@ impl TaskOptions {
             data: None,
             user_space: None,
             priority: Priority::normal(),
+            name: TaskName::default(),
             cpu_affinity: CpuSet::new_full(),
         }
     }
@@ -291,6 +371,12 @@ impl TaskOptions {
         self
     }
 
+    /// Sets the name of the task, truncating it if it is too long.
+    pub fn name(mut self, name: &str) -> Self {
+        self.name = TaskName::new(name);
+        self
+    }
+
     /// Sets the CPU affinity mask for the task.
     ///
     /// The `cpu_affinity` parameter represents
@@ -305,6 +391,8 @@ impl TaskOptions {
         /// all task will entering this function
         /// this function is mean to executing the task_fn in Task
         extern "C" fn kernel_task_entry() {
+            switch_trace::on_task_entry();
+
             let current_task = current_task()
                 .expect("no current task, it should have current task in kernel task entry");
             current_task.func.call(());
@@ -319,7 +407,11 @@ impl TaskOptions {
             kstack: KernelStack::new_with_guard_page()?,
             cpu: AtomicCpuId::default(),
             link: LinkedListAtomicLink::new(),
-            priority: self.priority,
+            priority: AtomicU16::new(self.priority.get()),
+            name: SpinLock::new(self.name),
+            cpu_time: AtomicU64::new(0),
+            #[cfg(debug_assertions)]
+            held_locks: crate::sync::TaskHeldLocks::new(),
             cpu_affinity: self.cpu_affinity,
         };
 
@@ -369,4 +461,61 @@ mod test {
         };
         let _ = crate::task::TaskOptions::new(task).data(()).spawn();
     }
+
+    #[ktest]
+    fn task_name() {
+        let task = crate::task::TaskOptions::new(|| {})
+            .data(())
+            .name("worker")
+            .build()
+            .unwrap();
+        assert_eq!(task.name().as_str(), "worker");
+
+        task.set_name("a-very-long-task-name");
+        assert_eq!(task.name().as_str(), "a-very-long-tas");
+
+        // A multi-byte character is not split when truncating.
+        task.set_name("abcdefghijklmn\u{e9}");
+        assert_eq!(task.name().as_str(), "abcdefghijklmn");
+    }
+
+    #[ktest]
+    fn cpu_time_accounting() {
+        use core::sync::atomic::{AtomicU64, Ordering};
+
+        use crate::{
+            arch::{read_tsc, timer::timer_freq, tsc_freq},
+            sync::Waiter,
+        };
+
+        const BUSY_NS: u64 = 20_000_000;
+
+        // The spawned task reports the CPU time accounted to it, which is checked
+        // here so that a failure fails this test.
+        let accounted = Arc::new(AtomicU64::new(0));
+        let (waiter, waker) = Waiter::new_pair();
+
+        let task_accounted = accounted.clone();
+        let task = move || {
+            let current = crate::task::Task::current().unwrap();
+            let start_cpu_time = current.cpu_time();
+
+            let start_tsc = read_tsc();
+            let busy_cycles = tsc_freq() * BUSY_NS / 1_000_000_000;
+            while read_tsc() - start_tsc < busy_cycles {
+                core::hint::spin_loop();
+            }
+
+            task_accounted.store(current.cpu_time() - start_cpu_time, Ordering::Release);
+            waker.wake_up();
+        };
+        crate::task::TaskOptions::new(task).data(()).spawn().unwrap();
+        waiter.wait();
+
+        // The time since the last tick has not been charged yet.
+        let tick_ns = 1_000_000_000 / timer_freq();
+        let accounted = accounted.load(Ordering::Acquire);
+        assert!(accounted + 2 * tick_ns >= BUSY_NS);
+        assert!(accounted <= BUSY_NS + 2 * tick_ns);
+    }
 }
@@ -0,0 +1,49 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use core::fmt;
+
+/// The maximum length of a task name in bytes, including the trailing NUL byte.
+pub const TASK_NAME_LEN: usize = 16;
+
+/// The name of a task.
+///
+/// The name is stored in a fixed-length buffer for debugging purposes. A name
+/// longer than `TASK_NAME_LEN - 1` bytes is truncated.
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+pub struct TaskName {
+    bytes: [u8; TASK_NAME_LEN],
+    len: usize,
+}
+
+impl TaskName {
+    /// Creates a new `TaskName`, truncating `name` if it is too long.
+    pub fn new(name: &str) -> Self {
+        let mut len = name.len().min(TASK_NAME_LEN - 1);
+        while !name.is_char_boundary(len) {
+            len -= 1;
+        }
+
+        let mut bytes = [0; TASK_NAME_LEN];
+        bytes[..len].copy_from_slice(&name.as_bytes()[..len]);
+        Self { bytes, len }
+    }
+
+    /// Returns the name as a string slice.
+    pub fn as_str(&self) -> &str {
+        // SAFETY: The bytes are copied from a string slice and truncated at a
+        // character boundary, so they are valid UTF-8.
+        unsafe { core::str::from_utf8_unchecked(&self.bytes[..self.len]) }
+    }
+}
+
+impl fmt::Display for TaskName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl fmt::Debug for TaskName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self.as_str(), f)
+    }
+}
@@ -0,0 +1,180 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Inspection of the registers saved on CPU exceptions.
+//!
+//! This is for the tools that report the state of the CPU, such as panic handlers
+//! and in-kernel debuggers. Along with the backtrace, the registers give the full
+//! picture of where and why the execution is interrupted.
+
+use core::fmt;
+
+use spin::Once;
+use trapframe::TrapFrame;
+
+use crate::cpu::this_cpu;
+
+/// A read-only view of the registers saved when a CPU exception occurs.
+pub struct ExceptionFrame<'a> {
+    frame: &'a TrapFrame,
+    cpu: u32,
+}
+
+impl<'a> ExceptionFrame<'a> {
+    /// Creates a view of `frame`, which is saved on the current CPU.
+    pub(crate) fn new(frame: &'a TrapFrame) -> Self {
+        Self {
+            frame,
+            cpu: this_cpu(),
+        }
+    }
+
+    /// Returns the ID of the CPU on which the exception occurs.
+    pub fn cpu(&self) -> u32 {
+        self.cpu
+    }
+
+    /// Returns the vector number of the exception.
+    pub fn trap_num(&self) -> usize {
+        self.frame.trap_num
+    }
+
+    /// Returns the error code pushed by the CPU, or zero if the exception has none.
+    pub fn error_code(&self) -> usize {
+        self.frame.error_code
+    }
+
+    /// Returns the saved instruction pointer (RIP).
+    ///
+    /// For faults, it is the address of the faulting instruction. For traps,
+    /// e.g., breakpoints, it is the address of the instruction that follows
+    /// the trapping one.
+    pub fn instruction_pointer(&self) -> usize {
+        self.frame.rip
+    }
+
+    /// Returns the saved stack pointer (RSP).
+    pub fn stack_pointer(&self) -> usize {
+        self.frame.rsp
+    }
+
+    /// Returns the saved flags register (RFLAGS).
+    pub fn rflags(&self) -> usize {
+        self.frame.rflags
+    }
+
+    /// Returns whether the exception occurs in the user mode.
+    ///
+    /// It is determined by the privilege level of the saved code segment selector.
+    pub fn is_from_user(&self) -> bool {
+        self.frame.cs & 0b11 == 3
+    }
+
+    /// Returns all the saved registers, including the general-purpose ones.
+    pub fn trap_frame(&self) -> &TrapFrame {
+        self.frame
+    }
+}
+
+impl fmt::Debug for ExceptionFrame<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let frame = self.frame;
+        writeln!(
+            f,
+            "exception {} on CPU {} in {} mode, error code {:#x}",
+            frame.trap_num,
+            self.cpu,
+            if self.is_from_user() {
+                "user"
+            } else {
+                "kernel"
+            },
+            frame.error_code
+        )?;
+        writeln!(
+            f,
+            "RIP {:#018x} RSP {:#018x} RFLAGS {:#018x} CS {:#x}",
+            frame.rip, frame.rsp, frame.rflags, frame.cs
+        )?;
+        writeln!(
+            f,
+            "RAX {:#018x} RBX {:#018x} RCX {:#018x} RDX {:#018x}",
+            frame.rax, frame.rbx, frame.rcx, frame.rdx
+        )?;
+        writeln!(
+            f,
+            "RSI {:#018x} RDI {:#018x} RBP {:#018x} R8  {:#018x}",
+            frame.rsi, frame.rdi, frame.rbp, frame.r8
+        )?;
+        writeln!(
+            f,
+            "R9  {:#018x} R10 {:#018x} R11 {:#018x} R12 {:#018x}",
+            frame.r9, frame.r10, frame.r11, frame.r12
+        )?;
+        write!(
+            f,
+            "R13 {:#018x} R14 {:#018x} R15 {:#018x}",
+            frame.r13, frame.r14, frame.r15
+        )
+    }
+}
+
+static KERNEL_EXCEPTION_HOOK: Once<fn(&ExceptionFrame) -> bool> = Once::new();
+
+/// Injects a hook for the kernel-mode CPU exceptions that OSTD does not handle.
+///
+/// The hook is called before OSTD panics on such an exception. If the hook returns
+/// `true`, the exception is considered handled and the execution resumes at the saved
+/// instruction pointer. So it should only claim the traps (e.g., breakpoints), after
+/// which the execution can continue.
+///
+/// This function only takes effect when it is called for the first time.
+pub fn inject_kernel_exception_hook(hook: fn(&ExceptionFrame) -> bool) {
+    KERNEL_EXCEPTION_HOOK.call_once(|| hook);
+}
+
+/// Calls the hook injected by [`inject_kernel_exception_hook`] and returns whether
+/// the exception is handled.
+pub(crate) fn call_kernel_exception_hook(frame: &ExceptionFrame) -> bool {
+    KERNEL_EXCEPTION_HOOK.get().is_some_and(|hook| hook(frame))
+}
+
+#[cfg(ktest)]
+mod test {
+    use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+    use super::*;
+    use crate::{cpu::BREAKPOINT, prelude::*};
+
+    static BREAKPOINT_RIP: AtomicUsize = AtomicUsize::new(0);
+    static BREAKPOINT_CPU: AtomicUsize = AtomicUsize::new(usize::MAX);
+    static IS_FROM_USER: AtomicBool = AtomicBool::new(true);
+
+    fn record_breakpoint(frame: &ExceptionFrame) -> bool {
+        if frame.trap_num() != BREAKPOINT.number as usize {
+            return false;
+        }
+        BREAKPOINT_RIP.store(frame.instruction_pointer(), Ordering::Relaxed);
+        BREAKPOINT_CPU.store(frame.cpu() as usize, Ordering::Relaxed);
+        IS_FROM_USER.store(frame.is_from_user(), Ordering::Relaxed);
+        true
+    }
+
+    #[ktest]
+    fn inspect_breakpoint_frame() {
+        inject_kernel_exception_hook(record_breakpoint);
+
+        let irq_guard = crate::trap::disable_local();
+        let expected_rip: usize;
+        // SAFETY: The breakpoint is handled by the hook, after which the execution
+        // continues with the next instruction.
+        unsafe {
+            core::arch::asm!("int3", "2:", "lea {}, [rip + 2b]", out(reg) expected_rip);
+        }
+
+        // The breakpoint is a trap, so the saved RIP points to the next instruction.
+        assert_eq!(BREAKPOINT_RIP.load(Ordering::Relaxed), expected_rip);
+        assert_eq!(BREAKPOINT_CPU.load(Ordering::Relaxed), this_cpu() as usize);
+        assert!(!IS_FROM_USER.load(Ordering::Relaxed));
+        drop(irq_guard);
+    }
+}
@@ -8,6 +8,7 @@ use trapframe::TrapFrame;
 
 use crate::{
     arch::irq::{self, IrqCallbackHandle, IRQ_ALLOCATOR},
+    cpu_local_cell,
     prelude::*,
     Error,
 };
@@ -133,7 +134,7 @@ pub fn disable_local() -> DisabledLocalIrqGuard {
 #[clippy::has_significant_drop]
 #[must_use]
 pub struct DisabledLocalIrqGuard {
-    was_enabled: bool,
+    _private: (),
 }
 
 impl !Send for DisabledLocalIrqGuard {}
@@ -144,22 +145,113 @@ impl DisabledLocalIrqGuard {
         if was_enabled {
             irq::disable_local();
         }
-        Self { was_enabled }
+
+        // Only the outermost guard records whether local IRQs should be enabled again. Inner
+        // guards, including those created in IRQ handlers, always see local IRQs disabled.
+        if IRQ_DISABLE_DEPTH.load() == 0 {
+            WAS_ENABLED.store(was_enabled);
+        } else {
+            debug_assert!(!was_enabled);
+        }
+        IRQ_DISABLE_DEPTH.add_assign(1);
+
+        Self { _private: () }
     }
 
     /// Transfers the saved IRQ status of this guard to a new guard.
-    /// The saved IRQ status of this guard is cleared.
+    /// This guard must be dropped after this function.
     pub fn transfer_to(&mut self) -> Self {
-        let was_enabled = self.was_enabled;
-        self.was_enabled = false;
-        Self { was_enabled }
+        IRQ_DISABLE_DEPTH.add_assign(1);
+        Self { _private: () }
+    }
+
+    /// Releases this guard, enables local IRQs and halts the CPU until the next IRQ arrives.
+    ///
+    /// Unlike dropping the guard and then halting, no IRQ can be handled in between, so an
+    /// IRQ raised while the guard is held is guaranteed to wake up the CPU.
+    ///
+    /// The CPU is halted only if this is the outermost guard and local IRQs were enabled
+    /// before the guard was created. Otherwise, local IRQs stay disabled after the guard is
+    /// released, so no IRQ could wake up the CPU. In that case, this method only releases the
+    /// guard and returns at once, which leaves it to the caller to check again.
+    ///
+    /// Returns whether the CPU has been halted.
+    pub(crate) fn enable_and_halt(self) -> bool {
+        if IRQ_DISABLE_DEPTH.load() != 1 || !WAS_ENABLED.load() {
+            drop(self);
+            return false;
+        }
+
+        core::mem::forget(self);
+        IRQ_DISABLE_DEPTH.store(0);
+        irq::enable_local_and_halt();
+        true
     }
 }
 
 impl Drop for DisabledLocalIrqGuard {
     fn drop(&mut self) {
-        if self.was_enabled {
+        debug_assert!(
+            !irq::is_local_enabled(),
+            "local IRQs are enabled while a `DisabledLocalIrqGuard` is held"
+        );
+
+        let depth = IRQ_DISABLE_DEPTH.load();
+        debug_assert!(depth > 0);
+        IRQ_DISABLE_DEPTH.store(depth - 1);
+
+        if depth == 1 && WAS_ENABLED.load() {
             irq::enable_local();
         }
     }
 }
+
+/// Returns the number of [`DisabledLocalIrqGuard`]s held on the current CPU.
+pub(crate) fn irq_disable_depth() -> u32 {
+    IRQ_DISABLE_DEPTH.load()
+}
+
+cpu_local_cell! {
+    static IRQ_DISABLE_DEPTH: u32 = 0;
+    static WAS_ENABLED: bool = false;
+}
+
+#[cfg(ktest)]
+mod test {
+    use super::*;
+    use crate::prelude::*;
+
+    #[ktest]
+    fn nested_guards() {
+        let was_enabled = irq::is_local_enabled();
+
+        let outer_guard = disable_local();
+        assert!(!irq::is_local_enabled());
+        assert_eq!(irq_disable_depth(), 1);
+
+        let inner_guard = disable_local();
+        assert_eq!(irq_disable_depth(), 2);
+        drop(inner_guard);
+
+        // Dropping the inner guard does not enable local IRQs.
+        assert!(!irq::is_local_enabled());
+        assert_eq!(irq_disable_depth(), 1);
+
+        drop(outer_guard);
+        assert_eq!(irq::is_local_enabled(), was_enabled);
+        assert_eq!(irq_disable_depth(), 0);
+    }
+
+    #[ktest]
+    fn out_of_order_drop() {
+        let was_enabled = irq::is_local_enabled();
+
+        let mut first_guard = disable_local();
+        let second_guard = first_guard.transfer_to();
+        drop(first_guard);
+        assert!(!irq::is_local_enabled());
+
+        drop(second_guard);
+        assert_eq!(irq::is_local_enabled(), was_enabled);
+    }
+}
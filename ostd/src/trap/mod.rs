@@ -2,20 +2,25 @@
 
 //! Handles trap across kernel and user space.
 
+mod frame;
 mod handler;
 mod irq;
 pub mod softirq;
 
+pub use frame::{inject_kernel_exception_hook, ExceptionFrame};
 pub use handler::in_interrupt_context;
 pub use softirq::SoftIrqLine;
 pub use trapframe::TrapFrame;
 
-pub(crate) use self::handler::call_irq_callback_functions;
 pub use self::irq::{disable_local, DisabledLocalIrqGuard, IrqCallbackFunction, IrqLine};
+pub(crate) use self::{
+    frame::call_kernel_exception_hook, handler::call_irq_callback_functions, irq::irq_disable_depth,
+};
 
 pub(crate) fn init() {
     unsafe {
         trapframe::init();
+        crate::arch::ist::init();
     }
     softirq::init();
 }
@@ -0,0 +1,137 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include <sys/syscall.h>
+#include <sys/time.h>
+#include <time.h>
+#include <unistd.h>
+
+#include "../network/test.h"
+
+#define NSEC_PER_SEC 1000000000L
+
+static long long to_ns(const struct timespec *ts)
+{
+	return (long long)ts->tv_sec * NSEC_PER_SEC + ts->tv_nsec;
+}
+
+static int is_normalized(const struct timespec *ts)
+{
+	return ts->tv_sec >= 0 && ts->tv_nsec >= 0 && ts->tv_nsec < NSEC_PER_SEC;
+}
+
+FN_TEST(monotonic)
+{
+	struct timespec ts1, ts2;
+
+	TEST_RES(clock_gettime(CLOCK_MONOTONIC, &ts1), is_normalized(&ts1));
+	TEST_RES(clock_gettime(CLOCK_MONOTONIC, &ts2),
+		 is_normalized(&ts2) && to_ns(&ts2) >= to_ns(&ts1));
+
+	usleep(10 * 1000);
+	TEST_RES(clock_gettime(CLOCK_MONOTONIC, &ts1),
+		 to_ns(&ts1) >= to_ns(&ts2) + 10 * 1000 * 1000);
+}
+END_TEST()
+
+FN_TEST(process_cputime)
+{
+	struct timespec start, ts1, ts2;
+	volatile int counter = 0;
+
+	TEST_RES(clock_gettime(CLOCK_PROCESS_CPUTIME_ID, &ts1),
+		 is_normalized(&ts1));
+
+	// Busy work for about 50 milliseconds.
+	CHECK(clock_gettime(CLOCK_MONOTONIC, &start));
+	do {
+		for (int i = 0; i < 10000; ++i)
+			++counter;
+		CHECK(clock_gettime(CLOCK_MONOTONIC, &ts2));
+	} while (to_ns(&ts2) - to_ns(&start) < 50 * 1000 * 1000);
+
+	TEST_RES(clock_gettime(CLOCK_PROCESS_CPUTIME_ID, &ts2),
+		 is_normalized(&ts2) && to_ns(&ts2) > to_ns(&ts1));
+}
+END_TEST()
+
+FN_TEST(invalid_clock)
+{
+	struct timespec ts;
+
+	TEST_ERRNO(clock_gettime(100, &ts), EINVAL);
+	// Bits 2, 1, and 0 are all set in an invalid dynamic clock ID.
+	TEST_ERRNO(clock_gettime(-1, &ts), EINVAL);
+	TEST_ERRNO(clock_settime(CLOCK_MONOTONIC, &ts), EINVAL);
+}
+END_TEST()
+
+FN_TEST(set_realtime)
+{
+	struct timespec real, mono1, mono2, ts;
+
+	TEST_SUCC(clock_gettime(CLOCK_MONOTONIC, &mono1));
+	TEST_SUCC(clock_gettime(CLOCK_REALTIME, &real));
+
+	ts = (struct timespec){ .tv_sec = real.tv_sec, .tv_nsec = NSEC_PER_SEC };
+	TEST_ERRNO(clock_settime(CLOCK_REALTIME, &ts), EINVAL);
+
+	// Move the real time one hour forward.
+	ts = (struct timespec){ .tv_sec = real.tv_sec + 3600,
+				.tv_nsec = real.tv_nsec };
+	TEST_SUCC(clock_settime(CLOCK_REALTIME, &ts));
+	TEST_RES(clock_gettime(CLOCK_REALTIME, &ts),
+		 ts.tv_sec >= real.tv_sec + 3600 &&
+			 ts.tv_sec < real.tv_sec + 3600 + 10);
+
+	// The monotonic time is not affected.
+	TEST_RES(clock_gettime(CLOCK_MONOTONIC, &mono2),
+		 to_ns(&mono2) >= to_ns(&mono1) &&
+			 to_ns(&mono2) < to_ns(&mono1) + 10 * NSEC_PER_SEC);
+
+	// Restore the real time.
+	TEST_SUCC(clock_gettime(CLOCK_REALTIME, &ts));
+	ts.tv_sec -= 3600;
+	TEST_SUCC(clock_settime(CLOCK_REALTIME, &ts));
+}
+END_TEST()
+
+static int is_near(long long ns1, long long ns2)
+{
+	return ns1 - ns2 < NSEC_PER_SEC && ns2 - ns1 < NSEC_PER_SEC;
+}
+
+FN_TEST(set_realtime_then_read_from_libc)
+{
+	struct timespec real, ts, raw;
+	struct timeval tv;
+
+	TEST_SUCC(clock_gettime(CLOCK_REALTIME, &real));
+
+	// Move the real time one hour forward.
+	ts = (struct timespec){ .tv_sec = real.tv_sec + 3600,
+				.tv_nsec = real.tv_nsec };
+	TEST_SUCC(clock_settime(CLOCK_REALTIME, &ts));
+
+	// The libc functions may read the time from the vDSO without a syscall.
+	// They should see the new time, just like the syscall does.
+	TEST_SUCC(syscall(SYS_clock_gettime, CLOCK_REALTIME, &raw));
+	TEST_RES(clock_gettime(CLOCK_REALTIME, &ts),
+		 is_near(to_ns(&ts), to_ns(&raw)));
+	TEST_RES(clock_gettime(CLOCK_REALTIME_COARSE, &ts),
+		 is_near(to_ns(&ts), to_ns(&raw)));
+	TEST_RES(gettimeofday(&tv, NULL),
+		 is_near((long long)tv.tv_sec * NSEC_PER_SEC +
+				 tv.tv_usec * 1000,
+			 to_ns(&raw)));
+
+	// Restore the real time.
+	TEST_SUCC(clock_gettime(CLOCK_REALTIME, &ts));
+	ts.tv_sec -= 3600;
+	TEST_SUCC(clock_settime(CLOCK_REALTIME, &ts));
+	TEST_RES(clock_gettime(CLOCK_REALTIME, &ts),
+		 to_ns(&ts) >= to_ns(&real) &&
+			 to_ns(&ts) < to_ns(&real) + 10 * NSEC_PER_SEC);
+}
+END_TEST()
@@ -0,0 +1,130 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include <fcntl.h>
+#include <sys/epoll.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+#include "../network/test.h"
+
+static int epfd;
+static int et_pipe[2];
+static int lt_pipe[2];
+
+#define ET_DATA 1
+#define LT_DATA 2
+
+FN_SETUP(epoll)
+{
+	struct epoll_event ev;
+
+	epfd = CHECK(epoll_create1(EPOLL_CLOEXEC));
+	CHECK(pipe2(et_pipe, O_NONBLOCK));
+	CHECK(pipe2(lt_pipe, O_NONBLOCK));
+
+	ev.events = EPOLLIN | EPOLLET;
+	ev.data.u64 = ET_DATA;
+	CHECK(epoll_ctl(epfd, EPOLL_CTL_ADD, et_pipe[0], &ev));
+
+	ev.events = EPOLLIN;
+	ev.data.u64 = LT_DATA;
+	CHECK(epoll_ctl(epfd, EPOLL_CTL_ADD, lt_pipe[0], &ev));
+}
+END_SETUP()
+
+static struct epoll_event events[4];
+
+// Returns a bitmask of the data of the reported events.
+static int wait_events(int timeout)
+{
+	int i, nr, mask = 0;
+
+	nr = epoll_wait(epfd, events, 4, timeout);
+	if (nr < 0)
+		return nr;
+
+	for (i = 0; i < nr; i++) {
+		if (events[i].events != EPOLLIN)
+			return -1;
+		mask |= events[i].data.u64;
+	}
+	return mask;
+}
+
+FN_TEST(edge_and_level)
+{
+	TEST_RES(wait_events(0), _ret == 0);
+
+	TEST_RES(write(et_pipe[1], "a", 1), _ret == 1);
+	TEST_RES(write(lt_pipe[1], "a", 1), _ret == 1);
+	TEST_RES(wait_events(0), _ret == (ET_DATA | LT_DATA));
+
+	// The data is not consumed. Only the level-triggered fd is reported again.
+	TEST_RES(wait_events(0), _ret == LT_DATA);
+	TEST_RES(wait_events(0), _ret == LT_DATA);
+
+	// New data is a new edge.
+	TEST_RES(write(et_pipe[1], "b", 1), _ret == 1);
+	TEST_RES(wait_events(0), _ret == (ET_DATA | LT_DATA));
+	TEST_RES(wait_events(0), _ret == LT_DATA);
+
+	TEST_RES(read(et_pipe[0], (char[2]){}, 2), _ret == 2);
+	TEST_RES(read(lt_pipe[0], (char[1]){}, 1), _ret == 1);
+	TEST_RES(wait_events(0), _ret == 0);
+}
+END_TEST()
+
+FN_TEST(oneshot)
+{
+	struct epoll_event ev = { .events = EPOLLIN | EPOLLONESHOT,
+				  .data.u64 = LT_DATA };
+
+	TEST_SUCC(epoll_ctl(epfd, EPOLL_CTL_MOD, lt_pipe[0], &ev));
+
+	TEST_RES(write(lt_pipe[1], "a", 1), _ret == 1);
+	TEST_RES(wait_events(0), _ret == LT_DATA);
+
+	// The fd is disarmed, but it is still in the interest list.
+	TEST_RES(wait_events(0), _ret == 0);
+	TEST_RES(write(lt_pipe[1], "b", 1), _ret == 1);
+	TEST_RES(wait_events(0), _ret == 0);
+	TEST_ERRNO(epoll_ctl(epfd, EPOLL_CTL_ADD, lt_pipe[0], &ev), EEXIST);
+
+	// Re-arming the fd reports the pending data.
+	TEST_SUCC(epoll_ctl(epfd, EPOLL_CTL_MOD, lt_pipe[0], &ev));
+	TEST_RES(wait_events(0), _ret == LT_DATA);
+	TEST_RES(wait_events(0), _ret == 0);
+
+	TEST_RES(read(lt_pipe[0], (char[2]){}, 2), _ret == 2);
+}
+END_TEST()
+
+FN_TEST(modify_while_waiting)
+{
+	struct epoll_event ev = { .events = 0, .data.u64 = LT_DATA };
+	int pid, status;
+
+	// The fd is readable, but the interest does not include EPOLLIN.
+	TEST_SUCC(epoll_ctl(epfd, EPOLL_CTL_MOD, lt_pipe[0], &ev));
+	TEST_RES(write(lt_pipe[1], "a", 1), _ret == 1);
+	TEST_RES(wait_events(0), _ret == 0);
+
+	pid = TEST_SUCC(fork());
+	if (pid == 0) {
+		// The child shares the epoll instance with the parent, and adds
+		// EPOLLIN to the interest while the parent is waiting.
+		usleep(100 * 1000);
+		ev.events = EPOLLIN;
+		exit(epoll_ctl(epfd, EPOLL_CTL_MOD, lt_pipe[0], &ev) < 0);
+	}
+
+	TEST_RES(wait_events(5000), _ret == LT_DATA);
+	TEST_RES(wait(&status), _ret == pid && WIFEXITED(status) &&
+					WEXITSTATUS(status) == 0);
+
+	TEST_RES(read(lt_pipe[0], (char[1]){}, 1), _ret == 1);
+	TEST_RES(wait_events(0), _ret == 0);
+}
+END_TEST()
@@ -0,0 +1,119 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include <poll.h>
+#include <stdint.h>
+#include <sys/eventfd.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+#include "../network/test.h"
+
+static int write_val(int fd, uint64_t val)
+{
+	return write(fd, &val, sizeof(val));
+}
+
+static uint64_t read_val(int fd)
+{
+	uint64_t val;
+
+	if (read(fd, &val, sizeof(val)) != sizeof(val))
+		return -1;
+	return val;
+}
+
+FN_TEST(counter_mode)
+{
+	int fd;
+	uint64_t val;
+	struct pollfd pfd = { .events = POLLIN | POLLOUT };
+
+	fd = TEST_SUCC(eventfd(1, EFD_NONBLOCK));
+	pfd.fd = fd;
+
+	TEST_RES(write_val(fd, 2), _ret == sizeof(uint64_t));
+	TEST_RES(write_val(fd, 3), _ret == sizeof(uint64_t));
+	TEST_RES(poll(&pfd, 1, 0),
+		 _ret == 1 && pfd.revents == (POLLIN | POLLOUT));
+
+	// The whole counter is read and reset.
+	TEST_RES(read_val(fd), _ret == 6);
+	TEST_ERRNO(read(fd, &val, sizeof(val)), EAGAIN);
+	TEST_RES(poll(&pfd, 1, 0), _ret == 1 && pfd.revents == POLLOUT);
+
+	TEST_ERRNO(read(fd, &val, sizeof(val) - 1), EINVAL);
+	TEST_ERRNO(write(fd, &val, sizeof(val) - 1), EINVAL);
+
+	TEST_SUCC(close(fd));
+}
+END_TEST()
+
+FN_TEST(semaphore_mode)
+{
+	int fd;
+	uint64_t val;
+
+	fd = TEST_SUCC(eventfd(2, EFD_NONBLOCK | EFD_SEMAPHORE));
+
+	TEST_RES(write_val(fd, 1), _ret == sizeof(uint64_t));
+
+	// Each read decrements the counter by one.
+	TEST_RES(read_val(fd), _ret == 1);
+	TEST_RES(read_val(fd), _ret == 1);
+	TEST_RES(read_val(fd), _ret == 1);
+	TEST_ERRNO(read(fd, &val, sizeof(val)), EAGAIN);
+
+	TEST_SUCC(close(fd));
+}
+END_TEST()
+
+FN_TEST(overflow)
+{
+	int fd;
+	struct pollfd pfd = { .events = POLLIN | POLLOUT };
+
+	fd = TEST_SUCC(eventfd(0, EFD_NONBLOCK));
+	pfd.fd = fd;
+
+	TEST_ERRNO(write_val(fd, UINT64_MAX), EINVAL);
+
+	TEST_RES(write_val(fd, UINT64_MAX - 1), _ret == sizeof(uint64_t));
+	TEST_RES(poll(&pfd, 1, 0), _ret == 1 && pfd.revents == POLLIN);
+	TEST_ERRNO(write_val(fd, 1), EAGAIN);
+
+	TEST_RES(read_val(fd), _ret == UINT64_MAX - 1);
+	TEST_RES(write_val(fd, 1), _ret == sizeof(uint64_t));
+
+	TEST_SUCC(close(fd));
+}
+END_TEST()
+
+FN_TEST(blocking_write_waits_for_read)
+{
+	int fd, pid, status;
+
+	fd = TEST_SUCC(eventfd(0, 0));
+	TEST_RES(write_val(fd, UINT64_MAX - 1), _ret == sizeof(uint64_t));
+
+	pid = fork();
+	if (pid == 0) {
+		if (write_val(fd, 5) != sizeof(uint64_t))
+			_exit(1);
+		_exit(0);
+	}
+	TEST_SUCC(pid);
+
+	// The child is blocked until the counter is read.
+	TEST_SUCC(usleep(100 * 1000));
+	TEST_RES(waitpid(pid, &status, WNOHANG), _ret == 0);
+
+	TEST_RES(read_val(fd), _ret == UINT64_MAX - 1);
+	TEST_RES(waitpid(pid, &status, 0),
+		 _ret == pid && WIFEXITED(status) && WEXITSTATUS(status) == 0);
+	TEST_RES(read_val(fd), _ret == 5);
+
+	TEST_SUCC(close(fd));
+}
+END_TEST()
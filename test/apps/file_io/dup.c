@@ -0,0 +1,97 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include <fcntl.h>
+#include <unistd.h>
+
+#include "../network/test.h"
+
+#define FILE_NAME "/tmp/test_dup.txt"
+
+static int fd;
+
+FN_SETUP(open)
+{
+	fd = CHECK(open(FILE_NAME, O_RDWR | O_CREAT | O_TRUNC, 0666));
+	CHECK_WITH(write(fd, "0123456789", 10), _ret == 10);
+	CHECK(lseek(fd, 0, SEEK_SET));
+}
+END_SETUP()
+
+FN_TEST(dup_shares_offset)
+{
+	int fd2;
+	char buf[4];
+
+	fd2 = TEST_RES(dup(fd), _ret > fd);
+	TEST_RES(fcntl(fd2, F_GETFD), _ret == 0);
+
+	TEST_RES(read(fd, buf, 4), _ret == 4);
+	TEST_RES(lseek(fd2, 0, SEEK_CUR), _ret == 4);
+	TEST_RES(read(fd2, buf, 4), _ret == 4 && buf[0] == '4');
+	TEST_RES(lseek(fd, 0, SEEK_CUR), _ret == 8);
+
+	TEST_SUCC(close(fd2));
+	TEST_ERRNO(dup(fd2), EBADF);
+}
+END_TEST()
+
+FN_TEST(dup2_replaces_target)
+{
+	int fd2;
+
+	fd2 = TEST_SUCC(open(FILE_NAME, O_RDONLY));
+	TEST_RES(lseek(fd2, 0, SEEK_CUR), _ret == 0);
+
+	TEST_RES(dup2(fd, fd2), _ret == fd2);
+	TEST_RES(lseek(fd2, 0, SEEK_CUR), _ret == 8);
+
+	// Duplicating an fd onto itself does nothing.
+	TEST_RES(dup2(fd2, fd2), _ret == fd2);
+	TEST_RES(lseek(fd2, 0, SEEK_CUR), _ret == 8);
+
+	TEST_SUCC(close(fd2));
+	TEST_ERRNO(dup2(fd2, fd2), EBADF);
+}
+END_TEST()
+
+FN_TEST(dup2_bad_fd_keeps_target)
+{
+	int fd3;
+
+	fd3 = TEST_SUCC(dup(fd));
+	TEST_ERRNO(dup2(fd3 + 1, fd3), EBADF);
+	TEST_RES(fcntl(fd3, F_GETFD), _ret == 0);
+
+	TEST_SUCC(close(fd3));
+}
+END_TEST()
+
+FN_TEST(dup3_cloexec)
+{
+	int fd2;
+
+	fd2 = TEST_SUCC(dup(fd));
+	TEST_SUCC(close(fd2));
+
+	TEST_RES(dup3(fd, fd2, O_CLOEXEC), _ret == fd2);
+	TEST_RES(fcntl(fd2, F_GETFD), _ret == FD_CLOEXEC);
+	TEST_RES(fcntl(fd, F_GETFD), _ret == 0);
+
+	TEST_RES(dup3(fd, fd2, 0), _ret == fd2);
+	TEST_RES(fcntl(fd2, F_GETFD), _ret == 0);
+
+	TEST_ERRNO(dup3(fd, fd, 0), EINVAL);
+	TEST_ERRNO(dup3(fd, fd2, O_NONBLOCK), EINVAL);
+
+	TEST_SUCC(close(fd2));
+}
+END_TEST()
+
+FN_SETUP(cleanup)
+{
+	CHECK(close(fd));
+	CHECK(unlink(FILE_NAME));
+}
+END_SETUP()
@@ -13,6 +13,8 @@
 #define BUFFER_SIZE (4 * KB)
 #define FILE_SIZE (256 * MB)
 #define NUM_OF_CALLS 1000000
+#define LARGE_BUFFER_SIZE (1 * MB)
+#define NUM_OF_LARGE_CALLS 1000
 
 int fill_file(int fd)
 {
@@ -88,6 +90,72 @@ int sequential_write(int fd)
 	return perform_sequential_io(fd, write, "write");
 }
 
+// `pwrite` consumes the user buffer directly, while `write` copies it to a
+// kernel buffer first. Compare them with large buffers, where the extra copy
+// matters the most.
+int compare_large_writes(int fd)
+{
+	struct timespec start, end;
+	char *buffer;
+	ssize_t ret;
+	off_t offset;
+	long write_nanoseconds, pwrite_nanoseconds;
+
+	buffer = malloc(LARGE_BUFFER_SIZE);
+	if (buffer == NULL) {
+		fprintf(stderr, "Failed to allocate the buffer.\n");
+		return -1;
+	}
+	memset(buffer, 0, LARGE_BUFFER_SIZE);
+
+	offset = lseek(fd, 0, SEEK_SET);
+	clock_gettime(CLOCK_MONOTONIC, &start);
+	for (int i = 0; i < NUM_OF_LARGE_CALLS; i++) {
+		if (offset >= FILE_SIZE) {
+			offset = lseek(fd, 0, SEEK_SET);
+		}
+		ret = write(fd, buffer, LARGE_BUFFER_SIZE);
+		if (ret == -1) {
+			fprintf(stderr, "Failed to write the file.\n");
+			goto err;
+		}
+		offset += ret;
+	}
+	clock_gettime(CLOCK_MONOTONIC, &end);
+	write_nanoseconds = calc_duration(&start, &end);
+
+	offset = 0;
+	clock_gettime(CLOCK_MONOTONIC, &start);
+	for (int i = 0; i < NUM_OF_LARGE_CALLS; i++) {
+		if (offset >= FILE_SIZE) {
+			offset = 0;
+		}
+		ret = pwrite(fd, buffer, LARGE_BUFFER_SIZE, offset);
+		if (ret == -1) {
+			fprintf(stderr, "Failed to pwrite the file.\n");
+			goto err;
+		}
+		offset += ret;
+	}
+	clock_gettime(CLOCK_MONOTONIC, &end);
+	pwrite_nanoseconds = calc_duration(&start, &end);
+
+	printf("Executed the large write and pwrite (buffer size: %dMB, file size: %dMB) syscalls %d times each.\n",
+	       LARGE_BUFFER_SIZE / MB, FILE_SIZE / MB, NUM_OF_LARGE_CALLS);
+	printf("write throughput: %.2f MB/s, pwrite throughput: %.2f MB/s\n",
+	       (double)LARGE_BUFFER_SIZE * NUM_OF_LARGE_CALLS /
+		       ((double)write_nanoseconds / 1e9) / MB,
+	       (double)LARGE_BUFFER_SIZE * NUM_OF_LARGE_CALLS /
+		       ((double)pwrite_nanoseconds / 1e9) / MB);
+
+	free(buffer);
+	return 0;
+
+err:
+	free(buffer);
+	return -1;
+}
+
 int main(int argc, char *argv[])
 {
 	if (argc < 2) {
@@ -126,6 +194,13 @@ int main(int argc, char *argv[])
 		return -1;
 	}
 
+	if (compare_large_writes(fd) < 0) {
+		fprintf(stderr,
+			"Failed to compare large writes on the file: %s.\n",
+			argv[1]);
+		return -1;
+	}
+
 	// TODO: Add more test cases such as random read and random write.
 
 	close(fd);
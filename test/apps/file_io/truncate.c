@@ -0,0 +1,105 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include <fcntl.h>
+#include <sys/stat.h>
+#include <unistd.h>
+
+#include "../network/test.h"
+
+#define FILE_NAME "/truncate_test.txt"
+#define PAGE_SIZE 4096
+
+static int fd;
+static char buf[2 * PAGE_SIZE];
+
+static int is_zeros(const char *data, size_t len)
+{
+	for (size_t i = 0; i < len; i++)
+		if (data[i] != 0)
+			return 0;
+	return 1;
+}
+
+FN_SETUP(create)
+{
+	fd = CHECK(open(FILE_NAME, O_RDWR | O_CREAT | O_TRUNC, 0666));
+	memset(buf, 'a', sizeof(buf));
+	CHECK_WITH(write(fd, buf, sizeof(buf)), _ret == sizeof(buf));
+}
+END_SETUP()
+
+FN_TEST(invalid_length)
+{
+	TEST_ERRNO(ftruncate(fd, -1), EINVAL);
+	TEST_ERRNO(truncate(FILE_NAME, -1), EINVAL);
+}
+END_TEST()
+
+FN_TEST(same_size)
+{
+	struct stat st;
+
+	TEST_SUCC(ftruncate(fd, sizeof(buf)));
+	TEST_RES(fstat(fd, &st), st.st_size == sizeof(buf));
+	TEST_RES(pread(fd, buf, sizeof(buf), 0),
+		 _ret == sizeof(buf) && buf[0] == 'a' &&
+			 buf[sizeof(buf) - 1] == 'a');
+}
+END_TEST()
+
+FN_TEST(grow_then_read_zeros)
+{
+	struct stat st;
+
+	TEST_SUCC(ftruncate(fd, 4 * PAGE_SIZE));
+	TEST_RES(fstat(fd, &st), st.st_size == 4 * PAGE_SIZE);
+	TEST_RES(pread(fd, buf, sizeof(buf), 2 * PAGE_SIZE),
+		 _ret == sizeof(buf) && is_zeros(buf, sizeof(buf)));
+}
+END_TEST()
+
+FN_TEST(shrink_then_read_eof)
+{
+	struct stat st;
+
+	// Shrink to the middle of a page, so that the cached data beyond the
+	// new size must be dropped
+	TEST_SUCC(truncate(FILE_NAME, PAGE_SIZE / 2));
+	TEST_RES(fstat(fd, &st), st.st_size == PAGE_SIZE / 2);
+	TEST_RES(pread(fd, buf, sizeof(buf), 0),
+		 _ret == PAGE_SIZE / 2 && buf[0] == 'a' &&
+			 buf[PAGE_SIZE / 2 - 1] == 'a');
+	TEST_RES(pread(fd, buf, sizeof(buf), PAGE_SIZE), _ret == 0);
+
+	// Grow again, the truncated data must not come back
+	TEST_SUCC(ftruncate(fd, PAGE_SIZE));
+	TEST_RES(pread(fd, buf, sizeof(buf), 0),
+		 _ret == PAGE_SIZE && buf[0] == 'a' &&
+			 is_zeros(buf + PAGE_SIZE / 2, PAGE_SIZE / 2));
+}
+END_TEST()
+
+FN_TEST(read_only_fd)
+{
+	int ro_fd;
+
+	ro_fd = TEST_SUCC(open(FILE_NAME, O_RDONLY));
+	TEST_ERRNO(ftruncate(ro_fd, 0), EINVAL);
+	TEST_SUCC(close(ro_fd));
+}
+END_TEST()
+
+FN_TEST(directory)
+{
+	TEST_ERRNO(truncate("/", 0), EISDIR);
+}
+END_TEST()
+
+FN_SETUP(cleanup)
+{
+	CHECK(close(fd));
+	CHECK(unlink(FILE_NAME));
+}
+END_SETUP()
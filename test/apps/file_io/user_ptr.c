@@ -0,0 +1,108 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include "../network/test.h"
+#include <fcntl.h>
+#include <sys/mman.h>
+#include <sys/stat.h>
+#include <unistd.h>
+
+// An address below the lowest user space address
+#define LOW_ADDR ((void *)0x1000UL)
+// An address in the kernel space of x86-64
+#define KERNEL_ADDR ((void *)0xffff800000000000UL)
+// The highest page in the user space of x86-64
+#define TOP_USER_PAGE ((void *)0x00007ffffffff000UL)
+
+#define PAGE_SIZE 4096
+#define FILE_NAME "/tmp/test_user_ptr.txt"
+
+static int fildes[2];
+
+FN_SETUP(pipe)
+{
+	CHECK(pipe(fildes));
+	CHECK(write(fildes[1], "hello", 5));
+}
+END_SETUP()
+
+FN_TEST(low_pointer)
+{
+	TEST_ERRNO(read(fildes[0], LOW_ADDR, 5), EFAULT);
+	TEST_ERRNO(write(fildes[1], LOW_ADDR, 5), EFAULT);
+}
+END_TEST()
+
+FN_TEST(kernel_pointer)
+{
+	TEST_ERRNO(read(fildes[0], KERNEL_ADDR, 5), EFAULT);
+	TEST_ERRNO(write(fildes[1], KERNEL_ADDR, 5), EFAULT);
+}
+END_TEST()
+
+FN_TEST(range_into_kernel_space)
+{
+	// The range starts in the user space but ends in the kernel space
+	TEST_ERRNO(read(fildes[0], TOP_USER_PAGE, 0x2000), EFAULT);
+	TEST_ERRNO(write(fildes[1], TOP_USER_PAGE, 0x2000), EFAULT);
+}
+END_TEST()
+
+FN_TEST(data_is_kept)
+{
+	char buf[5];
+
+	// The data are not consumed by the failed reads
+	TEST_RES(read(fildes[0], buf, sizeof(buf)),
+		 _ret == 5 && memcmp(buf, "hello", 5) == 0);
+}
+END_TEST()
+
+static int file_fd;
+static char *half_mapped;
+
+FN_SETUP(half_mapped)
+{
+	file_fd = CHECK(open(FILE_NAME, O_RDWR | O_CREAT | O_TRUNC, 0644));
+
+	// The first page is accessible, but the second one is not
+	half_mapped = (char *)CHECK_WITH(
+		(long)mmap(NULL, PAGE_SIZE * 2, PROT_READ | PROT_WRITE,
+			   MAP_PRIVATE | MAP_ANONYMOUS, -1, 0),
+		_ret != (long)MAP_FAILED);
+	memset(half_mapped, 'a', PAGE_SIZE);
+	CHECK(munmap(half_mapped + PAGE_SIZE, PAGE_SIZE));
+}
+END_SETUP()
+
+FN_TEST(pwrite_fault)
+{
+	TEST_ERRNO(pwrite(file_fd, half_mapped + PAGE_SIZE, PAGE_SIZE, 0),
+		   EFAULT);
+}
+END_TEST()
+
+FN_TEST(pwrite_partial)
+{
+	struct stat stat_buf;
+	char buf[PAGE_SIZE];
+
+	// The write stops at the fault, and only the written part extends the file
+	TEST_RES(pwrite(file_fd, half_mapped, PAGE_SIZE * 2, PAGE_SIZE),
+		 _ret == PAGE_SIZE);
+	TEST_RES(fstat(file_fd, &stat_buf),
+		 stat_buf.st_size == PAGE_SIZE * 2);
+
+	TEST_RES(pread(file_fd, buf, PAGE_SIZE, PAGE_SIZE),
+		 _ret == PAGE_SIZE && memcmp(buf, half_mapped, PAGE_SIZE) == 0);
+}
+END_TEST()
+
+FN_SETUP(cleanup)
+{
+	CHECK(munmap(half_mapped, PAGE_SIZE));
+	CHECK(close(file_fd));
+	CHECK(unlink(FILE_NAME));
+}
+END_SETUP()
@@ -0,0 +1,67 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include <sys/mman.h>
+#include <unistd.h>
+
+#include "../network/test.h"
+
+#define PAGE_SIZE 4096
+
+static char *mapping;
+
+FN_SETUP(mapping)
+{
+	mapping = (char *)CHECK_WITH(
+		(long)mmap(NULL, PAGE_SIZE * 4, PROT_READ | PROT_WRITE,
+			   MAP_PRIVATE | MAP_ANONYMOUS, -1, 0),
+		_ret != (long)MAP_FAILED);
+
+	// Leave a hole after the mapping
+	CHECK(munmap(mapping + PAGE_SIZE * 3, PAGE_SIZE));
+}
+END_SETUP()
+
+FN_TEST(lock_unmapped_range)
+{
+	TEST_ERRNO(mlock(mapping, PAGE_SIZE * 4), ENOMEM);
+	TEST_ERRNO(munlock(mapping + PAGE_SIZE * 3, PAGE_SIZE), ENOMEM);
+}
+END_TEST()
+
+FN_TEST(lock_outside_user_space)
+{
+	TEST_ERRNO(mlock(NULL, PAGE_SIZE), ENOMEM);
+	TEST_ERRNO(mlock((void *)0xffff800000000000UL, PAGE_SIZE), ENOMEM);
+	TEST_ERRNO(munlock((void *)0x00007ffffffff000UL, PAGE_SIZE * 2),
+		   ENOMEM);
+}
+END_TEST()
+
+FN_TEST(pageout_unaligned)
+{
+	TEST_ERRNO(madvise(mapping + 1, PAGE_SIZE, MADV_PAGEOUT), EINVAL);
+}
+END_TEST()
+
+FN_TEST(lock_and_unlock)
+{
+	// The range is extended to the page boundaries
+	TEST_SUCC(mlock(mapping + 1, PAGE_SIZE));
+	TEST_RES(mapping[0] == 0 && mapping[PAGE_SIZE] == 0, _ret);
+
+	mapping[PAGE_SIZE * 2 - 1] = 'a';
+	TEST_SUCC(madvise(mapping, PAGE_SIZE * 2, MADV_PAGEOUT));
+	TEST_RES(mapping[PAGE_SIZE * 2 - 1] == 'a', _ret);
+
+	TEST_SUCC(munlock(mapping, PAGE_SIZE * 3));
+	TEST_SUCC(mlock(mapping, 0));
+}
+END_TEST()
+
+FN_SETUP(cleanup)
+{
+	CHECK(munmap(mapping, PAGE_SIZE * 3));
+}
+END_SETUP()
@@ -0,0 +1,117 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include <sys/mman.h>
+#include <unistd.h>
+
+#include "../network/test.h"
+
+#define PAGE_SIZE 4096
+
+static char *region;
+static char *mapping;
+
+static void fill_pages(char *addr, int nr_pages)
+{
+	int i;
+
+	for (i = 0; i < nr_pages; ++i)
+		memset(addr + i * PAGE_SIZE, 'a' + i, PAGE_SIZE);
+}
+
+static int check_pages(char *addr, int nr_pages)
+{
+	int i, j;
+
+	for (i = 0; i < nr_pages; ++i)
+		for (j = 0; j < PAGE_SIZE; ++j)
+			if (addr[i * PAGE_SIZE + j] != 'a' + i)
+				return 0;
+
+	return 1;
+}
+
+FN_SETUP(mapping)
+{
+	region = (char *)CHECK_WITH(
+		(long)mmap(NULL, PAGE_SIZE * 8, PROT_READ | PROT_WRITE,
+			   MAP_PRIVATE | MAP_ANONYMOUS, -1, 0),
+		_ret != (long)MAP_FAILED);
+
+	mapping = region;
+	fill_pages(mapping, 1);
+}
+END_SETUP()
+
+FN_TEST(grow_in_place)
+{
+	// Free the space after the first page for the mapping to grow in place.
+	TEST_SUCC(munmap(mapping + PAGE_SIZE, PAGE_SIZE * 7));
+
+	TEST_RES((long)mremap(mapping, PAGE_SIZE, PAGE_SIZE * 3, 0),
+		 _ret == (long)mapping);
+	TEST_RES(check_pages(mapping, 1), _ret);
+
+	fill_pages(mapping, 3);
+}
+END_TEST()
+
+FN_TEST(grow_by_moving)
+{
+	char *blocker;
+
+	blocker = (char *)TEST_RES(
+		(long)mmap(mapping + PAGE_SIZE * 3, PAGE_SIZE,
+			   PROT_READ | PROT_WRITE,
+			   MAP_PRIVATE | MAP_ANONYMOUS | MAP_FIXED, -1, 0),
+		_ret == (long)(mapping + PAGE_SIZE * 3));
+
+	TEST_ERRNO((long)mremap(mapping, PAGE_SIZE * 3, PAGE_SIZE * 5, 0),
+		   ENOMEM);
+
+	mapping = (char *)TEST_RES((long)mremap(mapping, PAGE_SIZE * 3,
+						PAGE_SIZE * 5, MREMAP_MAYMOVE),
+				   _ret != (long)MAP_FAILED &&
+					   _ret != (long)mapping);
+	TEST_RES(check_pages(mapping, 3), _ret);
+
+	// The old range has been unmapped.
+	TEST_ERRNO((long)mremap(region, PAGE_SIZE, PAGE_SIZE, 0), EFAULT);
+
+	TEST_SUCC(munmap(blocker, PAGE_SIZE));
+}
+END_TEST()
+
+FN_TEST(shrink)
+{
+	TEST_RES((long)mremap(mapping, PAGE_SIZE * 5, PAGE_SIZE * 2, 0),
+		 _ret == (long)mapping);
+	TEST_RES(check_pages(mapping, 2), _ret);
+
+	// The tail has been unmapped.
+	TEST_ERRNO((long)mremap(mapping + PAGE_SIZE * 2, PAGE_SIZE, PAGE_SIZE,
+				0),
+		   EFAULT);
+}
+END_TEST()
+
+FN_TEST(move_to_fixed_address)
+{
+	TEST_ERRNO((long)mremap(mapping, PAGE_SIZE * 2, PAGE_SIZE * 2,
+				MREMAP_FIXED, region),
+		   EINVAL);
+	TEST_ERRNO((long)mremap(mapping, PAGE_SIZE * 2, PAGE_SIZE * 2,
+				MREMAP_MAYMOVE | MREMAP_FIXED,
+				mapping + PAGE_SIZE),
+		   EINVAL);
+
+	mapping = (char *)TEST_RES(
+		(long)mremap(mapping, PAGE_SIZE * 2, PAGE_SIZE * 2,
+			     MREMAP_MAYMOVE | MREMAP_FIXED, region),
+		_ret == (long)region);
+	TEST_RES(check_pages(mapping, 2), _ret);
+
+	TEST_SUCC(munmap(mapping, PAGE_SIZE * 2));
+}
+END_TEST()
@@ -0,0 +1,96 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include <signal.h>
+#include <stdint.h>
+#include <sys/resource.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+#include "../network/test.h"
+
+#define FRAME_SIZE 1024
+
+// Consumes about `depth` KiB of the stack.
+static int __attribute__((noinline)) recurse(int depth)
+{
+	volatile char frame[FRAME_SIZE];
+
+	frame[0] = (char)depth;
+	frame[FRAME_SIZE - 1] = (char)depth;
+	if (depth == 0)
+		return frame[0];
+
+	return recurse(depth - 1) + frame[FRAME_SIZE - 1];
+}
+
+static int recurse_in_child(int depth, rlim_t stack_limit)
+{
+	int pid, status;
+	struct rlimit rlim;
+
+	pid = fork();
+	if (pid == 0) {
+		if (stack_limit != 0) {
+			getrlimit(RLIMIT_STACK, &rlim);
+			rlim.rlim_cur = stack_limit;
+			if (setrlimit(RLIMIT_STACK, &rlim) < 0)
+				_exit(1);
+		}
+		recurse(depth);
+		_exit(0);
+	}
+
+	if (waitpid(pid, &status, 0) != pid)
+		return -1;
+	return status;
+}
+
+static int access_below_stack_in_child(size_t offset)
+{
+	int pid, status;
+	volatile char local;
+
+	pid = fork();
+	if (pid == 0) {
+		volatile char *addr = (volatile char *)((uintptr_t)&local - offset);
+
+		*addr = 1;
+		_exit(*addr == 1 ? 0 : 1);
+	}
+
+	if (waitpid(pid, &status, 0) != pid)
+		return -1;
+	return status;
+}
+
+FN_TEST(grow_stack)
+{
+	// Grow the stack to about 4 MiB.
+	TEST_RES(recurse_in_child(4 * 1024, 0),
+		 WIFEXITED(_ret) && WEXITSTATUS(_ret) == 0);
+}
+END_TEST()
+
+FN_TEST(access_far_below_stack)
+{
+	// The stack grows as long as it is within the limit, no matter how far
+	// the accessed address is below the mapped stack.
+	TEST_RES(access_below_stack_in_child(4 * 1024 * 1024),
+		 WIFEXITED(_ret) && WEXITSTATUS(_ret) == 0);
+}
+END_TEST()
+
+FN_TEST(exceed_limit)
+{
+	// The default limit is 8 MiB.
+	TEST_RES(recurse_in_child(16 * 1024, 0),
+		 WIFSIGNALED(_ret) && WTERMSIG(_ret) == SIGSEGV);
+
+	TEST_RES(recurse_in_child(1024, 2 * 1024 * 1024),
+		 WIFEXITED(_ret) && WEXITSTATUS(_ret) == 0);
+	TEST_RES(recurse_in_child(3 * 1024, 2 * 1024 * 1024),
+		 WIFSIGNALED(_ret) && WTERMSIG(_ret) == SIGSEGV);
+}
+END_TEST()
@@ -0,0 +1,83 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include <sys/mman.h>
+#include <unistd.h>
+
+#include "../network/test.h"
+
+#define PAGE_SIZE 4096
+
+static char *map_anonymous(void)
+{
+	return (char *)CHECK_WITH((long)mmap(NULL, PAGE_SIZE,
+					     PROT_READ | PROT_WRITE,
+					     MAP_PRIVATE | MAP_ANONYMOUS, -1,
+					     0),
+				  _ret != (long)MAP_FAILED);
+}
+
+static int is_zeroed(const volatile char *addr)
+{
+	int i;
+
+	for (i = 0; i < PAGE_SIZE; ++i)
+		if (addr[i] != 0)
+			return 0;
+
+	return 1;
+}
+
+FN_TEST(read_into_zero_page)
+{
+	int fildes[2];
+	char *first, *second;
+	char buf[PAGE_SIZE];
+
+	// Reading the first page maps the zero page into it, read-only.
+	first = map_anonymous();
+	TEST_RES(is_zeroed(first), _ret);
+
+	// The kernel writes the first page on behalf of `read()`. The write
+	// must not go to the zero page shared by all the mappings.
+	memset(buf, 'a', sizeof(buf));
+	CHECK(pipe(fildes));
+	TEST_RES(write(fildes[1], buf, sizeof(buf)), _ret == sizeof(buf));
+	TEST_RES(read(fildes[0], first, PAGE_SIZE), _ret == PAGE_SIZE);
+	TEST_RES(first[0] == 'a' && first[PAGE_SIZE - 1] == 'a', _ret);
+
+	// Another mapping still reads zeros.
+	second = map_anonymous();
+	TEST_RES(is_zeroed(second), _ret);
+
+	TEST_SUCC(munmap(first, PAGE_SIZE));
+	TEST_SUCC(munmap(second, PAGE_SIZE));
+	TEST_SUCC(close(fildes[0]));
+	TEST_SUCC(close(fildes[1]));
+}
+END_TEST()
+
+FN_TEST(read_into_untouched_page)
+{
+	int fildes[2];
+	char *first, *second;
+	char buf[PAGE_SIZE];
+
+	first = map_anonymous();
+
+	memset(buf, 'b', sizeof(buf));
+	CHECK(pipe(fildes));
+	TEST_RES(write(fildes[1], buf, sizeof(buf)), _ret == sizeof(buf));
+	TEST_RES(read(fildes[0], first, PAGE_SIZE), _ret == PAGE_SIZE);
+	TEST_RES(first[0] == 'b' && first[PAGE_SIZE - 1] == 'b', _ret);
+
+	second = map_anonymous();
+	TEST_RES(is_zeroed(second), _ret);
+
+	TEST_SUCC(munmap(first, PAGE_SIZE));
+	TEST_SUCC(munmap(second, PAGE_SIZE));
+	TEST_SUCC(close(fildes[0]));
+	TEST_SUCC(close(fildes[1]));
+}
+END_TEST()
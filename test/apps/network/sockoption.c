@@ -1,5 +1,6 @@
 // SPDX-License-Identifier: MPL-2.0
 
+#include <errno.h>
 #include <stdio.h>
 #include <stdlib.h>
 #include <sys/socket.h>
@@ -72,6 +73,48 @@ int main()
 		exit(EXIT_FAILURE);
 	}
 
+	// Set and get receive buffer size
+	int recvbuf = 65536;
+	if (setsockopt(sockfd, SOL_SOCKET, SO_RCVBUF, &recvbuf,
+		       sizeof(recvbuf)) < 0) {
+		perror("Setting SO_RCVBUF option failed");
+		exit(EXIT_FAILURE);
+	}
+
+	recvbuf = 0;
+	socklen_t recvbuf_len = sizeof(recvbuf);
+	if (getsockopt(sockfd, SOL_SOCKET, SO_RCVBUF, &recvbuf, &recvbuf_len) <
+		    0 ||
+	    recvbuf_len != sizeof(recvbuf) || recvbuf < 65536) {
+		perror("Getting SO_RCVBUF option failed");
+		exit(EXIT_FAILURE);
+	}
+
+	// Reject a value buffer that is too short
+	short short_option = 1;
+	if (setsockopt(sockfd, SOL_SOCKET, SO_RCVBUF, &short_option,
+		       sizeof(short_option)) == 0 ||
+	    errno != EINVAL) {
+		perror("Setting SO_RCVBUF option with a short buffer succeeded");
+		exit(EXIT_FAILURE);
+	}
+
+	// Reject unknown options and levels
+	socklen_t option_len = sizeof(option);
+	if (getsockopt(sockfd, SOL_SOCKET, 1000, &option, &option_len) == 0 ||
+	    errno != ENOPROTOOPT) {
+		perror("Getting an unknown option succeeded");
+		exit(EXIT_FAILURE);
+	}
+
+	option = 1;
+	if (setsockopt(sockfd, 1000, SO_REUSEADDR, &option, sizeof(option)) ==
+		    0 ||
+	    errno != ENOPROTOOPT) {
+		perror("Setting an option at an unknown level succeeded");
+		exit(EXIT_FAILURE);
+	}
+
 	// Close socket
 	close(sockfd);
 
@@ -0,0 +1,173 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include <poll.h>
+#include <sys/socket.h>
+#include <sys/time.h>
+#include <sys/un.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+#include "test.h"
+
+#define LISTEN_ADDR \
+	((struct sockaddr_un){ .sun_family = AF_UNIX, .sun_path = "/tmp/C0" })
+
+static int sk_listen;
+static int sk_pending;
+
+FN_SETUP(listen)
+{
+	unlink(LISTEN_ADDR.sun_path);
+
+	sk_listen = CHECK(socket(PF_UNIX, SOCK_STREAM, 0));
+	CHECK(bind(sk_listen, (struct sockaddr *)&LISTEN_ADDR,
+		   sizeof(LISTEN_ADDR)));
+
+	// At most one connection can be pending.
+	CHECK(listen(sk_listen, 0));
+}
+END_SETUP()
+
+static int connect_in_child(int *pid)
+{
+	int sk;
+
+	*pid = fork();
+	if (*pid != 0)
+		return *pid;
+
+	close(sk_listen);
+	sk = socket(PF_UNIX, SOCK_STREAM, 0);
+	if (connect(sk, (struct sockaddr *)&LISTEN_ADDR, sizeof(LISTEN_ADDR)) <
+	    0)
+		_exit(errno);
+	_exit(0);
+}
+
+FN_TEST(nonblocking_connect)
+{
+	int sk, sk_accepted, err;
+	socklen_t optlen = sizeof(err);
+	struct pollfd pfd;
+
+	sk_pending = TEST_SUCC(socket(PF_UNIX, SOCK_STREAM | SOCK_NONBLOCK, 0));
+	TEST_SUCC(connect(sk_pending, (struct sockaddr *)&LISTEN_ADDR,
+			  sizeof(LISTEN_ADDR)));
+
+	// The connection is established before the server accepts it.
+	pfd.fd = sk_pending;
+	pfd.events = POLLOUT;
+	TEST_RES(poll(&pfd, 1, 0), _ret == 1 && pfd.revents == POLLOUT);
+
+	// The backlog is full now. The connection is completed after the
+	// server accepts a pending connection.
+	sk = TEST_SUCC(socket(PF_UNIX, SOCK_STREAM | SOCK_NONBLOCK, 0));
+	TEST_ERRNO(connect(sk, (struct sockaddr *)&LISTEN_ADDR,
+			   sizeof(LISTEN_ADDR)),
+		   EINPROGRESS);
+	pfd.fd = sk;
+	TEST_RES(poll(&pfd, 1, 0), _ret == 0);
+	TEST_ERRNO(connect(sk, (struct sockaddr *)&LISTEN_ADDR,
+			   sizeof(LISTEN_ADDR)),
+		   EALREADY);
+
+	sk_accepted = TEST_SUCC(accept(sk_listen, NULL, NULL));
+	TEST_SUCC(close(sk_accepted));
+
+	TEST_RES(poll(&pfd, 1, 1000), _ret == 1 && pfd.revents == POLLOUT);
+	TEST_RES(getsockopt(sk, SOL_SOCKET, SO_ERROR, &err, &optlen),
+		 err == 0);
+	TEST_ERRNO(connect(sk, (struct sockaddr *)&LISTEN_ADDR,
+			   sizeof(LISTEN_ADDR)),
+		   EISCONN);
+	TEST_SUCC(close(sk));
+}
+END_TEST()
+
+FN_TEST(blocking_connect_waits_for_accept)
+{
+	int pid, status, sk;
+
+	TEST_SUCC(connect_in_child(&pid));
+
+	// The child is blocked until the server accepts a pending connection.
+	TEST_SUCC(usleep(100 * 1000));
+	TEST_RES(waitpid(pid, &status, WNOHANG), _ret == 0);
+
+	sk = TEST_SUCC(accept(sk_listen, NULL, NULL));
+	TEST_SUCC(close(sk));
+
+	TEST_RES(waitpid(pid, &status, 0),
+		 _ret == pid && WIFEXITED(status) && WEXITSTATUS(status) == 0);
+
+	sk = TEST_SUCC(accept(sk_listen, NULL, NULL));
+	TEST_SUCC(close(sk));
+}
+END_TEST()
+
+FN_TEST(blocking_connect_timeout)
+{
+	int sk, sk_timeout, sk_accepted;
+	struct timeval tv = { .tv_sec = 0, .tv_usec = 100 * 1000 };
+
+	sk = TEST_SUCC(socket(PF_UNIX, SOCK_STREAM, 0));
+	TEST_SUCC(connect(sk, (struct sockaddr *)&LISTEN_ADDR,
+			  sizeof(LISTEN_ADDR)));
+
+	// The backlog is full, so the connection times out.
+	sk_timeout = TEST_SUCC(socket(PF_UNIX, SOCK_STREAM, 0));
+	TEST_SUCC(setsockopt(sk_timeout, SOL_SOCKET, SO_SNDTIMEO, &tv,
+			     sizeof(tv)));
+	TEST_ERRNO(connect(sk_timeout, (struct sockaddr *)&LISTEN_ADDR,
+			   sizeof(LISTEN_ADDR)),
+		   ETIMEDOUT);
+	TEST_SUCC(close(sk_timeout));
+
+	sk_accepted = TEST_SUCC(accept(sk_listen, NULL, NULL));
+	TEST_SUCC(close(sk_accepted));
+	TEST_SUCC(close(sk));
+}
+END_TEST()
+
+FN_TEST(listener_closed)
+{
+	int pid, status, sk, sk_nonblocking, err;
+	socklen_t optlen = sizeof(err);
+	struct pollfd pfd;
+
+	sk = TEST_SUCC(socket(PF_UNIX, SOCK_STREAM, 0));
+	TEST_SUCC(connect(sk, (struct sockaddr *)&LISTEN_ADDR,
+			  sizeof(LISTEN_ADDR)));
+
+	TEST_SUCC(connect_in_child(&pid));
+	TEST_SUCC(usleep(100 * 1000));
+
+	sk_nonblocking =
+		TEST_SUCC(socket(PF_UNIX, SOCK_STREAM | SOCK_NONBLOCK, 0));
+	TEST_ERRNO(connect(sk_nonblocking, (struct sockaddr *)&LISTEN_ADDR,
+			   sizeof(LISTEN_ADDR)),
+		   EINPROGRESS);
+
+	// Closing the listening socket wakes up the blocked child, and fails
+	// the pending non-blocking connection.
+	TEST_SUCC(close(sk_listen));
+	TEST_RES(waitpid(pid, &status, 0),
+		 _ret == pid && WIFEXITED(status) &&
+			 WEXITSTATUS(status) == ECONNREFUSED);
+
+	pfd.fd = sk_nonblocking;
+	pfd.events = POLLOUT;
+	TEST_RES(poll(&pfd, 1, 0),
+		 _ret == 1 && pfd.revents == (POLLOUT | POLLERR));
+	TEST_RES(getsockopt(sk_nonblocking, SOL_SOCKET, SO_ERROR, &err,
+			    &optlen),
+		 err == ECONNREFUSED);
+	TEST_SUCC(close(sk_nonblocking));
+
+	TEST_SUCC(close(sk));
+	TEST_SUCC(close(sk_pending));
+	TEST_SUCC(unlink(LISTEN_ADDR.sun_path));
+}
+END_TEST()
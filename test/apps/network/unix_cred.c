@@ -0,0 +1,162 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include <sys/socket.h>
+#include <sys/un.h>
+#include <unistd.h>
+
+#include "test.h"
+
+static int sk_pair[2];
+
+FN_SETUP(socketpair)
+{
+	CHECK(socketpair(AF_UNIX, SOCK_STREAM, 0, sk_pair));
+}
+END_SETUP()
+
+static ssize_t send_cred(int sk, const struct ucred *cred)
+{
+	char data = 'a';
+	struct iovec iov = { .iov_base = &data, .iov_len = 1 };
+	union {
+		char buf[CMSG_SPACE(sizeof(struct ucred))];
+		struct cmsghdr align;
+	} control;
+	struct msghdr msg = {
+		.msg_iov = &iov,
+		.msg_iovlen = 1,
+		.msg_control = control.buf,
+		.msg_controllen = sizeof(control.buf),
+	};
+	struct cmsghdr *cmsg;
+
+	memset(&control, 0, sizeof(control));
+	cmsg = CMSG_FIRSTHDR(&msg);
+	cmsg->cmsg_level = SOL_SOCKET;
+	cmsg->cmsg_type = SCM_CREDENTIALS;
+	cmsg->cmsg_len = CMSG_LEN(sizeof(struct ucred));
+	memcpy(CMSG_DATA(cmsg), cred, sizeof(struct ucred));
+
+	return sendmsg(sk, &msg, 0);
+}
+
+static int set_passcred(int sk, int enabled)
+{
+	return setsockopt(sk, SOL_SOCKET, SO_PASSCRED, &enabled,
+			  sizeof(enabled));
+}
+
+static int recv_cred(int sk, struct ucred *cred)
+{
+	char data;
+	struct iovec iov = { .iov_base = &data, .iov_len = 1 };
+	union {
+		char buf[CMSG_SPACE(sizeof(struct ucred))];
+		struct cmsghdr align;
+	} control;
+	struct msghdr msg = {
+		.msg_iov = &iov,
+		.msg_iovlen = 1,
+		.msg_control = control.buf,
+		.msg_controllen = sizeof(control.buf),
+	};
+	struct cmsghdr *cmsg;
+
+	if (recvmsg(sk, &msg, 0) != 1)
+		return -1;
+
+	cmsg = CMSG_FIRSTHDR(&msg);
+	if (cmsg == NULL || cmsg->cmsg_level != SOL_SOCKET ||
+	    cmsg->cmsg_type != SCM_CREDENTIALS ||
+	    cmsg->cmsg_len != CMSG_LEN(sizeof(struct ucred)))
+		return -1;
+
+	memcpy(cred, CMSG_DATA(cmsg), sizeof(struct ucred));
+	return 0;
+}
+
+FN_TEST(passcred_option)
+{
+	int enabled = -1;
+	socklen_t len = sizeof(enabled);
+
+	TEST_RES(getsockopt(sk_pair[1], SOL_SOCKET, SO_PASSCRED, &enabled,
+			    &len),
+		 enabled == 0 && len == sizeof(enabled));
+
+	TEST_SUCC(set_passcred(sk_pair[1], 1));
+	TEST_RES(getsockopt(sk_pair[1], SOL_SOCKET, SO_PASSCRED, &enabled,
+			    &len),
+		 enabled == 1);
+	TEST_SUCC(set_passcred(sk_pair[1], 0));
+}
+END_TEST()
+
+FN_TEST(no_passcred)
+{
+	struct ucred cred = {
+		.pid = getpid(),
+		.uid = getuid(),
+		.gid = getgid(),
+	};
+	struct ucred received;
+
+	// The credentials are not received without `SO_PASSCRED`
+	TEST_RES(send_cred(sk_pair[0], &cred), _ret == 1);
+	TEST_RES(recv_cred(sk_pair[1], &received), _ret == -1);
+}
+END_TEST()
+
+FN_TEST(valid_credentials)
+{
+	struct ucred cred = {
+		.pid = getpid(),
+		.uid = getuid(),
+		.gid = getgid(),
+	};
+	struct ucred received;
+
+	TEST_SUCC(set_passcred(sk_pair[1], 1));
+	TEST_RES(send_cred(sk_pair[0], &cred), _ret == 1);
+	TEST_RES(recv_cred(sk_pair[1], &received),
+		 received.pid == cred.pid && received.uid == cred.uid &&
+			 received.gid == cred.gid);
+}
+END_TEST()
+
+FN_TEST(implicit_credentials)
+{
+	struct ucred received;
+
+	// The credentials of the sender are received even if it does not send any
+	TEST_RES(write(sk_pair[0], "a", 1), _ret == 1);
+	TEST_RES(recv_cred(sk_pair[1], &received),
+		 received.pid == getpid() && received.uid == getuid() &&
+			 received.gid == getgid());
+}
+END_TEST()
+
+FN_TEST(forged_credentials)
+{
+	struct ucred cred;
+
+	// Drop the privilege first, otherwise any credentials can be sent
+	TEST_SUCC(setresgid(1000, 1000, 1000));
+	TEST_SUCC(setresuid(1000, 1000, 1000));
+
+	cred.pid = getpid();
+	cred.uid = 0;
+	cred.gid = getgid();
+	TEST_ERRNO(send_cred(sk_pair[0], &cred), EPERM);
+
+	cred.uid = getuid();
+	cred.gid = 0;
+	TEST_ERRNO(send_cred(sk_pair[0], &cred), EPERM);
+
+	cred.gid = getgid();
+	cred.pid = getppid();
+	TEST_ERRNO(send_cred(sk_pair[0], &cred), EPERM);
+}
+END_TEST()
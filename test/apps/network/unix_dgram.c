@@ -0,0 +1,221 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include <poll.h>
+#include <sys/socket.h>
+#include <sys/un.h>
+#include <unistd.h>
+
+#include "test.h"
+
+#define ADDR_A "/tmp/D0"
+#define ADDR_B "/tmp/D1"
+#define ADDR_C "/tmp/D2"
+
+#define MESSAGE_A "Hello from A"
+#define MESSAGE_B "Hello from B"
+
+static struct sockaddr_un addr_a = { .sun_family = AF_UNIX, .sun_path = ADDR_A };
+static struct sockaddr_un addr_b = { .sun_family = AF_UNIX, .sun_path = ADDR_B };
+static struct sockaddr_un addr_c = { .sun_family = AF_UNIX, .sun_path = ADDR_C };
+
+static int sk_a;
+static int sk_b;
+static int sk_c;
+
+static int bind_at(struct sockaddr_un *addr)
+{
+	int sk;
+
+	unlink(addr->sun_path);
+
+	sk = CHECK(socket(PF_UNIX, SOCK_DGRAM, 0));
+	CHECK(bind(sk, (struct sockaddr *)addr, sizeof(*addr)));
+
+	return sk;
+}
+
+static int has_datagram(int sk)
+{
+	struct pollfd pfd = { .fd = sk, .events = POLLIN };
+
+	return CHECK(poll(&pfd, 1, 0)) == 1 && (pfd.revents & POLLIN);
+}
+
+FN_SETUP(sockets)
+{
+	sk_a = bind_at(&addr_a);
+	sk_b = bind_at(&addr_b);
+	sk_c = bind_at(&addr_c);
+}
+END_SETUP()
+
+FN_TEST(send_without_peer)
+{
+	TEST_ERRNO(write(sk_a, MESSAGE_A, sizeof(MESSAGE_A)), ENOTCONN);
+}
+END_TEST()
+
+FN_TEST(send_to_default_peer)
+{
+	struct sockaddr_un addr;
+	socklen_t addrlen = sizeof(addr);
+	char buf[64];
+
+	TEST_SUCC(connect(sk_a, (struct sockaddr *)&addr_b, sizeof(addr_b)));
+
+	TEST_RES(write(sk_a, MESSAGE_A, sizeof(MESSAGE_A)),
+		 _ret == sizeof(MESSAGE_A));
+	TEST_RES(send(sk_a, MESSAGE_A, sizeof(MESSAGE_A), 0),
+		 _ret == sizeof(MESSAGE_A));
+
+	TEST_RES(recvfrom(sk_b, buf, sizeof(buf), 0, (struct sockaddr *)&addr,
+			  &addrlen),
+		 _ret == sizeof(MESSAGE_A) &&
+			 memcmp(buf, MESSAGE_A, sizeof(MESSAGE_A)) == 0 &&
+			 strcmp(addr.sun_path, ADDR_A) == 0);
+	TEST_RES(read(sk_b, buf, sizeof(buf)),
+		 _ret == sizeof(MESSAGE_A) &&
+			 memcmp(buf, MESSAGE_A, sizeof(MESSAGE_A)) == 0);
+
+	TEST_RES(getpeername(sk_a, (struct sockaddr *)&addr, &addrlen),
+		 strcmp(addr.sun_path, ADDR_B) == 0);
+}
+END_TEST()
+
+FN_TEST(sendto_overrides_peer)
+{
+	char buf[64];
+
+	TEST_RES(sendto(sk_a, MESSAGE_A, sizeof(MESSAGE_A), 0,
+			(struct sockaddr *)&addr_c, sizeof(addr_c)),
+		 _ret == sizeof(MESSAGE_A));
+	TEST_RES(read(sk_c, buf, sizeof(buf)),
+		 _ret == sizeof(MESSAGE_A) &&
+			 memcmp(buf, MESSAGE_A, sizeof(MESSAGE_A)) == 0);
+
+	// The default peer is not changed.
+	TEST_RES(has_datagram(sk_b), _ret == 0);
+}
+END_TEST()
+
+FN_TEST(reconnect)
+{
+	char buf[64];
+
+	TEST_SUCC(connect(sk_b, (struct sockaddr *)&addr_a, sizeof(addr_a)));
+	TEST_SUCC(connect(sk_b, (struct sockaddr *)&addr_c, sizeof(addr_c)));
+
+	TEST_RES(write(sk_b, MESSAGE_B, sizeof(MESSAGE_B)),
+		 _ret == sizeof(MESSAGE_B));
+	TEST_RES(read(sk_c, buf, sizeof(buf)),
+		 _ret == sizeof(MESSAGE_B) &&
+			 memcmp(buf, MESSAGE_B, sizeof(MESSAGE_B)) == 0);
+}
+END_TEST()
+
+FN_TEST(truncate_message)
+{
+	char buf[4];
+
+	TEST_RES(write(sk_b, MESSAGE_B, sizeof(MESSAGE_B)),
+		 _ret == sizeof(MESSAGE_B));
+	TEST_RES(read(sk_c, buf, sizeof(buf)),
+		 _ret == sizeof(buf) && memcmp(buf, MESSAGE_B, sizeof(buf)) == 0);
+
+	// The rest of the datagram is discarded.
+	TEST_RES(has_datagram(sk_c), _ret == 0);
+}
+END_TEST()
+
+FN_TEST(peek_and_truncate)
+{
+	char buf[4];
+
+	TEST_RES(write(sk_b, MESSAGE_B, sizeof(MESSAGE_B)),
+		 _ret == sizeof(MESSAGE_B));
+
+	// The real length is reported without consuming the datagram.
+	TEST_RES(recv(sk_c, NULL, 0, MSG_PEEK | MSG_TRUNC),
+		 _ret == sizeof(MESSAGE_B));
+	TEST_RES(recv(sk_c, buf, sizeof(buf), MSG_PEEK | MSG_TRUNC),
+		 _ret == sizeof(MESSAGE_B) &&
+			 memcmp(buf, MESSAGE_B, sizeof(buf)) == 0);
+
+	// The real length is reported, but only the prefix is received.
+	memset(buf, 0, sizeof(buf));
+	TEST_RES(recv(sk_c, buf, sizeof(buf), MSG_TRUNC),
+		 _ret == sizeof(MESSAGE_B) &&
+			 memcmp(buf, MESSAGE_B, sizeof(buf)) == 0);
+	TEST_RES(has_datagram(sk_c), _ret == 0);
+}
+END_TEST()
+
+FN_TEST(empty_datagram)
+{
+	char buf[4];
+
+	TEST_RES(write(sk_b, buf, 0), _ret == 0);
+	TEST_RES(has_datagram(sk_c), _ret == 1);
+
+	TEST_RES(recv(sk_c, buf, sizeof(buf), MSG_PEEK | MSG_TRUNC), _ret == 0);
+	TEST_RES(recv(sk_c, buf, sizeof(buf), 0), _ret == 0);
+	TEST_RES(has_datagram(sk_c), _ret == 0);
+}
+END_TEST()
+
+FN_TEST(buffer_limits)
+{
+	static char buf[65536];
+	int sk, size = 4096;
+	ssize_t ret;
+	size_t sent = 0;
+
+	sk = TEST_SUCC(socket(PF_UNIX, SOCK_DGRAM | SOCK_NONBLOCK, 0));
+
+	// A datagram larger than the send buffer is rejected.
+	TEST_SUCC(setsockopt(sk, SOL_SOCKET, SO_SNDBUF, &size, sizeof(size)));
+	TEST_ERRNO(sendto(sk, buf, sizeof(buf), 0, (struct sockaddr *)&addr_c,
+			  sizeof(addr_c)),
+		   EMSGSIZE);
+
+	// The queued datagrams are limited by the receive buffer.
+	TEST_SUCC(setsockopt(sk_c, SOL_SOCKET, SO_RCVBUF, &size, sizeof(size)));
+	while ((ret = sendto(sk, buf, 1024, 0, (struct sockaddr *)&addr_c,
+			     sizeof(addr_c))) > 0)
+		sent += ret;
+	TEST_ERRNO(sendto(sk, buf, 1024, 0, (struct sockaddr *)&addr_c,
+			  sizeof(addr_c)),
+		   EAGAIN);
+	TEST_RES(sent, _ret > 0 && _ret <= 4 * size);
+
+	while (has_datagram(sk_c))
+		TEST_RES(read(sk_c, buf, sizeof(buf)), _ret == 1024);
+	TEST_RES(sendto(sk, buf, 1024, 0, (struct sockaddr *)&addr_c,
+			sizeof(addr_c)),
+		 _ret == 1024);
+	TEST_RES(read(sk_c, buf, sizeof(buf)), _ret == 1024);
+
+	TEST_SUCC(close(sk));
+}
+END_TEST()
+
+FN_TEST(peer_closed)
+{
+	TEST_SUCC(close(sk_c));
+
+	TEST_ERRNO(write(sk_b, MESSAGE_B, sizeof(MESSAGE_B)), ECONNREFUSED);
+}
+END_TEST()
+
+FN_SETUP(cleanup)
+{
+	CHECK(close(sk_a));
+	CHECK(close(sk_b));
+
+	CHECK(unlink(ADDR_A));
+	CHECK(unlink(ADDR_B));
+	CHECK(unlink(ADDR_C));
+}
+END_SETUP()
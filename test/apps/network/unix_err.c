@@ -1,5 +1,7 @@
 // SPDX-License-Identifier: MPL-2.0
 
+#include <netinet/in.h>
+#include <netinet/tcp.h>
 #include <sys/socket.h>
 #include <sys/un.h>
 #include <sys/poll.h>
@@ -218,3 +220,18 @@ FN_TEST(connect)
 		   EISCONN);
 }
 END_TEST()
+
+FN_TEST(tcp_options)
+{
+	int val = 1;
+	socklen_t optlen = sizeof(val);
+
+	// Like Linux, the options at the TCP level are not supported.
+	TEST_ERRNO(setsockopt(sk_connected, IPPROTO_TCP, TCP_NODELAY, &val,
+			      sizeof(val)),
+		   EOPNOTSUPP);
+	TEST_ERRNO(getsockopt(sk_connected, IPPROTO_TCP, TCP_CORK, &val,
+			      &optlen),
+		   EOPNOTSUPP);
+}
+END_TEST()
@@ -0,0 +1,204 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include <poll.h>
+#include <sys/socket.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+#include "test.h"
+
+#define MESSAGE "Hello, linger!"
+
+static int sk_pair[2];
+
+FN_SETUP(socketpair)
+{
+	CHECK(socketpair(AF_UNIX, SOCK_STREAM, 0, sk_pair));
+}
+END_SETUP()
+
+static int set_linger(int sk, int onoff, int seconds)
+{
+	struct linger linger = { .l_onoff = onoff, .l_linger = seconds };
+
+	return setsockopt(sk, SOL_SOCKET, SO_LINGER, &linger, sizeof(linger));
+}
+
+FN_TEST(get_and_set_linger)
+{
+	struct linger linger;
+	socklen_t optlen = sizeof(linger);
+
+	TEST_RES(getsockopt(sk_pair[0], SOL_SOCKET, SO_LINGER, &linger,
+			    &optlen),
+		 optlen == sizeof(linger) && linger.l_onoff == 0);
+
+	TEST_SUCC(set_linger(sk_pair[0], 1, 2));
+	TEST_RES(getsockopt(sk_pair[0], SOL_SOCKET, SO_LINGER, &linger,
+			    &optlen),
+		 optlen == sizeof(linger) && linger.l_onoff != 0 &&
+			 linger.l_linger == 2);
+}
+END_TEST()
+
+FN_TEST(peer_reads_after_close)
+{
+	char buf[sizeof(MESSAGE)];
+	int pid, status;
+
+	TEST_RES(write(sk_pair[0], MESSAGE, sizeof(MESSAGE)),
+		 _ret == sizeof(MESSAGE));
+
+	// The child drains the data while the parent lingers on close.
+	pid = fork();
+	if (pid == 0) {
+		close(sk_pair[0]);
+		usleep(100 * 1000);
+		if (read(sk_pair[1], buf, sizeof(buf)) != sizeof(MESSAGE) ||
+		    memcmp(buf, MESSAGE, sizeof(MESSAGE)) != 0)
+			_exit(1);
+		if (read(sk_pair[1], buf, sizeof(buf)) != 0)
+			_exit(2);
+		_exit(0);
+	}
+	TEST_SUCC(pid);
+
+	TEST_SUCC(close(sk_pair[0]));
+	TEST_SUCC(close(sk_pair[1]));
+
+	TEST_RES(waitpid(pid, &status, 0),
+		 _ret == pid && WIFEXITED(status) && WEXITSTATUS(status) == 0);
+}
+END_TEST()
+
+FN_TEST(peer_never_reads)
+{
+	char buf[sizeof(MESSAGE)];
+
+	TEST_SUCC(socketpair(AF_UNIX, SOCK_STREAM, 0, sk_pair));
+	TEST_SUCC(set_linger(sk_pair[0], 1, 1));
+
+	TEST_RES(write(sk_pair[0], MESSAGE, sizeof(MESSAGE)),
+		 _ret == sizeof(MESSAGE));
+
+	// The close times out because no one reads the data, but the data is
+	// still delivered to the peer.
+	TEST_SUCC(close(sk_pair[0]));
+
+	TEST_RES(read(sk_pair[1], buf, sizeof(buf)),
+		 _ret == sizeof(MESSAGE) &&
+			 memcmp(buf, MESSAGE, sizeof(MESSAGE)) == 0);
+	TEST_RES(read(sk_pair[1], buf, sizeof(buf)), _ret == 0);
+
+	TEST_SUCC(close(sk_pair[1]));
+}
+END_TEST()
+
+FN_TEST(linger_zero_resets)
+{
+	struct pollfd pfd = { .events = POLLIN | POLLOUT };
+	char buf[sizeof(MESSAGE)];
+	int sk[2];
+
+	TEST_SUCC(socketpair(AF_UNIX, SOCK_STREAM, 0, sk));
+	TEST_SUCC(set_linger(sk[0], 1, 0));
+
+	TEST_RES(write(sk[0], MESSAGE, sizeof(MESSAGE)),
+		 _ret == sizeof(MESSAGE));
+	TEST_SUCC(close(sk[0]));
+
+	// The abortive close discards the data that has not been read, and the
+	// reset is reported only once.
+	pfd.fd = sk[1];
+	TEST_RES(poll(&pfd, 1, 0), _ret == 1 && (pfd.revents & POLLERR));
+	TEST_ERRNO(read(sk[1], buf, sizeof(buf)), ECONNRESET);
+	TEST_RES(poll(&pfd, 1, 0), _ret == 1 && !(pfd.revents & POLLERR));
+	TEST_RES(read(sk[1], buf, sizeof(buf)), _ret == 0);
+
+	TEST_SUCC(close(sk[1]));
+}
+END_TEST()
+
+FN_TEST(close_with_unread_data_resets)
+{
+	char buf[sizeof(MESSAGE)];
+	int sk[2];
+
+	TEST_SUCC(socketpair(AF_UNIX, SOCK_STREAM, 0, sk));
+
+	TEST_RES(write(sk[0], MESSAGE, sizeof(MESSAGE)),
+		 _ret == sizeof(MESSAGE));
+	TEST_RES(write(sk[1], MESSAGE, sizeof(MESSAGE)),
+		 _ret == sizeof(MESSAGE));
+	TEST_SUCC(close(sk[0]));
+
+	// The data sent by the closed socket can still be read before the reset
+	// is reported.
+	TEST_RES(read(sk[1], buf, sizeof(buf)),
+		 _ret == sizeof(MESSAGE) &&
+			 memcmp(buf, MESSAGE, sizeof(MESSAGE)) == 0);
+	TEST_ERRNO(read(sk[1], buf, sizeof(buf)), ECONNRESET);
+	TEST_RES(read(sk[1], buf, sizeof(buf)), _ret == 0);
+
+	TEST_SUCC(close(sk[1]));
+}
+END_TEST()
+
+FN_TEST(write_after_reset)
+{
+	int sk[2];
+
+	TEST_SUCC(socketpair(AF_UNIX, SOCK_STREAM, 0, sk));
+
+	TEST_RES(write(sk[1], MESSAGE, sizeof(MESSAGE)),
+		 _ret == sizeof(MESSAGE));
+	TEST_SUCC(close(sk[0]));
+
+	TEST_ERRNO(send(sk[1], MESSAGE, sizeof(MESSAGE), MSG_NOSIGNAL),
+		   ECONNRESET);
+	TEST_ERRNO(send(sk[1], MESSAGE, sizeof(MESSAGE), MSG_NOSIGNAL), EPIPE);
+
+	TEST_SUCC(close(sk[1]));
+}
+END_TEST()
+
+FN_TEST(graceful_close)
+{
+	char buf[sizeof(MESSAGE)];
+	int sk[2];
+
+	TEST_SUCC(socketpair(AF_UNIX, SOCK_STREAM, 0, sk));
+
+	TEST_RES(write(sk[0], MESSAGE, sizeof(MESSAGE)),
+		 _ret == sizeof(MESSAGE));
+	TEST_SUCC(close(sk[0]));
+
+	TEST_RES(read(sk[1], buf, sizeof(buf)), _ret == sizeof(MESSAGE));
+	TEST_RES(read(sk[1], buf, sizeof(buf)), _ret == 0);
+
+	TEST_SUCC(close(sk[1]));
+}
+END_TEST()
+
+FN_TEST(so_error_after_reset)
+{
+	int sk[2], err;
+	socklen_t optlen = sizeof(err);
+
+	TEST_SUCC(socketpair(AF_UNIX, SOCK_STREAM, 0, sk));
+
+	TEST_RES(write(sk[1], MESSAGE, sizeof(MESSAGE)),
+		 _ret == sizeof(MESSAGE));
+	TEST_SUCC(close(sk[0]));
+
+	// Reading the error clears it
+	TEST_RES(getsockopt(sk[1], SOL_SOCKET, SO_ERROR, &err, &optlen),
+		 err == ECONNRESET);
+	TEST_RES(getsockopt(sk[1], SOL_SOCKET, SO_ERROR, &err, &optlen),
+		 err == 0);
+
+	TEST_SUCC(close(sk[1]));
+}
+END_TEST()
@@ -0,0 +1,72 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include <poll.h>
+#include <sys/socket.h>
+#include <unistd.h>
+
+#include "test.h"
+
+#define LOWAT 4096
+
+static int sk_pair[2];
+static char buf[LOWAT];
+
+FN_SETUP(socketpair)
+{
+	CHECK(socketpair(AF_UNIX, SOCK_STREAM | SOCK_NONBLOCK, 0, sk_pair));
+}
+END_SETUP()
+
+static int is_writable(int sk)
+{
+	struct pollfd pfd = { .fd = sk, .events = POLLOUT };
+
+	return poll(&pfd, 1, 0) == 1 && (pfd.revents & POLLOUT);
+}
+
+FN_TEST(get_and_set_sndlowat)
+{
+	int lowat;
+	socklen_t optlen = sizeof(lowat);
+
+	TEST_RES(getsockopt(sk_pair[0], SOL_SOCKET, SO_SNDLOWAT, &lowat,
+			    &optlen),
+		 optlen == sizeof(lowat) && lowat == 1);
+
+	lowat = LOWAT;
+	TEST_SUCC(setsockopt(sk_pair[0], SOL_SOCKET, SO_SNDLOWAT, &lowat,
+			     sizeof(lowat)));
+	TEST_RES(getsockopt(sk_pair[0], SOL_SOCKET, SO_SNDLOWAT, &lowat,
+			    &optlen),
+		 optlen == sizeof(lowat) && lowat == LOWAT);
+}
+END_TEST()
+
+FN_TEST(poll_out_with_sndlowat)
+{
+	TEST_RES(is_writable(sk_pair[0]), _ret);
+
+	// Fill the send buffer.
+	while (write(sk_pair[0], buf, sizeof(buf)) > 0)
+		;
+	TEST_RES(is_writable(sk_pair[0]), !_ret);
+
+	// The free space is still below the low watermark.
+	TEST_RES(read(sk_pair[1], buf, 1), _ret == 1);
+	TEST_RES(is_writable(sk_pair[0]), !_ret);
+	TEST_RES(write(sk_pair[0], buf, 1), _ret == 1);
+
+	// The free space reaches the low watermark.
+	TEST_RES(read(sk_pair[1], buf, LOWAT), _ret == LOWAT);
+	TEST_RES(is_writable(sk_pair[0]), _ret);
+}
+END_TEST()
+
+FN_SETUP(cleanup)
+{
+	CHECK(close(sk_pair[0]));
+	CHECK(close(sk_pair[1]));
+}
+END_SETUP()
@@ -0,0 +1,83 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include <fcntl.h>
+#include <poll.h>
+#include <signal.h>
+#include <sys/socket.h>
+#include <unistd.h>
+
+#include "test.h"
+
+static int sk_pair[2];
+static volatile int nr_sigurg;
+
+static void handle_sigurg(int signo)
+{
+	nr_sigurg++;
+}
+
+FN_SETUP(socketpair)
+{
+	CHECK(socketpair(AF_UNIX, SOCK_STREAM, 0, sk_pair));
+}
+END_SETUP()
+
+FN_SETUP(sigurg)
+{
+	struct sigaction action = { .sa_handler = handle_sigurg };
+
+	CHECK(sigaction(SIGURG, &action, NULL));
+	CHECK(fcntl(sk_pair[1], F_SETOWN, getpid()));
+}
+END_SETUP()
+
+FN_TEST(no_oob_byte)
+{
+	char buf[4];
+
+	TEST_ERRNO(recv(sk_pair[1], buf, sizeof(buf), MSG_OOB), EINVAL);
+	TEST_ERRNO(send(sk_pair[0], buf, 0, MSG_OOB), EOPNOTSUPP);
+}
+END_TEST()
+
+FN_TEST(send_and_recv_oob)
+{
+	struct pollfd pfd = { .fd = sk_pair[1], .events = POLLIN | POLLPRI };
+	char buf[4];
+
+	TEST_RES(send(sk_pair[0], "abc", 3, MSG_OOB),
+		 _ret == 3 && nr_sigurg == 1);
+
+	TEST_RES(poll(&pfd, 1, 0),
+		 _ret == 1 && pfd.revents == (POLLIN | POLLPRI));
+
+	// The last byte is the out-of-band byte
+	TEST_RES(recv(sk_pair[1], buf, sizeof(buf), MSG_OOB),
+		 _ret == 1 && buf[0] == 'c');
+	TEST_ERRNO(recv(sk_pair[1], buf, sizeof(buf), MSG_OOB), EINVAL);
+
+	TEST_RES(poll(&pfd, 1, 0), _ret == 1 && pfd.revents == POLLIN);
+	TEST_RES(recv(sk_pair[1], buf, sizeof(buf), 0),
+		 _ret == 2 && buf[0] == 'a' && buf[1] == 'b');
+}
+END_TEST()
+
+FN_TEST(replace_oob)
+{
+	struct pollfd pfd = { .fd = sk_pair[1], .events = POLLIN | POLLPRI };
+	char buf[4];
+
+	TEST_RES(send(sk_pair[0], "x", 1, MSG_OOB), _ret == 1);
+	TEST_RES(send(sk_pair[0], "y", 1, MSG_OOB),
+		 _ret == 1 && nr_sigurg == 3);
+
+	TEST_RES(poll(&pfd, 1, 0), _ret == 1 && (pfd.revents & POLLPRI));
+
+	// The second out-of-band byte replaces the first one
+	TEST_RES(recv(sk_pair[1], buf, sizeof(buf), MSG_OOB),
+		 _ret == 1 && buf[0] == 'y');
+	TEST_ERRNO(recv(sk_pair[1], buf, sizeof(buf), MSG_OOB), EINVAL);
+}
+END_TEST()
@@ -0,0 +1,119 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include <fcntl.h>
+#include <stdio.h>
+#include <sys/socket.h>
+#include <sys/un.h>
+#include <unistd.h>
+
+#include "test.h"
+
+#define ADDR_A "/tmp/P0"
+#define ADDR_B "/tmp/P1"
+
+#define FLAG_ACCEPTCON 0x10000
+#define SS_UNCONNECTED 1
+#define SS_CONNECTED 3
+
+static int sk_listen_a;
+static int sk_listen_b;
+static int sk_client;
+static int sk_accepted;
+
+static int listen_at(const char *path)
+{
+	struct sockaddr_un addr = { .sun_family = AF_UNIX };
+	int sk;
+
+	strcpy(addr.sun_path, path);
+	unlink(path);
+
+	sk = CHECK(socket(PF_UNIX, SOCK_STREAM, 0));
+	CHECK(bind(sk, (struct sockaddr *)&addr, sizeof(addr)));
+	CHECK(listen(sk, 2));
+
+	return sk;
+}
+
+FN_SETUP(sockets)
+{
+	struct sockaddr_un addr = { .sun_family = AF_UNIX, .sun_path = ADDR_A };
+
+	sk_listen_a = listen_at(ADDR_A);
+	sk_listen_b = listen_at(ADDR_B);
+
+	sk_client = CHECK(socket(PF_UNIX, SOCK_STREAM, 0));
+	CHECK(connect(sk_client, (struct sockaddr *)&addr, sizeof(addr)));
+	sk_accepted = CHECK(accept(sk_listen_a, NULL, NULL));
+}
+END_SETUP()
+
+// Returns the number of sockets in `/proc/net/unix` that are bound to `path`
+// and have the given flags and state.
+static int count_sockets(const char *path, unsigned int flags,
+			 unsigned int state)
+{
+	static char buf[8192];
+	char sk_path[sizeof(((struct sockaddr_un *)0)->sun_path)];
+	unsigned int sk_flags, sk_type, sk_state;
+	char *line, *saveptr;
+	int fd, len, count = 0;
+
+	fd = open("/proc/net/unix", O_RDONLY);
+	if (fd < 0)
+		return -1;
+	len = read(fd, buf, sizeof(buf) - 1);
+	close(fd);
+	if (len < 0)
+		return -1;
+	buf[len] = '\0';
+
+	// Skip the header line.
+	line = strtok_r(buf, "\n", &saveptr);
+	while ((line = strtok_r(NULL, "\n", &saveptr)) != NULL) {
+		if (sscanf(line, "%*s %*x %*x %x %x %x %*u %107s", &sk_flags,
+			   &sk_type, &sk_state, sk_path) != 4)
+			continue;
+		if (sk_type == SOCK_STREAM && sk_flags == flags &&
+		    sk_state == state && strcmp(sk_path, path) == 0)
+			++count;
+	}
+
+	return count;
+}
+
+FN_TEST(listening_sockets)
+{
+	TEST_RES(count_sockets(ADDR_A, FLAG_ACCEPTCON, SS_UNCONNECTED),
+		 _ret == 1);
+	TEST_RES(count_sockets(ADDR_B, FLAG_ACCEPTCON, SS_UNCONNECTED),
+		 _ret == 1);
+}
+END_TEST()
+
+FN_TEST(connected_sockets)
+{
+	// The accepted socket has the address of the listening socket, while the
+	// client socket is unnamed.
+	TEST_RES(count_sockets(ADDR_A, 0, SS_CONNECTED), _ret == 1);
+	TEST_RES(count_sockets(ADDR_B, 0, SS_CONNECTED), _ret == 0);
+}
+END_TEST()
+
+FN_TEST(closed_sockets)
+{
+	TEST_SUCC(close(sk_listen_b));
+	TEST_RES(count_sockets(ADDR_B, FLAG_ACCEPTCON, SS_UNCONNECTED),
+		 _ret == 0);
+
+	TEST_SUCC(close(sk_accepted));
+	TEST_RES(count_sockets(ADDR_A, 0, SS_CONNECTED), _ret == 0);
+	TEST_RES(count_sockets(ADDR_A, FLAG_ACCEPTCON, SS_UNCONNECTED),
+		 _ret == 1);
+
+	TEST_SUCC(close(sk_client));
+	TEST_SUCC(close(sk_listen_a));
+}
+END_TEST()
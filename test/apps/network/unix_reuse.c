@@ -0,0 +1,133 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#include <sys/socket.h>
+#include <sys/un.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+#include "test.h"
+
+#define REUSE_PATH "/tmp/R0"
+
+static struct sockaddr_un reuse_addr = { .sun_family = AF_UNIX,
+					 .sun_path = REUSE_PATH };
+
+static int sk_listen;
+static int sk_client;
+
+static int new_socket(int reuse)
+{
+	int sk;
+
+	sk = CHECK(socket(PF_UNIX, SOCK_STREAM, 0));
+	CHECK(setsockopt(sk, SOL_SOCKET, SO_REUSEADDR, &reuse, sizeof(reuse)));
+
+	return sk;
+}
+
+FN_SETUP(stale_socket_file)
+{
+	int status;
+	pid_t pid;
+
+	unlink(REUSE_PATH);
+
+	// The child exits without closing its listening socket, leaving the socket
+	// file behind.
+	pid = CHECK(fork());
+	if (pid == 0) {
+		int sk = new_socket(0);
+
+		if (bind(sk, (struct sockaddr *)&reuse_addr,
+			 sizeof(reuse_addr)) < 0 ||
+		    listen(sk, 1) < 0)
+			_exit(EXIT_FAILURE);
+		_exit(EXIT_SUCCESS);
+	}
+
+	CHECK_WITH(waitpid(pid, &status, 0),
+		   _ret == pid && WIFEXITED(status) &&
+			   WEXITSTATUS(status) == EXIT_SUCCESS);
+}
+END_SETUP()
+
+FN_TEST(bind_without_reuse_addr)
+{
+	int sk;
+
+	sk = TEST_SUCC(new_socket(0));
+	TEST_ERRNO(bind(sk, (struct sockaddr *)&reuse_addr, sizeof(reuse_addr)),
+		   EADDRINUSE);
+	TEST_SUCC(close(sk));
+}
+END_TEST()
+
+FN_TEST(rebind_with_reuse_addr)
+{
+	int sk_accepted;
+	int reuse;
+	socklen_t optlen;
+
+	sk_listen = TEST_SUCC(new_socket(1));
+
+	optlen = sizeof(reuse);
+	TEST_RES(getsockopt(sk_listen, SOL_SOCKET, SO_REUSEADDR, &reuse,
+			    &optlen),
+		 optlen == sizeof(reuse) && reuse == 1);
+
+	TEST_SUCC(bind(sk_listen, (struct sockaddr *)&reuse_addr,
+		       sizeof(reuse_addr)));
+	TEST_SUCC(listen(sk_listen, 1));
+
+	sk_client = TEST_SUCC(socket(PF_UNIX, SOCK_STREAM, 0));
+	TEST_SUCC(connect(sk_client, (struct sockaddr *)&reuse_addr,
+			  sizeof(reuse_addr)));
+	sk_accepted = TEST_SUCC(accept(sk_listen, NULL, NULL));
+	TEST_SUCC(close(sk_accepted));
+}
+END_TEST()
+
+FN_TEST(rebind_live_listener)
+{
+	int sk;
+
+	// The address is owned by a live listening socket, so it cannot be taken
+	// over even if `SO_REUSEADDR` is set.
+	sk = TEST_SUCC(new_socket(1));
+	TEST_ERRNO(bind(sk, (struct sockaddr *)&reuse_addr, sizeof(reuse_addr)),
+		   EADDRINUSE);
+	TEST_SUCC(close(sk));
+}
+END_TEST()
+
+FN_TEST(rebind_live_bound_socket)
+{
+	int sk_bound;
+	int sk;
+
+	TEST_SUCC(close(sk_listen));
+
+	// The address is also owned by a live socket that is only bound.
+	sk_bound = TEST_SUCC(new_socket(1));
+	TEST_SUCC(bind(sk_bound, (struct sockaddr *)&reuse_addr,
+		       sizeof(reuse_addr)));
+
+	sk = TEST_SUCC(new_socket(1));
+	TEST_ERRNO(bind(sk, (struct sockaddr *)&reuse_addr, sizeof(reuse_addr)),
+		   EADDRINUSE);
+
+	// The address can be reused once the socket is closed.
+	TEST_SUCC(close(sk_bound));
+	TEST_SUCC(bind(sk, (struct sockaddr *)&reuse_addr, sizeof(reuse_addr)));
+	TEST_SUCC(listen(sk, 1));
+	sk_listen = sk;
+}
+END_TEST()
+
+FN_SETUP(cleanup)
+{
+	CHECK(close(sk_client));
+	CHECK(close(sk_listen));
+	CHECK(unlink(REUSE_PATH));
+}
+END_SETUP()
@@ -0,0 +1,238 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include <fcntl.h>
+#include <sys/socket.h>
+#include <sys/un.h>
+#include <unistd.h>
+
+#include "test.h"
+
+static int sk_pair[2];
+
+FN_SETUP(socketpair)
+{
+	CHECK(socketpair(AF_UNIX, SOCK_STREAM, 0, sk_pair));
+}
+END_SETUP()
+
+static ssize_t send_fds(int sk, const int *fds, int nr_fds)
+{
+	char data = 'a';
+	struct iovec iov = { .iov_base = &data, .iov_len = 1 };
+	union {
+		char buf[CMSG_SPACE(sizeof(int) * 2)];
+		struct cmsghdr align;
+	} control;
+	struct msghdr msg = {
+		.msg_iov = &iov,
+		.msg_iovlen = 1,
+		.msg_control = control.buf,
+		.msg_controllen = CMSG_SPACE(sizeof(int) * nr_fds),
+	};
+	struct cmsghdr *cmsg;
+
+	memset(&control, 0, sizeof(control));
+	cmsg = CMSG_FIRSTHDR(&msg);
+	cmsg->cmsg_level = SOL_SOCKET;
+	cmsg->cmsg_type = SCM_RIGHTS;
+	cmsg->cmsg_len = CMSG_LEN(sizeof(int) * nr_fds);
+	memcpy(CMSG_DATA(cmsg), fds, sizeof(int) * nr_fds);
+
+	return sendmsg(sk, &msg, 0);
+}
+
+static struct msghdr recv_msg;
+
+// Receives one byte with a control buffer of `control_len` bytes, and returns the
+// received file descriptor, or -1 if there is none.
+static int recv_fd(int sk, size_t control_len)
+{
+	static char data;
+	static struct iovec iov = { .iov_base = &data, .iov_len = 1 };
+	static union {
+		char buf[CMSG_SPACE(sizeof(int) * 2)];
+		struct cmsghdr align;
+	} control;
+	struct cmsghdr *cmsg;
+	int fd;
+
+	memset(&control, 0, sizeof(control));
+	memset(&recv_msg, 0, sizeof(recv_msg));
+	recv_msg.msg_iov = &iov;
+	recv_msg.msg_iovlen = 1;
+	recv_msg.msg_control = control.buf;
+	recv_msg.msg_controllen = control_len;
+
+	if (recvmsg(sk, &recv_msg, 0) != 1)
+		return -2;
+
+	cmsg = CMSG_FIRSTHDR(&recv_msg);
+	if (cmsg == NULL)
+		return -1;
+	if (cmsg->cmsg_level != SOL_SOCKET || cmsg->cmsg_type != SCM_RIGHTS ||
+	    cmsg->cmsg_len != CMSG_LEN(sizeof(int)))
+		return -2;
+
+	memcpy(&fd, CMSG_DATA(cmsg), sizeof(int));
+	return fd;
+}
+
+static int pipe_fds[2];
+static int received_fd;
+
+FN_TEST(exactly_fitting_buffer)
+{
+	TEST_SUCC(pipe2(pipe_fds, O_NONBLOCK));
+
+	TEST_RES(send_fds(sk_pair[0], &pipe_fds[0], 1), _ret == 1);
+	received_fd = TEST_RES(recv_fd(sk_pair[1], CMSG_LEN(sizeof(int))),
+			       _ret >= 0 && !(recv_msg.msg_flags & MSG_CTRUNC) &&
+				       recv_msg.msg_controllen ==
+					       CMSG_LEN(sizeof(int)));
+
+	// The received file is the read end of the pipe.
+	TEST_RES(write(pipe_fds[1], "b", 1), _ret == 1);
+	TEST_RES(read(received_fd, &(char){ 0 }, 1), _ret == 1);
+
+	TEST_SUCC(close(received_fd));
+	TEST_SUCC(close(pipe_fds[0]));
+	TEST_SUCC(close(pipe_fds[1]));
+}
+END_TEST()
+
+FN_TEST(one_byte_short_buffer)
+{
+	TEST_SUCC(pipe2(pipe_fds, O_NONBLOCK));
+
+	TEST_RES(send_fds(sk_pair[0], &pipe_fds[1], 1), _ret == 1);
+	TEST_SUCC(close(pipe_fds[1]));
+
+	// No file fits, so the write end of the pipe is closed.
+	TEST_RES(recv_fd(sk_pair[1], CMSG_LEN(sizeof(int)) - 1),
+		 _ret == -1 && (recv_msg.msg_flags & MSG_CTRUNC) &&
+			 recv_msg.msg_controllen == 0);
+	TEST_RES(read(pipe_fds[0], &(char){ 0 }, 1), _ret == 0);
+
+	TEST_SUCC(close(pipe_fds[0]));
+}
+END_TEST()
+
+FN_TEST(two_fds_for_one)
+{
+	TEST_SUCC(pipe2(pipe_fds, O_NONBLOCK));
+
+	TEST_RES(send_fds(sk_pair[0], pipe_fds, 2), _ret == 1);
+	TEST_SUCC(close(pipe_fds[1]));
+
+	// Only the read end of the pipe is received. The write end is closed, otherwise
+	// reading from the pipe would fail with EAGAIN instead of seeing the EOF.
+	received_fd = TEST_RES(recv_fd(sk_pair[1], CMSG_LEN(sizeof(int))),
+			       _ret >= 0 && (recv_msg.msg_flags & MSG_CTRUNC));
+	TEST_RES(read(received_fd, &(char){ 0 }, 1), _ret == 0);
+
+	TEST_SUCC(close(received_fd));
+	TEST_SUCC(close(pipe_fds[0]));
+}
+END_TEST()
+
+// Receives up to `len` bytes, and stores the received file descriptor in `fd`, or -1
+// if there is none.
+static ssize_t recv_data_fd(int sk, char *buf, size_t len, int *fd)
+{
+	struct iovec iov = { .iov_base = buf, .iov_len = len };
+	union {
+		char buf[CMSG_SPACE(sizeof(int))];
+		struct cmsghdr align;
+	} control;
+	struct msghdr msg = {
+		.msg_iov = &iov,
+		.msg_iovlen = 1,
+		.msg_control = control.buf,
+		.msg_controllen = sizeof(control.buf),
+	};
+	struct cmsghdr *cmsg;
+	ssize_t ret;
+
+	ret = recvmsg(sk, &msg, 0);
+	if (ret < 0)
+		return ret;
+
+	*fd = -1;
+	cmsg = CMSG_FIRSTHDR(&msg);
+	if (cmsg != NULL)
+		memcpy(fd, CMSG_DATA(cmsg), sizeof(int));
+
+	return ret;
+}
+
+FN_TEST(rights_attached_to_data)
+{
+	char buf[2];
+	int fd;
+
+	TEST_SUCC(pipe2(pipe_fds, O_NONBLOCK));
+
+	// The files are received by the read that reaches the data sent with them.
+	TEST_RES(write(sk_pair[0], "x", 1), _ret == 1);
+	TEST_RES(send_fds(sk_pair[0], &pipe_fds[0], 1), _ret == 1);
+	TEST_RES(recv_data_fd(sk_pair[1], buf, sizeof(buf), &fd),
+		 _ret == 2 && buf[0] == 'x' && buf[1] == 'a' && fd >= 0);
+	TEST_SUCC(close(fd));
+
+	// A read stops at the end of the data sent with the files.
+	TEST_RES(send_fds(sk_pair[0], &pipe_fds[0], 1), _ret == 1);
+	TEST_RES(send_fds(sk_pair[0], &pipe_fds[1], 1), _ret == 1);
+	TEST_RES(recv_data_fd(sk_pair[1], buf, sizeof(buf), &fd),
+		 _ret == 1 && fd >= 0);
+	TEST_SUCC(close(fd));
+	TEST_RES(recv_data_fd(sk_pair[1], buf, sizeof(buf), &fd),
+		 _ret == 1 && fd >= 0);
+	TEST_SUCC(close(fd));
+
+	// The files are dropped if the data is read without them.
+	TEST_RES(send_fds(sk_pair[0], &pipe_fds[1], 1), _ret == 1);
+	TEST_RES(read(sk_pair[1], buf, sizeof(buf)), _ret == 1);
+	TEST_RES(write(sk_pair[0], "y", 1), _ret == 1);
+	TEST_RES(recv_data_fd(sk_pair[1], buf, sizeof(buf), &fd),
+		 _ret == 1 && buf[0] == 'y' && fd == -1);
+
+	TEST_SUCC(close(pipe_fds[0]));
+	TEST_SUCC(close(pipe_fds[1]));
+}
+END_TEST()
+
+#define DGRAM_PATH "/tmp/R1"
+
+FN_TEST(datagram_rights)
+{
+	struct sockaddr_un addr = { .sun_family = AF_UNIX,
+				    .sun_path = DGRAM_PATH };
+	int sk_recv;
+	int sk_send;
+
+	TEST_SUCC(pipe2(pipe_fds, O_NONBLOCK));
+
+	unlink(DGRAM_PATH);
+	sk_recv = TEST_SUCC(socket(PF_UNIX, SOCK_DGRAM, 0));
+	TEST_SUCC(bind(sk_recv, (struct sockaddr *)&addr, sizeof(addr)));
+	sk_send = TEST_SUCC(socket(PF_UNIX, SOCK_DGRAM, 0));
+	TEST_SUCC(connect(sk_send, (struct sockaddr *)&addr, sizeof(addr)));
+
+	TEST_RES(send_fds(sk_send, &pipe_fds[0], 1), _ret == 1);
+	received_fd = TEST_RES(recv_fd(sk_recv, CMSG_LEN(sizeof(int))),
+			       _ret >= 0);
+
+	// The received file is the read end of the pipe.
+	TEST_RES(write(pipe_fds[1], "b", 1), _ret == 1);
+	TEST_RES(read(received_fd, &(char){ 0 }, 1), _ret == 1);
+
+	TEST_SUCC(close(received_fd));
+	TEST_SUCC(close(sk_send));
+	TEST_SUCC(close(sk_recv));
+	TEST_SUCC(unlink(DGRAM_PATH));
+	TEST_SUCC(close(pipe_fds[0]));
+	TEST_SUCC(close(pipe_fds[1]));
+}
+END_TEST()
@@ -0,0 +1,105 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include <poll.h>
+#include <sys/epoll.h>
+#include <sys/socket.h>
+#include <unistd.h>
+
+#include "test.h"
+
+#define ALL_EVENTS (POLLIN | POLLOUT | POLLRDHUP | POLLHUP | POLLERR)
+
+static int poll_events(int sk)
+{
+	struct pollfd pfd = { .fd = sk, .events = ALL_EVENTS };
+
+	CHECK(poll(&pfd, 1, 0));
+	return pfd.revents;
+}
+
+static int sk_pair[2];
+
+FN_SETUP(socketpair)
+{
+	CHECK(socketpair(AF_UNIX, SOCK_STREAM, 0, sk_pair));
+}
+END_SETUP()
+
+FN_TEST(connected)
+{
+	TEST_RES(poll_events(sk_pair[0]), _ret == POLLOUT);
+	TEST_RES(poll_events(sk_pair[1]), _ret == POLLOUT);
+}
+END_TEST()
+
+FN_TEST(shut_read)
+{
+	TEST_SUCC(shutdown(sk_pair[0], SHUT_RD));
+
+	// The read side is shut down, so EOF can be read.
+	TEST_RES(poll_events(sk_pair[0]), _ret == (POLLIN | POLLOUT | POLLRDHUP));
+	// The peer can no longer send, but it is still reported to be writable.
+	TEST_RES(poll_events(sk_pair[1]), _ret == POLLOUT);
+}
+END_TEST()
+
+FN_TEST(shut_write)
+{
+	TEST_SUCC(shutdown(sk_pair[0], SHUT_WR));
+
+	// Both directions of the first socket are shut down.
+	TEST_RES(poll_events(sk_pair[0]),
+		 _ret == (POLLIN | POLLOUT | POLLRDHUP | POLLHUP));
+	TEST_RES(poll_events(sk_pair[1]),
+		 _ret == (POLLIN | POLLOUT | POLLRDHUP | POLLHUP));
+}
+END_TEST()
+
+FN_SETUP(cleanup)
+{
+	CHECK(close(sk_pair[0]));
+	CHECK(close(sk_pair[1]));
+}
+END_SETUP()
+
+FN_SETUP(socketpair_again)
+{
+	CHECK(socketpair(AF_UNIX, SOCK_STREAM, 0, sk_pair));
+}
+END_SETUP()
+
+FN_TEST(shut_write_only)
+{
+	TEST_SUCC(shutdown(sk_pair[1], SHUT_WR));
+
+	TEST_RES(poll_events(sk_pair[0]), _ret == (POLLIN | POLLOUT | POLLRDHUP));
+	TEST_RES(poll_events(sk_pair[1]), _ret == POLLOUT);
+}
+END_TEST()
+
+FN_TEST(epoll_rdhup)
+{
+	struct epoll_event ev = { .events = EPOLLRDHUP };
+	int epfd;
+
+	// The second socket is waiting for the first one to shut down writing.
+	epfd = TEST_SUCC(epoll_create1(0));
+	TEST_SUCC(epoll_ctl(epfd, EPOLL_CTL_ADD, sk_pair[1], &ev));
+	TEST_RES(epoll_wait(epfd, &ev, 1, 0), _ret == 0);
+
+	TEST_SUCC(shutdown(sk_pair[0], SHUT_WR));
+	TEST_RES(epoll_wait(epfd, &ev, 1, 0),
+		 _ret == 1 && ev.events == (EPOLLRDHUP | EPOLLHUP));
+
+	TEST_SUCC(close(epfd));
+}
+END_TEST()
+
+FN_SETUP(cleanup_again)
+{
+	CHECK(close(sk_pair[0]));
+	CHECK(close(sk_pair[1]));
+}
+END_SETUP()
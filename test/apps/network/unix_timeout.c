@@ -0,0 +1,184 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include <sys/socket.h>
+#include <sys/time.h>
+#include <sys/un.h>
+#include <sys/wait.h>
+#include <time.h>
+#include <unistd.h>
+
+#include "test.h"
+
+#define TIMEOUT_MS 100
+
+static int sk_pair[2];
+static char buf[4096];
+
+FN_SETUP(socketpair)
+{
+	CHECK(socketpair(AF_UNIX, SOCK_STREAM, 0, sk_pair));
+}
+END_SETUP()
+
+static long now_ms(void)
+{
+	struct timespec ts;
+
+	clock_gettime(CLOCK_MONOTONIC, &ts);
+	return ts.tv_sec * 1000 + ts.tv_nsec / 1000000;
+}
+
+static int set_timeout(int sk, int opt, long ms)
+{
+	struct timeval tv = { .tv_sec = ms / 1000,
+			      .tv_usec = (ms % 1000) * 1000 };
+
+	return setsockopt(sk, SOL_SOCKET, opt, &tv, sizeof(tv));
+}
+
+FN_TEST(get_and_set_timeout)
+{
+	struct timeval tv;
+	socklen_t optlen = sizeof(tv);
+
+	TEST_RES(getsockopt(sk_pair[0], SOL_SOCKET, SO_RCVTIMEO, &tv, &optlen),
+		 optlen == sizeof(tv) && tv.tv_sec == 0 && tv.tv_usec == 0);
+
+	TEST_SUCC(set_timeout(sk_pair[0], SO_RCVTIMEO, 1500));
+	TEST_RES(getsockopt(sk_pair[0], SOL_SOCKET, SO_RCVTIMEO, &tv, &optlen),
+		 optlen == sizeof(tv) && tv.tv_sec == 1 &&
+			 tv.tv_usec == 500000);
+	TEST_RES(getsockopt(sk_pair[0], SOL_SOCKET, SO_SNDTIMEO, &tv, &optlen),
+		 optlen == sizeof(tv) && tv.tv_sec == 0 && tv.tv_usec == 0);
+
+	tv.tv_sec = 0;
+	tv.tv_usec = 1000000;
+	TEST_ERRNO(setsockopt(sk_pair[0], SOL_SOCKET, SO_SNDTIMEO, &tv,
+			      sizeof(tv)),
+		   EDOM);
+
+	TEST_SUCC(set_timeout(sk_pair[0], SO_RCVTIMEO, 0));
+}
+END_TEST()
+
+FN_TEST(recv_timeout)
+{
+	long start;
+
+	TEST_SUCC(set_timeout(sk_pair[0], SO_RCVTIMEO, TIMEOUT_MS));
+
+	start = now_ms();
+	TEST_ERRNO(read(sk_pair[0], buf, 1), EAGAIN);
+	TEST_RES(now_ms() - start, _ret >= TIMEOUT_MS - 10);
+
+	// Pending data is returned immediately.
+	TEST_RES(write(sk_pair[1], "a", 1), _ret == 1);
+	TEST_RES(read(sk_pair[0], buf, 1), _ret == 1);
+
+	TEST_SUCC(set_timeout(sk_pair[0], SO_RCVTIMEO, 0));
+}
+END_TEST()
+
+FN_TEST(send_timeout)
+{
+	long start;
+
+	TEST_SUCC(set_timeout(sk_pair[0], SO_SNDTIMEO, TIMEOUT_MS));
+
+	// Fill the send buffer. The last write times out.
+	while (write(sk_pair[0], buf, sizeof(buf)) > 0)
+		;
+
+	start = now_ms();
+	TEST_ERRNO(write(sk_pair[0], buf, sizeof(buf)), EAGAIN);
+	TEST_RES(now_ms() - start, _ret >= TIMEOUT_MS - 10);
+
+	// Drain the buffer.
+	TEST_SUCC(set_timeout(sk_pair[1], SO_RCVTIMEO, TIMEOUT_MS));
+	while (read(sk_pair[1], buf, sizeof(buf)) > 0)
+		;
+
+	TEST_SUCC(set_timeout(sk_pair[0], SO_SNDTIMEO, 0));
+	TEST_SUCC(set_timeout(sk_pair[1], SO_RCVTIMEO, 0));
+}
+END_TEST()
+
+FN_TEST(zero_timeout_blocks)
+{
+	int pid, status;
+
+	// A zero timeout means no timeout. The read waits for the delayed write.
+	TEST_SUCC(set_timeout(sk_pair[0], SO_RCVTIMEO, 0));
+
+	pid = TEST_SUCC(fork());
+	if (pid == 0) {
+		usleep(2 * TIMEOUT_MS * 1000);
+		exit(write(sk_pair[1], "a", 1) != 1);
+	}
+
+	TEST_RES(read(sk_pair[0], buf, 1), _ret == 1);
+	TEST_RES(wait(&status), _ret == pid && WIFEXITED(status) &&
+					WEXITSTATUS(status) == 0);
+}
+END_TEST()
+
+#define DGRAM_ADDR \
+	((struct sockaddr_un){ .sun_family = AF_UNIX, .sun_path = "/tmp/T0" })
+
+static int sk_dgram;
+
+FN_SETUP(dgram)
+{
+	struct sockaddr_un addr = DGRAM_ADDR;
+
+	sk_dgram = CHECK(socket(AF_UNIX, SOCK_DGRAM, 0));
+	CHECK(bind(sk_dgram, (struct sockaddr *)&addr, sizeof(addr)));
+	// Send the datagrams to the socket itself
+	CHECK(connect(sk_dgram, (struct sockaddr *)&addr, sizeof(addr)));
+}
+END_SETUP()
+
+FN_TEST(dgram_recv_timeout)
+{
+	long start;
+
+	TEST_SUCC(set_timeout(sk_dgram, SO_RCVTIMEO, TIMEOUT_MS));
+
+	start = now_ms();
+	TEST_ERRNO(recv(sk_dgram, buf, 1, 0), EAGAIN);
+	TEST_RES(now_ms() - start, _ret >= TIMEOUT_MS - 10);
+
+	// Pending datagrams are returned immediately.
+	TEST_RES(send(sk_dgram, "a", 1, 0), _ret == 1);
+	TEST_RES(recv(sk_dgram, buf, 1, 0), _ret == 1);
+
+	TEST_SUCC(set_timeout(sk_dgram, SO_RCVTIMEO, 0));
+}
+END_TEST()
+
+FN_TEST(dgram_send_timeout)
+{
+	long start;
+
+	TEST_SUCC(set_timeout(sk_dgram, SO_SNDTIMEO, TIMEOUT_MS));
+
+	// Fill the inbox. The last send times out.
+	while (send(sk_dgram, buf, sizeof(buf), 0) > 0)
+		;
+
+	start = now_ms();
+	TEST_ERRNO(send(sk_dgram, buf, sizeof(buf), 0), EAGAIN);
+	TEST_RES(now_ms() - start, _ret >= TIMEOUT_MS - 10);
+
+	TEST_SUCC(set_timeout(sk_dgram, SO_SNDTIMEO, 0));
+}
+END_TEST()
+
+FN_SETUP(cleanup)
+{
+	CHECK(close(sk_dgram));
+	CHECK(unlink(DGRAM_ADDR.sun_path));
+}
+END_SETUP()
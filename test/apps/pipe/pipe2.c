@@ -0,0 +1,121 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include <fcntl.h>
+#include <limits.h>
+#include <signal.h>
+#include <unistd.h>
+
+#include "../network/test.h"
+
+#define PIPE_CAPACITY 65536
+
+static char buf[PIPE_CAPACITY * 2];
+static volatile int nr_sigpipe;
+
+static void sigpipe_handler(int sig)
+{
+	nr_sigpipe += 1;
+}
+
+FN_SETUP(sigpipe)
+{
+	CHECK_WITH((long)signal(SIGPIPE, sigpipe_handler), _ret != (long)SIG_ERR);
+}
+END_SETUP()
+
+FN_TEST(invalid_flags)
+{
+	int fildes[2];
+
+	TEST_ERRNO(pipe2(fildes, O_APPEND), EINVAL);
+}
+END_TEST()
+
+FN_TEST(flags)
+{
+	int fildes[2];
+
+	TEST_SUCC(pipe2(fildes, O_NONBLOCK | O_CLOEXEC));
+
+	TEST_RES(fcntl(fildes[0], F_GETFD), _ret == FD_CLOEXEC);
+	TEST_RES(fcntl(fildes[1], F_GETFD), _ret == FD_CLOEXEC);
+	TEST_RES(fcntl(fildes[0], F_GETFL), _ret == (O_RDONLY | O_NONBLOCK));
+	TEST_RES(fcntl(fildes[1], F_GETFL), _ret == (O_WRONLY | O_NONBLOCK));
+
+	TEST_SUCC(close(fildes[0]));
+	TEST_SUCC(close(fildes[1]));
+
+	TEST_SUCC(pipe2(fildes, 0));
+
+	TEST_RES(fcntl(fildes[0], F_GETFD), _ret == 0);
+	TEST_RES(fcntl(fildes[1], F_GETFL), _ret == O_WRONLY);
+
+	TEST_SUCC(close(fildes[0]));
+	TEST_SUCC(close(fildes[1]));
+}
+END_TEST()
+
+FN_TEST(nonblocking_read_write)
+{
+	int fildes[2];
+	char rbuf[4];
+
+	TEST_SUCC(pipe2(fildes, O_NONBLOCK));
+
+	TEST_ERRNO(read(fildes[0], rbuf, sizeof(rbuf)), EAGAIN);
+
+	TEST_RES(write(fildes[1], "abcd", 4), _ret == 4);
+	TEST_RES(read(fildes[0], rbuf, sizeof(rbuf)),
+		 _ret == 4 && memcmp(rbuf, "abcd", 4) == 0);
+	TEST_ERRNO(read(fildes[0], rbuf, sizeof(rbuf)), EAGAIN);
+
+	TEST_SUCC(close(fildes[0]));
+	TEST_SUCC(close(fildes[1]));
+}
+END_TEST()
+
+FN_TEST(nonblocking_write_full)
+{
+	int fildes[2];
+
+	TEST_SUCC(pipe2(fildes, O_NONBLOCK));
+
+	// A large write is partial.
+	TEST_RES(write(fildes[1], buf, sizeof(buf)), _ret == PIPE_CAPACITY);
+	TEST_ERRNO(write(fildes[1], buf, 1), EAGAIN);
+
+	TEST_RES(read(fildes[0], buf, PIPE_BUF), _ret == PIPE_BUF);
+	TEST_RES(write(fildes[1], buf, PIPE_BUF + 1), _ret == PIPE_BUF);
+
+	// A small write is atomic.
+	TEST_RES(read(fildes[0], buf, PIPE_CAPACITY), _ret == PIPE_CAPACITY);
+	TEST_RES(write(fildes[1], buf, PIPE_CAPACITY - 1),
+		 _ret == PIPE_CAPACITY - 1);
+	TEST_ERRNO(write(fildes[1], buf, 2), EAGAIN);
+	TEST_RES(write(fildes[1], buf, 1), _ret == 1);
+
+	TEST_SUCC(close(fildes[0]));
+	TEST_SUCC(close(fildes[1]));
+}
+END_TEST()
+
+FN_TEST(broken_pipe)
+{
+	int fildes[2];
+
+	TEST_SUCC(pipe2(fildes, 0));
+	TEST_SUCC(close(fildes[0]));
+
+	nr_sigpipe = 0;
+	TEST_ERRNO(write(fildes[1], buf, 1), EPIPE);
+	TEST_RES(nr_sigpipe, _ret == 1);
+
+	// Writing nothing does not fail.
+	TEST_RES(write(fildes[1], buf, 0), _ret == 0);
+	TEST_RES(nr_sigpipe, _ret == 1);
+
+	TEST_SUCC(close(fildes[1]));
+}
+END_TEST()
@@ -3,6 +3,8 @@
 #define _GNU_SOURCE
 
 #include "../network/test.h"
+#include <fcntl.h>
+#include <limits.h>
 #include <signal.h>
 #include <string.h>
 #include <sys/poll.h>
@@ -176,3 +178,31 @@ FN_TEST(close_second_then_poll)
 	TEST_SUCC(close(fildes[0]));
 }
 END_TEST()
+
+FN_TEST(nearly_full_then_poll)
+{
+	int fildes[2];
+	static char buf[65536 - 100];
+	struct pollfd pfd = { .events = POLLOUT };
+
+	CHECK(pipe2(fildes, O_NONBLOCK));
+
+	// The remaining room cannot hold an atomic write of `PIPE_BUF` bytes, so
+	// the pipe is not writable.
+	TEST_RES(write(fildes[1], buf, sizeof(buf)), _ret == sizeof(buf));
+	TEST_ERRNO(write(fildes[1], buf, PIPE_BUF), EAGAIN);
+
+	pfd.fd = fildes[1];
+	TEST_RES(poll(&pfd, 1, 0), _ret == 0);
+
+	TEST_RES(read(fildes[0], buf, PIPE_BUF), _ret == PIPE_BUF);
+
+	pfd.fd = fildes[1];
+	TEST_RES(poll(&pfd, 1, 0),
+		 _ret == 1 && (pfd.revents & POLL_MASK) == POLLOUT);
+	TEST_RES(write(fildes[1], buf, PIPE_BUF), _ret == PIPE_BUF);
+
+	TEST_SUCC(close(fildes[0]));
+	TEST_SUCC(close(fildes[1]));
+}
+END_TEST()
@@ -0,0 +1,69 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include <pthread.h>
+#include <signal.h>
+#include <sys/syscall.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+#include "../network/test.h"
+
+static volatile int leader_exited;
+static volatile sig_atomic_t received;
+
+static void handle_sigusr1(int signum)
+{
+	received = 1;
+}
+
+static void *worker(void *arg)
+{
+	int i;
+
+	while (!leader_exited)
+		usleep(1000);
+	// Give the leader some time to actually exit
+	usleep(100 * 1000);
+
+	// Only the worker can handle the process-directed signal now
+	if (kill(getpid(), SIGUSR1) < 0)
+		_exit(1);
+	for (i = 0; i < 100 && !received; ++i)
+		usleep(1000);
+
+	_exit(received ? 42 : 2);
+}
+
+static void run_thread_group(void)
+{
+	pthread_t thread;
+
+	if (signal(SIGUSR1, handle_sigusr1) == SIG_ERR)
+		_exit(3);
+	if (pthread_create(&thread, NULL, worker, NULL) != 0)
+		_exit(4);
+
+	// Exit the leader only. `pthread_exit` cannot be used in constructors,
+	// where the test functions run.
+	leader_exited = 1;
+	syscall(SYS_exit, 0);
+}
+
+FN_TEST(leader_exits_first)
+{
+	int status;
+	pid_t pid;
+
+	// The process lives on after the leader exits, until the last thread
+	// exits. So the exit status comes from the worker.
+	pid = fork();
+	if (pid == 0)
+		run_thread_group();
+	TEST_SUCC(pid);
+
+	TEST_RES(waitpid(pid, &status, 0),
+		 _ret == pid && WIFEXITED(status) && WEXITSTATUS(status) == 42);
+}
+END_TEST()
@@ -0,0 +1,99 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include <errno.h>
+#include <string.h>
+#include <sys/resource.h>
+#include <sys/syscall.h>
+#include <sys/wait.h>
+#include <unistd.h>
+#include <linux/capability.h>
+
+#include "../network/test.h"
+
+FN_TEST(default_nice)
+{
+	TEST_RES(getpriority(PRIO_PROCESS, 0), _ret == 0);
+	TEST_RES(getpriority(PRIO_PROCESS, getpid()), _ret == 0);
+	TEST_RES(getpriority(PRIO_PGRP, 0), _ret == 0);
+}
+END_TEST()
+
+FN_TEST(set_nice)
+{
+	TEST_RES(nice(5), _ret == 5);
+	TEST_RES(getpriority(PRIO_PROCESS, 0), _ret == 5);
+
+	TEST_SUCC(setpriority(PRIO_PROCESS, 0, -5));
+	TEST_RES(getpriority(PRIO_PROCESS, 0), _ret == -5);
+
+	// Out-of-range values are clamped
+	TEST_SUCC(setpriority(PRIO_PROCESS, 0, 100));
+	TEST_RES(getpriority(PRIO_PROCESS, 0), _ret == 19);
+	TEST_SUCC(setpriority(PRIO_PROCESS, 0, -100));
+	TEST_RES(getpriority(PRIO_PROCESS, 0), _ret == -20);
+
+	TEST_SUCC(setpriority(PRIO_PROCESS, 0, 0));
+}
+END_TEST()
+
+FN_TEST(inherit_nice)
+{
+	int pid, status;
+
+	TEST_SUCC(setpriority(PRIO_PROCESS, 0, 3));
+
+	pid = fork();
+	if (pid == 0)
+		_exit(getpriority(PRIO_PROCESS, 0) == 3 ? 0 : 1);
+	TEST_SUCC(pid);
+
+	TEST_RES(waitpid(pid, &status, 0),
+		 _ret == pid && WIFEXITED(status) && WEXITSTATUS(status) == 0);
+
+	TEST_SUCC(setpriority(PRIO_PROCESS, 0, 0));
+}
+END_TEST()
+
+FN_TEST(invalid_args)
+{
+	TEST_ERRNO(setpriority(42, 0, 0), EINVAL);
+	TEST_ERRNO(getpriority(42, 0), EINVAL);
+	TEST_ERRNO(setpriority(PRIO_PROCESS, 0x7fffffff, 0), ESRCH);
+	TEST_ERRNO(getpriority(PRIO_PROCESS, 0x7fffffff), ESRCH);
+}
+END_TEST()
+
+FN_TEST(unprivileged)
+{
+	struct __user_cap_header_struct header = {
+		.version = _LINUX_CAPABILITY_VERSION_3,
+		.pid = 0,
+	};
+	struct __user_cap_data_struct data[2];
+	int pid, status;
+
+	// The child drops all its capabilities, so it can lower its priority
+	// but cannot raise it again.
+	pid = fork();
+	if (pid == 0) {
+		memset(data, 0, sizeof(data));
+		if (syscall(SYS_capset, &header, data) < 0)
+			_exit(1);
+		if (nice(2) != 2)
+			_exit(2);
+		if (setpriority(PRIO_PROCESS, 0, 1) != -1 || errno != EACCES)
+			_exit(3);
+		if (getpriority(PRIO_PROCESS, 0) != 2)
+			_exit(4);
+		if (setpriority(PRIO_PROCESS, 0, 10) != 0)
+			_exit(5);
+		_exit(0);
+	}
+	TEST_SUCC(pid);
+
+	TEST_RES(waitpid(pid, &status, 0),
+		 _ret == pid && WIFEXITED(status) && WEXITSTATUS(status) == 0);
+}
+END_TEST()
@@ -0,0 +1,142 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include <errno.h>
+#include <sched.h>
+#include <string.h>
+#include <sys/resource.h>
+#include <sys/syscall.h>
+#include <sys/wait.h>
+#include <unistd.h>
+#include <linux/capability.h>
+
+#include "../network/test.h"
+
+FN_TEST(default_policy)
+{
+	TEST_RES(sched_getscheduler(0), _ret == SCHED_OTHER);
+}
+END_TEST()
+
+FN_TEST(set_fifo)
+{
+	struct sched_param param = { .sched_priority = 50 };
+
+	TEST_SUCC(sched_setscheduler(0, SCHED_FIFO, &param));
+	TEST_RES(sched_getscheduler(0), _ret == SCHED_FIFO);
+	TEST_RES(sched_getscheduler(getpid()), _ret == SCHED_FIFO);
+}
+END_TEST()
+
+FN_TEST(set_rr_and_back)
+{
+	struct sched_param param = { .sched_priority = 1 };
+
+	TEST_SUCC(sched_setscheduler(0, SCHED_RR, &param));
+	TEST_RES(sched_getscheduler(0), _ret == SCHED_RR);
+
+	param.sched_priority = 0;
+	TEST_SUCC(sched_setscheduler(0, SCHED_OTHER, &param));
+	TEST_RES(sched_getscheduler(0), _ret == SCHED_OTHER);
+}
+END_TEST()
+
+FN_TEST(keep_nice)
+{
+	struct sched_param param = { .sched_priority = 1 };
+
+	TEST_SUCC(setpriority(PRIO_PROCESS, 0, 5));
+	TEST_SUCC(sched_setscheduler(0, SCHED_RR, &param));
+
+	param.sched_priority = 0;
+	TEST_SUCC(sched_setscheduler(0, SCHED_OTHER, &param));
+	TEST_RES(getpriority(PRIO_PROCESS, 0), _ret == 5);
+
+	TEST_SUCC(setpriority(PRIO_PROCESS, 0, 0));
+}
+END_TEST()
+
+FN_TEST(invalid_args)
+{
+	struct sched_param param = { .sched_priority = 100 };
+
+	TEST_ERRNO(sched_setscheduler(0, SCHED_FIFO, &param), EINVAL);
+	param.sched_priority = 0;
+	TEST_ERRNO(sched_setscheduler(0, SCHED_FIFO, &param), EINVAL);
+	param.sched_priority = 1;
+	TEST_ERRNO(sched_setscheduler(0, SCHED_OTHER, &param), EINVAL);
+	TEST_ERRNO(sched_setscheduler(0, 42, &param), EINVAL);
+	TEST_ERRNO(sched_getscheduler(-1), EINVAL);
+	TEST_ERRNO(sched_getscheduler(0x7fffffff), ESRCH);
+}
+END_TEST()
+
+FN_TEST(unprivileged)
+{
+	struct __user_cap_header_struct header = {
+		.version = _LINUX_CAPABILITY_VERSION_3,
+		.pid = 0,
+	};
+	struct __user_cap_data_struct data[2];
+	struct sched_param param = { .sched_priority = 50 };
+	int pid, status;
+
+	// The child drops all its capabilities before asking for a real-time
+	// policy.
+	pid = fork();
+	if (pid == 0) {
+		memset(data, 0, sizeof(data));
+		if (syscall(SYS_capset, &header, data) < 0)
+			_exit(1);
+		if (sched_setscheduler(0, SCHED_FIFO, &param) != -1 ||
+		    errno != EPERM)
+			_exit(2);
+		if (sched_getscheduler(0) != SCHED_OTHER)
+			_exit(3);
+		_exit(0);
+	}
+	TEST_SUCC(pid);
+
+	TEST_RES(waitpid(pid, &status, 0),
+		 _ret == pid && WIFEXITED(status) && WEXITSTATUS(status) == 0);
+}
+END_TEST()
+
+FN_TEST(unprivileged_within_rlimit)
+{
+	struct __user_cap_header_struct header = {
+		.version = _LINUX_CAPABILITY_VERSION_3,
+		.pid = 0,
+	};
+	struct __user_cap_data_struct data[2];
+	struct rlimit rlimit = { .rlim_cur = 10, .rlim_max = 10 };
+	struct sched_param param;
+	int pid, status;
+
+	// Without capabilities, the child can set a real-time policy only up to
+	// the priority allowed by `RLIMIT_RTPRIO`.
+	pid = fork();
+	if (pid == 0) {
+		memset(data, 0, sizeof(data));
+		if (setrlimit(RLIMIT_RTPRIO, &rlimit) < 0)
+			_exit(1);
+		if (syscall(SYS_capset, &header, data) < 0)
+			_exit(2);
+		param.sched_priority = 11;
+		if (sched_setscheduler(0, SCHED_FIFO, &param) != -1 ||
+		    errno != EPERM)
+			_exit(3);
+		param.sched_priority = 10;
+		if (sched_setscheduler(0, SCHED_FIFO, &param) < 0)
+			_exit(4);
+		if (sched_getscheduler(0) != SCHED_FIFO)
+			_exit(5);
+		_exit(0);
+	}
+	TEST_SUCC(pid);
+
+	TEST_RES(waitpid(pid, &status, 0),
+		 _ret == pid && WIFEXITED(status) && WEXITSTATUS(status) == 0);
+}
+END_TEST()
@@ -61,4 +61,8 @@ echo "Start fdatasync test......"
 test_fdatasync
 echo "All fdatasync test passed."
 
+pipe/pipe2
 pipe/pipe_err
+file_io/dup
+file_io/truncate
+file_io/user_ptr
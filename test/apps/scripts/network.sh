@@ -24,5 +24,16 @@ echo "Start network test......"
 ./tcp_err
 ./udp_err
 ./unix_err
+./unix_cred
+./unix_connect
+./unix_linger
+./unix_proc
+./unix_dgram
+./unix_reuse
+./unix_lowat
+./unix_shutdown
+./unix_timeout
+./unix_rights
+./unix_oob
 
 echo "All network test passed"
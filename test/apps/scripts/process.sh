@@ -10,9 +10,12 @@ cd ${SCRIPT_DIR}/..
 echo "Start process test......"
 # These test programs are sorted by name.
 tests="
+clock/clock
 clone3/clone_process
+epoll/epoll_modes
 execve/execve
 eventfd2/eventfd2
+eventfd2/eventfd_modes
 fork/fork
 fork_c/fork
 getpid/getpid
@@ -20,12 +23,24 @@ hello_pie/hello
 hello_world/hello_world
 itimer/setitimer
 itimer/timer_create
+mmap/mlock
 mmap/mmap_and_fork
 mmap/mmap_shared_filebacked
+mmap/mremap
+mmap/stack_growth
+mmap/zero_page
 pthread/pthread_test
+pthread/thread_group
 pty/open_pty
+sched/nice
+sched/sched_setscheduler
+signal_c/fault_signal
 signal_c/parent_death_signal
+signal_c/rt_signal
+signal_c/sa_restart
 signal_c/signal_test
+signal_c/signalfd
+timerfd/timerfd
 "
 
 for testcase in ${tests}
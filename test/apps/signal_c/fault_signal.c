@@ -0,0 +1,152 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include <fcntl.h>
+#include <setjmp.h>
+#include <signal.h>
+#include <sys/mman.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+#include "../network/test.h"
+
+#define PAGE_SIZE 4096
+
+static sigjmp_buf jmp_env;
+static volatile int fault_signo;
+static volatile int fault_code;
+static void *volatile fault_addr;
+
+static void handle_fault(int signo, siginfo_t *info, void *context)
+{
+	fault_signo = info->si_signo;
+	fault_code = info->si_code;
+	fault_addr = info->si_addr;
+	siglongjmp(jmp_env, 1);
+}
+
+// Returns 1 if accessing `addr` raises a fault signal, or 0 otherwise.
+static int access_addr(volatile char *addr, int write)
+{
+	fault_signo = 0;
+	fault_code = 0;
+	fault_addr = NULL;
+
+	if (sigsetjmp(jmp_env, 1) != 0)
+		return 1;
+
+	if (write)
+		*addr = 'a';
+	else
+		(void)*addr;
+
+	return 0;
+}
+
+static char *unmapped_page;
+static char *readonly_page;
+
+FN_SETUP(signal_handler)
+{
+	struct sigaction action = {
+		.sa_sigaction = handle_fault,
+		.sa_flags = SA_SIGINFO,
+	};
+
+	CHECK(sigaction(SIGSEGV, &action, NULL));
+}
+END_SETUP()
+
+FN_SETUP(pages)
+{
+	readonly_page = (char *)CHECK_WITH(
+		(long)mmap(NULL, PAGE_SIZE * 2, PROT_READ | PROT_WRITE,
+			   MAP_PRIVATE | MAP_ANONYMOUS, -1, 0),
+		_ret != (long)MAP_FAILED);
+	unmapped_page = readonly_page + PAGE_SIZE;
+
+	CHECK(mprotect(readonly_page, PAGE_SIZE, PROT_READ));
+	CHECK(munmap(unmapped_page, PAGE_SIZE));
+}
+END_SETUP()
+
+FN_TEST(read_unmapped)
+{
+	TEST_RES(access_addr(unmapped_page + 1, 0),
+		 _ret == 1 && fault_signo == SIGSEGV &&
+			 fault_code == SEGV_MAPERR &&
+			 fault_addr == unmapped_page + 1);
+}
+END_TEST()
+
+FN_TEST(write_unmapped)
+{
+	TEST_RES(access_addr(unmapped_page + 2, 1),
+		 _ret == 1 && fault_signo == SIGSEGV &&
+			 fault_code == SEGV_MAPERR &&
+			 fault_addr == unmapped_page + 2);
+}
+END_TEST()
+
+FN_TEST(write_readonly)
+{
+	TEST_RES(access_addr(readonly_page, 0), _ret == 0);
+	TEST_RES(access_addr(readonly_page + 3, 1),
+		 _ret == 1 && fault_signo == SIGSEGV &&
+			 fault_code == SEGV_ACCERR &&
+			 fault_addr == readonly_page + 3);
+}
+END_TEST()
+
+FN_TEST(syscall_efault)
+{
+	int fd;
+
+	// Faults in syscalls must be reported as errors instead of signals
+	fault_signo = 0;
+	fd = TEST_SUCC(open("/dev/zero", O_RDONLY));
+	TEST_ERRNO(read(fd, unmapped_page, 1), EFAULT);
+	TEST_ERRNO(read(fd, readonly_page, 1), EFAULT);
+	TEST_RES(fault_signo, _ret == 0);
+	TEST_SUCC(close(fd));
+}
+END_TEST()
+
+static void fault_with_sigsegv(int ignore)
+{
+	sigset_t set;
+
+	if (ignore) {
+		if (signal(SIGSEGV, SIG_IGN) == SIG_ERR)
+			_exit(1);
+	} else {
+		sigemptyset(&set);
+		sigaddset(&set, SIGSEGV);
+		if (sigprocmask(SIG_BLOCK, &set, NULL) < 0)
+			_exit(1);
+	}
+
+	*(volatile char *)unmapped_page = 'a';
+	_exit(0);
+}
+
+FN_TEST(blocked_or_ignored)
+{
+	int ignore, status;
+	pid_t pid;
+
+	// A fault signal cannot be blocked or ignored, otherwise the faulting
+	// instruction would be retried forever
+	for (ignore = 0; ignore <= 1; ++ignore) {
+		pid = fork();
+		if (pid == 0)
+			fault_with_sigsegv(ignore);
+		TEST_SUCC(pid);
+
+		TEST_RES(waitpid(pid, &status, 0),
+			 _ret == pid && WIFSIGNALED(status) &&
+				 WTERMSIG(status) == SIGSEGV);
+	}
+}
+END_TEST()
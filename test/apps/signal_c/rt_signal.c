@@ -0,0 +1,98 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include <signal.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+#include "../network/test.h"
+
+#define NR_SIGNALS 8
+
+static int received_values[NR_SIGNALS];
+static int received_codes[NR_SIGNALS];
+static volatile int nr_received;
+
+static void handle_rt_signal(int signo, siginfo_t *info, void *context)
+{
+	if (nr_received < NR_SIGNALS) {
+		received_values[nr_received] = info->si_value.sival_int;
+		received_codes[nr_received] = info->si_code;
+	}
+	nr_received++;
+}
+
+static sigset_t rt_set;
+
+FN_SETUP(signal_handler)
+{
+	struct sigaction action = {
+		.sa_sigaction = handle_rt_signal,
+		.sa_flags = SA_SIGINFO,
+	};
+
+	CHECK(sigaction(SIGRTMIN, &action, NULL));
+
+	sigemptyset(&rt_set);
+	sigaddset(&rt_set, SIGRTMIN);
+}
+END_SETUP()
+
+static int check_received(void)
+{
+	int i;
+
+	if (nr_received != NR_SIGNALS)
+		return 0;
+
+	for (i = 0; i < NR_SIGNALS; ++i)
+		if (received_values[i] != i + 100 ||
+		    received_codes[i] != SI_QUEUE)
+			return 0;
+
+	return 1;
+}
+
+FN_TEST(queued_in_order)
+{
+	union sigval value;
+	int i;
+
+	TEST_SUCC(sigprocmask(SIG_BLOCK, &rt_set, NULL));
+
+	for (i = 0; i < NR_SIGNALS; ++i) {
+		value.sival_int = i + 100;
+		TEST_SUCC(sigqueue(getpid(), SIGRTMIN, value));
+	}
+	TEST_RES(nr_received, _ret == 0);
+
+	// All the queued signals should be delivered after unblocking
+	TEST_SUCC(sigprocmask(SIG_UNBLOCK, &rt_set, NULL));
+	TEST_RES(check_received(), _ret == 1);
+}
+END_TEST()
+
+FN_TEST(queue_overflow)
+{
+	union sigval value = { .sival_int = 0 };
+	int status;
+	pid_t pid;
+
+	// Fill the queue in a child process so that the pending signals are
+	// discarded after the child exits
+	pid = TEST_SUCC(fork());
+	if (pid == 0) {
+		CHECK(sigprocmask(SIG_BLOCK, &rt_set, NULL));
+		for (;;) {
+			if (sigqueue(getpid(), SIGRTMIN, value) < 0)
+				_exit(errno == EAGAIN ? EXIT_SUCCESS :
+							EXIT_FAILURE);
+		}
+	}
+
+	TEST_RES(wait4(pid, &status, 0, NULL),
+		 _ret == pid && WIFEXITED(status) &&
+			 WEXITSTATUS(status) == EXIT_SUCCESS);
+}
+END_TEST()
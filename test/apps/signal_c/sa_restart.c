@@ -0,0 +1,109 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include <poll.h>
+#include <signal.h>
+#include <sys/time.h>
+#include <time.h>
+#include <unistd.h>
+
+#include "../network/test.h"
+
+static int pipe_fds[2];
+static volatile int nr_handled;
+
+static void handle_alarm(int signo)
+{
+	char byte = 'a';
+
+	nr_handled++;
+	// The interrupted read will get this byte if it is restarted
+	if (write(pipe_fds[1], &byte, 1) != 1)
+		_exit(EXIT_FAILURE);
+}
+
+static int install_handler(int flags)
+{
+	struct sigaction action = {
+		.sa_handler = handle_alarm,
+		.sa_flags = flags,
+	};
+
+	nr_handled = 0;
+	return sigaction(SIGALRM, &action, NULL);
+}
+
+static int start_timer(void)
+{
+	struct itimerval timer = {
+		.it_value = { .tv_sec = 0, .tv_usec = 100 * 1000 },
+	};
+
+	return setitimer(ITIMER_REAL, &timer, NULL);
+}
+
+FN_SETUP(pipe)
+{
+	CHECK(pipe(pipe_fds));
+}
+END_SETUP()
+
+FN_TEST(restart_read)
+{
+	char byte;
+
+	TEST_SUCC(install_handler(SA_RESTART));
+	TEST_SUCC(start_timer());
+
+	TEST_RES(read(pipe_fds[0], &byte, 1),
+		 _ret == 1 && byte == 'a' && nr_handled == 1);
+}
+END_TEST()
+
+FN_TEST(interrupt_read)
+{
+	char byte;
+
+	TEST_SUCC(install_handler(0));
+	TEST_SUCC(start_timer());
+
+	TEST_ERRNO(read(pipe_fds[0], &byte, 1), EINTR);
+	TEST_RES(nr_handled, _ret == 1);
+
+	// Consume the byte written by the handler
+	TEST_RES(read(pipe_fds[0], &byte, 1), _ret == 1 && byte == 'a');
+}
+END_TEST()
+
+FN_TEST(interrupt_poll)
+{
+	struct pollfd pfd = { .fd = pipe_fds[0], .events = POLLIN };
+	char byte;
+
+	// Poll with a timeout is never restarted
+	TEST_SUCC(install_handler(SA_RESTART));
+	TEST_SUCC(start_timer());
+
+	TEST_ERRNO(poll(&pfd, 1, 1000 * 1000), EINTR);
+	TEST_RES(nr_handled, _ret == 1);
+
+	TEST_RES(read(pipe_fds[0], &byte, 1), _ret == 1 && byte == 'a');
+}
+END_TEST()
+
+FN_TEST(interrupt_nanosleep)
+{
+	struct timespec duration = { .tv_sec = 1000 };
+	char byte;
+
+	// Sleeping is never restarted
+	TEST_SUCC(install_handler(SA_RESTART));
+	TEST_SUCC(start_timer());
+
+	TEST_ERRNO(nanosleep(&duration, NULL), EINTR);
+	TEST_RES(nr_handled, _ret == 1);
+
+	TEST_RES(read(pipe_fds[0], &byte, 1), _ret == 1 && byte == 'a');
+}
+END_TEST()
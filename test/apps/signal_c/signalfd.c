@@ -0,0 +1,108 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include <poll.h>
+#include <signal.h>
+#include <sys/signalfd.h>
+#include <unistd.h>
+
+#include "../network/test.h"
+
+static sigset_t usr_set;
+static sigset_t usr1_set;
+
+FN_SETUP(block_signals)
+{
+	sigemptyset(&usr1_set);
+	sigaddset(&usr1_set, SIGUSR1);
+
+	usr_set = usr1_set;
+	sigaddset(&usr_set, SIGUSR2);
+
+	CHECK(sigprocmask(SIG_BLOCK, &usr_set, NULL));
+}
+END_SETUP()
+
+FN_TEST(invalid_args)
+{
+	struct signalfd_siginfo info;
+	int fd;
+
+	TEST_ERRNO(signalfd(-1, &usr1_set, 0x1), EINVAL);
+	TEST_ERRNO(signalfd(STDIN_FILENO, &usr1_set, 0), EINVAL);
+
+	fd = TEST_SUCC(signalfd(-1, &usr1_set, SFD_NONBLOCK));
+	TEST_ERRNO(read(fd, &info, sizeof(info) - 1), EINVAL);
+	TEST_ERRNO(read(fd, &info, sizeof(info)), EAGAIN);
+	TEST_SUCC(close(fd));
+}
+END_TEST()
+
+FN_TEST(read_signal)
+{
+	struct signalfd_siginfo info;
+	struct pollfd pfd = { .events = POLLIN };
+	int fd;
+
+	fd = TEST_SUCC(signalfd(-1, &usr1_set, 0));
+	pfd.fd = fd;
+	TEST_RES(poll(&pfd, 1, 0), _ret == 0);
+
+	TEST_SUCC(kill(getpid(), SIGUSR1));
+	TEST_RES(poll(&pfd, 1, 0), _ret == 1 && pfd.revents == POLLIN);
+
+	TEST_RES(read(fd, &info, sizeof(info)),
+		 _ret == sizeof(info) && info.ssi_signo == SIGUSR1 &&
+			 info.ssi_code == SI_USER && info.ssi_pid == getpid());
+	TEST_RES(poll(&pfd, 1, 0), _ret == 0);
+
+	TEST_SUCC(close(fd));
+}
+END_TEST()
+
+FN_TEST(read_multiple_signals)
+{
+	struct signalfd_siginfo infos[3];
+	int fd;
+
+	fd = TEST_SUCC(signalfd(-1, &usr_set, SFD_NONBLOCK));
+
+	TEST_SUCC(kill(getpid(), SIGUSR2));
+	TEST_SUCC(kill(getpid(), SIGUSR1));
+
+	// Both signals are returned by a single read
+	TEST_RES(read(fd, infos, sizeof(infos)),
+		 _ret == 2 * sizeof(infos[0]) &&
+			 infos[0].ssi_signo == SIGUSR1 &&
+			 infos[1].ssi_signo == SIGUSR2);
+	TEST_ERRNO(read(fd, infos, sizeof(infos)), EAGAIN);
+
+	TEST_SUCC(close(fd));
+}
+END_TEST()
+
+FN_TEST(signal_not_in_mask)
+{
+	struct signalfd_siginfo info;
+	sigset_t pending;
+	int fd;
+
+	fd = TEST_SUCC(signalfd(-1, &usr1_set, SFD_NONBLOCK));
+
+	// The signal that is not in the mask is left pending
+	TEST_SUCC(kill(getpid(), SIGUSR2));
+	TEST_ERRNO(read(fd, &info, sizeof(info)), EAGAIN);
+	TEST_SUCC(sigpending(&pending));
+	TEST_RES(sigismember(&pending, SIGUSR2), _ret == 1);
+
+	// The signal is readable after the mask is updated
+	TEST_RES(signalfd(fd, &usr_set, 0), _ret == fd);
+	TEST_RES(read(fd, &info, sizeof(info)),
+		 _ret == sizeof(info) && info.ssi_signo == SIGUSR2);
+	TEST_SUCC(sigpending(&pending));
+	TEST_RES(sigismember(&pending, SIGUSR2), _ret == 0);
+
+	TEST_SUCC(close(fd));
+}
+END_TEST()
@@ -0,0 +1,107 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include <fcntl.h>
+#include <poll.h>
+#include <stdint.h>
+#include <sys/timerfd.h>
+#include <unistd.h>
+
+#include "../network/test.h"
+
+#define MS_TO_NS(ms) ((ms) * 1000 * 1000)
+
+static int set_timer(int fd, long value_ms, long interval_ms)
+{
+	struct itimerspec its = {
+		.it_value = { .tv_nsec = MS_TO_NS(value_ms) },
+		.it_interval = { .tv_nsec = MS_TO_NS(interval_ms) },
+	};
+
+	return timerfd_settime(fd, 0, &its, NULL);
+}
+
+static uint64_t read_ticks(int fd)
+{
+	uint64_t ticks;
+
+	if (read(fd, &ticks, sizeof(ticks)) != sizeof(ticks))
+		return 0;
+	return ticks;
+}
+
+FN_TEST(invalid_args)
+{
+	int fd;
+	uint64_t ticks;
+
+	TEST_ERRNO(timerfd_create(CLOCK_MONOTONIC, 0x1), EINVAL);
+	TEST_ERRNO(timerfd_create(-1, 0), EINVAL);
+
+	fd = TEST_SUCC(timerfd_create(CLOCK_MONOTONIC, TFD_NONBLOCK));
+	TEST_ERRNO(read(fd, &ticks, sizeof(ticks) - 1), EINVAL);
+	TEST_ERRNO(timerfd_settime(STDIN_FILENO, 0,
+				   &(struct itimerspec){ 0 }, NULL),
+		   EINVAL);
+	TEST_SUCC(close(fd));
+}
+END_TEST()
+
+FN_TEST(one_shot)
+{
+	int fd;
+	uint64_t ticks;
+	struct itimerspec its;
+	struct pollfd pfd = { .events = POLLIN };
+
+	fd = TEST_SUCC(timerfd_create(CLOCK_MONOTONIC, TFD_NONBLOCK));
+	pfd.fd = fd;
+
+	// The timer is not armed.
+	TEST_ERRNO(read(fd, &ticks, sizeof(ticks)), EAGAIN);
+	TEST_RES(poll(&pfd, 1, 0), _ret == 0);
+
+	TEST_SUCC(set_timer(fd, 50, 0));
+	TEST_RES(timerfd_gettime(fd, &its),
+		 its.it_value.tv_sec == 0 && its.it_value.tv_nsec > 0 &&
+			 its.it_interval.tv_nsec == 0);
+	TEST_ERRNO(read(fd, &ticks, sizeof(ticks)), EAGAIN);
+
+	TEST_RES(poll(&pfd, 1, 1000), _ret == 1 && pfd.revents == POLLIN);
+	TEST_RES(read_ticks(fd), _ret == 1);
+
+	// The expiration has been consumed.
+	TEST_ERRNO(read(fd, &ticks, sizeof(ticks)), EAGAIN);
+	TEST_RES(poll(&pfd, 1, 0), _ret == 0);
+	TEST_RES(timerfd_gettime(fd, &its),
+		 its.it_value.tv_sec == 0 && its.it_value.tv_nsec == 0);
+
+	TEST_SUCC(close(fd));
+}
+END_TEST()
+
+FN_TEST(periodic)
+{
+	int fd;
+	uint64_t ticks;
+
+	fd = TEST_SUCC(timerfd_create(CLOCK_MONOTONIC, 0));
+	TEST_SUCC(set_timer(fd, 20, 20));
+
+	// The expirations accumulate before being read.
+	TEST_SUCC(usleep(150 * 1000));
+	TEST_RES(read_ticks(fd), _ret >= 3);
+
+	// A blocking read waits for the next expiration.
+	TEST_RES(read_ticks(fd), _ret >= 1);
+
+	// Disarm the timer.
+	TEST_SUCC(set_timer(fd, 0, 0));
+	TEST_SUCC(fcntl(fd, F_SETFL, O_NONBLOCK));
+	TEST_SUCC(usleep(50 * 1000));
+	TEST_ERRNO(read(fd, &ticks, sizeof(ticks)), EAGAIN);
+
+	TEST_SUCC(close(fd));
+}
+END_TEST()